@@ -7,6 +7,9 @@ mod service;
 mod cli;
 mod command;
 
-fn main() -> sc_cli::Result<()> {
-	command::run()
+fn main() {
+	if let Err(e) = command::run() {
+		eprintln!("Error: {}", e);
+		std::process::exit(e.exit_code() as i32);
+	}
 }
@@ -1,7 +1,6 @@
 //! Service and ServiceFactory implementation. Specialized wrapper over substrate service.
 
 use std::sync::Arc;
-use std::time::Duration;
 use sc_client_api::ExecutorProvider;
 use sc_consensus::LongestChain;
 use node_template_runtime::{self, opaque::Block, RuntimeApi};
@@ -90,6 +89,10 @@ pub fn new_full(config: Configuration) -> Result<impl AbstractService, ServiceEr
 	let force_authoring = config.force_authoring;
 	let name = config.network.node_name.clone();
 	let disable_grandpa = config.disable_grandpa;
+	let justification_period = config.grandpa_justification_period;
+	let gossip_duration = config.grandpa_gossip_duration;
+	let gossip_rebroadcast_period = config.network.gossip_rebroadcast_period;
+	let gossip_validation_workers = config.network.gossip_validation_workers;
 
 	let (builder, mut import_setup, inherent_data_providers) = new_full_start!(config);
 
@@ -106,11 +109,14 @@ pub fn new_full(config: Configuration) -> Result<impl AbstractService, ServiceEr
 		.build()?;
 
 	if role.is_authority() {
-		let proposer = sc_basic_authorship::ProposerFactory::new(
+		let mut proposer = sc_basic_authorship::ProposerFactory::new(
 			service.client(),
 			service.transaction_pool(),
 			service.prometheus_registry().as_ref(),
 		);
+		if let Some(transaction_selector) = service.transaction_selector() {
+			proposer.set_transaction_selector(transaction_selector);
+		}
 
 		let client = service.client();
 		let select_chain = service.select_chain()
@@ -130,6 +136,7 @@ pub fn new_full(config: Configuration) -> Result<impl AbstractService, ServiceEr
 			force_authoring,
 			service.keystore(),
 			can_author_with,
+			service.prometheus_registry().as_ref(),
 		)?;
 
 		// the AURA authoring task is considered essential, i.e. if it
@@ -146,13 +153,14 @@ pub fn new_full(config: Configuration) -> Result<impl AbstractService, ServiceEr
 	};
 
 	let grandpa_config = sc_finality_grandpa::Config {
-		// FIXME #1578 make this available through chainspec
-		gossip_duration: Duration::from_millis(333),
-		justification_period: 512,
+		gossip_duration,
+		justification_period,
 		name: Some(name),
 		observer_enabled: false,
 		keystore,
 		is_authority: role.is_network_authority(),
+		gossip_rebroadcast_period,
+		gossip_validation_workers,
 	};
 
 	let enable_grandpa = !disable_grandpa;
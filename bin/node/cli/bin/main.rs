@@ -20,6 +20,9 @@
 
 #![warn(missing_docs)]
 
-fn main() -> sc_cli::Result<()> {
-	node_cli::run()
+fn main() {
+	if let Err(e) = node_cli::run() {
+		eprintln!("Error: {}", e);
+		std::process::exit(e.exit_code() as i32);
+	}
 }
@@ -0,0 +1,239 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2020 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Reports how full the imported blocks are.
+//!
+//! For every imported block, the weight consumed by its extrinsics, the number of extrinsics of
+//! each dispatch class and the size of the block are recorded in Prometheus histograms and sent
+//! to telemetry.
+
+use std::sync::Arc;
+
+use codec::{Decode, Encode};
+use frame_support::weights::{DispatchClass, GetDispatchInfo, Weight};
+use frame_system::ExtrinsicsWeight;
+use futures::prelude::*;
+use log::debug;
+use node_primitives::Block;
+use node_runtime::UncheckedExtrinsic;
+use prometheus_endpoint::{
+	register, exponential_buckets, Histogram, HistogramOpts, HistogramVec, Opts, PrometheusError,
+	Registry,
+};
+use sc_client_api::{Backend, BlockBackend, BlockchainEvents, StorageProvider};
+use sc_telemetry::{telemetry, SUBSTRATE_INFO};
+use sp_core::{hashing::twox_128, storage::StorageKey};
+use sp_runtime::{generic::BlockId, traits::Block as BlockT};
+
+/// Usage of the resources of a block.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+struct BlockUsage {
+	/// Size of the encoded block, in bytes.
+	size: usize,
+	/// Number of extrinsics of each dispatch class.
+	normal_extrinsics: u32,
+	operational_extrinsics: u32,
+	mandatory_extrinsics: u32,
+	/// Weight consumed by the extrinsics, if known.
+	weight: Option<ExtrinsicsWeight>,
+}
+
+impl BlockUsage {
+	/// Computes the usage of a block made of `header` and `extrinsics`, which consumed `weight`.
+	fn new(
+		header: &<Block as BlockT>::Header,
+		extrinsics: &[<Block as BlockT>::Extrinsic],
+		weight: Option<ExtrinsicsWeight>,
+	) -> Self {
+		let mut usage = BlockUsage {
+			size: header.encoded_size() + extrinsics.encoded_size(),
+			weight,
+			..Default::default()
+		};
+
+		for extrinsic in extrinsics {
+			let class = match UncheckedExtrinsic::decode(&mut &extrinsic.encode()[..]) {
+				Ok(extrinsic) => extrinsic.function.get_dispatch_info().class,
+				Err(e) => {
+					debug!("Failed to decode extrinsic, assuming a normal one: {}", e);
+					DispatchClass::Normal
+				},
+			};
+			match class {
+				DispatchClass::Normal => usage.normal_extrinsics += 1,
+				DispatchClass::Operational => usage.operational_extrinsics += 1,
+				DispatchClass::Mandatory => usage.mandatory_extrinsics += 1,
+			}
+		}
+
+		usage
+	}
+
+	fn weight(&self, class: DispatchClass) -> Option<Weight> {
+		self.weight.as_ref().map(|weight| weight.get(class))
+	}
+}
+
+struct Metrics {
+	block_size: Histogram,
+	block_extrinsics: HistogramVec,
+	block_weight: HistogramVec,
+}
+
+impl Metrics {
+	fn register(registry: &Registry) -> Result<Self, PrometheusError> {
+		Ok(Self {
+			block_size: register(Histogram::with_opts(
+				HistogramOpts::new(
+					"block_size_bytes",
+					"Size of the imported blocks",
+				).buckets(exponential_buckets(256.0, 2.0, 16)?),
+			)?, registry)?,
+			block_extrinsics: register(HistogramVec::new(
+				HistogramOpts {
+					common_opts: Opts::new(
+						"block_extrinsics",
+						"Number of extrinsics of each dispatch class in the imported blocks",
+					),
+					buckets: exponential_buckets(1.0, 2.0, 12)?,
+				},
+				&["class"],
+			)?, registry)?,
+			block_weight: register(HistogramVec::new(
+				HistogramOpts {
+					common_opts: Opts::new(
+						"block_weight",
+						"Weight consumed by each dispatch class in the imported blocks",
+					),
+					buckets: exponential_buckets(1_000_000_000.0, 2.0, 12)?,
+				},
+				&["class"],
+			)?, registry)?,
+		})
+	}
+
+	fn observe(&self, usage: &BlockUsage) {
+		self.block_size.observe(usage.size as f64);
+		for (class, count) in &[
+			("normal", usage.normal_extrinsics),
+			("operational", usage.operational_extrinsics),
+			("mandatory", usage.mandatory_extrinsics),
+		] {
+			self.block_extrinsics.with_label_values(&[*class]).observe(*count as f64);
+		}
+		for (label, class) in &[
+			("normal", DispatchClass::Normal),
+			("operational", DispatchClass::Operational),
+		] {
+			if let Some(weight) = usage.weight(*class) {
+				self.block_weight.with_label_values(&[*label]).observe(weight as f64);
+			}
+		}
+	}
+}
+
+/// Returns the key under which `frame_system` stores the weight consumed by the current block.
+fn block_weight_key() -> StorageKey {
+	StorageKey([twox_128(b"System"), twox_128(b"BlockWeight")].concat())
+}
+
+/// Returns a future recording the usage of every block imported by `client`.
+pub fn report_block_usage<C, BE>(
+	client: Arc<C>,
+	registry: Option<&Registry>,
+) -> Result<impl Future<Output = ()>, PrometheusError> where
+	C: BlockchainEvents<Block> + BlockBackend<Block> + StorageProvider<Block, BE>,
+	C: Send + Sync + 'static,
+	BE: Backend<Block> + 'static,
+{
+	let metrics = registry.map(Metrics::register).transpose()?;
+	let weight_key = block_weight_key();
+
+	Ok(client.import_notification_stream().for_each(move |notification| {
+		let id = BlockId::Hash(notification.hash);
+		let extrinsics = match client.block_body(&id) {
+			Ok(Some(extrinsics)) => extrinsics,
+			Ok(None) => return future::ready(()),
+			Err(e) => {
+				debug!("Failed to read the body of block {}: {}", notification.hash, e);
+				return future::ready(())
+			},
+		};
+		let weight = client.storage(&id, &weight_key).ok().flatten()
+			.and_then(|weight| ExtrinsicsWeight::decode(&mut &weight.0[..]).ok());
+
+		let usage = BlockUsage::new(&notification.header, &extrinsics, weight);
+		if let Some(metrics) = &metrics {
+			metrics.observe(&usage);
+		}
+		telemetry!(
+			SUBSTRATE_INFO;
+			"block.usage";
+			"height" => notification.header.number,
+			"hash" => ?notification.hash,
+			"size" => usage.size,
+			"normal_extrinsics" => usage.normal_extrinsics,
+			"operational_extrinsics" => usage.operational_extrinsics,
+			"mandatory_extrinsics" => usage.mandatory_extrinsics,
+			"normal_weight" => usage.weight(DispatchClass::Normal),
+			"operational_weight" => usage.weight(DispatchClass::Operational),
+		);
+
+		future::ready(())
+	}))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use node_runtime::Call;
+	use sp_runtime::{OpaqueExtrinsic, traits::Header as _};
+
+	fn opaque(call: Call) -> OpaqueExtrinsic {
+		OpaqueExtrinsic::decode(&mut &UncheckedExtrinsic::new_unsigned(call).encode()[..]).unwrap()
+	}
+
+	#[test]
+	fn counts_extrinsics_by_dispatch_class() {
+		let extrinsics = vec![
+			opaque(Call::Timestamp(pallet_timestamp::Call::set(42))),
+			opaque(Call::System(frame_system::Call::remark(vec![1, 2, 3]))),
+			opaque(Call::System(frame_system::Call::remark(Vec::new()))),
+			opaque(Call::System(frame_system::Call::set_code(Vec::new()))),
+		];
+		let header = <Block as BlockT>::Header::new(
+			1,
+			Default::default(),
+			Default::default(),
+			Default::default(),
+			Default::default(),
+		);
+		let mut weight = ExtrinsicsWeight::default();
+		weight.add(10, DispatchClass::Normal);
+		weight.add(20, DispatchClass::Operational);
+
+		let usage = BlockUsage::new(&header, &extrinsics, Some(weight));
+
+		assert_eq!(usage.size, header.encoded_size() + extrinsics.encoded_size());
+		assert_eq!(usage.normal_extrinsics, 2);
+		assert_eq!(usage.operational_extrinsics, 1);
+		assert_eq!(usage.mandatory_extrinsics, 1);
+		assert_eq!(usage.weight(DispatchClass::Normal), Some(10));
+		assert_eq!(usage.weight(DispatchClass::Operational), Some(20));
+	}
+}
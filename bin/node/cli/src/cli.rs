@@ -28,6 +28,13 @@ pub struct Cli {
 	#[allow(missing_docs)]
 	#[structopt(flatten)]
 	pub run: RunCmd,
+
+	/// Extra chains run in the same process, e.g. the light client of a bridged chain.
+	///
+	/// Each chain has its own section, separated from the others by `--`, made of its name
+	/// followed by the arguments of its node: `-- bridge --chain bridge.json --light --port 30334`.
+	#[structopt(raw = true)]
+	pub extra_chains: Vec<String>,
 }
 
 /// Possible subcommands of the main binary.
@@ -44,7 +51,22 @@ pub enum Subcommand {
 	)]
 	Inspect(node_inspect::cli::InspectCmd),
 
+	/// Work with chain specifications.
+	#[structopt(name = "chain-spec")]
+	ChainSpec(sc_cli::ChainSpecCmd),
+
+	/// Work with the configuration of the node.
+	#[structopt(name = "config")]
+	Config(sc_cli::ConfigCmd),
+
 	/// The custom benchmark subcommmand benchmarking runtime pallets.
 	#[structopt(name = "benchmark", about = "Benchmark runtime pallets.")]
 	Benchmark(frame_benchmarking_cli::BenchmarkCmd),
+
+	/// The custom benchmark subcommand measuring the storage latency of the node's database.
+	#[structopt(
+		name = "benchmark-storage",
+		about = "Benchmark storage reads and writes against the node's database."
+	)]
+	BenchmarkStorage(frame_benchmarking_cli::StorageCmd),
 }
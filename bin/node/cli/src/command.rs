@@ -63,6 +63,20 @@ impl SubstrateCli for Cli {
 	}
 }
 
+/// Create the extra chain configured by the command line `section`.
+///
+/// This node only runs chains of its own runtime. Chains of other runtimes are created here,
+/// with the `SubstrateCli` loading their chain specs and their own service builders.
+fn extra_chain(
+	runner: &sc_cli::Runner<Cli>,
+	cli: &Cli,
+	section: sc_cli::ChainSection,
+) -> Result<sc_cli::ExtraChain> {
+	let config = runner.extra_chain_configuration(cli, &section.run)?;
+
+	Ok(sc_cli::ExtraChain::new(section.name, config, service::new_light, service::new_full))
+}
+
 /// Parse command line arguments into service configuration.
 pub fn run() -> Result<()> {
 	let cli = Cli::from_args();
@@ -70,10 +84,15 @@ pub fn run() -> Result<()> {
 	match &cli.subcommand {
 		None => {
 			let runner = cli.create_runner(&cli.run)?;
-			runner.run_node(
+			let extra_chains = sc_cli::parse_chain_sections(&cli.extra_chains)?
+				.into_iter()
+				.map(|section| extra_chain(&runner, &cli, section))
+				.collect::<Result<Vec<_>>>()?;
+			runner.run_node_with_extra_chains(
 				service::new_light,
 				service::new_full,
-				node_runtime::VERSION
+				node_runtime::VERSION,
+				extra_chains,
 			)
 		}
 		Some(Subcommand::Inspect(cmd)) => {
@@ -81,6 +100,8 @@ pub fn run() -> Result<()> {
 
 			runner.sync_run(|config| cmd.run::<Block, RuntimeApi, Executor>(config))
 		}
+		Some(Subcommand::ChainSpec(cmd)) => cmd.run(&cli),
+		Some(Subcommand::Config(cmd)) => cmd.run(&cli),
 		Some(Subcommand::Benchmark(cmd)) => {
 			if cfg!(feature = "runtime-benchmarks") {
 				let runner = cli.create_runner(cmd)?;
@@ -92,6 +113,17 @@ pub fn run() -> Result<()> {
 				Ok(())
 			}
 		}
+		Some(Subcommand::BenchmarkStorage(cmd)) => {
+			if cfg!(feature = "runtime-benchmarks") {
+				let runner = cli.create_runner(cmd)?;
+
+				runner.sync_run(|config| cmd.run::<Block>(config))
+			} else {
+				println!("Benchmarking wasn't enabled when building the node. \
+				You can enable it with `--features runtime-benchmarks`.");
+				Ok(())
+			}
+		}
 		Some(Subcommand::Base(subcommand)) => {
 			let runner = cli.create_runner(subcommand)?;
 
@@ -34,6 +34,7 @@ pub mod chain_spec;
 
 #[macro_use]
 mod service;
+mod block_usage;
 #[cfg(feature = "browser")]
 mod browser;
 #[cfg(feature = "cli")]
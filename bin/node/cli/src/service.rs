@@ -45,7 +45,16 @@ macro_rules! new_full_start {
 
 		let mut import_setup = None;
 		let mut rpc_setup = None;
+		let mut justification_check = None;
+		let mut consensus_replay = None;
 		let inherent_data_providers = sp_inherents::InherentDataProviders::new();
+		if let Some(clock) = $config.clock.clone() {
+			// registered before BABE, which would register the system clock otherwise
+			inherent_data_providers
+				.register_provider(sp_timestamp::ClockInherentDataProvider(clock))
+				.map_err(sp_consensus::Error::InherentData)?;
+		}
+		let dev_faucet = $config.chain_spec.chain_type() == sc_service::ChainType::Development;
 
 		let builder = sc_service::ServiceBuilder::new_full::<
 			node_primitives::Block, node_runtime::RuntimeApi, node_executor::Executor
@@ -62,7 +71,7 @@ macro_rules! new_full_start {
 				))
 			})?
 			.with_import_queue(|
-				_config,
+				config,
 				client,
 				mut select_chain,
 				_transaction_pool,
@@ -71,12 +80,22 @@ macro_rules! new_full_start {
 			| {
 				let select_chain = select_chain.take()
 					.ok_or_else(|| sc_service::Error::SelectChainRequired)?;
-				let (grandpa_block_import, grandpa_link) = grandpa::block_import(
+				let (mut grandpa_block_import, grandpa_link) = grandpa::block_import(
 					client.clone(),
 					&(client.clone() as Arc<_>),
 					select_chain,
 				)?;
+				if config.grandpa_request_justifications {
+					grandpa_block_import.set_justification_request_period(
+						config.grandpa_justification_period,
+					);
+				}
+				grandpa_block_import.set_justification_verification_workers(
+					config.grandpa_justification_workers,
+				);
 				let justification_import = grandpa_block_import.clone();
+				justification_check = Some(grandpa_block_import.clone());
+				consensus_replay = Some((grandpa_block_import.clone(), config.grandpa_gossip_duration));
 
 				let (block_import, babe_link) = sc_consensus_babe::block_import(
 					sc_consensus_babe::Config::get_or_compute(&*client)?,
@@ -98,12 +117,28 @@ macro_rules! new_full_start {
 				import_setup = Some((block_import, grandpa_link, babe_link));
 				Ok(import_queue)
 			})?
+			.with_justification_check({
+				let grandpa_block_import = justification_check.take()
+					.expect("GRANDPA block import is set up with the import queue; qed");
+				move || Box::new(grandpa_block_import.justification_check())
+			})?
+			.with_consensus_replay({
+				let (grandpa_block_import, gossip_duration) = consensus_replay.take()
+					.expect("GRANDPA block import is set up with the import queue; qed");
+				move || Box::new(grandpa_block_import.consensus_replay(gossip_duration))
+			})?
 			.with_rpc_extensions_builder(|builder| {
 				let grandpa_link = import_setup.as_ref().map(|s| &s.1)
 					.expect("GRANDPA LinkHalf is present for full services or set up failed; qed.");
 
 				let shared_authority_set = grandpa_link.shared_authority_set().clone();
 				let shared_voter_state = grandpa::SharedVoterState::empty();
+				let justification_stream = grandpa_link.justification_stream();
+				let subscriptions = sc_rpc::Subscriptions::new(Arc::new(builder.spawn_handle()));
+				let finality_proof_provider = Arc::new(GrandpaFinalityProofProvider::new(
+					builder.backend().clone(),
+					builder.client().clone() as Arc<dyn StorageAndProofProvider<_, _>>,
+				));
 
 				rpc_setup = Some((shared_voter_state.clone()));
 
@@ -118,6 +153,7 @@ macro_rules! new_full_start {
 				let select_chain = builder.select_chain().cloned()
 					.expect("SelectChain is present for full services or set up failed; qed.");
 				let keystore = builder.keystore().clone();
+				let inherent_data_providers = inherent_data_providers.clone();
 
 				Ok(move |deny_unsafe| {
 					let deps = node_rpc::FullDeps {
@@ -125,6 +161,8 @@ macro_rules! new_full_start {
 						pool: pool.clone(),
 						select_chain: select_chain.clone(),
 						deny_unsafe,
+						dev_faucet,
+						inherent_data_providers: inherent_data_providers.clone(),
 						babe: node_rpc::BabeDeps {
 							babe_config: babe_config.clone(),
 							shared_epoch_changes: shared_epoch_changes.clone(),
@@ -133,6 +171,9 @@ macro_rules! new_full_start {
 						grandpa: node_rpc::GrandpaDeps {
 							shared_voter_state: shared_voter_state.clone(),
 							shared_authority_set: shared_authority_set.clone(),
+							finality_provider: finality_proof_provider.clone(),
+							justification_stream: justification_stream.clone(),
+							subscriptions: subscriptions.clone(),
 						},
 					};
 
@@ -159,11 +200,21 @@ macro_rules! new_full {
 			force_authoring,
 			name,
 			disable_grandpa,
+			justification_period,
+			gossip_duration,
+			gossip_rebroadcast_period,
+			gossip_validation_workers,
+			clock,
 		) = (
 			$config.role.clone(),
 			$config.force_authoring,
 			$config.network.node_name.clone(),
 			$config.disable_grandpa,
+			$config.grandpa_justification_period,
+			$config.grandpa_gossip_duration,
+			$config.network.gossip_rebroadcast_period,
+			$config.network.gossip_validation_workers,
+			$config.clock.clone(),
 		);
 
 		let (builder, mut import_setup, inherent_data_providers, mut rpc_setup) =
@@ -186,11 +237,14 @@ macro_rules! new_full {
 		($with_startup_data)(&block_import, &babe_link);
 
 		if let sc_service::config::Role::Authority { .. } = &role {
-			let proposer = sc_basic_authorship::ProposerFactory::new(
+			let mut proposer = sc_basic_authorship::ProposerFactory::new(
 				service.client(),
 				service.transaction_pool(),
 				service.prometheus_registry().as_ref(),
 			);
+			if let Some(transaction_selector) = service.transaction_selector() {
+				proposer.set_transaction_selector(transaction_selector);
+			}
 
 			let client = service.client();
 			let select_chain = service.select_chain()
@@ -210,12 +264,20 @@ macro_rules! new_full {
 				force_authoring,
 				babe_link,
 				can_author_with,
+				clock: clock.unwrap_or_else(|| Arc::new(sp_timestamp::SystemClock)),
+				prometheus_registry: service.prometheus_registry(),
 			};
 
 			let babe = sc_consensus_babe::start_babe(babe_config)?;
 			service.spawn_essential_task("babe-proposer", babe);
 		}
 
+		let block_usage = crate::block_usage::report_block_usage(
+			service.client(),
+			service.prometheus_registry().as_ref(),
+		)?;
+		service.spawn_task("block-usage", block_usage);
+
 		// Spawn authority discovery module.
 		if matches!(role, sc_service::config::Role::Authority{..} | sc_service::config::Role::Sentry {..}) {
 			let (sentries, authority_discovery_role) = match role {
@@ -258,13 +320,14 @@ macro_rules! new_full {
 		};
 
 		let config = grandpa::Config {
-			// FIXME #1578 make this available through chainspec
-			gossip_duration: std::time::Duration::from_millis(333),
-			justification_period: 512,
+			gossip_duration,
+			justification_period,
 			name: Some(name),
 			observer_enabled: false,
 			keystore,
 			is_authority: role.is_network_authority(),
+			gossip_rebroadcast_period,
+			gossip_validation_workers,
 		};
 
 		let enable_grandpa = !disable_grandpa;
@@ -622,4 +685,25 @@ mod tests {
 			],
 		)
 	}
+
+	#[test]
+	fn test_embedded_node() {
+		sc_service_test::embedded_node(
+			crate::chain_spec::tests::integration_test_config_with_single_authority(),
+			|config| new_full(config),
+		)
+	}
+
+	#[test]
+	fn test_network_finalizes_blocks() {
+		let chain_spec = crate::chain_spec::tests::integration_test_config_with_two_authorities();
+		let mut network = sc_service::testing::TestNetwork::new(
+			&chain_spec,
+			&["//Alice", "//Bob"],
+			1,
+			|config| new_full(config),
+		).unwrap();
+
+		network.wait_for_finality(3, std::time::Duration::from_secs(5 * 60)).unwrap();
+	}
 }
@@ -0,0 +1,69 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2020 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+#![cfg(unix)]
+
+use assert_cmd::cargo::cargo_bin;
+use nix::sys::signal::{kill, Signal::SIGINT};
+use nix::unistd::Pid;
+use std::{convert::TryInto, process::Command, thread, time::Duration};
+use tempfile::tempdir;
+
+pub mod common;
+
+#[test]
+fn running_extra_chains_works_and_can_be_interrupted() {
+	let (base_path, extra_base_path) = (tempdir().unwrap(), tempdir().unwrap());
+	let mut cmd = Command::new(cargo_bin("substrate"))
+		.args(&["--dev", "-d"])
+		.arg(base_path.path())
+		.args(&["--", "bridge", "--dev", "--light", "-d"])
+		.arg(extra_base_path.path())
+		.args(&["--port", "30433", "--rpc-port", "9833", "--ws-port", "9844", "--prometheus-port", "9715"])
+		.spawn()
+		.unwrap();
+
+	thread::sleep(Duration::from_secs(20));
+	assert!(cmd.try_wait().unwrap().is_none(), "the process should still be running");
+	kill(Pid::from_raw(cmd.id().try_into().unwrap()), SIGINT).unwrap();
+	assert_eq!(
+		common::wait_for(&mut cmd, 30).map(|x| x.success()),
+		Some(true),
+		"the process must exit gracefully",
+	);
+}
+
+#[test]
+fn refuses_extra_chains_listening_on_the_addresses_of_the_node() {
+	let (base_path, extra_base_path) = (tempdir().unwrap(), tempdir().unwrap());
+	// the extra chain listens on the RPC port of the main chain.
+	let mut cmd = Command::new(cargo_bin("substrate"))
+		.args(&["--dev", "-d"])
+		.arg(base_path.path())
+		.args(&["--rpc-port", "9833", "--", "bridge", "--dev", "-d"])
+		.arg(extra_base_path.path())
+		.args(&["--port", "30433", "--rpc-port", "9833", "--ws-port", "9844", "--prometheus-port", "9715"])
+		.spawn()
+		.unwrap();
+
+	assert_eq!(
+		common::wait_for(&mut cmd, 30).map(|x| x.success()),
+		Some(false),
+		"the process must refuse to start",
+	);
+}
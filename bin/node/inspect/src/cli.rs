@@ -19,6 +19,7 @@
 //! Structs to easily compose inspect sub-command for CLI.
 
 use std::fmt::Debug;
+use std::path::PathBuf;
 use sc_cli::{ImportParams, SharedParams};
 use structopt::StructOpt;
 
@@ -48,8 +49,26 @@ pub enum InspectSubCmd {
 		/// Can be either a block hash (no 0x prefix) or a number to retrieve existing block,
 		/// or a 0x-prefixed bytes hex string, representing SCALE encoding of
 		/// a block.
-		#[structopt(value_name = "HASH or NUMBER or BYTES")]
-		input: String,
+		#[structopt(value_name = "HASH or NUMBER or BYTES", required_unless = "file")]
+		input: Option<String>,
+
+		/// Read the SCALE encoded block from a file, either as raw or as 0x-prefixed hex bytes.
+		#[structopt(long, value_name = "PATH", conflicts_with = "input", parse(from_os_str))]
+		file: Option<PathBuf>,
+	},
+	/// Decode header and print out the details.
+	Header {
+		/// Address of the header to print out.
+		///
+		/// Can be either a block hash (no 0x prefix) or a number to retrieve existing header,
+		/// or a 0x-prefixed bytes hex string, representing SCALE encoding of
+		/// a header.
+		#[structopt(value_name = "HASH or NUMBER or BYTES", required_unless = "file")]
+		input: Option<String>,
+
+		/// Read the SCALE encoded header from a file, either as raw or as 0x-prefixed hex bytes.
+		#[structopt(long, value_name = "PATH", conflicts_with = "input", parse(from_os_str))]
+		file: Option<PathBuf>,
 	},
 	/// Decode extrinsic with native version of runtime and print out the details.
 	Extrinsic {
@@ -58,7 +77,12 @@ pub enum InspectSubCmd {
 		/// Can be either a block hash (no 0x prefix) or number and the index, in the form
 		/// of `{block}:{index}` or a 0x-prefixed bytes hex string,
 		/// representing SCALE encoding of an extrinsic.
-		#[structopt(value_name = "BLOCK:INDEX or BYTES")]
-		input: String,
+		#[structopt(value_name = "BLOCK:INDEX or BYTES", required_unless = "file")]
+		input: Option<String>,
+
+		/// Read the SCALE encoded extrinsic from a file, either as raw or as 0x-prefixed hex
+		/// bytes.
+		#[structopt(long, value_name = "PATH", conflicts_with = "input", parse(from_os_str))]
+		file: Option<PathBuf>,
 	},
 }
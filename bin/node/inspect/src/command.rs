@@ -19,33 +19,60 @@
 //! Command ran by the CLI
 
 use crate::cli::{InspectCmd, InspectSubCmd};
-use crate::Inspector;
+use crate::{BlockAddress, ExtrinsicAddress, Inspector};
+use crate::metadata::{ExtrinsicCall, MetadataPrinter};
 use sc_cli::{CliConfiguration, ImportParams, Result, SharedParams};
-use sc_service::{new_full_client, Configuration, NativeExecutionDispatch};
-use sp_runtime::traits::Block;
-use std::str::FromStr;
+use sc_service::{new_full_client, Configuration, NativeExecutionDispatch, TFullClient};
+use sp_api::{ConstructRuntimeApi, Metadata, ProvideRuntimeApi};
+use sp_blockchain::HeaderBackend;
+use sp_runtime::{generic::BlockId, traits::Block};
+use std::{fs, path::Path, str::FromStr};
 
 impl InspectCmd {
 	/// Run the inspect command, passing the inspector.
+	///
+	/// The calls of the extrinsics are decoded with the metadata of the runtime of the best block.
 	pub fn run<B, RA, EX>(&self, config: Configuration) -> Result<()>
 	where
 		B: Block,
 		B::Hash: FromStr,
-		RA: Send + Sync + 'static,
+		B::Extrinsic: ExtrinsicCall,
+		RA: ConstructRuntimeApi<B, TFullClient<B, RA, EX>> + Send + Sync + 'static,
+		RA::RuntimeApi: Metadata<B>,
 		EX: NativeExecutionDispatch + 'static,
 	{
 		let client = new_full_client::<B, RA, EX>(&config)?;
-		let inspect = Inspector::<B>::new(client);
+		let best = BlockId::Hash(client.info().best_hash);
+		let metadata = client.runtime_api()
+			.metadata(&best)
+			.map_err(|e| format!("Could not get the runtime metadata: {:?}", e))?;
+		let printer = MetadataPrinter::new(&metadata)?;
+		let inspect = Inspector::<B, _>::with_printer(client, printer);
 
 		match &self.command {
-			InspectSubCmd::Block { input } => {
-				let input = input.parse()?;
+			InspectSubCmd::Block { input, file } => {
+				let input = match file {
+					Some(file) => BlockAddress::Bytes(read_file(file)?),
+					None => parse_input(input)?,
+				};
 				let res = inspect.block(input).map_err(|e| format!("{}", e))?;
 				println!("{}", res);
 				Ok(())
 			}
-			InspectSubCmd::Extrinsic { input } => {
-				let input = input.parse()?;
+			InspectSubCmd::Header { input, file } => {
+				let input = match file {
+					Some(file) => BlockAddress::Bytes(read_file(file)?),
+					None => parse_input(input)?,
+				};
+				let res = inspect.header(input).map_err(|e| format!("{}", e))?;
+				println!("{}", res);
+				Ok(())
+			}
+			InspectSubCmd::Extrinsic { input, file } => {
+				let input = match file {
+					Some(file) => ExtrinsicAddress::Bytes(read_file(file)?),
+					None => parse_input(input)?,
+				};
 				let res = inspect.extrinsic(input).map_err(|e| format!("{}", e))?;
 				println!("{}", res);
 				Ok(())
@@ -54,6 +81,25 @@ impl InspectCmd {
 	}
 }
 
+/// Parse the address given on the command line.
+fn parse_input<T: FromStr<Err = String>>(input: &Option<String>) -> Result<T> {
+	let input = input.as_ref()
+		.ok_or_else(|| "Either an input or `--file` is required".to_string())?;
+	Ok(input.parse()?)
+}
+
+/// Read SCALE encoded data from a file, containing either the raw or the hex encoded bytes.
+fn read_file(path: &Path) -> Result<Vec<u8>> {
+	let content = fs::read(path)?;
+
+	let hex = std::str::from_utf8(&content).ok()
+		.map(str::trim)
+		.filter(|s| s.starts_with("0x"))
+		.and_then(|s| sp_core::bytes::from_hex(s).ok());
+
+	Ok(hex.unwrap_or(content))
+}
+
 impl CliConfiguration for InspectCmd {
 	fn shared_params(&self) -> &SharedParams {
 		&self.shared_params
@@ -62,4 +108,8 @@ impl CliConfiguration for InspectCmd {
 	fn import_params(&self) -> Option<&ImportParams> {
 		Some(&self.import_params)
 	}
+
+	fn database_read_only(&self) -> Result<bool> {
+		self.import_params.database_params.database_read_only(true)
+	}
 }
@@ -17,15 +17,16 @@
 // along with this program. If not, see <https://www.gnu.org/licenses/>.
 
 //! A CLI extension for substrate node, adding sub-command to pretty print debug info
-//! about blocks and extrinsics.
+//! about blocks, headers and extrinsics.
 //!
-//! The blocks and extrinsics can either be retrieved from the database (on-chain),
+//! The blocks, headers and extrinsics can either be retrieved from the database (on-chain),
 //! or a raw SCALE-encoding can be provided.
 
 #![warn(missing_docs)]
 
 pub mod cli;
 pub mod command;
+pub mod metadata;
 
 use std::{
 	fmt,
@@ -39,7 +40,7 @@ use sp_blockchain::HeaderBackend;
 use sp_core::hexdisplay::HexDisplay;
 use sp_runtime::{
 	generic::BlockId,
-	traits::{Block, HashFor, NumberFor, Hash}
+	traits::{Block, HashFor, Header, NumberFor, Hash}
 };
 
 /// A helper type for a generic block input.
@@ -54,6 +55,13 @@ pub trait PrettyPrinter<TBlock: Block> {
 	fn fmt_block(&self, fmt: &mut fmt::Formatter, block: &TBlock) -> fmt::Result;
 	/// Nicely format extrinsic.
 	fn fmt_extrinsic(&self, fmt: &mut fmt::Formatter, extrinsic: &TBlock::Extrinsic) -> fmt::Result;
+	/// Nicely format header.
+	fn fmt_header(&self, fmt: &mut fmt::Formatter, header: &TBlock::Header) -> fmt::Result {
+		writeln!(fmt, "Hash: {:?}", header.hash())?;
+		writeln!(fmt, "{:?}", header)?;
+		writeln!(fmt, "Header bytes: {:?}", HexDisplay::from(&header.encode()))?;
+		Ok(())
+	}
 }
 
 /// Default dummy debug printer.
@@ -177,6 +185,34 @@ impl<TBlock: Block, TPrinter: PrettyPrinter<TBlock>> Inspector<TBlock, TPrinter>
 		})
 	}
 
+	/// Get a pretty-printed header.
+	pub fn header(&self, input: BlockAddressFor<TBlock>) -> Result<String, Error> {
+		struct HeaderPrinter<'a, A: Block, B>(A::Header, &'a B);
+		impl<'a, A: Block, B: PrettyPrinter<A>> fmt::Display for HeaderPrinter<'a, A, B> {
+			fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+				self.1.fmt_header(fmt, &self.0)
+			}
+		}
+
+		let header = match input {
+			BlockAddress::Bytes(bytes) => {
+				TBlock::Header::decode(&mut &*bytes)?
+			},
+			BlockAddress::Number(number) => {
+				let id = BlockId::number(number);
+				self.chain.header(id)?
+					.ok_or_else(|| Error::NotFound(format!("Could not find header {:?}", id)))?
+			},
+			BlockAddress::Hash(hash) => {
+				let id = BlockId::hash(hash);
+				self.chain.header(id)?
+					.ok_or_else(|| Error::NotFound(format!("Could not find header {:?}", id)))?
+			},
+		};
+
+		Ok(format!("{}", HeaderPrinter(header, &self.printer)))
+	}
+
 	/// Get a pretty-printed extrinsic.
 	pub fn extrinsic(
 		&self,
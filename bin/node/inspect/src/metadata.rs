@@ -0,0 +1,341 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2020 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Pretty printing of the extrinsics using the runtime metadata.
+//!
+//! The call of an extrinsic is printed with the names of its pallet, function and arguments
+//! taken from the metadata, and the values of the arguments whose type is known.
+
+use std::fmt;
+use codec::{Compact, Decode, Encode};
+use frame_metadata::{DecodeDifferent, RuntimeMetadata, RuntimeMetadataPrefixed};
+use sp_core::{bytes::to_hex, hexdisplay::HexDisplay};
+use sp_runtime::{generic::UncheckedExtrinsic, traits::{Block, SignedExtension}};
+use crate::{DebugPrinter, PrettyPrinter};
+
+/// An extrinsic whose call can be retrieved.
+pub trait ExtrinsicCall {
+	/// Returns the SCALE-encoded call of the extrinsic.
+	fn encoded_call(&self) -> Vec<u8>;
+}
+
+impl<Address, Call, Signature, Extra> ExtrinsicCall
+	for UncheckedExtrinsic<Address, Call, Signature, Extra>
+where
+	Call: Encode,
+	Extra: SignedExtension,
+{
+	fn encoded_call(&self) -> Vec<u8> {
+		self.function.encode()
+	}
+}
+
+/// A function of a pallet, with the names and the types of its arguments.
+#[derive(Debug, Clone, PartialEq)]
+struct Function {
+	name: String,
+	arguments: Vec<(String, String)>,
+}
+
+/// Printer decoding the calls of the extrinsics with the runtime metadata.
+pub struct MetadataPrinter {
+	/// The names and the functions of the pallets with calls, by index in the call enum of the
+	/// runtime.
+	pallets: Vec<(String, Vec<Function>)>,
+}
+
+impl MetadataPrinter {
+	/// Create a printer from the SCALE-encoded metadata of the runtime.
+	///
+	/// Only the V11 metadata is supported.
+	pub fn new(metadata: &[u8]) -> Result<Self, String> {
+		let modules = match RuntimeMetadataPrefixed::decode(&mut &metadata[..])
+			.map_err(|e| format!("Invalid runtime metadata: {}", e))?
+			.1
+		{
+			RuntimeMetadata::V11(metadata) => metadata.modules,
+			_ => return Err("Unsupported runtime metadata version".into()),
+		};
+
+		let pallets = decoded(modules)?
+			.into_iter()
+			.filter_map(|module| module.calls.map(|calls| (module.name, calls)))
+			.map(|(name, calls)| {
+				let functions = decoded(calls)?
+					.into_iter()
+					.map(|function| Ok(Function {
+						name: decoded(function.name)?,
+						arguments: decoded(function.arguments)?
+							.into_iter()
+							.map(|argument| Ok((decoded(argument.name)?, decoded(argument.ty)?)))
+							.collect::<Result<_, String>>()?,
+					}))
+					.collect::<Result<_, String>>()?;
+				Ok((decoded(name)?, functions))
+			})
+			.collect::<Result<_, String>>()?;
+		Ok(MetadataPrinter { pallets })
+	}
+
+	/// Write the call `call` decoded with the metadata.
+	fn fmt_call(&self, fmt: &mut fmt::Formatter, call: &[u8]) -> fmt::Result {
+		let mut input = call;
+		let pallet_index = u8::decode(&mut input).ok();
+		let function_index = u8::decode(&mut input).ok();
+		let (pallet, function) = match (pallet_index, function_index) {
+			(Some(pallet_index), Some(function_index)) => {
+				match self.pallets.get(pallet_index as usize) {
+					Some((pallet, functions)) => match functions.get(function_index as usize) {
+						Some(function) => (pallet, function),
+						None => return writeln!(fmt, " Call: unknown call of {}", pallet),
+					},
+					None => return writeln!(fmt, " Call: unknown pallet {}", pallet_index),
+				}
+			},
+			_ => return writeln!(fmt, " Call: undecodable"),
+		};
+
+		writeln!(fmt, " Call: {}.{}", pallet, function.name)?;
+		for (name, ty) in &function.arguments {
+			match decode_value(ty, &mut input) {
+				Some(value) => writeln!(fmt, "  {}: {} = {}", name, ty, value)?,
+				None => {
+					// the following arguments can't be located without the size of this one
+					writeln!(fmt, "  {}: {} = <undecoded>", name, ty)?;
+					writeln!(fmt, "  Remaining bytes: {:?}", HexDisplay::from(&input))?;
+					return Ok(());
+				},
+			}
+		}
+		Ok(())
+	}
+}
+
+impl<TBlock: Block> PrettyPrinter<TBlock> for MetadataPrinter
+where
+	TBlock::Extrinsic: ExtrinsicCall,
+{
+	fn fmt_block(&self, fmt: &mut fmt::Formatter, block: &TBlock) -> fmt::Result {
+		writeln!(fmt, "Header:")?;
+		writeln!(fmt, "{:?}", block.header())?;
+		writeln!(fmt, "Block bytes: {:?}", HexDisplay::from(&block.encode()))?;
+		writeln!(fmt, "Extrinsics ({})", block.extrinsics().len())?;
+		for (idx, ex) in block.extrinsics().iter().enumerate() {
+			writeln!(fmt, "- {}:", idx)?;
+			<MetadataPrinter as PrettyPrinter<TBlock>>::fmt_extrinsic(self, fmt, ex)?;
+		}
+		Ok(())
+	}
+
+	fn fmt_extrinsic(&self, fmt: &mut fmt::Formatter, extrinsic: &TBlock::Extrinsic) -> fmt::Result {
+		self.fmt_call(fmt, &extrinsic.encoded_call())?;
+		<DebugPrinter as PrettyPrinter<TBlock>>::fmt_extrinsic(&DebugPrinter, fmt, extrinsic)
+	}
+}
+
+/// Returns the decoded variant of `value`, which is the only one of the decoded metadata.
+fn decoded<B: 'static, O: 'static>(value: DecodeDifferent<B, O>) -> Result<O, String> {
+	match value {
+		DecodeDifferent::Decoded(value) => Ok(value),
+		DecodeDifferent::Encode(_) => Err("Invalid runtime metadata".into()),
+	}
+}
+
+/// Decode a value of the type named `ty` in the metadata, `None` if the type is unknown or the
+/// value can't be decoded.
+///
+/// Besides the primitive types, the types aliased by the node runtime (`AccountId`, `Balance`,
+/// `BlockNumber`, ...) are decoded as the node runtime defines them.
+fn decode_value(ty: &str, input: &mut &[u8]) -> Option<String> {
+	let ty = ty.trim();
+
+	if let Some(item) = generic_argument(ty, "Compact") {
+		let name = item.rsplit("::").next().unwrap_or(item);
+		return match name {
+			"u8" => Compact::<u8>::decode(input).ok().map(|v| v.0.to_string()),
+			"u16" => Compact::<u16>::decode(input).ok().map(|v| v.0.to_string()),
+			"u32" | "BlockNumber" | "Index" => {
+				Compact::<u32>::decode(input).ok().map(|v| v.0.to_string())
+			},
+			"u64" | "Moment" => Compact::<u64>::decode(input).ok().map(|v| v.0.to_string()),
+			"u128" | "Balance" | "BalanceOf<T>" => {
+				Compact::<u128>::decode(input).ok().map(|v| v.0.to_string())
+			},
+			_ => None,
+		};
+	}
+	if let Some(item) = generic_argument(ty, "Vec") {
+		let len = Compact::<u32>::decode(input).ok()?.0 as usize;
+		if item == "u8" {
+			return decode_bytes(len, input);
+		}
+		let items = (0..len).map(|_| decode_value(item, input)).collect::<Option<Vec<_>>>()?;
+		return Some(format!("[{}]", items.join(", ")));
+	}
+	if let Some(value) = generic_argument(ty, "Option") {
+		return match u8::decode(input).ok()? {
+			0 => Some("None".into()),
+			1 => decode_value(value, input).map(|value| format!("Some({})", value)),
+			_ => None,
+		};
+	}
+
+	let name = ty.rsplit("::").next().unwrap_or(ty);
+	match name {
+		"bool" => bool::decode(input).ok().map(|v| v.to_string()),
+		"u8" => u8::decode(input).ok().map(|v| v.to_string()),
+		"u16" => u16::decode(input).ok().map(|v| v.to_string()),
+		"u32" | "BlockNumber" | "Index" | "SessionIndex" => {
+			u32::decode(input).ok().map(|v| v.to_string())
+		},
+		"u64" | "Moment" => u64::decode(input).ok().map(|v| v.to_string()),
+		"u128" | "Balance" | "BalanceOf<T>" => u128::decode(input).ok().map(|v| v.to_string()),
+		"AccountId" | "Hash" | "H256" => decode_bytes(32, input),
+		"Bytes" => {
+			let len = Compact::<u32>::decode(input).ok()?.0 as usize;
+			decode_bytes(len, input)
+		},
+		_ => None,
+	}
+}
+
+/// Returns the argument of `ty` if it is the generic type `name` with a single argument.
+fn generic_argument<'a>(ty: &'a str, name: &str) -> Option<&'a str> {
+	if ty.starts_with(name) && ty[name.len()..].starts_with('<') && ty.ends_with('>') {
+		Some(ty[name.len() + 1..ty.len() - 1].trim())
+	} else {
+		None
+	}
+}
+
+fn decode_bytes(len: usize, input: &mut &[u8]) -> Option<String> {
+	if input.len() < len {
+		return None;
+	}
+	let (bytes, remaining) = input.split_at(len);
+	*input = remaining;
+	Some(to_hex(bytes, false))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use frame_metadata::{
+		ExtrinsicMetadata, FnEncode, FunctionArgumentMetadata, FunctionMetadata, ModuleMetadata,
+		RuntimeMetadataV11, META_RESERVED,
+	};
+
+	fn system_calls() -> &'static [FunctionMetadata] {
+		&[]
+	}
+
+	fn balances_calls() -> &'static [FunctionMetadata] {
+		&[
+			FunctionMetadata {
+				name: DecodeDifferent::Encode("transfer"),
+				arguments: DecodeDifferent::Encode(&[
+					FunctionArgumentMetadata {
+						name: DecodeDifferent::Encode("dest"),
+						ty: DecodeDifferent::Encode("T::AccountId"),
+					},
+					FunctionArgumentMetadata {
+						name: DecodeDifferent::Encode("value"),
+						ty: DecodeDifferent::Encode("Compact<T::Balance>"),
+					},
+				]),
+				documentation: DecodeDifferent::Encode(&[]),
+			},
+			FunctionMetadata {
+				name: DecodeDifferent::Encode("opaque"),
+				arguments: DecodeDifferent::Encode(&[
+					FunctionArgumentMetadata {
+						name: DecodeDifferent::Encode("what"),
+						ty: DecodeDifferent::Encode("Opaque"),
+					},
+					FunctionArgumentMetadata {
+						name: DecodeDifferent::Encode("count"),
+						ty: DecodeDifferent::Encode("u32"),
+					},
+				]),
+				documentation: DecodeDifferent::Encode(&[]),
+			},
+		]
+	}
+
+	fn module(
+		name: &'static str,
+		calls: Option<fn() -> &'static [FunctionMetadata]>,
+	) -> ModuleMetadata {
+		ModuleMetadata {
+			name: DecodeDifferent::Encode(name),
+			storage: None,
+			calls: calls.map(|calls| DecodeDifferent::Encode(FnEncode(calls))),
+			event: None,
+			constants: DecodeDifferent::Encode(FnEncode(|| &[])),
+			errors: DecodeDifferent::Encode(FnEncode(|| &[])),
+		}
+	}
+
+	fn printer() -> MetadataPrinter {
+		let modules = vec![
+			// pallets without calls aren't in the call enum
+			module("Randomness", None),
+			module("System", Some(system_calls)),
+			module("Balances", Some(balances_calls)),
+		];
+		let metadata = RuntimeMetadataPrefixed(META_RESERVED, RuntimeMetadata::V11(
+			RuntimeMetadataV11 {
+				modules: DecodeDifferent::Encode(Box::leak(modules.into_boxed_slice())),
+				extrinsic: ExtrinsicMetadata { version: 4, signed_extensions: Vec::new() },
+			},
+		)).encode();
+		MetadataPrinter::new(&metadata).unwrap()
+	}
+
+	struct Call<'a>(&'a MetadataPrinter, Vec<u8>);
+
+	impl<'a> fmt::Display for Call<'a> {
+		fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+			self.0.fmt_call(fmt, &self.1)
+		}
+	}
+
+	#[test]
+	fn decodes_calls_with_the_metadata() {
+		let printer = printer();
+
+		let transfer = (1u8, 0u8, [1u8; 32], Compact(100u128)).encode();
+		assert_eq!(
+			Call(&printer, transfer).to_string(),
+			format!(
+				" Call: Balances.transfer\n  dest: T::AccountId = 0x{}\n  \
+				value: Compact<T::Balance> = 100\n",
+				"01".repeat(32),
+			),
+		);
+
+		let opaque = (1u8, 1u8, 7u8, 5u32).encode();
+		assert_eq!(
+			Call(&printer, opaque).to_string(),
+			" Call: Balances.opaque\n  what: Opaque = <undecoded>\n  Remaining bytes: 0705000000\n",
+		);
+
+		assert_eq!(Call(&printer, vec![2, 0]).to_string(), " Call: unknown pallet 2\n");
+		assert_eq!(Call(&printer, vec![0, 0]).to_string(), " Call: unknown call of System\n");
+	}
+}
@@ -0,0 +1,358 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2020 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: Apache-2.0
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Preview of the block the node would author on top of its best block.
+
+use std::{fmt, marker::PhantomData, sync::Arc, time::{Duration, Instant}};
+
+use codec::{Decode, Encode};
+use jsonrpc_core::{Error as RpcError, ErrorCode};
+use jsonrpc_derive::rpc;
+use node_primitives::{Balance, Block, BlockNumber, Hash};
+use node_runtime::UncheckedExtrinsic;
+use pallet_transaction_payment_rpc::TransactionPaymentRuntimeApi;
+use sc_block_builder::{BlockBuilderApi, BlockBuilderProvider};
+use sc_client_api::backend::{Backend, StateBackendFor};
+use sc_rpc_api::DenyUnsafe;
+use serde::Serialize;
+use sp_api::{ApiExt, ExecutionContext, ProvideRuntimeApi};
+use sp_blockchain::{ApplyExtrinsicFailed::Validity, Error::ApplyExtrinsicFailed, HeaderBackend};
+use sp_consensus::RecordProof;
+use sp_inherents::{InherentData, InherentDataProviders};
+use sp_runtime::{generic::BlockId, traits::{BlakeTwo256, Block as BlockT, Hash as HashT}};
+use sp_transaction_pool::{InPoolTransaction, TransactionPool};
+
+type FutureResult<T> = Box<dyn jsonrpc_core::futures::Future<Item = T, Error = RpcError> + Send>;
+
+/// Error code of the block preview RPC failures.
+const BLOCK_PREVIEW_ERROR: i64 = 1;
+
+/// Time given to the block building when the caller doesn't specify any.
+const DEFAULT_MAX_DURATION: Duration = Duration::from_secs(2);
+
+/// Number of transactions tried once the block seems full, as the proposer does.
+const MAX_SKIPPED_TRANSACTIONS: usize = 8;
+
+/// Block preview RPC methods.
+#[rpc]
+pub trait BlockPreviewApi {
+	/// Build the block the node would author right now on top of its best block, without
+	/// importing or broadcasting it.
+	///
+	/// The block building is given `max_duration` milliseconds, two seconds by default.
+	#[rpc(name = "author_previewBlock")]
+	fn preview_block(&self, max_duration: Option<u64>) -> FutureResult<BlockPreview>;
+}
+
+/// Summary of a previewed block.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BlockPreview {
+	/// Hash of the parent block.
+	pub parent_hash: Hash,
+	/// Number of the block.
+	pub number: BlockNumber,
+	/// Extrinsics of the block, inherents included, in order.
+	pub extrinsics: Vec<ExtrinsicPreview>,
+	/// Total weight of the extrinsics.
+	pub weight: u64,
+	/// Size of the encoded block in bytes.
+	pub size: u32,
+}
+
+/// Summary of an extrinsic of a previewed block.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExtrinsicPreview {
+	/// Hash of the extrinsic.
+	pub hash: Hash,
+	/// Size of the encoded extrinsic in bytes.
+	pub size: u32,
+	/// Weight of the extrinsic.
+	pub weight: u64,
+}
+
+/// Previews blocks built from the ready transactions of the pool.
+///
+/// The blocks are built from a snapshot of the ready transactions, the way the proposer would
+/// build them, except that the transactions the block rejects are skipped instead of being
+/// removed from the pool. The block is built without the consensus digests of the author.
+pub struct BlockPreviewer<B, C, P> {
+	client: Arc<C>,
+	pool: Arc<P>,
+	inherent_data_providers: InherentDataProviders,
+	deny_unsafe: DenyUnsafe,
+	_phantom: PhantomData<B>,
+}
+
+impl<B, C, P> BlockPreviewer<B, C, P> {
+	/// Create new `BlockPreviewer` building blocks with the transactions of `pool`.
+	pub fn new(
+		client: Arc<C>,
+		pool: Arc<P>,
+		inherent_data_providers: InherentDataProviders,
+		deny_unsafe: DenyUnsafe,
+	) -> Self {
+		BlockPreviewer {
+			client,
+			pool,
+			inherent_data_providers,
+			deny_unsafe,
+			_phantom: PhantomData,
+		}
+	}
+}
+
+fn preview_error(message: &str, e: impl fmt::Debug) -> RpcError {
+	RpcError {
+		code: ErrorCode::ServerError(BLOCK_PREVIEW_ERROR),
+		message: message.into(),
+		data: Some(format!("{:?}", e).into()),
+	}
+}
+
+/// Build a block on top of `parent` from `inherent_data` and then from `transactions`, in order.
+///
+/// The transactions the runtime rejects are skipped. The building stops once the block is full
+/// or `deadline` is reached.
+fn build_block<B, C>(
+	client: &C,
+	parent: BlockId<Block>,
+	inherent_data: InherentData,
+	transactions: Vec<<Block as BlockT>::Extrinsic>,
+	deadline: Instant,
+) -> Result<Block, sp_blockchain::Error> where
+	B: Backend<Block> + Send + Sync + 'static,
+	C: ProvideRuntimeApi<Block> + BlockBuilderProvider<B, Block, C>,
+	C::Api: ApiExt<Block, StateBackend = StateBackendFor<B, Block>>
+		+ BlockBuilderApi<Block, Error = sp_blockchain::Error>,
+{
+	let mut block_builder = client.new_block_at(&parent, Default::default(), RecordProof::No)?;
+
+	let inherents = client.runtime_api().inherent_extrinsics_with_context(
+		&parent,
+		ExecutionContext::BlockConstruction,
+		inherent_data,
+	)?;
+	for inherent in inherents {
+		match block_builder.push(inherent) {
+			Err(ApplyExtrinsicFailed(Validity(e))) if e.was_mandatory() =>
+				return Err(ApplyExtrinsicFailed(Validity(e))),
+			_ => {},
+		}
+	}
+
+	let mut skipped = 0;
+	for xt in transactions {
+		if Instant::now() > deadline {
+			break;
+		}
+
+		match block_builder.push(xt) {
+			Err(ApplyExtrinsicFailed(Validity(e))) if e.exhausted_resources() => {
+				if skipped == MAX_SKIPPED_TRANSACTIONS {
+					break;
+				}
+				skipped += 1;
+			},
+			_ => {},
+		}
+	}
+
+	Ok(block_builder.build()?.block)
+}
+
+/// Returns the summary of `block`, querying the weights of its extrinsics from `client`.
+fn summarize<C>(client: &C, block: Block) -> Result<BlockPreview, RpcError> where
+	C: ProvideRuntimeApi<Block>,
+	C::Api: TransactionPaymentRuntimeApi<Block, Balance, UncheckedExtrinsic>,
+	<C::Api as sp_api::ApiErrorExt>::Error: fmt::Debug,
+{
+	let size = block.encoded_size() as u32;
+	let (header, extrinsics) = block.deconstruct();
+	let at = BlockId::hash(header.parent_hash);
+	let api = client.runtime_api();
+
+	let extrinsics = extrinsics.into_iter()
+		.map(|xt| {
+			let encoded = xt.encode();
+			let uxt = UncheckedExtrinsic::decode(&mut &*encoded)
+				.map_err(|e| preview_error("Unable to decode an extrinsic.", e))?;
+			let info = api.query_info(&at, uxt, encoded.len() as u32)
+				.map_err(|e| preview_error("Unable to query the weight of an extrinsic.", e))?;
+
+			Ok(ExtrinsicPreview {
+				hash: BlakeTwo256::hash(&encoded),
+				size: encoded.len() as u32,
+				weight: info.weight,
+			})
+		})
+		.collect::<Result<Vec<_>, RpcError>>()?;
+
+	Ok(BlockPreview {
+		parent_hash: header.parent_hash,
+		number: header.number,
+		weight: extrinsics.iter().map(|xt| xt.weight).sum(),
+		extrinsics,
+		size,
+	})
+}
+
+impl<B, C, P> BlockPreviewApi for BlockPreviewer<B, C, P> where
+	B: Backend<Block> + Send + Sync + 'static,
+	C: ProvideRuntimeApi<Block> + BlockBuilderProvider<B, Block, C> + HeaderBackend<Block>
+		+ Send + Sync + 'static,
+	C::Api: ApiExt<Block, StateBackend = StateBackendFor<B, Block>>
+		+ BlockBuilderApi<Block, Error = sp_blockchain::Error>
+		+ TransactionPaymentRuntimeApi<Block, Balance, UncheckedExtrinsic>,
+	P: TransactionPool<Block = Block> + 'static,
+{
+	fn preview_block(&self, max_duration: Option<u64>) -> FutureResult<BlockPreview> {
+		if let Err(err) = self.deny_unsafe.check_if_safe() {
+			return Box::new(jsonrpc_core::futures::future::err(err.into()));
+		}
+
+		let best_hash = self.client.info().best_hash;
+		match self.client.header(BlockId::hash(best_hash)) {
+			Ok(Some(_)) => {},
+			Ok(None) => return Box::new(jsonrpc_core::futures::future::err(
+				preview_error("Unable to find the best block.", best_hash),
+			)),
+			Err(e) => return Box::new(jsonrpc_core::futures::future::err(
+				preview_error("Unable to find the best block.", e),
+			)),
+		}
+		let inherent_data = match self.inherent_data_providers.create_inherent_data() {
+			Ok(inherent_data) => inherent_data,
+			Err(e) => return Box::new(jsonrpc_core::futures::future::err(
+				preview_error("Unable to create the inherent data.", e),
+			)),
+		};
+
+		let max_duration = max_duration.map_or(DEFAULT_MAX_DURATION, Duration::from_millis);
+		// the pool isn't told about the transactions the block rejects, unlike when authoring.
+		let transactions = self.pool.ready().map(|tx| tx.data().clone()).collect();
+
+		let preview = build_block(
+			&*self.client,
+			BlockId::hash(best_hash),
+			inherent_data,
+			transactions,
+			Instant::now() + max_duration,
+		)
+			.map_err(|e| preview_error("Unable to build the block.", e))
+			.and_then(|block| summarize(&*self.client, block));
+
+		Box::new(jsonrpc_core::futures::future::result(preview))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	use futures::executor::block_on;
+	use jsonrpc_core::futures::Future;
+	use node_runtime::{
+		AvailableBlockRatio, BalancesCall, Call, CheckedExtrinsic, MaximumBlockLength, SystemCall,
+		VERSION,
+	};
+	use node_testing::{
+		client::{Backend, Client, TestClientBuilder, TestClientBuilderExt},
+		keyring::{alice, bob, charlie, sign, signed_extra},
+	};
+	use sc_transaction_pool::{BasicPool, FullChainApi};
+	use sp_transaction_pool::TransactionSource;
+
+	type Pool = BasicPool<FullChainApi<Client, Block>, Block>;
+
+	fn previewer() -> BlockPreviewer<Backend, Client, Pool> {
+		let client = Arc::new(TestClientBuilder::new().build());
+		let pool = Arc::new(
+			BasicPool::new(
+				Default::default(),
+				Arc::new(FullChainApi::new(client.clone())),
+				None,
+			).0
+		);
+		let inherent_data_providers = InherentDataProviders::new();
+		inherent_data_providers
+			.register_provider(sp_timestamp::InherentDataProvider)
+			.unwrap();
+
+		BlockPreviewer::new(client, pool, inherent_data_providers, DenyUnsafe::No)
+	}
+
+	fn submit(previewer: &BlockPreviewer<Backend, Client, Pool>, xt: CheckedExtrinsic) {
+		let info = previewer.client.info();
+		let xt = sign(xt, VERSION.spec_version, VERSION.transaction_version, info.genesis_hash.into());
+		let xt = Decode::decode(&mut &xt.encode()[..]).unwrap();
+		block_on(previewer.pool.submit_one(
+			&BlockId::hash(info.best_hash),
+			TransactionSource::External,
+			xt,
+		)).unwrap();
+	}
+
+	/// Returns a remark by Charlie exactly as long as the longest normal extrinsic, which is
+	/// valid on its own but doesn't fit in a block next to the inherents.
+	fn longest_remark() -> CheckedExtrinsic {
+		let remark = |len| CheckedExtrinsic {
+			signed: Some((charlie(), signed_extra(0, 0))),
+			function: Call::System(SystemCall::remark(vec![0; len])),
+		};
+		let limit = (AvailableBlockRatio::get() * MaximumBlockLength::get()) as usize;
+		let overhead = sign(remark(limit), VERSION.spec_version, VERSION.transaction_version, [0; 32])
+			.encoded_size() - limit;
+		remark(limit - overhead)
+	}
+
+	#[test]
+	fn previews_the_ready_transactions_without_removing_them() {
+		let previewer = previewer();
+		submit(&previewer, CheckedExtrinsic {
+			signed: Some((alice(), signed_extra(0, 0))),
+			function: Call::Balances(BalancesCall::transfer(bob().into(), 1_000)),
+		});
+
+		let preview = previewer.preview_block(None).wait().unwrap();
+
+		assert_eq!(preview.number, 1);
+		// the timestamp inherent and the transfer.
+		assert_eq!(preview.extrinsics.len(), 2);
+		assert_eq!(previewer.pool.status().ready, 1);
+	}
+
+	#[test]
+	fn keeps_the_transactions_the_block_rejects_in_the_pool() {
+		let previewer = previewer();
+		submit(&previewer, longest_remark());
+
+		let preview = previewer.preview_block(None).wait().unwrap();
+
+		// only the timestamp inherent.
+		assert_eq!(preview.extrinsics.len(), 1);
+		assert_eq!(previewer.pool.status().ready, 1);
+	}
+
+	#[test]
+	fn refuses_to_preview_when_unsafe_rpcs_are_denied() {
+		let previewer = BlockPreviewer { deny_unsafe: DenyUnsafe::Yes, ..previewer() };
+
+		assert!(previewer.preview_block(None).wait().is_err());
+	}
+}
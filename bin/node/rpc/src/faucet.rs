@@ -0,0 +1,229 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2020 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: Apache-2.0
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Faucet funding accounts from a development account, on development chains.
+
+use std::{fmt, sync::Arc};
+
+use codec::Encode;
+use futures::{FutureExt, TryFutureExt, compat::Future01CompatExt, lock::Mutex};
+use jsonrpc_core::{Error as RpcError, ErrorCode};
+use jsonrpc_derive::rpc;
+use node_primitives::{AccountId, Balance, Block, Hash, Index};
+use node_runtime::{BalancesCall, Call, SignedExtra, SignedPayload, UncheckedExtrinsic};
+use sc_rpc_api::DenyUnsafe;
+use sp_api::{Core, ProvideRuntimeApi};
+use sp_blockchain::HeaderBackend;
+use sp_keyring::Sr25519Keyring;
+use sp_runtime::generic::{BlockId, Era};
+use sp_transaction_pool::{TransactionPool, TransactionSource, error::IntoPoolError};
+use substrate_frame_rpc_system::{AccountNonceApi, FullSystem, SystemApi};
+
+type FutureResult<T> = Box<dyn jsonrpc_core::futures::Future<Item = T, Error = RpcError> + Send>;
+
+/// Error code of the faucet RPC failures.
+const FAUCET_ERROR: i64 = 1;
+
+/// Development account the funds are transferred from.
+const FAUCET_ACCOUNT: Sr25519Keyring = Sr25519Keyring::Alice;
+
+/// Faucet RPC methods.
+#[rpc]
+pub trait FaucetApi {
+	/// Transfer `amount` from the development account Alice to `dest`.
+	///
+	/// Returns the hash of the transfer extrinsic submitted to the transaction pool.
+	#[rpc(name = "faucet_fund")]
+	fn fund(&self, dest: AccountId, amount: Balance) -> FutureResult<Hash>;
+}
+
+/// Faucet signing the transfers with the well-known key of a development account.
+///
+/// This is only meant for development chains, where this account is endowed.
+///
+/// The transfers are submitted one at a time, so that each one is given the nonce following the
+/// transfers already in the pool.
+pub struct Faucet<C, P> {
+	client: Arc<C>,
+	pool: Arc<P>,
+	system: Arc<FullSystem<P, C, Block>>,
+	submission: Arc<Mutex<()>>,
+	deny_unsafe: DenyUnsafe,
+}
+
+impl<C, P: TransactionPool> Faucet<C, P> {
+	/// Create new `Faucet` submitting the transfers to `pool`.
+	pub fn new(client: Arc<C>, pool: Arc<P>, deny_unsafe: DenyUnsafe) -> Self {
+		Faucet {
+			system: Arc::new(FullSystem::new(client.clone(), pool.clone())),
+			client,
+			pool,
+			submission: Arc::new(Mutex::new(())),
+			deny_unsafe,
+		}
+	}
+}
+
+fn faucet_error(message: &str, e: impl fmt::Debug) -> RpcError {
+	RpcError {
+		code: ErrorCode::ServerError(FAUCET_ERROR),
+		message: message.into(),
+		data: Some(format!("{:?}", e).into()),
+	}
+}
+
+/// Returns the transfer of `amount` to `dest` by the faucet account, with the nonce `index`,
+/// valid on the chain whose genesis is `genesis_hash` and that runs `version`.
+fn transfer_extrinsic(
+	dest: AccountId,
+	amount: Balance,
+	index: Index,
+	genesis_hash: Hash,
+	version: &sp_api::RuntimeVersion,
+) -> UncheckedExtrinsic {
+	let function = Call::Balances(BalancesCall::transfer(dest.into(), amount));
+	let extra: SignedExtra = (
+		frame_system::CheckSpecVersion::new(),
+		frame_system::CheckTxVersion::new(),
+		frame_system::CheckGenesis::new(),
+		frame_system::CheckEra::from(Era::Immortal),
+		frame_system::CheckNonce::from(index),
+		frame_system::CheckWeight::new(),
+		pallet_transaction_payment::ChargeTransactionPayment::from(0),
+		pallet_grandpa::ValidateEquivocationReport::new(),
+	);
+	let payload = SignedPayload::from_raw(
+		function,
+		extra,
+		(
+			version.spec_version,
+			version.transaction_version,
+			genesis_hash,
+			genesis_hash,
+			(),
+			(),
+			(),
+			(),
+		),
+	);
+	let signature = payload.using_encoded(|payload| FAUCET_ACCOUNT.sign(payload));
+	let (function, extra, _) = payload.deconstruct();
+
+	UncheckedExtrinsic::new_signed(
+		function,
+		FAUCET_ACCOUNT.to_account_id().into(),
+		signature.into(),
+		extra,
+	)
+}
+
+impl<C, P> FaucetApi for Faucet<C, P> where
+	C: ProvideRuntimeApi<Block> + HeaderBackend<Block> + Send + Sync + 'static,
+	C::Api: AccountNonceApi<Block, AccountId, Index> + Core<Block>,
+	<C::Api as sp_api::ApiErrorExt>::Error: fmt::Debug,
+	P: TransactionPool<Block = Block, Hash = Hash> + 'static,
+{
+	fn fund(&self, dest: AccountId, amount: Balance) -> FutureResult<Hash> {
+		if let Err(err) = self.deny_unsafe.check_if_safe() {
+			return Box::new(jsonrpc_core::futures::future::err(err.into()));
+		}
+
+		let info = self.client.info();
+		let at = BlockId::hash(info.best_hash);
+		let version = match self.client.runtime_api().version(&at) {
+			Ok(version) => version,
+			Err(e) => return Box::new(jsonrpc_core::futures::future::err(
+				faucet_error("Unable to query the runtime version.", e),
+			)),
+		};
+		let system = self.system.clone();
+		let submission = self.submission.clone();
+		let pool = self.pool.clone();
+
+		let future = async move {
+			// the nonce accounts for the pool, so it is only read once the previous transfer is in.
+			let _submission = submission.lock().await;
+			let index = SystemApi::<AccountId, Index>::nonce(
+				&*system,
+				FAUCET_ACCOUNT.to_account_id(),
+			).compat().await?;
+
+			let xt = transfer_extrinsic(dest, amount, index, info.genesis_hash, &version);
+			let xt = codec::Decode::decode(&mut &xt.encode()[..])
+				.map_err(|e| faucet_error("Unable to encode the transfer.", e))?;
+			pool.submit_one(&at, TransactionSource::External, xt)
+				.map_err(|e| match e.into_pool_error() {
+					Ok(e) => faucet_error("Unable to submit the transfer.", e),
+					Err(_) => faucet_error("Unable to submit the transfer.", "Unknown error"),
+				})
+				.await
+		};
+
+		Box::new(future.boxed().compat())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	use futures::executor::block_on;
+	use node_testing::client::{TestClientBuilder, TestClientBuilderExt};
+	use sc_transaction_pool::{BasicPool, FullChainApi};
+
+	fn faucet(deny_unsafe: DenyUnsafe) -> Faucet<
+		node_testing::client::Client,
+		BasicPool<FullChainApi<node_testing::client::Client, Block>, Block>,
+	> {
+		let client = Arc::new(TestClientBuilder::new().build());
+		let pool = Arc::new(
+			BasicPool::new(
+				Default::default(),
+				Arc::new(FullChainApi::new(client.clone())),
+				None,
+			).0
+		);
+		Faucet::new(client, pool, deny_unsafe)
+	}
+
+	#[test]
+	fn concurrent_transfers_get_consecutive_nonces() {
+		let faucet = faucet(DenyUnsafe::No);
+		let bob = Sr25519Keyring::Bob.to_account_id();
+		let charlie = Sr25519Keyring::Charlie.to_account_id();
+
+		let (first, second) = block_on(futures::future::join(
+			faucet.fund(bob, 1_000).compat(),
+			faucet.fund(charlie, 1_000).compat(),
+		));
+
+		// a transfer reusing the nonce of the other one would have been rejected by the pool, and
+		// a gap in the nonces would have left the transfer in the future queue.
+		assert_ne!(first.unwrap(), second.unwrap());
+		assert_eq!(faucet.pool.status().ready, 2);
+	}
+
+	#[test]
+	fn refuses_to_fund_when_unsafe_rpcs_are_denied() {
+		let faucet = faucet(DenyUnsafe::Yes);
+
+		let funded = faucet.fund(Sr25519Keyring::Bob.to_account_id(), 1_000).compat();
+
+		assert!(block_on(funded).is_err());
+		assert_eq!(faucet.pool.status().ready, 0);
+	}
+}
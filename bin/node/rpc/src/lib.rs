@@ -30,6 +30,9 @@
 
 #![warn(missing_docs)]
 
+pub mod block_preview;
+pub mod faucet;
+
 use std::{sync::Arc, fmt};
 
 use node_primitives::{Block, BlockNumber, AccountId, Index, Balance, Hash};
@@ -43,9 +46,11 @@ use sp_consensus_babe::BabeApi;
 use sc_consensus_epochs::SharedEpochChanges;
 use sc_consensus_babe::{Config, Epoch};
 use sc_consensus_babe_rpc::BabeRpcHandler;
-use sc_finality_grandpa::{SharedVoterState, SharedAuthoritySet};
+use sc_finality_grandpa::{
+	FinalityProofProvider, SharedVoterState, SharedAuthoritySet, GrandpaJustificationStream,
+};
 use sc_finality_grandpa_rpc::GrandpaRpcHandler;
-use sc_rpc_api::DenyUnsafe;
+use sc_rpc_api::{DenyUnsafe, Subscriptions};
 
 /// Light client extra dependencies.
 pub struct LightDeps<C, F, P> {
@@ -70,15 +75,21 @@ pub struct BabeDeps {
 }
 
 /// Extra dependencies for GRANDPA
-pub struct GrandpaDeps {
+pub struct GrandpaDeps<B> {
 	/// Voting round info.
 	pub shared_voter_state: SharedVoterState,
 	/// Authority set info.
 	pub shared_authority_set: SharedAuthoritySet<Hash, BlockNumber>,
+	/// Finality proof provider.
+	pub finality_provider: Arc<FinalityProofProvider<B, Block>>,
+	/// Receiving end of the justification notifications.
+	pub justification_stream: GrandpaJustificationStream<Block>,
+	/// Subscription manager of the justification notifications.
+	pub subscriptions: Subscriptions,
 }
 
 /// Full client dependencies.
-pub struct FullDeps<C, P, SC> {
+pub struct FullDeps<C, P, SC, B> {
 	/// The client instance to use.
 	pub client: Arc<C>,
 	/// Transaction pool instance.
@@ -87,31 +98,41 @@ pub struct FullDeps<C, P, SC> {
 	pub select_chain: SC,
 	/// Whether to deny unsafe calls
 	pub deny_unsafe: DenyUnsafe,
+	/// Whether to expose the faucet of the development chains.
+	pub dev_faucet: bool,
+	/// Inherent data providers of the blocks authored by the node.
+	pub inherent_data_providers: sp_inherents::InherentDataProviders,
 	/// BABE specific dependencies.
 	pub babe: BabeDeps,
 	/// GRANDPA specific dependencies.
-	pub grandpa: GrandpaDeps,
+	pub grandpa: GrandpaDeps<B>,
 }
 
 /// Instantiate all Full RPC extensions.
-pub fn create_full<C, P, M, SC>(
-	deps: FullDeps<C, P, SC>,
-) -> jsonrpc_core::IoHandler<M> where
+pub fn create_full<C, P, SC, B>(
+	deps: FullDeps<C, P, SC, B>,
+) -> jsonrpc_core::IoHandler<sc_rpc::Metadata> where
 	C: ProvideRuntimeApi<Block>,
 	C: HeaderBackend<Block> + HeaderMetadata<Block, Error=BlockChainError> + 'static,
+	C: sc_block_builder::BlockBuilderProvider<B, Block, C>,
 	C: Send + Sync + 'static,
+	C::Api: sp_api::ApiExt<Block, StateBackend = sc_client_api::StateBackendFor<B, Block>>,
+	C::Api: sc_block_builder::BlockBuilderApi<Block, Error = BlockChainError>,
 	C::Api: substrate_frame_rpc_system::AccountNonceApi<Block, AccountId, Index>,
 	C::Api: pallet_contracts_rpc::ContractsRuntimeApi<Block, AccountId, Balance, BlockNumber>,
 	C::Api: pallet_transaction_payment_rpc::TransactionPaymentRuntimeApi<Block, Balance, UncheckedExtrinsic>,
 	C::Api: BabeApi<Block>,
+	C::Api: sp_api::Core<Block>,
 	<C::Api as sp_api::ApiErrorExt>::Error: fmt::Debug,
-	P: TransactionPool + 'static,
-	M: jsonrpc_core::Metadata + Default,
+	P: TransactionPool<Block = Block, Hash = Hash> + 'static,
 	SC: SelectChain<Block> +'static,
+	B: sc_client_api::Backend<Block> + Send + Sync + 'static,
 {
 	use substrate_frame_rpc_system::{FullSystem, SystemApi};
 	use pallet_contracts_rpc::{Contracts, ContractsApi};
 	use pallet_transaction_payment_rpc::{TransactionPayment, TransactionPaymentApi};
+	use faucet::{Faucet, FaucetApi};
+	use block_preview::{BlockPreviewer, BlockPreviewApi};
 
 	let mut io = jsonrpc_core::IoHandler::default();
 	let FullDeps {
@@ -119,6 +140,8 @@ pub fn create_full<C, P, M, SC>(
 		pool,
 		select_chain,
 		deny_unsafe,
+		dev_faucet,
+		inherent_data_providers,
 		babe,
 		grandpa,
 	} = deps;
@@ -130,11 +153,27 @@ pub fn create_full<C, P, M, SC>(
 	let GrandpaDeps {
 		shared_voter_state,
 		shared_authority_set,
+		finality_provider,
+		justification_stream,
+		subscriptions,
 	} = grandpa;
 
 	io.extend_with(
-		SystemApi::to_delegate(FullSystem::new(client.clone(), pool))
+		SystemApi::to_delegate(FullSystem::new(client.clone(), pool.clone()))
+	);
+	io.extend_with(
+		BlockPreviewApi::to_delegate(BlockPreviewer::<B, _, _>::new(
+			client.clone(),
+			pool.clone(),
+			inherent_data_providers,
+			deny_unsafe,
+		))
 	);
+	if dev_faucet {
+		io.extend_with(
+			FaucetApi::to_delegate(Faucet::new(client.clone(), pool, deny_unsafe))
+		);
+	}
 	// Making synchronous calls in light client freezes the browser currently,
 	// more context: https://github.com/paritytech/substrate/pull/3480
 	// These RPCs should use an asynchronous caller instead.
@@ -158,7 +197,13 @@ pub fn create_full<C, P, M, SC>(
 	);
 	io.extend_with(
 		sc_finality_grandpa_rpc::GrandpaApi::to_delegate(
-			GrandpaRpcHandler::new(shared_authority_set, shared_voter_state)
+			GrandpaRpcHandler::new(
+				shared_authority_set,
+				shared_voter_state,
+				finality_provider,
+				justification_stream,
+				subscriptions,
+			)
 		)
 	);
 
@@ -267,16 +267,21 @@ impl BenchDb {
 		keyring: &BenchKeyring,
 	) -> (Client, std::sync::Arc<Backend>) {
 		let db_config = sc_client_db::DatabaseSettings {
-			state_cache_size: 16*1024*1024,
+			trie_cache_size: 16*1024*1024,
+			value_cache_size: 16*1024*1024,
 			state_cache_child_ratio: Some((0, 100)),
 			pruning: PruningMode::ArchiveAll,
 			source: database_type.into_settings(dir.into()),
+			cold_storage: None,
+			read_only: false,
+			block_compression: sc_client_db::BlockCompression::None,
 		};
 
 		let (client, backend) = sc_service::new_client(
 			db_config,
 			NativeExecutor::new(WasmExecutionMethod::Compiled, None, 8),
 			&keyring.generate_genesis(),
+			&sc_service::DefaultGenesisBlockBuilder,
 			None,
 			None,
 			ExecutionExtensions::new(profile.into_execution_strategies(), None),
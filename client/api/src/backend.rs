@@ -69,6 +69,9 @@ pub struct ImportSummary<Block: BlockT> {
 	pub storage_changes: Option<(StorageCollection, ChildStorageCollection)>,
 	/// Blocks that got retracted because of this one got imported.
 	pub retracted: Vec<Block::Hash>,
+	/// Blocks that got enacted on the best chain because this one got imported,
+	/// ending with this block. Empty if the block is not the new best.
+	pub enacted: Vec<Block::Hash>,
 }
 
 /// Import operation wrapper
@@ -79,6 +82,11 @@ pub struct ClientImportOperation<Block: BlockT, B: Backend<Block>> {
 	pub notify_imported: Option<ImportSummary<Block>>,
 	/// A list of hashes of blocks that got finalized.
 	pub notify_finalized: Vec<Block::Hash>,
+	/// Summary of the imported block for the indexers, when no import notification is made,
+	/// e.g. during the initial sync.
+	pub index_imported: Option<ImportSummary<Block>>,
+	/// The hashes of all the blocks that got finalized, for the indexers.
+	pub index_finalized: Vec<Block::Hash>,
 }
 
 /// Helper function to apply auxiliary data insertion into an operation.
@@ -381,6 +389,10 @@ pub trait StorageProvider<Block: BlockT, B: Backend<Block>> {
 		storage_key: Option<&PrefixedStorageKey>,
 		key: &StorageKey
 	) -> sp_blockchain::Result<Vec<(NumberFor<Block>, u32)>>;
+
+	/// Returns true if the state of the given block is available, i.e. the block is known and its
+	/// state isn't pruned.
+	fn has_state_at(&self, id: &BlockId<Block>) -> sp_blockchain::Result<bool>;
 }
 
 /// Client backend.
@@ -16,7 +16,7 @@
 
 //! A set of APIs supported by the client along with their primitives.
 
-use std::{fmt, collections::HashSet};
+use std::{fmt, collections::HashSet, sync::Arc, time::Duration};
 use sp_core::storage::StorageKey;
 use sp_runtime::{
 	traits::{Block as BlockT, NumberFor},
@@ -24,17 +24,27 @@ use sp_runtime::{
 	Justification,
 };
 use sp_consensus::BlockOrigin;
+use sp_state_machine::{ChildStorageCollection, StorageCollection};
 
 use crate::blockchain::Info;
 use crate::notifications::StorageEventStream;
-use sp_utils::mpsc::TracingUnboundedReceiver;
+use crate::notification_sinks::NotificationStream;
 use sp_blockchain;
 
 /// Type that implements `futures::Stream` of block import events.
-pub type ImportNotifications<Block> = TracingUnboundedReceiver<BlockImportNotification<Block>>;
+pub type ImportNotifications<Block> = NotificationStream<BlockImportNotification<Block>>;
 
 /// A stream of block finality notifications.
-pub type FinalityNotifications<Block> = TracingUnboundedReceiver<FinalityNotification<Block>>;
+pub type FinalityNotifications<Block> = NotificationStream<FinalityNotification<Block>>;
+
+/// A stream of chain reorganization notifications.
+pub type ReorgNotifications<Block> = NotificationStream<ReorgNotification<Block>>;
+
+/// A stream of batched block finality notifications.
+pub type FinalityBatchNotifications<Block> = NotificationStream<FinalityBatchNotification<Block>>;
+
+/// A stream of the imported and finalized blocks, for indexers.
+pub type IndexerNotifications<Block> = NotificationStream<IndexerNotification<Block>>;
 
 /// Expected hashes of blocks at given heights.
 ///
@@ -64,6 +74,43 @@ pub trait BlockchainEvents<Block: BlockT> {
 	/// finalized block.
 	fn finality_notification_stream(&self) -> FinalityNotifications<Block>;
 
+	/// Get a block import event stream for an external subscriber, e.g. an RPC subscription.
+	///
+	/// Unlike the stream of [`import_notification_stream`](Self::import_notification_stream),
+	/// its queue is bounded and the notifications are dropped or the stream ends once it is full.
+	fn external_import_notification_stream(&self) -> ImportNotifications<Block> {
+		self.import_notification_stream()
+	}
+
+	/// Get a stream of finality notifications for an external subscriber, e.g. an RPC
+	/// subscription, whose queue is bounded as the one of
+	/// [`external_import_notification_stream`](Self::external_import_notification_stream).
+	fn external_finality_notification_stream(&self) -> FinalityNotifications<Block> {
+		self.finality_notification_stream()
+	}
+
+	/// Get a stream of chain reorganization notifications, fired whenever importing
+	/// a new best block retracts blocks from the previous best chain.
+	fn reorg_notification_stream(&self) -> ReorgNotifications<Block>;
+
+	/// Get a stream of finality notifications where all blocks finalized by a single
+	/// finalization step are reported together in one notification.
+	fn finality_batch_notification_stream(&self) -> FinalityBatchNotifications<Block>;
+
+	/// Get a stream of the imported blocks, with their body and storage changes, and of the
+	/// finalized blocks, for indexers running in the same process as the node.
+	///
+	/// Unlike the other streams, no notification is ever missed: the stream of a subscriber
+	/// whose queue is full ends instead, and the subscriber has to catch up by other means.
+	fn indexer_notification_stream(&self) -> IndexerNotifications<Block>;
+
+	/// Same as `indexer_notification_stream`, but the queue of the subscriber is unbounded, for
+	/// the tasks of the node which must see every block, e.g. the replay log.
+	fn unbounded_indexer_notification_stream(&self) -> IndexerNotifications<Block>;
+
+	/// Whether the notifications of the indexers carry the storage changes of the blocks.
+	fn storage_diffs_enabled(&self) -> bool;
+
 	/// Get storage changes event stream.
 	///
 	/// Passing `None` as `filter_keys` subscribes to all storage changes.
@@ -74,6 +121,19 @@ pub trait BlockchainEvents<Block: BlockT> {
 	) -> sp_blockchain::Result<StorageEventStream<Block::Hash>>;
 }
 
+/// Keeping the state of blocks from being pruned while it is read, e.g. page by page over RPC.
+pub trait StatePinning<Block: BlockT> {
+	/// Keep the state of the block `hash` until `duration` has elapsed or it is unpinned.
+	///
+	/// Pinning a pinned block sets its expiry to `duration` from now, but a block isn't kept
+	/// pinned for more than a maximum time overall. Returns the time the state is actually kept
+	/// for. Fails if the state is already pruned, or if too many blocks are pinned.
+	fn pin_state(&self, hash: Block::Hash, duration: Duration) -> sp_blockchain::Result<Duration>;
+
+	/// Allow pruning the state of the block `hash` again. Returns `false` if it wasn't pinned.
+	fn unpin_state(&self, hash: &Block::Hash) -> bool;
+}
+
 /// Interface for fetching block data.
 pub trait BlockBackend<Block: BlockT> {
 	/// Get block body by ID. Returns `None` if the body is not stored.
@@ -90,6 +150,9 @@ pub trait BlockBackend<Block: BlockT> {
 
 	/// Get block justification set by id.
 	fn justification(&self, id: &BlockId<Block>) -> sp_blockchain::Result<Option<Justification>>;
+
+	/// Get the hashes of the leaves of the block tree, the highest ones first.
+	fn leaves(&self) -> sp_blockchain::Result<Vec<Block::Hash>>;
 }
 
 /// Provide a list of potential uncle headers for a given block.
@@ -154,6 +217,8 @@ pub struct StateDbMemoryInfo {
 pub struct MemoryInfo {
 	/// Size of state cache.
 	pub state_cache: MemorySize,
+	/// Size of trie node cache.
+	pub trie_cache: MemorySize,
 	/// Size of backend database cache.
 	pub database_cache: MemorySize,
 	/// Size of the state db.
@@ -185,6 +250,10 @@ pub struct IoInfo {
 	pub state_writes_cache: u64,
 	/// State write (trie nodes) to backend db.
 	pub state_writes_nodes: u64,
+	/// State reads (trie nodes).
+	pub trie_node_reads: u64,
+	/// State reads (trie nodes) from cache.
+	pub trie_node_reads_cache: u64,
 }
 
 /// Usage statistics for running client instance.
@@ -204,10 +273,12 @@ impl fmt::Display for UsageInfo {
 	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
 		write!(
 			f,
-			"caches: ({} state, {} db overlay), \
+			"caches: ({} state, {} trie, {} db overlay), \
 			 state db: ({} non-canonical, {} pruning, {} pinned), \
-			 i/o: ({} tx, {} write, {} read, {} avg tx, {}/{} key cache reads/total, {} trie nodes writes)",
+			 i/o: ({} tx, {} write, {} read, {} avg tx, {}/{} key cache reads/total, \
+			 {}/{} trie node cache reads/total, {} trie nodes writes)",
 			self.memory.state_cache,
+			self.memory.trie_cache,
 			self.memory.database_cache,
 			self.memory.state_db.non_canonical,
 			self.memory.state_db.pruning.unwrap_or_default(),
@@ -218,6 +289,8 @@ impl fmt::Display for UsageInfo {
 			self.io.average_transaction_size,
 			self.io.state_reads_cache,
 			self.io.state_reads,
+			self.io.trie_node_reads_cache,
+			self.io.trie_node_reads,
 			self.io.state_writes_nodes,
 		)
 	}
@@ -246,3 +319,54 @@ pub struct FinalityNotification<Block: BlockT> {
 	/// Imported block header.
 	pub header: Block::Header,
 }
+
+/// Summary of a chain reorganization.
+#[derive(Clone, Debug)]
+pub struct ReorgNotification<Block: BlockT> {
+	/// Hash of the new best block.
+	pub new_best: Block::Hash,
+	/// Blocks removed from the best chain, ordered by block number.
+	pub retracted: Vec<Block::Hash>,
+	/// Blocks added to the best chain, ordered by block number. The last
+	/// element is always `new_best`.
+	pub enacted: Vec<Block::Hash>,
+}
+
+/// Notification of the indexers, for each imported block and then for each finalized block.
+#[derive(Clone, Debug)]
+pub enum IndexerNotification<Block: BlockT> {
+	/// A block was imported.
+	Imported(Arc<IndexedBlock<Block>>),
+	/// A block was finalized. Blocks are finalized in ascending order.
+	Finalized(FinalityNotification<Block>),
+}
+
+/// A block imported by the client, as sent to the indexers.
+#[derive(Debug)]
+pub struct IndexedBlock<Block: BlockT> {
+	/// Hash of the block.
+	pub hash: Block::Hash,
+	/// Header of the block.
+	pub header: Block::Header,
+	/// Body of the block, `None` if it isn't stored.
+	pub body: Option<Vec<Block::Extrinsic>>,
+	/// Origin of the block.
+	pub origin: BlockOrigin,
+	/// Is this the new best block.
+	pub is_new_best: bool,
+	/// Changes of the top and child storages made by the block, `None` if the storage diffs are
+	/// disabled or if the block wasn't executed, e.g. when its state was downloaded.
+	///
+	/// The events deposited by the runtime are among the changes of the top storage, e.g. under
+	/// the `System Events` key for FRAME runtimes.
+	pub storage_changes: Option<(StorageCollection, ChildStorageCollection)>,
+}
+
+/// Summary of a batch of blocks finalized together.
+#[derive(Clone, Debug)]
+pub struct FinalityBatchNotification<Block: BlockT> {
+	/// Finalized block hashes, ordered by block number.
+	pub finalized: Vec<Block::Hash>,
+	/// Header of the last block in `finalized`.
+	pub header: Block::Header,
+}
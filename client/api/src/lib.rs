@@ -26,6 +26,7 @@ pub mod in_mem;
 pub mod light;
 pub mod leaves;
 pub mod notifications;
+pub mod notification_sinks;
 pub mod proof_provider;
 
 pub use sp_blockchain as blockchain;
@@ -35,6 +36,7 @@ pub use call_executor::*;
 pub use client::*;
 pub use light::*;
 pub use notifications::*;
+pub use notification_sinks::*;
 pub use proof_provider::*;
 
 pub use sp_state_machine::{StorageProof, ExecutionStrategy, CloneableSpawn};
@@ -0,0 +1,280 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2017-2020 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Block import and finality notification streams.
+//!
+//! The streams of external subscribers, e.g. RPC subscriptions, have a bounded queue and the
+//! slow subscriber policy applies to them. The streams of the node's own tasks are unbounded, as
+//! those can't afford to miss a notification.
+
+use std::{
+	pin::Pin,
+	sync::{Arc, atomic::{AtomicUsize, Ordering}},
+	task::{Context, Poll},
+};
+
+use futures::{Stream, channel::mpsc::TryRecvError};
+use parking_lot::Mutex;
+use prometheus_endpoint::{Registry, CounterVec, GaugeVec, Opts, PrometheusError, U64, register};
+use sp_utils::mpsc::{TracingUnboundedSender, TracingUnboundedReceiver, tracing_unbounded};
+
+/// Default maximum number of notifications queued for a single subscriber.
+pub const DEFAULT_NOTIFICATION_CAPACITY: usize = 1024;
+
+/// What to do with a notification for a subscriber whose queue is full.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SlowSubscriberPolicy {
+	/// Don't send the notification to the subscriber, which misses it.
+	DropNotification,
+	/// End the stream of the subscriber once it has received the queued notifications.
+	Disconnect,
+}
+
+/// Configuration of the notification streams of the client.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NotificationConfig {
+	/// Maximum number of notifications queued for a single subscriber.
+	pub capacity: usize,
+	/// What to do with the notifications for subscribers whose queue is full.
+	pub slow_subscriber_policy: SlowSubscriberPolicy,
+}
+
+impl Default for NotificationConfig {
+	fn default() -> Self {
+		NotificationConfig {
+			capacity: DEFAULT_NOTIFICATION_CAPACITY,
+			slow_subscriber_policy: SlowSubscriberPolicy::DropNotification,
+		}
+	}
+}
+
+/// Prometheus metrics of the notification streams, labeled by stream.
+#[derive(Debug, Clone)]
+pub struct NotificationMetrics {
+	lag: GaugeVec<U64>,
+	overflows: CounterVec<U64>,
+}
+
+impl NotificationMetrics {
+	/// Register the metrics in `registry`.
+	pub fn register(registry: &Registry) -> Result<Self, PrometheusError> {
+		Ok(NotificationMetrics {
+			lag: register(GaugeVec::new(
+				Opts::new(
+					"notification_subscriber_lag",
+					"Number of notifications queued for the slowest subscriber",
+				),
+				&["stream"],
+			)?, registry)?,
+			overflows: register(CounterVec::new(
+				Opts::new(
+					"notification_overflows",
+					"Number of notifications not sent to subscribers whose queue is full",
+				),
+				&["stream", "action"], // dropped | disconnected
+			)?, registry)?,
+		})
+	}
+}
+
+struct Sink<T> {
+	sender: TracingUnboundedSender<T>,
+	queued: Arc<AtomicUsize>,
+	/// Whether the capacity and the slow subscriber policy apply to the subscriber.
+	bounded: bool,
+}
+
+/// Senders of one kind of notification to each of its subscribers.
+pub struct NotificationSinks<T> {
+	name: &'static str,
+	config: NotificationConfig,
+	metrics: Option<NotificationMetrics>,
+	sinks: Mutex<Vec<Sink<T>>>,
+}
+
+impl<T: Clone> NotificationSinks<T> {
+	/// Create the senders of the stream `name`, which is also the label of its metrics.
+	pub fn new(
+		name: &'static str,
+		config: NotificationConfig,
+		metrics: Option<NotificationMetrics>,
+	) -> Self {
+		NotificationSinks {
+			name,
+			config,
+			metrics,
+			sinks: Mutex::new(Vec::new()),
+		}
+	}
+
+	/// Add a subscriber whose queue is bounded by the capacity.
+	pub fn subscribe(&self) -> NotificationStream<T> {
+		self.add_subscriber(true)
+	}
+
+	/// Add a subscriber which never misses a notification, whatever the number queued.
+	pub fn subscribe_unbounded(&self) -> NotificationStream<T> {
+		self.add_subscriber(false)
+	}
+
+	fn add_subscriber(&self, bounded: bool) -> NotificationStream<T> {
+		let (sender, receiver) = tracing_unbounded(self.name);
+		let queued = Arc::new(AtomicUsize::new(0));
+		self.sinks.lock().push(Sink { sender, queued: queued.clone(), bounded });
+
+		NotificationStream { receiver, queued }
+	}
+
+	/// Send `notification` to all the subscribers, applying the slow subscriber policy to the
+	/// bounded ones whose queue is full, and forget the subscribers that are gone.
+	pub fn notify(&self, notification: T) {
+		let mut lag = 0;
+		self.sinks.lock().retain(|sink| {
+			if sink.bounded && sink.queued.load(Ordering::Acquire) >= self.config.capacity {
+				lag = self.config.capacity;
+				return match self.config.slow_subscriber_policy {
+					SlowSubscriberPolicy::DropNotification => {
+						self.note_overflow("dropped");
+						!sink.sender.is_closed()
+					},
+					SlowSubscriberPolicy::Disconnect => {
+						self.note_overflow("disconnected");
+						false
+					},
+				};
+			}
+
+			// Counted before sending, so that the subscriber never sees more notifications than
+			// counted.
+			let queued = sink.queued.fetch_add(1, Ordering::AcqRel) + 1;
+			lag = lag.max(queued);
+			sink.sender.unbounded_send(notification.clone()).is_ok()
+		});
+
+		if let Some(metrics) = &self.metrics {
+			metrics.lag.with_label_values(&[self.name]).set(lag as u64);
+		}
+	}
+
+	/// Forget the subscribers that are gone.
+	pub fn remove_closed(&self) {
+		self.sinks.lock().retain(|sink| !sink.sender.is_closed());
+	}
+
+	/// Returns the number of subscribers.
+	pub fn len(&self) -> usize {
+		self.sinks.lock().len()
+	}
+
+	fn note_overflow(&self, action: &str) {
+		if let Some(metrics) = &self.metrics {
+			metrics.overflows.with_label_values(&[self.name, action]).inc();
+		}
+	}
+}
+
+/// Stream of the notifications sent by [`NotificationSinks`].
+#[derive(Debug)]
+pub struct NotificationStream<T> {
+	receiver: TracingUnboundedReceiver<T>,
+	queued: Arc<AtomicUsize>,
+}
+
+impl<T> NotificationStream<T> {
+	/// Returns the next notification if there is one queued, without waiting for it.
+	///
+	/// Like `Receiver::try_next`, `Ok(None)` means that the stream ended.
+	pub fn try_next(&mut self) -> Result<Option<T>, TryRecvError> {
+		let next = self.receiver.try_next();
+		if let Ok(Some(_)) = next {
+			self.queued.fetch_sub(1, Ordering::AcqRel);
+		}
+		next
+	}
+}
+
+impl<T> Stream for NotificationStream<T> {
+	type Item = T;
+
+	fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<T>> {
+		let this = &mut *self;
+		let next = Pin::new(&mut this.receiver).poll_next(cx);
+		if let Poll::Ready(Some(_)) = next {
+			this.queued.fetch_sub(1, Ordering::AcqRel);
+		}
+		next
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use futures::{executor::block_on_stream, StreamExt};
+
+	fn sinks(policy: SlowSubscriberPolicy) -> NotificationSinks<u32> {
+		NotificationSinks::new(
+			"test",
+			NotificationConfig { capacity: 2, slow_subscriber_policy: policy },
+			None,
+		)
+	}
+
+	#[test]
+	fn drops_notifications_for_slow_subscribers() {
+		let sinks = sinks(SlowSubscriberPolicy::DropNotification);
+		let mut stream = sinks.subscribe();
+
+		sinks.notify(1);
+		sinks.notify(2);
+		sinks.notify(3);
+		assert_eq!(futures::executor::block_on(stream.next()), Some(1));
+		sinks.notify(4);
+		drop(sinks);
+
+		assert_eq!(block_on_stream(stream).collect::<Vec<_>>(), vec![2, 4]);
+	}
+
+	#[test]
+	fn disconnects_slow_subscribers() {
+		let sinks = sinks(SlowSubscriberPolicy::Disconnect);
+		let stream = sinks.subscribe();
+
+		sinks.notify(1);
+		sinks.notify(2);
+		sinks.notify(3);
+		assert_eq!(sinks.len(), 0);
+
+		assert_eq!(block_on_stream(stream).collect::<Vec<_>>(), vec![1, 2]);
+	}
+
+	#[test]
+	fn unbounded_subscribers_miss_nothing() {
+		let sinks = sinks(SlowSubscriberPolicy::Disconnect);
+		let bounded = sinks.subscribe();
+		let unbounded = sinks.subscribe_unbounded();
+
+		for n in 1..=4 {
+			sinks.notify(n);
+		}
+		assert_eq!(sinks.len(), 1);
+		drop(sinks);
+
+		assert_eq!(block_on_stream(bounded).collect::<Vec<_>>(), vec![1, 2]);
+		assert_eq!(block_on_stream(unbounded).collect::<Vec<_>>(), vec![1, 2, 3, 4]);
+	}
+}
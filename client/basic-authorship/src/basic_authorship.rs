@@ -31,7 +31,7 @@ use sp_runtime::{
 	generic::BlockId,
 	traits::{Block as BlockT, Hash as HashT, Header as HeaderT, DigestFor, BlakeTwo256},
 };
-use sp_transaction_pool::{TransactionPool, InPoolTransaction};
+use sp_transaction_pool::{TransactionPool, InPoolTransaction, TransactionSelector};
 use sc_telemetry::{telemetry, CONSENSUS_INFO};
 use sc_block_builder::{BlockBuilderApi, BlockBuilderProvider};
 use sp_api::{ProvideRuntimeApi, ApiExt};
@@ -50,6 +50,8 @@ pub struct ProposerFactory<A, B, C> {
 	transaction_pool: Arc<A>,
 	/// Prometheus Link,
 	metrics: PrometheusMetrics,
+	/// Selection of the ready transactions included in the blocks, if customized.
+	transaction_selector: Option<Arc<dyn TransactionSelector<A>>>,
 	/// phantom member to pin the `Backend` type.
 	_phantom: PhantomData<B>,
 }
@@ -64,9 +66,16 @@ impl<A, B, C> ProposerFactory<A, B, C> {
 			client,
 			transaction_pool,
 			metrics: PrometheusMetrics::new(prometheus),
+			transaction_selector: None,
 			_phantom: PhantomData,
 		}
 	}
+
+	/// Use `selector` to choose which ready transactions are included in the proposed blocks,
+	/// instead of all of them by priority.
+	pub fn set_transaction_selector(&mut self, selector: Arc<dyn TransactionSelector<A>>) {
+		self.transaction_selector = Some(selector);
+	}
 }
 
 impl<B, Block, C, A> ProposerFactory<A, B, C>
@@ -98,6 +107,7 @@ impl<B, Block, C, A> ProposerFactory<A, B, C>
 			transaction_pool: self.transaction_pool.clone(),
 			now,
 			metrics: self.metrics.clone(),
+			transaction_selector: self.transaction_selector.clone(),
 			_phantom: PhantomData,
 		};
 
@@ -137,6 +147,7 @@ pub struct Proposer<B, Block: BlockT, C, A: TransactionPool> {
 	transaction_pool: Arc<A>,
 	now: Box<dyn Fn() -> time::Instant + Send + Sync>,
 	metrics: PrometheusMetrics,
+	transaction_selector: Option<Arc<dyn TransactionSelector<A>>>,
 	_phantom: PhantomData<B>,
 }
 
@@ -240,6 +251,10 @@ impl<A, B, Block, C> Proposer<B, Block, C, A>
 				self.transaction_pool.ready()
 			}
 		};
+		let pending_iterator = match &self.transaction_selector {
+			Some(selector) => selector.select(&self.parent_id, pending_iterator),
+			None => pending_iterator,
+		};
 
 		debug!("Attempting to push transactions from the pool.");
 		debug!("Pool status: {:?}", self.transaction_pool.status());
@@ -336,7 +351,9 @@ mod tests {
 		prelude::*,
 		runtime::{Extrinsic, Transfer},
 	};
-	use sp_transaction_pool::{ChainEvent, MaintainedTransactionPool, TransactionSource};
+	use sp_transaction_pool::{
+		ChainEvent, MaintainedTransactionPool, ReadyTransactions, TransactionSource,
+	};
 	use sc_transaction_pool::{BasicPool, FullChainApi};
 	use sp_api::Core;
 	use backend::Backend;
@@ -593,4 +610,55 @@ mod tests {
 		let block = propose_block(&client, 1, 2, 5);
 		client.import(BlockOrigin::Own, block).unwrap();
 	}
+
+	#[test]
+	fn should_only_push_selected_transactions() {
+		struct FirstOnly;
+
+		impl<P: TransactionPool> TransactionSelector<P> for FirstOnly {
+			fn select(
+				&self,
+				_at: &BlockId<P::Block>,
+				ready: ReadyTransactions<P>,
+			) -> ReadyTransactions<P> {
+				Box::new(ready.take(1))
+			}
+		}
+
+		let client = Arc::new(substrate_test_runtime_client::new());
+		let txpool = Arc::new(
+			BasicPool::new(
+				Default::default(),
+				Arc::new(FullChainApi::new(client.clone())),
+				None,
+			).0
+		);
+
+		futures::executor::block_on(
+			txpool.submit_at(&BlockId::number(0), SOURCE, vec![extrinsic(0), extrinsic(1)])
+		).unwrap();
+
+		futures::executor::block_on(
+			txpool.maintain(chain_event(
+				0,
+				client.header(&BlockId::Number(0u64)).expect("header get error").expect("there should be header")
+			))
+		);
+
+		let mut proposer_factory = ProposerFactory::new(client.clone(), txpool.clone(), None);
+		proposer_factory.set_transaction_selector(Arc::new(FirstOnly));
+
+		let proposer = proposer_factory.init_with_now(
+			&client.header(&BlockId::number(0)).unwrap().unwrap(),
+			Box::new(move || time::Instant::now()),
+		);
+
+		let deadline = time::Duration::from_secs(9);
+		let block = futures::executor::block_on(
+			proposer.propose(Default::default(), Default::default(), deadline, RecordProof::No)
+		).map(|r| r.block).unwrap();
+
+		assert_eq!(block.extrinsics().len(), 1);
+		assert_eq!(txpool.ready().count(), 2);
+	}
 }
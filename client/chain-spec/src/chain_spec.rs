@@ -18,9 +18,9 @@
 
 //! Substrate chain configurations.
 
-use std::{borrow::Cow, fs::File, path::PathBuf, sync::Arc, collections::HashMap};
+use std::{borrow::Cow, fs::File, path::PathBuf, sync::Arc, collections::{BTreeMap, HashMap}};
 use serde::{Serialize, Deserialize};
-use sp_core::storage::{StorageKey, StorageData, ChildInfo, Storage, StorageChild};
+use sp_core::{Bytes, storage::{StorageKey, StorageData, ChildInfo, Storage, StorageChild}};
 use sp_runtime::BuildStorage;
 use serde_json as json;
 use crate::{RuntimeGenesis, ChainType, extension::GetExtension, Properties};
@@ -95,19 +95,7 @@ impl<G: RuntimeGenesis, E> BuildStorage for ChainSpec<G, E> {
 	fn build_storage(&self) -> Result<Storage, String> {
 		match self.genesis.resolve()? {
 			Genesis::Runtime(gc) => gc.build_storage(),
-			Genesis::Raw(RawGenesis { top: map, children_default: children_map }) => Ok(Storage {
-				top: map.into_iter().map(|(k, v)| (k.0, v.0)).collect(),
-				children_default: children_map.into_iter().map(|(storage_key, child_content)| {
-					let child_info = ChildInfo::new_default(storage_key.0.as_slice());
-					(
-						storage_key.0,
-						StorageChild {
-							data: child_content.into_iter().map(|(k, v)| (k.0, v.0)).collect(),
-							child_info,
-						},
-					)
-				}).collect(),
-			}),
+			Genesis::Raw(raw) => Ok(raw.into()),
 		}
 	}
 
@@ -130,6 +118,41 @@ pub struct RawGenesis {
 	pub children_default: HashMap<StorageKey, GenesisStorage>,
 }
 
+impl From<RawGenesis> for Storage {
+	fn from(raw: RawGenesis) -> Storage {
+		Storage {
+			top: raw.top.into_iter().map(|(k, v)| (k.0, v.0)).collect(),
+			children_default: raw.children_default.into_iter().map(|(storage_key, child_content)| {
+				let child_info = ChildInfo::new_default(storage_key.0.as_slice());
+				(
+					storage_key.0,
+					StorageChild {
+						data: child_content.into_iter().map(|(k, v)| (k.0, v.0)).collect(),
+						child_info,
+					},
+				)
+			}).collect(),
+		}
+	}
+}
+
+/// Decode the genesis storage of a raw chain spec JSON.
+///
+/// Fails if the spec doesn't contain raw genesis storage, e.g. if it wasn't built with `raw`.
+pub fn raw_genesis_storage_from_json(json: &[u8]) -> Result<Storage, String> {
+	#[derive(Deserialize)]
+	struct GenesisContainer {
+		genesis: Genesis<json::Value>,
+	}
+
+	let container: GenesisContainer = json::from_slice(json)
+		.map_err(|e| format!("Error parsing spec json: {}", e))?;
+	match container.genesis {
+		Genesis::Raw(raw) => Ok(raw.into()),
+		Genesis::Runtime(_) => Err("Chain spec doesn't contain raw genesis storage".into()),
+	}
+}
+
 #[derive(Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 #[serde(deny_unknown_fields)]
@@ -157,6 +180,24 @@ struct ClientSpec<E> {
 	consensus_engine: (),
 	#[serde(skip_serializing)]
 	genesis: serde::de::IgnoredAny,
+	/// Runtime code to use instead of the on-chain code, keyed by the number of the block it is
+	/// used from.
+	#[serde(default)]
+	code_substitutes: BTreeMap<String, Bytes>,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	light_sync_state: Option<LightSyncState>,
+}
+
+/// A checkpoint that light clients can start syncing from instead of genesis.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+#[serde(deny_unknown_fields)]
+pub struct LightSyncState {
+	/// SCALE encoded header of the finalized block the checkpoint was taken at.
+	pub finalized_block_header: Bytes,
+	/// Consensus data at the checkpoint (e.g. epoch changes or the GRANDPA authority set),
+	/// as pairs of auxiliary storage key and value.
+	pub consensus_data: Vec<(Bytes, Bytes)>,
 }
 
 /// A type denoting empty extensions.
@@ -222,6 +263,12 @@ impl<G, E> ChainSpec<G, E> {
 		&self.client_spec.extensions
 	}
 
+	/// Register runtime `code` to be used instead of the on-chain code from the block with number
+	/// `block_number` on.
+	pub fn add_code_substitute(&mut self, block_number: u64, code: Vec<u8>) {
+		self.client_spec.code_substitutes.insert(block_number.to_string(), Bytes(code));
+	}
+
 	/// Create hardcoded spec.
 	pub fn from_genesis<F: Fn() -> G + 'static + Send + Sync>(
 		name: &str,
@@ -245,6 +292,8 @@ impl<G, E> ChainSpec<G, E> {
 			extensions,
 			consensus_engine: (),
 			genesis: Default::default(),
+			code_substitutes: BTreeMap::new(),
+			light_sync_state: None,
 		};
 
 		ChainSpec {
@@ -363,6 +412,13 @@ where
 		ChainSpec::extensions(self) as &dyn GetExtension
 	}
 
+	fn extension_field(&self, name: &str) -> Option<json::Value> {
+		match json::to_value(&self.client_spec.extensions) {
+			Ok(json::Value::Object(mut fields)) => fields.remove(name),
+			_ => None,
+		}
+	}
+
 	fn as_json(&self, raw: bool) -> Result<String, String> {
 		ChainSpec::as_json(self, raw)
 	}
@@ -378,6 +434,21 @@ where
 	fn set_storage(&mut self, storage: Storage) {
 		self.genesis = GenesisSource::Storage(storage);
 	}
+
+	fn light_sync_state(&self) -> Option<&LightSyncState> {
+		self.client_spec.light_sync_state.as_ref()
+	}
+
+	fn set_light_sync_state(&mut self, light_sync_state: LightSyncState) {
+		self.client_spec.light_sync_state = Some(light_sync_state);
+	}
+
+	fn code_substitutes(&self) -> BTreeMap<String, Vec<u8>> {
+		self.client_spec.code_substitutes
+			.iter()
+			.map(|(number, code)| (number.clone(), code.0.clone()))
+			.collect()
+	}
 }
 
 #[cfg(test)]
@@ -430,4 +501,80 @@ mod tests {
 
 		assert_eq!(spec.extensions().my_property, "Test Extension");
 	}
+
+	#[test]
+	fn raw_genesis_storage_should_round_trip() {
+		let mut genesis = HashMap::new();
+		genesis.insert("key".to_string(), "value".to_string());
+		let spec = TestSpec::from_genesis(
+			"Test",
+			"test",
+			ChainType::Local,
+			move || Genesis(genesis.clone()),
+			Vec::new(),
+			None,
+			None,
+			None,
+			None,
+		);
+
+		let json = spec.as_json(true).unwrap();
+		assert_eq!(
+			raw_genesis_storage_from_json(json.as_bytes()).unwrap().top,
+			spec.build_storage().unwrap().top,
+		);
+
+		let json = spec.as_json(false).unwrap();
+		assert!(raw_genesis_storage_from_json(json.as_bytes()).is_err());
+	}
+
+	#[test]
+	fn code_substitutes_should_round_trip() {
+		let mut spec = TestSpec::from_json_bytes(Cow::Owned(
+			include_bytes!("../res/chain_spec.json").to_vec()
+		)).unwrap();
+		assert!(crate::ChainSpec::code_substitutes(&spec).is_empty());
+
+		spec.add_code_substitute(42, vec![1, 2, 3]);
+		let json = spec.as_json(false).unwrap();
+		let spec = TestSpec::from_json_bytes(json.into_bytes()).unwrap();
+
+		let substitutes = crate::ChainSpec::code_substitutes(&spec);
+		assert_eq!(substitutes.len(), 1);
+		assert_eq!(substitutes["42"], vec![1, 2, 3]);
+	}
+
+	#[test]
+	fn light_sync_state_should_round_trip() {
+		let mut spec = TestSpec::from_json_bytes(Cow::Owned(
+			include_bytes!("../res/chain_spec.json").to_vec()
+		)).unwrap();
+		assert!(crate::ChainSpec::light_sync_state(&spec).is_none());
+
+		crate::ChainSpec::set_light_sync_state(&mut spec, LightSyncState {
+			finalized_block_header: Bytes(vec![1, 2, 3]),
+			consensus_data: vec![(Bytes(b"key".to_vec()), Bytes(vec![4, 5]))],
+		});
+		let json = spec.as_json(false).unwrap();
+		let spec = TestSpec::from_json_bytes(json.into_bytes()).unwrap();
+
+		let state = crate::ChainSpec::light_sync_state(&spec).unwrap();
+		assert_eq!(state.finalized_block_header, Bytes(vec![1, 2, 3]));
+		assert_eq!(state.consensus_data, vec![(Bytes(b"key".to_vec()), Bytes(vec![4, 5]))]);
+	}
+
+	#[test]
+	fn should_get_extension_field_by_name() {
+		let spec = TestSpec2::from_json_bytes(Cow::Owned(
+			include_bytes!("../res/chain_spec2.json").to_vec()
+		)).unwrap();
+		let spec: &dyn crate::ChainSpec = &spec;
+
+		assert_eq!(
+			crate::get_extension_field::<String>(spec, "myProperty"),
+			Ok(Some("Test Extension".to_string())),
+		);
+		assert_eq!(crate::get_extension_field::<String>(spec, "unknown"), Ok(None));
+		assert!(crate::get_extension_field::<u64>(spec, "myProperty").is_err());
+	}
 }
@@ -110,7 +110,9 @@
 mod chain_spec;
 mod extension;
 
-pub use chain_spec::{ChainSpec as GenericChainSpec, NoExtension};
+pub use chain_spec::{
+	ChainSpec as GenericChainSpec, NoExtension, LightSyncState, raw_genesis_storage_from_json,
+};
 pub use extension::{Group, Fork, Forks, Extension, GetExtension, get_extension};
 pub use sc_chain_spec_derive::{ChainSpecExtension, ChainSpecGroup};
 pub use sp_chain_spec::{Properties, ChainType};
@@ -121,6 +123,22 @@ use sc_network::config::MultiaddrWithPeerId;
 use sc_telemetry::TelemetryEndpoints;
 use sp_core::storage::Storage;
 
+/// Get a chain spec extension field deserialized into `T`.
+///
+/// This gives typed access to custom configuration embedded in the spec JSON without knowing
+/// the concrete extension type the spec was constructed with. Returns `Ok(None)` if the field
+/// is not defined and an error if it can't be decoded as `T`.
+pub fn get_extension_field<T: DeserializeOwned>(
+	spec: &dyn ChainSpec,
+	name: &str,
+) -> Result<Option<T>, String> {
+	spec.extension_field(name)
+		.map(|value| serde_json::from_value(value)
+			.map_err(|e| format!("Error decoding chain spec extension `{}`: {}", name, e))
+		)
+		.transpose()
+}
+
 /// A set of traits for the runtime genesis config.
 pub trait RuntimeGenesis: Serialize + DeserializeOwned + BuildStorage {}
 impl<T: Serialize + DeserializeOwned + BuildStorage> RuntimeGenesis for T {}
@@ -145,6 +163,11 @@ pub trait ChainSpec: BuildStorage + Send {
 	fn properties(&self) -> Properties;
 	/// Returns a reference to defined chain spec extensions.
 	fn extensions(&self) -> &dyn GetExtension;
+	/// Returns the raw JSON value of the chain spec extension field with the given name.
+	///
+	/// Extension fields are flattened into the top level of the spec JSON, so `name` is the
+	/// key as it appears there. Returns `None` if the spec does not define such a field.
+	fn extension_field(&self, name: &str) -> Option<serde_json::Value>;
 	/// Add a bootnode to the list.
 	fn add_boot_node(&mut self, addr: MultiaddrWithPeerId);
 	/// Return spec as JSON.
@@ -157,4 +180,14 @@ pub trait ChainSpec: BuildStorage + Send {
 	///
 	/// This will be used as storage at genesis.
 	fn set_storage(&mut self, storage: Storage);
+	/// Returns runtime code substitutes, keyed by the number of the block they are used from.
+	///
+	/// A substitute replaces the on-chain runtime code from the block with the given number on,
+	/// as long as the on-chain code is the one of that block. This allows a chain to sync past
+	/// blocks whose runtime is broken.
+	fn code_substitutes(&self) -> std::collections::BTreeMap<String, Vec<u8>>;
+	/// Returns the light client sync checkpoint embedded in the spec, if any.
+	fn light_sync_state(&self) -> Option<&LightSyncState>;
+	/// Embed a light client sync checkpoint into the spec.
+	fn set_light_sync_state(&mut self, light_sync_state: LightSyncState);
 }
@@ -77,6 +77,58 @@ impl Into<sc_tracing::TracingReceiver> for TracingReceiver {
 	}
 }
 
+arg_enum! {
+	/// Blocks that trigger an offchain worker run.
+	#[allow(missing_docs)]
+	#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+	pub enum OffchainWorkerTrigger {
+		BestBlock,
+		FinalizedBlock,
+	}
+}
+
+impl Into<sc_service::config::OffchainWorkerTrigger> for OffchainWorkerTrigger {
+	fn into(self) -> sc_service::config::OffchainWorkerTrigger {
+		match self {
+			OffchainWorkerTrigger::BestBlock => sc_service::config::OffchainWorkerTrigger::BestBlock,
+			OffchainWorkerTrigger::FinalizedBlock =>
+				sc_service::config::OffchainWorkerTrigger::FinalizedBlock,
+		}
+	}
+}
+
+arg_enum! {
+	/// What to do with the notifications for subscribers that don't keep up.
+	#[allow(missing_docs)]
+	#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+	pub enum SlowSubscriberPolicy {
+		Drop,
+		Disconnect,
+	}
+}
+
+impl Into<sc_service::config::SlowSubscriberPolicy> for SlowSubscriberPolicy {
+	fn into(self) -> sc_service::config::SlowSubscriberPolicy {
+		match self {
+			SlowSubscriberPolicy::Drop =>
+				sc_service::config::SlowSubscriberPolicy::DropNotification,
+			SlowSubscriberPolicy::Disconnect =>
+				sc_service::config::SlowSubscriberPolicy::Disconnect,
+		}
+	}
+}
+
+arg_enum! {
+	/// Which finalized blocks get a GRANDPA justification, besides the blocks enacting authority
+	/// set changes.
+	#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+	pub enum JustificationPolicy {
+		EveryBlock,
+		Periodic,
+		OnDemand,
+	}
+}
+
 arg_enum! {
 	#[allow(missing_docs)]
 	#[derive(Debug, Copy, Clone, PartialEq, Eq)]
@@ -85,6 +137,25 @@ arg_enum! {
 	}
 }
 
+arg_enum! {
+	/// Which peers to keep connected while performing a major sync.
+	#[allow(missing_docs)]
+	#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+	pub enum SyncPeerStrategy {
+		Any,
+		PreferAhead,
+	}
+}
+
+impl Into<sc_network::config::SyncPeerStrategy> for SyncPeerStrategy {
+	fn into(self) -> sc_network::config::SyncPeerStrategy {
+		match self {
+			SyncPeerStrategy::Any => sc_network::config::SyncPeerStrategy::Any,
+			SyncPeerStrategy::PreferAhead => sc_network::config::SyncPeerStrategy::PreferAhead,
+		}
+	}
+}
+
 arg_enum! {
 	/// How to execute blocks
 	#[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -162,6 +233,75 @@ arg_enum! {
 	}
 }
 
+arg_enum! {
+	/// Compression of the block bodies and justifications in the database.
+	#[allow(missing_docs)]
+	#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+	pub enum DatabaseCompression {
+		// Values are stored uncompressed.
+		None,
+		// Values are compressed with zstd.
+		Zstd,
+	}
+}
+
+arg_enum! {
+	/// Deployment profile that adjusts the defaults of other parameters.
+	#[allow(missing_docs)]
+	#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+	pub enum Profile {
+		// Block authoring node, implies `--validator`.
+		Validator,
+		// Node serving RPC to the public.
+		Rpc,
+		// Node keeping the state of all blocks.
+		Archive,
+		// Light client, implies `--light`.
+		Light,
+	}
+}
+
+impl Profile {
+	/// Default state pruning mode, `None` to use the default of the node role.
+	pub fn pruning(self) -> Option<sc_service::PruningMode> {
+		match self {
+			Profile::Archive => Some(sc_service::PruningMode::ArchiveAll),
+			_ => None,
+		}
+	}
+
+	/// Default size of the trie node cache and of the storage value cache in bytes.
+	pub fn cache_size(self) -> usize {
+		match self {
+			Profile::Validator => DEFAULT_CACHE_SIZE,
+			Profile::Rpc | Profile::Archive => 4 * DEFAULT_CACHE_SIZE,
+			Profile::Light => DEFAULT_CACHE_SIZE / 4,
+		}
+	}
+
+	/// Default number of incoming and outgoing peers.
+	pub fn peers(self) -> (u32, u32) {
+		match self {
+			Profile::Validator | Profile::Archive => (DEFAULT_IN_PEERS, DEFAULT_OUT_PEERS),
+			Profile::Rpc => (2 * DEFAULT_IN_PEERS, DEFAULT_OUT_PEERS),
+			Profile::Light => (DEFAULT_IN_PEERS / 5, DEFAULT_OUT_PEERS / 5),
+		}
+	}
+
+	/// Default maximum number of transactions and kilobytes in the transaction pool.
+	pub fn pool_limits(self) -> (usize, usize) {
+		match self {
+			Profile::Validator | Profile::Archive => (DEFAULT_POOL_LIMIT, DEFAULT_POOL_KBYTES),
+			Profile::Rpc => (2 * DEFAULT_POOL_LIMIT, 2 * DEFAULT_POOL_KBYTES),
+			Profile::Light => (DEFAULT_POOL_LIMIT / 8, DEFAULT_POOL_KBYTES / 8),
+		}
+	}
+
+	/// Whether RPC is exposed on all interfaces by default.
+	pub fn rpc_external(self) -> bool {
+		self == Profile::Rpc
+	}
+}
 
 arg_enum! {
 	/// Whether off-chain workers are enabled.
@@ -174,6 +314,26 @@ arg_enum! {
 	}
 }
 
+arg_enum! {
+	/// Encoding of exported runtime metadata.
+	#[allow(missing_docs)]
+	#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+	pub enum MetadataFormat {
+		Scale,
+		Json,
+	}
+}
+
+/// Default value for the `--trie-cache-size` and `--value-cache-size` parameters.
+pub const DEFAULT_CACHE_SIZE: usize = 64 * 1024 * 1024;
+/// Default value for the `--in-peers` parameter.
+pub const DEFAULT_IN_PEERS: u32 = 25;
+/// Default value for the `--out-peers` parameter.
+pub const DEFAULT_OUT_PEERS: u32 = 25;
+/// Default value for the `--pool-limit` parameter.
+pub const DEFAULT_POOL_LIMIT: usize = 8192;
+/// Default value for the `--pool-kbytes` parameter.
+pub const DEFAULT_POOL_KBYTES: usize = 20480;
 /// Default value for the `--execution-syncing` parameter.
 pub const DEFAULT_EXECUTION_SYNCING: ExecutionStrategy = ExecutionStrategy::NativeElseWasm;
 /// Default value for the `--execution-import-block` parameter.
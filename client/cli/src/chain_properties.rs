@@ -0,0 +1,154 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2020 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Chain spec properties affecting how addresses and balances are rendered.
+
+use crate::error::{Error, Result};
+use log::warn;
+use sc_service::{ChainSpec, Properties};
+use sp_core::crypto::{set_default_ss58_version, Ss58AddressFormat};
+use std::{convert::TryFrom, fmt};
+
+/// The `properties` of a chain spec the CLI uses to render addresses and balances.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ChainProperties {
+	/// Prefix of the SS58 addresses, from `ss58Format`.
+	pub ss58_format: Option<u8>,
+	/// Symbol of the native token, from `tokenSymbol`.
+	pub token_symbol: Option<String>,
+	/// Number of decimals of the native token, from `tokenDecimals`.
+	pub token_decimals: Option<u32>,
+}
+
+impl ChainProperties {
+	/// Read the properties of `spec`, failing if the SS58 prefix is malformed.
+	///
+	/// The malformed token properties, which are only used for display, are ignored with a
+	/// warning.
+	pub fn from_chain_spec(spec: &dyn ChainSpec) -> Result<Self> {
+		Self::from_properties(&spec.properties())
+	}
+
+	fn from_properties(properties: &Properties) -> Result<Self> {
+		let invalid = |key: &str, value: &serde_json::Value| Error::Input(format!(
+			"Invalid chain spec property {}: {}", key, value,
+		));
+		let ss58_format = properties.get("ss58Format")
+			.map(|v| v.as_u64()
+				.and_then(|n| u8::try_from(n).ok())
+				.ok_or_else(|| invalid("ss58Format", v)))
+			.transpose()?;
+		let token_symbol = properties.get("tokenSymbol")
+			.and_then(|v| v.as_str()
+				.map(Into::into)
+				.or_else(|| ignore_invalid("tokenSymbol", v)));
+		let token_decimals = properties.get("tokenDecimals")
+			.and_then(|v| v.as_u64()
+				.and_then(|n| u32::try_from(n).ok())
+				.or_else(|| ignore_invalid("tokenDecimals", v)));
+
+		Ok(ChainProperties { ss58_format, token_symbol, token_decimals })
+	}
+
+	/// Make the SS58 prefix of these properties the one used to render the addresses, if any.
+	pub fn apply_ss58_format(&self) {
+		if let Some(prefix) = self.ss58_format {
+			set_default_ss58_version(ss58_address_format(prefix));
+		}
+	}
+}
+
+impl fmt::Display for ChainProperties {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match (&self.token_symbol, self.token_decimals) {
+			(Some(symbol), Some(decimals)) => write!(f, "{} ({} decimals)", symbol, decimals)?,
+			(Some(symbol), None) => write!(f, "{}", symbol)?,
+			(None, Some(decimals)) => write!(f, "{} decimals", decimals)?,
+			(None, None) => write!(f, "unspecified token")?,
+		}
+		let prefix = u8::from(self.ss58_format.map_or_else(Default::default, ss58_address_format));
+		write!(f, ", SS58 prefix {}", prefix)
+	}
+}
+
+/// Warn that the chain spec property `key` is ignored, as its `value` is malformed.
+fn ignore_invalid<T>(key: &str, value: &serde_json::Value) -> Option<T> {
+	warn!("Ignoring invalid chain spec property {}: {}", key, value);
+	None
+}
+
+/// Returns the address format of `prefix`, which is either a known network or a custom one.
+fn ss58_address_format(prefix: u8) -> Ss58AddressFormat {
+	Ss58AddressFormat::try_from(prefix).unwrap_or(Ss58AddressFormat::Custom(prefix))
+}
+
+/// Parse an SS58 prefix given either as a number or as the name of a known network.
+pub(crate) fn parse_ss58_prefix(prefix: &str) -> std::result::Result<u8, String> {
+	Ss58AddressFormat::try_from(prefix)
+		.map(u8::from)
+		.map_err(|()| format!(
+			"Invalid SS58 prefix {:?}: expected a number or a network name", prefix,
+		))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn reads_and_validates_properties() {
+		let properties = serde_json::json!({
+			"ss58Format": 2,
+			"tokenSymbol": "KSM",
+			"tokenDecimals": 12,
+		});
+		let properties = ChainProperties::from_properties(properties.as_object().unwrap()).unwrap();
+		assert_eq!(properties, ChainProperties {
+			ss58_format: Some(2),
+			token_symbol: Some("KSM".into()),
+			token_decimals: Some(12),
+		});
+		assert_eq!(properties.to_string(), "KSM (12 decimals), SS58 prefix 2");
+
+		let properties = serde_json::json!({ "ss58Format": 256 });
+		assert!(ChainProperties::from_properties(properties.as_object().unwrap()).is_err());
+	}
+
+	#[test]
+	fn ignores_malformed_token_properties() {
+		let properties = serde_json::json!({
+			"ss58Format": 2,
+			"tokenSymbol": 42,
+			"tokenDecimals": "12",
+		});
+		let properties = ChainProperties::from_properties(properties.as_object().unwrap()).unwrap();
+		assert_eq!(properties, ChainProperties {
+			ss58_format: Some(2),
+			token_symbol: None,
+			token_decimals: None,
+		});
+		assert_eq!(properties.to_string(), "unspecified token, SS58 prefix 2");
+	}
+
+	#[test]
+	fn parses_ss58_prefixes() {
+		assert_eq!(parse_ss58_prefix("kusama"), Ok(2));
+		assert_eq!(parse_ss58_prefix("200"), Ok(200));
+		assert!(parse_ss58_prefix("unknown").is_err());
+	}
+}
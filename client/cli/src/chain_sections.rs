@@ -0,0 +1,98 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2020 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Command line sections configuring the extra chains run in the same process as the node.
+
+use crate::error::{Error, Result};
+use crate::RunCmd;
+use std::collections::HashSet;
+use structopt::StructOpt;
+
+/// Separator of the sections on the command line.
+const SECTION_SEPARATOR: &str = "--";
+
+/// The configuration of an extra chain, from its section of the command line.
+#[derive(Debug, Clone)]
+pub struct ChainSection {
+	/// Name of the section, identifying the chain in the logs.
+	pub name: String,
+	/// The `run` arguments of the chain.
+	pub run: RunCmd,
+}
+
+/// Parse the sections of `args`, the arguments following `--` on the command line.
+///
+/// Sections are separated by `--`. Each one starts with the name of the chain, followed by the
+/// `run` arguments of its node, e.g. `-- bridge --chain bridge.json --light --port 30334`.
+/// The ports of every chain must differ, and so must the base paths of chains with the same id.
+pub fn parse_chain_sections(args: &[String]) -> Result<Vec<ChainSection>> {
+	let mut names = HashSet::new();
+
+	args.split(|arg| arg == SECTION_SEPARATOR)
+		.filter(|section| !section.is_empty())
+		.map(|section| {
+			let name = &section[0];
+			if name.starts_with('-') {
+				return Err(Error::Input(format!(
+					"Expected the name of a chain before {:?}", name,
+				)));
+			}
+			if !names.insert(name.clone()) {
+				return Err(Error::Input(format!("Duplicate chain section {:?}", name)));
+			}
+
+			let run = RunCmd::from_iter_safe(section)
+				.map_err(|e| Error::Input(format!("Chain section {:?}: {}", name, e.message)))?;
+			Ok(ChainSection { name: name.clone(), run })
+		})
+		.collect()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn args(args: &[&str]) -> Vec<String> {
+		args.iter().map(|arg| arg.to_string()).collect()
+	}
+
+	#[test]
+	fn parses_sections() {
+		let sections = parse_chain_sections(&args(&[
+			"bridge", "--chain", "bridge.json", "--light", "--port", "30334",
+			"--",
+			"other", "--dev",
+		])).unwrap();
+
+		assert_eq!(sections.len(), 2);
+		assert_eq!(sections[0].name, "bridge");
+		assert_eq!(sections[0].run.shared_params.chain, Some("bridge.json".into()));
+		assert!(sections[0].run.light);
+		assert_eq!(sections[1].name, "other");
+		assert!(sections[1].run.shared_params.dev);
+
+		assert!(parse_chain_sections(&[]).unwrap().is_empty());
+	}
+
+	#[test]
+	fn rejects_invalid_sections() {
+		assert!(parse_chain_sections(&args(&["--dev"])).is_err());
+		assert!(parse_chain_sections(&args(&["bridge", "--", "bridge"])).is_err());
+		assert!(parse_chain_sections(&args(&["bridge", "--unknown-flag"])).is_err());
+	}
+}
@@ -0,0 +1,138 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2018-2020 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::error;
+use crate::params::{BlockNumber, DatabaseParams, PruningParams, SharedParams};
+use crate::CliConfiguration;
+use sc_service::{chain_ops::BlockBenchmark, Configuration, ServiceBuilderCommand};
+use sp_runtime::traits::{Block as BlockT, Header as HeaderT};
+use std::fmt::Debug;
+use std::fs;
+use std::io::{self, Write};
+use std::path::PathBuf;
+use structopt::StructOpt;
+
+/// The `benchmark-block` command used to time the re-execution of known blocks.
+///
+/// Blocks are executed on top of the state of their parent without changing the database,
+/// so the impact of runtime or database changes can be compared on the same chain.
+#[derive(Debug, StructOpt, Clone)]
+pub struct BenchmarkBlockCmd {
+	/// Output file name or stdout if unspecified.
+	#[structopt(parse(from_os_str))]
+	pub output: Option<PathBuf>,
+
+	/// Specify starting block number.
+	///
+	/// Default is 1.
+	#[structopt(long = "from", value_name = "BLOCK")]
+	pub from: Option<BlockNumber>,
+
+	/// Specify last block number.
+	#[structopt(long = "to", value_name = "BLOCK")]
+	pub to: BlockNumber,
+
+	/// Output one JSON object per block rather than CSV.
+	#[structopt(long)]
+	pub json: bool,
+
+	#[allow(missing_docs)]
+	#[structopt(flatten)]
+	pub shared_params: SharedParams,
+
+	#[allow(missing_docs)]
+	#[structopt(flatten)]
+	pub pruning_params: PruningParams,
+
+	#[allow(missing_docs)]
+	#[structopt(flatten)]
+	pub database_params: DatabaseParams,
+}
+
+impl BenchmarkBlockCmd {
+	/// Run the benchmark-block command
+	pub fn run<B, BC, BB>(
+		&self,
+		config: Configuration,
+		builder: B,
+	) -> error::Result<()>
+	where
+		B: FnOnce(Configuration) -> Result<BC, sc_service::error::Error>,
+		BC: ServiceBuilderCommand<Block = BB> + Unpin,
+		BB: sp_runtime::traits::Block + Debug,
+		<<<BB as BlockT>::Header as HeaderT>::Number as std::str::FromStr>::Err: std::fmt::Debug,
+	{
+		let from = match &self.from {
+			Some(from) => from.parse()?,
+			None => 1u32.into(),
+		};
+		let to = self.to.parse()?;
+
+		let mut output: Box<dyn io::Write> = match &self.output {
+			Some(filename) => Box::new(fs::File::create(filename)?),
+			None => Box::new(io::stdout()),
+		};
+
+		let json = self.json;
+		if !json {
+			writeln!(
+				output,
+				"number,hash,extrinsics,execution_micros,storage_micros,proof_micros,proof_size",
+			)?;
+		}
+
+		builder(config)?.benchmark_blocks(from, to, &mut |block: BlockBenchmark| {
+			let line = if json {
+				serde_json::to_string(&block)
+					.map_err(|e| sc_service::Error::Other(format!("Error encoding result: {}", e)))?
+			} else {
+				format!(
+					"{},{},{},{},{},{},{}",
+					block.number,
+					block.hash,
+					block.extrinsics,
+					block.execution_micros,
+					block.storage_micros,
+					block.proof_micros,
+					block.proof_size,
+				)
+			};
+			writeln!(output, "{}", line).map_err(Into::into)
+		})?;
+
+		Ok(())
+	}
+}
+
+impl CliConfiguration for BenchmarkBlockCmd {
+	fn shared_params(&self) -> &SharedParams {
+		&self.shared_params
+	}
+
+	fn pruning_params(&self) -> Option<&PruningParams> {
+		Some(&self.pruning_params)
+	}
+
+	fn database_params(&self) -> Option<&DatabaseParams> {
+		Some(&self.database_params)
+	}
+
+	fn database_read_only(&self) -> error::Result<bool> {
+		self.database_params.database_read_only(true)
+	}
+}
@@ -0,0 +1,89 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2018-2020 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::error;
+use crate::params::{ImportParams, SharedParams};
+use crate::CliConfiguration;
+use log::info;
+use sc_service::Configuration;
+use std::{fs, io::Write, path::PathBuf};
+use structopt::StructOpt;
+
+/// The `build-genesis` command used to build a raw chain specification whose genesis storage
+/// is generated by the runtime wasm.
+#[derive(Debug, StructOpt, Clone)]
+pub struct BuildGenesisCmd {
+	/// Runtime wasm blob whose genesis builder generates the genesis storage.
+	///
+	/// The runtime has to implement the `GenesisBuilder` runtime api.
+	#[structopt(long = "runtime", value_name = "PATH", parse(from_os_str))]
+	pub runtime: PathBuf,
+
+	/// File containing the genesis config passed to the genesis builder of the runtime.
+	///
+	/// Its format is defined by the runtime. Without it, the default genesis of the runtime is
+	/// built.
+	#[structopt(long = "genesis-config", value_name = "PATH", parse(from_os_str))]
+	pub genesis_config: Option<PathBuf>,
+
+	#[allow(missing_docs)]
+	#[structopt(flatten)]
+	pub shared_params: SharedParams,
+
+	#[allow(missing_docs)]
+	#[structopt(flatten)]
+	pub import_params: ImportParams,
+}
+
+impl BuildGenesisCmd {
+	/// Run the build-genesis command
+	pub fn run(&self, config: Configuration) -> error::Result<()> {
+		info!("Building genesis storage from {}", self.runtime.display());
+		let code = fs::read(&self.runtime)?;
+		let genesis_config = match self.genesis_config {
+			Some(ref path) => fs::read(path)?,
+			None => Vec::new(),
+		};
+
+		let storage = sc_service::chain_ops::build_genesis_storage_from_wasm(
+			&code,
+			&genesis_config,
+			config.wasm_method,
+			config.default_heap_pages,
+		)?;
+
+		let mut spec = config.chain_spec;
+		spec.set_storage(storage);
+
+		let json = sc_service::chain_ops::build_spec(&*spec, true)?;
+		if std::io::stdout().write_all(json.as_bytes()).is_err() {
+			let _ = std::io::stderr().write_all(b"Error writing to stdout\n");
+		}
+		Ok(())
+	}
+}
+
+impl CliConfiguration for BuildGenesisCmd {
+	fn shared_params(&self) -> &SharedParams {
+		&self.shared_params
+	}
+
+	fn import_params(&self) -> Option<&ImportParams> {
+		Some(&self.import_params)
+	}
+}
@@ -23,6 +23,7 @@ use crate::CliConfiguration;
 use log::info;
 use sc_network::config::build_multiaddr;
 use sc_service::{config::MultiaddrWithPeerId, Configuration};
+use sp_runtime::traits::Block as BlockT;
 use structopt::StructOpt;
 use std::io::Write;
 
@@ -30,6 +31,9 @@ use std::io::Write;
 #[derive(Debug, StructOpt, Clone)]
 pub struct BuildSpecCmd {
 	/// Force raw genesis storage output.
+	///
+	/// The raw genesis storage is checked to produce the same genesis state root as the
+	/// original specification.
 	#[structopt(long = "raw")]
 	pub raw: bool,
 
@@ -51,7 +55,7 @@ pub struct BuildSpecCmd {
 
 impl BuildSpecCmd {
 	/// Run the build-spec command
-	pub fn run(&self, config: Configuration) -> error::Result<()> {
+	pub fn run<B: BlockT>(&self, config: Configuration) -> error::Result<()> {
 		info!("Building chain spec");
 		let mut spec = config.chain_spec;
 		let raw_output = self.raw;
@@ -66,7 +70,11 @@ impl BuildSpecCmd {
 			spec.add_boot_node(addr)
 		}
 
-		let json = sc_service::chain_ops::build_spec(&*spec, raw_output)?;
+		let json = if raw_output {
+			sc_service::chain_ops::build_raw_spec_checked::<B>(&*spec)?
+		} else {
+			sc_service::chain_ops::build_spec(&*spec, raw_output)?
+		};
 		if std::io::stdout().write_all(json.as_bytes()).is_err() {
 			let _ = std::io::stderr().write_all(b"Error writing to stdout\n");
 		}
@@ -0,0 +1,473 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2020 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::{error, params::SharedParams, SubstrateCli};
+use sc_network::{config::MultiaddrWithPeerId, multiaddr::Protocol};
+use sc_service::ChainSpec;
+use sc_telemetry::TelemetryEndpoints;
+use sp_core::{hashing::blake2_256, hexdisplay::HexDisplay, storage::Storage};
+use sp_runtime::BuildStorage;
+use serde_json::{json, Map, Value};
+use std::{collections::{BTreeMap, BTreeSet, HashMap}, path::Path};
+use structopt::StructOpt;
+
+/// The `chain-spec` command used to work with chain specifications.
+#[derive(Debug, StructOpt, Clone)]
+pub enum ChainSpecCmd {
+	/// Validate a chain specification.
+	Validate(ValidateChainSpecCmd),
+
+	/// Compare two chain specifications.
+	Diff(DiffChainSpecCmd),
+}
+
+impl ChainSpecCmd {
+	/// Run the `chain-spec` command
+	pub fn run<C: SubstrateCli>(&self, cli: &C) -> error::Result<()> {
+		match self {
+			ChainSpecCmd::Validate(cmd) => cmd.run(cli),
+			ChainSpecCmd::Diff(cmd) => cmd.run(cli),
+		}
+	}
+}
+
+/// The `chain-spec validate` command used to check a chain specification for errors.
+///
+/// Unlike starting a node with the chain specification, all problems are collected and
+/// reported at once.
+#[derive(Debug, StructOpt, Clone)]
+pub struct ValidateChainSpecCmd {
+	#[allow(missing_docs)]
+	#[structopt(flatten)]
+	pub shared_params: SharedParams,
+}
+
+impl ValidateChainSpecCmd {
+	/// Run the `chain-spec validate` command
+	pub fn run<C: SubstrateCli>(&self, cli: &C) -> error::Result<()> {
+		let chain_id = self.shared_params.chain_id(self.shared_params.is_dev());
+
+		// Check the raw JSON first, to be able to point at the offending fields even if the
+		// chain spec can't be loaded at all.
+		let mut errors = if Path::new(&chain_id).is_file() {
+			let json = std::fs::read(&chain_id)?;
+			match serde_json::from_slice(&json) {
+				Ok(json) => validate_json(&json),
+				Err(e) => vec![format!("Invalid JSON: {}", e)],
+			}
+		} else {
+			Vec::new()
+		};
+
+		match cli.load_spec(&chain_id) {
+			Ok(spec) => errors.extend(validate_spec(&*spec)),
+			Err(e) => errors.push(format!("Failed to load chain spec: {}", e)),
+		}
+
+		if errors.is_empty() {
+			println!("Chain spec `{}` is valid", chain_id);
+			Ok(())
+		} else {
+			Err(error::Error::Input(format!(
+				"Chain spec `{}` is invalid:\n  - {}", chain_id, errors.join("\n  - "),
+			)))
+		}
+	}
+}
+
+/// The `chain-spec diff` command used to compare two chain specifications.
+///
+/// The differences of the genesis storage, the boot nodes, the properties, the extensions and the
+/// other fields are printed as JSON. Non-raw specs are compared by the storage they build.
+#[derive(Debug, StructOpt, Clone)]
+pub struct DiffChainSpecCmd {
+	/// The first chain specification, either a path or the id of a built-in chain.
+	#[structopt(value_name = "FIRST")]
+	pub first: String,
+
+	/// The second chain specification, either a path or the id of a built-in chain.
+	#[structopt(value_name = "SECOND")]
+	pub second: String,
+}
+
+impl DiffChainSpecCmd {
+	/// Run the `chain-spec diff` command
+	pub fn run<C: SubstrateCli>(&self, cli: &C) -> error::Result<()> {
+		let load = |chain_id: &str| -> error::Result<(Value, Storage)> {
+			let spec = cli.load_spec(chain_id)
+				.map_err(|e| format!("Failed to load chain spec `{}`: {}", chain_id, e))?;
+			let json = serde_json::from_str(&spec.as_json(false)?)
+				.map_err(|e| format!("Failed to read chain spec `{}`: {}", chain_id, e))?;
+			let storage = spec.build_storage().map_err(|e| format!(
+				"Failed to build the genesis storage of `{}`: {}", chain_id, e,
+			))?;
+			Ok((json, storage))
+		};
+		let (first_json, first_storage) = load(&self.first)?;
+		let (second_json, second_storage) = load(&self.second)?;
+
+		let diff = ChainSpecDiff::new(&first_json, &first_storage, &second_json, &second_storage);
+		if diff.is_empty() {
+			println!("Chain specs `{}` and `{}` are equivalent", self.first, self.second);
+		} else {
+			let report = serde_json::to_string_pretty(&diff.to_json()).map_err(|e| e.to_string())?;
+			println!("{}", report);
+		}
+
+		Ok(())
+	}
+}
+
+/// Top level fields of a chain spec JSON, apart from the ones compared separately. The other
+/// fields belong to the extensions.
+const CHAIN_SPEC_FIELDS: &[&str] = &[
+	"name",
+	"id",
+	"chainType",
+	"telemetryEndpoints",
+	"protocolId",
+	"consensusEngine",
+	"codeSubstitutes",
+	"lightSyncState",
+];
+
+/// A value that differs between two chain specs, `None` if a spec doesn't define it.
+#[derive(Debug, PartialEq)]
+struct Change<V> {
+	first: Option<V>,
+	second: Option<V>,
+}
+
+/// Differences between two chain specs.
+#[derive(Debug, Default)]
+struct ChainSpecDiff {
+	/// Changed top level fields, by name.
+	fields: BTreeMap<String, Change<Value>>,
+	/// Boot nodes only listed in the first spec.
+	removed_boot_nodes: Vec<String>,
+	/// Boot nodes only listed in the second spec.
+	added_boot_nodes: Vec<String>,
+	/// Changed properties, by name.
+	properties: BTreeMap<String, Change<Value>>,
+	/// Changed extensions, by name.
+	extensions: BTreeMap<String, Change<Value>>,
+	/// Changed genesis storage entries, by hex encoded key, prefixed with the key of their child
+	/// trie if any.
+	genesis_storage: BTreeMap<String, Change<Vec<u8>>>,
+}
+
+impl ChainSpecDiff {
+	fn new(
+		first_json: &Value,
+		first_storage: &Storage,
+		second_json: &Value,
+		second_storage: &Storage,
+	) -> Self {
+		let (first_fields, first_extensions) = split_fields(first_json);
+		let (second_fields, second_extensions) = split_fields(second_json);
+		let first_boot_nodes = boot_nodes(first_json);
+		let second_boot_nodes = boot_nodes(second_json);
+
+		ChainSpecDiff {
+			fields: diff_maps(&first_fields, &second_fields),
+			removed_boot_nodes: first_boot_nodes.difference(&second_boot_nodes).cloned().collect(),
+			added_boot_nodes: second_boot_nodes.difference(&first_boot_nodes).cloned().collect(),
+			properties: diff_maps(&properties(first_json), &properties(second_json)),
+			extensions: diff_maps(&first_extensions, &second_extensions),
+			genesis_storage: diff_maps(
+				&flatten_storage(first_storage),
+				&flatten_storage(second_storage),
+			),
+		}
+	}
+
+	fn is_empty(&self) -> bool {
+		self.fields.is_empty() &&
+			self.removed_boot_nodes.is_empty() &&
+			self.added_boot_nodes.is_empty() &&
+			self.properties.is_empty() &&
+			self.extensions.is_empty() &&
+			self.genesis_storage.is_empty()
+	}
+
+	fn to_json(&self) -> Value {
+		let changes = |changes: &BTreeMap<String, Change<Value>>| changes.iter()
+			.map(|(key, change)| (key.clone(), json!({
+				"first": change.first,
+				"second": change.second,
+			})))
+			.collect::<Map<_, _>>();
+		let storage = self.genesis_storage.iter()
+			.map(|(key, change)| (key.clone(), json!({
+				"first": change.first.as_ref().map(|v| storage_value(v)),
+				"second": change.second.as_ref().map(|v| storage_value(v)),
+			})))
+			.collect::<Map<_, _>>();
+
+		json!({
+			"fields": changes(&self.fields),
+			"bootNodes": { "removed": self.removed_boot_nodes, "added": self.added_boot_nodes },
+			"properties": changes(&self.properties),
+			"extensions": changes(&self.extensions),
+			"genesisStorage": storage,
+		})
+	}
+}
+
+/// Split the top level fields of a chain spec JSON into the known fields and the extensions.
+fn split_fields(json: &Value) -> (BTreeMap<String, Value>, BTreeMap<String, Value>) {
+	let mut fields = BTreeMap::new();
+	let mut extensions = BTreeMap::new();
+	for (key, value) in json.as_object().into_iter().flatten() {
+		match key.as_str() {
+			"bootNodes" | "properties" | "genesis" => {},
+			key if CHAIN_SPEC_FIELDS.contains(&key) => {
+				fields.insert(key.into(), value.clone());
+			},
+			key => {
+				extensions.insert(key.into(), value.clone());
+			},
+		}
+	}
+	(fields, extensions)
+}
+
+fn boot_nodes(json: &Value) -> BTreeSet<String> {
+	json.get("bootNodes")
+		.and_then(Value::as_array)
+		.into_iter()
+		.flatten()
+		.map(|boot_node| boot_node.as_str().map_or_else(|| boot_node.to_string(), Into::into))
+		.collect()
+}
+
+fn properties(json: &Value) -> BTreeMap<String, Value> {
+	json.get("properties")
+		.and_then(Value::as_object)
+		.into_iter()
+		.flatten()
+		.map(|(key, value)| (key.clone(), value.clone()))
+		.collect()
+}
+
+/// Returns the entries of the top trie and of the child tries of `storage` in a single map.
+fn flatten_storage(storage: &Storage) -> BTreeMap<String, Vec<u8>> {
+	let top = storage.top.iter()
+		.map(|(key, value)| (format!("0x{}", HexDisplay::from(key)), value.clone()));
+	let children = storage.children_default.iter().flat_map(|(child_key, child)| {
+		child.data.iter().map(move |(key, value)| (
+			format!("0x{}/0x{}", HexDisplay::from(child_key), HexDisplay::from(key)),
+			value.clone(),
+		))
+	});
+	top.chain(children).collect()
+}
+
+/// Returns the entries of two maps that differ.
+fn diff_maps<V: PartialEq + Clone>(
+	first: &BTreeMap<String, V>,
+	second: &BTreeMap<String, V>,
+) -> BTreeMap<String, Change<V>> {
+	first.keys().chain(second.keys())
+		.filter(|key| first.get(*key) != second.get(*key))
+		.map(|key| (key.clone(), Change {
+			first: first.get(key).cloned(),
+			second: second.get(key).cloned(),
+		}))
+		.collect()
+}
+
+/// Maximum size of the storage values printed in full. Larger ones, e.g. the runtime code, are
+/// printed as their size and hash.
+const MAX_PRINTED_VALUE_SIZE: usize = 64;
+
+fn storage_value(value: &[u8]) -> String {
+	if value.len() <= MAX_PRINTED_VALUE_SIZE {
+		format!("0x{}", HexDisplay::from(&value))
+	} else {
+		format!("{} bytes, blake2-256 0x{}", value.len(), HexDisplay::from(&blake2_256(value)))
+	}
+}
+
+/// Validate the fields of a chain spec JSON that have to be well-formed for the spec to load.
+fn validate_json(json: &Value) -> Vec<String> {
+	let mut errors = Vec::new();
+
+	match json.get("bootNodes") {
+		Some(Value::Array(boot_nodes)) => {
+			for (i, boot_node) in boot_nodes.iter().enumerate() {
+				match boot_node.as_str().map(str::parse::<MultiaddrWithPeerId>) {
+					Some(Ok(_)) => {},
+					Some(Err(e)) => errors.push(format!("bootNodes[{}]: {} ({})", i, e, boot_node)),
+					None => errors.push(format!("bootNodes[{}]: expected a string, got {}", i, boot_node)),
+				}
+			}
+		},
+		Some(other) => errors.push(format!("bootNodes: expected an array, got {}", other)),
+		None => errors.push("bootNodes: missing field".into()),
+	}
+
+	match json.get("telemetryEndpoints") {
+		Some(Value::Array(endpoints)) => {
+			for (i, endpoint) in endpoints.iter().enumerate() {
+				let (url, verbosity) = match endpoint.as_array().map(Vec::as_slice) {
+					Some([Value::String(url), Value::Number(verbosity)]) => (url, verbosity),
+					_ => {
+						errors.push(format!(
+							"telemetryEndpoints[{}]: expected a `[url, verbosity]` pair, got {}", i, endpoint,
+						));
+						continue
+					},
+				};
+				if verbosity.as_u64().map_or(true, |v| v > u8::max_value() as u64) {
+					errors.push(format!("telemetryEndpoints[{}]: invalid verbosity {}", i, verbosity));
+				}
+				if let Err(e) = TelemetryEndpoints::new(vec![(url.clone(), 0)]) {
+					errors.push(format!("telemetryEndpoints[{}]: invalid URL `{}`: {}", i, url, e));
+				}
+			}
+		},
+		Some(Value::Null) | None => {},
+		Some(other) => errors.push(format!("telemetryEndpoints: expected an array, got {}", other)),
+	}
+
+	match json.get("properties") {
+		Some(Value::Object(_)) | Some(Value::Null) | None => {},
+		Some(other) => errors.push(format!("properties: expected an object, got {}", other)),
+	}
+
+	errors
+}
+
+/// Validate a loaded chain spec.
+fn validate_spec(spec: &dyn ChainSpec) -> Vec<String> {
+	let mut errors = Vec::new();
+
+	let mut peer_ids = HashMap::new();
+	for boot_node in spec.boot_nodes() {
+		if boot_node.multiaddr.iter().any(|p| matches!(p, Protocol::P2p(_))) {
+			errors.push(format!("Boot node `{}` contains more than one peer id", boot_node));
+		}
+		if let Some(other) = peer_ids.insert(boot_node.multiaddr.clone(), boot_node.peer_id.clone()) {
+			if other != boot_node.peer_id {
+				errors.push(format!(
+					"Boot node address {} is listed with different peer ids {} and {}",
+					boot_node.multiaddr, other, boot_node.peer_id,
+				));
+			}
+		}
+	}
+
+	let properties = spec.properties();
+	for key in &["tokenDecimals", "ss58Format"] {
+		match properties.get(*key) {
+			Some(value) if value.as_u64().is_none() => errors.push(format!(
+				"properties.{}: expected an unsigned integer, got {}", key, value,
+			)),
+			_ => {},
+		}
+	}
+	match properties.get("tokenSymbol") {
+		Some(value) if !value.is_string() => errors.push(format!(
+			"properties.tokenSymbol: expected a string, got {}", value,
+		)),
+		_ => {},
+	}
+
+	if let Err(e) = spec.build_storage() {
+		errors.push(format!("Failed to build genesis storage: {}", e));
+	}
+
+	errors
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn reports_invalid_json_fields() {
+		let json = serde_json::json!({
+			"bootNodes": [
+				"/ip4/127.0.0.1/tcp/30333/p2p/QmSk5HQbn6LhUwDiNMseVUjuRYhEtYj4aUZ6WfWoGURpdV",
+				"/ip4/127.0.0.1/tcp/30333",
+				42,
+			],
+			"telemetryEndpoints": [["wss://telemetry.polkadot.io/submit/", 0], ["not a url", 0], "foo"],
+			"properties": [],
+		});
+
+		let errors = validate_json(&json);
+		assert_eq!(errors.len(), 5, "{:?}", errors);
+		assert!(errors[0].starts_with("bootNodes[1]"));
+		assert!(errors[1].starts_with("bootNodes[2]"));
+		assert!(errors[2].starts_with("telemetryEndpoints[1]"));
+		assert!(errors[3].starts_with("telemetryEndpoints[2]"));
+		assert!(errors[4].starts_with("properties"));
+	}
+
+	#[test]
+	fn accepts_valid_json() {
+		let json = serde_json::json!({
+			"bootNodes": ["/ip4/127.0.0.1/tcp/30333/p2p/QmSk5HQbn6LhUwDiNMseVUjuRYhEtYj4aUZ6WfWoGURpdV"],
+			"telemetryEndpoints": [["wss://telemetry.polkadot.io/submit/", 0]],
+			"properties": { "tokenDecimals": 15 },
+		});
+
+		assert!(validate_json(&json).is_empty());
+	}
+
+	#[test]
+	fn reports_chain_spec_differences() {
+		let first = serde_json::json!({
+			"name": "Local",
+			"bootNodes": ["/ip4/127.0.0.1/tcp/30333/p2p/QmSk5HQbn6LhUwDiNMseVUjuRYhEtYj4aUZ6WfWoGURpdV"],
+			"properties": { "tokenSymbol": "FIR", "tokenDecimals": 15 },
+			"forkBlocks": null,
+			"genesis": { "raw": {} },
+		});
+		let second = serde_json::json!({
+			"name": "Local",
+			"bootNodes": ["/ip4/127.0.0.2/tcp/30333/p2p/QmSk5HQbn6LhUwDiNMseVUjuRYhEtYj4aUZ6WfWoGURpdV"],
+			"properties": { "tokenSymbol": "FIR", "tokenDecimals": 12 },
+			"forkBlocks": [],
+			"genesis": { "raw": { "top": {} } },
+		});
+		let mut first_storage = Storage::default();
+		first_storage.top.insert(b"same".to_vec(), vec![1]);
+		first_storage.top.insert(b"changed".to_vec(), vec![1]);
+		first_storage.top.insert(b"removed".to_vec(), vec![1]);
+		let mut second_storage = first_storage.clone();
+		second_storage.top.insert(b"changed".to_vec(), vec![2]);
+		second_storage.top.remove(&b"removed".to_vec());
+
+		let diff = ChainSpecDiff::new(&first, &first_storage, &second, &second_storage);
+		assert!(diff.fields.is_empty());
+		assert_eq!(diff.removed_boot_nodes.len(), 1);
+		assert_eq!(diff.added_boot_nodes.len(), 1);
+		assert_eq!(diff.properties.keys().collect::<Vec<_>>(), vec!["tokenDecimals"]);
+		assert_eq!(diff.extensions.keys().collect::<Vec<_>>(), vec!["forkBlocks"]);
+		assert_eq!(diff.genesis_storage.len(), 2);
+		assert_eq!(
+			diff.genesis_storage[&format!("0x{}", HexDisplay::from(&b"removed".to_vec()))],
+			Change { first: Some(vec![1]), second: None },
+		);
+
+		assert!(ChainSpecDiff::new(&first, &first_storage, &first, &first_storage).is_empty());
+	}
+}
@@ -0,0 +1,264 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2020 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::{error, SubstrateCli};
+use serde_json::{json, Map, Value};
+use structopt::StructOpt;
+
+/// The `config` command used to work with the configuration of the node.
+#[derive(Debug, StructOpt, Clone)]
+pub enum ConfigCmd {
+	/// Print a JSON Schema describing all the options of the node.
+	///
+	/// The options are the properties of the schema, named after their long command line flag
+	/// without the leading `--`. Flags are booleans, options taking a value are typed after the
+	/// values their parser accepts, and options that can be repeated are arrays.
+	Schema,
+}
+
+impl ConfigCmd {
+	/// Run the `config` command
+	pub fn run<C: SubstrateCli + StructOpt>(&self, _cli: &C) -> error::Result<()> {
+		match self {
+			ConfigCmd::Schema => {
+				let schema = options_schema::<C>(&format!("{} node options", C::impl_name()))?;
+				println!("{}", serde_json::to_string_pretty(&schema).map_err(|e| e.to_string())?);
+				Ok(())
+			},
+		}
+	}
+}
+
+/// An option of the command line, as described by the help.
+#[derive(Debug, Default)]
+struct OptionHelp {
+	/// Long flag, without the leading `--`.
+	long: String,
+	/// Whether the option takes a value.
+	takes_value: bool,
+	/// Whether the option can be repeated.
+	multiple: bool,
+	/// Description of the option, without the default and possible values.
+	description: Vec<String>,
+	default: Option<String>,
+	possible_values: Option<Vec<String>>,
+}
+
+/// Returns a JSON Schema titled `title` describing the options of the command line `T`.
+fn options_schema<T: StructOpt>(title: &str) -> error::Result<Value> {
+	let mut help = Vec::new();
+	T::clap().write_long_help(&mut help).map_err(|e| e.to_string())?;
+	let help = String::from_utf8_lossy(&help);
+
+	let mut properties = Map::new();
+	for option in parse_options(&help) {
+		if option.long == "help" || option.long == "version" {
+			continue;
+		}
+
+		let mut property = if option.takes_value {
+			let value_type = match option.possible_values {
+				Some(_) => "string",
+				None => value_type::<T>(&option.long),
+			};
+			let mut value = json!({ "type": value_type });
+			if let Some(ref possible_values) = option.possible_values {
+				value["enum"] = json!(possible_values);
+			}
+
+			let mut property = if option.multiple {
+				json!({ "type": "array", "items": value })
+			} else {
+				value
+			};
+			if let Some(ref default) = option.default {
+				property["default"] = match value_type {
+					"integer" | "number" => default.parse().map(Value::Number)
+						.unwrap_or_else(|_| json!(default)),
+					_ => json!(default),
+				};
+			}
+			property
+		} else {
+			json!({ "type": "boolean", "default": false })
+		};
+		if !option.description.is_empty() {
+			property["description"] = json!(option.description.join("\n").trim());
+		}
+		properties.insert(option.long.clone(), property);
+	}
+
+	Ok(json!({
+		"$schema": "http://json-schema.org/draft-07/schema#",
+		"title": title,
+		"type": "object",
+		"properties": properties,
+		"additionalProperties": false,
+	}))
+}
+
+/// Returns the JSON type of the values of the option `--long` of `T`, found by checking which
+/// values the command line accepts for it.
+fn value_type<T: StructOpt>(long: &str) -> &'static str {
+	let accepts = |value: &str| {
+		T::from_iter_safe(&["", &format!("--{}", long), value]).is_ok()
+	};
+	if accepts("not a number") {
+		"string"
+	} else if accepts("1") && !accepts("0.5") {
+		"integer"
+	} else if accepts("0.5") {
+		"number"
+	} else {
+		"string"
+	}
+}
+
+/// Returns the options described in the `FLAGS` and `OPTIONS` sections of the long `help`.
+fn parse_options(help: &str) -> Vec<OptionHelp> {
+	let mut options = Vec::<OptionHelp>::new();
+	let mut in_options = false;
+
+	for line in help.lines() {
+		if !line.starts_with(' ') {
+			in_options = line == "FLAGS:" || line == "OPTIONS:";
+			continue;
+		}
+		if !in_options {
+			continue;
+		}
+
+		let trimmed = line.trim_start();
+		let indent = line.len() - trimmed.len();
+		// the options are indented by at most 8 spaces, and their description by more
+		if indent <= 8 && trimmed.starts_with('-') {
+			let (spec, description) = match trimmed.find("  ") {
+				Some(i) => (&trimmed[..i], Some(trimmed[i..].trim())),
+				None => (trimmed, None),
+			};
+			let long = match spec.split(|c| c == ' ' || c == ',')
+				.find(|word| word.starts_with("--"))
+			{
+				Some(long) => &long[2..],
+				None => continue,
+			};
+			options.push(OptionHelp {
+				long: long.into(),
+				takes_value: spec.contains('<'),
+				multiple: spec.ends_with("..."),
+				description: description.into_iter().map(Into::into).collect(),
+				..Default::default()
+			});
+		} else if let Some(option) = options.last_mut() {
+			option.description.push(trimmed.into());
+		}
+	}
+
+	for option in &mut options {
+		let description = option.description.join("\n");
+		option.default = bracketed(&description, "[default: ").map(Into::into);
+		option.possible_values = bracketed(&description, "[possible values: ")
+			.map(|values| values.split(", ").map(Into::into).collect());
+		option.description = description
+			.split('\n')
+			.map(|line| match line.find("[default: ").or_else(|| line.find("[possible values: ")) {
+				Some(i) => line[..i].trim_end().to_string(),
+				None => line.to_string(),
+			})
+			.collect();
+	}
+	options
+}
+
+/// Returns the text between `start` and the next `]` in `text`.
+fn bracketed<'a>(text: &'a str, start: &str) -> Option<&'a str> {
+	let from = text.find(start)? + start.len();
+	let to = text[from..].find(']')? + from;
+	Some(&text[from..to])
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::RunCmd;
+
+	#[derive(Debug, StructOpt)]
+	struct TestCli {
+		/// Whether to run.
+		#[structopt(long)]
+		run: bool,
+
+		/// Number of peers.
+		#[structopt(long, default_value = "25")]
+		peers: u32,
+
+		/// Ratio of the peers.
+		#[structopt(long)]
+		ratio: Option<f64>,
+
+		/// Name of the node.
+		#[structopt(long)]
+		name: Option<String>,
+
+		/// Reserved nodes.
+		#[structopt(long)]
+		reserved: Vec<String>,
+
+		/// Mode of the node.
+		#[structopt(long, possible_values = &["Fast", "Slow"], default_value = "Fast")]
+		mode: String,
+	}
+
+	#[test]
+	fn types_the_options_after_their_values() {
+		let schema = options_schema::<TestCli>("Test").unwrap();
+		let properties = &schema["properties"];
+
+		assert_eq!(properties["run"], json!({
+			"type": "boolean",
+			"default": false,
+			"description": "Whether to run.",
+		}));
+		assert_eq!(properties["peers"], json!({
+			"type": "integer",
+			"default": 25,
+			"description": "Number of peers.",
+		}));
+		assert_eq!(properties["ratio"]["type"], "number");
+		assert_eq!(properties["name"]["type"], "string");
+		assert_eq!(properties["reserved"]["type"], "array");
+		assert_eq!(properties["reserved"]["items"]["type"], "string");
+		assert_eq!(properties["mode"]["default"], "Fast");
+		assert_eq!(properties["mode"]["enum"], json!(["Fast", "Slow"]));
+		assert!(properties.get("help").is_none());
+	}
+
+	#[test]
+	fn describes_the_options_of_the_run_command() {
+		let schema = options_schema::<RunCmd>("Substrate").unwrap();
+		let properties = &schema["properties"];
+
+		assert_eq!(properties["validator"]["type"], "boolean");
+		assert_eq!(properties["base-path"]["type"], "string");
+		assert!(properties["base-path"]["description"].as_str().unwrap().contains("base path"));
+		assert_eq!(properties["port"]["type"], "integer");
+		assert_eq!(properties["bootnodes"]["type"], "array");
+		assert_eq!(properties["rpc-methods"]["default"], "Auto");
+		assert!(properties["rpc-methods"]["enum"].as_array().unwrap().contains(&json!("Unsafe")));
+	}
+}
@@ -0,0 +1,151 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2020 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::{CliConfiguration, error, params::{DatabaseParams, PruningParams, SharedParams}};
+use sc_client_db::DatabaseType;
+use sc_service::{Configuration, DatabaseConfig, Role, ServiceBuilderCommand};
+use sp_runtime::traits::Block as BlockT;
+use std::fmt::Debug;
+use structopt::StructOpt;
+
+/// The `db` command used to maintain the node database.
+///
+/// The node must not be running while the database is maintained.
+#[derive(Debug, StructOpt, Clone)]
+pub struct DbCmd {
+	#[allow(missing_docs)]
+	#[structopt(subcommand)]
+	pub action: DbAction,
+
+	#[allow(missing_docs)]
+	#[structopt(flatten)]
+	pub shared_params: SharedParams,
+
+	#[allow(missing_docs)]
+	#[structopt(flatten)]
+	pub pruning_params: PruningParams,
+
+	#[allow(missing_docs)]
+	#[structopt(flatten)]
+	pub database_params: DatabaseParams,
+}
+
+/// Operations of the `db` command.
+#[derive(Debug, StructOpt, Clone)]
+pub enum DbAction {
+	/// Upgrade the database to the version used by this node.
+	///
+	/// The node upgrades the database on startup as well, this allows doing it ahead of time.
+	Migrate {
+		/// Only print the migrations that would be applied.
+		#[structopt(long = "dry-run")]
+		dry_run: bool,
+	},
+	/// Check the database for missing or inconsistent entries.
+	///
+	/// The headers, bodies and justifications of all canonical blocks and the state of the
+	/// best block are checked.
+	Check {
+		/// Revert the chain to the last consistent block if any issues are found.
+		///
+		/// Finalized blocks are never reverted.
+		#[structopt(long = "repair")]
+		repair: bool,
+	},
+}
+
+impl DbCmd {
+	/// Run the `db` command
+	pub fn run<B, BC, BB>(
+		&self,
+		config: Configuration,
+		builder: B,
+	) -> error::Result<()>
+	where
+		B: FnOnce(Configuration) -> Result<BC, sc_service::error::Error>,
+		BC: ServiceBuilderCommand<Block = BB> + Unpin,
+		BB: BlockT + Debug,
+	{
+		match self.action {
+			DbAction::Migrate { dry_run } => self.migrate::<BB>(&config, dry_run),
+			DbAction::Check { repair } => {
+				let check = builder(config)?.check_database(repair)?;
+
+				for issue in &check.issues {
+					println!("{}", issue);
+				}
+				println!("Checked {} blocks, found {} issues.", check.checked_blocks, check.issues.len());
+				if check.reverted > 0 {
+					println!("Reverted {} blocks.", check.reverted);
+				}
+
+				Ok(())
+			},
+		}
+	}
+
+	fn migrate<B: BlockT>(&self, config: &Configuration, dry_run: bool) -> error::Result<()> {
+		let db_path = match config.database {
+			DatabaseConfig::RocksDb { ref path, .. } => path,
+			_ => return Err(error::Error::Input("Only RocksDb databases are versioned".into())),
+		};
+		let db_type = match config.role {
+			Role::Light => DatabaseType::Light,
+			_ => DatabaseType::Full,
+		};
+
+		let pending = sc_client_db::pending_migrations(db_path)?;
+		if pending.is_empty() {
+			println!("Database at {:?} is up to date.", db_path);
+			return Ok(());
+		}
+
+		for migration in &pending {
+			println!("{} -> {}: {}", migration.from, migration.to, migration.description);
+		}
+
+		if !dry_run {
+			sc_client_db::upgrade_db::<B>(db_path, db_type)?;
+			println!("Database at {:?} migrated.", db_path);
+		}
+
+		Ok(())
+	}
+}
+
+impl CliConfiguration for DbCmd {
+	fn shared_params(&self) -> &SharedParams {
+		&self.shared_params
+	}
+
+	fn pruning_params(&self) -> Option<&PruningParams> {
+		Some(&self.pruning_params)
+	}
+
+	fn database_params(&self) -> Option<&DatabaseParams> {
+		Some(&self.database_params)
+	}
+
+	fn database_read_only(&self) -> error::Result<bool> {
+		let inspecting = match self.action {
+			DbAction::Migrate { dry_run } => dry_run,
+			DbAction::Check { repair } => !repair,
+		};
+		self.database_params.database_read_only(inspecting)
+	}
+}
@@ -0,0 +1,122 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2020 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::error;
+use crate::params::{BlockNumberOrHash, DatabaseParams, PruningParams, SharedParams};
+use crate::CliConfiguration;
+use log::{info, warn};
+use sc_service::{chain_ops::BlockExecution, Configuration, ServiceBuilderCommand};
+use sp_runtime::traits::{Block as BlockT, NumberFor};
+use std::{fmt::Debug, fs, str::FromStr};
+use structopt::StructOpt;
+
+/// The `diff-block` command used to execute a known block in two different ways, e.g. with the
+/// native and the wasm runtime, and compare the storage changes and events of the executions.
+///
+/// The block is executed on top of the state of its parent without changing the database.
+#[derive(Debug, StructOpt, Clone)]
+pub struct DiffBlockCmd {
+	/// Block hash or number to execute.
+	#[structopt(value_name = "HASH or NUMBER")]
+	pub input: BlockNumberOrHash,
+
+	/// First way to execute the block: `native`, `wasm` for the on-chain runtime or the path of
+	/// a runtime wasm blob.
+	#[structopt(long = "first", value_name = "EXECUTION", default_value = "native")]
+	pub first: String,
+
+	/// Second way to execute the block: `native`, `wasm` for the on-chain runtime or the path of
+	/// a runtime wasm blob.
+	#[structopt(long = "second", value_name = "EXECUTION", default_value = "wasm")]
+	pub second: String,
+
+	#[allow(missing_docs)]
+	#[structopt(flatten)]
+	pub shared_params: SharedParams,
+
+	#[allow(missing_docs)]
+	#[structopt(flatten)]
+	pub pruning_params: PruningParams,
+
+	#[allow(missing_docs)]
+	#[structopt(flatten)]
+	pub database_params: DatabaseParams,
+}
+
+impl DiffBlockCmd {
+	/// Run the diff-block command
+	pub fn run<B, BC, BB>(
+		&self,
+		config: Configuration,
+		builder: B,
+	) -> error::Result<()>
+	where
+		B: FnOnce(Configuration) -> Result<BC, sc_service::error::Error>,
+		BC: ServiceBuilderCommand<Block = BB> + Unpin,
+		BB: BlockT + Debug,
+		<NumberFor<BB> as FromStr>::Err: std::fmt::Debug,
+		BB::Hash: FromStr,
+		<BB::Hash as FromStr>::Err: std::fmt::Debug,
+	{
+		let block = self.input.parse()?;
+		let first = parse_execution(&self.first)?;
+		let second = parse_execution(&self.second)?;
+
+		let diff = builder(config)?.compare_block_execution(block, &first, &second)?;
+		if diff.is_empty() {
+			info!("✅ Both executions of block #{} made the same changes", diff.number);
+		} else {
+			warn!(
+				"⚠️  The executions of block #{} differ: {} storage entries{}",
+				diff.number,
+				diff.storage.len(),
+				if diff.events.is_some() { " and the events" } else { "" },
+			);
+		}
+		println!("{}", serde_json::to_string_pretty(&diff).map_err(|e| e.to_string())?);
+
+		Ok(())
+	}
+}
+
+/// Parse a way to execute a block, reading the runtime wasm blob if it's a path.
+fn parse_execution(execution: &str) -> error::Result<BlockExecution> {
+	Ok(match execution {
+		"native" => BlockExecution::Native,
+		"wasm" => BlockExecution::Wasm,
+		path => BlockExecution::CustomWasm(fs::read(path)?),
+	})
+}
+
+impl CliConfiguration for DiffBlockCmd {
+	fn shared_params(&self) -> &SharedParams {
+		&self.shared_params
+	}
+
+	fn pruning_params(&self) -> Option<&PruningParams> {
+		Some(&self.pruning_params)
+	}
+
+	fn database_params(&self) -> Option<&DatabaseParams> {
+		Some(&self.database_params)
+	}
+
+	fn database_read_only(&self) -> error::Result<bool> {
+		self.database_params.database_read_only(true)
+	}
+}
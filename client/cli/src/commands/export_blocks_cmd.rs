@@ -113,4 +113,8 @@ impl CliConfiguration for ExportBlocksCmd {
 	fn database_params(&self) -> Option<&DatabaseParams> {
 		Some(&self.database_params)
 	}
+
+	fn database_read_only(&self) -> error::Result<bool> {
+		self.database_params.database_read_only(true)
+	}
 }
@@ -0,0 +1,125 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2020 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::{
+	CliConfiguration, error, MetadataFormat,
+	params::{BlockNumberOrHash, DatabaseParams, PruningParams, SharedParams},
+};
+use codec::Decode;
+use frame_metadata::RuntimeMetadataPrefixed;
+use log::info;
+use sc_service::{Configuration, ServiceBuilderCommand};
+use sp_runtime::traits::{Block as BlockT, NumberFor};
+use std::{fmt::Debug, fs, io::{self, Write}, path::PathBuf, str::FromStr};
+use structopt::StructOpt;
+
+/// The `export-metadata` command used to export the runtime metadata of a given block.
+#[derive(Debug, StructOpt, Clone)]
+pub struct ExportMetadataCmd {
+	/// Block hash or number.
+	///
+	/// Default is the genesis block.
+	#[structopt(value_name = "HASH or NUMBER")]
+	pub input: Option<BlockNumberOrHash>,
+
+	/// Output file name or stdout if unspecified.
+	#[structopt(long, short = "o", value_name = "PATH", parse(from_os_str))]
+	pub output: Option<PathBuf>,
+
+	/// Encoding of the exported metadata.
+	#[structopt(
+		long,
+		value_name = "FORMAT",
+		possible_values = &MetadataFormat::variants(),
+		case_insensitive = true,
+		default_value = "Json"
+	)]
+	pub format: MetadataFormat,
+
+	#[allow(missing_docs)]
+	#[structopt(flatten)]
+	pub shared_params: SharedParams,
+
+	#[allow(missing_docs)]
+	#[structopt(flatten)]
+	pub pruning_params: PruningParams,
+
+	#[allow(missing_docs)]
+	#[structopt(flatten)]
+	pub database_params: DatabaseParams,
+}
+
+impl ExportMetadataCmd {
+	/// Run the `export-metadata` command
+	pub fn run<B, BC, BB>(
+		&self,
+		config: Configuration,
+		builder: B,
+	) -> error::Result<()>
+	where
+		B: FnOnce(Configuration) -> Result<BC, sc_service::error::Error>,
+		BC: ServiceBuilderCommand<Block = BB> + Unpin,
+		BB: BlockT + Debug,
+		<NumberFor<BB> as FromStr>::Err: std::fmt::Debug,
+		BB::Hash: FromStr,
+		<BB::Hash as FromStr>::Err: std::fmt::Debug,
+	{
+		info!("Exporting runtime metadata...");
+		let block_id = self.input.clone().map(|b| b.parse()).transpose()?;
+		let metadata = builder(config)?.runtime_metadata(block_id)?;
+		let metadata = encode_metadata(metadata, self.format)?;
+
+		let mut output: Box<dyn Write> = match &self.output {
+			Some(path) => Box::new(fs::File::create(path)?),
+			None => Box::new(io::stdout()),
+		};
+		output.write_all(&metadata)?;
+		Ok(())
+	}
+}
+
+impl CliConfiguration for ExportMetadataCmd {
+	fn shared_params(&self) -> &SharedParams {
+		&self.shared_params
+	}
+
+	fn pruning_params(&self) -> Option<&PruningParams> {
+		Some(&self.pruning_params)
+	}
+
+	fn database_params(&self) -> Option<&DatabaseParams> {
+		Some(&self.database_params)
+	}
+
+	fn database_read_only(&self) -> error::Result<bool> {
+		self.database_params.database_read_only(true)
+	}
+}
+
+/// Convert the SCALE encoded `metadata` into the given `format`.
+fn encode_metadata(metadata: Vec<u8>, format: MetadataFormat) -> error::Result<Vec<u8>> {
+	match format {
+		MetadataFormat::Scale => Ok(metadata),
+		MetadataFormat::Json => {
+			let metadata = RuntimeMetadataPrefixed::decode(&mut &metadata[..])
+				.map_err(|e| error::Error::Other(format!("Invalid runtime metadata: {}", e)))?;
+			serde_json::to_vec_pretty(&metadata)
+				.map_err(|e| error::Error::Other(format!("Error encoding metadata: {}", e)))
+		},
+	}
+}
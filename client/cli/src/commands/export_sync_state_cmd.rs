@@ -0,0 +1,89 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2020 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::{CliConfiguration, error, params::{PruningParams, SharedParams}};
+use log::info;
+use sc_service::{Configuration, ServiceBuilderCommand};
+use sp_runtime::traits::Block as BlockT;
+use std::{fmt::Debug, io::Write};
+use structopt::StructOpt;
+
+/// Auxiliary storage keys of the consensus data that is embedded by default.
+///
+/// These are the keys BABE stores its epoch changes and GRANDPA its authority set under.
+const DEFAULT_CONSENSUS_AUX_KEYS: &[&str] = &["babe_epoch_changes", "grandpa_voters"];
+
+/// The `export-sync-state` command used to embed a light client sync checkpoint at the
+/// last finalized block into a chain spec.
+#[derive(Debug, StructOpt, Clone)]
+pub struct ExportSyncStateCmd {
+	/// Auxiliary storage key of consensus data to embed into the checkpoint.
+	///
+	/// Defaults to the keys used by BABE and GRANDPA.
+	#[structopt(long = "aux-key", value_name = "KEY")]
+	pub aux_keys: Vec<String>,
+
+	#[allow(missing_docs)]
+	#[structopt(flatten)]
+	pub shared_params: SharedParams,
+
+	#[allow(missing_docs)]
+	#[structopt(flatten)]
+	pub pruning_params: PruningParams,
+}
+
+impl ExportSyncStateCmd {
+	/// Run the `export-sync-state` command
+	pub fn run<B, BC, BB>(
+		&self,
+		config: Configuration,
+		builder: B,
+	) -> error::Result<()>
+	where
+		B: FnOnce(Configuration) -> Result<BC, sc_service::error::Error>,
+		BC: ServiceBuilderCommand<Block = BB> + Unpin,
+		BB: BlockT + Debug,
+	{
+		info!("Exporting sync state...");
+		let mut spec = config.chain_spec.cloned_box();
+		let aux_keys = if self.aux_keys.is_empty() {
+			DEFAULT_CONSENSUS_AUX_KEYS.iter().map(|k| k.as_bytes()).collect::<Vec<_>>()
+		} else {
+			self.aux_keys.iter().map(|k| k.as_bytes()).collect()
+		};
+		let sync_state = builder(config)?.export_sync_state(&aux_keys)?;
+		spec.set_light_sync_state(sync_state);
+
+		info!("Generating new chain spec...");
+		let json = sc_service::chain_ops::build_spec(&*spec, false)?;
+		if std::io::stdout().write_all(json.as_bytes()).is_err() {
+			let _ = std::io::stderr().write_all(b"Error writing to stdout\n");
+		}
+		Ok(())
+	}
+}
+
+impl CliConfiguration for ExportSyncStateCmd {
+	fn shared_params(&self) -> &SharedParams {
+		&self.shared_params
+	}
+
+	fn pruning_params(&self) -> Option<&PruningParams> {
+		Some(&self.pruning_params)
+	}
+}
@@ -0,0 +1,106 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2020 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::error;
+use crate::params::{BlockNumberOrHash, SharedParams};
+use crate::remote_state;
+use crate::rpc_client::{block_on, RpcClient};
+use crate::CliConfiguration;
+use log::info;
+use sc_service::Configuration;
+use sp_core::hashing::twox_128;
+use std::{fs, io::Write, path::PathBuf};
+use structopt::StructOpt;
+
+/// The `fork-off` command used to copy the state of a live chain into a raw chain spec or a
+/// storage snapshot, to reproduce issues of the live chain locally.
+#[derive(Debug, StructOpt, Clone)]
+pub struct ForkOffCmd {
+	/// HTTP RPC endpoint of a node of the live chain, e.g. `http://127.0.0.1:9933`.
+	#[structopt(long = "rpc-url", value_name = "URL")]
+	pub rpc_url: String,
+
+	/// Block hash or number whose state is copied. Defaults to the best block.
+	#[structopt(long = "at", value_name = "HASH or NUMBER")]
+	pub at: Option<BlockNumberOrHash>,
+
+	/// Only copy the storage of the pallet with the given name, e.g. `Balances`. Can be repeated.
+	///
+	/// The rest of the state is the genesis state of the chain spec.
+	#[structopt(long = "pallet", value_name = "NAME")]
+	pub pallets: Vec<String>,
+
+	/// Write the state to this file as a snapshot that tests can load with
+	/// `TestExternalities::from_snapshot`, instead of printing a raw chain spec to stdout.
+	#[structopt(long = "snapshot", value_name = "PATH", parse(from_os_str))]
+	pub snapshot: Option<PathBuf>,
+
+	#[allow(missing_docs)]
+	#[structopt(flatten)]
+	pub shared_params: SharedParams,
+}
+
+impl ForkOffCmd {
+	/// Run the fork-off command
+	pub fn run(&self, config: Configuration) -> error::Result<()> {
+		let mut spec = config.chain_spec;
+		let prefixes = self.pallets.iter()
+			.map(|pallet| twox_128(pallet.as_bytes()).to_vec())
+			.collect::<Vec<_>>();
+
+		let rpc = RpcClient::new(&self.rpc_url)?;
+		let at = self.at.as_ref().map(BlockNumberOrHash::as_str);
+		let remote = block_on(async {
+			let hash = remote_state::block_hash(&rpc, at).await?;
+			info!("Fetching the state of block {:?} from {}", hash, self.rpc_url);
+			remote_state::fetch_storage(&rpc, hash, &prefixes).await
+		})?;
+
+		let storage = if prefixes.is_empty() {
+			remote
+		} else {
+			let mut storage = spec.build_storage()?;
+			storage.top.retain(|key, _| !prefixes.iter().any(|prefix| key.starts_with(prefix)));
+			storage.top.extend(remote.top);
+			storage.children_default.extend(remote.children_default);
+			storage
+		};
+
+		match self.snapshot {
+			Some(ref path) => {
+				fs::write(path, sp_state_machine::encode_storage_snapshot(&storage))?;
+				info!("Storage snapshot written to {}", path.display());
+			},
+			None => {
+				spec.set_storage(storage);
+				let json = sc_service::chain_ops::build_spec(&*spec, true)?;
+				if std::io::stdout().write_all(json.as_bytes()).is_err() {
+					let _ = std::io::stderr().write_all(b"Error writing to stdout\n");
+				}
+			},
+		}
+
+		Ok(())
+	}
+}
+
+impl CliConfiguration for ForkOffCmd {
+	fn shared_params(&self) -> &SharedParams {
+		&self.shared_params
+	}
+}
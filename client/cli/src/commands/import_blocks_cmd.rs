@@ -26,6 +26,7 @@ use std::fmt::Debug;
 use std::fs;
 use std::io::{self, Read, Seek};
 use std::path::PathBuf;
+use std::sync::Arc;
 use structopt::StructOpt;
 
 /// The `import-blocks` command used to import blocks.
@@ -45,6 +46,21 @@ pub struct ImportBlocksCmd {
 	#[structopt(long)]
 	pub binary: bool,
 
+	/// Only check that the blocks follow each other and that their extrinsics roots match their
+	/// bodies, without importing them.
+	///
+	/// These checks always run before the blocks are imported, so that a corrupted input fails
+	/// before any block is executed.
+	#[structopt(long)]
+	pub validate_only: bool,
+
+	/// Check the justifications of the blocks as well before importing them, following the
+	/// changes of the authorities signaled by the blocks.
+	///
+	/// Otherwise the justifications are only checked while the blocks are imported.
+	#[structopt(long)]
+	pub check_justifications: bool,
+
 	#[allow(missing_docs)]
 	#[structopt(flatten)]
 	pub shared_params: SharedParams,
@@ -73,17 +89,27 @@ impl ImportBlocksCmd {
 		<<<BB as BlockT>::Header as HeaderT>::Number as std::str::FromStr>::Err: std::fmt::Debug,
 		<BB as BlockT>::Hash: std::str::FromStr,
 	{
-		let file: Box<dyn ReadPlusSeek + Send> = match &self.input {
-			Some(filename) => Box::new(fs::File::open(filename)?),
-			None => {
-				let mut buffer = Vec::new();
-				io::stdin().read_to_end(&mut buffer)?;
-				Box::new(io::Cursor::new(buffer))
+		// The input is read twice, once to validate the blocks and once to import them.
+		let mut stdin = Vec::new();
+		if self.input.is_none() {
+			io::stdin().read_to_end(&mut stdin)?;
+		}
+		let stdin: Arc<[u8]> = stdin.into();
+		let open_input = || -> io::Result<Box<dyn ReadPlusSeek + Send>> {
+			match &self.input {
+				Some(filename) => Ok(Box::new(fs::File::open(filename)?)),
+				None => Ok(Box::new(io::Cursor::new(stdin.clone()))),
 			}
 		};
 
-		builder(config)?
-			.import_blocks(file, false, self.binary)
+		let builder = builder(config)?;
+		builder.validate_blocks(open_input()?, self.binary, self.check_justifications)?;
+		if self.validate_only {
+			return Ok(());
+		}
+
+		builder
+			.import_blocks(open_input()?, false, self.binary)
 			.await
 			.map_err(Into::into)
 	}
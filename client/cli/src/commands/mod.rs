@@ -15,23 +15,55 @@
 
 // You should have received a copy of the GNU General Public License
 // along with this program. If not, see <https://www.gnu.org/licenses/>.
+mod benchmark_block_cmd;
+mod build_genesis_cmd;
 mod build_spec_cmd;
+mod chain_spec_cmd;
 mod check_block_cmd;
+mod config_cmd;
+mod db_cmd;
+mod diff_block_cmd;
 mod export_blocks_cmd;
+mod export_metadata_cmd;
 mod export_state_cmd;
+mod export_sync_state_cmd;
+mod fork_off_cmd;
 mod import_blocks_cmd;
+mod network_cmd;
+mod offchain_storage_cmd;
 mod purge_chain_cmd;
+mod replay_cmd;
 mod revert_cmd;
+mod rotate_keys_cmd;
 mod run_cmd;
+mod state_cmd;
+mod status_cmd;
+mod try_runtime_cmd;
 
+pub use self::benchmark_block_cmd::BenchmarkBlockCmd;
+pub use self::build_genesis_cmd::BuildGenesisCmd;
 pub use self::build_spec_cmd::BuildSpecCmd;
+pub use self::chain_spec_cmd::{ChainSpecCmd, DiffChainSpecCmd, ValidateChainSpecCmd};
 pub use self::check_block_cmd::CheckBlockCmd;
+pub use self::config_cmd::ConfigCmd;
+pub use self::db_cmd::{DbCmd, DbAction};
+pub use self::diff_block_cmd::DiffBlockCmd;
 pub use self::export_blocks_cmd::ExportBlocksCmd;
 pub use self::import_blocks_cmd::ImportBlocksCmd;
+pub use self::network_cmd::{NetworkCmd, NetworkAction};
+pub use self::offchain_storage_cmd::{OffchainStorageCmd, OffchainStorageAction};
 pub use self::purge_chain_cmd::PurgeChainCmd;
+pub use self::replay_cmd::ReplayCmd;
 pub use self::revert_cmd::RevertCmd;
+pub use self::rotate_keys_cmd::RotateKeysCmd;
 pub use self::run_cmd::RunCmd;
+pub use self::state_cmd::{StateCmd, StateAction};
+pub use self::status_cmd::StatusCmd;
+pub use self::try_runtime_cmd::TryRuntimeCmd;
 pub use self::export_state_cmd::ExportStateCmd;
+pub use self::export_metadata_cmd::ExportMetadataCmd;
+pub use self::export_sync_state_cmd::ExportSyncStateCmd;
+pub use self::fork_off_cmd::ForkOffCmd;
 use std::fmt::Debug;
 use structopt::StructOpt;
 
@@ -45,6 +77,10 @@ pub enum Subcommand {
 	/// Build a spec.json file, outputs to stdout.
 	BuildSpec(BuildSpecCmd),
 
+	/// Build a raw spec.json file with the genesis storage built by a runtime wasm, outputs to
+	/// stdout.
+	BuildGenesis(BuildGenesisCmd),
+
 	/// Export blocks to a file.
 	ExportBlocks(ExportBlocksCmd),
 
@@ -62,6 +98,46 @@ pub enum Subcommand {
 
 	/// Export state as raw chain spec.
 	ExportState(ExportStateCmd),
+
+	/// Export the runtime metadata as SCALE or JSON.
+	ExportMetadata(ExportMetadataCmd),
+
+	/// Embed a light client sync checkpoint at the last finalized block into the chain spec.
+	ExportSyncState(ExportSyncStateCmd),
+
+	/// Inspect or modify the persistent offchain storage.
+	OffchainStorage(OffchainStorageCmd),
+
+	/// Time the re-execution of known blocks.
+	BenchmarkBlock(BenchmarkBlockCmd),
+
+	/// Execute a known block in two different ways and compare the changes they make.
+	DiffBlock(DiffBlockCmd),
+
+	/// Maintain the node database.
+	Db(DbCmd),
+
+	/// Inspect the state of the chain.
+	State(StateCmd),
+
+	/// Print the identity and chain status of a node as JSON.
+	#[structopt(alias = "whoami")]
+	Status(StatusCmd),
+
+	/// Generate new session keys in the keystore of a running node.
+	RotateKeys(RotateKeysCmd),
+
+	/// Dry-run the migrations of a new runtime on the state of a block.
+	TryRuntime(TryRuntimeCmd),
+
+	/// Copy the state of a live chain into a raw chain spec or a storage snapshot.
+	ForkOff(ForkOffCmd),
+
+	/// Manage the network identity of the node.
+	Network(NetworkCmd),
+
+	/// Replay the consensus inputs recorded with `--replay-log`.
+	Replay(ReplayCmd),
 }
 
 // TODO: move to config.rs?
@@ -168,6 +244,18 @@ macro_rules! substrate_cli_subcommands {
 				}
 			}
 
+			fn data_path(&self) -> $crate::Result<::std::option::Option<::std::path::PathBuf>> {
+				match self {
+					$($enum::$variant(cmd) => cmd.data_path()),*
+				}
+			}
+
+			fn profile_name(&self) -> $crate::Result<::std::option::Option<String>> {
+				match self {
+					$($enum::$variant(cmd) => cmd.profile_name()),*
+				}
+			}
+
 			fn is_dev(&self) -> $crate::Result<bool> {
 				match self {
 					$($enum::$variant(cmd) => cmd.is_dev()),*
@@ -235,9 +323,76 @@ macro_rules! substrate_cli_subcommands {
 				}
 			}
 
-			fn state_cache_size(&self) -> $crate::Result<usize> {
+			fn cold_storage(
+				&self,
+				cache_size: usize,
+				database: $crate::Database,
+			) -> $crate::Result<::std::option::Option<::sc_service::config::ColdStorageConfig>> {
+				match self {
+					$($enum::$variant(cmd) => cmd.cold_storage(cache_size, database)),*
+				}
+			}
+
+			fn database_read_only(&self) -> $crate::Result<bool> {
+				match self {
+					$($enum::$variant(cmd) => cmd.database_read_only()),*
+				}
+			}
+
+			fn block_compression(&self)
+			-> $crate::Result<::sc_service::config::BlockCompression> {
+				match self {
+					$($enum::$variant(cmd) => cmd.block_compression()),*
+				}
+			}
+
+			fn profile(&self) -> $crate::Result<::std::option::Option<$crate::Profile>> {
+				match self {
+					$($enum::$variant(cmd) => cmd.profile()),*
+				}
+			}
+
+			fn strict_config(&self) -> $crate::Result<bool> {
 				match self {
-					$($enum::$variant(cmd) => cmd.state_cache_size()),*
+					$($enum::$variant(cmd) => cmd.strict_config()),*
+				}
+			}
+
+			fn insecure_keystore_permissions(&self) -> $crate::Result<bool> {
+				match self {
+					$($enum::$variant(cmd) => cmd.insecure_keystore_permissions()),*
+				}
+			}
+
+			fn authoring_shutdown_timeout(
+				&self,
+			) -> $crate::Result<::std::option::Option<::std::time::Duration>> {
+				match self {
+					$($enum::$variant(cmd) => cmd.authoring_shutdown_timeout()),*
+				}
+			}
+
+			fn runtime_threads(&self) -> $crate::Result<::std::option::Option<usize>> {
+				match self {
+					$($enum::$variant(cmd) => cmd.runtime_threads()),*
+				}
+			}
+
+			fn blocking_threads(&self) -> $crate::Result<::std::option::Option<usize>> {
+				match self {
+					$($enum::$variant(cmd) => cmd.blocking_threads()),*
+				}
+			}
+
+			fn trie_cache_size(&self) -> $crate::Result<usize> {
+				match self {
+					$($enum::$variant(cmd) => cmd.trie_cache_size()),*
+				}
+			}
+
+			fn value_cache_size(&self) -> $crate::Result<usize> {
+				match self {
+					$($enum::$variant(cmd) => cmd.value_cache_size()),*
 				}
 			}
 
@@ -260,6 +415,18 @@ macro_rules! substrate_cli_subcommands {
 				}
 			}
 
+			fn chain_spec_sha256(&self) -> $crate::Result<::std::option::Option<[u8; 32]>> {
+				match self {
+					$($enum::$variant(cmd) => cmd.chain_spec_sha256()),*
+				}
+			}
+
+			fn ss58_prefix(&self) -> $crate::Result<::std::option::Option<u8>> {
+				match self {
+					$($enum::$variant(cmd) => cmd.ss58_prefix()),*
+				}
+			}
+
 			fn init<C: $crate::SubstrateCli>(&self) -> $crate::Result<()> {
 				match self {
 					$($enum::$variant(cmd) => cmd.init::<C>()),*
@@ -285,6 +452,32 @@ macro_rules! substrate_cli_subcommands {
 				}
 			}
 
+			fn signature_verification(&self)
+			-> $crate::Result<::sc_service::config::SignatureVerification> {
+				match self {
+					$($enum::$variant(cmd) => cmd.signature_verification()),*
+				}
+			}
+
+			fn client_notifications(&self)
+			-> $crate::Result<::sc_service::config::NotificationConfig> {
+				match self {
+					$($enum::$variant(cmd) => cmd.client_notifications()),*
+				}
+			}
+
+			fn storage_diffs(&self) -> $crate::Result<bool> {
+				match self {
+					$($enum::$variant(cmd) => cmd.storage_diffs()),*
+				}
+			}
+
+			fn replay_log(&self) -> $crate::Result<::std::option::Option<::std::path::PathBuf>> {
+				match self {
+					$($enum::$variant(cmd) => cmd.replay_log()),*
+				}
+			}
+
 			fn rpc_http(&self) -> $crate::Result<::std::option::Option<::std::net::SocketAddr>> {
 				match self {
 					$($enum::$variant(cmd) => cmd.rpc_http()),*
@@ -303,12 +496,80 @@ macro_rules! substrate_cli_subcommands {
 				}
 			}
 
+			fn rpc_finalized_only(&self) -> $crate::Result<bool> {
+				match self {
+					$($enum::$variant(cmd) => cmd.rpc_finalized_only()),*
+				}
+			}
+
+			fn rpc_subscription_replay(&self)
+			-> $crate::Result<::std::option::Option<::std::time::Duration>> {
+				match self {
+					$($enum::$variant(cmd) => cmd.rpc_subscription_replay()),*
+				}
+			}
+
+			fn rpc_call_timeout(&self)
+			-> $crate::Result<::std::option::Option<::std::time::Duration>> {
+				match self {
+					$($enum::$variant(cmd) => cmd.rpc_call_timeout()),*
+				}
+			}
+
+			fn admin_rpc_peers(&self)
+			-> $crate::Result<::std::vec::Vec<::sc_service::config::PeerId>> {
+				match self {
+					$($enum::$variant(cmd) => cmd.admin_rpc_peers()),*
+				}
+			}
+
+			fn node_labels(&self) -> $crate::Result<::std::vec::Vec<(String, String)>> {
+				match self {
+					$($enum::$variant(cmd) => cmd.node_labels()),*
+				}
+			}
+
+			fn disk_space_watchdog(&self) -> $crate::Result<
+				::std::option::Option<::sc_service::config::DiskSpaceWatchdogConfig>
+			> {
+				match self {
+					$($enum::$variant(cmd) => cmd.disk_space_watchdog()),*
+				}
+			}
+
+			fn pool_memory_limits(&self) -> $crate::Result<
+				::std::option::Option<::sc_service::config::PoolMemoryLimitsConfig>
+			> {
+				match self {
+					$($enum::$variant(cmd) => cmd.pool_memory_limits()),*
+				}
+			}
+
+			fn unsafe_rpc_external(&self) -> $crate::Result<bool> {
+				match self {
+					$($enum::$variant(cmd) => cmd.unsafe_rpc_external()),*
+				}
+			}
+
+			fn unsafe_ws_external(&self) -> $crate::Result<bool> {
+				match self {
+					$($enum::$variant(cmd) => cmd.unsafe_ws_external()),*
+				}
+			}
+
 			fn rpc_ws_max_connections(&self) -> $crate::Result<::std::option::Option<usize>> {
 				match self {
 					$($enum::$variant(cmd) => cmd.rpc_ws_max_connections()),*
 				}
 			}
 
+			fn rpc_ws_idle_timeout(&self)
+			-> $crate::Result<::std::option::Option<::std::time::Duration>> {
+				match self {
+					$($enum::$variant(cmd) => cmd.rpc_ws_idle_timeout()),*
+				}
+			}
+
 			fn rpc_cors(&self, is_dev: bool)
 			-> $crate::Result<::std::option::Option<::std::vec::Vec<String>>> {
 				match self {
@@ -316,6 +577,13 @@ macro_rules! substrate_cli_subcommands {
 				}
 			}
 
+			fn rpc_allowed_hosts(&self, is_dev: bool)
+			-> $crate::Result<::std::option::Option<::std::vec::Vec<String>>> {
+				match self {
+					$($enum::$variant(cmd) => cmd.rpc_allowed_hosts(is_dev)),*
+				}
+			}
+
 			fn prometheus_config(&self)
 			-> $crate::Result<::std::option::Option<::sc_service::config::PrometheusConfig>> {
 				match self {
@@ -339,6 +607,12 @@ macro_rules! substrate_cli_subcommands {
 				}
 			}
 
+			fn telemetry_auth(&self) -> $crate::Result<::sc_service::config::TelemetryAuth> {
+				match self {
+					$($enum::$variant(cmd) => cmd.telemetry_auth()),*
+				}
+			}
+
 			fn default_heap_pages(&self) -> $crate::Result<::std::option::Option<u64>> {
 				match self {
 					$($enum::$variant(cmd) => cmd.default_heap_pages()),*
@@ -366,6 +640,34 @@ macro_rules! substrate_cli_subcommands {
 				}
 			}
 
+			fn grandpa_justification_period(&self)
+			-> $crate::Result<::std::option::Option<u32>> {
+				match self {
+					$($enum::$variant(cmd) => cmd.grandpa_justification_period()),*
+				}
+			}
+
+			fn grandpa_request_justifications(&self) -> $crate::Result<bool> {
+				match self {
+					$($enum::$variant(cmd) => cmd.grandpa_request_justifications()),*
+				}
+			}
+
+			fn grandpa_justification_workers(&self) -> $crate::Result<usize> {
+				match self {
+					$($enum::$variant(cmd) => cmd.grandpa_justification_workers()),*
+				}
+			}
+
+			fn grandpa_gossip_duration(
+				&self,
+				chain_spec: &Box<dyn ::sc_service::ChainSpec>,
+			) -> $crate::Result<::std::time::Duration> {
+				match self {
+					$($enum::$variant(cmd) => cmd.grandpa_gossip_duration(chain_spec)),*
+				}
+			}
+
 			fn dev_key_seed(&self, is_dev: bool) -> $crate::Result<::std::option::Option<String>> {
 				match self {
 					$($enum::$variant(cmd) => cmd.dev_key_seed(is_dev)),*
@@ -402,11 +704,19 @@ macro_rules! substrate_cli_subcommands {
 					$($enum::$variant(cmd) => cmd.log_filters()),*
 				}
 			}
+
+			fn log_rate_limits(&self) -> $crate::Result<Vec<$crate::LogRateLimit>> {
+				match self {
+					$($enum::$variant(cmd) => cmd.log_rate_limits()),*
+				}
+			}
 		}
 	}
 }
 
 substrate_cli_subcommands!(
-	Subcommand => BuildSpec, ExportBlocks, ImportBlocks, CheckBlock, Revert, PurgeChain, ExportState
+	Subcommand => BuildSpec, BuildGenesis, ExportBlocks, ImportBlocks, CheckBlock, Revert,
+	PurgeChain, ExportState, ExportMetadata, ExportSyncState, OffchainStorage, BenchmarkBlock,
+	DiffBlock, Db, State, Status, RotateKeys, TryRuntime, ForkOff, Network, Replay
 );
 
@@ -0,0 +1,94 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2020 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::{error, CliConfiguration, params::{NodeKeyParams, SharedParams}};
+use sc_network::config::{rotate_ed25519_secret_file, NodeKeyConfig, Secret};
+use sc_service::Configuration;
+use std::time::Duration;
+use structopt::StructOpt;
+
+/// The `network` command used to manage the network identity of the node.
+#[derive(Debug, StructOpt, Clone)]
+pub struct NetworkCmd {
+	#[allow(missing_docs)]
+	#[structopt(subcommand)]
+	pub action: NetworkAction,
+
+	#[allow(missing_docs)]
+	#[structopt(flatten)]
+	pub shared_params: SharedParams,
+
+	#[allow(missing_docs)]
+	#[structopt(flatten)]
+	pub node_key_params: NodeKeyParams,
+}
+
+/// Operations of the `network` command.
+#[derive(Debug, StructOpt, Clone)]
+pub enum NetworkAction {
+	/// Replace the secret key of the node with a newly generated one, which changes its peer ID.
+	///
+	/// The key file is replaced atomically and the previous key is kept next to it, in
+	/// `<key file>.previous`. The node keeps its previous identity until the end of the grace
+	/// period, leaving time to update the nodes referring to it, and uses its new identity once
+	/// restarted afterwards.
+	RotateNodeKey {
+		/// Number of seconds during which the node keeps its previous identity.
+		#[structopt(long = "grace-period", value_name = "SECONDS", default_value = "86400")]
+		grace_period: u64,
+	},
+}
+
+impl NetworkCmd {
+	/// Run the `network` command
+	pub fn run(&self, config: Configuration) -> error::Result<()> {
+		match self.action {
+			NetworkAction::RotateNodeKey { grace_period } => {
+				let file = match config.network.node_key {
+					NodeKeyConfig::Ed25519(Secret::File(file)) => file,
+					_ => return Err(error::Error::Input(
+						"The node key must be stored in a file to be rotated".into()
+					)),
+				};
+
+				let grace_period = Duration::from_secs(grace_period);
+				let (previous, new) = rotate_ed25519_secret_file(&file, grace_period)?;
+				if let Some(previous) = previous {
+					println!("Previous peer ID: {}", previous.public().into_peer_id());
+				}
+				println!("New peer ID: {}", new.public().into_peer_id());
+				println!(
+					"Update the nodes referring to its previous peer ID, e.g. in their bootnodes \
+					or reserved peers, then restart the node after {}s to use its new identity.",
+					grace_period.as_secs(),
+				);
+				Ok(())
+			},
+		}
+	}
+}
+
+impl CliConfiguration for NetworkCmd {
+	fn shared_params(&self) -> &SharedParams {
+		&self.shared_params
+	}
+
+	fn node_key_params(&self) -> Option<&NodeKeyParams> {
+		Some(&self.node_key_params)
+	}
+}
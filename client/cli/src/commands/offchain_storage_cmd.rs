@@ -0,0 +1,117 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2020 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::{CliConfiguration, error, params::{DatabaseParams, SharedParams}};
+use sc_service::{Configuration, ServiceBuilderCommand};
+use sp_core::{Bytes, hexdisplay::HexDisplay, offchain::{OffchainStorage, STORAGE_PREFIX}};
+use sp_runtime::traits::Block as BlockT;
+use std::fmt::Debug;
+use structopt::StructOpt;
+
+/// The `offchain-storage` command used to inspect and modify the persistent offchain storage.
+///
+/// This can be used to clear locks left behind by offchain workers that crashed while
+/// holding them. The node must not be running while the storage is modified.
+#[derive(Debug, StructOpt, Clone)]
+pub struct OffchainStorageCmd {
+	#[allow(missing_docs)]
+	#[structopt(subcommand)]
+	pub action: OffchainStorageAction,
+
+	#[allow(missing_docs)]
+	#[structopt(flatten)]
+	pub shared_params: SharedParams,
+
+	#[allow(missing_docs)]
+	#[structopt(flatten)]
+	pub database_params: DatabaseParams,
+}
+
+/// Operations of the `offchain-storage` command.
+///
+/// Keys and values are hex encoded.
+#[derive(Debug, StructOpt, Clone)]
+pub enum OffchainStorageAction {
+	/// Print the value stored under a key.
+	Get {
+		/// Hex encoded key.
+		#[structopt(value_name = "KEY")]
+		key: Bytes,
+	},
+	/// Store a value under a key.
+	Set {
+		/// Hex encoded key.
+		#[structopt(value_name = "KEY")]
+		key: Bytes,
+		/// Hex encoded value.
+		#[structopt(value_name = "VALUE")]
+		value: Bytes,
+	},
+	/// Remove the value stored under a key.
+	Clear {
+		/// Hex encoded key.
+		#[structopt(value_name = "KEY")]
+		key: Bytes,
+	},
+}
+
+impl OffchainStorageCmd {
+	/// Run the `offchain-storage` command
+	pub fn run<B, BC, BB>(
+		&self,
+		config: Configuration,
+		builder: B,
+	) -> error::Result<()>
+	where
+		B: FnOnce(Configuration) -> Result<BC, sc_service::error::Error>,
+		BC: ServiceBuilderCommand<Block = BB> + Unpin,
+		BB: BlockT + Debug,
+	{
+		let mut storage = builder(config)?.offchain_storage()
+			.ok_or_else(|| error::Error::Input("The database has no offchain storage".into()))?;
+
+		match &self.action {
+			OffchainStorageAction::Get { key } => match storage.get(STORAGE_PREFIX, key) {
+				Some(value) => println!("0x{}", HexDisplay::from(&value)),
+				None => println!("No value stored under the key"),
+			},
+			OffchainStorageAction::Set { key, value } => storage.set(STORAGE_PREFIX, key, value),
+			OffchainStorageAction::Clear { key } => storage.remove(STORAGE_PREFIX, key),
+		}
+
+		Ok(())
+	}
+}
+
+impl CliConfiguration for OffchainStorageCmd {
+	fn shared_params(&self) -> &SharedParams {
+		&self.shared_params
+	}
+
+	fn database_params(&self) -> Option<&DatabaseParams> {
+		Some(&self.database_params)
+	}
+
+	fn database_read_only(&self) -> error::Result<bool> {
+		let inspecting = match self.action {
+			OffchainStorageAction::Get { .. } => true,
+			_ => false,
+		};
+		self.database_params.database_read_only(inspecting)
+	}
+}
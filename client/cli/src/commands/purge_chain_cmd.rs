@@ -19,7 +19,7 @@
 use crate::error;
 use crate::params::{DatabaseParams, SharedParams};
 use crate::CliConfiguration;
-use sc_service::Configuration;
+use sc_service::{Configuration, Role};
 use std::fmt::Debug;
 use std::fs;
 use std::io::{self, Write};
@@ -32,6 +32,10 @@ pub struct PurgeChainCmd {
 	#[structopt(short = "y")]
 	pub yes: bool,
 
+	/// Remove the database of the light client instead of the full node.
+	#[structopt(long = "light")]
+	pub light: bool,
+
 	#[allow(missing_docs)]
 	#[structopt(flatten)]
 	pub shared_params: SharedParams,
@@ -85,6 +89,10 @@ impl CliConfiguration for PurgeChainCmd {
 		&self.shared_params
 	}
 
+	fn role(&self, _is_dev: bool) -> error::Result<Role> {
+		Ok(if self.light { Role::Light } else { Role::Full })
+	}
+
 	fn database_params(&self) -> Option<&DatabaseParams> {
 		Some(&self.database_params)
 	}
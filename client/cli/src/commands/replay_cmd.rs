@@ -0,0 +1,202 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2020 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::error;
+use crate::params::ImportParams;
+use crate::params::SharedParams;
+use crate::CliConfiguration;
+use codec::Encode;
+use log::{info, warn};
+use sc_network::PeerId;
+use sc_service::replay_log::{decode_entries, ReplayEntry};
+use sc_service::{Configuration, ServiceBuilderCommand};
+use sp_consensus::ConsensusReplay;
+use sp_runtime::generic::SignedBlock;
+use sp_runtime::traits::{Block as BlockT, Header as HeaderT};
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::fs;
+use std::io::Cursor;
+use std::path::PathBuf;
+use structopt::StructOpt;
+
+/// The `replay` command used to replay the consensus inputs recorded with `--replay-log`.
+#[derive(Debug, StructOpt, Clone)]
+pub struct ReplayCmd {
+	/// The replay log recorded by the node.
+	#[structopt(parse(from_os_str))]
+	pub log: PathBuf,
+
+	/// Only print the timeline of the recorded inputs, without importing the blocks.
+	#[structopt(long)]
+	pub timeline_only: bool,
+
+	#[allow(missing_docs)]
+	#[structopt(flatten)]
+	pub shared_params: SharedParams,
+
+	#[allow(missing_docs)]
+	#[structopt(flatten)]
+	pub import_params: ImportParams,
+}
+
+impl ReplayCmd {
+	/// Run the replay command.
+	///
+	/// The recorded blocks are imported in order with the justifications they were finalized
+	/// with, then the timeline of the recorded inputs is printed. The finalizations and the
+	/// gossip messages of the timeline are fed to the consensus replay of the node, whose timers
+	/// follow the recorded times, and what happened in the consensus is printed along the
+	/// timeline. The blocks should be imported into a fresh database, e.g. with `--base-path`.
+	///
+	/// With `--timeline-only`, only the timeline is printed.
+	pub async fn run<B, BC, BB>(
+		&self,
+		config: Configuration,
+		builder: B,
+	) -> error::Result<()>
+	where
+		B: FnOnce(Configuration) -> Result<BC, sc_service::error::Error>,
+		BC: ServiceBuilderCommand<Block = BB> + Unpin,
+		BB: BlockT + Debug,
+	{
+		let log = fs::read(&self.log)?;
+		let (entries, undecoded) = decode_entries::<BB, BB::Hash>(&log);
+		if undecoded > 0 {
+			warn!("Ignoring the last {} bytes of the replay log, which are malformed", undecoded);
+		}
+
+		// The times restart from zero with each recording appended to the log, the recordings
+		// are laid out one after the other.
+		let mut offset = 0;
+		let mut last_elapsed = 0;
+		let timeline = entries.into_iter().map(|entry| {
+			if entry.elapsed() < last_elapsed {
+				offset += last_elapsed;
+			}
+			last_elapsed = entry.elapsed();
+			(offset + entry.elapsed(), entry)
+		}).collect::<Vec<_>>();
+
+		if self.timeline_only {
+			print_timeline(&timeline, None);
+			return Ok(());
+		}
+
+		let mut blocks = Vec::<SignedBlock<BB>>::new();
+		let mut block_indices = HashMap::new();
+		for (_, entry) in &timeline {
+			match entry {
+				ReplayEntry::Imported { block, .. } => {
+					block_indices.insert(block.block.hash(), blocks.len());
+					blocks.push(block.clone());
+				},
+				ReplayEntry::Finalized { hash, justification, .. } => {
+					if let Some(block) = block_indices.get(hash).map(|index| &mut blocks[*index]) {
+						if block.justification.is_none() {
+							block.justification = justification.clone();
+						}
+					}
+				},
+				ReplayEntry::Gossip { .. } => {},
+			}
+		}
+
+		// The blocks are imported through the binary format of `import-blocks`.
+		let mut input = (blocks.len() as u64).encode();
+		for block in blocks {
+			block.encode_to(&mut input);
+		}
+
+		let builder = builder(config)?;
+		let mut replay = builder.consensus_replay();
+		if replay.is_none() {
+			warn!("The consensus of the node can't be replayed, only the timeline is printed");
+		}
+		builder.import_blocks(Cursor::new(input), false, true).await?;
+
+		print_timeline(&timeline, replay.as_mut());
+		Ok(())
+	}
+}
+
+/// Print the timeline of the recorded inputs, feeding them to `replay` and printing what happened
+/// in the consensus along them.
+fn print_timeline<B: BlockT>(
+	timeline: &[(u64, ReplayEntry<B, B::Hash>)],
+	mut replay: Option<&mut Box<dyn ConsensusReplay<B>>>,
+) {
+	let print_events = |events: Vec<(u64, String)>| {
+		for (elapsed, event) in events {
+			info!("{:>10} ms:   {}", elapsed, event);
+		}
+	};
+
+	for (elapsed, entry) in timeline {
+		let elapsed = *elapsed;
+		if let Some(replay) = replay.as_mut() {
+			print_events(replay.advance(elapsed));
+		}
+
+		match entry {
+			ReplayEntry::Imported { block, .. } => {
+				info!(
+					"{:>10} ms: imported #{} ({})",
+					elapsed,
+					block.block.header().number(),
+					block.block.hash(),
+				);
+			},
+			ReplayEntry::Finalized { hash, justification, .. } => {
+				info!(
+					"{:>10} ms: finalized {}{}",
+					elapsed,
+					hash,
+					if justification.is_some() { " with a justification" } else { "" },
+				);
+				if let Some(replay) = replay.as_mut() {
+					print_events(replay.finalized(elapsed, *hash));
+				}
+			},
+			ReplayEntry::Gossip { peer, engine_id, message, .. } => {
+				let peer = PeerId::from_bytes(peer.clone())
+					.map_or_else(|_| "unknown peer".into(), |peer| peer.to_base58());
+				info!(
+					"{:>10} ms: {} message of {} bytes from {}",
+					elapsed,
+					String::from_utf8_lossy(engine_id),
+					message.len(),
+					peer,
+				);
+				if let Some(replay) = replay.as_mut() {
+					print_events(replay.gossip(elapsed, *engine_id, message));
+				}
+			},
+		}
+	}
+}
+
+impl CliConfiguration for ReplayCmd {
+	fn shared_params(&self) -> &SharedParams {
+		&self.shared_params
+	}
+
+	fn import_params(&self) -> Option<&ImportParams> {
+		Some(&self.import_params)
+	}
+}
@@ -0,0 +1,176 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2020 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::{CliConfiguration, error, params::SharedParams};
+use crate::rpc_client::{block_on, call, RpcClient};
+use codec::{Compact, Decode, Encode};
+use frame_metadata::{DecodeDifferent, ModuleMetadata, RuntimeMetadata, RuntimeMetadataPrefixed};
+use sp_core::bytes::to_hex;
+use structopt::StructOpt;
+
+/// The `rotate-keys` command used to generate new session keys in the keystore of a running
+/// node.
+#[derive(Debug, StructOpt, Clone)]
+pub struct RotateKeysCmd {
+	/// HTTP RPC endpoint of the node.
+	///
+	/// Key rotation is an unsafe RPC method, so the node must either listen locally or run with
+	/// `--rpc-methods Unsafe`.
+	#[structopt(long = "rpc-url", value_name = "URL", default_value = "http://127.0.0.1:9933")]
+	pub rpc_url: String,
+
+	#[allow(missing_docs)]
+	#[structopt(flatten)]
+	pub shared_params: SharedParams,
+}
+
+impl RotateKeysCmd {
+	/// Run the `rotate-keys` command
+	pub fn run(&self) -> error::Result<()> {
+		let rpc = RpcClient::new(&self.rpc_url)?;
+		let (keys, decoded, metadata) = block_on(async {
+			let keys = call("author_rotateKeys", rpc.author.rotate_keys()).await?;
+			let decoded = call(
+				"author_decodeSessionKeys",
+				rpc.author.decode_session_keys(keys.clone()),
+			).await?;
+			let metadata = call("state_getMetadata", rpc.state.metadata(None)).await?;
+			Ok((keys, decoded, metadata))
+		})?;
+
+		for (public, key_type) in decoded {
+			println!("{}: {}", key_type, to_hex(&public, false));
+		}
+		println!("Session keys: {}", to_hex(&keys, false));
+		match set_keys_call(&metadata, &keys) {
+			Some(call) => {
+				println!(
+					"Register them by submitting the following SCALE encoded call from the \
+					controller account:",
+				);
+				println!("{}", to_hex(&call, false));
+			},
+			None => println!(
+				"The runtime has no `session.set_keys` call or its metadata isn't supported, \
+				the call registering the keys can't be encoded.",
+			),
+		}
+		Ok(())
+	}
+}
+
+impl CliConfiguration for RotateKeysCmd {
+	fn shared_params(&self) -> &SharedParams {
+		&self.shared_params
+	}
+}
+
+/// SCALE encode the `Session::set_keys` call registering the SCALE encoded session `keys`,
+/// with the indices of the pallet and the call found in the SCALE encoded runtime `metadata`.
+///
+/// The calls are indexed by the position of their pallet among the pallets declaring calls. The
+/// ownership proof isn't checked by the session pallet, so it is left empty.
+fn set_keys_call(metadata: &[u8], keys: &[u8]) -> Option<Vec<u8>> {
+	let modules = match RuntimeMetadataPrefixed::decode(&mut &metadata[..]).ok()?.1 {
+		RuntimeMetadata::V11(metadata) => metadata.modules,
+		_ => return None,
+	};
+	let modules = match modules {
+		DecodeDifferent::Decoded(modules) => modules,
+		DecodeDifferent::Encode(_) => return None,
+	};
+
+	let (pallet_index, calls) = modules.into_iter()
+		.filter_map(|ModuleMetadata { name, calls, .. }| calls.map(|calls| (name, calls)))
+		.enumerate()
+		.find(|(_, (name, _))| *name == DecodeDifferent::Decoded("Session".into()))
+		.map(|(index, (_, calls))| (index, calls))?;
+	let calls = match calls {
+		DecodeDifferent::Decoded(calls) => calls,
+		DecodeDifferent::Encode(_) => return None,
+	};
+	let call_index = calls.iter()
+		.position(|call| call.name == DecodeDifferent::Decoded("set_keys".into()))?;
+
+	let mut call = vec![pallet_index as u8, call_index as u8];
+	call.extend(keys);
+	Compact(0u32).encode_to(&mut call);
+	Some(call)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use frame_metadata::{
+		ExtrinsicMetadata, FnEncode, FunctionMetadata, RuntimeMetadataV11, META_RESERVED,
+	};
+
+	fn call(name: &'static str) -> FunctionMetadata {
+		FunctionMetadata {
+			name: DecodeDifferent::Encode(name),
+			arguments: DecodeDifferent::Encode(&[]),
+			documentation: DecodeDifferent::Encode(&[]),
+		}
+	}
+
+	fn session_calls() -> &'static [FunctionMetadata] {
+		Box::leak(vec![call("purge_keys"), call("set_keys")].into_boxed_slice())
+	}
+
+	fn system_calls() -> &'static [FunctionMetadata] {
+		Box::leak(vec![call("remark")].into_boxed_slice())
+	}
+
+	fn module(
+		name: &'static str,
+		calls: Option<fn() -> &'static [FunctionMetadata]>,
+	) -> ModuleMetadata {
+		ModuleMetadata {
+			name: DecodeDifferent::Encode(name),
+			storage: None,
+			calls: calls.map(|calls| DecodeDifferent::Encode(FnEncode(calls))),
+			event: None,
+			constants: DecodeDifferent::Encode(FnEncode(|| &[])),
+			errors: DecodeDifferent::Encode(FnEncode(|| &[])),
+		}
+	}
+
+	fn metadata(modules: Vec<ModuleMetadata>) -> Vec<u8> {
+		RuntimeMetadataPrefixed(META_RESERVED, RuntimeMetadata::V11(RuntimeMetadataV11 {
+			modules: DecodeDifferent::Encode(Box::leak(modules.into_boxed_slice())),
+			extrinsic: ExtrinsicMetadata { version: 4, signed_extensions: Vec::new() },
+		})).encode()
+	}
+
+	#[test]
+	fn encodes_set_keys_call() {
+		let metadata = metadata(vec![
+			module("System", Some(system_calls)),
+			module("Authorship", None),
+			module("Session", Some(session_calls)),
+		]);
+		assert_eq!(set_keys_call(&metadata, &[1, 2]), Some(vec![1, 1, 1, 2, 0]));
+	}
+
+	#[test]
+	fn set_keys_call_requires_session_pallet() {
+		let metadata = metadata(vec![module("System", Some(system_calls))]);
+		assert_eq!(set_keys_call(&metadata, &[1, 2]), None);
+		assert_eq!(set_keys_call(&[], &[1, 2]), None);
+	}
+}
@@ -16,7 +16,7 @@
 // You should have received a copy of the GNU General Public License
 // along with this program. If not, see <https://www.gnu.org/licenses/>.
 
-use crate::arg_enums::RpcMethods;
+use crate::arg_enums::{JustificationPolicy, Profile, RpcMethods, SlowSubscriberPolicy};
 use crate::error::{Error, Result};
 use crate::params::ImportParams;
 use crate::params::KeystoreParams;
@@ -25,15 +25,28 @@ use crate::params::SharedParams;
 use crate::params::TransactionPoolParams;
 use crate::params::OffchainWorkerParams;
 use crate::CliConfiguration;
+use crate::config::{
+	default_justification_workers, grandpa_gossip_duration_property,
+	DEFAULT_GRANDPA_GOSSIP_DURATION, DEFAULT_PROMETHEUS_PORT, DEFAULT_RPC_HTTP_PORT,
+	DEFAULT_RPC_WS_PORT,
+};
 use regex::Regex;
 use sc_service::{
-	config::{MultiaddrWithPeerId, PrometheusConfig, TransactionPoolOptions},
+	config::{
+		DiskSpaceWatchdogConfig, MultiaddrWithPeerId, NotificationConfig, PeerId,
+		PoolMemoryLimitsConfig, PrometheusConfig, PrometheusPushConfig, TransactionPoolOptions,
+	},
 	ChainSpec, Role,
 };
-use sc_telemetry::TelemetryEndpoints;
+use sc_telemetry::{TelemetryAuth, TelemetryClientCertificate, TelemetryEndpoints};
 use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::path::PathBuf;
+use std::time::Duration;
 use structopt::StructOpt;
 
+/// Time between two checks of the free space on the volume of the database.
+const DISK_SPACE_CHECK_INTERVAL: Duration = Duration::from_secs(10);
+
 /// The `run` command used to run a node.
 #[derive(Debug, StructOpt, Clone)]
 pub struct RunCmd {
@@ -68,21 +81,89 @@ pub struct RunCmd {
 	#[structopt(long)]
 	pub no_grandpa: bool,
 
+	/// Which finalized blocks get a GRANDPA justification, besides the blocks enacting authority
+	/// set changes, which always do: every block, one every `--grandpa-justification-period`
+	/// blocks, or no other block.
+	///
+	/// Nodes serving light clients or bridges need the justifications, minimal validators don't.
+	#[structopt(
+		long = "grandpa-justifications",
+		value_name = "POLICY",
+		possible_values = &JustificationPolicy::variants(),
+		case_insensitive = true,
+		default_value = "Periodic"
+	)]
+	pub grandpa_justifications: JustificationPolicy,
+
+	/// Generate and store a GRANDPA justification at least every this many blocks, with
+	/// `--grandpa-justifications Periodic`.
+	///
+	/// Justifications are needed by light clients and bridges to prove finality. Lower values
+	/// make finality proofs shorter at the cost of more justifications being stored.
+	#[structopt(
+		long = "grandpa-justification-period",
+		value_name = "BLOCKS",
+		default_value = "512"
+	)]
+	pub grandpa_justification_period: u32,
+
+	/// Request from the peers the justifications of the blocks imported without one, for the
+	/// blocks selected by `--grandpa-justifications`, so that the node has them as well.
+	///
+	/// Only periodic justifications can be requested, requesting one per block would slow the
+	/// sync down.
+	#[structopt(long = "grandpa-request-justifications")]
+	pub grandpa_request_justifications: bool,
+
+	/// Number of threads checking the signatures of the imported GRANDPA justifications. With 0,
+	/// they are checked by the thread importing the blocks.
+	///
+	/// Defaults to the number of CPUs, or to 0 with `--sequential-signature-verification`.
+	#[structopt(long = "grandpa-justification-workers", value_name = "COUNT")]
+	pub grandpa_justification_workers: Option<usize>,
+
+	/// Duration (in milliseconds) of a GRANDPA gossip round step.
+	///
+	/// Prevotes are cast after twice this duration and precommits after four times it. Small
+	/// networks with fast block times can use shorter rounds. All the voters must use the same
+	/// duration, so this can't differ from the `grandpaGossipDuration` property of the chain
+	/// spec. Defaults to that property, or 333 ms.
+	#[structopt(long = "grandpa-gossip-duration", value_name = "MILLISECONDS")]
+	pub grandpa_gossip_duration: Option<u64>,
+
 	/// Experimental: Run in light client mode.
+	///
+	/// Light clients only import headers and fetch state on demand from full nodes. Their
+	/// database is kept apart from the one of full nodes and they default to the `light`
+	/// profile.
 	#[structopt(long = "light", conflicts_with = "sentry")]
 	pub light: bool,
 
+	/// Adjust the defaults of other parameters to a common kind of deployment.
+	///
+	/// `validator` implies `--validator` and `light` implies `--light`. Parameters that are
+	/// passed explicitly always take precedence over the defaults of the profile.
+	#[structopt(
+		long = "profile",
+		value_name = "PROFILE",
+		possible_values = &Profile::variants(),
+		case_insensitive = true,
+	)]
+	pub profile: Option<Profile>,
+
 	/// Listen to all RPC interfaces.
 	///
 	/// Default is local. Note: not all RPC methods are safe to be exposed publicly. Use an RPC proxy
 	/// server to filter out dangerous methods. More details: https://github.com/paritytech/substrate/wiki/Public-RPC.
-	/// Use `--unsafe-rpc-external` to suppress the warning if you understand the risks.
+	/// The node refuses to start if it is a validator or unsafe RPC methods are exposed, as with
+	/// `--rpc-methods=unsafe`, unless `--unsafe-rpc-external` is used.
 	#[structopt(long = "rpc-external")]
 	pub rpc_external: bool,
 
-	/// Listen to all RPC interfaces.
+	/// Listen to all RPC interfaces, even if the node is a validator or unsafe RPC methods are
+	/// exposed on them.
 	///
-	/// Same as `--rpc-external`.
+	/// The node refuses to listen on an external interface in these cases otherwise.
 	#[structopt(long)]
 	pub unsafe_rpc_external: bool,
 
@@ -102,17 +183,72 @@ pub struct RunCmd {
 	)]
 	pub rpc_methods: RpcMethods,
 
+	/// Serve only finalized data over RPC.
+	///
+	/// The requests that don't specify a block are served the latest finalized block rather than
+	/// the best block, the new heads subscription follows the finalized heads, and the blocks that
+	/// aren't finalized yet are only served when requested by hash.
+	#[structopt(long = "rpc-finalized-only")]
+	pub rpc_finalized_only: bool,
+
+	/// Keep the heads announced to the RPC subscriptions for this many seconds.
+	///
+	/// A client reconnecting within that period can pass the hash of the last header it received
+	/// when subscribing to heads again, and is sent the headers it missed rather than the current
+	/// head. Resuming from a header that isn't kept anymore fails.
+	///
+	/// The storage subscriptions are resumed from the state of the last block received instead,
+	/// as long as it isn't pruned.
+	#[structopt(long = "rpc-subscription-replay", value_name = "SECONDS")]
+	pub rpc_subscription_replay: Option<u64>,
+
+	/// Interrupt the runtime calls made on behalf of RPC requests, such as `state_call`, after
+	/// this many milliseconds.
+	///
+	/// When set, these calls are always executed in wasm, which is the only execution that can be
+	/// interrupted.
+	#[structopt(long = "rpc-call-timeout", value_name = "MILLISECONDS")]
+	pub rpc_call_timeout: Option<u64>,
+
+	/// Allow the peer with this id to call `system_health`, `system_peers` and
+	/// `author_rotateKeys` over the network, even without any RPC server. Can be given multiple
+	/// times.
+	///
+	/// The calls are tunneled through the connection to the peer, which authenticates it.
+	#[structopt(long = "admin-rpc-peer", value_name = "PEER_ID", number_of_values = 1)]
+	pub admin_rpc_peers: Vec<PeerId>,
+
+	/// Warn when the free space on the volume of the database drops below this many MiB.
+	#[structopt(long = "db-min-free-space", value_name = "MiB", default_value = "1024")]
+	pub db_min_free_space: u64,
+
+	/// Stop accepting transactions when the free space on the volume of the database drops
+	/// below this many MiB.
+	#[structopt(long = "db-critical-free-space", value_name = "MiB", default_value = "256")]
+	pub db_critical_free_space: u64,
+
+	/// Stop the node when the free space on the volume of the database drops below
+	/// `--db-critical-free-space`, before the database gets corrupted by a full disk.
+	#[structopt(long = "halt-on-critical-disk-space")]
+	pub halt_on_critical_disk_space: bool,
+
+	/// Do not watch the free space on the volume of the database.
+	#[structopt(long = "disable-disk-space-watchdog")]
+	pub disable_disk_space_watchdog: bool,
+
 	/// Listen to all Websocket interfaces.
 	///
 	/// Default is local. Note: not all RPC methods are safe to be exposed publicly. Use an RPC proxy
 	/// server to filter out dangerous methods. More details: https://github.com/paritytech/substrate/wiki/Public-RPC.
-	/// Use `--unsafe-ws-external` to suppress the warning if you understand the risks.
+	/// The node refuses to start if it is a validator or unsafe RPC methods are exposed, as with
+	/// `--rpc-methods=unsafe`, unless `--unsafe-ws-external` is used.
 	#[structopt(long = "ws-external")]
 	pub ws_external: bool,
 
-	/// Listen to all Websocket interfaces.
+	/// Listen to all Websocket interfaces, even if the node is a validator or unsafe RPC methods
+	/// are exposed on them.
 	///
-	/// Same as `--ws-external` but doesn't warn you about it.
+	/// The node refuses to listen on an external interface in these cases otherwise.
 	#[structopt(long = "unsafe-ws-external")]
 	pub unsafe_ws_external: bool,
 
@@ -134,6 +270,15 @@ pub struct RunCmd {
 	#[structopt(long = "ws-max-connections", value_name = "COUNT")]
 	pub ws_max_connections: Option<usize>,
 
+	/// Close the WS RPC connections on which no request was received and no subscription
+	/// notification was sent for this many seconds.
+	///
+	/// Connections are otherwise only closed by the clients, or once their TCP connection fails.
+	/// The connections on which nothing was sent for half this time are sent an `rpc_ping`
+	/// notification, so that the proxies in between don't close them.
+	#[structopt(long = "ws-idle-timeout", value_name = "SECONDS")]
+	pub ws_idle_timeout: Option<u64>,
+
 	/// Specify browser Origins allowed to access the HTTP & WS RPC servers.
 	///
 	/// A comma-separated list of origins (protocol://domain or special `null`
@@ -143,6 +288,15 @@ pub struct RunCmd {
 	#[structopt(long = "rpc-cors", value_name = "ORIGINS", parse(try_from_str = parse_cors))]
 	pub rpc_cors: Option<Cors>,
 
+	/// Specify the hosts allowed in the `Host` header of requests to the HTTP & WS RPC servers.
+	///
+	/// A comma-separated list of hosts (`domain` or `domain:port`, `*` wildcards are supported),
+	/// to protect against DNS rebinding attacks. The listening address is always allowed. Value
+	/// of `all` will disable host validation. Default is to only allow the listening address,
+	/// unless all origins are allowed by `--rpc-cors`.
+	#[structopt(long = "rpc-allowed-hosts", value_name = "HOSTS", parse(try_from_str = parse_cors))]
+	pub rpc_allowed_hosts: Option<Cors>,
+
 	/// Specify Prometheus data source server TCP Port.
 	#[structopt(long = "prometheus-port", value_name = "PORT")]
 	pub prometheus_port: Option<u16>,
@@ -153,6 +307,43 @@ pub struct RunCmd {
 	#[structopt(long = "no-prometheus")]
 	pub no_prometheus: bool,
 
+	/// Periodically push the Prometheus metrics to the Pushgateway at the given URL.
+	///
+	/// Useful for nodes that can't be scraped, e.g. because they are behind a NAT.
+	#[structopt(
+		long = "prometheus-push-gateway",
+		value_name = "URL",
+		conflicts_with = "no-prometheus"
+	)]
+	pub prometheus_push_gateway: Option<String>,
+
+	/// Value of the `job` label of the metrics pushed to the Pushgateway.
+	#[structopt(long = "prometheus-push-job", value_name = "NAME", default_value = "substrate")]
+	pub prometheus_push_job: String,
+
+	/// Value of the `instance` label of the metrics pushed to the Pushgateway.
+	///
+	/// Defaults to the node name.
+	#[structopt(long = "prometheus-push-instance", value_name = "NAME")]
+	pub prometheus_push_instance: Option<String>,
+
+	/// Number of seconds between two pushes to the Pushgateway.
+	#[structopt(long = "prometheus-push-interval", value_name = "SECONDS", default_value = "15")]
+	pub prometheus_push_interval: u64,
+
+	/// Label of the node given as `KEY=VALUE`, e.g. `env=staging`. Can be given multiple times.
+	///
+	/// The labels are attached to all the Prometheus metrics of the node and sent to the
+	/// telemetry, to tell apart the nodes of different deployments. The names of the labels of
+	/// the metrics, e.g. `chain` or `status`, are refused.
+	#[structopt(
+		long = "node-labels",
+		value_name = "KEY=VALUE",
+		number_of_values = 1,
+		parse(try_from_str = parse_node_label)
+	)]
+	pub node_labels: Vec<(String, String)>,
+
 	/// The human-readable name for this node.
 	///
 	/// The node name will be reported to the telemetry server, if enabled.
@@ -174,6 +365,66 @@ pub struct RunCmd {
 	#[structopt(long = "telemetry-url", value_name = "URL VERBOSITY", parse(try_from_str = parse_telemetry_endpoints))]
 	pub telemetry_endpoints: Vec<(String, u8)>,
 
+	/// Trust the DER encoded certificate authority in the given file when connecting to `wss`
+	/// telemetry servers.
+	///
+	/// Can be passed multiple times, e.g. for private servers with self-signed certificates.
+	#[structopt(long = "telemetry-ca", value_name = "PATH", parse(from_os_str))]
+	pub telemetry_ca: Vec<PathBuf>,
+
+	/// Authenticate at the telemetry servers with the bearer token read from the given file.
+	///
+	/// The token is sent in the `Authorization` header of the WebSocket handshake.
+	#[structopt(long = "telemetry-token-file", value_name = "PATH", parse(from_os_str))]
+	pub telemetry_token_file: Option<PathBuf>,
+
+	/// Present the DER encoded certificate in the given file to the `wss` telemetry servers
+	/// requiring mutual TLS.
+	#[structopt(
+		long = "telemetry-client-cert",
+		value_name = "PATH",
+		parse(from_os_str),
+		requires = "telemetry-client-key"
+	)]
+	pub telemetry_client_cert: Option<PathBuf>,
+
+	/// DER encoded private key of the `--telemetry-client-cert` certificate.
+	#[structopt(
+		long = "telemetry-client-key",
+		value_name = "PATH",
+		parse(from_os_str),
+		requires = "telemetry-client-cert"
+	)]
+	pub telemetry_client_key: Option<PathBuf>,
+
+	/// Maximum number of block import and finality notifications queued for a single RPC
+	/// subscription. The node's own tasks are never limited.
+	#[structopt(long = "notification-capacity", value_name = "COUNT", default_value = "1024")]
+	pub notification_capacity: usize,
+
+	/// What to do with the notifications for a subscriber whose queue is full: drop them, or
+	/// disconnect the subscriber.
+	#[structopt(
+		long = "slow-subscriber-policy",
+		value_name = "POLICY",
+		possible_values = &SlowSubscriberPolicy::variants(),
+		case_insensitive = true,
+		default_value = "Drop"
+	)]
+	pub slow_subscriber_policy: SlowSubscriberPolicy,
+
+	/// Keep the storage changes of each imported block, for the `state_subscribeStorageDiffs`
+	/// RPC subscribers and the indexers running in the node process.
+	#[structopt(long = "storage-diffs")]
+	pub storage_diffs: bool,
+
+	/// Record the blocks imported and finalized by the node and the gossip messages it receives
+	/// to this file, appending to it.
+	///
+	/// The log can be replayed offline with the `replay` subcommand, to debug finality stalls.
+	#[structopt(long = "replay-log", value_name = "PATH", parse(from_os_str))]
+	pub replay_log: Option<PathBuf>,
+
 	#[allow(missing_docs)]
 	#[structopt(flatten)]
 	pub offchain_worker_params: OffchainWorkerParams,
@@ -250,6 +501,35 @@ pub struct RunCmd {
 		conflicts_with_all = &[ "sentry", "public-addr" ]
 	)]
 	pub sentry_nodes: Vec<MultiaddrWithPeerId>,
+
+	/// Refuse to start if the configuration contains combinations of settings that are most
+	/// likely a mistake, instead of only warning about them.
+	#[structopt(long = "strict-config")]
+	pub strict_config: bool,
+
+	/// Start even if the keystore or node key files are accessible by other users than their
+	/// owner, only warning about them.
+	#[structopt(long = "insecure-keystore-permissions")]
+	pub insecure_keystore_permissions: bool,
+
+	/// On shutdown, wait up to the given number of seconds for the authorship of a block in the
+	/// current slot to complete.
+	///
+	/// Minimizes the slots missed by validators during rolling restarts.
+	#[structopt(long = "authoring-shutdown-timeout", value_name = "SECONDS")]
+	pub authoring_shutdown_timeout: Option<u64>,
+
+	/// Number of threads futures of the node run on.
+	///
+	/// Defaults to the number of CPUs, at least 2 and at most 16 (4 for light clients).
+	#[structopt(long = "runtime-threads", value_name = "COUNT")]
+	pub runtime_threads: Option<usize>,
+
+	/// Maximum number of threads running blocking tasks, such as the block import.
+	///
+	/// Defaults to 64, or 16 for light clients.
+	#[structopt(long = "blocking-threads", value_name = "COUNT")]
+	pub blocking_threads: Option<usize>,
 }
 
 impl RunCmd {
@@ -277,6 +557,16 @@ impl RunCmd {
 			None
 		}
 	}
+
+	/// Whether the node runs as a validator, either with `--validator` or the validator profile.
+	pub fn is_validator(&self) -> bool {
+		self.validator || self.profile == Some(Profile::Validator)
+	}
+
+	/// Whether the node runs as a light client, either with `--light` or the light profile.
+	pub fn is_light(&self) -> bool {
+		self.light || self.profile == Some(Profile::Light)
+	}
 }
 
 impl CliConfiguration for RunCmd {
@@ -319,7 +609,7 @@ impl CliConfiguration for RunCmd {
 
 	fn dev_key_seed(&self, is_dev: bool) -> Result<Option<String>> {
 		Ok(self.get_keyring().map(|a| format!("//{}", a)).or_else(|| {
-			if is_dev && !self.light {
+			if is_dev && !self.is_light() {
 				Some("//Alice".into())
 			} else {
 				None
@@ -343,10 +633,48 @@ impl CliConfiguration for RunCmd {
 		})
 	}
 
+	fn client_notifications(&self) -> Result<NotificationConfig> {
+		Ok(NotificationConfig {
+			capacity: self.notification_capacity,
+			slow_subscriber_policy: self.slow_subscriber_policy.into(),
+		})
+	}
+
+	fn storage_diffs(&self) -> Result<bool> {
+		Ok(self.storage_diffs)
+	}
+
+	fn replay_log(&self) -> Result<Option<PathBuf>> {
+		Ok(self.replay_log.clone())
+	}
+
+	fn telemetry_auth(&self) -> Result<TelemetryAuth> {
+		let trusted_certificates = self.telemetry_ca.iter()
+			.map(std::fs::read)
+			.collect::<std::io::Result<Vec<_>>>()?;
+		let client_certificate = match (&self.telemetry_client_cert, &self.telemetry_client_key) {
+			(Some(cert), Some(key)) => Some(TelemetryClientCertificate {
+				certificates: vec![std::fs::read(cert)?],
+				private_key: std::fs::read(key)?,
+			}),
+			_ => None,
+		};
+		let bearer_token = match self.telemetry_token_file {
+			Some(ref path) => Some(std::fs::read_to_string(path)?.trim().to_owned()),
+			None => None,
+		};
+
+		Ok(TelemetryAuth { trusted_certificates, client_certificate, bearer_token })
+	}
+
+	fn profile(&self) -> Result<Option<Profile>> {
+		Ok(self.profile.or_else(|| if self.light { Some(Profile::Light) } else { None }))
+	}
+
 	fn role(&self, is_dev: bool) -> Result<Role> {
 		let keyring = self.get_keyring();
-		let is_light = self.light;
-		let is_authority = (self.validator || is_dev || keyring.is_some()) && !is_light;
+		let is_light = self.is_light();
+		let is_authority = (self.is_validator() || is_dev || keyring.is_some()) && !is_light;
 
 		Ok(if is_light {
 			sc_service::Role::Light
@@ -363,6 +691,32 @@ impl CliConfiguration for RunCmd {
 		})
 	}
 
+	fn strict_config(&self) -> Result<bool> {
+		Ok(self.strict_config)
+	}
+
+	fn insecure_keystore_permissions(&self) -> Result<bool> {
+		Ok(self.insecure_keystore_permissions)
+	}
+
+	fn authoring_shutdown_timeout(&self) -> Result<Option<Duration>> {
+		Ok(self.authoring_shutdown_timeout.map(Duration::from_secs))
+	}
+
+	fn runtime_threads(&self) -> Result<Option<usize>> {
+		if self.runtime_threads == Some(0) {
+			return Err(Error::Input("--runtime-threads must be at least 1".into()));
+		}
+		Ok(self.runtime_threads)
+	}
+
+	fn blocking_threads(&self) -> Result<Option<usize>> {
+		if self.blocking_threads == Some(0) {
+			return Err(Error::Input("--blocking-threads must be at least 1".into()));
+		}
+		Ok(self.blocking_threads)
+	}
+
 	fn force_authoring(&self) -> Result<bool> {
 		// Imply forced authoring on --dev
 		Ok(self.shared_params.dev || self.force_authoring)
@@ -378,9 +732,19 @@ impl CliConfiguration for RunCmd {
 				Ipv4Addr::LOCALHOST
 			};
 
-			Some(PrometheusConfig::new_with_default_registry(
-				SocketAddr::new(interface.into(), self.prometheus_port.unwrap_or(9615))
-			))
+			let mut config = PrometheusConfig::new_with_default_registry(
+				SocketAddr::new(interface.into(), self.prometheus_port.unwrap_or(DEFAULT_PROMETHEUS_PORT))
+			);
+			config.push_gateway = self.prometheus_push_gateway.clone().map(|url| {
+				PrometheusPushConfig {
+					url,
+					job: self.prometheus_push_job.clone(),
+					instance: self.prometheus_push_instance.clone(),
+					interval: Duration::from_secs(self.prometheus_push_interval),
+				}
+			});
+
+			Some(config)
 		})
 	}
 
@@ -388,10 +752,65 @@ impl CliConfiguration for RunCmd {
 		Ok(self.no_grandpa)
 	}
 
+	fn grandpa_justification_period(&self) -> Result<Option<u32>> {
+		if self.grandpa_justification_period == 0 {
+			return Err(Error::Input(
+				"--grandpa-justification-period must be greater than 0".into()
+			));
+		}
+
+		Ok(match self.grandpa_justifications {
+			JustificationPolicy::EveryBlock => Some(1),
+			JustificationPolicy::Periodic => Some(self.grandpa_justification_period),
+			JustificationPolicy::OnDemand => None,
+		})
+	}
+
+	fn grandpa_request_justifications(&self) -> Result<bool> {
+		if self.grandpa_request_justifications
+			&& self.grandpa_justifications != JustificationPolicy::Periodic
+		{
+			return Err(Error::Input(
+				"--grandpa-request-justifications requires periodic justifications".into()
+			));
+		}
+
+		Ok(self.grandpa_request_justifications)
+	}
+
+	fn grandpa_justification_workers(&self) -> Result<usize> {
+		match self.grandpa_justification_workers {
+			Some(workers) => Ok(workers),
+			None => Ok(default_justification_workers(self.signature_verification()?)),
+		}
+	}
+
+	fn grandpa_gossip_duration(&self, chain_spec: &Box<dyn ChainSpec>) -> Result<Duration> {
+		let from_chain_spec = grandpa_gossip_duration_property(&chain_spec.properties())?;
+
+		match (self.grandpa_gossip_duration.map(Duration::from_millis), from_chain_spec) {
+			(Some(duration), _) if duration == Duration::from_millis(0) => Err(Error::Input(
+				"--grandpa-gossip-duration must be greater than 0".into()
+			)),
+			(Some(duration), Some(expected)) if duration != expected => Err(Error::Input(format!(
+				"--grandpa-gossip-duration of {} ms differs from the {} ms of the chain spec, \
+				all the GRANDPA voters must use the same duration",
+				duration.as_millis(),
+				expected.as_millis(),
+			))),
+			(Some(duration), _) | (None, Some(duration)) => Ok(duration),
+			(None, None) => Ok(DEFAULT_GRANDPA_GOSSIP_DURATION),
+		}
+	}
+
 	fn rpc_ws_max_connections(&self) -> Result<Option<usize>> {
 		Ok(self.ws_max_connections)
 	}
 
+	fn rpc_ws_idle_timeout(&self) -> Result<Option<Duration>> {
+		Ok(self.ws_idle_timeout.map(Duration::from_secs))
+	}
+
 	fn rpc_cors(&self, is_dev: bool) -> Result<Option<Vec<String>>> {
 		Ok(self
 			.rpc_cors
@@ -413,34 +832,92 @@ impl CliConfiguration for RunCmd {
 			.into())
 	}
 
+	fn rpc_allowed_hosts(&self, is_dev: bool) -> Result<Option<Vec<String>>> {
+		match self.rpc_allowed_hosts.clone() {
+			Some(hosts) => Ok(hosts.into()),
+			None => Ok(self.rpc_cors(is_dev)?.map(|_| Vec::new())),
+		}
+	}
+
 	fn rpc_http(&self) -> Result<Option<SocketAddr>> {
 		let interface = rpc_interface(
-			self.rpc_external,
+			self.rpc_external || self.profile()?.map_or(false, Profile::rpc_external),
 			self.unsafe_rpc_external,
-			self.rpc_methods,
-			self.validator
-		)?;
+		);
 
-		Ok(Some(SocketAddr::new(interface, self.rpc_port.unwrap_or(9933))))
+		Ok(Some(SocketAddr::new(interface, self.rpc_port.unwrap_or(DEFAULT_RPC_HTTP_PORT))))
 	}
 
 	fn rpc_ws(&self) -> Result<Option<SocketAddr>> {
 		let interface = rpc_interface(
-			self.ws_external,
+			self.ws_external || self.profile()?.map_or(false, Profile::rpc_external),
 			self.unsafe_ws_external,
-			self.rpc_methods,
-			self.validator
-		)?;
+		);
 
-		Ok(Some(SocketAddr::new(interface, self.ws_port.unwrap_or(9944))))
+		Ok(Some(SocketAddr::new(interface, self.ws_port.unwrap_or(DEFAULT_RPC_WS_PORT))))
 	}
 
 	fn rpc_methods(&self) -> Result<sc_service::config::RpcMethods> {
 		Ok(self.rpc_methods.into())
 	}
 
+	fn rpc_finalized_only(&self) -> Result<bool> {
+		Ok(self.rpc_finalized_only)
+	}
+
+	fn rpc_subscription_replay(&self) -> Result<Option<Duration>> {
+		Ok(self.rpc_subscription_replay.map(Duration::from_secs))
+	}
+
+	fn rpc_call_timeout(&self) -> Result<Option<Duration>> {
+		Ok(self.rpc_call_timeout.map(Duration::from_millis))
+	}
+
+	fn admin_rpc_peers(&self) -> Result<Vec<PeerId>> {
+		Ok(self.admin_rpc_peers.clone())
+	}
+
+	fn node_labels(&self) -> Result<Vec<(String, String)>> {
+		let mut keys = std::collections::HashSet::new();
+		if let Some((key, _)) = self.node_labels.iter().find(|(key, _)| !keys.insert(key)) {
+			return Err(Error::Input(format!("Node label {:?} is given multiple times", key)));
+		}
+
+		Ok(self.node_labels.clone())
+	}
+
+	fn disk_space_watchdog(&self) -> Result<Option<DiskSpaceWatchdogConfig>> {
+		if self.disable_disk_space_watchdog {
+			return Ok(None);
+		}
+		if self.db_critical_free_space > self.db_min_free_space {
+			return Err(Error::Input(
+				"--db-critical-free-space must not exceed --db-min-free-space".into(),
+			));
+		}
+
+		Ok(Some(DiskSpaceWatchdogConfig {
+			soft_limit: self.db_min_free_space << 20,
+			hard_limit: self.db_critical_free_space << 20,
+			halt: self.halt_on_critical_disk_space,
+			interval: DISK_SPACE_CHECK_INTERVAL,
+		}))
+	}
+
+	fn unsafe_rpc_external(&self) -> Result<bool> {
+		Ok(self.unsafe_rpc_external)
+	}
+
+	fn unsafe_ws_external(&self) -> Result<bool> {
+		Ok(self.unsafe_ws_external)
+	}
+
 	fn transaction_pool(&self) -> Result<TransactionPoolOptions> {
-		Ok(self.pool_config.transaction_pool())
+		Ok(self.pool_config.transaction_pool(self.profile()?))
+	}
+
+	fn pool_memory_limits(&self) -> Result<Option<PoolMemoryLimitsConfig>> {
+		self.pool_config.pool_memory_limits()
 	}
 
 	fn max_runtime_instances(&self) -> Result<Option<usize>> {
@@ -470,33 +947,11 @@ pub fn is_node_name_valid(_name: &str) -> std::result::Result<(), &str> {
 	Ok(())
 }
 
-fn rpc_interface(
-	is_external: bool,
-	is_unsafe_external: bool,
-	rpc_methods: RpcMethods,
-	is_validator: bool,
-) -> Result<IpAddr> {
-	if is_external && is_validator && rpc_methods != RpcMethods::Unsafe {
-		return Err(Error::Input(
-			"--rpc-external and --ws-external options shouldn't be \
-		used if the node is running as a validator. Use `--unsafe-rpc-external` \
-		or `--rpc-methods=unsafe` if you understand the risks. See the options \
-		description for more information."
-				.to_owned(),
-		));
-	}
-
+fn rpc_interface(is_external: bool, is_unsafe_external: bool) -> IpAddr {
 	if is_external || is_unsafe_external {
-		if rpc_methods == RpcMethods::Unsafe {
-			log::warn!(
-				"It isn't safe to expose RPC publicly without a proxy server that filters \
-			available set of RPC methods."
-			);
-		}
-
-		Ok(Ipv4Addr::UNSPECIFIED.into())
+		Ipv4Addr::UNSPECIFIED.into()
 	} else {
-		Ok(Ipv4Addr::LOCALHOST.into())
+		Ipv4Addr::LOCALHOST.into()
 	}
 }
 
@@ -531,6 +986,35 @@ fn parse_telemetry_endpoints(s: &str) -> std::result::Result<(String, u8), Telem
 	}
 }
 
+/// The names of the labels of the metrics of the node, which a node label added to all of them
+/// would clash with. `chain` is added to the metrics of every chain along the node labels.
+const RESERVED_LABEL_NAMES: &[&str] = &[
+	"action", "cache", "chain", "direction", "entity", "event_name", "fd_type", "kind", "message",
+	"name", "outcome", "over", "protocol", "reason", "result", "status", "stream", "subsystem",
+	"subtype", "task_name", "trie", "version",
+];
+
+/// Parse a node label given as `KEY=VALUE`, where the key is a valid Prometheus label name.
+fn parse_node_label(s: &str) -> std::result::Result<(String, String), String> {
+	let pos = s.find('=').ok_or_else(|| format!("Expected KEY=VALUE, found {:?}", s))?;
+	let (key, value) = (&s[..pos], &s[pos + 1..]);
+
+	let is_valid = key.chars().enumerate()
+		.all(|(i, c)| c == '_' || c.is_ascii_alphabetic() || (i > 0 && c.is_ascii_digit()));
+	if key.is_empty() || !is_valid || key.starts_with("__") {
+		return Err(format!(
+			"Invalid node label {:?}: expected letters, digits and underscores, \
+			not starting with a digit or `__`",
+			key,
+		));
+	}
+	if RESERVED_LABEL_NAMES.contains(&key) {
+		return Err(format!("The node label {:?} is reserved for the labels of the metrics", key));
+	}
+
+	Ok((key.into(), value.into()))
+}
+
 /// CORS setting
 ///
 /// The type is introduced to overcome `Option<Option<T>>`
@@ -552,7 +1036,7 @@ impl From<Cors> for Option<Vec<String>> {
 	}
 }
 
-/// Parse cors origins.
+/// Parse cors origins, or allowed hosts.
 fn parse_cors(s: &str) -> std::result::Result<Cors, Box<dyn std::error::Error>> {
 	let mut is_all = false;
 	let mut origins = Vec::new();
@@ -591,4 +1075,115 @@ mod tests {
 		assert!(is_node_name_valid("www.visit.me").is_err());
 		assert!(is_node_name_valid("email@domain").is_err());
 	}
+
+	#[test]
+	fn profile_adjusts_defaults() {
+		let cmd = RunCmd::from_iter(&["substrate", "--profile", "rpc", "--pool-limit", "100"]);
+		assert!(!cmd.is_validator());
+		assert_eq!(cmd.trie_cache_size().unwrap(), Profile::Rpc.cache_size());
+		assert_eq!(cmd.transaction_pool().unwrap().ready.count, 100);
+		assert_eq!(
+			cmd.transaction_pool().unwrap().ready.total_bytes,
+			Profile::Rpc.pool_limits().1 * 1024,
+		);
+		assert!(cmd.rpc_http().unwrap().unwrap().ip().is_unspecified());
+
+		let cmd = RunCmd::from_iter(&["substrate", "--profile", "light"]);
+		assert!(cmd.is_light());
+		assert!(matches!(cmd.role(false).unwrap(), Role::Light));
+
+		let cmd = RunCmd::from_iter(&["substrate", "--light"]);
+		assert_eq!(cmd.profile().unwrap(), Some(Profile::Light));
+		assert_eq!(cmd.trie_cache_size().unwrap(), Profile::Light.cache_size());
+	}
+
+	#[test]
+	fn allowed_hosts_follow_cors_by_default() {
+		let cmd = RunCmd::from_iter(&["substrate"]);
+		assert_eq!(cmd.rpc_allowed_hosts(false).unwrap(), Some(Vec::new()));
+		assert_eq!(cmd.rpc_allowed_hosts(true).unwrap(), None);
+
+		let cmd = RunCmd::from_iter(&["substrate", "--rpc-cors", "all"]);
+		assert_eq!(cmd.rpc_allowed_hosts(false).unwrap(), None);
+
+		let cmd = RunCmd::from_iter(
+			&["substrate", "--dev", "--rpc-allowed-hosts", "example.com,*.example.com:9933"],
+		);
+		assert_eq!(
+			cmd.rpc_allowed_hosts(true).unwrap(),
+			Some(vec!["example.com".into(), "*.example.com:9933".into()]),
+		);
+
+		let cmd = RunCmd::from_iter(&["substrate", "--rpc-allowed-hosts", "all"]);
+		assert_eq!(cmd.rpc_allowed_hosts(false).unwrap(), None);
+	}
+
+	#[test]
+	fn parses_node_labels() {
+		assert_eq!(parse_node_label("env=staging"), Ok(("env".into(), "staging".into())));
+		assert_eq!(parse_node_label("region_2="), Ok(("region_2".into(), "".into())));
+		assert_eq!(parse_node_label("url=a=b"), Ok(("url".into(), "a=b".into())));
+		assert!(parse_node_label("env").is_err());
+		assert!(parse_node_label("=staging").is_err());
+		assert!(parse_node_label("2env=staging").is_err());
+		assert!(parse_node_label("__env=staging").is_err());
+		assert!(parse_node_label("deploy-env=staging").is_err());
+		assert!(parse_node_label("chain=kusama").is_err());
+		for name in &["reason", "name", "status", "direction", "protocol", "task_name", "version"] {
+			assert!(parse_node_label(&format!("{}=staging", name)).is_err());
+		}
+
+		let cmd = RunCmd::from_iter(
+			&["substrate", "--node-labels", "env=staging", "--node-labels", "region=eu"],
+		);
+		assert_eq!(cmd.node_labels().unwrap(), vec![
+			("env".into(), "staging".into()),
+			("region".into(), "eu".into()),
+		]);
+
+		let cmd = RunCmd::from_iter(
+			&["substrate", "--node-labels", "env=staging", "--node-labels", "env=prod"],
+		);
+		assert!(cmd.node_labels().is_err());
+	}
+
+	#[test]
+	fn only_periodic_justifications_are_requested() {
+		let request = |policy: &str| RunCmd::from_iter(&[
+			"substrate",
+			"--grandpa-justifications",
+			policy,
+			"--grandpa-request-justifications",
+		]).grandpa_request_justifications();
+
+		assert_eq!(request("Periodic").unwrap(), true);
+		assert!(request("EveryBlock").is_err());
+		assert!(request("OnDemand").is_err());
+	}
+
+	#[test]
+	fn justification_workers_follow_signature_verification() {
+		let cmd = RunCmd::from_iter(&["substrate"]);
+		assert_eq!(cmd.grandpa_justification_workers().unwrap(), num_cpus::get());
+
+		let cmd = RunCmd::from_iter(&["substrate", "--sequential-signature-verification"]);
+		assert_eq!(cmd.grandpa_justification_workers().unwrap(), 0);
+
+		let cmd = RunCmd::from_iter(&[
+			"substrate",
+			"--sequential-signature-verification",
+			"--grandpa-justification-workers",
+			"3",
+		]);
+		assert_eq!(cmd.grandpa_justification_workers().unwrap(), 3);
+	}
+
+	#[test]
+	fn read_only_database_is_refused() {
+		let cmd = RunCmd::from_iter(&["substrate", "--db-read-only"]);
+		assert!(cmd.database_read_only().is_err());
+
+		let cmd = RunCmd::from_iter(&["substrate"]);
+		assert_eq!(cmd.database_read_only().unwrap(), false);
+	}
 }
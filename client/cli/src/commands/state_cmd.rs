@@ -0,0 +1,295 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2020 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::{
+	CliConfiguration, error,
+	params::{BlockNumberOrHash, DatabaseParams, PruningParams, SharedParams},
+};
+use codec::Decode;
+use frame_metadata::{DecodeDifferent, RuntimeMetadata, RuntimeMetadataPrefixed};
+use log::{info, warn};
+use sc_service::{Configuration, ServiceBuilderCommand};
+use serde_json::json;
+use sp_core::{bytes::to_hex, hashing::twox_128, storage::well_known_keys};
+use sp_runtime::traits::{Block as BlockT, NumberFor};
+use std::{collections::{BTreeMap, HashMap}, fmt::Debug, str::FromStr};
+use structopt::StructOpt;
+
+/// Length of the prefix shared by all the storage entries of a pallet.
+const PALLET_PREFIX_LEN: usize = 16;
+
+/// The `state` command used to inspect the state of the chain.
+#[derive(Debug, StructOpt, Clone)]
+pub struct StateCmd {
+	#[allow(missing_docs)]
+	#[structopt(subcommand)]
+	pub action: StateAction,
+
+	#[allow(missing_docs)]
+	#[structopt(flatten)]
+	pub shared_params: SharedParams,
+
+	#[allow(missing_docs)]
+	#[structopt(flatten)]
+	pub pruning_params: PruningParams,
+
+	#[allow(missing_docs)]
+	#[structopt(flatten)]
+	pub database_params: DatabaseParams,
+}
+
+/// Operations of the `state` command.
+#[derive(Debug, StructOpt, Clone)]
+pub enum StateAction {
+	/// Print the number of storage entries and their size in bytes for each pallet.
+	///
+	/// The entries are grouped by the prefix of their key, which is named after the pallet it
+	/// belongs to when the runtime metadata knows it. Each child trie is reported separately.
+	Stats {
+		/// Block hash or number whose state is inspected. Defaults to the best block.
+		#[structopt(long = "at", value_name = "HASH or NUMBER")]
+		at: Option<BlockNumberOrHash>,
+
+		/// Print the statistics as JSON rather than a table.
+		#[structopt(long)]
+		json: bool,
+	},
+}
+
+/// Number of storage entries under a key prefix and their size.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+struct PrefixStats {
+	/// Number of storage entries.
+	items: u64,
+	/// Total size of the keys, in bytes.
+	key_bytes: u64,
+	/// Total size of the values, in bytes.
+	value_bytes: u64,
+}
+
+impl PrefixStats {
+	fn note(&mut self, key: &[u8], value: &[u8]) {
+		self.items += 1;
+		self.key_bytes += key.len() as u64;
+		self.value_bytes += value.len() as u64;
+	}
+
+	fn total_bytes(&self) -> u64 {
+		self.key_bytes + self.value_bytes
+	}
+}
+
+impl StateCmd {
+	/// Run the `state` command
+	pub fn run<B, BC, BB>(
+		&self,
+		config: Configuration,
+		builder: B,
+	) -> error::Result<()>
+	where
+		B: FnOnce(Configuration) -> Result<BC, sc_service::error::Error>,
+		BC: ServiceBuilderCommand<Block = BB> + Unpin,
+		BB: BlockT + Debug,
+		<NumberFor<BB> as FromStr>::Err: std::fmt::Debug,
+		BB::Hash: FromStr,
+		<BB::Hash as FromStr>::Err: std::fmt::Debug,
+	{
+		match self.action {
+			StateAction::Stats { ref at, json } => {
+				let block_id = at.clone().map(|b| b.parse()).transpose()?;
+				let builder = builder(config)?;
+
+				let names = match builder.runtime_metadata(block_id.clone()) {
+					Ok(metadata) => pallet_names(&metadata),
+					Err(e) => {
+						warn!("Failed to read the runtime metadata, pallets aren't named: {}", e);
+						HashMap::new()
+					},
+				};
+
+				info!("Reading the state...");
+				let mut stats = BTreeMap::new();
+				builder.for_each_state_entry(block_id, &mut |child, key, value| {
+					note_entry(&mut stats, &names, child, key, value)
+				})?;
+				if json {
+					print_json(&stats)
+				} else {
+					print_table(&stats);
+					Ok(())
+				}
+			},
+		}
+	}
+}
+
+impl CliConfiguration for StateCmd {
+	fn shared_params(&self) -> &SharedParams {
+		&self.shared_params
+	}
+
+	fn pruning_params(&self) -> Option<&PruningParams> {
+		Some(&self.pruning_params)
+	}
+
+	fn database_params(&self) -> Option<&DatabaseParams> {
+		Some(&self.database_params)
+	}
+
+	fn database_read_only(&self) -> error::Result<bool> {
+		self.database_params.database_read_only(true)
+	}
+}
+
+/// Returns the names of the pallets declaring storage in the SCALE encoded `metadata`, by the
+/// prefix of their storage keys.
+fn pallet_names(metadata: &[u8]) -> HashMap<Vec<u8>, String> {
+	let modules = match RuntimeMetadataPrefixed::decode(&mut &metadata[..]) {
+		Ok(RuntimeMetadataPrefixed(_, RuntimeMetadata::V11(metadata))) => metadata.modules,
+		_ => {
+			warn!("Unsupported runtime metadata, pallets aren't named");
+			return HashMap::new();
+		},
+	};
+
+	let mut names = HashMap::new();
+	if let DecodeDifferent::Decoded(modules) = modules {
+		for module in modules {
+			if let Some(DecodeDifferent::Decoded(storage)) = module.storage {
+				if let DecodeDifferent::Decoded(prefix) = storage.prefix {
+					names.insert(twox_128(prefix.as_bytes()).to_vec(), prefix);
+				}
+			}
+		}
+	}
+	names
+}
+
+/// Count a storage entry in `stats`, grouping the entries by pallet, named after `names` or
+/// else the hex encoded prefix.
+///
+/// Well-known keys such as `:code` are reported on their own, and the entries of the child trie
+/// stored under `child` under `child:<storage key>`. The entries of the top trie pointing to the
+/// child tries aren't counted.
+fn note_entry(
+	stats: &mut BTreeMap<String, PrefixStats>,
+	names: &HashMap<Vec<u8>, String>,
+	child: Option<&[u8]>,
+	key: &[u8],
+	value: &[u8],
+) {
+	let group = match child {
+		Some(storage_key) => format!("child:{}", to_hex(storage_key, false)),
+		None if key.starts_with(well_known_keys::DEFAULT_CHILD_STORAGE_KEY_PREFIX) => return,
+		None if key.starts_with(b":") => String::from_utf8_lossy(key).into_owned(),
+		None => {
+			let prefix = &key[..key.len().min(PALLET_PREFIX_LEN)];
+			names.get(prefix).cloned().unwrap_or_else(|| to_hex(prefix, false))
+		},
+	};
+	stats.entry(group).or_default().note(key, value);
+}
+
+/// Returns the groups of `stats` sorted by decreasing size.
+fn by_size(stats: &BTreeMap<String, PrefixStats>) -> Vec<(&String, &PrefixStats)> {
+	let mut sorted = stats.iter().collect::<Vec<_>>();
+	sorted.sort_by(|(_, a), (_, b)| b.total_bytes().cmp(&a.total_bytes()));
+	sorted
+}
+
+fn print_table(stats: &BTreeMap<String, PrefixStats>) {
+	let width = stats.keys().map(String::len).max().unwrap_or(0).max("Prefix".len());
+	println!(
+		"{:<width$} {:>12} {:>16} {:>16}",
+		"Prefix", "Items", "Key bytes", "Value bytes",
+		width = width,
+	);
+
+	let mut total = PrefixStats::default();
+	for (group, group_stats) in by_size(stats) {
+		println!(
+			"{:<width$} {:>12} {:>16} {:>16}",
+			group, group_stats.items, group_stats.key_bytes, group_stats.value_bytes,
+			width = width,
+		);
+		total.items += group_stats.items;
+		total.key_bytes += group_stats.key_bytes;
+		total.value_bytes += group_stats.value_bytes;
+	}
+
+	println!(
+		"{:<width$} {:>12} {:>16} {:>16}",
+		"Total", total.items, total.key_bytes, total.value_bytes,
+		width = width,
+	);
+}
+
+fn print_json(stats: &BTreeMap<String, PrefixStats>) -> error::Result<()> {
+	let stats = by_size(stats).into_iter()
+		.map(|(group, group_stats)| json!({
+			"prefix": group,
+			"items": group_stats.items,
+			"keyBytes": group_stats.key_bytes,
+			"valueBytes": group_stats.value_bytes,
+		}))
+		.collect::<Vec<_>>();
+	println!("{}", serde_json::to_string_pretty(&stats).map_err(|e| e.to_string())?);
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn groups_entries_by_pallet() {
+		let balances = twox_128(b"Balances").to_vec();
+		let unknown = twox_128(b"Unknown").to_vec();
+		let key = |prefix: &[u8], suffix: &[u8]| [prefix, suffix].concat();
+
+		let child_root = key(well_known_keys::DEFAULT_CHILD_STORAGE_KEY_PREFIX, b"child");
+		let entries = vec![
+			(None, b":code".to_vec(), vec![0; 100]),
+			(None, key(&balances, b"a"), vec![0; 10]),
+			(None, key(&balances, b"bb"), vec![0; 20]),
+			(None, key(&unknown, b""), vec![0; 5]),
+			(None, b"short".to_vec(), vec![0; 1]),
+			(None, child_root, vec![0; 32]),
+			(Some(&b"child"[..]), b"k".to_vec(), vec![0; 3]),
+		];
+
+		let names = vec![(balances, "Balances".to_string())].into_iter().collect();
+		let mut stats = BTreeMap::new();
+		for (child, key, value) in entries {
+			note_entry(&mut stats, &names, child, &key, &value);
+		}
+
+		let stats_of = |items, key_bytes, value_bytes| {
+			PrefixStats { items, key_bytes, value_bytes }
+		};
+		assert_eq!(stats.len(), 5);
+		assert_eq!(stats[":code"], stats_of(1, 5, 100));
+		assert_eq!(stats["Balances"], stats_of(2, 35, 30));
+		assert_eq!(stats[&to_hex(&unknown, false)], stats_of(1, 16, 5));
+		assert_eq!(stats[&to_hex(b"short", false)], stats_of(1, 5, 1));
+		assert_eq!(stats[&format!("child:{}", to_hex(b"child", false))], stats_of(1, 1, 3));
+
+		let order = by_size(&stats).into_iter().map(|(group, _)| group.clone()).collect::<Vec<_>>();
+		assert_eq!(order[..2], [":code".to_string(), "Balances".to_string()]);
+	}
+}
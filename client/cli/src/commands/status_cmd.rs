@@ -0,0 +1,192 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2020 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::{CliConfiguration, error, params::{DatabaseParams, PruningParams, SharedParams}};
+use crate::rpc_client::{block_on, call, RpcClient};
+use sc_network::config::{NodeKeyConfig, Secret};
+use sc_service::{Configuration, Role, ServiceBuilderCommand};
+use serde_json::{json, Value};
+use sp_rpc::list::ListOrValue;
+use sp_rpc::number::NumberOrHex;
+use sp_runtime::traits::{Block as BlockT, SaturatedConversion};
+use std::fmt::Debug;
+use structopt::StructOpt;
+
+/// The `status` command used to print the identity and chain status of a node as JSON.
+///
+/// The information is either requested from a running node over RPC, or read from the local
+/// data directory of a node that isn't running.
+#[derive(Debug, StructOpt, Clone)]
+pub struct StatusCmd {
+	/// HTTP RPC endpoint of a running node to query, e.g. `http://127.0.0.1:9933`.
+	///
+	/// Without it, the local data directory is read instead.
+	#[structopt(long = "rpc-url", value_name = "URL")]
+	pub rpc_url: Option<String>,
+
+	#[allow(missing_docs)]
+	#[structopt(flatten)]
+	pub shared_params: SharedParams,
+
+	#[allow(missing_docs)]
+	#[structopt(flatten)]
+	pub pruning_params: PruningParams,
+
+	#[allow(missing_docs)]
+	#[structopt(flatten)]
+	pub database_params: DatabaseParams,
+}
+
+impl StatusCmd {
+	/// Run the `status` command
+	pub fn run<B, BC, BB>(
+		&self,
+		config: Configuration,
+		builder: B,
+	) -> error::Result<()>
+	where
+		B: FnOnce(Configuration) -> Result<BC, sc_service::error::Error>,
+		BC: ServiceBuilderCommand<Block = BB> + Unpin,
+		BB: BlockT + Debug,
+	{
+		let status = match self.rpc_url {
+			Some(ref url) => rpc_status(url)?,
+			None => local_status(config, builder)?,
+		};
+
+		println!("{}", serde_json::to_string_pretty(&status).map_err(|e| e.to_string())?);
+		Ok(())
+	}
+}
+
+impl CliConfiguration for StatusCmd {
+	fn shared_params(&self) -> &SharedParams {
+		&self.shared_params
+	}
+
+	fn pruning_params(&self) -> Option<&PruningParams> {
+		Some(&self.pruning_params)
+	}
+
+	fn database_params(&self) -> Option<&DatabaseParams> {
+		Some(&self.database_params)
+	}
+
+	fn database_read_only(&self) -> error::Result<bool> {
+		self.database_params.database_read_only(true)
+	}
+}
+
+/// Read the status of a node that isn't running from its data directory.
+fn local_status<B, BC, BB>(config: Configuration, builder: B) -> error::Result<Value>
+where
+	B: FnOnce(Configuration) -> Result<BC, sc_service::error::Error>,
+	BC: ServiceBuilderCommand<Block = BB> + Unpin,
+	BB: BlockT + Debug,
+{
+	// A node key that doesn't exist yet would be generated by reading it.
+	let peer_id = match config.network.node_key {
+		NodeKeyConfig::Ed25519(Secret::New) => None,
+		NodeKeyConfig::Ed25519(Secret::File(ref path)) if !path.exists() => None,
+		ref node_key => Some(node_key.clone().into_keypair()?.public().into_peer_id().to_base58()),
+	};
+	let role = match config.role {
+		Role::Full => "Full",
+		Role::Light => "LightClient",
+		Role::Authority { .. } => "Authority",
+		Role::Sentry { .. } => "Sentry",
+	};
+	let node_name = config.network.node_name.clone();
+	let chain = config.chain_spec.name().to_owned();
+	let listen_addresses = config.network.listen_addresses.iter()
+		.map(ToString::to_string)
+		.collect::<Vec<_>>();
+	let info = builder(config)?.chain_info();
+
+	Ok(json!({
+		"peerId": peer_id,
+		"nodeName": node_name,
+		"roles": [role],
+		"chain": chain,
+		"genesisHash": format!("{:?}", info.genesis_hash),
+		"bestNumber": info.best_number.saturated_into::<u64>(),
+		"bestHash": format!("{:?}", info.best_hash),
+		"finalizedNumber": info.finalized_number.saturated_into::<u64>(),
+		"finalizedHash": format!("{:?}", info.finalized_hash),
+		"listenAddresses": listen_addresses,
+	}))
+}
+
+/// Request the status of a running node over RPC.
+fn rpc_status(url: &str) -> error::Result<Value> {
+	let rpc = RpcClient::new(url)?;
+
+	block_on(async {
+		let peer_id = call("system_localPeerId", rpc.system.system_local_peer_id()).await?;
+		// Nodes that predate `system_nodeName` don't report their name.
+		let node_name = call("system_nodeName", rpc.system.system_node_name()).await.ok();
+		let roles = call("system_nodeRoles", rpc.system.system_node_roles()).await?;
+		let chain = call("system_chain", rpc.system.system_chain()).await?;
+		let genesis = Some(ListOrValue::Value(NumberOrHex::Number(0)));
+		let genesis_hash = call("chain_getBlockHash", rpc.chain.block_hash(genesis)).await?;
+		let best_hash = call("chain_getBlockHash", rpc.chain.block_hash(None)).await?;
+		let best_hash = match best_hash {
+			ListOrValue::Value(hash) => hash,
+			ListOrValue::List(_) => None,
+		};
+		let best_header = call("chain_getHeader", rpc.chain.header(best_hash)).await?;
+		let finalized_hash = call("chain_getFinalizedHead", rpc.chain.finalized_head()).await?;
+		let finalized_header =
+			call("chain_getHeader", rpc.chain.header(Some(finalized_hash))).await?;
+		let listen_addresses = call(
+			"system_localListenAddresses",
+			rpc.system.system_local_listen_addresses(),
+		).await?;
+
+		Ok(json!({
+			"peerId": peer_id,
+			"nodeName": node_name,
+			"roles": roles,
+			"chain": chain,
+			"genesisHash": genesis_hash,
+			"bestNumber": best_header.as_ref().and_then(header_number),
+			"bestHash": best_hash,
+			"finalizedNumber": finalized_header.as_ref().and_then(header_number),
+			"finalizedHash": finalized_hash,
+			"listenAddresses": listen_addresses,
+		}))
+	})
+}
+
+/// Decode the hex encoded number of a header returned over RPC.
+fn header_number(header: &Value) -> Option<u64> {
+	header["number"].as_str()
+		.and_then(|number| u64::from_str_radix(number.trim_start_matches("0x"), 16).ok())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn decodes_header_numbers() {
+		assert_eq!(header_number(&json!({ "number": "0x1a" })), Some(26));
+		assert_eq!(header_number(&json!({ "number": "0x0" })), Some(0));
+		assert_eq!(header_number(&json!({})), None);
+	}
+}
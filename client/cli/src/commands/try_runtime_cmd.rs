@@ -0,0 +1,130 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2020 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::error;
+use crate::params::{BlockNumberOrHash, ImportParams, SharedParams};
+use crate::remote_state;
+use crate::rpc_client::{block_on, RpcClient};
+use crate::CliConfiguration;
+use log::info;
+use sc_service::{Configuration, ServiceBuilderCommand};
+use sp_runtime::traits::{Block as BlockT, NumberFor};
+use std::{fmt::Debug, fs, path::PathBuf, str::FromStr};
+use structopt::StructOpt;
+
+/// The `try-runtime` command used to dry-run the migrations of a new runtime on the state of a
+/// block.
+///
+/// The state is read from the local database or fetched from a running node, and nothing is
+/// written anywhere.
+#[derive(Debug, StructOpt, Clone)]
+pub struct TryRuntimeCmd {
+	/// Runtime wasm blob whose migrations are executed.
+	///
+	/// The runtime has to implement the `TryRuntime` runtime api, e.g. by being built with the
+	/// `try-runtime` feature.
+	#[structopt(long = "runtime", value_name = "PATH", parse(from_os_str))]
+	pub runtime: PathBuf,
+
+	/// Block hash or number whose state is migrated. Defaults to the best block.
+	#[structopt(long = "at", value_name = "HASH or NUMBER")]
+	pub at: Option<BlockNumberOrHash>,
+
+	/// HTTP RPC endpoint of a running node to fetch the state from, e.g. `http://127.0.0.1:9933`.
+	///
+	/// Without it, the state is read from the local database.
+	#[structopt(long = "rpc-url", value_name = "URL")]
+	pub rpc_url: Option<String>,
+
+	/// Print the weight and all the storage changes as JSON rather than a summary.
+	#[structopt(long)]
+	pub json: bool,
+
+	#[allow(missing_docs)]
+	#[structopt(flatten)]
+	pub shared_params: SharedParams,
+
+	#[allow(missing_docs)]
+	#[structopt(flatten)]
+	pub import_params: ImportParams,
+}
+
+impl TryRuntimeCmd {
+	/// Run the try-runtime command
+	pub fn run<B, BC, BB>(
+		&self,
+		config: Configuration,
+		builder: B,
+	) -> error::Result<()>
+	where
+		B: FnOnce(Configuration) -> Result<BC, sc_service::error::Error>,
+		BC: ServiceBuilderCommand<Block = BB> + Unpin,
+		BB: BlockT + Debug,
+		<NumberFor<BB> as FromStr>::Err: std::fmt::Debug,
+		BB::Hash: FromStr,
+		<BB::Hash as FromStr>::Err: std::fmt::Debug,
+	{
+		let code = fs::read(&self.runtime)?;
+		let (wasm_method, heap_pages) = (config.wasm_method, config.default_heap_pages);
+
+		let storage = match self.rpc_url {
+			Some(ref url) => {
+				let rpc = RpcClient::new(url)?;
+				let at = self.at.as_ref().map(BlockNumberOrHash::as_str);
+				block_on(async {
+					let hash = remote_state::block_hash(&rpc, at).await?;
+					info!("Fetching the state of block {:?} from {}", hash, url);
+					remote_state::fetch_storage(&rpc, hash, &[]).await
+				})?
+			},
+			None => {
+				let block_id = self.at.clone().map(|b| b.parse()).transpose()?;
+				builder(config)?.export_raw_state(block_id)?
+			},
+		};
+
+		info!("Executing the migrations of {}", self.runtime.display());
+		let dry_run = sc_service::chain_ops::try_runtime_upgrade(
+			storage,
+			&code,
+			wasm_method,
+			heap_pages,
+		)?;
+
+		if self.json {
+			println!("{}", serde_json::to_string_pretty(&dry_run).map_err(|e| e.to_string())?);
+		} else {
+			println!("Weight consumed: {}", dry_run.weight);
+			println!("Storage entries added: {}", dry_run.changes.added.len());
+			println!("Storage entries changed: {}", dry_run.changes.changed.len());
+			println!("Storage entries removed: {}", dry_run.changes.removed.len());
+		}
+
+		Ok(())
+	}
+}
+
+impl CliConfiguration for TryRuntimeCmd {
+	fn shared_params(&self) -> &SharedParams {
+		&self.shared_params
+	}
+
+	fn import_params(&self) -> Option<&ImportParams> {
+		Some(&self.import_params)
+	}
+}
@@ -18,25 +18,33 @@
 
 //! Configuration trait for a CLI based on substrate
 
-use crate::arg_enums::Database;
-use crate::error::Result;
+use crate::arg_enums::{Database, Profile};
+use crate::error::{Error, Result};
+use crate::remote_chain_spec::{
+	fetch_chain_spec, is_remote_chain_spec, parse_sha256, CHAIN_SPEC_CACHE_PATH,
+};
 use crate::{
-	init_logger, DatabaseParams, ImportParams, KeystoreParams, NetworkParams, NodeKeyParams,
-	OffchainWorkerParams, PruningParams, SharedParams, SubstrateCli,
+	init_rate_limited_logger, DatabaseParams, ImportParams, KeystoreParams, LogRateLimit,
+	NetworkParams, NodeKeyParams, OffchainWorkerParams, PruningParams, SharedParams, SubstrateCli,
 };
+use log::{info, warn};
 use names::{Generator, Name};
 use sc_client_api::execution_extensions::ExecutionStrategies;
 use sc_service::config::{
-	Configuration, DatabaseConfig, ExtTransport, KeystoreConfig, NetworkConfiguration,
-	NodeKeyConfig, OffchainWorkerConfig, PrometheusConfig, PruningMode, Role, RpcMethods,
-	TaskType, TelemetryEndpoints, TransactionPoolOptions, WasmExecutionMethod,
+	BlockCompression, ColdStorageConfig, Configuration, DatabaseConfig, DiskSpaceWatchdogConfig,
+	ExtTransport, KeystoreConfig, NetworkConfiguration, NodeKeyConfig, NotificationConfig,
+	OffchainWorkerConfig, PeerId, PoolMemoryLimitsConfig, PrometheusConfig, PruningMode, Role,
+	RpcMethods, SignatureVerification, TaskType, TelemetryAuth, TelemetryEndpoints,
+	TransactionPoolOptions, WasmExecutionMethod,
 };
+use sc_network::multiaddr::Protocol;
 use sc_service::{ChainSpec, TracingReceiver};
 use std::future::Future;
 use std::net::SocketAddr;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::pin::Pin;
 use std::sync::Arc;
+use std::time::Duration;
 
 /// The maximum number of characters for a node name.
 pub(crate) const NODE_NAME_MAX_LENGTH: usize = 32;
@@ -44,6 +52,39 @@ pub(crate) const NODE_NAME_MAX_LENGTH: usize = 32;
 /// default sub directory to store network config
 pub(crate) const DEFAULT_NETWORK_CONFIG_PATH: &'static str = "network";
 
+/// default sub directory of the chain directory to store the named instances of the chain
+pub(crate) const DEFAULT_PROFILES_PATH: &'static str = "profiles";
+
+/// file of the directory of a named instance holding the offset of its default ports
+pub(crate) const PORT_OFFSET_FILE: &'static str = "port_offset";
+
+/// difference between the default ports of two named instances
+pub(crate) const PROFILE_PORT_STEP: u16 = 100;
+
+/// default port of the p2p network
+pub(crate) const DEFAULT_P2P_PORT: u16 = 30333;
+
+/// default port of the HTTP RPC server
+pub(crate) const DEFAULT_RPC_HTTP_PORT: u16 = 9933;
+
+/// default port of the WebSockets RPC server
+pub(crate) const DEFAULT_RPC_WS_PORT: u16 = 9944;
+
+/// default port of the Prometheus exporter
+pub(crate) const DEFAULT_PROMETHEUS_PORT: u16 = 9615;
+
+/// default sub directory to store the database of light clients
+pub(crate) const DEFAULT_LIGHT_DATABASE_PATH: &'static str = "light";
+
+/// default file in the database directory to save the banned transactions of the pool to
+pub(crate) const DEFAULT_BANNED_TRANSACTIONS_PATH: &'static str = "banned_transactions.json";
+
+/// default duration of a GRANDPA gossip round step
+pub(crate) const DEFAULT_GRANDPA_GOSSIP_DURATION: Duration = Duration::from_millis(333);
+
+/// chain spec property holding the duration in milliseconds of a GRANDPA gossip round step
+pub(crate) const GRANDPA_GOSSIP_DURATION_PROPERTY: &'static str = "grandpaGossipDuration";
+
 /// A trait that allows converting an object to a Configuration
 pub trait CliConfiguration: Sized {
 	/// Get the SharedParams for this object
@@ -91,6 +132,20 @@ pub trait CliConfiguration: Sized {
 		Ok(self.shared_params().base_path())
 	}
 
+	/// Get the path the chain data is stored under instead of the base path (if any)
+	///
+	/// By default this is retrieved from `SharedParams`.
+	fn data_path(&self) -> Result<Option<PathBuf>> {
+		Ok(self.shared_params().data_path())
+	}
+
+	/// Get the name of the instance of the chain whose files are kept apart (if any)
+	///
+	/// By default this is retrieved from `SharedParams`.
+	fn profile_name(&self) -> Result<Option<String>> {
+		Ok(self.shared_params().profile_name().map(Into::into))
+	}
+
 	/// Returns `true` if the node is for development or not
 	///
 	/// By default this is retrieved from `SharedParams`.
@@ -98,6 +153,13 @@ pub trait CliConfiguration: Sized {
 		Ok(self.shared_params().is_dev())
 	}
 
+	/// Get the deployment profile adjusting the defaults of other parameters.
+	///
+	/// By default this is `None`.
+	fn profile(&self) -> Result<Option<Profile>> {
+		Ok(None)
+	}
+
 	/// Gets the role
 	///
 	/// By default this is `Role::Full`.
@@ -134,6 +196,7 @@ pub trait CliConfiguration: Sized {
 				client_id,
 				node_name,
 				node_key,
+				self.profile()?,
 			)
 		} else {
 			NetworkConfiguration::new(
@@ -192,12 +255,58 @@ pub trait CliConfiguration: Sized {
 		})
 	}
 
-	/// Get the state cache size.
+	/// Get the cold storage configuration for the given database backend.
+	///
+	/// By default this is retrieved from `DatabaseParams` if it is available. Otherwise its `None`.
+	fn cold_storage(
+		&self,
+		cache_size: usize,
+		database: Database,
+	) -> Result<Option<ColdStorageConfig>> {
+		Ok(self.database_params().and_then(|x| x.cold_database()).map(|(path, depth)| {
+			let source = match database {
+				Database::RocksDb => DatabaseConfig::RocksDb { path, cache_size },
+				Database::SubDb => DatabaseConfig::SubDb { path },
+				Database::ParityDb => DatabaseConfig::ParityDb { path },
+			};
+			ColdStorageConfig { source, depth }
+		}))
+	}
+
+	/// Whether the database is opened read-only.
+	///
+	/// By default the database is never opened read-only, and `--db-read-only` is refused if
+	/// `DatabaseParams` is available. The commands which only inspect the database override this.
+	fn database_read_only(&self) -> Result<bool> {
+		self.database_params().map_or(Ok(false), |x| x.database_read_only(false))
+	}
+
+	/// Get the compression of the block bodies and justifications written to the database.
+	///
+	/// By default this is retrieved from `DatabaseParams` if it is available. Otherwise its
+	/// `BlockCompression::None`.
+	fn block_compression(&self) -> Result<BlockCompression> {
+		self.database_params()
+			.map_or(Ok(BlockCompression::None), |x| x.block_compression())
+	}
+
+	/// Get the trie node cache size.
+	///
+	/// By default this is retrieved from `ImportParams` if it is available. Otherwise its `0`.
+	fn trie_cache_size(&self) -> Result<usize> {
+		let profile = self.profile()?;
+		Ok(self.import_params()
+			.map(|x| x.trie_cache_size(profile))
+			.unwrap_or(Default::default()))
+	}
+
+	/// Get the storage value cache size.
 	///
 	/// By default this is retrieved from `ImportParams` if it is available. Otherwise its `0`.
-	fn state_cache_size(&self) -> Result<usize> {
+	fn value_cache_size(&self) -> Result<usize> {
+		let profile = self.profile()?;
 		Ok(self.import_params()
-			.map(|x| x.state_cache_size())
+			.map(|x| x.value_cache_size(profile))
 			.unwrap_or(Default::default()))
 	}
 
@@ -210,9 +319,14 @@ pub trait CliConfiguration: Sized {
 
 	/// Get the pruning mode.
 	///
-	/// By default this is retrieved from `PruningMode` if it is available. Otherwise its
-	/// `PruningMode::default()`.
+	/// By default this is retrieved from `PruningMode` if it is available, falling back to the
+	/// pruning mode of the profile. Otherwise its `PruningMode::default()`.
 	fn pruning(&self, unsafe_pruning: bool, role: &Role) -> Result<PruningMode> {
+		let is_explicit = self.pruning_params().map_or(false, |x| x.pruning.is_some());
+		if let (false, Some(pruning)) = (is_explicit, self.profile()?.and_then(Profile::pruning)) {
+			return Ok(pruning);
+		}
+
 		self.pruning_params()
 			.map(|x| x.pruning(unsafe_pruning, role))
 			.unwrap_or(Ok(Default::default()))
@@ -225,6 +339,23 @@ pub trait CliConfiguration: Sized {
 		Ok(self.shared_params().chain_id(is_dev))
 	}
 
+	/// Get the expected sha256 checksum of a chain spec downloaded from a URL.
+	///
+	/// By default this is retrieved from `SharedParams`.
+	fn chain_spec_sha256(&self) -> Result<Option<[u8; 32]>> {
+		self.shared_params()
+			.chain_spec_sha256()
+			.map(parse_sha256)
+			.transpose()
+	}
+
+	/// Get the SS58 prefix overriding the `ss58Format` property of the chain spec.
+	///
+	/// By default this is retrieved from `SharedParams`.
+	fn ss58_prefix(&self) -> Result<Option<u8>> {
+		Ok(self.shared_params().ss58_prefix())
+	}
+
 	/// Get the name of the node.
 	///
 	/// By default a random name is generated.
@@ -252,6 +383,38 @@ pub trait CliConfiguration: Sized {
 			.unwrap_or(Default::default()))
 	}
 
+	/// Get how the signatures batched by the runtime are verified.
+	///
+	/// By default this is retrieved from `ImportParams` if it is available. Otherwise its
+	/// `SignatureVerification::default()`.
+	fn signature_verification(&self) -> Result<SignatureVerification> {
+		Ok(self.import_params()
+			.map(|x| x.signature_verification())
+			.unwrap_or(Default::default()))
+	}
+
+	/// Get the configuration of the block import and finality notification streams.
+	///
+	/// By default this is `NotificationConfig::default()`.
+	fn client_notifications(&self) -> Result<NotificationConfig> {
+		Ok(Default::default())
+	}
+
+	/// Send the storage changes of the imported blocks to the indexers and the storage diff
+	/// subscribers.
+	///
+	/// By default this is `false`.
+	fn storage_diffs(&self) -> Result<bool> {
+		Ok(false)
+	}
+
+	/// Get the file the consensus inputs of the node are recorded to (`None` if disabled).
+	///
+	/// By default this is `None`.
+	fn replay_log(&self) -> Result<Option<PathBuf>> {
+		Ok(Default::default())
+	}
+
 	/// Get the RPC HTTP address (`None` if disabled).
 	///
 	/// By default this is `None`.
@@ -274,6 +437,76 @@ pub trait CliConfiguration: Sized {
 		Ok(Default::default())
 	}
 
+	/// Returns `Ok(true)` if the RPC requests that don't specify a block are served the latest
+	/// finalized block, and the blocks that aren't finalized yet are hidden.
+	///
+	/// By default this is `false`.
+	fn rpc_finalized_only(&self) -> Result<bool> {
+		Ok(false)
+	}
+
+	/// Get how long the heads announced to the RPC subscriptions are kept to be replayed
+	/// (`None` if disabled).
+	///
+	/// By default this is `None`.
+	fn rpc_subscription_replay(&self) -> Result<Option<Duration>> {
+		Ok(Default::default())
+	}
+
+	/// Get the time after which the runtime calls made on behalf of RPC requests are interrupted
+	/// (`None` if unlimited).
+	///
+	/// By default this is `None`.
+	fn rpc_call_timeout(&self) -> Result<Option<Duration>> {
+		Ok(Default::default())
+	}
+
+	/// Get the peers allowed to call the admin RPC methods over the network.
+	///
+	/// By default this is empty.
+	fn admin_rpc_peers(&self) -> Result<Vec<PeerId>> {
+		Ok(Default::default())
+	}
+
+	/// Get the labels of the node, attached to its metrics and sent to the telemetry.
+	///
+	/// By default this is empty.
+	fn node_labels(&self) -> Result<Vec<(String, String)>> {
+		Ok(Default::default())
+	}
+
+	/// Get the free space limits of the volume of the database watched by the node (`None` if
+	/// the free space isn't watched).
+	///
+	/// By default this is `None`.
+	fn disk_space_watchdog(&self) -> Result<Option<DiskSpaceWatchdogConfig>> {
+		Ok(Default::default())
+	}
+
+	/// Get the memory limits of the node above which the limits of the transaction pool are
+	/// reduced (`None` if the pool limits are fixed).
+	///
+	/// By default this is `None`.
+	fn pool_memory_limits(&self) -> Result<Option<PoolMemoryLimitsConfig>> {
+		Ok(Default::default())
+	}
+
+	/// Returns `Ok(true)` if the HTTP RPC server may expose unsafe RPC methods on an external
+	/// interface.
+	///
+	/// By default this is `false`, which makes `create_configuration` refuse such setups.
+	fn unsafe_rpc_external(&self) -> Result<bool> {
+		Ok(false)
+	}
+
+	/// Returns `Ok(true)` if the WebSockets RPC server may expose unsafe RPC methods on an
+	/// external interface.
+	///
+	/// By default this is `false`, which makes `create_configuration` refuse such setups.
+	fn unsafe_ws_external(&self) -> Result<bool> {
+		Ok(false)
+	}
+
 	/// Get the RPC websockets maximum connections (`None` if unlimited).
 	///
 	/// By default this is `None`.
@@ -281,6 +514,13 @@ pub trait CliConfiguration: Sized {
 		Ok(Default::default())
 	}
 
+	/// Get the time after which idle RPC websockets connections are closed (`None` if never).
+	///
+	/// By default this is `None`.
+	fn rpc_ws_idle_timeout(&self) -> Result<Option<Duration>> {
+		Ok(Default::default())
+	}
+
 	/// Get the RPC cors (`None` if disabled)
 	///
 	/// By default this is `None`.
@@ -288,6 +528,15 @@ pub trait CliConfiguration: Sized {
 		Ok(Some(Vec::new()))
 	}
 
+	/// Get the hosts allowed in the `Host` header of RPC requests, in addition to the listening
+	/// address (`None` if all hosts are allowed).
+	///
+	/// By default only the listening address is allowed, unless all the origins are allowed by
+	/// `rpc_cors`.
+	fn rpc_allowed_hosts(&self, is_dev: bool) -> Result<Option<Vec<String>>> {
+		Ok(self.rpc_cors(is_dev)?.map(|_| Vec::new()))
+	}
+
 	/// Get the prometheus configuration (`None` if disabled)
 	///
 	/// By default this is `None`.
@@ -312,6 +561,13 @@ pub trait CliConfiguration: Sized {
 		Ok(Default::default())
 	}
 
+	/// Get the authentication towards private telemetry servers
+	///
+	/// By default no authentication is used.
+	fn telemetry_auth(&self) -> Result<TelemetryAuth> {
+		Ok(Default::default())
+	}
+
 	/// Get the default value for heap pages
 	///
 	/// By default this is `None`.
@@ -342,6 +598,39 @@ pub trait CliConfiguration: Sized {
 		Ok(Default::default())
 	}
 
+	/// Get the period in blocks after which GRANDPA generates a justification (`None` if
+	/// justifications are only generated when required)
+	///
+	/// By default this is `512`.
+	fn grandpa_justification_period(&self) -> Result<Option<u32>> {
+		Ok(Some(512))
+	}
+
+	/// Returns `Ok(true)` if the periodic GRANDPA justifications of the imported blocks should be
+	/// requested from the peers
+	///
+	/// By default this is `false`.
+	fn grandpa_request_justifications(&self) -> Result<bool> {
+		Ok(Default::default())
+	}
+
+	/// Get the number of threads checking the signatures of the imported GRANDPA justifications
+	///
+	/// By default this is the number of CPUs when the signatures are verified in parallel, and
+	/// `0`, which checks them on the thread importing the blocks, otherwise.
+	fn grandpa_justification_workers(&self) -> Result<usize> {
+		Ok(default_justification_workers(self.signature_verification()?))
+	}
+
+	/// Get the duration of a GRANDPA gossip round step
+	///
+	/// By default this is the `grandpaGossipDuration` property of the chain spec, or else
+	/// `333ms`.
+	fn grandpa_gossip_duration(&self, chain_spec: &Box<dyn ChainSpec>) -> Result<Duration> {
+		Ok(grandpa_gossip_duration_property(&chain_spec.properties())?
+			.unwrap_or(DEFAULT_GRANDPA_GOSSIP_DURATION))
+	}
+
 	/// Get the development key seed from the current object
 	///
 	/// By default this is `None`.
@@ -386,6 +675,43 @@ pub trait CliConfiguration: Sized {
 		Ok(Default::default())
 	}
 
+	/// Refuse to start a node whose configuration fails `Configuration::sanity_check`.
+	///
+	/// By default this is `false`.
+	fn strict_config(&self) -> Result<bool> {
+		Ok(false)
+	}
+
+	/// Start a node whose keystore or node key files are accessible by other users than their
+	/// owner, only warning about them.
+	///
+	/// By default this is `false`.
+	fn insecure_keystore_permissions(&self) -> Result<bool> {
+		Ok(false)
+	}
+
+	/// Get the maximum time to wait on shutdown for in-progress block authorship to complete.
+	///
+	/// By default this is `None`, which shuts down without waiting.
+	fn authoring_shutdown_timeout(&self) -> Result<Option<Duration>> {
+		Ok(None)
+	}
+
+	/// Get the number of worker threads of the tokio runtime the node runs on.
+	///
+	/// By default this is `None`, which uses a default depending on the role and on the number
+	/// of CPUs.
+	fn runtime_threads(&self) -> Result<Option<usize>> {
+		Ok(None)
+	}
+
+	/// Get the maximum number of threads running blocking tasks, such as the block import.
+	///
+	/// By default this is `None`, which uses a default depending on the role.
+	fn blocking_threads(&self) -> Result<Option<usize>> {
+		Ok(None)
+	}
+
 	/// Activate or not the automatic announcing of blocks after import
 	///
 	/// By default this is `false`.
@@ -400,63 +726,161 @@ pub trait CliConfiguration: Sized {
 		task_executor: Arc<dyn Fn(Pin<Box<dyn Future<Output = ()> + Send>>, TaskType) + Send + Sync>,
 	) -> Result<Configuration> {
 		let is_dev = self.is_dev()?;
-		let chain_id = self.chain_id(is_dev)?;
-		let chain_spec = cli.load_spec(chain_id.as_str())?;
-		let config_dir = self
+		let mut chain_id = self.chain_id(is_dev)?;
+		let base_path = self
 			.base_path()?
 			.unwrap_or_else(|| {
 				directories::ProjectDirs::from("", "", C::executable_name())
 					.expect("app directories exist on all supported platforms; qed")
 					.data_local_dir()
 					.into()
-			})
-			.join("chains")
-			.join(chain_spec.id());
+			});
+		if is_remote_chain_spec(&chain_id) {
+			chain_id = fetch_chain_spec(
+				&chain_id,
+				self.chain_spec_sha256()?,
+				&base_path.join(CHAIN_SPEC_CACHE_PATH),
+			)?
+				.to_string_lossy()
+				.into_owned();
+		}
+		let chain_spec = cli.load_spec(chain_id.as_str())?;
+		let profile_name = self.profile_name()?
+			.map(|name| crate::params::parse_profile_name(&name))
+			.transpose()?;
+		let config_dir = chain_dir(&base_path, chain_spec.id(), profile_name.as_deref());
+		let data_dir = match self.data_path()? {
+			Some(data_path) => chain_dir(&data_path, chain_spec.id(), profile_name.as_deref()),
+			None => config_dir.clone(),
+		};
+		// the named instances don't listen on the default ports of each other
+		let port_offset = match profile_name {
+			Some(_) => profile_port_offset(&base_path, &config_dir)?,
+			None => 0,
+		};
 		let net_config_dir = config_dir.join(DEFAULT_NETWORK_CONFIG_PATH);
 		let client_id = C::client_id();
-		let database_cache_size = self.database_cache_size()?.unwrap_or(128);
+		let role = self.role(is_dev)?;
+		let (database_dir, default_database_cache_size) = match role {
+			// Light clients keep their database apart, so that a chain can be run with either role
+			// without purging it first.
+			Role::Light => (data_dir.join(DEFAULT_LIGHT_DATABASE_PATH), 32),
+			_ => (data_dir.clone(), 128),
+		};
+		let database_cache_size = self.database_cache_size()?.unwrap_or(default_database_cache_size);
 		let database = self.database()?.unwrap_or(Database::RocksDb);
+		let database_config = self.database_config(&database_dir, database_cache_size, database)?;
+		if let Role::Light = role {
+			let legacy_config = self.database_config(&data_dir, database_cache_size, database)?;
+			move_legacy_light_database(&legacy_config, &database_config)?;
+		}
 		let node_key = self.node_key(&net_config_dir)?;
-		let role = self.role(is_dev)?;
 		let max_runtime_instances = self.max_runtime_instances()?.unwrap_or(8);
+		let node_labels = self.node_labels()?;
 
 		let unsafe_pruning = self
 			.import_params()
 			.map(|p| p.unsafe_pruning)
 			.unwrap_or(false);
 
+		let database_read_only = self.database_read_only()?;
+
+		let mut transaction_pool = self.transaction_pool()?;
+		// the banned transactions are saved next to the database, which isn't written to when
+		// it's read-only.
+		if transaction_pool.banned_transactions_path.is_none() && !database_read_only {
+			transaction_pool.banned_transactions_path =
+				Some(database_dir.join(DEFAULT_BANNED_TRANSACTIONS_PATH));
+		}
+
+		let rpc_http = self.rpc_http()?
+			.map(|address| offset_default_port(address, DEFAULT_RPC_HTTP_PORT, port_offset));
+		let rpc_ws = self.rpc_ws()?
+			.map(|address| offset_default_port(address, DEFAULT_RPC_WS_PORT, port_offset));
+		let rpc_methods = self.rpc_methods()?;
+		let mut network = self.network_config(
+			&chain_spec,
+			is_dev,
+			net_config_dir,
+			client_id.as_str(),
+			self.node_name()?.as_str(),
+			node_key,
+		)?;
+		for address in network.listen_addresses.iter_mut() {
+			*address = address.iter()
+				.map(|protocol| match protocol {
+					Protocol::Tcp(port) if port == DEFAULT_P2P_PORT =>
+						Protocol::Tcp(port.saturating_add(port_offset)),
+					protocol => protocol,
+				})
+				.collect();
+		}
+		let prometheus_config = self.prometheus_config()?
+			.map(|mut prometheus| {
+				prometheus.port = offset_default_port(
+					prometheus.port,
+					DEFAULT_PROMETHEUS_PORT,
+					port_offset,
+				);
+				prometheus.with_labels(node_labels.iter().cloned())
+			});
+		check_rpc_exposure(
+			&[
+				(rpc_http, self.unsafe_rpc_external()?, "--unsafe-rpc-external"),
+				(rpc_ws, self.unsafe_ws_external()?, "--unsafe-ws-external"),
+			],
+			rpc_methods,
+			&role,
+		)?;
+
 		Ok(Configuration {
 			impl_name: C::impl_name(),
 			impl_version: C::impl_version(),
 			task_executor,
-			transaction_pool: self.transaction_pool()?,
-			network: self.network_config(
-				&chain_spec,
-				is_dev,
-				net_config_dir,
-				client_id.as_str(),
-				self.node_name()?.as_str(),
-				node_key,
-			)?,
+			transaction_pool,
+			network,
 			keystore: self.keystore_config(&config_dir)?,
-			database: self.database_config(&config_dir, database_cache_size, database)?,
-			state_cache_size: self.state_cache_size()?,
+			database: database_config,
+			cold_storage: self.cold_storage(database_cache_size, database)?,
+			database_read_only,
+			block_compression: self.block_compression()?,
+			node_labels,
+			disk_space_watchdog: self.disk_space_watchdog()?,
+			pool_memory_limits: self.pool_memory_limits()?,
+			trie_cache_size: self.trie_cache_size()?,
+			value_cache_size: self.value_cache_size()?,
 			state_cache_child_ratio: self.state_cache_child_ratio()?,
 			pruning: self.pruning(unsafe_pruning, &role)?,
 			wasm_method: self.wasm_method()?,
 			execution_strategies: self.execution_strategies(is_dev)?,
-			rpc_http: self.rpc_http()?,
-			rpc_ws: self.rpc_ws()?,
-			rpc_methods: self.rpc_methods()?,
+			signature_verification: self.signature_verification()?,
+			client_notifications: self.client_notifications()?,
+			storage_diffs: self.storage_diffs()?,
+			replay_log: self.replay_log()?,
+			rpc_http,
+			rpc_ws,
+			rpc_methods,
+			rpc_finalized_only: self.rpc_finalized_only()?,
+			rpc_subscription_replay: self.rpc_subscription_replay()?,
+			rpc_call_timeout: self.rpc_call_timeout()?,
+			admin_rpc_peers: self.admin_rpc_peers()?,
 			rpc_ws_max_connections: self.rpc_ws_max_connections()?,
+			rpc_ws_idle_timeout: self.rpc_ws_idle_timeout()?,
 			rpc_cors: self.rpc_cors(is_dev)?,
-			prometheus_config: self.prometheus_config()?,
+			rpc_allowed_hosts: self.rpc_allowed_hosts(is_dev)?,
+			prometheus_config,
 			telemetry_endpoints: self.telemetry_endpoints(&chain_spec)?,
 			telemetry_external_transport: self.telemetry_external_transport()?,
+			telemetry_auth: self.telemetry_auth()?,
 			default_heap_pages: self.default_heap_pages()?,
 			offchain_worker: self.offchain_worker(&role)?,
 			force_authoring: self.force_authoring()?,
 			disable_grandpa: self.disable_grandpa()?,
+			grandpa_justification_period: self.grandpa_justification_period()?,
+			grandpa_request_justifications: self.grandpa_request_justifications()?,
+			grandpa_justification_workers: self.grandpa_justification_workers()?,
+			grandpa_gossip_duration: self.grandpa_gossip_duration(&chain_spec)?,
+			clock: None,
 			dev_key_seed: self.dev_key_seed(is_dev)?,
 			tracing_targets: self.tracing_targets()?,
 			tracing_receiver: self.tracing_receiver()?,
@@ -477,6 +901,13 @@ pub trait CliConfiguration: Sized {
 		Ok(self.shared_params().log_filters().join(","))
 	}
 
+	/// Get the limits of the number of identical messages logged per target every minute.
+	///
+	/// By default this is retrieved from `SharedParams`.
+	fn log_rate_limits(&self) -> Result<Vec<LogRateLimit>> {
+		Ok(self.shared_params().log_rate_limits().to_vec())
+	}
+
 	/// Initialize substrate. This must be done only once.
 	///
 	/// This method:
@@ -486,16 +917,157 @@ pub trait CliConfiguration: Sized {
 	/// 3. Initialize the logger
 	fn init<C: SubstrateCli>(&self) -> Result<()> {
 		let logger_pattern = self.log_filters()?;
+		let log_rate_limits = self.log_rate_limits()?;
 
 		sp_panic_handler::set(C::support_url(), C::impl_version());
 
 		fdlimit::raise_fd_limit();
-		init_logger(&logger_pattern);
+		init_rate_limited_logger(&logger_pattern, &log_rate_limits);
 
 		Ok(())
 	}
 }
 
+/// Move the database of a light client from the directory it was kept in before light clients
+/// had their own, so that it isn't left behind and synced again from scratch.
+fn move_legacy_light_database(legacy: &DatabaseConfig, database: &DatabaseConfig) -> Result<()> {
+	let (legacy_path, path) = match (legacy.path(), database.path()) {
+		(Some(legacy_path), Some(path)) => (legacy_path, path),
+		_ => return Ok(()),
+	};
+	// the legacy directory may as well hold the database of a full node
+	if path.exists()
+		|| sc_client_db::stored_database_type(legacy) != Some(sc_client_db::DatabaseType::Light)
+	{
+		return Ok(());
+	}
+
+	info!("Moving the light client database from {} to {}", legacy_path.display(), path.display());
+	if let Some(parent) = path.parent() {
+		std::fs::create_dir_all(parent)?;
+	}
+	std::fs::rename(legacy_path, path)?;
+
+	Ok(())
+}
+
+/// Refuse to expose RPC on a non-loopback interface if the node is an authority or unsafe RPC
+/// methods are served there.
+///
+/// `servers` are the listen addresses of the RPC servers, with whether each of them may be
+/// exposed anyway and the flag allowing it. Exposed validators get their keys replaced through
+/// `author_insertKey` and similar methods.
+fn check_rpc_exposure(
+	servers: &[(Option<SocketAddr>, bool, &str)],
+	rpc_methods: RpcMethods,
+	role: &Role,
+) -> Result<()> {
+	for (address, allowed, flag) in servers {
+		let address = match address {
+			Some(address) if !address.ip().is_loopback() => address,
+			_ => continue,
+		};
+		let risk = if role.is_authority() {
+			"the node is running as a validator"
+		} else if rpc_methods.serves_unsafe(address) {
+			"unsafe RPC methods are enabled"
+		} else {
+			continue;
+		};
+
+		if *allowed {
+			warn!("Exposing RPC on {} while {} ({})", address, risk, flag);
+		} else {
+			return Err(Error::Input(format!(
+				"Refusing to expose RPC on {} while {}. Use an RPC proxy server that filters out \
+				unsafe methods, or `{}` if you understand the risks.",
+				address,
+				risk,
+				flag,
+			)));
+		}
+	}
+
+	Ok(())
+}
+
+/// Returns the directory of the chain `chain_id` under `path`, or the one of its instance named
+/// `profile_name`.
+pub(crate) fn chain_dir(path: &Path, chain_id: &str, profile_name: Option<&str>) -> PathBuf {
+	let chain_dir = path.join("chains").join(chain_id);
+	match profile_name {
+		Some(name) => chain_dir.join(DEFAULT_PROFILES_PATH).join(name),
+		None => chain_dir,
+	}
+}
+
+/// Returns the offset of the default ports of the named instance stored in `profile_dir`.
+///
+/// The first time an instance is run, it gets the smallest offset unused by the instances of
+/// all the chains under `base_path`, which is saved in its directory.
+fn profile_port_offset(base_path: &Path, profile_dir: &Path) -> Result<u16> {
+	let read_offset = |dir: &Path| -> Option<u16> {
+		std::fs::read_to_string(dir.join(PORT_OFFSET_FILE)).ok()?.trim().parse().ok()
+	};
+	if let Some(offset) = read_offset(profile_dir) {
+		return Ok(offset);
+	}
+
+	let mut used = std::collections::HashSet::new();
+	for chain in std::fs::read_dir(base_path.join("chains")).into_iter().flatten().flatten() {
+		let profiles = chain.path().join(DEFAULT_PROFILES_PATH);
+		for profile in std::fs::read_dir(profiles).into_iter().flatten().flatten() {
+			used.extend(read_offset(&profile.path()));
+		}
+	}
+	let offset = (1..)
+		.map(|n: u16| n.saturating_mul(PROFILE_PORT_STEP))
+		.find(|offset| !used.contains(offset))
+		.expect("there are fewer instances than offsets; qed");
+
+	std::fs::create_dir_all(profile_dir)?;
+	std::fs::write(profile_dir.join(PORT_OFFSET_FILE), offset.to_string())?;
+	Ok(offset)
+}
+
+/// Returns `address` with `offset` added to its port if it is the `default` one.
+fn offset_default_port(mut address: SocketAddr, default: u16, offset: u16) -> SocketAddr {
+	if address.port() == default {
+		address.set_port(default.saturating_add(offset));
+	}
+	address
+}
+
+/// Read the duration of a GRANDPA gossip round step from the `properties` of a chain spec.
+///
+/// All the voters of a network are expected to use the same duration, which private networks
+/// with fast block times can lower through this property.
+pub(crate) fn grandpa_gossip_duration_property(
+	properties: &serde_json::Map<String, serde_json::Value>,
+) -> Result<Option<Duration>> {
+	match properties.get(GRANDPA_GOSSIP_DURATION_PROPERTY) {
+		None => Ok(None),
+		Some(value) => match value.as_u64() {
+			Some(millis) if millis > 0 => Ok(Some(Duration::from_millis(millis))),
+			_ => Err(Error::Input(format!(
+				"The `{}` property of the chain spec must be a positive number of milliseconds, \
+				got {}",
+				GRANDPA_GOSSIP_DURATION_PROPERTY,
+				value,
+			))),
+		},
+	}
+}
+
+/// Returns the number of threads checking the signatures of the GRANDPA justifications when
+/// none is given, following how the signatures batched by the runtime are verified.
+pub(crate) fn default_justification_workers(signature_verification: SignatureVerification) -> usize {
+	match signature_verification {
+		SignatureVerification::Parallel => num_cpus::get(),
+		SignatureVerification::Sequential => 0,
+	}
+}
+
 /// Generate a valid random name for the node
 pub fn generate_node_name() -> String {
 	loop {
@@ -509,3 +1081,118 @@ pub fn generate_node_name() -> String {
 		}
 	};
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::RunCmd;
+	use std::net::{IpAddr, Ipv4Addr};
+	use structopt::StructOpt;
+
+	fn check_cli_rpc_exposure(args: &[&str]) -> Result<()> {
+		let cmd = RunCmd::from_iter(std::iter::once("substrate").chain(args.iter().cloned()));
+		check_rpc_exposure(
+			&[
+				(cmd.rpc_http()?, cmd.unsafe_rpc_external()?, "--unsafe-rpc-external"),
+				(cmd.rpc_ws()?, cmd.unsafe_ws_external()?, "--unsafe-ws-external"),
+			],
+			cmd.rpc_methods()?,
+			&cmd.role(cmd.is_dev()?)?,
+		)
+	}
+
+	#[test]
+	fn refuses_unsafe_external_rpc() {
+		let local = Some(SocketAddr::new(Ipv4Addr::LOCALHOST.into(), 9933));
+		let external = Some(SocketAddr::new(IpAddr::from(Ipv4Addr::UNSPECIFIED), 9944));
+
+		let authority = Role::Authority { sentry_nodes: Vec::new() };
+
+		let check = |servers: &[(Option<SocketAddr>, bool)], methods, role: &Role| {
+			let servers = servers.iter()
+				.map(|(address, allowed)| (*address, *allowed, "--unsafe-rpc-external"))
+				.collect::<Vec<_>>();
+			check_rpc_exposure(&servers, methods, role).is_ok()
+		};
+
+		assert!(check(&[(local, false), (None, false)], RpcMethods::Unsafe, &authority));
+		assert!(check(&[(local, false), (external, false)], RpcMethods::Safe, &Role::Full));
+		assert!(check(&[(local, false), (external, false)], RpcMethods::Auto, &Role::Full));
+		assert!(!check(&[(local, false), (external, false)], RpcMethods::Safe, &authority));
+		assert!(!check(&[(external, false), (None, false)], RpcMethods::Unsafe, &Role::Full));
+		assert!(check(&[(external, true), (None, false)], RpcMethods::Unsafe, &Role::Full));
+		assert!(check(&[(external, true), (None, false)], RpcMethods::Safe, &authority));
+		assert!(!check(&[(external, true), (external, false)], RpcMethods::Unsafe, &Role::Full));
+	}
+
+	#[test]
+	fn validator_refuses_external_rpc() {
+		assert!(check_cli_rpc_exposure(&["--ws-external"]).is_ok());
+		assert!(check_cli_rpc_exposure(&["--dev", "--ws-external"]).is_err());
+		assert!(check_cli_rpc_exposure(&["--validator", "--rpc-external", "--ws-external"]).is_err());
+		assert!(check_cli_rpc_exposure(
+			&["--validator", "--unsafe-rpc-external", "--unsafe-ws-external"],
+		).is_ok());
+	}
+
+	#[test]
+	fn unsafe_external_rpc_is_allowed_per_server() {
+		assert!(check_cli_rpc_exposure(&["--rpc-external", "--rpc-methods=unsafe"]).is_err());
+		assert!(check_cli_rpc_exposure(
+			&["--rpc-external", "--unsafe-ws-external", "--rpc-methods=unsafe"],
+		).is_err());
+		assert!(check_cli_rpc_exposure(
+			&["--unsafe-rpc-external", "--ws-external", "--rpc-methods=unsafe"],
+		).is_err());
+		assert!(check_cli_rpc_exposure(
+			&["--unsafe-rpc-external", "--unsafe-ws-external", "--rpc-methods=unsafe"],
+		).is_ok());
+	}
+
+	#[test]
+	fn reads_grandpa_gossip_duration_from_properties() {
+		let properties = |value: serde_json::Value| {
+			let mut properties = serde_json::Map::new();
+			properties.insert(GRANDPA_GOSSIP_DURATION_PROPERTY.into(), value);
+			properties
+		};
+
+		assert_eq!(grandpa_gossip_duration_property(&Default::default()).unwrap(), None);
+		assert_eq!(
+			grandpa_gossip_duration_property(&properties(100.into())).unwrap(),
+			Some(Duration::from_millis(100)),
+		);
+		assert!(grandpa_gossip_duration_property(&properties(0.into())).is_err());
+		assert!(grandpa_gossip_duration_property(&properties("100".into())).is_err());
+	}
+
+	#[test]
+	fn named_instances_are_stored_apart() {
+		let base_path = Path::new("/base");
+		assert_eq!(chain_dir(base_path, "flaming-fir", None), Path::new("/base/chains/flaming-fir"));
+		assert_eq!(
+			chain_dir(base_path, "flaming-fir", Some("validator-1")),
+			Path::new("/base/chains/flaming-fir/profiles/validator-1"),
+		);
+	}
+
+	#[test]
+	fn named_instances_get_distinct_default_ports() {
+		let base_path = tempfile::tempdir().unwrap();
+		let profile = |chain, name| chain_dir(base_path.path(), chain, Some(name));
+
+		let first = profile_port_offset(base_path.path(), &profile("dev", "a")).unwrap();
+		let second = profile_port_offset(base_path.path(), &profile("local", "b")).unwrap();
+		assert_eq!((first, second), (PROFILE_PORT_STEP, 2 * PROFILE_PORT_STEP));
+		// the offset of an instance is kept
+		assert_eq!(profile_port_offset(base_path.path(), &profile("dev", "a")).unwrap(), first);
+
+		let address = |port| SocketAddr::new(Ipv4Addr::LOCALHOST.into(), port);
+		assert_eq!(
+			offset_default_port(address(DEFAULT_RPC_HTTP_PORT), DEFAULT_RPC_HTTP_PORT, first),
+			address(DEFAULT_RPC_HTTP_PORT + PROFILE_PORT_STEP),
+		);
+		// the ports which aren't the default ones are kept
+		assert_eq!(offset_default_port(address(8000), DEFAULT_RPC_HTTP_PORT, first), address(8000));
+	}
+}
@@ -0,0 +1,305 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2020 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Building a node `Configuration` without command line arguments.
+
+use crate::error::Result;
+use crate::{CliConfiguration, KeystoreParams, SharedParams, SubstrateCli};
+use sc_service::config::{
+	Configuration, Multiaddr, MultiaddrWithPeerId, NetworkConfiguration, NodeKeyConfig,
+	PrometheusConfig, PruningMode, Role, RpcMethods, TaskType,
+};
+use sc_service::ChainSpec;
+use std::future::Future;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::sync::Arc;
+
+/// Builds a [`Configuration`] programmatically, e.g. for tests or for embedding a node.
+///
+/// Everything that isn't set is defaulted by [`CliConfiguration::create_configuration`], which
+/// the builder implements, the same way as for the commands. In particular the chain spec is
+/// loaded through [`SubstrateCli::load_spec`] and the directories are derived from the base path.
+///
+/// Unlike `run`, the RPC servers and Prometheus exporter are disabled unless set.
+#[derive(Debug, Clone)]
+pub struct ConfigurationBuilder {
+	shared_params: SharedParams,
+	keystore_params: Option<KeystoreParams>,
+	role: Option<Role>,
+	node_name: Option<String>,
+	dev_key_seed: Option<String>,
+	listen_addresses: Vec<Multiaddr>,
+	boot_nodes: Vec<MultiaddrWithPeerId>,
+	pruning: Option<PruningMode>,
+	rpc_http: Option<SocketAddr>,
+	rpc_ws: Option<SocketAddr>,
+	rpc_methods: RpcMethods,
+	prometheus: Option<SocketAddr>,
+}
+
+impl ConfigurationBuilder {
+	/// Create a builder for a full node of the default chain.
+	pub fn new() -> Self {
+		ConfigurationBuilder {
+			shared_params: SharedParams {
+				chain: None,
+				chain_spec_sha256: None,
+				dev: false,
+				base_path: None,
+				data_path: None,
+				profile_name: None,
+				log: Vec::new(),
+				log_rate_limit: Vec::new(),
+				ss58_prefix: None,
+			},
+			keystore_params: Some(KeystoreParams {
+				keystore_path: None,
+				password_interactive: false,
+				password: None,
+				password_filename: None,
+			}),
+			role: None,
+			node_name: None,
+			dev_key_seed: None,
+			listen_addresses: Vec::new(),
+			boot_nodes: Vec::new(),
+			pruning: None,
+			rpc_http: None,
+			rpc_ws: None,
+			rpc_methods: RpcMethods::Auto,
+			prometheus: None,
+		}
+	}
+
+	/// Use the chain spec with the given id, as passed to `--chain`.
+	pub fn with_chain(mut self, chain: impl Into<String>) -> Self {
+		self.shared_params.chain = Some(chain.into());
+		self
+	}
+
+	/// Run the development chain, like `--dev`.
+	///
+	/// Unless set otherwise, the node is then an authority with the keys of `//Alice`.
+	pub fn with_dev(mut self) -> Self {
+		self.shared_params.dev = true;
+		self
+	}
+
+	/// Store the node data under `base_path`.
+	pub fn with_base_path(mut self, base_path: impl Into<PathBuf>) -> Self {
+		self.shared_params.base_path = Some(base_path.into());
+		self
+	}
+
+	/// Store the chain data under `data_path` rather than under the base path.
+	pub fn with_data_path(mut self, data_path: impl Into<PathBuf>) -> Self {
+		self.shared_params.data_path = Some(data_path.into());
+		self
+	}
+
+	/// Store the files of the node under the directory of the instance of the chain named `name`.
+	pub fn with_profile_name(mut self, name: impl Into<String>) -> Self {
+		self.shared_params.profile_name = Some(name.into());
+		self
+	}
+
+	/// Set the role of the node. Defaults to an authority for the development chain, and to a
+	/// full node otherwise.
+	pub fn with_role(mut self, role: Role) -> Self {
+		self.role = Some(role);
+		self
+	}
+
+	/// Set the name of the node. Defaults to a random name.
+	pub fn with_node_name(mut self, name: impl Into<String>) -> Self {
+		self.node_name = Some(name.into());
+		self
+	}
+
+	/// Insert the session keys derived from `seed` (e.g. `//Alice`) into the keystore.
+	pub fn with_dev_key_seed(mut self, seed: impl Into<String>) -> Self {
+		self.dev_key_seed = Some(seed.into());
+		self
+	}
+
+	/// Store the keys at `path` instead of in the `keystore` directory of the chain.
+	pub fn with_keystore_path(mut self, path: impl Into<PathBuf>) -> Self {
+		let params = self.keystore_params.get_or_insert_with(|| KeystoreParams {
+			keystore_path: None,
+			password_interactive: false,
+			password: None,
+			password_filename: None,
+		});
+		params.keystore_path = Some(path.into());
+		self
+	}
+
+	/// Keep the keys in memory only.
+	pub fn with_in_memory_keystore(mut self) -> Self {
+		self.keystore_params = None;
+		self
+	}
+
+	/// Listen on the given addresses for peer-to-peer connections. No address by default.
+	pub fn with_listen_addresses(mut self, addresses: Vec<Multiaddr>) -> Self {
+		self.listen_addresses = addresses;
+		self
+	}
+
+	/// Connect to the given boot nodes in addition to those of the chain spec.
+	pub fn with_boot_nodes(mut self, boot_nodes: Vec<MultiaddrWithPeerId>) -> Self {
+		self.boot_nodes = boot_nodes;
+		self
+	}
+
+	/// Set the state pruning mode.
+	pub fn with_pruning(mut self, pruning: PruningMode) -> Self {
+		self.pruning = Some(pruning);
+		self
+	}
+
+	/// Serve RPC over HTTP on the given address, exposing the given method set.
+	pub fn with_rpc_http(mut self, address: SocketAddr, methods: RpcMethods) -> Self {
+		self.rpc_http = Some(address);
+		self.rpc_methods = methods;
+		self
+	}
+
+	/// Serve RPC over websockets on the given address, exposing the given method set.
+	pub fn with_rpc_ws(mut self, address: SocketAddr, methods: RpcMethods) -> Self {
+		self.rpc_ws = Some(address);
+		self.rpc_methods = methods;
+		self
+	}
+
+	/// Export Prometheus metrics on the given address.
+	pub fn with_prometheus(mut self, address: SocketAddr) -> Self {
+		self.prometheus = Some(address);
+		self
+	}
+
+	/// Build the configuration.
+	pub fn build<C: SubstrateCli>(
+		&self,
+		cli: &C,
+		task_executor: Arc<dyn Fn(Pin<Box<dyn Future<Output = ()> + Send>>, TaskType) + Send + Sync>,
+	) -> Result<Configuration> {
+		self.create_configuration(cli, task_executor)
+	}
+}
+
+impl Default for ConfigurationBuilder {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl CliConfiguration for ConfigurationBuilder {
+	fn shared_params(&self) -> &SharedParams {
+		&self.shared_params
+	}
+
+	fn keystore_params(&self) -> Option<&KeystoreParams> {
+		self.keystore_params.as_ref()
+	}
+
+	fn role(&self, is_dev: bool) -> Result<Role> {
+		Ok(match &self.role {
+			Some(role) => role.clone(),
+			None if is_dev => Role::Authority { sentry_nodes: Vec::new() },
+			None => Role::Full,
+		})
+	}
+
+	fn node_name(&self) -> Result<String> {
+		Ok(self.node_name.clone().unwrap_or_else(crate::generate_node_name))
+	}
+
+	fn dev_key_seed(&self, is_dev: bool) -> Result<Option<String>> {
+		Ok(self.dev_key_seed.clone().or_else(|| match (is_dev, &self.role) {
+			(true, None) | (true, Some(Role::Authority { .. })) => Some("//Alice".into()),
+			_ => None,
+		}))
+	}
+
+	fn network_config(
+		&self,
+		chain_spec: &Box<dyn ChainSpec>,
+		_is_dev: bool,
+		net_config_dir: PathBuf,
+		client_id: &str,
+		node_name: &str,
+		node_key: NodeKeyConfig,
+	) -> Result<NetworkConfiguration> {
+		let mut config = NetworkConfiguration::new(
+			node_name,
+			client_id,
+			node_key,
+			Some(net_config_dir),
+		);
+		config.listen_addresses = self.listen_addresses.clone();
+		config.boot_nodes = chain_spec.boot_nodes().to_vec();
+		config.boot_nodes.extend(self.boot_nodes.iter().cloned());
+
+		Ok(config)
+	}
+
+	fn pruning(&self, _unsafe_pruning: bool, _role: &Role) -> Result<PruningMode> {
+		Ok(self.pruning.clone().unwrap_or_default())
+	}
+
+	fn rpc_http(&self) -> Result<Option<SocketAddr>> {
+		Ok(self.rpc_http)
+	}
+
+	fn rpc_ws(&self) -> Result<Option<SocketAddr>> {
+		Ok(self.rpc_ws)
+	}
+
+	fn rpc_methods(&self) -> Result<RpcMethods> {
+		Ok(self.rpc_methods)
+	}
+
+	fn prometheus_config(&self) -> Result<Option<PrometheusConfig>> {
+		Ok(self.prometheus.map(PrometheusConfig::new_with_default_registry))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn defaults_like_the_run_command() {
+		let builder = ConfigurationBuilder::new();
+		assert!(matches!(builder.role(false).unwrap(), Role::Full));
+		assert_eq!(builder.dev_key_seed(false).unwrap(), None);
+		assert_eq!(builder.chain_id(false).unwrap(), "");
+
+		let builder = ConfigurationBuilder::new().with_dev();
+		assert!(builder.is_dev().unwrap());
+		assert!(matches!(builder.role(true).unwrap(), Role::Authority { .. }));
+		assert_eq!(builder.dev_key_seed(true).unwrap(), Some("//Alice".into()));
+		assert_eq!(builder.chain_id(true).unwrap(), "dev");
+
+		let builder = ConfigurationBuilder::new().with_dev().with_role(Role::Light);
+		assert_eq!(builder.dev_key_seed(true).unwrap(), None);
+	}
+}
@@ -38,6 +38,14 @@ pub enum Error {
 	/// Invalid listen multiaddress
 	#[display(fmt="Invalid listen multiaddress")]
 	InvalidListenMultiaddress,
+	/// An address the node should listen on is already in use.
+	#[display(fmt="Address {} is already in use", _0)]
+	#[from(ignore)]
+	PortInUse(std::net::SocketAddr),
+	/// The chain spec doesn't match the chain that was expected.
+	#[display(fmt="Incompatible chain: {}", _0)]
+	#[from(ignore)]
+	IncompatibleChain(String),
 	/// Other uncategorized error.
 	#[from(ignore)]
 	Other(String),
@@ -57,6 +65,56 @@ impl std::convert::From<&str> for Error {
 	}
 }
 
+/// Exit code of the process for each category of errors, so that supervisors can tell them apart.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExitCode {
+	/// Any error that doesn't fit in another category.
+	Failure = 1,
+	/// Invalid command line arguments or configuration.
+	InvalidConfig = 2,
+	/// The database is corrupted, or can't be read by this version.
+	Database = 3,
+	/// An address the node should listen on is already in use.
+	PortInUse = 4,
+	/// The chain spec doesn't match the chain that was expected.
+	IncompatibleChain = 5,
+}
+
+impl Error {
+	/// The exit code that the process should return after failing with this error.
+	///
+	/// Errors of the service and of the client are categorized by their cause, e.g. a database
+	/// error of the client results in `ExitCode::Database`.
+	pub fn exit_code(&self) -> ExitCode {
+		match self {
+			Error::Io(err) => io_exit_code(err),
+			Error::Cli(_) | Error::Input(_) | Error::InvalidListenMultiaddress =>
+				ExitCode::InvalidConfig,
+			Error::Service(sc_service::Error::Io(err)) => io_exit_code(err),
+			Error::Service(sc_service::Error::Client(err)) | Error::Client(err) =>
+				client_exit_code(err),
+			Error::Service(_) | Error::Other(_) => ExitCode::Failure,
+			Error::PortInUse(_) => ExitCode::PortInUse,
+			Error::IncompatibleChain(_) => ExitCode::IncompatibleChain,
+		}
+	}
+}
+
+fn io_exit_code(err: &std::io::Error) -> ExitCode {
+	match err.kind() {
+		std::io::ErrorKind::AddrInUse => ExitCode::PortInUse,
+		_ => ExitCode::Failure,
+	}
+}
+
+fn client_exit_code(err: &sp_blockchain::Error) -> ExitCode {
+	match err {
+		sp_blockchain::Error::Backend(_) => ExitCode::Database,
+		sp_blockchain::Error::Blockchain(err) => client_exit_code(err),
+		_ => ExitCode::Failure,
+	}
+}
+
 impl std::error::Error for Error {
 	fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
 		match self {
@@ -66,7 +124,29 @@ impl std::error::Error for Error {
 			Error::Client(ref err) => Some(err),
 			Error::Input(_) => None,
 			Error::InvalidListenMultiaddress => None,
+			Error::PortInUse(_) => None,
+			Error::IncompatibleChain(_) => None,
 			Error::Other(_) => None,
 		}
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn exit_codes_follow_the_cause() {
+		let addr_in_use = || std::io::Error::new(std::io::ErrorKind::AddrInUse, "in use");
+		assert_eq!(Error::Io(addr_in_use()).exit_code(), ExitCode::PortInUse);
+		assert_eq!(
+			Error::Service(sc_service::Error::Io(addr_in_use())).exit_code(),
+			ExitCode::PortInUse,
+		);
+		let corrupted = sp_blockchain::Error::Backend("DB is corrupted".into());
+		assert_eq!(Error::Service(corrupted.into()).exit_code(), ExitCode::Database);
+		assert_eq!(Error::from("invalid").exit_code(), ExitCode::InvalidConfig);
+		assert_eq!(Error::Other("other".into()).exit_code(), ExitCode::Failure);
+		assert_eq!(Error::IncompatibleChain("spec".into()).exit_code() as i32, 5);
+	}
+}
@@ -22,19 +22,35 @@
 #![warn(unused_extern_crates)]
 
 mod arg_enums;
+mod chain_properties;
+mod chain_sections;
 mod commands;
 mod config;
+mod config_builder;
 mod error;
+mod log_rate_limit;
 mod params;
+mod preflight;
+mod remote_chain_spec;
+mod remote_state;
+mod rpc_client;
 mod runner;
 
 pub use arg_enums::*;
+pub use chain_properties::ChainProperties;
+pub use chain_sections::{parse_chain_sections, ChainSection};
 pub use commands::*;
 pub use config::*;
+pub use config_builder::ConfigurationBuilder;
 pub use error::*;
 use lazy_static::lazy_static;
 use log::info;
+pub use log_rate_limit::LogRateLimit;
+use log_rate_limit::RateLimitedLogger;
 pub use params::*;
+pub use preflight::{
+	check_chains_listen_addresses, check_listen_addresses, check_secret_file_permissions,
+};
 use regex::Regex;
 pub use runner::*;
 use sc_service::{ChainSpec, Configuration, TaskType};
@@ -214,6 +230,12 @@ pub trait SubstrateCli: Sized {
 
 /// Initialize the logger
 pub fn init_logger(pattern: &str) {
+	init_rate_limited_logger(pattern, &[])
+}
+
+/// Initialize the logger, limiting the number of identical messages logged per target according
+/// to `rate_limits`.
+pub fn init_rate_limited_logger(pattern: &str, rate_limits: &[LogRateLimit]) {
 	use ansi_term::Colour;
 
 	let mut builder = env_logger::Builder::new();
@@ -276,7 +298,17 @@ pub fn init_logger(pattern: &str) {
 		writeln!(buf, "{}", output)
 	});
 
-	if builder.try_init().is_err() {
+	let logger = builder.build();
+	let max_level = logger.filter();
+	let logger: Box<dyn log::Log> = if rate_limits.is_empty() {
+		Box::new(logger)
+	} else {
+		Box::new(RateLimitedLogger::new(logger, rate_limits))
+	};
+
+	if log::set_boxed_logger(logger).is_ok() {
+		log::set_max_level(max_level);
+	} else {
 		info!("💬 Not registering Substrate logger, as there is already a global logger registered!");
 	}
 }
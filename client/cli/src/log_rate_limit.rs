@@ -0,0 +1,363 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2020 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Rate limiting of the identical log messages.
+//!
+//! A misbehaving peer can make a target log the same warning many times per second. Once a
+//! message of a limited target has been logged the allowed number of times in a minute, its
+//! next occurrences are only counted, and their number is logged when the minute is over.
+
+use log::{Level, Log, Metadata, Record};
+use std::{
+	collections::HashMap,
+	fmt,
+	str::FromStr,
+	sync::Mutex,
+	time::{Duration, Instant},
+};
+
+/// Period over which the occurrences of a message are counted.
+const PERIOD: Duration = Duration::from_secs(60);
+
+/// Interval at which the messages whose period is over are reported and forgotten.
+const SWEEP_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Maximum number of distinct messages counted at once. Messages beyond are logged unlimited.
+const MAX_COUNTED_MESSAGES: usize = 10_000;
+
+/// Limit of the number of identical messages a target logs per minute.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LogRateLimit {
+	/// Prefix of the targets the limit applies to, or `None` for all the targets.
+	pub target: Option<String>,
+	/// Number of identical messages logged per minute.
+	pub max_messages: u32,
+}
+
+impl FromStr for LogRateLimit {
+	type Err = String;
+
+	/// Parse a limit written `[<target>=]<count>`.
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let (target, count) = match s.rfind('=') {
+			Some(i) => (Some(&s[..i]), &s[i + 1..]),
+			None => (None, s),
+		};
+		if target == Some("") {
+			return Err(format!("Missing target in the log rate limit {:?}", s));
+		}
+		let max_messages = count.parse()
+			.map_err(|e| format!("Invalid count in the log rate limit {:?}: {}", s, e))?;
+
+		Ok(LogRateLimit { target: target.map(Into::into), max_messages })
+	}
+}
+
+/// Occurrences of a message in the current period.
+struct Occurrences {
+	/// Start of the period.
+	start: Instant,
+	/// Level of the first occurrence.
+	level: Level,
+	/// Number of occurrences, the logged ones included.
+	count: u32,
+	/// Number of occurrences logged.
+	max_messages: u32,
+}
+
+/// Occurrences of a message which weren't logged.
+#[derive(Debug, PartialEq, Eq)]
+struct Suppressed {
+	level: Level,
+	target: String,
+	message: String,
+	count: u32,
+}
+
+impl Occurrences {
+	fn suppressed(&self, target: String, message: String) -> Option<Suppressed> {
+		if self.count > self.max_messages {
+			Some(Suppressed {
+				level: self.level,
+				target,
+				message,
+				count: self.count - self.max_messages,
+			})
+		} else {
+			None
+		}
+	}
+}
+
+/// Counts the occurrences of the messages to tell which ones are logged.
+struct RateLimiter {
+	/// The limits, the one with the longest target first.
+	limits: Vec<LogRateLimit>,
+	/// Occurrences of the messages of the limited targets, by target and message.
+	messages: HashMap<(String, String), Occurrences>,
+	/// Last time the messages whose period is over were forgotten.
+	last_sweep: Instant,
+}
+
+impl RateLimiter {
+	fn new(limits: &[LogRateLimit], now: Instant) -> Self {
+		let mut limits = limits.to_vec();
+		limits.sort_by_key(|limit| std::cmp::Reverse(limit.target.as_ref().map_or(0, String::len)));
+		RateLimiter { limits, messages: HashMap::new(), last_sweep: now }
+	}
+
+	/// Returns the limit of `target`. As for the log filters, the limit of the longest target
+	/// prefixing `target` applies.
+	fn limit(&self, target: &str) -> Option<u32> {
+		self.limits.iter()
+			.find(|limit| limit.target.as_ref().map_or(true, |prefix| target.starts_with(prefix)))
+			.map(|limit| limit.max_messages)
+	}
+
+	/// Count an occurrence of `message` and returns whether it is logged.
+	///
+	/// The messages whose period is over are pushed to `suppressed` if some of their occurrences
+	/// weren't logged.
+	fn check(
+		&mut self,
+		level: Level,
+		target: &str,
+		message: fmt::Arguments,
+		now: Instant,
+		suppressed: &mut Vec<Suppressed>,
+	) -> bool {
+		if now.duration_since(self.last_sweep) >= SWEEP_INTERVAL {
+			self.sweep(now, suppressed);
+		}
+
+		let max_messages = match self.limit(target) {
+			Some(max_messages) => max_messages,
+			None => return true,
+		};
+		let key = (target.to_owned(), message.to_string());
+
+		if let Some(occurrences) = self.messages.get_mut(&key) {
+			if now.duration_since(occurrences.start) < PERIOD {
+				occurrences.count = occurrences.count.saturating_add(1);
+				return occurrences.count <= max_messages;
+			}
+			// the period is over but the message hasn't been swept yet
+			suppressed.extend(occurrences.suppressed(key.0.clone(), key.1.clone()));
+			*occurrences = Occurrences { start: now, level, count: 1, max_messages };
+			return max_messages > 0;
+		}
+
+		if self.messages.len() < MAX_COUNTED_MESSAGES {
+			self.messages.insert(key, Occurrences { start: now, level, count: 1, max_messages });
+			max_messages > 0
+		} else {
+			true
+		}
+	}
+
+	/// Forget the messages whose period is over, pushing to `suppressed` the ones with
+	/// occurrences that weren't logged.
+	fn sweep(&mut self, now: Instant, suppressed: &mut Vec<Suppressed>) {
+		self.last_sweep = now;
+
+		let expired = self.messages.iter()
+			.filter(|(_, occurrences)| now.duration_since(occurrences.start) >= PERIOD)
+			.map(|(key, _)| key.clone())
+			.collect::<Vec<_>>();
+		for key in expired {
+			if let Some(occurrences) = self.messages.remove(&key) {
+				suppressed.extend(occurrences.suppressed(key.0, key.1));
+			}
+		}
+	}
+}
+
+/// Logger rate limiting the identical messages of the targets with a limit.
+pub(crate) struct RateLimitedLogger<L> {
+	inner: L,
+	limiter: Mutex<RateLimiter>,
+}
+
+impl<L> RateLimitedLogger<L> {
+	/// Wrap `inner`, limiting its messages according to `limits`.
+	pub(crate) fn new(inner: L, limits: &[LogRateLimit]) -> Self {
+		RateLimitedLogger {
+			inner,
+			limiter: Mutex::new(RateLimiter::new(limits, Instant::now())),
+		}
+	}
+}
+
+impl<L: Log> RateLimitedLogger<L> {
+	/// Log the number of occurrences of the `suppressed` messages.
+	fn log_suppressed(&self, suppressed: Vec<Suppressed>) {
+		for Suppressed { level, target, message, count } in suppressed {
+			self.inner.log(&Record::builder()
+				.level(level)
+				.target(&target)
+				.args(format_args!("suppressed {} similar messages: {}", count, message))
+				.build());
+		}
+	}
+}
+
+impl<L: Log> Log for RateLimitedLogger<L> {
+	fn enabled(&self, metadata: &Metadata) -> bool {
+		self.inner.enabled(metadata)
+	}
+
+	fn log(&self, record: &Record) {
+		if !self.inner.enabled(record.metadata()) {
+			return;
+		}
+
+		let mut suppressed = Vec::new();
+		let logged = self.limiter
+			.lock()
+			.unwrap_or_else(|poisoned| poisoned.into_inner())
+			.check(
+				record.level(),
+				record.target(),
+				*record.args(),
+				Instant::now(),
+				&mut suppressed,
+			);
+
+		self.log_suppressed(suppressed);
+		if logged {
+			self.inner.log(record);
+		}
+	}
+
+	fn flush(&self) {
+		// report the messages whose period is over, which are otherwise only reported with the
+		// next message logged.
+		let mut suppressed = Vec::new();
+		self.limiter
+			.lock()
+			.unwrap_or_else(|poisoned| poisoned.into_inner())
+			.sweep(Instant::now(), &mut suppressed);
+		self.log_suppressed(suppressed);
+
+		self.inner.flush()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn parses_limits() {
+		assert_eq!("10".parse(), Ok(LogRateLimit { target: None, max_messages: 10 }));
+		assert_eq!(
+			"sub-libp2p=5".parse(),
+			Ok(LogRateLimit { target: Some("sub-libp2p".into()), max_messages: 5 }),
+		);
+		assert!("=5".parse::<LogRateLimit>().is_err());
+		assert!("sync=many".parse::<LogRateLimit>().is_err());
+	}
+
+	#[derive(Default)]
+	struct MockLogger {
+		lines: Mutex<Vec<String>>,
+	}
+
+	impl Log for MockLogger {
+		fn enabled(&self, _metadata: &Metadata) -> bool {
+			true
+		}
+
+		fn log(&self, record: &Record) {
+			let line = format!("{} {}: {}", record.level(), record.target(), record.args());
+			self.lines.lock().unwrap().push(line);
+		}
+
+		fn flush(&self) {
+			self.lines.lock().unwrap().push("flush".into());
+		}
+	}
+
+	#[test]
+	fn flush_reports_the_suppressed_messages() {
+		let past = Instant::now().checked_sub(PERIOD).expect("the clock started a minute ago");
+		let logger = RateLimitedLogger {
+			inner: MockLogger::default(),
+			limiter: Mutex::new(RateLimiter::new(
+				&[LogRateLimit { target: None, max_messages: 1 }],
+				past,
+			)),
+		};
+		{
+			let mut limiter = logger.limiter.lock().unwrap();
+			let mut suppressed = Vec::new();
+			for _ in 0..3 {
+				limiter.check(Level::Warn, "sync", format_args!("bad peer"), past, &mut suppressed);
+			}
+			assert!(suppressed.is_empty());
+		}
+
+		logger.flush();
+		assert_eq!(*logger.inner.lines.lock().unwrap(), vec![
+			"WARN sync: suppressed 2 similar messages: bad peer".to_string(),
+			"flush".into(),
+		]);
+	}
+
+	#[test]
+	fn limits_identical_messages_per_target() {
+		let start = Instant::now();
+		let mut limiter = RateLimiter::new(&[
+			LogRateLimit { target: None, max_messages: 3 },
+			LogRateLimit { target: Some("sync".into()), max_messages: 2 },
+		], start);
+		let mut suppressed = Vec::new();
+		let mut check = |level, target, message: &str, now| {
+			limiter.check(level, target, format_args!("{}", message), now, &mut suppressed)
+		};
+
+		let logged = (0..5)
+			.map(|_| check(Level::Warn, "sync::peer", "bad peer 1", start))
+			.collect::<Vec<_>>();
+		assert_eq!(logged, vec![true, true, false, false, false]);
+		// other messages and targets are counted on their own
+		assert!(check(Level::Warn, "sync", "bad peer 2", start));
+		for _ in 0..3 {
+			assert!(check(Level::Info, "db", "bad peer 1", start));
+		}
+		assert!(!check(Level::Info, "db", "bad peer 1", start));
+
+		// the suppressed messages are reported once their period is over
+		assert!(check(Level::Warn, "sync::peer", "bad peer 1", start + PERIOD));
+		suppressed.sort_by(|a, b| a.target.cmp(&b.target));
+		assert_eq!(suppressed, vec![
+			Suppressed {
+				level: Level::Info,
+				target: "db".into(),
+				message: "bad peer 1".into(),
+				count: 1,
+			},
+			Suppressed {
+				level: Level::Warn,
+				target: "sync::peer".into(),
+				message: "bad peer 1".into(),
+				count: 3,
+			},
+		]);
+	}
+}
@@ -16,7 +16,10 @@
 // You should have received a copy of the GNU General Public License
 // along with this program. If not, see <https://www.gnu.org/licenses/>.
 
-use crate::arg_enums::Database;
+use crate::arg_enums::{Database, DatabaseCompression};
+use crate::error::{Error, Result};
+use sc_service::config::BlockCompression;
+use std::path::PathBuf;
 use structopt::StructOpt;
 
 /// Parameters for block import.
@@ -34,6 +37,46 @@ pub struct DatabaseParams {
 	/// Limit the memory the database cache can use.
 	#[structopt(long = "db-cache", value_name = "MiB")]
 	pub database_cache_size: Option<usize>,
+
+	/// Move the bodies of old finalized blocks to a database at this path.
+	///
+	/// Headers and justifications stay in the main database.
+	#[structopt(long = "cold-db-path", value_name = "PATH", parse(from_os_str))]
+	pub cold_database_path: Option<PathBuf>,
+
+	/// Number of finalized blocks whose bodies are kept in the main database when
+	/// `--cold-db-path` is set.
+	#[structopt(long = "cold-db-depth", value_name = "COUNT", default_value = "65536")]
+	pub cold_database_depth: u32,
+
+	/// Open the existing database read-only, e.g. to inspect it without risking to modify it.
+	///
+	/// Only supported by the subcommands inspecting the database, and refused when running a
+	/// node. The command exits with an error as soon as it tries to write to the database. A
+	/// RocksDB database is opened as a secondary instance, so it can be inspected while a node
+	/// runs on it.
+	#[structopt(long = "db-read-only")]
+	pub database_read_only: bool,
+
+	/// Compression of the block bodies and justifications written to the database.
+	///
+	/// The values already stored are read whatever their compression, so this can be changed
+	/// at any time. It only applies to the blocks imported from then on. The ParityDb and SubDb
+	/// databases created by earlier versions of the node can't be upgraded to store compressed
+	/// values, and ignore it.
+	#[structopt(
+		long = "db-compression",
+		value_name = "ALGORITHM",
+		possible_values = &DatabaseCompression::variants(),
+		case_insensitive = true,
+		default_value = "None"
+	)]
+	pub database_compression: DatabaseCompression,
+
+	/// Level of the zstd compression, from 1 to 22. Higher levels compress better but more
+	/// slowly.
+	#[structopt(long = "db-compression-level", value_name = "LEVEL", default_value = "3")]
+	pub database_compression_level: i32,
 }
 
 impl DatabaseParams {
@@ -46,4 +89,36 @@ impl DatabaseParams {
 	pub fn database_cache_size(&self) -> Option<usize> {
 		self.database_cache_size
 	}
+
+	/// Path of the cold database and number of block bodies kept in the main database.
+	pub fn cold_database(&self) -> Option<(PathBuf, u32)> {
+		self.cold_database_path.clone().map(|path| (path, self.cold_database_depth))
+	}
+
+	/// Whether the database is opened read-only by a command, which only inspects the database
+	/// if `inspecting`.
+	///
+	/// `--db-read-only` is refused for the commands which may write to the database, e.g. to run
+	/// a node.
+	pub fn database_read_only(&self, inspecting: bool) -> Result<bool> {
+		if self.database_read_only && !inspecting {
+			return Err(Error::Input(
+				"`--db-read-only` is only supported by the commands inspecting the database".into(),
+			));
+		}
+		Ok(self.database_read_only)
+	}
+
+	/// Compression of the block bodies and justifications written to the database.
+	pub fn block_compression(&self) -> Result<BlockCompression> {
+		match self.database_compression {
+			DatabaseCompression::None => Ok(BlockCompression::None),
+			DatabaseCompression::Zstd => match self.database_compression_level {
+				level @ 1..=22 => Ok(BlockCompression::Zstd { level }),
+				level => Err(Error::Input(format!(
+					"Invalid zstd compression level {}: expected a level from 1 to 22", level,
+				))),
+			},
+		}
+	}
 }
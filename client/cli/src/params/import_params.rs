@@ -17,7 +17,7 @@
 // along with this program. If not, see <https://www.gnu.org/licenses/>.
 
 use crate::arg_enums::{
-	ExecutionStrategy, TracingReceiver, WasmExecutionMethod,
+	ExecutionStrategy, Profile, TracingReceiver, WasmExecutionMethod, DEFAULT_CACHE_SIZE,
 	DEFAULT_EXECUTION_BLOCK_CONSTRUCTION, DEFAULT_EXECUTION_IMPORT_BLOCK,
 	DEFAULT_EXECUTION_OFFCHAIN_WORKER, DEFAULT_EXECUTION_OTHER, DEFAULT_EXECUTION_SYNCING,
 };
@@ -59,13 +59,22 @@ pub struct ImportParams {
 	#[structopt(flatten)]
 	pub execution_strategies: ExecutionStrategiesParams,
 
-	/// Specify the state cache size.
-	#[structopt(
-		long = "state-cache-size",
-		value_name = "Bytes",
-		default_value = "67108864"
-	)]
-	pub state_cache_size: usize,
+	/// Verify the signatures batched by the runtime one after the other, on the thread executing
+	/// the block, rather than in parallel on the threads of the node.
+	#[structopt(long = "sequential-signature-verification")]
+	pub sequential_signature_verification: bool,
+
+	/// Specify the size of the trie node cache, shared by all states.
+	///
+	/// Defaults to 64 MiB, or to the value of the `--profile`.
+	#[structopt(long = "trie-cache-size", value_name = "Bytes")]
+	pub trie_cache_size: Option<usize>,
+
+	/// Specify the size of the storage value cache.
+	///
+	/// Defaults to 64 MiB, or to the value of the `--profile`.
+	#[structopt(long = "value-cache-size", alias = "state-cache-size", value_name = "Bytes")]
+	pub value_cache_size: Option<usize>,
 
 	/// Comma separated list of targets for tracing.
 	#[structopt(long = "tracing-targets", value_name = "TARGETS")]
@@ -93,9 +102,16 @@ impl ImportParams {
 		self.tracing_targets.clone()
 	}
 
-	/// Specify the state cache size.
-	pub fn state_cache_size(&self) -> usize {
-		self.state_cache_size
+	/// Specify the trie node cache size.
+	pub fn trie_cache_size(&self, profile: Option<Profile>) -> usize {
+		self.trie_cache_size
+			.unwrap_or_else(|| profile.map_or(DEFAULT_CACHE_SIZE, Profile::cache_size))
+	}
+
+	/// Specify the storage value cache size.
+	pub fn value_cache_size(&self, profile: Option<Profile>) -> usize {
+		self.value_cache_size
+			.unwrap_or_else(|| profile.map_or(DEFAULT_CACHE_SIZE, Profile::cache_size))
 	}
 
 	/// Get the WASM execution method from the parameters
@@ -103,6 +119,15 @@ impl ImportParams {
 		self.wasm_method.into()
 	}
 
+	/// Get how the signatures batched by the runtime are verified.
+	pub fn signature_verification(&self) -> sc_service::config::SignatureVerification {
+		if self.sequential_signature_verification {
+			sc_service::config::SignatureVerification::Sequential
+		} else {
+			sc_service::config::SignatureVerification::Parallel
+		}
+	}
+
 	/// Get execution strategies for the parameters
 	pub fn execution_strategies(
 		&self,
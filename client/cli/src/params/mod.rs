@@ -95,6 +95,11 @@ impl FromStr for BlockNumberOrHash {
 }
 
 impl BlockNumberOrHash {
+	/// The block hash or number, as given.
+	pub fn as_str(&self) -> &str {
+		&self.0
+	}
+
 	/// Parse the inner value as `BlockId`.
 	pub fn parse<B: BlockT>(&self) -> Result<BlockId<B>, String>
 	where
@@ -16,13 +16,17 @@
 // You should have received a copy of the GNU General Public License
 // along with this program. If not, see <https://www.gnu.org/licenses/>.
 
+use crate::arg_enums::{Profile, SyncPeerStrategy, DEFAULT_IN_PEERS, DEFAULT_OUT_PEERS};
+use crate::config::DEFAULT_P2P_PORT;
 use crate::params::node_key_params::NodeKeyParams;
 use sc_network::{
 	config::{NetworkConfiguration, NodeKeyConfig, NonReservedPeerMode, TransportConfig},
 	multiaddr::Protocol,
 };
 use sc_service::{ChainSpec, config::{Multiaddr, MultiaddrWithPeerId}};
+use std::num::{NonZeroU32, NonZeroU64};
 use std::path::PathBuf;
+use std::time::Duration;
 use structopt::StructOpt;
 
 /// Parameters used to create the network configuration.
@@ -63,12 +67,36 @@ pub struct NetworkParams {
 	pub no_private_ipv4: bool,
 
 	/// Specify the number of outgoing connections we're trying to maintain.
-	#[structopt(long = "out-peers", value_name = "COUNT", default_value = "25")]
-	pub out_peers: u32,
+	///
+	/// Defaults to 25, or to the value of the `--profile`.
+	#[structopt(long = "out-peers", value_name = "COUNT")]
+	pub out_peers: Option<u32>,
 
 	/// Specify the maximum number of incoming connections we're accepting.
-	#[structopt(long = "in-peers", value_name = "COUNT", default_value = "25")]
-	pub in_peers: u32,
+	///
+	/// Defaults to 25, or to the value of the `--profile`.
+	#[structopt(long = "in-peers", value_name = "COUNT")]
+	pub in_peers: Option<u32>,
+
+	/// Specify the maximum number of light client peers.
+	///
+	/// Light client peers count towards `--in-peers` and `--out-peers`. By default, they
+	/// aren't limited separately.
+	#[structopt(long = "max-light-peers", value_name = "COUNT")]
+	pub max_light_peers: Option<u32>,
+
+	/// Which peers to keep connected while performing a major sync.
+	///
+	/// `PreferAhead` disconnects peers that aren't ahead of us, to free their slots for peers
+	/// that blocks can be downloaded from. Reserved nodes and boot nodes are always kept.
+	#[structopt(
+		long = "sync-peer-strategy",
+		value_name = "STRATEGY",
+		possible_values = &SyncPeerStrategy::variants(),
+		case_insensitive = true,
+		default_value = "Any"
+	)]
+	pub sync_peer_strategy: SyncPeerStrategy,
 
 	/// Disable mDNS discovery.
 	///
@@ -88,6 +116,25 @@ pub struct NetworkParams {
 	)]
 	pub max_parallel_downloads: u32,
 
+	/// Maximum number of blocks to ask for in a single block request.
+	///
+	/// Larger requests speed up syncing over fast links, smaller ones avoid timeouts with slow
+	/// peers.
+	#[structopt(
+		long = "max-blocks-per-request",
+		value_name = "COUNT",
+		default_value = "128"
+	)]
+	pub max_blocks_per_request: NonZeroU32,
+
+	/// Time (in seconds) after which a block request without response is considered failed.
+	#[structopt(
+		long = "sync-request-timeout",
+		value_name = "SECONDS",
+		default_value = "40"
+	)]
+	pub sync_request_timeout: NonZeroU64,
+
 	#[allow(missing_docs)]
 	#[structopt(flatten)]
 	pub node_key_params: NodeKeyParams,
@@ -107,6 +154,44 @@ pub struct NetworkParams {
 	/// This option will be removed in the future.
 	#[structopt(long)]
 	pub legacy_network_protocol: bool,
+
+	/// Period (in seconds) after which consensus gossip messages are rebroadcast to peers when
+	/// nothing new has been gossiped.
+	///
+	/// Defaults to the value chosen by each gossip protocol. Raise it on high-latency links.
+	///
+	/// Block announcements have no equivalent option: they are sent as soon as a block is
+	/// imported, not periodically.
+	#[structopt(long = "gossip-rebroadcast-period", value_name = "SECONDS")]
+	pub gossip_rebroadcast_period: Option<u64>,
+
+	/// Number of threads checking the signatures of incoming consensus gossip messages.
+	///
+	/// With large validator sets, checking them in the gossip task can delay the processing of
+	/// other messages. With 0, they are checked by the gossip task.
+	#[structopt(long = "gossip-validation-workers", value_name = "COUNT", default_value = "0")]
+	pub gossip_validation_workers: usize,
+
+	/// Maximum number of requests served to each light client per second.
+	///
+	/// Requests above this rate are ignored and lower the reputation of the light client.
+	#[structopt(
+		long = "max-light-requests-per-second",
+		value_name = "COUNT",
+		default_value = "32"
+	)]
+	pub max_light_requests_per_second: u32,
+
+	/// Maximum size (in MiB) of a response served to a light client.
+	///
+	/// Requests whose response would be larger, such as storage proofs of many keys, are not
+	/// answered.
+	#[structopt(
+		long = "max-light-response-size",
+		value_name = "MiB",
+		default_value = "16"
+	)]
+	pub max_light_response_size: usize,
 }
 
 impl NetworkParams {
@@ -119,8 +204,11 @@ impl NetworkParams {
 		client_id: &str,
 		node_name: &str,
 		node_key: NodeKeyConfig,
+		profile: Option<Profile>,
 	) -> NetworkConfiguration {
-		let port = self.port.unwrap_or(30333);
+		let port = self.port.unwrap_or(DEFAULT_P2P_PORT);
+		let (in_peers, out_peers) = profile
+			.map_or((DEFAULT_IN_PEERS, DEFAULT_OUT_PEERS), Profile::peers);
 
 		let listen_addresses = if self.listen_addr.is_empty() {
 			vec![
@@ -152,11 +240,14 @@ impl NetworkParams {
 			listen_addresses,
 			public_addresses,
 			notifications_protocols: Vec::new(),
+			request_response_protocols: Vec::new(),
 			node_key,
 			node_name: node_name.to_string(),
 			client_version: client_id.to_string(),
-			in_peers: self.in_peers,
-			out_peers: self.out_peers,
+			in_peers: self.in_peers.unwrap_or(in_peers),
+			out_peers: self.out_peers.unwrap_or(out_peers),
+			max_light_peers: self.max_light_peers,
+			sync_peer_strategy: self.sync_peer_strategy.into(),
 			transport: TransportConfig::Normal {
 				enable_mdns: !is_dev && !self.no_mdns,
 				allow_private_ipv4: !self.no_private_ipv4,
@@ -164,8 +255,14 @@ impl NetworkParams {
 				use_yamux_flow_control: !self.no_yamux_flow_control,
 			},
 			max_parallel_downloads: self.max_parallel_downloads,
+			max_blocks_per_request: self.max_blocks_per_request.get(),
+			block_request_timeout: Duration::from_secs(self.sync_request_timeout.get()),
 			allow_non_globals_in_dht: self.discover_local || is_dev,
 			use_new_block_requests_protocol: !self.legacy_network_protocol,
+			gossip_rebroadcast_period: self.gossip_rebroadcast_period.map(Duration::from_secs),
+			gossip_validation_workers: self.gossip_validation_workers,
+			max_light_requests_per_sec: self.max_light_requests_per_second,
+			max_light_response_size: self.max_light_response_size.saturating_mul(1024 * 1024),
 		}
 	}
 }
@@ -26,9 +26,11 @@
 use structopt::StructOpt;
 use sc_service::config::OffchainWorkerConfig;
 use sc_network::config::Role;
+use sc_service::config::OffchainHttpConfig as HttpConfig;
+use std::{num::NonZeroU32, time::Duration};
 
 use crate::error;
-use crate::OffchainWorkerEnabled;
+use crate::{OffchainWorkerEnabled, OffchainWorkerTrigger};
 
 
 /// Offchain worker related parameters.
@@ -49,12 +51,62 @@ pub struct OffchainWorkerParams {
 	/// Enable Offchain Indexing API, which allows block import to write to Offchain DB.
 	///
 	/// Enables a runtime to write directly to a offchain workers
-	/// DB during block import.
+	/// DB during block import. This is independent of whether the node
+	/// runs offchain workers itself, so indexer nodes can persist the
+	/// data without executing workers.
 	#[structopt(
 		long = "enable-offchain-indexing",
 		value_name = "ENABLE_OFFCHAIN_INDEXING"
 	)]
 	pub indexing_enabled: bool,
+
+	/// Abort offchain worker HTTP requests that don't complete within this many milliseconds.
+	#[structopt(long = "offchain-http-timeout", value_name = "MILLISECONDS")]
+	pub http_timeout: Option<u64>,
+
+	/// Maximum number of HTTP requests a single offchain worker run may have in progress at once.
+	#[structopt(long = "offchain-http-max-requests", value_name = "COUNT")]
+	pub http_max_requests: Option<usize>,
+
+	/// Host that offchain workers may send HTTP requests to.
+	///
+	/// Can be given multiple times. By default requests to any host are allowed.
+	#[structopt(long = "offchain-http-allowed-host", value_name = "HOST")]
+	pub http_allowed_hosts: Vec<String>,
+
+	/// Send all offchain worker HTTP requests through the given proxy.
+	///
+	/// The requests fail, rather than being sent directly, if the proxy can't be set up.
+	#[structopt(long = "offchain-http-proxy", value_name = "URL")]
+	pub http_proxy: Option<String>,
+
+	/// Number of threads offchain workers run on.
+	///
+	/// By default one thread per CPU is used.
+	#[structopt(long = "offchain-worker-threads", value_name = "COUNT")]
+	pub threads: Option<usize>,
+
+	/// Abort the pending HTTP requests of an offchain worker run after this many milliseconds.
+	#[structopt(long = "offchain-worker-timeout", value_name = "MILLISECONDS")]
+	pub timeout: Option<u64>,
+
+	/// Blocks that trigger an offchain worker run.
+	#[structopt(
+		long = "offchain-worker-trigger",
+		value_name = "TRIGGER",
+		possible_values = &OffchainWorkerTrigger::variants(),
+		case_insensitive = true,
+		default_value = "BestBlock"
+	)]
+	pub trigger: OffchainWorkerTrigger,
+
+	/// Only run offchain workers on blocks whose number is a multiple of this.
+	#[structopt(long = "offchain-worker-block-interval", value_name = "BLOCKS")]
+	pub block_interval: Option<NonZeroU32>,
+
+	/// Don't run offchain workers while the node is major syncing.
+	#[structopt(long = "offchain-worker-only-when-synced")]
+	pub only_when_synced: bool,
 }
 
 impl OffchainWorkerParams {
@@ -65,14 +117,39 @@ impl OffchainWorkerParams {
 	) -> error::Result<OffchainWorkerConfig>
 	{
 		let enabled = match (&self.enabled, role) {
+			// Light clients don't have the state that offchain workers run on.
+			(_, Role::Light) => false,
 			(OffchainWorkerEnabled::WhenValidating, Role::Authority { .. }) => true,
 			(OffchainWorkerEnabled::Always, _) => true,
 			(OffchainWorkerEnabled::Never, _) => false,
 			(OffchainWorkerEnabled::WhenValidating, _) => false,
 		};
 
-		let indexing_enabled = enabled && self.indexing_enabled;
+		let proxy = self.http_proxy.as_ref()
+			.map(|proxy| proxy.parse().map_err(|e| error::Error::Input(
+				format!("Invalid offchain HTTP proxy `{}`: {}", proxy, e)
+			)))
+			.transpose()?;
+		let http = HttpConfig {
+			request_timeout: self.http_timeout.map(Duration::from_millis),
+			max_requests: self.http_max_requests,
+			allowed_hosts: if self.http_allowed_hosts.is_empty() {
+				None
+			} else {
+				Some(self.http_allowed_hosts.clone())
+			},
+			proxy,
+		};
 
-		Ok(OffchainWorkerConfig { enabled, indexing_enabled })
+		Ok(OffchainWorkerConfig {
+			enabled,
+			indexing_enabled: self.indexing_enabled,
+			http,
+			threads: self.threads,
+			timeout: self.timeout.map(Duration::from_millis),
+			trigger: self.trigger.into(),
+			block_interval: self.block_interval,
+			only_when_synced: self.only_when_synced,
+		})
 	}
 }
@@ -23,7 +23,11 @@ use structopt::StructOpt;
 /// Parameters to define the pruning mode
 #[derive(Debug, StructOpt, Clone)]
 pub struct PruningParams {
-	/// Specify the state pruning mode, a number of blocks to keep or 'archive'.
+	/// Specify the state pruning mode, a number of blocks to keep, 'archive' or
+	/// 'archive-canonical'.
+	///
+	/// 'archive-canonical' keeps the state of all the blocks of the canonical chain but discards
+	/// the state of forks once they're pruned, which needs less disk space than 'archive'.
 	///
 	/// Default is to keep all block states if the node is running as a
 	/// validator (i.e. 'archive'), otherwise state is only kept for the last
@@ -41,6 +45,7 @@ impl PruningParams {
 		// unless `unsafe_pruning` is set.
 		Ok(match &self.pruning {
 			Some(ref s) if s == "archive" => PruningMode::ArchiveAll,
+			Some(ref s) if s == "archive-canonical" => PruningMode::ArchiveCanonical,
 			None if role.is_network_authority() => PruningMode::ArchiveAll,
 			None => PruningMode::default(),
 			Some(s) => {
@@ -52,10 +57,42 @@ impl PruningParams {
 					));
 				}
 
-				PruningMode::keep_blocks(s.parse().map_err(|_| {
+				let blocks = s.parse().map_err(|_| {
 					error::Error::Input("Invalid pruning mode specified".to_string())
-				})?)
+				})?;
+				if blocks == 0 {
+					return Err(error::Error::Input(
+						"The state of at least one block must be kept".to_string(),
+					));
+				}
+				PruningMode::keep_blocks(blocks)
 			}
 		})
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn pruning(mode: &str, role: &Role) -> error::Result<PruningMode> {
+		PruningParams { pruning: Some(mode.into()) }.pruning(false, role)
+	}
+
+	#[test]
+	fn parses_pruning_modes() {
+		let role = Role::Full;
+		assert_eq!(pruning("archive", &role).unwrap(), PruningMode::ArchiveAll);
+		assert_eq!(pruning("archive-canonical", &role).unwrap(), PruningMode::ArchiveCanonical);
+		assert_eq!(pruning("1000", &role).unwrap(), PruningMode::keep_blocks(1000));
+		assert!(pruning("none", &role).is_err());
+		assert!(pruning("0", &role).is_err());
+	}
+
+	#[test]
+	fn archive_canonical_is_allowed_for_authorities() {
+		let role = Role::Authority { sentry_nodes: Vec::new() };
+		assert_eq!(pruning("archive-canonical", &role).unwrap(), PruningMode::ArchiveCanonical);
+		assert!(pruning("1000", &role).is_err());
+	}
+}
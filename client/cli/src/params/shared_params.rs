@@ -16,6 +16,7 @@
 // You should have received a copy of the GNU General Public License
 // along with this program. If not, see <https://www.gnu.org/licenses/>.
 
+use crate::LogRateLimit;
 use std::path::PathBuf;
 use structopt::StructOpt;
 
@@ -23,9 +24,19 @@ use structopt::StructOpt;
 #[derive(Debug, StructOpt, Clone)]
 pub struct SharedParams {
 	/// Specify the chain specification (one of dev, local, or staging).
+	///
+	/// An `http://` or `https://` URL can be given to download the chain specification. It is
+	/// cached under the base path and reused if the download fails.
 	#[structopt(long, value_name = "CHAIN_SPEC")]
 	pub chain: Option<String>,
 
+	/// Expected hex encoded sha256 checksum of a chain specification downloaded from a URL.
+	///
+	/// The download fails if the checksum doesn't match. A cached chain specification with a
+	/// matching checksum is used without downloading it again.
+	#[structopt(long, value_name = "HEX", requires = "chain")]
+	pub chain_spec_sha256: Option<String>,
+
 	/// Specify the development chain.
 	#[structopt(long, conflicts_with_all = &["chain"])]
 	pub dev: bool,
@@ -39,12 +50,51 @@ pub struct SharedParams {
 	)]
 	pub base_path: Option<PathBuf>,
 
+	/// Specify a custom path for the chain data, i.e. the databases.
+	///
+	/// The databases are stored under `<PATH>/chains/<chain id>` rather than under the base path,
+	/// which keeps the keystore and the network key. This allows putting the large databases on a
+	/// different volume.
+	#[structopt(long, value_name = "PATH", parse(from_os_str))]
+	pub data_path: Option<PathBuf>,
+
+	/// Run a named instance of the chain, isolated from the other instances.
+	///
+	/// The databases, the keystore and the network key of the instance are stored under
+	/// `chains/<chain id>/profiles/<NAME>` rather than `chains/<chain id>`, which allows running
+	/// several nodes of the same chain with a single base path. The name may only contain ASCII
+	/// letters, digits, `-` and `_`.
+	///
+	/// The default p2p, RPC and Prometheus ports of each instance are shifted by a multiple of 100
+	/// allocated on its first run.
+	#[structopt(long, value_name = "NAME", parse(try_from_str = parse_profile_name))]
+	pub profile_name: Option<String>,
+
 	/// Sets a custom logging filter. Syntax is <target>=<level>, e.g. -lsync=debug.
 	///
 	/// Log levels (least to most verbose) are error, warn, info, debug, and trace.
 	/// By default, all targets log `info`. The global log level can be set with -l<level>.
 	#[structopt(short = "l", long, value_name = "LOG_PATTERN")]
 	pub log: Vec<String>,
+
+	/// Limit the number of identical messages logged per target every minute.
+	///
+	/// Syntax is [<target>=]<count>, e.g. --log-rate-limit sub-libp2p=10. The limit of the
+	/// longest target prefixing the target of a message applies, and a limit without target
+	/// applies to all the targets. The number of suppressed messages is logged once the minute
+	/// is over. Messages aren't limited by default.
+	#[structopt(long, value_name = "[TARGET=]COUNT")]
+	pub log_rate_limit: Vec<LogRateLimit>,
+
+	/// Render the SS58 addresses with this prefix rather than the `ss58Format` of the chain spec.
+	///
+	/// Either a number or the name of a known network, e.g. `polkadot` or `kusama`.
+	#[structopt(
+		long,
+		value_name = "PREFIX",
+		parse(try_from_str = crate::chain_properties::parse_ss58_prefix)
+	)]
+	pub ss58_prefix: Option<u8>,
 }
 
 impl SharedParams {
@@ -53,6 +103,16 @@ impl SharedParams {
 		self.base_path.clone()
 	}
 
+	/// Specify custom path for the chain data.
+	pub fn data_path(&self) -> Option<PathBuf> {
+		self.data_path.clone()
+	}
+
+	/// Get the name of the instance of the chain, if any.
+	pub fn profile_name(&self) -> Option<&str> {
+		self.profile_name.as_ref().map(String::as_str)
+	}
+
 	/// Specify the development chain.
 	pub fn is_dev(&self) -> bool {
 		self.dev
@@ -72,8 +132,51 @@ impl SharedParams {
 		}
 	}
 
+	/// Get the expected checksum of a remote chain spec, if any.
+	pub fn chain_spec_sha256(&self) -> Option<&str> {
+		self.chain_spec_sha256.as_ref().map(String::as_str)
+	}
+
 	/// Get the filters for the logging
 	pub fn log_filters(&self) -> &[String] {
 		&self.log
 	}
+
+	/// Get the limits of the number of identical messages logged per target.
+	pub fn log_rate_limits(&self) -> &[LogRateLimit] {
+		&self.log_rate_limit
+	}
+
+	/// Get the SS58 prefix overriding the one of the chain spec, if any.
+	pub fn ss58_prefix(&self) -> Option<u8> {
+		self.ss58_prefix
+	}
+}
+
+/// Parse the name of an instance of the chain, which is used as a directory name.
+pub(crate) fn parse_profile_name(name: &str) -> Result<String, String> {
+	if name.is_empty() {
+		return Err("The profile name can't be empty".into());
+	}
+	if !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+		return Err(format!(
+			"Invalid profile name {:?}: only ASCII letters, digits, '-' and '_' are allowed",
+			name,
+		));
+	}
+	Ok(name.into())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn profile_names_are_plain_directory_names() {
+		assert_eq!(parse_profile_name("validator-1_b"), Ok("validator-1_b".into()));
+		assert!(parse_profile_name("").is_err());
+		assert!(parse_profile_name("..").is_err());
+		assert!(parse_profile_name("a/b").is_err());
+		assert!(parse_profile_name("a b").is_err());
+	}
 }
@@ -16,35 +16,86 @@
 // You should have received a copy of the GNU General Public License
 // along with this program. If not, see <https://www.gnu.org/licenses/>.
 
-use sc_service::config::TransactionPoolOptions;
+use crate::arg_enums::{Profile, DEFAULT_POOL_KBYTES, DEFAULT_POOL_LIMIT};
+use crate::error::{Error, Result};
+use sc_service::config::{PoolMemoryLimitsConfig, TransactionPoolOptions};
+use std::time::Duration;
 use structopt::StructOpt;
 
+/// Time between two checks of the memory used by the node.
+const POOL_MEMORY_CHECK_INTERVAL: Duration = Duration::from_secs(5);
+
 /// Parameters used to create the pool configuration.
 #[derive(Debug, StructOpt, Clone)]
 pub struct TransactionPoolParams {
 	/// Maximum number of transactions in the transaction pool.
-	#[structopt(long = "pool-limit", value_name = "COUNT", default_value = "8192")]
-	pub pool_limit: usize,
+	///
+	/// Defaults to 8192, or to the value of the `--profile`.
+	#[structopt(long = "pool-limit", value_name = "COUNT")]
+	pub pool_limit: Option<usize>,
 
 	/// Maximum number of kilobytes of all transactions stored in the pool.
-	#[structopt(long = "pool-kbytes", value_name = "COUNT", default_value = "20480")]
-	pub pool_kbytes: usize,
+	///
+	/// Defaults to 20480, or to the value of the `--profile`.
+	#[structopt(long = "pool-kbytes", value_name = "COUNT")]
+	pub pool_kbytes: Option<usize>,
+
+	/// Memory used by the node in MiB above which only half of the pool limits are enforced,
+	/// until the memory used is back below.
+	///
+	/// By default the pool limits don't depend on the memory used.
+	#[structopt(long = "pool-memory-soft-limit", value_name = "MiB")]
+	pub pool_memory_soft_limit: Option<u64>,
+
+	/// Memory used by the node in MiB above which only a tenth of the pool limits are enforced.
+	///
+	/// Defaults to one and a half times `--pool-memory-soft-limit`.
+	#[structopt(
+		long = "pool-memory-hard-limit",
+		value_name = "MiB",
+		requires = "pool-memory-soft-limit"
+	)]
+	pub pool_memory_hard_limit: Option<u64>,
 }
 
 impl TransactionPoolParams {
 	/// Fill the given `PoolConfiguration` by looking at the cli parameters.
-	pub fn transaction_pool(&self) -> TransactionPoolOptions {
+	pub fn transaction_pool(&self, profile: Option<Profile>) -> TransactionPoolOptions {
 		let mut opts = TransactionPoolOptions::default();
+		let (pool_limit, pool_kbytes) = profile
+			.map_or((DEFAULT_POOL_LIMIT, DEFAULT_POOL_KBYTES), Profile::pool_limits);
+		let pool_limit = self.pool_limit.unwrap_or(pool_limit);
+		let pool_kbytes = self.pool_kbytes.unwrap_or(pool_kbytes);
 
 		// ready queue
-		opts.ready.count = self.pool_limit;
-		opts.ready.total_bytes = self.pool_kbytes * 1024;
+		opts.ready.count = pool_limit;
+		opts.ready.total_bytes = pool_kbytes * 1024;
 
 		// future queue
 		let factor = 10;
-		opts.future.count = self.pool_limit / factor;
-		opts.future.total_bytes = self.pool_kbytes * 1024 / factor;
+		opts.future.count = pool_limit / factor;
+		opts.future.total_bytes = pool_kbytes * 1024 / factor;
 
 		opts
 	}
+
+	/// Get the memory limits above which the pool limits are reduced, if any.
+	pub fn pool_memory_limits(&self) -> Result<Option<PoolMemoryLimitsConfig>> {
+		let soft_limit = match self.pool_memory_soft_limit {
+			Some(soft_limit) => soft_limit,
+			None => return Ok(None),
+		};
+		let hard_limit = self.pool_memory_hard_limit.unwrap_or(soft_limit + soft_limit / 2);
+		if hard_limit < soft_limit {
+			return Err(Error::Input(
+				"--pool-memory-hard-limit must not be below --pool-memory-soft-limit".into()
+			));
+		}
+
+		Ok(Some(PoolMemoryLimitsConfig {
+			soft_limit: soft_limit << 20,
+			hard_limit: hard_limit << 20,
+			interval: POOL_MEMORY_CHECK_INTERVAL,
+		}))
+	}
 }
@@ -0,0 +1,216 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2020 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Checks of the addresses the node listens on and of the permissions of its secret key files,
+//! done before any service is started.
+
+use crate::error::{Error, Result};
+use log::{error, warn};
+use sc_network::config::{NodeKeyConfig, Secret, TransportConfig};
+use sc_network::multiaddr::{Multiaddr, Protocol};
+use sc_service::Configuration;
+use std::fs;
+use std::net::{SocketAddr, TcpListener};
+use std::path::Path;
+
+/// Check that all the addresses of the RPC servers, of the Prometheus exporter and of the
+/// network can be listened on.
+///
+/// Every problem is logged, and the first one is returned. Addresses with port 0 are not checked.
+/// Note that the RPC servers would otherwise fall back to a random port if theirs is in use.
+pub fn check_listen_addresses(config: &Configuration) -> Result<()> {
+	check_chains_listen_addresses(&[config])
+}
+
+/// Like [`check_listen_addresses`], for the chains run in the same process, which also must not
+/// listen on the addresses of each other.
+pub fn check_chains_listen_addresses(configs: &[&Configuration]) -> Result<()> {
+	let mut problems = Vec::new();
+	let mut addresses = Vec::new();
+
+	for config in configs {
+		addresses.extend(config.rpc_http.map(|address| ("RPC", address)));
+		addresses.extend(config.rpc_ws.map(|address| ("WebSocket RPC", address)));
+		addresses.extend(
+			config.prometheus_config.as_ref().map(|config| ("Prometheus", config.port))
+		);
+
+		if let TransportConfig::Normal { .. } = config.network.transport {
+			for address in &config.network.listen_addresses {
+				match network_socket_address(address) {
+					Ok(Some(address)) => addresses.push(("network", address)),
+					Ok(None) => {},
+					Err(e) => problems.push(e),
+				}
+			}
+		}
+	}
+
+	for (index, &(service, address)) in addresses.iter().enumerate() {
+		if address.port() == 0 {
+			continue;
+		}
+
+		let duplicate = addresses[..index].iter().find(|(_, other)| *other == address);
+		if let Some((other_service, _)) = duplicate {
+			problems.push(Error::Input(format!(
+				"The {} and {} servers are both configured to listen on {}",
+				other_service, service, address,
+			)));
+		} else if let Err(e) = TcpListener::bind(address) {
+			problems.push(match e.kind() {
+				std::io::ErrorKind::AddrInUse => Error::PortInUse(address),
+				_ => Error::Input(format!("Can't listen on {} for {}: {}", address, service, e)),
+			});
+		}
+	}
+
+	if problems.len() > 1 {
+		for problem in &problems {
+			error!("❌ {}", problem);
+		}
+	}
+
+	match problems.into_iter().next() {
+		Some(problem) => Err(problem),
+		None => Ok(()),
+	}
+}
+
+/// Check that the files of the keystore and the node key file aren't accessible by other users
+/// than their owner.
+///
+/// Unless `allow_insecure` is set, a node with such files is refused to start. Otherwise a
+/// warning is logged.
+pub fn check_secret_file_permissions(config: &Configuration, allow_insecure: bool) -> Result<()> {
+	let mut files = Vec::new();
+	if let Some(path) = config.keystore.path().filter(|path| path.is_dir()) {
+		for entry in fs::read_dir(path)? {
+			let file = entry?.path();
+			if file.is_file() {
+				files.push(file);
+			}
+		}
+	}
+	if let NodeKeyConfig::Ed25519(Secret::File(file)) = &config.network.node_key {
+		if file.is_file() {
+			files.push(file.clone());
+		}
+	}
+
+	let mut insecure = Vec::new();
+	for file in files {
+		if is_accessible_by_others(&file)? {
+			insecure.push(file);
+		}
+	}
+	if insecure.is_empty() {
+		return Ok(());
+	}
+
+	let insecure = insecure.iter()
+		.map(|file| file.display().to_string())
+		.collect::<Vec<_>>()
+		.join(", ");
+	if allow_insecure {
+		warn!("⚠️  Secret key files accessible by other users: {}", insecure);
+		Ok(())
+	} else {
+		Err(Error::Input(format!(
+			"Secret key files accessible by other users: {}. Restrict them with `chmod 600`, \
+			or pass --insecure-keystore-permissions to start anyway",
+			insecure,
+		)))
+	}
+}
+
+/// Returns whether the group or other users have any permission on `file`.
+#[cfg(unix)]
+fn is_accessible_by_others(file: &Path) -> Result<bool> {
+	use std::os::unix::fs::PermissionsExt;
+	Ok(fs::metadata(file)?.permissions().mode() & 0o077 != 0)
+}
+
+/// Returns whether the group or other users have any permission on `file`.
+#[cfg(not(unix))]
+fn is_accessible_by_others(_file: &Path) -> Result<bool> {
+	Ok(false)
+}
+
+/// Returns the TCP address to bind for a network listen address, or `None` if it isn't bound to
+/// a TCP port of this machine.
+fn network_socket_address(address: &Multiaddr) -> Result<Option<SocketAddr>> {
+	let invalid = |reason: &str| Error::Input(format!(
+		"Invalid listen address {}: {}", address, reason,
+	));
+
+	let mut protocols = address.iter();
+	let ip = match protocols.next() {
+		Some(Protocol::Ip4(ip)) => ip.into(),
+		Some(Protocol::Ip6(ip)) => ip.into(),
+		Some(Protocol::Memory(_)) => return Ok(None),
+		Some(Protocol::Dns4(_)) | Some(Protocol::Dns6(_)) =>
+			return Err(invalid("expected an IP address, not a domain name")),
+		_ => return Err(invalid("expected an IP address")),
+	};
+	let port = match protocols.next() {
+		Some(Protocol::Tcp(port)) => port,
+		_ => return Err(invalid("expected a TCP port after the IP address")),
+	};
+	match protocols.next() {
+		None | Some(Protocol::Ws(_)) | Some(Protocol::Wss(_)) => {},
+		Some(_) => return Err(invalid("expected nothing or `/ws` after the TCP port")),
+	}
+
+	Ok(Some(SocketAddr::new(ip, port)))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn validates_network_listen_addresses() {
+		let parse = |address: &str| network_socket_address(&address.parse().unwrap());
+		let socket = |address: &str| Some(address.parse().unwrap());
+
+		assert_eq!(parse("/ip4/127.0.0.1/tcp/30333").unwrap(), socket("127.0.0.1:30333"));
+		assert_eq!(parse("/ip6/::/tcp/30333/ws").unwrap(), socket("[::]:30333"));
+		assert_eq!(parse("/memory/1").unwrap(), None);
+		assert!(parse("/dns4/example.com/tcp/30333").is_err());
+		assert!(parse("/ip4/127.0.0.1").is_err());
+		assert!(parse("/ip4/127.0.0.1/udp/30333").is_err());
+	}
+
+	#[cfg(unix)]
+	#[test]
+	fn detects_secret_files_accessible_by_others() {
+		use std::os::unix::fs::PermissionsExt;
+
+		let dir = tempfile::tempdir().unwrap();
+		let file = dir.path().join("secret");
+		fs::write(&file, b"secret").unwrap();
+
+		fs::set_permissions(&file, fs::Permissions::from_mode(0o600)).unwrap();
+		assert!(!is_accessible_by_others(&file).unwrap());
+		fs::set_permissions(&file, fs::Permissions::from_mode(0o640)).unwrap();
+		assert!(is_accessible_by_others(&file).unwrap());
+		fs::set_permissions(&file, fs::Permissions::from_mode(0o604)).unwrap();
+		assert!(is_accessible_by_others(&file).unwrap());
+	}
+}
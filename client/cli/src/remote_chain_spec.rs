@@ -0,0 +1,189 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2020 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Fetching chain specifications from remote URLs.
+
+use crate::error::{Error, Result};
+use futures::StreamExt;
+use log::{info, warn};
+use sp_core::hexdisplay::HexDisplay;
+use sp_core::hashing::{blake2_256, sha2_256};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Name of the directory, relative to the base path, that downloaded chain specs are cached in.
+pub(crate) const CHAIN_SPEC_CACHE_PATH: &str = "chain-specs";
+
+/// Maximum size of a downloaded chain spec. The largest public chain specs are a few megabytes.
+const MAX_CHAIN_SPEC_SIZE: usize = 64 * 1024 * 1024;
+
+/// Returns `true` if the given chain id refers to a remote chain spec.
+pub(crate) fn is_remote_chain_spec(chain_id: &str) -> bool {
+	chain_id.starts_with("http://") || chain_id.starts_with("https://")
+}
+
+/// Parse a hex encoded sha256 checksum, with or without `0x` prefix.
+pub(crate) fn parse_sha256(s: &str) -> Result<[u8; 32]> {
+	let bytes = sp_core::bytes::from_hex(s)
+		.map_err(|e| Error::Input(format!("Invalid chain spec checksum `{}`: {}", s, e)))?;
+
+	if bytes.len() != 32 {
+		return Err(Error::Input(format!(
+			"Invalid chain spec checksum `{}`: expected 32 bytes, got {}", s, bytes.len(),
+		)));
+	}
+
+	let mut checksum = [0; 32];
+	checksum.copy_from_slice(&bytes);
+	Ok(checksum)
+}
+
+/// Make the chain spec at `url` available locally and return the path to it.
+///
+/// The spec is cached in `cache_dir`. If `expected_sha256` is given, a matching cached spec
+/// is used without downloading it again and a freshly downloaded spec must match it. Without a
+/// checksum the spec is always downloaded, falling back to the cached copy if that fails.
+pub(crate) fn fetch_chain_spec(
+	url: &str,
+	expected_sha256: Option<[u8; 32]>,
+	cache_dir: &Path,
+) -> Result<PathBuf> {
+	let cache_path = cache_dir.join(format!("{}.json", HexDisplay::from(&blake2_256(url.as_bytes()))));
+
+	if let Some(expected) = expected_sha256 {
+		if let Ok(cached) = fs::read(&cache_path) {
+			if sha2_256(&cached) == expected {
+				info!("📋 Using cached chain spec for {}", url);
+				return Ok(cache_path);
+			}
+		}
+	}
+
+	info!("📋 Downloading chain spec from {}", url);
+	let spec = match download(url) {
+		Ok(spec) => spec,
+		Err(e) if expected_sha256.is_none() && cache_path.exists() => {
+			warn!("Failed to download chain spec ({}), using cached copy", e);
+			return Ok(cache_path);
+		},
+		Err(e) => return Err(e),
+	};
+
+	if let Some(expected) = expected_sha256 {
+		let actual = sha2_256(&spec);
+		if actual != expected {
+			return Err(Error::IncompatibleChain(format!(
+				"Chain spec checksum mismatch for {}: expected 0x{}, got 0x{}",
+				url,
+				HexDisplay::from(&expected),
+				HexDisplay::from(&actual),
+			)));
+		}
+	}
+
+	fs::create_dir_all(cache_dir)?;
+	fs::write(&cache_path, &spec)?;
+
+	Ok(cache_path)
+}
+
+/// Download the content at `url`.
+fn download(url: &str) -> Result<Vec<u8>> {
+	let uri: hyper::Uri = url.parse()
+		.map_err(|e| Error::Input(format!("Invalid chain spec URL `{}`: {}", url, e)))?;
+
+	let mut runtime = tokio::runtime::Builder::new()
+		.basic_scheduler()
+		.enable_all()
+		.build()?;
+
+	runtime.block_on(async move {
+		let client = hyper::Client::builder()
+			.build::<_, hyper::Body>(hyper_rustls::HttpsConnector::new());
+		let response = client.get(uri).await
+			.map_err(|e| Error::Other(format!("Error downloading chain spec: {}", e)))?;
+
+		if !response.status().is_success() {
+			return Err(Error::Other(format!(
+				"Error downloading chain spec: server responded with {}", response.status(),
+			)));
+		}
+
+		read_body(response.into_body(), MAX_CHAIN_SPEC_SIZE).await
+	})
+}
+
+/// Read a response `body`, failing as soon as it exceeds `max_size` bytes.
+async fn read_body(mut body: hyper::Body, max_size: usize) -> Result<Vec<u8>> {
+	let mut content = Vec::new();
+	while let Some(chunk) = body.next().await {
+		let chunk = chunk.map_err(|e| Error::Other(format!("Error downloading chain spec: {}", e)))?;
+		if content.len() + chunk.len() > max_size {
+			return Err(Error::Other(format!(
+				"Error downloading chain spec: the spec is larger than {} bytes", max_size,
+			)));
+		}
+		content.extend_from_slice(&chunk);
+	}
+
+	Ok(content)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn detects_remote_chain_specs() {
+		assert!(is_remote_chain_spec("https://example.com/spec.json"));
+		assert!(is_remote_chain_spec("http://example.com/spec.json"));
+		assert!(!is_remote_chain_spec("./spec.json"));
+		assert!(!is_remote_chain_spec("dev"));
+	}
+
+	#[test]
+	fn parses_sha256_checksums() {
+		let hex = "0xe3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855";
+		assert_eq!(parse_sha256(hex).unwrap(), sha2_256(&[]));
+		assert_eq!(parse_sha256(&hex[2..]).unwrap(), sha2_256(&[]));
+		assert!(parse_sha256("0x1234").is_err());
+		assert!(parse_sha256("not hex").is_err());
+	}
+
+	#[test]
+	fn uses_cached_spec_matching_checksum() {
+		let dir = tempfile::tempdir().unwrap();
+		let url = "https://invalid.invalid/spec.json";
+		let spec = b"{}";
+		let cache_path = dir.path().join(format!("{}.json", HexDisplay::from(&blake2_256(url.as_bytes()))));
+		fs::write(&cache_path, spec).unwrap();
+
+		assert_eq!(fetch_chain_spec(url, Some(sha2_256(spec)), dir.path()).unwrap(), cache_path);
+	}
+
+	#[test]
+	fn rejects_oversized_specs() {
+		let body = |chunks: Vec<&'static [u8]>| hyper::Body::wrap_stream(futures::stream::iter(
+			chunks.into_iter().map(Ok::<_, std::io::Error>)
+		));
+
+		let content = futures::executor::block_on(read_body(body(vec![b"{", b"}"]), 2));
+		assert_eq!(content.unwrap(), b"{}".to_vec());
+		assert!(futures::executor::block_on(read_body(body(vec![b"{", b"\"\"}"]), 2)).is_err());
+	}
+}
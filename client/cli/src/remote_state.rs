@@ -0,0 +1,131 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2020 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Fetching the state of a block from a running node over RPC.
+
+use crate::error::{Error, Result};
+use crate::rpc_client::{call, RpcClient};
+use futures::future::try_join_all;
+use log::info;
+use sp_core::H256;
+use sp_core::storage::{
+	well_known_keys, ChildInfo, PrefixedStorageKey, Storage, StorageChild, StorageKey, StorageMap,
+};
+use sp_rpc::{list::ListOrValue, number::NumberOrHex};
+
+/// Number of keys requested at once, the maximum allowed by `state_getKeysPaged`.
+const KEYS_PAGE_SIZE: u32 = 1000;
+
+/// Returns the hash of the block `at`, given as a hash or a number, or of the best block.
+pub(crate) async fn block_hash(rpc: &RpcClient, at: Option<&str>) -> Result<H256> {
+	let number = match at {
+		None => None,
+		Some(hash) if hash.starts_with("0x") => return hash[2..].parse()
+			.map_err(|e| Error::Input(format!("Invalid block hash `{}`: {:?}", hash, e))),
+		Some(number) => Some(number.parse::<u64>()
+			.map_err(|e| Error::Input(format!("Invalid block number `{}`: {}", number, e)))?),
+	};
+
+	let number = number.map(|number| ListOrValue::Value(NumberOrHex::Number(number)));
+	match call("chain_getBlockHash", rpc.chain.block_hash(number)).await? {
+		ListOrValue::Value(Some(hash)) => Ok(hash),
+		_ => Err(Error::Input(format!("Unknown block {}", at.unwrap_or("best")))),
+	}
+}
+
+/// Fetch the storage entries whose key starts with one of `prefixes` at block `hash`, or the whole
+/// storage if there is no prefix.
+///
+/// The child tries whose root is fetched are fetched entirely.
+pub(crate) async fn fetch_storage(
+	rpc: &RpcClient,
+	hash: H256,
+	prefixes: &[Vec<u8>],
+) -> Result<Storage> {
+	let mut storage = Storage::default();
+	let all = [Vec::new()];
+	let prefixes = if prefixes.is_empty() { &all[..] } else { prefixes };
+
+	for prefix in prefixes {
+		let mut start_key = None;
+		loop {
+			let keys = call("state_getKeysPaged", rpc.state.storage_keys_paged(
+				Some(StorageKey(prefix.clone())),
+				KEYS_PAGE_SIZE,
+				start_key.take(),
+				Some(hash),
+			)).await?;
+
+			let values = try_join_all(keys.iter().map(|key| {
+				call("state_getStorage", rpc.state.storage(key.clone(), Some(hash)))
+			})).await?;
+			for (key, value) in keys.iter().zip(values) {
+				if let Some(value) = value {
+					storage.top.insert(key.0.clone(), value.0);
+				}
+			}
+
+			info!("📥 Fetched {} storage entries", storage.top.len());
+			if keys.len() < KEYS_PAGE_SIZE as usize {
+				break;
+			}
+			start_key = keys.last().cloned();
+		}
+	}
+
+	let child_roots = storage.top.keys()
+		.filter(|key| key.starts_with(well_known_keys::DEFAULT_CHILD_STORAGE_KEY_PREFIX))
+		.cloned()
+		.collect::<Vec<_>>();
+	for prefixed_key in child_roots {
+		storage.top.remove(&prefixed_key);
+		let storage_key = &prefixed_key[well_known_keys::DEFAULT_CHILD_STORAGE_KEY_PREFIX.len()..];
+		let child_info = ChildInfo::new_default(storage_key);
+		let data = fetch_child_storage(rpc, hash, &prefixed_key).await?;
+		storage.children_default.insert(storage_key.to_vec(), StorageChild { data, child_info });
+	}
+
+	Ok(storage)
+}
+
+/// Fetch all the entries of the child trie whose root is stored at `prefixed_key`.
+async fn fetch_child_storage(
+	rpc: &RpcClient,
+	hash: H256,
+	prefixed_key: &[u8],
+) -> Result<StorageMap> {
+	let child_key = PrefixedStorageKey::new(prefixed_key.to_vec());
+	let keys = call(
+		"childstate_getKeys",
+		rpc.child_state.storage_keys(child_key.clone(), StorageKey(Vec::new()), Some(hash)),
+	).await?;
+
+	let values = try_join_all(keys.iter().map(|key| call(
+		"childstate_getStorage",
+		rpc.child_state.storage(child_key.clone(), key.clone(), Some(hash)),
+	))).await?;
+
+	let mut data = StorageMap::new();
+	for (key, value) in keys.into_iter().zip(values) {
+		if let Some(value) = value {
+			data.insert(key.0, value.0);
+		}
+	}
+
+	Ok(data)
+}
@@ -0,0 +1,88 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2020 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! JSON-RPC clients used by commands that talk to a running node.
+//!
+//! The commands don't know the types of the chain of the node: the hashes are assumed to be
+//! `H256`, the block numbers to fit in a `u64`, and the headers are kept as JSON.
+
+use crate::error::{Error, Result};
+use futures::compat::Future01CompatExt;
+use jsonrpc_core_client::{transports::http, RpcChannel, RpcError};
+use serde_json::Value;
+use sp_core::H256;
+
+/// Client of the `chain_*` methods.
+pub(crate) type ChainClient = sc_rpc_api::chain::ChainClient<u64, H256, Value, Value>;
+/// Client of the `state_*` methods.
+pub(crate) type StateClient = sc_rpc_api::state::StateClient<H256>;
+/// Client of the `childstate_*` methods.
+pub(crate) type ChildStateClient = sc_rpc_api::child_state::ChildStateClient<H256>;
+/// Client of the `system_*` methods.
+pub(crate) type SystemClient = sc_rpc_api::system::SystemClient<H256, u64>;
+/// Client of the `author_*` methods.
+pub(crate) type AuthorClient = sc_rpc_api::author::AuthorClient<H256, H256>;
+
+/// Typed JSON-RPC clients of the HTTP endpoint of a node.
+///
+/// The requests are sent from a background runtime, which stops when the clients are dropped.
+pub(crate) struct RpcClient {
+	pub(crate) chain: ChainClient,
+	pub(crate) state: StateClient,
+	pub(crate) child_state: ChildStateClient,
+	pub(crate) system: SystemClient,
+	pub(crate) author: AuthorClient,
+	_runtime: tokio01::runtime::Runtime,
+}
+
+impl RpcClient {
+	/// Create the clients of the HTTP RPC endpoint at `url`.
+	pub(crate) fn new(url: &str) -> Result<Self> {
+		url.parse::<hyper::Uri>()
+			.map_err(|e| Error::Input(format!("Invalid RPC URL `{}`: {}", url, e)))?;
+
+		let mut runtime = tokio01::runtime::Runtime::new()?;
+		let channel: RpcChannel = runtime.block_on(http::connect(url))
+			.map_err(|e| Error::Other(format!("Error connecting to `{}`: {}", url, e)))?;
+
+		Ok(RpcClient {
+			chain: channel.clone().into(),
+			state: channel.clone().into(),
+			child_state: channel.clone().into(),
+			system: channel.clone().into(),
+			author: channel.into(),
+			_runtime: runtime,
+		})
+	}
+}
+
+/// Wait for the result of the call of `method`.
+pub(crate) async fn call<T>(
+	method: &str,
+	call: impl futures01::Future<Item = T, Error = RpcError>,
+) -> Result<T> {
+	call.compat().await
+		.map_err(|e| Error::Other(format!("Error calling `{}`: {}", method, e)))
+}
+
+/// Run `f` to completion on the current thread.
+///
+/// The calls themselves are driven by the runtime of the `RpcClient`.
+pub(crate) fn block_on<F: std::future::Future<Output = Result<T>>, T>(f: F) -> Result<T> {
+	futures::executor::block_on(f)
+}
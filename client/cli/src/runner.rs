@@ -16,19 +16,29 @@
 // You should have received a copy of the GNU General Public License
 // along with this program. If not, see <https://www.gnu.org/licenses/>.
 
+use crate::ChainProperties;
 use crate::CliConfiguration;
+use crate::error::Error;
 use crate::Result;
 use crate::SubstrateCli;
 use crate::Subcommand;
 use chrono::prelude::*;
 use futures::pin_mut;
 use futures::select;
-use futures::{future, future::FutureExt, Future};
-use log::info;
+use futures::{future, future::{FusedFuture, FutureExt}, Future};
+use log::{info, warn};
 use sc_service::{AbstractService, Configuration, Role, ServiceBuilderCommand, TaskType};
 use sp_runtime::traits::{Block as BlockT, Header as HeaderT};
-use sp_utils::metrics::{TOKIO_THREADS_ALIVE, TOKIO_THREADS_TOTAL};
-use std::{str::FromStr, fmt::Debug, marker::PhantomData, sync::Arc};
+use sp_utils::{metrics::{TOKIO_THREADS_ALIVE, TOKIO_THREADS_TOTAL}, shutdown};
+use std::{
+	str::FromStr, fmt::Debug, marker::PhantomData, pin::Pin, sync::Arc, time::{Duration, Instant},
+};
+
+/// A running service whose type is erased, so that services of different chains can be run
+/// together.
+type BoxedService = Pin<Box<
+	dyn Future<Output = std::result::Result<(), sc_service::Error>> + Send
+>>;
 
 #[cfg(target_family = "unix")]
 async fn main<F, E>(func: F) -> std::result::Result<(), Box<dyn std::error::Error>>
@@ -77,10 +87,25 @@ where
 	Ok(())
 }
 
-/// Build a tokio runtime with all features
+/// Build a tokio runtime with all features, sized for a full node
 pub fn build_runtime() -> std::result::Result<tokio::runtime::Runtime, std::io::Error> {
+	build_runtime_with_threads(
+		default_runtime_threads(&Role::Full),
+		default_blocking_threads(&Role::Full),
+	)
+}
+
+/// Build a tokio runtime with all features, running futures on `runtime_threads` worker threads
+/// and blocking tasks on up to `blocking_threads` additional threads.
+pub fn build_runtime_with_threads(
+	runtime_threads: usize,
+	blocking_threads: usize,
+) -> std::result::Result<tokio::runtime::Runtime, std::io::Error> {
 	tokio::runtime::Builder::new()
 		.threaded_scheduler()
+		.core_threads(runtime_threads)
+		// tokio counts the worker threads in the maximum.
+		.max_threads(runtime_threads + blocking_threads)
 		.on_thread_start(||{
 			TOKIO_THREADS_ALIVE.inc();
 			TOKIO_THREADS_TOTAL.inc();
@@ -92,6 +117,26 @@ pub fn build_runtime() -> std::result::Result<tokio::runtime::Runtime, std::io::
 		.build()
 }
 
+/// Join `items` for display, or `none` if there are none.
+fn list_or_none(items: &[String]) -> String {
+	if items.is_empty() { "none".into() } else { items.join(", ") }
+}
+
+/// Returns the default number of worker threads of the tokio runtime for a node with `role`.
+///
+/// One per CPU, but at least two so that a busy future doesn't stall the node on small machines,
+/// and at most 16 as nodes don't have enough concurrent futures to keep more busy.
+fn default_runtime_threads(role: &Role) -> usize {
+	let max = if let Role::Light = role { 4 } else { 16 };
+	num_cpus::get().max(2).min(max)
+}
+
+/// Returns the default maximum number of threads running blocking tasks, e.g. the block import,
+/// for a node with `role`.
+fn default_blocking_threads(role: &Role) -> usize {
+	if let Role::Light = role { 16 } else { 64 }
+}
+
 fn run_until_exit<FUT, ERR>(mut tokio_runtime: tokio::runtime::Runtime, future: FUT) -> Result<()>
 where
 	FUT: Future<Output = std::result::Result<(), ERR>> + future::Future,
@@ -105,9 +150,97 @@ where
 	Ok(())
 }
 
+/// Wait until no in-progress work delays the shutdown anymore, or `timeout` has passed.
+async fn wait_for_shutdown_guards(timeout: Duration) {
+	let started = Instant::now();
+
+	if shutdown::active_guards() > 0 {
+		info!("⏳ Waiting for block authorship in the current slot to complete");
+	}
+
+	while shutdown::active_guards() > 0 {
+		if started.elapsed() >= timeout {
+			warn!("Block authorship didn't complete within {:?}, shutting down anyway", timeout);
+			return;
+		}
+
+		tokio::time::delay_for(Duration::from_millis(50)).await;
+	}
+}
+
+/// A chain run in the same process as the main chain of the node, e.g. the light client of a
+/// bridged chain.
+///
+/// The chain doesn't need to share the runtime or the service types of the main chain: its
+/// configuration is created with the `SubstrateCli` loading its own chain specs, and it is
+/// started with its own service builders.
+pub struct ExtraChain {
+	name: String,
+	config: Configuration,
+	start: Box<
+		dyn FnOnce(Configuration)
+			-> sc_service::error::Result<(BoxedService, Option<sc_telemetry::Telemetry>)>
+	>,
+}
+
+impl ExtraChain {
+	/// Run the chain called `name` with `new_light` or `new_full`, depending on the role in
+	/// `config`.
+	///
+	/// The configuration is created with [`Runner::extra_chain_configuration`].
+	pub fn new<FNL, FNF, SL, SF>(
+		name: impl Into<String>,
+		config: Configuration,
+		new_light: FNL,
+		new_full: FNF,
+	) -> Self where
+		FNL: FnOnce(Configuration) -> sc_service::error::Result<SL> + 'static,
+		FNF: FnOnce(Configuration) -> sc_service::error::Result<SF> + 'static,
+		SL: AbstractService,
+		SF: AbstractService,
+	{
+		fn erase<S: AbstractService>(
+			service: S,
+		) -> (BoxedService, Option<sc_telemetry::Telemetry>) {
+			let telemetry = service.telemetry();
+			(Box::pin(service), telemetry)
+		}
+
+		ExtraChain {
+			name: name.into(),
+			config,
+			start: Box::new(move |config| match config.role {
+				Role::Light => new_light(config).map(erase),
+				_ => new_full(config).map(erase),
+			}),
+		}
+	}
+
+	/// Run the chain called `name` with `new_service` whatever the role in `config`, e.g. for a
+	/// chain only ever run as a light client.
+	pub fn with_service<F, S>(name: impl Into<String>, config: Configuration, new_service: F) -> Self
+	where
+		F: FnOnce(Configuration) -> sc_service::error::Result<S> + 'static,
+		S: AbstractService,
+	{
+		ExtraChain {
+			name: name.into(),
+			config,
+			start: Box::new(move |config| new_service(config).map(|service| {
+				let telemetry = service.telemetry();
+				(Box::pin(service) as BoxedService, telemetry)
+			})),
+		}
+	}
+}
+
 /// A Substrate CLI runtime that can be used to run a node or a command
 pub struct Runner<C: SubstrateCli> {
 	config: Configuration,
+	chain_properties: ChainProperties,
+	strict_config: bool,
+	insecure_keystore_permissions: bool,
+	authoring_shutdown_timeout: Option<Duration>,
 	tokio_runtime: tokio::runtime::Runtime,
 	phantom: PhantomData<C>,
 }
@@ -115,7 +248,12 @@ pub struct Runner<C: SubstrateCli> {
 impl<C: SubstrateCli> Runner<C> {
 	/// Create a new runtime with the command provided in argument
 	pub fn new<T: CliConfiguration>(cli: &C, command: &T) -> Result<Runner<C>> {
-		let tokio_runtime = build_runtime()?;
+		let role = command.role(command.is_dev()?)?;
+		let runtime_threads = command.runtime_threads()?
+			.unwrap_or_else(|| default_runtime_threads(&role));
+		let blocking_threads = command.blocking_threads()?
+			.unwrap_or_else(|| default_blocking_threads(&role));
+		let tokio_runtime = build_runtime_with_threads(runtime_threads, blocking_threads)?;
 		let runtime_handle = tokio_runtime.handle().clone();
 
 		let task_executor = Arc::new(
@@ -133,13 +271,43 @@ impl<C: SubstrateCli> Runner<C> {
 			}
 		);
 
+		let config = command.create_configuration(cli, task_executor)?;
+		let mut chain_properties = ChainProperties::from_chain_spec(&*config.chain_spec)?;
+		if let Some(prefix) = command.ss58_prefix()? {
+			chain_properties.ss58_format = Some(prefix);
+		}
+		chain_properties.apply_ss58_format();
+
 		Ok(Runner {
-			config: command.create_configuration(cli, task_executor)?,
+			config,
+			chain_properties,
+			strict_config: command.strict_config()?,
+			insecure_keystore_permissions: command.insecure_keystore_permissions()?,
+			authoring_shutdown_timeout: command.authoring_shutdown_timeout()?,
 			tokio_runtime,
 			phantom: PhantomData,
 		})
 	}
 
+	/// Create the configuration of an extra chain of the node from the `command` of its section,
+	/// using `cli` to load its chain spec.
+	///
+	/// The chain shares the tokio runtime of the node, and its metrics are labelled with the id of
+	/// its chain spec.
+	pub fn extra_chain_configuration<D: SubstrateCli, T: CliConfiguration>(
+		&self,
+		cli: &D,
+		command: &T,
+	) -> Result<Configuration> {
+		let mut config = cli.create_configuration(command, self.config.task_executor.clone())?;
+		let chain_id = config.chain_spec.id().to_owned();
+		let node_labels = &config.node_labels;
+		config.prometheus_config = config.prometheus_config.take()
+			.map(|prometheus| prometheus.with_chain_label(&chain_id, node_labels));
+
+		Ok(config)
+	}
+
 	/// A helper function that runs an `AbstractService` with tokio and stops if the process receives
 	/// the signal `SIGTERM` or `SIGINT`.
 	pub fn run_node<FNL, FNF, SL, SF>(
@@ -152,6 +320,26 @@ impl<C: SubstrateCli> Runner<C> {
 		FNF: FnOnce(Configuration) -> sc_service::error::Result<SF>,
 		SL: AbstractService + Unpin,
 		SF: AbstractService + Unpin,
+	{
+		self.run_node_with_extra_chains(new_light, new_full, runtime_version, Vec::new())
+	}
+
+	/// Like [`Runner::run_node`], but also runs `extra_chains` in the same process. The node stops
+	/// as soon as any of its chains stops.
+	///
+	/// When there are extra chains, the metrics of the main chain are labelled with the id of its
+	/// chain spec as well.
+	pub fn run_node_with_extra_chains<FNL, FNF, SL, SF>(
+		mut self,
+		new_light: FNL,
+		new_full: FNF,
+		runtime_version: sp_version::RuntimeVersion,
+		extra_chains: Vec<ExtraChain>,
+	) -> Result<()> where
+		FNL: FnOnce(Configuration) -> sc_service::error::Result<SL>,
+		FNF: FnOnce(Configuration) -> sc_service::error::Result<SF>,
+		SL: AbstractService + Unpin,
+		SF: AbstractService + Unpin,
 	{
 		info!("{}", C::impl_name());
 		info!("✌️  version {}", C::impl_version());
@@ -162,6 +350,7 @@ impl<C: SubstrateCli> Runner<C> {
 			Local::today().year(),
 		);
 		info!("📋 Chain specification: {}", self.config.chain_spec.name());
+		info!("🪙  Token: {}", self.chain_properties);
 		info!("🏷  Node name: {}", self.config.network.node_name);
 		info!("👤 Role: {}", self.config.display_role());
 		info!("💾 Database: {} at {}",
@@ -170,9 +359,53 @@ impl<C: SubstrateCli> Runner<C> {
 		);
 		info!("⛓  Native runtime: {}", runtime_version);
 
+		let security = self.config.security_summary();
+		info!("🗄  State pruning: {}", security.pruning);
+		info!("🔌 RPC: {}", list_or_none(&security.rpc));
+		info!("🔑 Keystore: {}", security.keystore);
+		info!("📡 Telemetry: {}", list_or_none(&security.telemetry));
+
+		let mut warnings = self.config.sanity_check();
+		for warning in &warnings {
+			warn!("⚠️  {}", warning);
+		}
+		for chain in &extra_chains {
+			info!(
+				"🔗 Extra chain {}: {} ({})",
+				chain.name,
+				chain.config.chain_spec.name(),
+				chain.config.display_role(),
+			);
+			let chain_warnings = chain.config.sanity_check();
+			for warning in &chain_warnings {
+				warn!("⚠️  {}: {}", chain.name, warning);
+			}
+			warnings.extend(chain_warnings);
+		}
+		if self.strict_config && !warnings.is_empty() {
+			return Err(Error::Input(
+				"Refusing to start with the configuration warned about above (--strict-config)".into()
+			));
+		}
+		let configs = std::iter::once(&self.config)
+			.chain(extra_chains.iter().map(|chain| &chain.config))
+			.collect::<Vec<_>>();
+		crate::check_chains_listen_addresses(&configs)?;
+		let allow_insecure_keys = self.insecure_keystore_permissions;
+		for config in configs {
+			crate::check_secret_file_permissions(config, allow_insecure_keys)?;
+		}
+
+		if !extra_chains.is_empty() {
+			let chain_id = self.config.chain_spec.id().to_owned();
+			let node_labels = &self.config.node_labels;
+			self.config.prometheus_config = self.config.prometheus_config.take()
+				.map(|prometheus| prometheus.with_chain_label(&chain_id, node_labels));
+		}
+
 		match self.config.role {
-			Role::Light => self.run_service_until_exit(new_light),
-			_ => self.run_service_until_exit(new_full),
+			Role::Light => self.run_service_until_exit(new_light, extra_chains),
+			_ => self.run_service_until_exit(new_full, extra_chains),
 		}
 	}
 
@@ -188,7 +421,8 @@ impl<C: SubstrateCli> Runner<C> {
 		<<BB as BlockT>::Hash as FromStr>::Err: Debug,
 	{
 		match subcommand {
-			Subcommand::BuildSpec(cmd) => cmd.run(self.config),
+			Subcommand::BuildSpec(cmd) => cmd.run::<BB>(self.config),
+			Subcommand::BuildGenesis(cmd) => cmd.run(self.config),
 			Subcommand::ExportBlocks(cmd) => {
 				run_until_exit(self.tokio_runtime, cmd.run(self.config, builder))
 			}
@@ -201,10 +435,29 @@ impl<C: SubstrateCli> Runner<C> {
 			Subcommand::Revert(cmd) => cmd.run(self.config, builder),
 			Subcommand::PurgeChain(cmd) => cmd.run(self.config),
 			Subcommand::ExportState(cmd) => cmd.run(self.config, builder),
+			Subcommand::ExportMetadata(cmd) => cmd.run(self.config, builder),
+			Subcommand::ExportSyncState(cmd) => cmd.run(self.config, builder),
+			Subcommand::OffchainStorage(cmd) => cmd.run(self.config, builder),
+			Subcommand::BenchmarkBlock(cmd) => cmd.run(self.config, builder),
+			Subcommand::DiffBlock(cmd) => cmd.run(self.config, builder),
+			Subcommand::Db(cmd) => cmd.run(self.config, builder),
+			Subcommand::State(cmd) => cmd.run(self.config, builder),
+			Subcommand::Status(cmd) => cmd.run(self.config, builder),
+			Subcommand::RotateKeys(cmd) => cmd.run(),
+			Subcommand::TryRuntime(cmd) => cmd.run(self.config, builder),
+			Subcommand::ForkOff(cmd) => cmd.run(self.config),
+			Subcommand::Network(cmd) => cmd.run(self.config),
+			Subcommand::Replay(cmd) => {
+				run_until_exit(self.tokio_runtime, cmd.run(self.config, builder))
+			}
 		}
 	}
 
-	fn run_service_until_exit<T, F>(mut self, service_builder: F) -> Result<()>
+	fn run_service_until_exit<T, F>(
+		mut self,
+		service_builder: F,
+		extra_chains: Vec<ExtraChain>,
+	) -> Result<()>
 	where
 		F: FnOnce(Configuration) -> std::result::Result<T, sc_service::error::Error>,
 		T: AbstractService + Unpin,
@@ -219,11 +472,27 @@ impl<C: SubstrateCli> Runner<C> {
 		// and drop the runtime first.
 		let _telemetry = service.telemetry();
 
+		let mut services: Vec<BoxedService> = vec![Box::pin(service)];
+		let mut _extra_telemetry = Vec::new();
+		for chain in extra_chains {
+			let (service, telemetry) = (chain.start)(chain.config)?;
+			services.push(service);
+			_extra_telemetry.push(telemetry);
+		}
+
 		{
-			let f = service.fuse();
+			let mut f = future::select_all(services).map(|(result, _, _)| result).fuse();
 			self.tokio_runtime
-				.block_on(main(f))
+				.block_on(main(&mut f))
 				.map_err(|e| e.to_string())?;
+
+			// The service is still running if we were interrupted by a signal, so in-progress
+			// block authorship can still complete.
+			if let Some(timeout) = self.authoring_shutdown_timeout {
+				if !f.is_terminated() {
+					self.tokio_runtime.block_on(wait_for_shutdown_guards(timeout));
+				}
+			}
 		}
 
 		// The `service` **must** have been destroyed here for the shutdown signal to propagate
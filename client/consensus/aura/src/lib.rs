@@ -71,7 +71,8 @@ use sp_timestamp::{
 use sc_telemetry::{telemetry, CONSENSUS_TRACE, CONSENSUS_DEBUG, CONSENSUS_INFO};
 
 use sc_consensus_slots::{
-	CheckedHeader, SlotWorker, SlotInfo, SlotCompatible, StorageChanges, check_equivocation,
+	CheckedHeader, SlotWorker, SlotInfo, SlotCompatible, StorageChanges, ClockDrift,
+	AuthorshipStats, check_equivocation,
 };
 
 use sc_keystore::KeyStorePtr;
@@ -149,6 +150,7 @@ pub fn start_aura<B, C, SC, E, I, P, SO, CAW, Error>(
 	force_authoring: bool,
 	keystore: KeyStorePtr,
 	can_author_with: CAW,
+	registry: Option<&Registry>,
 ) -> Result<impl Future<Output = ()>, sp_consensus::Error> where
 	B: BlockT,
 	C: ProvideRuntimeApi<B> + BlockOf + ProvideCache<B> + AuxStore + Send + Sync,
@@ -171,6 +173,7 @@ pub fn start_aura<B, C, SC, E, I, P, SO, CAW, Error>(
 		keystore,
 		sync_oracle: sync_oracle.clone(),
 		force_authoring,
+		authorship_stats: AuthorshipStats::new(registry),
 		_key_type: PhantomData::<P>,
 	};
 	register_aura_inherent_data_provider(
@@ -185,6 +188,7 @@ pub fn start_aura<B, C, SC, E, I, P, SO, CAW, Error>(
 		inherent_data_providers,
 		AuraSlotCompatible,
 		can_author_with,
+		Arc::new(sp_timestamp::SystemClock),
 	))
 }
 
@@ -195,12 +199,13 @@ struct AuraWorker<C, E, I, P, SO> {
 	keystore: KeyStorePtr,
 	sync_oracle: SO,
 	force_authoring: bool,
+	authorship_stats: AuthorshipStats,
 	_key_type: PhantomData<P>,
 }
 
 impl<B, C, E, I, P, Error, SO> sc_consensus_slots::SimpleSlotWorker<B> for AuraWorker<C, E, I, P, SO> where
 	B: BlockT,
-	C: ProvideRuntimeApi<B> + BlockOf + ProvideCache<B> + Sync,
+	C: ProvideRuntimeApi<B> + BlockOf + ProvideCache<B> + AuxStore + Sync,
 	C::Api: AuraApi<B, AuthorityId<P>>,
 	E: Environment<B, Error = Error>,
 	E::Proposer: Proposer<B, Error = Error, Transaction = sp_api::TransactionFor<C, B>>,
@@ -255,6 +260,19 @@ impl<B, C, E, I, P, Error, SO> sc_consensus_slots::SimpleSlotWorker<B> for AuraW
 		})
 	}
 
+	fn authority_key(
+		&self,
+		epoch_data: &Self::EpochData,
+	) -> Option<sc_consensus_slots::AuthorityKey> {
+		let keystore = self.keystore.read();
+		Some(sc_consensus_slots::AuthorityKey {
+			set_hash: sp_core::blake2_256(&epoch_data.encode()),
+			has_key: epoch_data.iter().any(|a| {
+				keystore.key_pair_by_type::<P>(a, sp_application_crypto::key_types::AURA).is_ok()
+			}),
+		})
+	}
+
 	fn pre_digest_data(
 		&self,
 		slot_number: u64,
@@ -312,6 +330,15 @@ impl<B, C, E, I, P, Error, SO> sc_consensus_slots::SimpleSlotWorker<B> for AuraW
 		self.force_authoring
 	}
 
+	fn authorship_stats(&self) -> &AuthorshipStats {
+		&self.authorship_stats
+	}
+
+	fn note_authored_slot(&self, slot_number: u64) -> Result<Option<u64>, sp_consensus::Error> {
+		sc_consensus_slots::note_authored_slot(&*self.client, slot_number)
+			.map_err(|e| sp_consensus::Error::ClientImport(e.to_string()))
+	}
+
 	fn sync_oracle(&mut self) -> &mut Self::SyncOracle {
 		&mut self.sync_oracle
 	}
@@ -352,7 +379,7 @@ impl<B, C, E, I, P, Error, SO> sc_consensus_slots::SimpleSlotWorker<B> for AuraW
 
 impl<B: BlockT, C, E, I, P, Error, SO> SlotWorker<B> for AuraWorker<C, E, I, P, SO> where
 	B: BlockT,
-	C: ProvideRuntimeApi<B> + BlockOf + ProvideCache<B> + Sync + Send,
+	C: ProvideRuntimeApi<B> + BlockOf + ProvideCache<B> + AuxStore + Sync + Send,
 	C::Api: AuraApi<B, AuthorityId<P>>,
 	E: Environment<B, Error = Error> + Send + Sync,
 	E::Proposer: Proposer<B, Error = Error, Transaction = sp_api::TransactionFor<C, B>>,
@@ -496,6 +523,7 @@ pub struct AuraVerifier<C, P> {
 	client: Arc<C>,
 	phantom: PhantomData<P>,
 	inherent_data_providers: sp_inherents::InherentDataProviders,
+	clock_drift: ClockDrift,
 }
 
 impl<C, P> AuraVerifier<C, P>
@@ -595,6 +623,10 @@ impl<B: BlockT, C, P> Verifier<B> for AuraVerifier<C, P> where
 		).map_err(|e| e.to_string())?;
 		match checked_header {
 			CheckedHeader::Checked(pre_header, (slot_num, seal)) => {
+				if origin == BlockOrigin::NetworkBroadcast {
+					self.clock_drift.note_block(slot_num, timestamp_now);
+				}
+
 				// if the body is passed through, we need to use the runtime
 				// to check that the internally-set timestamp in the inherents
 				// actually matches the slot set in the seal.
@@ -650,6 +682,11 @@ impl<B: BlockT, C, P> Verifier<B> for AuraVerifier<C, P> where
 				Ok((import_block, maybe_keys))
 			}
 			CheckedHeader::Deferred(a, b) => {
+				// blocks from a future slot are the ones telling that the local clock is behind
+				if origin == BlockOrigin::NetworkBroadcast {
+					self.clock_drift.note_block(b, timestamp_now);
+				}
+
 				debug!(target: "aura", "Checking {:?} failed; {:?}, {:?}.", hash, a, b);
 				telemetry!(CONSENSUS_DEBUG; "aura.header_too_far_in_future";
 					"hash" => ?hash, "a" => ?a, "b" => ?b
@@ -842,6 +879,7 @@ pub fn import_queue<B, I, C, P, S>(
 		client: client.clone(),
 		inherent_data_providers,
 		phantom: PhantomData,
+		clock_drift: ClockDrift::new(slot_duration.get(), registry),
 	};
 
 	Ok(BasicQueue::new(
@@ -954,6 +992,7 @@ mod tests {
 						client,
 						inherent_data_providers,
 						phantom: Default::default(),
+						clock_drift: ClockDrift::new(SLOT_DURATION, None),
 					}
 				},
 				PeersClient::Light(_, _) => unreachable!("No (yet) tests for light client + Aura"),
@@ -1027,6 +1066,7 @@ mod tests {
 				false,
 				keystore,
 				sp_consensus::AlwaysCanAuthor,
+				None,
 			).expect("Starts aura"));
 		}
 
@@ -1085,6 +1125,7 @@ mod tests {
 			keystore,
 			sync_oracle: DummyOracle.clone(),
 			force_authoring: false,
+			authorship_stats: Default::default(),
 			_key_type: PhantomData::<AuthorityPair>,
 		};
 
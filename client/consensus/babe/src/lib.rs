@@ -92,7 +92,7 @@ use sp_api::{ProvideRuntimeApi, NumberFor};
 use sc_keystore::KeyStorePtr;
 use parking_lot::Mutex;
 use sp_inherents::{InherentDataProviders, InherentData};
-use sc_telemetry::{telemetry, CONSENSUS_TRACE, CONSENSUS_DEBUG};
+use sc_telemetry::{telemetry, CONSENSUS_TRACE, CONSENSUS_DEBUG, CONSENSUS_INFO};
 use sp_consensus::{
 	self, BlockImport, Environment, Proposer, BlockCheckParams,
 	ForkChoiceStrategy, BlockImportParams, BlockOrigin, Error as ConsensusError,
@@ -111,7 +111,8 @@ use futures::prelude::*;
 use log::{debug, info, log, trace, warn};
 use prometheus_endpoint::Registry;
 use sc_consensus_slots::{
-	SlotWorker, SlotInfo, SlotCompatible, StorageChanges, CheckedHeader, check_equivocation,
+	SlotWorker, SlotInfo, SlotCompatible, StorageChanges, CheckedHeader, ClockDrift,
+	AuthorshipStats, check_equivocation,
 };
 use sc_consensus_epochs::{
 	descendent_query, SharedEpochChanges, EpochChangesFor, Epoch as EpochT, ViableEpochDescriptor,
@@ -355,6 +356,14 @@ pub struct BabeParams<B: BlockT, C, E, I, SO, SC, CAW> {
 
 	/// Checks if the current native implementation can author with a runtime at a given block.
 	pub can_author_with: CAW,
+
+	/// The clock triggering the slots, normally `sp_timestamp::SystemClock`.
+	///
+	/// It should be the clock of the timestamp inherent data provider.
+	pub clock: Arc<dyn sp_timestamp::Clock>,
+
+	/// The Prometheus registry the authorship metrics of the worker are registered in.
+	pub prometheus_registry: Option<Registry>,
 }
 
 /// Start the babe worker.
@@ -369,13 +378,16 @@ pub fn start_babe<B, C, SC, E, I, SO, CAW, Error>(BabeParams {
 	force_authoring,
 	babe_link,
 	can_author_with,
+	clock,
+	prometheus_registry,
 }: BabeParams<B, C, E, I, SO, SC, CAW>) -> Result<
 	impl futures::Future<Output=()>,
 	sp_consensus::Error,
 > where
 	B: BlockT,
 	C: ProvideRuntimeApi<B> + ProvideCache<B> + ProvideUncles<B> + BlockchainEvents<B>
-		+ HeaderBackend<B> + HeaderMetadata<B, Error = ClientError> + Send + Sync + 'static,
+		+ HeaderBackend<B> + HeaderMetadata<B, Error = ClientError> + AuxStore
+		+ Send + Sync + 'static,
 	C::Api: BabeApi<B>,
 	SC: SelectChain<B> + 'static,
 	E: Environment<B, Error = Error> + Send + Sync,
@@ -396,6 +408,8 @@ pub fn start_babe<B, C, SC, E, I, SO, CAW, Error>(BabeParams {
 		keystore,
 		epoch_changes: babe_link.epoch_changes.clone(),
 		config: config.clone(),
+		reported_epoch: Mutex::new(None),
+		authorship_stats: AuthorshipStats::new(prometheus_registry.as_ref()),
 	};
 
 	register_babe_inherent_data_provider(&inherent_data_providers, config.slot_duration())?;
@@ -414,6 +428,7 @@ pub fn start_babe<B, C, SC, E, I, SO, CAW, Error>(BabeParams {
 		inherent_data_providers,
 		babe_link.time_source,
 		can_author_with,
+		clock,
 	))
 }
 
@@ -426,6 +441,34 @@ struct BabeWorker<B: BlockT, C, E, I, SO> {
 	keystore: KeyStorePtr,
 	epoch_changes: SharedEpochChanges<B, Epoch>,
 	config: Config,
+	/// Index of the last epoch reported to telemetry.
+	reported_epoch: Mutex<Option<u64>>,
+	authorship_stats: AuthorshipStats,
+}
+
+impl<B: BlockT, C, E, I, SO> BabeWorker<B, C, E, I, SO> {
+	/// Report `epoch` to telemetry, unless it was the last one reported.
+	///
+	/// In FRAME runtimes, sessions are rotated at every epoch change so the epoch index is also
+	/// the session index.
+	fn report_epoch(&self, epoch: &Epoch) {
+		let mut reported_epoch = self.reported_epoch.lock();
+		if *reported_epoch == Some(epoch.epoch_index) {
+			return;
+		}
+		*reported_epoch = Some(epoch.epoch_index);
+
+		let keystore = self.keystore.read();
+		let authority_index = epoch.authorities.iter()
+			.position(|(id, _)| keystore.key_pair::<AuthorityPair>(id).is_ok());
+		telemetry!(CONSENSUS_INFO; "babe.epoch";
+			"epoch_index" => epoch.epoch_index,
+			"start_slot" => epoch.start_slot,
+			"duration" => epoch.duration,
+			"authorities" => epoch.authorities.len(),
+			"authority_index" => ?authority_index,
+		);
+	}
 }
 
 impl<B, C, E, I, Error, SO> sc_consensus_slots::SimpleSlotWorker<B> for BabeWorker<B, C, E, I, SO> where
@@ -433,7 +476,8 @@ impl<B, C, E, I, Error, SO> sc_consensus_slots::SimpleSlotWorker<B> for BabeWork
 	C: ProvideRuntimeApi<B> +
 		ProvideCache<B> +
 		HeaderBackend<B> +
-		HeaderMetadata<B, Error = ClientError>,
+		HeaderMetadata<B, Error = ClientError> +
+		AuxStore,
 	C::Api: BabeApi<B>,
 	E: Environment<B, Error = Error>,
 	E::Proposer: Proposer<B, Error = Error, Transaction = sp_api::TransactionFor<C, B>>,
@@ -486,14 +530,13 @@ impl<B, C, E, I, Error, SO> sc_consensus_slots::SimpleSlotWorker<B> for BabeWork
 		epoch_descriptor: &ViableEpochDescriptor<B::Hash, NumberFor<B>, Epoch>,
 	) -> Option<Self::Claim> {
 		debug!(target: "babe", "Attempting to claim slot {}", slot_number);
-		let s = authorship::claim_slot(
-			slot_number,
-			self.epoch_changes.lock().viable_epoch(
-				&epoch_descriptor,
-				|slot| Epoch::genesis(&self.config, slot)
-			)?.as_ref(),
-			&self.keystore,
-		);
+		let epoch_changes = self.epoch_changes.lock();
+		let epoch = epoch_changes.viable_epoch(
+			&epoch_descriptor,
+			|slot| Epoch::genesis(&self.config, slot)
+		)?;
+		self.report_epoch(epoch.as_ref());
+		let s = authorship::claim_slot(slot_number, epoch.as_ref(), &self.keystore);
 
 		if s.is_some() {
 			debug!(target: "babe", "Claimed slot {}", slot_number);
@@ -502,6 +545,24 @@ impl<B, C, E, I, Error, SO> sc_consensus_slots::SimpleSlotWorker<B> for BabeWork
 		s
 	}
 
+	fn authority_key(
+		&self,
+		epoch_descriptor: &ViableEpochDescriptor<B::Hash, NumberFor<B>, Epoch>,
+	) -> Option<sc_consensus_slots::AuthorityKey> {
+		let epoch_changes = self.epoch_changes.lock();
+		let epoch = epoch_changes.viable_epoch(
+			&epoch_descriptor,
+			|slot| Epoch::genesis(&self.config, slot)
+		)?;
+		let authorities = &epoch.as_ref().authorities;
+		let keystore = self.keystore.read();
+		Some(sc_consensus_slots::AuthorityKey {
+			set_hash: sp_core::blake2_256(&authorities.encode()),
+			has_key: authorities.iter()
+				.any(|(id, _)| keystore.key_pair::<AuthorityPair>(id).is_ok()),
+		})
+	}
+
 	fn pre_digest_data(
 		&self,
 		_slot_number: u64,
@@ -561,6 +622,15 @@ impl<B, C, E, I, Error, SO> sc_consensus_slots::SimpleSlotWorker<B> for BabeWork
 		self.force_authoring
 	}
 
+	fn authorship_stats(&self) -> &AuthorshipStats {
+		&self.authorship_stats
+	}
+
+	fn note_authored_slot(&self, slot_number: u64) -> Result<Option<u64>, sp_consensus::Error> {
+		sc_consensus_slots::note_authored_slot(&*self.client, slot_number)
+			.map_err(|e| sp_consensus::Error::ClientImport(e.to_string()))
+	}
+
 	fn sync_oracle(&mut self) -> &mut Self::SyncOracle {
 		&mut self.sync_oracle
 	}
@@ -604,7 +674,8 @@ impl<B, C, E, I, Error, SO> SlotWorker<B> for BabeWorker<B, C, E, I, SO> where
 	C: ProvideRuntimeApi<B> +
 		ProvideCache<B> +
 		HeaderBackend<B> +
-		HeaderMetadata<B, Error = ClientError> + Send + Sync,
+		HeaderMetadata<B, Error = ClientError> +
+		AuxStore + Send + Sync,
 	C::Api: BabeApi<B>,
 	E: Environment<B, Error = Error> + Send + Sync,
 	E::Proposer: Proposer<B, Error = Error, Transaction = sp_api::TransactionFor<C, B>>,
@@ -726,6 +797,7 @@ pub struct BabeVerifier<Block: BlockT, Client> {
 	config: Config,
 	epoch_changes: SharedEpochChanges<Block, Epoch>,
 	time_source: TimeSource,
+	clock_drift: ClockDrift,
 }
 
 impl<Block, Client> BabeVerifier<Block, Client>
@@ -787,7 +859,8 @@ impl<Block, Client> Verifier<Block> for BabeVerifier<Block, Client> where
 			.create_inherent_data()
 			.map_err(Error::<Block>::Runtime)?;
 
-		let (_, slot_now, _) = self.time_source.extract_timestamp_and_slot(&inherent_data)
+		let (timestamp_now, slot_now, _) = self.time_source
+			.extract_timestamp_and_slot(&inherent_data)
 			.map_err(Error::<Block>::Extraction)?;
 
 		let hash = header.hash();
@@ -847,6 +920,10 @@ impl<Block, Client> Verifier<Block> for BabeVerifier<Block, Client> where
 					);
 				}
 
+				if origin == BlockOrigin::NetworkBroadcast {
+					self.clock_drift.note_block(slot_number, timestamp_now);
+				}
+
 				// if the body is passed through, we need to use the runtime
 				// to check that the internally-set timestamp in the inherents
 				// actually matches the slot set in the seal.
@@ -883,6 +960,11 @@ impl<Block, Client> Verifier<Block> for BabeVerifier<Block, Client> where
 				Ok((import_block, Default::default()))
 			}
 			CheckedHeader::Deferred(a, b) => {
+				// blocks from a future slot are the ones telling that the local clock is behind
+				if origin == BlockOrigin::NetworkBroadcast {
+					self.clock_drift.note_block(b, timestamp_now);
+				}
+
 				debug!(target: "babe", "Checking {:?} failed; {:?}, {:?}.", hash, a, b);
 				telemetry!(CONSENSUS_DEBUG; "babe.header_too_far_in_future";
 					"hash" => ?hash, "a" => ?a, "b" => ?b
@@ -1305,6 +1387,7 @@ pub fn import_queue<Block: BlockT, Client, Inner>(
 	let verifier = BabeVerifier {
 		client,
 		inherent_data_providers,
+		clock_drift: ClockDrift::new(babe_link.config.slot_duration, registry),
 		config: babe_link.config,
 		epoch_changes: babe_link.epoch_changes,
 		time_source: babe_link.time_source,
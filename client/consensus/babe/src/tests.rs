@@ -299,6 +299,7 @@ impl TestNetFactory for BabeTestNet {
 				config: data.link.config.clone(),
 				epoch_changes: data.link.epoch_changes.clone(),
 				time_source: data.link.time_source.clone(),
+				clock_drift: ClockDrift::new(data.link.config.slot_duration, None),
 			},
 			mutator: MUTATOR.with(|m| m.borrow().clone()),
 		}
@@ -408,6 +409,8 @@ fn run_one_test(
 			babe_link: data.link.clone(),
 			keystore,
 			can_author_with: sp_consensus::AlwaysCanAuthor,
+			clock: Arc::new(sp_timestamp::SystemClock),
+			prometheus_registry: None,
 		}).expect("Starts babe"));
 	}
 
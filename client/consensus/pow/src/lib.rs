@@ -583,6 +583,12 @@ fn mine_loop<B: BlockT, C, Algorithm, E, SO, S, CAW>(
 			continue 'outer
 		}
 
+		if sync_oracle.is_in_maintenance() {
+			debug!(target: "pow", "Skipping proposal due to maintenance.");
+			std::thread::sleep(std::time::Duration::new(1, 0));
+			continue 'outer
+		}
+
 		let (best_hash, best_header) = match select_chain {
 			Some(select_chain) => {
 				let header = select_chain.best_chain()
@@ -0,0 +1,336 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Tracking of the slots claimed by this node, reported to telemetry so that dashboards can show
+//! the performance of the validators.
+//!
+//! The slots this node missed are also counted by reason in the `slots_missed_total` Prometheus
+//! counter, so that operators can be alerted.
+
+use std::{collections::VecDeque, sync::Arc};
+use log::{debug, info, warn};
+use parking_lot::Mutex;
+use prometheus_endpoint::{register, CounterVec, Opts, PrometheusError, Registry, U64};
+use sc_telemetry::{telemetry, CONSENSUS_INFO, CONSENSUS_WARN};
+
+/// Number of claimed slots the statistics are computed over.
+pub const RECENT_SLOTS: usize = 100;
+
+/// Whether the keystore holds a key of an authority set.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AuthorityKey {
+	/// Hash of the authority set.
+	pub set_hash: [u8; 32],
+	/// Whether the keystore holds the key of one of the authorities of the set.
+	pub has_key: bool,
+}
+
+/// Reason why this node didn't author a block in a slot.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum MissedSlotReason {
+	/// The slot had already passed when the worker handled it, while the keystore held a key of
+	/// the authorities.
+	SlotSkipped,
+	/// The slot was skipped while waiting for the network, while the keystore held a key of the
+	/// authorities.
+	Offline,
+	/// The keystore lost the key it held of the current authorities.
+	EmptyKeystore,
+	/// A block was already authored in this or a later slot.
+	AlreadyAuthored,
+	/// The authorship of the slot couldn't be recorded.
+	AuxStorage,
+	/// The proposer failed to build a block.
+	ProposerFailed,
+	/// The proposer didn't build a block before the end of the slot.
+	ProposerTimeout,
+	/// The authored block couldn't be imported.
+	ImportFailed,
+}
+
+impl MissedSlotReason {
+	fn as_str(&self) -> &'static str {
+		match self {
+			MissedSlotReason::SlotSkipped => "slot_skipped",
+			MissedSlotReason::Offline => "offline",
+			MissedSlotReason::EmptyKeystore => "empty_keystore",
+			MissedSlotReason::AlreadyAuthored => "already_authored",
+			MissedSlotReason::AuxStorage => "aux_storage",
+			MissedSlotReason::ProposerFailed => "proposer_failed",
+			MissedSlotReason::ProposerTimeout => "proposer_timeout",
+			MissedSlotReason::ImportFailed => "import_failed",
+		}
+	}
+}
+
+fn register_missed_slots(registry: &Registry) -> Result<CounterVec<U64>, PrometheusError> {
+	register(
+		CounterVec::new(
+			Opts::new(
+				"slots_missed_total",
+				"Number of slots this node could have authored a block in but didn't, by reason",
+			),
+			&["reason"],
+		)?,
+		registry,
+	)
+}
+
+/// Outcome of the last [`RECENT_SLOTS`] slots claimed by this node.
+#[derive(Debug, Default)]
+struct ClaimedSlots {
+	/// Claimed slot numbers, and whether a block was authored in them.
+	slots: VecDeque<(u64, bool)>,
+}
+
+impl ClaimedSlots {
+	fn note(&mut self, slot_number: u64, authored: bool) {
+		if self.slots.len() == RECENT_SLOTS {
+			self.slots.pop_front();
+		}
+		self.slots.push_back((slot_number, authored));
+	}
+
+	fn claimed(&self) -> usize {
+		self.slots.len()
+	}
+
+	fn authored(&self) -> usize {
+		self.slots.iter().filter(|(_, authored)| *authored).count()
+	}
+
+	fn missed(&self) -> usize {
+		self.claimed() - self.authored()
+	}
+}
+
+/// What is known of the keys of the authorities held by the keystore.
+#[derive(Debug, Default)]
+struct KeyState {
+	/// Hash of the last authority set the keystore held a key of.
+	set_with_key: Option<[u8; 32]>,
+	/// Whether the key of that set went missing.
+	missing: bool,
+}
+
+/// Authorship statistics of a slot worker.
+///
+/// Each slot worker, and so each chain of the node, keeps its own statistics and counts its
+/// missed slots in the registry of its chain. Clones share them.
+#[derive(Debug, Clone, Default)]
+pub struct AuthorshipStats {
+	claimed_slots: Arc<Mutex<ClaimedSlots>>,
+	authority_key: Arc<Mutex<KeyState>>,
+	missed_slots: Option<CounterVec<U64>>,
+}
+
+impl AuthorshipStats {
+	/// Create the statistics of a slot worker, counting its missed slots in the
+	/// `slots_missed_total` counter of `registry`.
+	pub fn new(registry: Option<&Registry>) -> Self {
+		let missed_slots = registry.and_then(|registry| match register_missed_slots(registry) {
+			Ok(counter) => Some(counter),
+			Err(e) => {
+				debug!(target: "slots", "Failed to register missed slots counter: {:?}", e);
+				None
+			},
+		});
+
+		AuthorshipStats { missed_slots, ..Default::default() }
+	}
+
+	/// Record that this node claimed `slot_number`, and whether it authored a block in it or
+	/// missed it, e.g. because proposing took too long or the block couldn't be imported.
+	pub(crate) fn note_claimed_slot(&self, slot_number: u64, authored: bool) {
+		let mut slots = self.claimed_slots.lock();
+		slots.note(slot_number, authored);
+
+		telemetry!(CONSENSUS_INFO; "slots.claimed_slot";
+			"slot" => slot_number,
+			"authored" => authored,
+			"recent_claimed" => slots.claimed(),
+			"recent_authored" => slots.authored(),
+			"recent_missed" => slots.missed(),
+		);
+	}
+
+	/// Record that this node skipped `slot_number`, in which it may have authored a block, for
+	/// `reason`.
+	///
+	/// Whether the slot could have been claimed isn't checked, so that skipping slots stays
+	/// cheap, and the slot is only counted in the `slots_missed_total` counter.
+	pub(crate) fn note_skipped_slot(
+		&self,
+		slot_number: u64,
+		reason: MissedSlotReason,
+		logging_target: &str,
+	) {
+		self.note_missed(reason);
+		debug!(target: logging_target, "Skipped slot {}: {}", slot_number, reason.as_str());
+	}
+
+	/// Record that this node claimed `slot_number` but didn't author a block in it.
+	pub(crate) fn note_missed_slot(
+		&self,
+		slot_number: u64,
+		reason: MissedSlotReason,
+		logging_target: &str,
+	) {
+		self.note_missed(reason);
+		self.note_claimed_slot(slot_number, false);
+
+		warn!(
+			target: logging_target,
+			"Missed slot {}: {}",
+			slot_number,
+			reason.as_str(),
+		);
+		telemetry!(CONSENSUS_WARN; "slots.missed_slot";
+			"slot" => slot_number,
+			"reason" => reason.as_str(),
+		);
+	}
+
+	/// Record whether the keystore holds a key of the authorities at `slot_number`.
+	///
+	/// A node that never held a key of the current authority set isn't one of its authorities,
+	/// so only losing the key of a set is reported: the node can't claim slots anymore, so each
+	/// of them is counted as missed and a warning is logged when the key goes missing.
+	pub(crate) fn note_authority_key(
+		&self,
+		slot_number: u64,
+		key: AuthorityKey,
+		logging_target: &str,
+	) {
+		let mut state = self.authority_key.lock();
+		if key.has_key {
+			if state.missing {
+				info!(target: logging_target, "🔑 The keystore holds a key of the authorities again");
+			}
+			*state = KeyState { set_with_key: Some(key.set_hash), missing: false };
+			return;
+		}
+		if state.set_with_key != Some(key.set_hash) {
+			return;
+		}
+
+		self.note_missed(MissedSlotReason::EmptyKeystore);
+		if !state.missing {
+			state.missing = true;
+			warn!(
+				target: logging_target,
+				"🔑 Missed slot {}: {}, the keystore doesn't hold the key of the current \
+				authorities anymore. Please check that the session keys weren't removed.",
+				slot_number,
+				MissedSlotReason::EmptyKeystore.as_str(),
+			);
+			telemetry!(CONSENSUS_WARN; "slots.missed_slot";
+				"slot" => slot_number,
+				"reason" => MissedSlotReason::EmptyKeystore.as_str(),
+			);
+		}
+	}
+
+	fn note_missed(&self, reason: MissedSlotReason) {
+		if let Some(counter) = &self.missed_slots {
+			counter.with_label_values(&[reason.as_str()]).inc();
+		}
+	}
+
+	/// Whether the keystore lost the key of the current authorities.
+	#[cfg(test)]
+	fn missing_authority_key(&self) -> bool {
+		self.authority_key.lock().missing
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn workers_count_their_own_slots() {
+		let (first, second) = (AuthorshipStats::default(), AuthorshipStats::default());
+		let shared = first.clone();
+
+		first.note_claimed_slot(1, true);
+		shared.note_claimed_slot(2, false);
+		second.note_claimed_slot(3, true);
+
+		assert_eq!(first.claimed_slots.lock().claimed(), 2);
+		assert_eq!(first.claimed_slots.lock().missed(), 1);
+		assert_eq!(second.claimed_slots.lock().claimed(), 1);
+	}
+
+	#[test]
+	fn only_losing_the_key_of_a_set_is_reported() {
+		let stats = AuthorshipStats::default();
+		let key = |set: u8, has_key| AuthorityKey { set_hash: [set; 32], has_key };
+
+		// never held a key of the set
+		stats.note_authority_key(1, key(1, false), "test");
+		assert!(!stats.missing_authority_key());
+
+		stats.note_authority_key(2, key(1, true), "test");
+		stats.note_authority_key(3, key(1, false), "test");
+		assert!(stats.missing_authority_key());
+
+		stats.note_authority_key(4, key(1, true), "test");
+		assert!(!stats.missing_authority_key());
+
+		// the node left the authorities with the next set
+		stats.note_authority_key(5, key(2, false), "test");
+		assert!(!stats.missing_authority_key());
+
+		// the state of the key is kept per worker
+		let other = AuthorshipStats::default();
+		other.note_authority_key(6, key(1, false), "test");
+		assert!(!other.missing_authority_key());
+	}
+
+	#[test]
+	fn chains_count_their_missed_slots_in_their_own_registry() {
+		let (first_registry, second_registry) = (Registry::new(), Registry::new());
+		let first = AuthorshipStats::new(Some(&first_registry));
+		let second = AuthorshipStats::new(Some(&second_registry));
+		let missed = |registry: &Registry| registry.gather().iter()
+			.filter(|family| family.get_name() == "slots_missed_total")
+			.flat_map(|family| family.get_metric().iter().map(|m| m.get_counter().get_value()))
+			.sum::<f64>();
+
+		first.note_skipped_slot(1, MissedSlotReason::Offline, "test");
+		first.note_missed_slot(2, MissedSlotReason::ProposerTimeout, "test");
+		second.note_skipped_slot(3, MissedSlotReason::SlotSkipped, "test");
+
+		assert_eq!(missed(&first_registry), 2.0);
+		assert_eq!(missed(&second_registry), 1.0);
+	}
+
+	#[test]
+	fn counts_recent_slots_only() {
+		let mut slots = ClaimedSlots::default();
+		for slot_number in 0..RECENT_SLOTS as u64 {
+			slots.note(slot_number, slot_number % 4 != 0);
+		}
+		assert_eq!(slots.claimed(), RECENT_SLOTS);
+		assert_eq!(slots.missed(), RECENT_SLOTS / 4);
+
+		slots.note(RECENT_SLOTS as u64, true);
+		assert_eq!(slots.claimed(), RECENT_SLOTS);
+		assert_eq!(slots.missed(), RECENT_SLOTS / 4 - 1);
+		assert_eq!(slots.authored(), RECENT_SLOTS - RECENT_SLOTS / 4 + 1);
+	}
+}
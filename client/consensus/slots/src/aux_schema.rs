@@ -23,6 +23,7 @@ use sp_runtime::traits::Header;
 
 const SLOT_HEADER_MAP_KEY: &[u8] = b"slot_header_map";
 const SLOT_HEADER_START: &[u8] = b"slot_header_start";
+const LAST_AUTHORED_SLOT_KEY: &[u8] = b"slots_last_authored_slot";
 
 /// We keep at least this number of slots in database.
 pub const MAX_SLOT_CAPACITY: u64 = 1000;
@@ -154,6 +155,21 @@ pub fn check_equivocation<C, H, P>(
 	Ok(None)
 }
 
+/// Records that this node is about to author a block in `slot`.
+///
+/// If this node already authored a block in `slot` or a later slot, nothing is recorded and that
+/// slot is returned: authoring another block would be an equivocation.
+pub fn note_authored_slot<C: AuxStore>(backend: &C, slot: u64) -> ClientResult<Option<u64>> {
+	if let Some(last_authored) = load_decode::<_, u64>(backend, LAST_AUTHORED_SLOT_KEY)? {
+		if last_authored >= slot {
+			return Ok(Some(last_authored));
+		}
+	}
+
+	backend.insert_aux(&[(LAST_AUTHORED_SLOT_KEY, slot.encode().as_slice())], &[])?;
+	Ok(None)
+}
+
 #[cfg(test)]
 mod test {
 	use sp_core::{sr25519, Pair};
@@ -161,7 +177,7 @@ mod test {
 	use sp_runtime::testing::{Header as HeaderTest, Digest as DigestTest};
 	use substrate_test_runtime_client;
 
-	use super::{MAX_SLOT_CAPACITY, PRUNING_BOUND, check_equivocation};
+	use super::{MAX_SLOT_CAPACITY, PRUNING_BOUND, check_equivocation, note_authored_slot};
 
 	fn create_header(number: u64) -> HeaderTest {
 		// so that different headers for the same number get different hashes
@@ -267,4 +283,15 @@ mod test {
 			).unwrap().is_none(),
 		);
 	}
+
+	#[test]
+	fn note_authored_slot_refuses_past_slots() {
+		let client = substrate_test_runtime_client::new();
+
+		assert_eq!(note_authored_slot(&client, 5).unwrap(), None);
+		assert_eq!(note_authored_slot(&client, 5).unwrap(), Some(5));
+		assert_eq!(note_authored_slot(&client, 4).unwrap(), Some(5));
+		assert_eq!(note_authored_slot(&client, 6).unwrap(), None);
+		assert_eq!(note_authored_slot(&client, 6).unwrap(), Some(6));
+	}
 }
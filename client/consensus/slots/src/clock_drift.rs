@@ -0,0 +1,160 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Estimation of the drift of the local clock from the slots of the blocks broadcast by peers.
+//!
+//! A block is expected to be received during the slot it was authored in. A block received before
+//! its slot starts means that the local clock is behind, and blocks received after their slot ends
+//! mean that it is ahead. Some blocks are late because of the network, so the estimate is the
+//! median over the recently received blocks.
+
+use std::collections::VecDeque;
+use log::{debug, info, warn};
+use prometheus_endpoint::{register, Gauge, PrometheusError, Registry, I64};
+use sc_telemetry::{telemetry, CONSENSUS_WARN};
+
+/// Number of received blocks the drift is estimated from.
+pub const DRIFT_SAMPLES: usize = 16;
+
+/// Estimate of the drift of the local clock, reported as a Prometheus gauge.
+///
+/// A warning is logged when the drift exceeds half a slot, as blocks are then authored or
+/// received in the wrong slot, which leads to rejected blocks and equivocations.
+pub struct ClockDrift {
+	/// Duration of a slot, in milliseconds.
+	slot_duration: u64,
+	/// Drift observed for the recently received blocks, in milliseconds.
+	samples: VecDeque<i64>,
+	/// Whether the drift exceeded the threshold at the last block.
+	drifting: bool,
+	gauge: Option<Gauge<I64>>,
+}
+
+impl ClockDrift {
+	/// Create a new estimate for slots of `slot_duration` milliseconds, registering its gauge in
+	/// `registry`.
+	pub fn new(slot_duration: u64, registry: Option<&Registry>) -> Self {
+		let gauge = registry.and_then(|registry| register_gauge(registry)
+			.map_err(|e| debug!(target: "slots", "Failed to register clock drift gauge: {:?}", e))
+			.ok()
+		);
+
+		ClockDrift {
+			slot_duration,
+			samples: VecDeque::with_capacity(DRIFT_SAMPLES),
+			drifting: false,
+			gauge,
+		}
+	}
+
+	/// Note a block authored in `slot_number` and received from a peer at `timestamp_now`, the
+	/// local time in milliseconds.
+	pub fn note_block(&mut self, slot_number: u64, timestamp_now: u64) {
+		let slot_start = slot_number.saturating_mul(self.slot_duration);
+		let slot_end = slot_start.saturating_add(self.slot_duration);
+		let sample = if timestamp_now < slot_start {
+			-((slot_start - timestamp_now) as i64)
+		} else if timestamp_now > slot_end {
+			(timestamp_now - slot_end) as i64
+		} else {
+			0
+		};
+
+		if self.samples.len() == DRIFT_SAMPLES {
+			self.samples.pop_front();
+		}
+		self.samples.push_back(sample);
+
+		let drift = self.drift().expect("a sample was just added; qed");
+		if let Some(ref gauge) = self.gauge {
+			gauge.set(drift);
+		}
+
+		let drifting = drift.abs() as u64 > self.slot_duration / 2;
+		if drifting && !self.drifting {
+			warn!(
+				target: "slots",
+				"⏰ The local clock seems to be {} by {} ms compared to the blocks of other \
+				nodes. Please check that it is synchronized, e.g. with NTP.",
+				if drift > 0 { "ahead" } else { "behind" },
+				drift.abs(),
+			);
+			telemetry!(CONSENSUS_WARN; "slots.clock_drift"; "drift_ms" => drift);
+		} else if !drifting && self.drifting {
+			info!(target: "slots", "⏰ The local clock is synchronized again");
+		}
+		self.drifting = drifting;
+	}
+
+	/// Returns the estimated drift of the local clock in milliseconds, positive if it is ahead,
+	/// or `None` if no block was received yet.
+	pub fn drift(&self) -> Option<i64> {
+		if self.samples.is_empty() {
+			return None;
+		}
+
+		let mut samples = self.samples.iter().cloned().collect::<Vec<_>>();
+		samples.sort();
+		Some(samples[samples.len() / 2])
+	}
+}
+
+fn register_gauge(registry: &Registry) -> Result<Gauge<I64>, PrometheusError> {
+	register(
+		Gauge::new(
+			"consensus_clock_drift_milliseconds",
+			"Estimated drift of the local clock compared to the slots of the blocks of other nodes",
+		)?,
+		registry,
+	)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn estimates_drift_from_received_blocks() {
+		let mut clock_drift = ClockDrift::new(1000, None);
+		assert_eq!(clock_drift.drift(), None);
+
+		// received during their slot.
+		clock_drift.note_block(10, 10_500);
+		clock_drift.note_block(11, 11_000);
+		assert_eq!(clock_drift.drift(), Some(0));
+		assert!(!clock_drift.drifting);
+
+		// received before their slot starts, because the local clock is behind.
+		for slot_number in 12..30 {
+			clock_drift.note_block(slot_number, slot_number * 1000 - 700);
+		}
+		assert_eq!(clock_drift.drift(), Some(-700));
+		assert!(clock_drift.drifting);
+
+		// a few blocks delayed by the network don't change the estimate.
+		for slot_number in 30..35 {
+			clock_drift.note_block(slot_number, slot_number * 1000 + 3000);
+		}
+		assert_eq!(clock_drift.drift(), Some(-700));
+
+		// received after their slot ends, because the local clock is ahead.
+		for slot_number in 35..51 {
+			clock_drift.note_block(slot_number, slot_number * 1000 + 1200);
+		}
+		assert_eq!(clock_drift.drift(), Some(200));
+		assert!(!clock_drift.drifting);
+	}
+}
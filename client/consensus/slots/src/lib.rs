@@ -25,10 +25,16 @@
 
 mod slots;
 mod aux_schema;
+mod authorship_stats;
+mod clock_drift;
 
 pub use slots::{SignedDuration, SlotInfo};
 use slots::Slots;
-pub use aux_schema::{check_equivocation, MAX_SLOT_CAPACITY, PRUNING_BOUND};
+pub use aux_schema::{check_equivocation, note_authored_slot, MAX_SLOT_CAPACITY, PRUNING_BOUND};
+pub use authorship_stats::{
+	AuthorityKey, AuthorshipStats, RECENT_SLOTS,
+};
+pub use clock_drift::{ClockDrift, DRIFT_SAMPLES};
 
 use codec::{Decode, Encode};
 use sp_consensus::{BlockImport, Proposer, SyncOracle, SelectChain, CanAuthorWith, SlotData, RecordProof};
@@ -42,6 +48,7 @@ use sp_api::{ProvideRuntimeApi, ApiRef};
 use std::{fmt::Debug, ops::Deref, pin::Pin, sync::Arc, time::{Instant, Duration}};
 use sc_telemetry::{telemetry, CONSENSUS_DEBUG, CONSENSUS_WARN, CONSENSUS_INFO};
 use parking_lot::Mutex;
+use authorship_stats::MissedSlotReason;
 
 /// The changes that need to applied to the storage to create the state for a block.
 ///
@@ -105,6 +112,14 @@ pub trait SimpleSlotWorker<B: BlockT> {
 		epoch_data: &Self::EpochData,
 	) -> Option<Self::Claim>;
 
+	/// Returns whether the keystore holds the key of any of the authorities given the epoch data.
+	/// None indicates that it is unknown.
+	///
+	/// This is checked at every slot, so it must be cheaper than claiming the slot.
+	fn authority_key(&self, _epoch_data: &Self::EpochData) -> Option<AuthorityKey> {
+		None
+	}
+
 	/// Return the pre digest data to include in a block authored with the given claim.
 	fn pre_digest_data(
 		&self,
@@ -130,6 +145,16 @@ pub trait SimpleSlotWorker<B: BlockT> {
 	/// Whether to force authoring if offline.
 	fn force_authoring(&self) -> bool;
 
+	/// The authorship statistics of this worker.
+	fn authorship_stats(&self) -> &AuthorshipStats;
+
+	/// Records that a block is about to be authored in `slot_number`, or returns the slot the
+	/// last block was authored in if it isn't before `slot_number`.
+	///
+	/// The record must be durable, e.g. by using [`note_authored_slot`], so that no second block
+	/// is authored in a slot after a restart.
+	fn note_authored_slot(&self, slot_number: u64) -> Result<Option<u64>, sp_consensus::Error>;
+
 	/// Returns a handle to a `SyncOracle`.
 	fn sync_oracle(&mut self) -> &mut Self::SyncOracle;
 
@@ -163,16 +188,29 @@ pub trait SimpleSlotWorker<B: BlockT> {
 	{
 		let (timestamp, slot_number, slot_duration) =
 			(slot_info.timestamp, slot_info.number, slot_info.duration);
+		let logging_target = self.logging_target();
+		let authorship_stats = self.authorship_stats().clone();
 
 		{
 			let slot_now = SignedDuration::default().slot_now(slot_duration);
 			if slot_now > slot_number {
 				// if this is behind, return.
-				debug!(target: self.logging_target(),
+				debug!(target: logging_target,
 					"Skipping proposal slot {} since our current view is {}",
 					slot_number, slot_now,
 				);
 
+				let has_key = self.epoch_data(&chain_head, slot_number).ok()
+					.and_then(|epoch_data| self.authority_key(&epoch_data))
+					.map_or(false, |key| key.has_key);
+				if has_key {
+					authorship_stats.note_skipped_slot(
+						slot_number,
+						MissedSlotReason::SlotSkipped,
+						logging_target,
+					);
+				}
+
 				return Box::pin(future::ready(Ok(())));
 			}
 		}
@@ -198,23 +236,79 @@ pub trait SimpleSlotWorker<B: BlockT> {
 			self.sync_oracle().is_offline() &&
 			authorities_len.map(|a| a > 1).unwrap_or(false)
 		{
-			debug!(target: self.logging_target(), "Skipping proposal slot. Waiting for the network.");
+			debug!(target: logging_target, "Skipping proposal slot. Waiting for the network.");
 			telemetry!(
 				CONSENSUS_DEBUG;
 				"slots.skipping_proposal_slot";
 				"authorities_len" => authorities_len,
 			);
 
+			if self.authority_key(&epoch_data).map_or(false, |key| key.has_key) {
+				authorship_stats.note_skipped_slot(
+					slot_number,
+					MissedSlotReason::Offline,
+					logging_target,
+				);
+			}
+
 			return Box::pin(future::ready(Ok(())));
 		}
 
+		if let Some(key) = self.authority_key(&epoch_data) {
+			authorship_stats.note_authority_key(slot_number, key, logging_target);
+		}
+
 		let claim = match self.claim_slot(&chain_head, slot_number, &epoch_data) {
 			None => return Box::pin(future::ready(Ok(()))),
 			Some(claim) => claim,
 		};
 
+		match self.note_authored_slot(slot_number) {
+			Ok(None) => {},
+			Ok(Some(last_authored_slot)) => {
+				warn!(
+					target: logging_target,
+					"Refusing to author a block in slot {}, a block was already authored in \
+					slot {}",
+					slot_number,
+					last_authored_slot,
+				);
+
+				telemetry!(CONSENSUS_WARN; "slots.refusing_to_equivocate";
+					"slot" => slot_number,
+					"last_authored_slot" => last_authored_slot,
+				);
+				authorship_stats.note_missed_slot(
+					slot_number,
+					MissedSlotReason::AlreadyAuthored,
+					logging_target,
+				);
+
+				return Box::pin(future::ready(Ok(())));
+			},
+			Err(err) => {
+				warn!("Unable to record authorship of slot {}: {:?}", slot_number, err);
+
+				telemetry!(CONSENSUS_WARN; "slots.unable_recording_authorship";
+					"slot" => slot_number, "err" => ?err,
+				);
+				authorship_stats.note_missed_slot(
+					slot_number,
+					MissedSlotReason::AuxStorage,
+					logging_target,
+				);
+
+				return Box::pin(future::ready(Ok(())));
+			},
+		}
+
+		// A graceful shutdown waits until the block authored in this slot has been imported, so
+		// that the slot isn't lost. GRANDPA votes are gossiped as soon as they are cast and don't
+		// need to delay the shutdown.
+		let shutdown_guard = sp_utils::shutdown::delay_shutdown();
+
 		debug!(
-			target: self.logging_target(), "Starting authorship at slot {}; timestamp = {}",
+			target: logging_target, "Starting authorship at slot {}; timestamp = {}",
 			slot_number,
 			timestamp,
 		);
@@ -231,7 +325,7 @@ pub trait SimpleSlotWorker<B: BlockT> {
 				"slot" => slot_number, "err" => ?err
 			);
 
-			err
+			(err, MissedSlotReason::ProposerFailed)
 		});
 
 		let slot_remaining_duration = self.slot_remaining_duration(&slot_info);
@@ -246,7 +340,10 @@ pub trait SimpleSlotWorker<B: BlockT> {
 			},
 			slot_remaining_duration,
 			RecordProof::No,
-		).map_err(|e| sp_consensus::Error::ClientImport(format!("{:?}", e))));
+		).map_err(|e| (
+			sp_consensus::Error::ClientImport(format!("{:?}", e)),
+			MissedSlotReason::ProposerFailed,
+		)));
 
 		let delay: Box<dyn Future<Output=()> + Unpin + Send> = match proposing_remaining_duration {
 			Some(r) => Box::new(Delay::new(r)),
@@ -264,13 +361,15 @@ pub trait SimpleSlotWorker<B: BlockT> {
 					telemetry!(CONSENSUS_INFO; "slots.discarding_proposal_took_too_long";
 						"slot" => slot_number,
 					);
-					Err(sp_consensus::Error::ClientImport("Timeout in the Slots proposer".into()))
+					Err((
+						sp_consensus::Error::ClientImport("Timeout in the Slots proposer".into()),
+						MissedSlotReason::ProposerTimeout,
+					))
 				},
 			}));
 
 		let block_import_params_maker = self.block_import_params();
 		let block_import = self.block_import();
-		let logging_target = self.logging_target();
 
 		Box::pin(proposal_work.and_then(move |(proposal, claim)| {
 			let (header, body) = proposal.block.deconstruct();
@@ -289,7 +388,7 @@ pub trait SimpleSlotWorker<B: BlockT> {
 
 			let block_import_params = match block_import_params {
 				Ok(params) => params,
-				Err(e) => return future::err(e),
+				Err(e) => return future::err((e, MissedSlotReason::ImportFailed)),
 			};
 
 			info!(
@@ -305,7 +404,9 @@ pub trait SimpleSlotWorker<B: BlockT> {
 				"hash_previously" => ?header_hash,
 			);
 
-			if let Err(err) = block_import.lock().import_block(block_import_params, Default::default()) {
+			let imported = block_import.lock()
+				.import_block(block_import_params, Default::default());
+			if let Err(err) = &imported {
 				warn!(target: logging_target,
 					"Error with block built on {:?}: {:?}",
 					parent_hash,
@@ -316,7 +417,26 @@ pub trait SimpleSlotWorker<B: BlockT> {
 					"hash" => ?parent_hash, "err" => ?err,
 				);
 			}
-			future::ready(Ok(()))
+
+			drop(shutdown_guard);
+			future::ready(Ok(imported.is_ok()))
+		}).map(move |authored| match authored {
+			Ok(true) => {
+				authorship_stats.note_claimed_slot(slot_number, true);
+				Ok(())
+			},
+			Ok(false) => {
+				authorship_stats.note_missed_slot(
+					slot_number,
+					MissedSlotReason::ImportFailed,
+					logging_target,
+				);
+				Ok(())
+			},
+			Err((err, reason)) => {
+				authorship_stats.note_missed_slot(slot_number, reason, logging_target);
+				Err(err)
+			},
 		}))
 	}
 }
@@ -336,8 +456,8 @@ pub trait SlotCompatible {
 
 /// Start a new slot worker.
 ///
-/// Every time a new slot is triggered, `worker.on_slot` is called and the future it returns is
-/// polled until completion, unless we are major syncing.
+/// Every time a new slot of `clock` is triggered, `worker.on_slot` is called and the future it
+/// returns is polled until completion, unless we are major syncing.
 pub fn start_slot_worker<B, C, W, T, SO, SC, CAW>(
 	slot_duration: SlotDuration<T>,
 	client: C,
@@ -346,6 +466,7 @@ pub fn start_slot_worker<B, C, W, T, SO, SC, CAW>(
 	inherent_data_providers: InherentDataProviders,
 	timestamp_extractor: SC,
 	can_author_with: CAW,
+	clock: Arc<dyn sp_timestamp::Clock>,
 ) -> impl Future<Output = ()>
 where
 	B: BlockT,
@@ -364,6 +485,7 @@ where
 		slot_duration.slot_duration(),
 		inherent_data_providers,
 		timestamp_extractor,
+		clock,
 	).inspect_err(|e| debug!(target: "slots", "Faulty timer: {:?}", e))
 		.try_for_each(move |slot_info| {
 			// only propose when we are not syncing.
@@ -372,6 +494,11 @@ where
 				return Either::Right(future::ready(Ok(())));
 			}
 
+			if sync_oracle.is_in_maintenance() {
+				debug!(target: "slots", "Skipping proposal slot due to maintenance.");
+				return Either::Right(future::ready(Ok(())));
+			}
+
 			let slot_num = slot_info.number;
 			let chain_head = match client.best_chain() {
 				Ok(x) => x,
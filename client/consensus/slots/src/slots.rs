@@ -22,9 +22,9 @@ use super::SlotCompatible;
 use sp_consensus::Error;
 use futures::{prelude::*, task::Context, task::Poll};
 use sp_inherents::{InherentData, InherentDataProviders};
+use sp_timestamp::Clock;
 
-use std::{pin::Pin, time::{Duration, Instant}};
-use futures_timer::Delay;
+use std::{pin::Pin, sync::Arc, time::{Duration, Instant}};
 
 /// Returns current duration since unix epoch.
 pub fn duration_now() -> Duration {
@@ -87,9 +87,10 @@ pub struct SlotInfo {
 pub(crate) struct Slots<SC> {
 	last_slot: u64,
 	slot_duration: u64,
-	inner_delay: Option<Delay>,
+	inner_delay: Option<Pin<Box<dyn Future<Output = ()> + Send>>>,
 	inherent_data_providers: InherentDataProviders,
 	timestamp_extractor: SC,
+	clock: Arc<dyn Clock>,
 }
 
 impl<SC> Slots<SC> {
@@ -98,6 +99,7 @@ impl<SC> Slots<SC> {
 		slot_duration: u64,
 		inherent_data_providers: InherentDataProviders,
 		timestamp_extractor: SC,
+		clock: Arc<dyn Clock>,
 	) -> Self {
 		Slots {
 			last_slot: 0,
@@ -105,6 +107,7 @@ impl<SC> Slots<SC> {
 			inner_delay: None,
 			inherent_data_providers,
 			timestamp_extractor,
+			clock,
 		}
 	}
 }
@@ -118,14 +121,14 @@ impl<SC: SlotCompatible + Unpin> Stream for Slots<SC> {
 			self.inner_delay = match self.inner_delay.take() {
 				None => {
 					// schedule wait.
-					let wait_dur = time_until_next(duration_now(), slot_duration);
-					Some(Delay::new(wait_dur))
+					let wait_dur = time_until_next(self.clock.now(), slot_duration);
+					Some(self.clock.delay(wait_dur))
 				}
 				Some(d) => Some(d),
 			};
 
 			if let Some(ref mut inner_delay) = self.inner_delay {
-				match Future::poll(Pin::new(inner_delay), cx) {
+				match inner_delay.as_mut().poll(cx) {
 					Poll::Pending => return Poll::Pending,
 					Poll::Ready(()) => {}
 				}
@@ -146,7 +149,7 @@ impl<SC: SlotCompatible + Unpin> Stream for Slots<SC> {
 			let ends_in = offset +
 				time_until_next(Duration::from_millis(timestamp), slot_duration);
 			let ends_at = Instant::now() + ends_in;
-			self.inner_delay = Some(Delay::new(ends_in));
+			self.inner_delay = Some(self.clock.delay(ends_in));
 
 			// never yield the same slot twice.
 			if slot_num > self.last_slot {
@@ -0,0 +1,128 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2020 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Compression of the block bodies and justifications stored in the database.
+//!
+//! Every stored value starts with a byte telling how the rest of it is compressed, so that
+//! values written with different settings can be read back whatever the current settings are.
+//! The values of the databases created before the tags were introduced are untagged until the
+//! database is upgraded, see `utils::block_values_tagged`.
+
+use std::borrow::Cow;
+
+/// Tag of the values stored uncompressed.
+const UNCOMPRESSED: u8 = 0;
+/// Tag of the values compressed with zstd.
+const ZSTD: u8 = 1;
+
+/// Compression of the block bodies and justifications written to the database.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlockCompression {
+	/// Values are stored as they are.
+	None,
+	/// Values are compressed with zstd at the given level, from 1 to 22.
+	Zstd {
+		/// Compression level. Higher levels compress better but more slowly.
+		level: i32,
+	},
+}
+
+impl Default for BlockCompression {
+	fn default() -> Self {
+		BlockCompression::None
+	}
+}
+
+/// Returns the value to store for the encoded `value`, `compression` being `None` for the
+/// databases whose values are untagged.
+///
+/// Values that don't get smaller once compressed are stored uncompressed.
+pub(crate) fn compress(compression: Option<BlockCompression>, value: Vec<u8>) -> Vec<u8> {
+	let compression = match compression {
+		Some(compression) => compression,
+		None => return value,
+	};
+
+	if let BlockCompression::Zstd { level } = compression {
+		match zstd::stream::encode_all(&value[..], level) {
+			Ok(compressed) if compressed.len() < value.len() => {
+				let mut stored = Vec::with_capacity(compressed.len() + 1);
+				stored.push(ZSTD);
+				stored.extend(compressed);
+				return stored;
+			},
+			Ok(_) => {},
+			Err(e) => log::warn!(target: "db", "Storing a value uncompressed: {}", e),
+		}
+	}
+
+	tag(value)
+}
+
+/// Returns `value` tagged as uncompressed.
+pub(crate) fn tag(mut value: Vec<u8>) -> Vec<u8> {
+	value.insert(0, UNCOMPRESSED);
+	value
+}
+
+/// Returns the encoded value of the `stored` value, which is `tagged` or not.
+pub(crate) fn decompress(stored: &[u8], tagged: bool) -> Result<Cow<[u8]>, String> {
+	if !tagged {
+		return Ok(Cow::Borrowed(stored));
+	}
+
+	match stored.split_first() {
+		Some((&UNCOMPRESSED, value)) => Ok(Cow::Borrowed(value)),
+		Some((&ZSTD, compressed)) => zstd::stream::decode_all(compressed)
+			.map(Cow::Owned)
+			.map_err(|e| format!("Error decompressing a value: {}", e)),
+		Some((tag, _)) => Err(format!("Unknown compression of a value: {}", tag)),
+		None => Err("Empty value".into()),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn values_are_read_back_whatever_the_compression() {
+		let value = vec![42u8; 1000];
+		for compression in &[BlockCompression::None, BlockCompression::Zstd { level: 3 }] {
+			let stored = compress(Some(*compression), value.clone());
+			assert_eq!(decompress(&stored, true).unwrap(), &value[..]);
+		}
+		let zstd = Some(BlockCompression::Zstd { level: 3 });
+		assert!(compress(zstd, value.clone()).len() < value.len());
+
+		// not worth compressing
+		let stored = compress(zstd, vec![1, 2, 3]);
+		assert_eq!(stored, vec![UNCOMPRESSED, 1, 2, 3]);
+
+		assert!(decompress(&[7, 1, 2], true).is_err());
+		assert!(decompress(&[], true).is_err());
+	}
+
+	#[test]
+	fn untagged_values_are_stored_and_read_as_they_are() {
+		// the first byte would be taken for an unknown tag
+		let value = vec![7u8, 42, 42];
+		assert_eq!(compress(None, value.clone()), value);
+		assert_eq!(decompress(&value, false).unwrap(), &value[..]);
+	}
+}
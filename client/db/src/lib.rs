@@ -36,12 +36,14 @@ pub mod bench;
 
 mod children;
 mod cache;
+mod compression;
 mod changes_tries_storage;
 mod storage_cache;
 #[cfg(any(feature = "kvdb-rocksdb", test))]
 mod upgrade;
 mod utils;
 mod stats;
+mod trie_cache;
 #[cfg(feature = "parity-db")]
 mod parity_db;
 #[cfg(feature = "subdb")]
@@ -85,14 +87,19 @@ use sc_state_db::StateDb;
 use sp_blockchain::{CachedHeaderMetadata, HeaderMetadata, HeaderMetadataCache};
 use crate::storage_cache::{CachingState, SyncingCachingState, SharedCache, new_shared_cache};
 use crate::stats::StateUsageStats;
+use crate::trie_cache::TrieNodeCache;
 use log::{trace, debug, warn};
 
 // Re-export the Database trait so that one can pass an implementation of it.
 pub use sp_database::Database;
 pub use sc_state_db::PruningMode;
+pub use compression::BlockCompression;
 
 #[cfg(any(feature = "kvdb-rocksdb", test))]
 pub use bench::BenchmarkingState;
+#[cfg(any(feature = "kvdb-rocksdb", test))]
+pub use upgrade::{MigrationInfo, pending_migrations, upgrade_db};
+pub use utils::{DatabaseType, stored_database_type};
 
 const MIN_BLOCKS_TO_KEEP_CHANGES_TRIES_FOR: u32 = 32768;
 
@@ -260,14 +267,38 @@ impl<B: BlockT> StateBackend<HashFor<B>> for RefTrackingState<B> {
 
 /// Database settings.
 pub struct DatabaseSettings {
-	/// State cache size.
-	pub state_cache_size: usize,
-	/// Ratio of cache size dedicated to child tries.
+	/// Size of the cache of trie nodes, shared by all states.
+	pub trie_cache_size: usize,
+	/// Size of the cache of storage values.
+	pub value_cache_size: usize,
+	/// Ratio of the storage value cache size dedicated to child tries.
 	pub state_cache_child_ratio: Option<(usize, usize)>,
 	/// Pruning mode.
 	pub pruning: PruningMode,
 	/// Where to find the database.
 	pub source: DatabaseSettingsSrc,
+	/// Cold storage that the bodies of old finalized blocks are moved to, if any.
+	pub cold_storage: Option<ColdStorageSettings>,
+	/// Open the existing database without ever writing to it. Any attempt to write to it is a
+	/// fatal error.
+	pub read_only: bool,
+	/// Compression of the block bodies and justifications written to the database. Values
+	/// already stored are read whatever their compression. The values of the databases that
+	/// aren't tagged with their compression are stored uncompressed, see `utils::block_values_tagged`.
+	pub block_compression: BlockCompression,
+}
+
+/// Settings of the cold storage.
+///
+/// Block bodies are moved to the cold storage once they are `depth` blocks below the last
+/// finalized block. Headers and justifications always stay in the main database. Bodies of
+/// blocks that were finalized before the cold storage was configured are not moved.
+#[derive(Clone)]
+pub struct ColdStorageSettings {
+	/// Where to find the cold database.
+	pub source: DatabaseSettingsSrc,
+	/// Number of finalized blocks whose bodies are kept in the main database.
+	pub depth: u32,
 }
 
 /// Where to find the database..
@@ -362,20 +393,33 @@ impl<'a> sc_state_db::MetaDb for StateMetaDb<'a> {
 	}
 }
 
+/// Database that bodies of old finalized blocks are moved to.
+struct ColdStorage {
+	db: Arc<dyn Database<DbHash>>,
+	/// Whether the bodies of the cold database are tagged with their compression.
+	tagged: bool,
+	depth: u32,
+}
+
 /// Block database
 pub struct BlockchainDb<Block: BlockT> {
 	db: Arc<dyn Database<DbHash>>,
+	/// Whether the bodies and justifications of `db` are tagged with their compression.
+	tagged: bool,
+	cold: Option<ColdStorage>,
 	meta: Arc<RwLock<Meta<NumberFor<Block>, Block::Hash>>>,
 	leaves: RwLock<LeafSet<Block::Hash, NumberFor<Block>>>,
 	header_metadata_cache: Arc<HeaderMetadataCache<Block>>,
 }
 
 impl<Block: BlockT> BlockchainDb<Block> {
-	fn new(db: Arc<dyn Database<DbHash>>) -> ClientResult<Self> {
+	fn new(db: Arc<dyn Database<DbHash>>, cold: Option<ColdStorage>) -> ClientResult<Self> {
 		let meta = read_meta::<Block>(&*db, columns::HEADER)?;
 		let leaves = LeafSet::read_from_db(&*db, columns::META, meta_keys::LEAF_PREFIX)?;
 		Ok(BlockchainDb {
+			tagged: utils::block_values_tagged(&*db),
 			db,
+			cold,
 			leaves: RwLock::new(leaves),
 			meta: Arc::new(RwLock::new(meta)),
 			header_metadata_cache: Arc::new(HeaderMetadataCache::default()),
@@ -454,8 +498,19 @@ impl<Block: BlockT> sc_client_api::blockchain::HeaderBackend<Block> for Blockcha
 
 impl<Block: BlockT> sc_client_api::blockchain::Backend<Block> for BlockchainDb<Block> {
 	fn body(&self, id: BlockId<Block>) -> ClientResult<Option<Vec<Block::Extrinsic>>> {
-		match read_db(&*self.db, columns::KEY_LOOKUP, columns::BODY, id)? {
-			Some(body) => match Decode::decode(&mut &body[..]) {
+		let body = match read_db(&*self.db, columns::KEY_LOOKUP, columns::BODY, id)? {
+			Some(body) => Some((body, self.tagged)),
+			None => match self.cold {
+				Some(ref cold) => utils::block_id_to_lookup_key(&*self.db, columns::KEY_LOOKUP, id)?
+					.and_then(|key| cold.db.get(columns::BODY, &key))
+					.map(|body| (body, cold.tagged)),
+				None => None,
+			},
+		};
+		match body {
+			Some((body, tagged)) => match compression::decompress(&body, tagged)
+				.and_then(|body| Decode::decode(&mut &body[..]).map_err(|e| e.to_string()))
+			{
 				Ok(body) => Ok(Some(body)),
 				Err(err) => return Err(sp_blockchain::Error::Backend(
 					format!("Error decoding body: {}", err)
@@ -467,7 +522,11 @@ impl<Block: BlockT> sc_client_api::blockchain::Backend<Block> for BlockchainDb<B
 
 	fn justification(&self, id: BlockId<Block>) -> ClientResult<Option<Justification>> {
 		match read_db(&*self.db, columns::KEY_LOOKUP, columns::JUSTIFICATION, id)? {
-			Some(justification) => match Decode::decode(&mut &justification[..]) {
+			Some(justification) => match compression::decompress(&justification, self.tagged)
+				.and_then(|justification| {
+					Decode::decode(&mut &justification[..]).map_err(|e| e.to_string())
+				})
+			{
 				Ok(justification) => Ok(Some(justification)),
 				Err(err) => return Err(sp_blockchain::Error::Backend(
 					format!("Error decoding justification: {}", err)
@@ -685,15 +744,16 @@ struct StorageDb<Block: BlockT> {
 	pub db: Arc<dyn Database<DbHash>>,
 	pub state_db: StateDb<Block::Hash, Vec<u8>>,
 	prefix_keys: bool,
+	trie_cache: TrieNodeCache,
 }
 
 impl<Block: BlockT> sp_state_machine::Storage<HashFor<Block>> for StorageDb<Block> {
 	fn get(&self, key: &Block::Hash, prefix: Prefix) -> Result<Option<DBValue>, String> {
 		if self.prefix_keys {
 			let key = prefixed_key::<HashFor<Block>>(key, prefix);
-			self.state_db.get(&key, self)
+			self.trie_cache.get_or_fetch(&key, || self.state_db.get(&key, self))
 		} else {
-			self.state_db.get(key.as_ref(), self)
+			self.trie_cache.get_or_fetch(key.as_ref(), || self.state_db.get(key.as_ref(), self))
 		}
 		.map_err(|e| format!("Database backend error: {:?}", e))
 	}
@@ -777,8 +837,11 @@ pub struct Backend<Block: BlockT> {
 	shared_cache: SharedCache<Block>,
 	import_lock: Arc<RwLock<()>>,
 	is_archive: bool,
-	io_stats: FrozenForDuration<(kvdb::IoStats, StateUsageInfo)>,
+	io_stats: FrozenForDuration<(kvdb::IoStats, StateUsageInfo, (u64, u64))>,
 	state_usage: Arc<StateUsageStats>,
+	/// Compression of the block values written, `None` if the values of the database are
+	/// untagged.
+	block_compression: Option<BlockCompression>,
 }
 
 impl<Block: BlockT> Backend<Block> {
@@ -786,7 +849,11 @@ impl<Block: BlockT> Backend<Block> {
 	///
 	/// The pruning window is how old a block must be before the state is pruned.
 	pub fn new(config: DatabaseSettings, canonicalization_delay: u64) -> ClientResult<Self> {
-		let db = crate::utils::open_database::<Block>(&config, DatabaseType::Full)?;
+		let db = crate::utils::open_database::<Block>(
+			&config.source,
+			DatabaseType::Full,
+			config.read_only,
+		)?;
 		Self::from_database(db as Arc<_>, canonicalization_delay, &config)
 	}
 
@@ -796,10 +863,14 @@ impl<Block: BlockT> Backend<Block> {
 		let db = kvdb_memorydb::create(crate::utils::NUM_COLUMNS);
 		let db = sp_database::as_database(db);
 		let db_setting = DatabaseSettings {
-			state_cache_size: 16777216,
+			trie_cache_size: 16777216,
+			value_cache_size: 16777216,
 			state_cache_child_ratio: Some((50, 100)),
 			pruning: PruningMode::keep_blocks(keep_blocks),
 			source: DatabaseSettingsSrc::Custom(db),
+			cold_storage: None,
+			read_only: false,
+			block_compression: BlockCompression::None,
 		};
 
 		Self::new(db_setting, canonicalization_delay).expect("failed to create test-db")
@@ -811,7 +882,33 @@ impl<Block: BlockT> Backend<Block> {
 		config: &DatabaseSettings,
 	) -> ClientResult<Self> {
 		let is_archive_pruning = config.pruning.is_archive();
-		let blockchain = BlockchainDb::new(db.clone())?;
+		let cold = match config.cold_storage {
+			Some(ref cold) => {
+				let cold_db = crate::utils::open_database::<Block>(
+					&cold.source,
+					DatabaseType::Full,
+					config.read_only,
+				)?;
+				Some(ColdStorage {
+					tagged: utils::block_values_tagged(&*cold_db),
+					db: cold_db,
+					depth: cold.depth,
+				})
+			},
+			None => None,
+		};
+		let blockchain = BlockchainDb::new(db.clone(), cold)?;
+		let block_compression = if blockchain.tagged {
+			Some(config.block_compression)
+		} else {
+			if config.block_compression != BlockCompression::None {
+				warn!(
+					"The block bodies and justifications of the database aren't tagged with their \
+					compression, they are stored uncompressed"
+				);
+			}
+			None
+		};
 		let meta = blockchain.meta.clone();
 		let map_e = |e: sc_state_db::Error<io::Error>| sp_blockchain::Error::from(
 			format!("State database error: {:?}", e)
@@ -825,6 +922,7 @@ impl<Block: BlockT> Backend<Block> {
 			db: db.clone(),
 			state_db,
 			prefix_keys: !config.source.supports_ref_counting(),
+			trie_cache: TrieNodeCache::new(config.trie_cache_size),
 		};
 		let offchain_storage = offchain::LocalStorage::new(db.clone());
 		let changes_tries_storage = DbChangesTrieStorage::new(
@@ -850,13 +948,14 @@ impl<Block: BlockT> Backend<Block> {
 			blockchain,
 			canonicalization_delay,
 			shared_cache: new_shared_cache(
-				config.state_cache_size,
+				config.value_cache_size,
 				config.state_cache_child_ratio.unwrap_or(DEFAULT_CHILD_RATIO),
 			),
 			import_lock: Default::default(),
 			is_archive: is_archive_pruning,
 			io_stats: FrozenForDuration::new(std::time::Duration::from_secs(1)),
 			state_usage: Arc::new(StateUsageStats::new()),
+			block_compression,
 		})
 	}
 
@@ -970,7 +1069,7 @@ impl<Block: BlockT> Backend<Block> {
 			transaction.set_from_vec(
 				columns::JUSTIFICATION,
 				&utils::number_and_hash_to_lookup_key(number, hash)?,
-				justification.encode(),
+				compression::compress(self.block_compression, justification.encode()),
 			);
 		}
 		Ok((*hash, number, false, true))
@@ -1068,10 +1167,18 @@ impl<Block: BlockT> Backend<Block> {
 
 			transaction.set_from_vec(columns::HEADER, &lookup_key, pending_block.header.encode());
 			if let Some(body) = &pending_block.body {
-				transaction.set_from_vec(columns::BODY, &lookup_key, body.encode());
+				transaction.set_from_vec(
+					columns::BODY,
+					&lookup_key,
+					compression::compress(self.block_compression, body.encode()),
+				);
 			}
 			if let Some(justification) = pending_block.justification {
-				transaction.set_from_vec(columns::JUSTIFICATION, &lookup_key, justification.encode());
+				transaction.set_from_vec(
+					columns::JUSTIFICATION,
+					&lookup_key,
+					compression::compress(self.block_compression, justification.encode()),
+				);
 			}
 
 			if number.is_zero() {
@@ -1311,6 +1418,10 @@ impl<Block: BlockT> Backend<Block> {
 			}
 		}
 
+		if let Some(ref cold) = self.blockchain.cold {
+			self.move_body_to_cold_storage(transaction, cold, f_num)?;
+		}
+
 		let new_displaced = self.blockchain.leaves.write().finalize_height(f_num);
 		match displaced {
 			x @ &mut None => *x = Some(new_displaced),
@@ -1319,6 +1430,84 @@ impl<Block: BlockT> Backend<Block> {
 
 		Ok(())
 	}
+
+	// moves the body of the block `cold.depth` blocks below the newly finalized block to the
+	// cold storage. The body is written to the cold storage before the transaction removing it
+	// from the main database is committed, so it is never missing from both.
+	fn move_body_to_cold_storage(
+		&self,
+		transaction: &mut Transaction<DbHash>,
+		cold: &ColdStorage,
+		f_num: NumberFor<Block>,
+	) -> ClientResult<()> {
+		let number = match f_num.saturated_into::<u64>().checked_sub(cold.depth as u64) {
+			Some(number) => number,
+			None => return Ok(()),
+		};
+		let lookup_key = utils::block_id_to_lookup_key::<Block>(
+			&*self.storage.db,
+			columns::KEY_LOOKUP,
+			BlockId::Number(number.saturated_into()),
+		)?;
+
+		if let Some(lookup_key) = lookup_key {
+			if let Some(body) = self.storage.db.get(columns::BODY, &lookup_key) {
+				let body = match (self.blockchain.tagged, cold.tagged) {
+					(true, false) => compression::decompress(&body, true)
+						.map_err(sp_blockchain::Error::Backend)?
+						.into_owned(),
+					(false, true) => compression::tag(body),
+					_ => body,
+				};
+				let mut cold_transaction = Transaction::new();
+				cold_transaction.set_from_vec(columns::BODY, &lookup_key, body);
+				cold.db.commit(cold_transaction);
+				transaction.remove(columns::BODY, &lookup_key);
+			}
+		}
+
+		Ok(())
+	}
+}
+
+#[cfg(feature = "runtime-benchmarks")]
+impl<Block: BlockT> Backend<Block> {
+	/// Write the trie nodes inserted by `update` straight to the state column and remove
+	/// them again. Returns the time the write took.
+	///
+	/// This bypasses the state db and must only be used to benchmark the database of a node
+	/// that is not running. Nodes that were already present are left in place.
+	pub fn benchmark_state_write(
+		&self,
+		mut update: PrefixedMemoryDB<HashFor<Block>>,
+	) -> ClientResult<std::time::Duration> {
+		let db = &self.storage.db;
+		let mut write = Transaction::new();
+		let mut revert = Transaction::new();
+
+		for (mut key, (val, rc)) in update.drain() {
+			if rc <= 0 {
+				continue
+			}
+			if !self.storage.prefix_keys {
+				key.drain(0 .. key.len() - DB_HASH_LEN);
+			}
+			// Without reference counting, removing a node that was already present would
+			// corrupt the state.
+			let existed = self.storage.prefix_keys && db.get(columns::STATE, &key).is_some();
+			write.set(columns::STATE, &key, &val);
+			if !existed {
+				revert.remove(columns::STATE, &key);
+			}
+		}
+
+		let started = std::time::Instant::now();
+		db.commit(write);
+		let elapsed = started.elapsed();
+		db.commit(revert);
+
+		Ok(elapsed)
+	}
 }
 
 fn apply_state_commit(transaction: &mut Transaction<DbHash>, commit: sc_state_db::CommitSet<Vec<u8>>) {
@@ -1448,22 +1637,26 @@ impl<Block: BlockT> sc_client_api::backend::Backend<Block> for Backend<Block> {
 	}
 
 	fn usage_info(&self) -> Option<UsageInfo> {
-		let (io_stats, state_stats) = self.io_stats.take_or_else(||
-			(
-				// TODO: implement DB stats and cache size retrieval
-				kvdb::IoStats::empty(),
-				self.state_usage.take(),
-			)
-		);
+		let (io_stats, state_stats, (trie_cache_hits, trie_cache_misses)) =
+			self.io_stats.take_or_else(||
+				(
+					// TODO: implement DB stats and cache size retrieval
+					kvdb::IoStats::empty(),
+					self.state_usage.take(),
+					self.storage.trie_cache.take_stats(),
+				)
+			);
 		let database_cache = MemorySize::from_bytes(0);
 		let state_cache = MemorySize::from_bytes(
 			(*&self.shared_cache).lock().used_storage_cache_size(),
 		);
+		let trie_cache = MemorySize::from_bytes(self.storage.trie_cache.used_size());
 		let state_db = self.storage.state_db.memory_info();
 
 		Some(UsageInfo {
 			memory: MemoryInfo {
 				state_cache,
+				trie_cache,
 				database_cache,
 				state_db,
 			},
@@ -1479,6 +1672,8 @@ impl<Block: BlockT> sc_client_api::backend::Backend<Block> for Backend<Block> {
 				state_writes_cache: state_stats.overlay_writes.ops,
 				state_reads_cache: state_stats.cache_reads.ops,
 				state_writes_nodes: state_stats.nodes_writes.ops,
+				trie_node_reads: trie_cache_hits + trie_cache_misses,
+				trie_node_reads_cache: trie_cache_hits,
 			},
 		})
 	}
@@ -1774,10 +1969,14 @@ pub(crate) mod tests {
 		};
 
 		let backend = Backend::<Block>::new(DatabaseSettings {
-			state_cache_size: 16777216,
+			trie_cache_size: 16777216,
+			value_cache_size: 16777216,
 			state_cache_child_ratio: Some((50, 100)),
 			pruning: PruningMode::keep_blocks(1),
 			source: DatabaseSettingsSrc::Custom(backing),
+			cold_storage: None,
+			read_only: false,
+			block_compression: BlockCompression::None,
 		}, 0).unwrap();
 		assert_eq!(backend.blockchain().info().best_number, 9);
 		for i in 0..10 {
@@ -2283,6 +2482,88 @@ pub(crate) mod tests {
 		}
 	}
 
+	#[test]
+	fn old_bodies_are_moved_to_cold_storage() {
+		let db = sp_database::as_database(kvdb_memorydb::create(crate::utils::NUM_COLUMNS));
+		let cold = sp_database::as_database(kvdb_memorydb::create(crate::utils::NUM_COLUMNS));
+		let backend = Backend::<Block>::new(DatabaseSettings {
+			trie_cache_size: 16777216,
+			value_cache_size: 16777216,
+			state_cache_child_ratio: Some((50, 100)),
+			pruning: PruningMode::keep_blocks(10),
+			source: DatabaseSettingsSrc::Custom(db),
+			cold_storage: Some(ColdStorageSettings {
+				source: DatabaseSettingsSrc::Custom(cold.clone()),
+				depth: 2,
+			}),
+			read_only: false,
+			block_compression: BlockCompression::None,
+		}, 10).unwrap();
+
+		let mut hashes = Vec::new();
+		let mut parent = Default::default();
+		for number in 0..5 {
+			parent = insert_header(&backend, number, parent, None, Default::default());
+			hashes.push(parent);
+		}
+		for number in 1..5 {
+			backend.finalize_block(BlockId::Number(number), None).unwrap();
+		}
+
+		for (number, hash) in hashes.into_iter().enumerate() {
+			let lookup_key = utils::number_and_hash_to_lookup_key(number as u64, hash).unwrap();
+			let is_cold = number <= 2;
+			assert_eq!(backend.storage.db.get(columns::BODY, &lookup_key).is_none(), is_cold);
+			assert_eq!(cold.get(columns::BODY, &lookup_key).is_some(), is_cold);
+			assert_eq!(backend.blockchain().body(BlockId::Hash(hash)).unwrap(), Some(Vec::new()));
+		}
+	}
+
+	#[test]
+	fn untagged_databases_are_read_and_written_untagged() {
+		// a database created before the block values were tagged
+		let db = sp_database::as_database(kvdb_memorydb::create(crate::utils::NUM_COLUMNS));
+		let mut transaction = Transaction::new();
+		transaction.set(columns::META, meta_keys::TYPE, b"full");
+		db.commit(transaction);
+		let cold = sp_database::as_database(kvdb_memorydb::create(crate::utils::NUM_COLUMNS));
+		let backend = Backend::<Block>::new(DatabaseSettings {
+			trie_cache_size: 16777216,
+			value_cache_size: 16777216,
+			state_cache_child_ratio: Some((50, 100)),
+			pruning: PruningMode::keep_blocks(10),
+			source: DatabaseSettingsSrc::Custom(db),
+			cold_storage: Some(ColdStorageSettings {
+				source: DatabaseSettingsSrc::Custom(cold.clone()),
+				depth: 2,
+			}),
+			read_only: false,
+			block_compression: BlockCompression::Zstd { level: 3 },
+		}, 10).unwrap();
+		assert!(!backend.blockchain.tagged);
+		assert!(backend.blockchain.cold.as_ref().unwrap().tagged);
+
+		let mut hashes = Vec::new();
+		let mut parent = Default::default();
+		for number in 0..5 {
+			parent = insert_header(&backend, number, parent, None, Default::default());
+			hashes.push(parent);
+		}
+		for number in 1..5 {
+			backend.finalize_block(BlockId::Number(number), None).unwrap();
+		}
+
+		for (number, hash) in hashes.into_iter().enumerate() {
+			let lookup_key = utils::number_and_hash_to_lookup_key(number as u64, hash).unwrap();
+			if number <= 2 {
+				assert_eq!(cold.get(columns::BODY, &lookup_key), Some(vec![0, 0]));
+			} else {
+				assert_eq!(backend.storage.db.get(columns::BODY, &lookup_key), Some(vec![0]));
+			}
+			assert_eq!(backend.blockchain().body(BlockId::Hash(hash)).unwrap(), Some(Vec::new()));
+		}
+	}
+
 	#[test]
 	fn test_finalize_non_sequential() {
 		let backend = Backend::<Block>::new_test(10, 10);
@@ -73,7 +73,11 @@ pub struct LightStorage<Block: BlockT> {
 impl<Block: BlockT> LightStorage<Block> {
 	/// Create new storage with given settings.
 	pub fn new(config: DatabaseSettings) -> ClientResult<Self> {
-		let db = crate::utils::open_database::<Block>(&config, DatabaseType::Light)?;
+		let db = crate::utils::open_database::<Block>(
+			&config.source,
+			DatabaseType::Light,
+			config.read_only,
+		)?;
 		Self::from_kvdb(db as Arc<_>)
 	}
 
@@ -585,6 +589,7 @@ impl<Block> Storage<Block> for LightStorage<Block>
 			memory: MemoryInfo {
 				database_cache,
 				state_cache: Default::default(),
+				trie_cache: Default::default(),
 				state_db: Default::default(),
 			},
 			io: IoInfo {
@@ -600,6 +605,8 @@ impl<Block> Storage<Block> for LightStorage<Block>
 				state_reads_cache: 0,
 				state_writes_cache: 0,
 				state_writes_nodes: 0,
+				trie_node_reads: 0,
+				trie_node_reads_cache: 0,
 			}
 		})
 	}
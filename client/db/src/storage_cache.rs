@@ -49,7 +49,7 @@ pub struct Cache<B: BlockT> {
 	modifications: VecDeque<BlockChanges<B::Header>>,
 }
 
-struct LRUMap<K, V>(LinkedHashMap<K, V>, usize, usize);
+pub(crate) struct LRUMap<K, V>(LinkedHashMap<K, V>, usize, usize);
 
 /// Internal trait similar to `heapsize` but using
 /// a simply estimation.
@@ -57,7 +57,7 @@ struct LRUMap<K, V>(LinkedHashMap<K, V>, usize, usize);
 /// This should not be made public, it is implementation
 /// detail trait. If it need to become public please
 /// consider using `malloc_size_of`.
-trait EstimateSize {
+pub(crate) trait EstimateSize {
 	/// Return a size estimation of additional size needed
 	/// to cache this struct (in bytes).
 	fn estimate_size(&self) -> usize;
@@ -91,7 +91,12 @@ impl<T: EstimateSize> EstimateSize for (T, T) {
 }
 
 impl<K: EstimateSize + Eq + StdHash, V: EstimateSize> LRUMap<K, V> {
-	fn remove(&mut self, k: &K) {
+	/// Create an empty map that holds at most `limit` bytes.
+	pub(crate) fn new(limit: usize) -> Self {
+		LRUMap(LinkedHashMap::new(), 0, limit)
+	}
+
+	pub(crate) fn remove(&mut self, k: &K) {
 		let map = &mut self.0;
 		let storage_used_size = &mut self.1;
 		if let Some(v) = map.remove(k) {
@@ -100,7 +105,7 @@ impl<K: EstimateSize + Eq + StdHash, V: EstimateSize> LRUMap<K, V> {
 		}
 	}
 
-	fn add(&mut self, k: K, v: V) {
+	pub(crate) fn add(&mut self, k: K, v: V) {
 		let lmap = &mut self.0;
 		let storage_used_size = &mut self.1;
 		let limit = self.2;
@@ -132,13 +137,13 @@ impl<K: EstimateSize + Eq + StdHash, V: EstimateSize> LRUMap<K, V> {
 		}
 	}
 
-	fn get<Q:?Sized>(&mut self, k: &Q) -> Option<&mut V>
+	pub(crate) fn get<Q:?Sized>(&mut self, k: &Q) -> Option<&mut V>
 		where K: std::borrow::Borrow<Q>,
 			Q: StdHash + Eq {
 		self.0.get_refresh(k)
 	}
 
-	fn used_size(&self) -> usize {
+	pub(crate) fn used_size(&self) -> usize {
 		self.1
 	}
 	fn clear(&mut self) {
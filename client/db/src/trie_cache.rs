@@ -0,0 +1,117 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2020 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Shared cache of encoded trie nodes.
+//!
+//! Trie nodes are stored under the hash of their encoding, so a cached node never becomes
+//! stale. The cache is shared by all states of a backend, which avoids reading and decoding
+//! the same upper trie nodes from the database over and over during block import.
+
+use std::sync::atomic::{AtomicU64, Ordering as AtomicOrdering};
+use parking_lot::Mutex;
+use sp_trie::DBValue;
+use crate::storage_cache::LRUMap;
+
+/// Shared LRU cache of trie nodes, keyed by their database key.
+pub(crate) struct TrieNodeCache {
+	nodes: Mutex<LRUMap<Vec<u8>, DBValue>>,
+	hits: AtomicU64,
+	misses: AtomicU64,
+}
+
+impl TrieNodeCache {
+	/// Create a new cache holding at most `size` bytes of trie nodes.
+	pub fn new(size: usize) -> Self {
+		TrieNodeCache {
+			nodes: Mutex::new(LRUMap::new(size)),
+			hits: 0.into(),
+			misses: 0.into(),
+		}
+	}
+
+	/// Return the node stored under `key`, reading it with `fetch` if it isn't cached.
+	///
+	/// Missing nodes are not cached, as they may be inserted later on.
+	pub fn get_or_fetch<E>(
+		&self,
+		key: &[u8],
+		fetch: impl FnOnce() -> Result<Option<DBValue>, E>,
+	) -> Result<Option<DBValue>, E> {
+		if let Some(node) = self.nodes.lock().get(key) {
+			self.hits.fetch_add(1, AtomicOrdering::Relaxed);
+			return Ok(Some(node.clone()));
+		}
+
+		self.misses.fetch_add(1, AtomicOrdering::Relaxed);
+		let node = fetch()?;
+		if let Some(node) = &node {
+			self.nodes.lock().add(key.to_vec(), node.clone());
+		}
+		Ok(node)
+	}
+
+	/// Returns the used memory size of the cache in bytes.
+	pub fn used_size(&self) -> usize {
+		self.nodes.lock().used_size()
+	}
+
+	/// Returns the number of cache hits and misses and resets them.
+	pub fn take_stats(&self) -> (u64, u64) {
+		(
+			self.hits.swap(0, AtomicOrdering::Relaxed),
+			self.misses.swap(0, AtomicOrdering::Relaxed),
+		)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn fetch(node: Option<Vec<u8>>) -> impl FnOnce() -> Result<Option<DBValue>, ()> {
+		move || Ok(node)
+	}
+
+	#[test]
+	fn caches_present_nodes_only() {
+		let cache = TrieNodeCache::new(1024);
+
+		assert_eq!(cache.get_or_fetch(b"missing", fetch(None)), Ok(None));
+		assert_eq!(cache.get_or_fetch(b"missing", fetch(Some(vec![1]))), Ok(Some(vec![1])));
+		assert_eq!(cache.get_or_fetch(b"missing", fetch(None)), Ok(Some(vec![1])));
+
+		assert_eq!(cache.take_stats(), (1, 2));
+		assert_eq!(cache.take_stats(), (0, 0));
+	}
+
+	#[test]
+	fn evicts_least_recently_used_nodes() {
+		let cache = TrieNodeCache::new(16);
+
+		cache.get_or_fetch(b"aaaa", fetch(Some(vec![1; 4]))).unwrap();
+		cache.get_or_fetch(b"bbbb", fetch(Some(vec![2; 4]))).unwrap();
+		cache.get_or_fetch(b"aaaa", fetch(None)).unwrap();
+		cache.get_or_fetch(b"cccc", fetch(Some(vec![3; 4]))).unwrap();
+		assert_eq!(cache.take_stats(), (1, 3));
+
+		// `bbbb` was evicted to make room for `cccc`.
+		assert_eq!(cache.get_or_fetch(b"aaaa", fetch(None)), Ok(Some(vec![1; 4])));
+		assert_eq!(cache.get_or_fetch(b"bbbb", fetch(None)), Ok(None));
+		assert!(cache.used_size() <= 16);
+	}
+}
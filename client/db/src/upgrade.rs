@@ -20,30 +20,184 @@ use std::fs;
 use std::io::{Read, Write, ErrorKind};
 use std::path::{Path, PathBuf};
 
+use kvdb::KeyValueDB;
+use log::info;
 use sp_runtime::traits::Block as BlockT;
-use crate::utils::DatabaseType;
+use crate::{columns, compression, utils::{DatabaseType, NUM_COLUMNS, meta_keys}};
 
 /// Version file name.
 const VERSION_FILE_NAME: &'static str = "db_version";
 
 /// Current db version.
-const CURRENT_VERSION: u32 = 1;
+const CURRENT_VERSION: u32 = 2;
+
+/// Oldest db version that can be upgraded to the current version.
+const MIN_SUPPORTED_VERSION: u32 = 1;
+
+/// A migration of the database from one version to the next.
+struct Migration {
+	/// Version the migration upgrades from. The database has version `from + 1` afterwards.
+	from: u32,
+	/// Description of the migration, shown to the user.
+	description: &'static str,
+	/// Applies the migration to the database at the given path.
+	///
+	/// Progress is reported by calling the given function with the number of processed and
+	/// total items.
+	apply: fn(&Path, DatabaseType, &mut dyn FnMut(u64, u64)) -> sp_blockchain::Result<()>,
+}
+
+/// All migrations, ordered by version.
+///
+/// There must be exactly one migration for every version from `MIN_SUPPORTED_VERSION` up to,
+/// but not including, `CURRENT_VERSION`.
+const MIGRATIONS: &[Migration] = &[
+	Migration {
+		from: 1,
+		description: "tag the block bodies and justifications with their compression",
+		apply: tag_block_values,
+	},
+];
+
+/// Key of the meta column storing the column and the last key tagged by the migration to
+/// version 2, so that an interrupted migration doesn't tag the same values twice.
+const TAG_PROGRESS_KEY: &[u8] = b"tag_block_values_progress";
+
+/// Number of values tagged in a single database transaction.
+const TAG_BATCH_SIZE: u64 = 10_000;
+
+/// Migration to version 2: prefixes the stored block bodies and justifications with the tag of
+/// the uncompressed values.
+fn tag_block_values(
+	db_path: &Path,
+	db_type: DatabaseType,
+	progress: &mut dyn FnMut(u64, u64),
+) -> sp_blockchain::Result<()> {
+	tag_block_values_in_batches(db_path, db_type, TAG_BATCH_SIZE, progress)
+}
+
+/// Tags the block values `batch_size` at a time, see `tag_block_values`. Every transaction
+/// records the last value it tagged, and the database is marked as tagged in the last one.
+fn tag_block_values_in_batches(
+	db_path: &Path,
+	db_type: DatabaseType,
+	batch_size: u64,
+	progress: &mut dyn FnMut(u64, u64),
+) -> sp_blockchain::Result<()> {
+	// Light databases store neither bodies nor justifications.
+	if db_type != DatabaseType::Full {
+		return Ok(());
+	}
+
+	let path = db_path.to_str()
+		.ok_or_else(|| sp_blockchain::Error::Backend("Invalid database path".into()))?;
+	let db_config = kvdb_rocksdb::DatabaseConfig::with_columns(NUM_COLUMNS);
+	let db = kvdb_rocksdb::Database::open(&db_config, path).map_err(db_err)?;
+
+	// Column and last key tagged before the migration was interrupted.
+	let resume_from = db.get(columns::META, TAG_PROGRESS_KEY).map_err(db_err)?
+		.and_then(|progress| progress.split_first()
+			.map(|(column, key)| (*column as u32, key.to_vec())));
+
+	let block_columns = [columns::BODY, columns::JUSTIFICATION];
+	let total = block_columns.iter().map(|column| db.iter(*column).count() as u64).sum();
+	let mut done = 0;
+	let mut transaction = db.transaction();
+	for column in block_columns.iter().copied() {
+		for (key, value) in db.iter(column) {
+			done += 1;
+			let tagged = match &resume_from {
+				Some((resume_column, resume_key)) =>
+					column < *resume_column || (column == *resume_column && *key <= **resume_key),
+				None => false,
+			};
+			if tagged {
+				continue;
+			}
+
+			transaction.put_vec(column, &key, compression::tag(value.into_vec()));
+			let mut progress_value = vec![column as u8];
+			progress_value.extend_from_slice(&key);
+			transaction.put_vec(columns::META, TAG_PROGRESS_KEY, progress_value);
+			if done % batch_size == 0 {
+				db.write(std::mem::replace(&mut transaction, db.transaction())).map_err(db_err)?;
+				progress(done, total);
+			}
+		}
+	}
+
+	transaction.delete(columns::META, TAG_PROGRESS_KEY);
+	transaction.put(columns::META, meta_keys::TAGGED_BLOCK_VALUES, &[1]);
+	db.write(transaction).map_err(db_err)?;
+	progress(done, total);
+	Ok(())
+}
+
+/// A migration that is pending for a database.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MigrationInfo {
+	/// Version the migration upgrades from.
+	pub from: u32,
+	/// Version the migration upgrades to.
+	pub to: u32,
+	/// Description of the migration.
+	pub description: &'static str,
+}
+
+/// Returns the migrations that upgrading the database at `db_path` would apply, without
+/// applying them.
+pub fn pending_migrations(db_path: &Path) -> sp_blockchain::Result<Vec<MigrationInfo>> {
+	let migrations = match stored_version(db_path)? {
+		Some(version) => migrations_from(version)?,
+		None => &[],
+	};
+
+	Ok(migrations.iter().map(|migration| MigrationInfo {
+		from: migration.from,
+		to: migration.from + 1,
+		description: migration.description,
+	}).collect())
+}
 
 /// Upgrade database to current version.
-pub fn upgrade_db<Block: BlockT>(db_path: &Path, _db_type: DatabaseType) -> sp_blockchain::Result<()> {
-	let is_empty = db_path.read_dir().map_or(true, |mut d| d.next().is_none());
-	if !is_empty {
-		let db_version = current_version(db_path)?;
-		match db_version {
-			0 => Err(sp_blockchain::Error::Backend(format!("Unsupported database version: {}", db_version)))?,
-			1 => (),
-			_ => Err(sp_blockchain::Error::Backend(format!("Future database version: {}", db_version)))?,
+///
+/// The version file is updated after every migration, so an interrupted upgrade resumes with
+/// the migration that didn't finish.
+pub fn upgrade_db<Block: BlockT>(db_path: &Path, db_type: DatabaseType) -> sp_blockchain::Result<()> {
+	if let Some(version) = stored_version(db_path)? {
+		for migration in migrations_from(version)? {
+			let to = migration.from + 1;
+			info!("Migrating database from version {} to {}: {}", migration.from, to, migration.description);
+			(migration.apply)(db_path, db_type, &mut |done, total| {
+				info!("Migrating database to version {}: {}/{}", to, done, total)
+			})?;
+			update_version(db_path, to)?;
 		}
 	}
 
-	update_version(db_path)
+	update_version(db_path, CURRENT_VERSION)
 }
 
+/// Returns the version of the database at the given path, or `None` if there is no database.
+fn stored_version(db_path: &Path) -> sp_blockchain::Result<Option<u32>> {
+	let is_empty = db_path.read_dir().map_or(true, |mut d| d.next().is_none());
+	if is_empty {
+		Ok(None)
+	} else {
+		current_version(db_path).map(Some)
+	}
+}
+
+/// Returns the migrations needed to upgrade a database of the given version.
+fn migrations_from(version: u32) -> sp_blockchain::Result<&'static [Migration]> {
+	if version < MIN_SUPPORTED_VERSION {
+		Err(sp_blockchain::Error::Backend(format!("Unsupported database version: {}", version)))
+	} else if version > CURRENT_VERSION {
+		Err(sp_blockchain::Error::Backend(format!("Future database version: {}", version)))
+	} else {
+		Ok(&MIGRATIONS[(version - MIN_SUPPORTED_VERSION) as usize..])
+	}
+}
 
 /// Reads current database version from the file at given path.
 /// If the file does not exist returns 0.
@@ -66,12 +220,12 @@ fn db_err(err: std::io::Error) -> sp_blockchain::Error {
 	sp_blockchain::Error::Backend(format!("{}", err))
 }
 
-/// Writes current database version to the file.
+/// Writes the given database version to the file.
 /// Creates a new file if the version file does not exist yet.
-fn update_version(path: &Path) -> sp_blockchain::Result<()> {
+fn update_version(path: &Path, version: u32) -> sp_blockchain::Result<()> {
 	fs::create_dir_all(path).map_err(db_err)?;
 	let mut file = fs::File::create(version_file_path(path)).map_err(db_err)?;
-	file.write_all(format!("{}", CURRENT_VERSION).as_bytes()).map_err(db_err)?;
+	file.write_all(format!("{}", version).as_bytes()).map_err(db_err)?;
 	Ok(())
 }
 
@@ -84,8 +238,7 @@ fn version_file_path(path: &Path) -> PathBuf {
 
 #[cfg(test)]
 mod tests {
-	use sc_state_db::PruningMode;
-	use crate::{DatabaseSettings, DatabaseSettingsSrc};
+	use crate::DatabaseSettingsSrc;
 	use crate::tests::Block;
 	use super::*;
 
@@ -98,12 +251,87 @@ mod tests {
 	}
 
 	fn open_database(db_path: &Path) -> sp_blockchain::Result<()> {
-		crate::utils::open_database::<Block>(&DatabaseSettings {
-			state_cache_size: 0,
-			state_cache_child_ratio: None,
-			pruning: PruningMode::ArchiveAll,
-			source: DatabaseSettingsSrc::RocksDb { path: db_path.to_owned(), cache_size: 128 },
-		}, DatabaseType::Full).map(|_| ())
+		crate::utils::open_database::<Block>(
+			&DatabaseSettingsSrc::RocksDb { path: db_path.to_owned(), cache_size: 128 },
+			DatabaseType::Full,
+			false,
+		).map(|_| ())
+	}
+
+	#[test]
+	fn block_values_are_tagged_once() {
+		let db_dir = tempfile::TempDir::new().unwrap();
+		{
+			let db_config = kvdb_rocksdb::DatabaseConfig::with_columns(NUM_COLUMNS);
+			let db = kvdb_rocksdb::Database::open(&db_config, db_dir.path().to_str().unwrap())
+				.unwrap();
+			let mut transaction = db.transaction();
+			transaction.put(columns::BODY, b"block1", &[1, 2, 3]);
+			transaction.put(columns::BODY, b"block2", &[4]);
+			transaction.put(columns::JUSTIFICATION, b"block1", &[5]);
+			// as if the migration had been interrupted after the first body
+			transaction.put(columns::BODY, b"block0", &[0, 9]);
+			transaction.put(columns::META, TAG_PROGRESS_KEY, b"\x05block0");
+			db.write(transaction).unwrap();
+		}
+
+		tag_block_values(db_dir.path(), DatabaseType::Full, &mut |_, _| {}).unwrap();
+
+		let db_config = kvdb_rocksdb::DatabaseConfig::with_columns(NUM_COLUMNS);
+		let db = kvdb_rocksdb::Database::open(&db_config, db_dir.path().to_str().unwrap())
+			.unwrap();
+		assert_eq!(db.get(columns::BODY, b"block0").unwrap(), Some(vec![0, 9]));
+		assert_eq!(db.get(columns::BODY, b"block1").unwrap(), Some(vec![0, 1, 2, 3]));
+		assert_eq!(db.get(columns::BODY, b"block2").unwrap(), Some(vec![0, 4]));
+		assert_eq!(db.get(columns::JUSTIFICATION, b"block1").unwrap(), Some(vec![0, 5]));
+		assert_eq!(db.get(columns::META, TAG_PROGRESS_KEY).unwrap(), None);
+		assert!(db.get(columns::META, meta_keys::TAGGED_BLOCK_VALUES).unwrap().is_some());
+	}
+
+	#[test]
+	fn interrupted_tagging_resumes_after_the_last_batch() {
+		let db_dir = tempfile::TempDir::new().unwrap();
+		let open = || kvdb_rocksdb::Database::open(
+			&kvdb_rocksdb::DatabaseConfig::with_columns(NUM_COLUMNS),
+			db_dir.path().to_str().unwrap(),
+		).unwrap();
+		{
+			let db = open();
+			let mut transaction = db.transaction();
+			for block in 0u8..5 {
+				transaction.put(columns::BODY, &[block], &[block]);
+			}
+			transaction.put(columns::JUSTIFICATION, &[0], &[9]);
+			db.write(transaction).unwrap();
+		}
+
+		// crash once the first batch is committed
+		let crashed = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+			tag_block_values_in_batches(db_dir.path(), DatabaseType::Full, 2, &mut |_, _| {
+				panic!("crash")
+			})
+		}));
+		assert!(crashed.is_err());
+		{
+			let db = open();
+			assert_eq!(db.get(columns::BODY, &[1]).unwrap(), Some(vec![0, 1]));
+			assert_eq!(db.get(columns::BODY, &[2]).unwrap(), Some(vec![2]));
+			assert_eq!(
+				db.get(columns::META, TAG_PROGRESS_KEY).unwrap(),
+				Some(vec![columns::BODY as u8, 1]),
+			);
+			assert!(db.get(columns::META, meta_keys::TAGGED_BLOCK_VALUES).unwrap().is_none());
+		}
+
+		tag_block_values_in_batches(db_dir.path(), DatabaseType::Full, 2, &mut |_, _| {}).unwrap();
+
+		let db = open();
+		for block in 0u8..5 {
+			assert_eq!(db.get(columns::BODY, &[block]).unwrap(), Some(vec![0, block]));
+		}
+		assert_eq!(db.get(columns::JUSTIFICATION, &[0]).unwrap(), Some(vec![0, 9]));
+		assert_eq!(db.get(columns::META, TAG_PROGRESS_KEY).unwrap(), None);
+		assert!(db.get(columns::META, meta_keys::TAGGED_BLOCK_VALUES).unwrap().is_some());
 	}
 
 	#[test]
@@ -120,4 +348,24 @@ mod tests {
 		open_database(db_dir.path()).unwrap();
 		assert_eq!(current_version(db_dir.path()).unwrap(), CURRENT_VERSION);
 	}
+
+	#[test]
+	fn migrations_are_ordered_and_complete() {
+		assert_eq!(MIGRATIONS.len() as u32, CURRENT_VERSION - MIN_SUPPORTED_VERSION);
+		for (index, migration) in MIGRATIONS.iter().enumerate() {
+			assert_eq!(migration.from, MIN_SUPPORTED_VERSION + index as u32);
+		}
+	}
+
+	#[test]
+	fn pending_migrations_of_current_database_are_empty() {
+		let db_dir = tempfile::TempDir::new().unwrap();
+		assert_eq!(pending_migrations(db_dir.path()).unwrap(), vec![]);
+
+		create_db(db_dir.path(), Some(CURRENT_VERSION));
+		assert_eq!(pending_migrations(db_dir.path()).unwrap(), vec![]);
+
+		create_db(db_dir.path(), Some(CURRENT_VERSION + 1));
+		assert!(pending_migrations(db_dir.path()).is_err());
+	}
 }
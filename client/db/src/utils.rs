@@ -26,13 +26,13 @@ use log::debug;
 
 use codec::Decode;
 use sp_trie::DBValue;
-use sp_database::Transaction;
+use sp_database::{ColumnId, Transaction};
 use sp_runtime::generic::BlockId;
 use sp_runtime::traits::{
 	Block as BlockT, Header as HeaderT, Zero,
 	UniqueSaturatedFrom, UniqueSaturatedInto,
 };
-use crate::{DatabaseSettings, DatabaseSettingsSrc, Database, DbHash};
+use crate::{DatabaseSettingsSrc, Database, DbHash};
 
 /// Number of columns in the db. Must be the same for both full && light dbs.
 /// Otherwise RocksDb will fail to open database && check its type.
@@ -59,6 +59,8 @@ pub mod meta_keys {
 	pub const LEAF_PREFIX: &[u8; 4] = b"leaf";
 	/// Children prefix list key.
 	pub const CHILDREN_PREFIX: &[u8; 8] = b"children";
+	/// Set if the block bodies and justifications are tagged with their compression.
+	pub const TAGGED_BLOCK_VALUES: &[u8; 6] = b"tagged";
 }
 
 /// Database metadata.
@@ -209,20 +211,38 @@ pub fn block_id_to_lookup_key<Block>(
 
 /// Opens the configured database.
 pub fn open_database<Block: BlockT>(
-	config: &DatabaseSettings,
+	source: &DatabaseSettingsSrc,
 	db_type: DatabaseType,
+	read_only: bool,
 ) -> sp_blockchain::Result<Arc<dyn Database<DbHash>>> {
 	let db_open_error = |feat| Err(
 		sp_blockchain::Error::Backend(
 			format!("`{}` feature not enabled, database can not be opened", feat),
 		),
 	);
+	let no_database_error = || Err(sp_blockchain::Error::Backend(
+		"There is no database to open read-only".into(),
+	));
 
-	let db: Arc<dyn Database<DbHash>> = match &config.source {
+	if read_only && source.path().map_or(false, |path| !path.exists()) {
+		return no_database_error();
+	}
+
+	let db: Arc<dyn Database<DbHash>> = match source {
 		#[cfg(any(feature = "kvdb-rocksdb", test))]
 		DatabaseSettingsSrc::RocksDb { path, cache_size } => {
-			// first upgrade database to required version
-			crate::upgrade::upgrade_db::<Block>(&path, db_type)?;
+			if read_only {
+				if let Some(migration) = crate::upgrade::pending_migrations(&path)?.pop() {
+					return Err(sp_blockchain::Error::Backend(format!(
+						"The database must be upgraded to version {}, which can't be done \
+						read-only",
+						migration.to,
+					)));
+				}
+			} else {
+				// first upgrade database to required version
+				crate::upgrade::upgrade_db::<Block>(&path, db_type)?;
+			}
 
 			// and now open database assuming that it has the latest version
 			let mut db_config = kvdb_rocksdb::DatabaseConfig::with_columns(NUM_COLUMNS);
@@ -241,6 +261,12 @@ pub fn open_database<Block: BlockT>(
 			}
 
 			db_config.memory_budget = memory_budget;
+			if read_only {
+				// A secondary instance doesn't take the lock of the database, which may be open
+				// in a running node, and never writes to it.
+				db_config.secondary = Some(secondary_path()?);
+				db_config.max_open_files = -1;
+			}
 
 			log::trace!(
 				target: "db",
@@ -280,11 +306,87 @@ pub fn open_database<Block: BlockT>(
 		DatabaseSettingsSrc::Custom(db) => db.clone(),
 	};
 
+	let db = if read_only {
+		if db.get(COLUMN_META, meta_keys::TYPE).is_none() {
+			return no_database_error();
+		}
+		Arc::new(ReadOnlyDatabase(db))
+	} else {
+		db
+	};
+
 	check_database_type(&*db, db_type)?;
 
 	Ok(db)
 }
 
+/// Returns the directory of the logs of a secondary RocksDB instance of this process.
+#[cfg(any(feature = "kvdb-rocksdb", test))]
+fn secondary_path() -> sp_blockchain::Result<String> {
+	let path = std::env::temp_dir().join(format!("substrate-db-secondary-{}", std::process::id()));
+	std::fs::create_dir_all(&path)
+		.map_err(|e| sp_blockchain::Error::Backend(format!("{}", e)))?;
+	path.to_str()
+		.map(Into::into)
+		.ok_or_else(|| sp_blockchain::Error::Backend("Invalid database path".into()))
+}
+
+/// Returns the type of the existing database of `source`, `None` if there is no database or
+/// its type can't be read. The database isn't upgraded.
+pub fn stored_database_type(source: &DatabaseSettingsSrc) -> Option<DatabaseType> {
+	if source.path().map_or(false, |path| !path.exists()) {
+		return None;
+	}
+
+	let db: Arc<dyn Database<DbHash>> = match source {
+		#[cfg(any(feature = "kvdb-rocksdb", test))]
+		DatabaseSettingsSrc::RocksDb { path, .. } => {
+			let mut db_config = kvdb_rocksdb::DatabaseConfig::with_columns(NUM_COLUMNS);
+			db_config.secondary = Some(secondary_path().ok()?);
+			db_config.max_open_files = -1;
+			sp_database::as_database(kvdb_rocksdb::Database::open(&db_config, path.to_str()?).ok()?)
+		},
+		#[cfg(feature = "subdb")]
+		DatabaseSettingsSrc::SubDb { path } => crate::subdb::open(&path, NUM_COLUMNS).ok()?,
+		#[cfg(feature = "parity-db")]
+		DatabaseSettingsSrc::ParityDb { path } => crate::parity_db::open(&path).ok()?,
+		DatabaseSettingsSrc::Custom(db) => db.clone(),
+		#[allow(unreachable_patterns)]
+		_ => return None,
+	};
+
+	match db.get(COLUMN_META, meta_keys::TYPE)?.as_slice() {
+		b"full" => Some(DatabaseType::Full),
+		b"light" => Some(DatabaseType::Light),
+		_ => None,
+	}
+}
+
+/// A database that panics when written to, like databases do on critical errors.
+struct ReadOnlyDatabase(Arc<dyn Database<DbHash>>);
+
+impl Database<DbHash> for ReadOnlyDatabase {
+	fn commit(&self, _transaction: Transaction<DbHash>) {
+		panic!("Critical database error: attempt to write to the database, which is read-only");
+	}
+
+	fn get(&self, col: ColumnId, key: &[u8]) -> Option<Vec<u8>> {
+		self.0.get(col, key)
+	}
+
+	fn with_get(&self, col: ColumnId, key: &[u8], f: &mut dyn FnMut(&[u8])) {
+		self.0.with_get(col, key, f)
+	}
+
+	fn lookup(&self, hash: &DbHash) -> Option<Vec<u8>> {
+		self.0.lookup(hash)
+	}
+
+	fn with_lookup(&self, hash: &DbHash, f: &mut dyn FnMut(&[u8])) {
+		self.0.with_lookup(hash, f)
+	}
+}
+
 /// Check database type.
 pub fn check_database_type(db: &dyn Database<DbHash>, db_type: DatabaseType) -> sp_blockchain::Result<()> {
 	match db.get(COLUMN_META, meta_keys::TYPE) {
@@ -295,8 +397,10 @@ pub fn check_database_type(db: &dyn Database<DbHash>, db_type: DatabaseType) ->
 			}
 		},
 		None => {
+			// a new database, whose block values are tagged from the start.
 			let mut transaction = Transaction::new();
 			transaction.set(COLUMN_META, meta_keys::TYPE, db_type.as_str().as_bytes());
+			transaction.set(COLUMN_META, meta_keys::TAGGED_BLOCK_VALUES, &[1]);
 			db.commit(transaction)
 		},
 	}
@@ -304,6 +408,15 @@ pub fn check_database_type(db: &dyn Database<DbHash>, db_type: DatabaseType) ->
 	Ok(())
 }
 
+/// Whether the block bodies and justifications of the database are tagged with their
+/// compression.
+///
+/// The databases created before the tags were introduced are only tagged once upgraded, which is
+/// done for RocksDB. The values of the other databases are read and written untagged.
+pub fn block_values_tagged(db: &dyn Database<DbHash>) -> bool {
+	db.get(COLUMN_META, meta_keys::TAGGED_BLOCK_VALUES).is_some()
+}
+
 /// Read database column entry for the given block.
 pub fn read_db<Block>(
 	db: &dyn Database<DbHash>,
@@ -439,4 +552,45 @@ mod tests {
 		assert_eq!(DatabaseType::Full.as_str(), "full");
 		assert_eq!(DatabaseType::Light.as_str(), "light");
 	}
+
+	#[test]
+	fn read_only_database_must_exist_and_refuses_writes() {
+		let db: Arc<dyn Database<DbHash>> = Arc::new(sp_database::MemDb::default());
+		let source = DatabaseSettingsSrc::Custom(db.clone());
+		assert!(open_database::<Block>(&source, DatabaseType::Full, true).is_err());
+
+		open_database::<Block>(&source, DatabaseType::Full, false).unwrap();
+		let read_only = open_database::<Block>(&source, DatabaseType::Full, true).unwrap();
+		assert_eq!(read_only.get(COLUMN_META, meta_keys::TYPE), Some(b"full".to_vec()));
+		assert!(open_database::<Block>(&source, DatabaseType::Light, true).is_err());
+
+		let write = std::panic::catch_unwind(std::panic::AssertUnwindSafe(
+			|| read_only.set(COLUMN_META, b"key", b"value"),
+		));
+		assert!(write.is_err());
+		assert_eq!(db.get(COLUMN_META, b"key"), None);
+	}
+
+	#[test]
+	fn stored_database_type_is_read() {
+		let dir = tempfile::tempdir().unwrap();
+		let source = DatabaseSettingsSrc::RocksDb { path: dir.path().join("db"), cache_size: 16 };
+		assert_eq!(stored_database_type(&source), None);
+
+		drop(open_database::<Block>(&source, DatabaseType::Light, false).unwrap());
+		assert_eq!(stored_database_type(&source), Some(DatabaseType::Light));
+	}
+
+	#[test]
+	fn read_only_database_opens_locked_rocksdb() {
+		let dir = tempfile::tempdir().unwrap();
+		let source = DatabaseSettingsSrc::RocksDb { path: dir.path().join("db"), cache_size: 16 };
+		let db = open_database::<Block>(&source, DatabaseType::Full, false).unwrap();
+		assert!(open_database::<Block>(&source, DatabaseType::Full, false).is_err());
+
+		let read_only = open_database::<Block>(&source, DatabaseType::Full, true).unwrap();
+		assert_eq!(read_only.get(COLUMN_META, meta_keys::TYPE), Some(b"full".to_vec()));
+		assert_eq!(stored_database_type(&source), Some(DatabaseType::Full));
+		drop(db);
+	}
 }
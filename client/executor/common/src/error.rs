@@ -81,6 +81,9 @@ pub enum Error {
 	/// Execution of a host function failed.
 	#[display(fmt="Host function {} execution failed with: {}", _0, _1)]
 	FunctionExecution(String, String),
+	/// The call was interrupted because it exceeded its deadline.
+	#[display(fmt="Runtime call interrupted after exceeding its deadline")]
+	Interrupted,
 }
 
 impl std::error::Error for Error {
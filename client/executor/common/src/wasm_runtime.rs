@@ -18,6 +18,7 @@
 
 use crate::error::Error;
 use sp_wasm_interface::Value;
+use std::sync::{Arc, atomic::{AtomicU64, Ordering}};
 
 /// A trait that defines an abstract WASM runtime module.
 ///
@@ -38,4 +39,88 @@ pub trait WasmInstance: Send {
 	/// Get the value from a global with the given `name`.
 	/// This method is only suitable for getting immutable globals.
 	fn get_global_const(&self, name: &str) -> Result<Option<Value>, Error>;
+
+	/// Get the size in bytes of the linear memory of this instance.
+	fn memory_size(&self) -> u64;
+
+	/// Get a handle interrupting the calls to this instance from another thread, if the
+	/// execution engine supports it.
+	fn interrupt_handle(&self) -> Option<InterruptHandle> {
+		None
+	}
+}
+
+/// A handle interrupting a call on a `WasmInstance`.
+///
+/// The interrupted call traps the next time it calls a host function. A call can only be
+/// interrupted between `arm` and `disarm`, and each `arm` starts a new generation, so an interrupt
+/// arriving late never reaches a later call.
+#[derive(Clone, Default, Debug)]
+pub struct InterruptHandle(Arc<AtomicU64>);
+
+impl InterruptHandle {
+	/// Make the next call interruptible and return its generation.
+	pub fn arm(&self) -> u64 {
+		self.next_generation()
+	}
+
+	/// Stop interrupting the call that was armed last.
+	pub fn disarm(&self) {
+		self.next_generation();
+	}
+
+	/// Interrupt the call of the given `generation`, if it is still in progress.
+	pub fn interrupt(&self, generation: u64) {
+		let _ = self.0.compare_exchange(
+			generation << 1,
+			generation << 1 | 1,
+			Ordering::SeqCst,
+			Ordering::SeqCst,
+		);
+	}
+
+	/// Returns whether the call in progress was interrupted.
+	pub fn is_interrupted(&self) -> bool {
+		self.0.load(Ordering::SeqCst) & 1 == 1
+	}
+
+	fn next_generation(&self) -> u64 {
+		// the low bit tells whether the generation was interrupted.
+		let mut current = self.0.load(Ordering::SeqCst);
+		loop {
+			let generation = (current >> 1) + 1;
+			let next = generation << 1;
+			match self.0.compare_exchange(current, next, Ordering::SeqCst, Ordering::SeqCst) {
+				Ok(_) => return generation,
+				Err(actual) => current = actual,
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn interrupts_only_reach_their_generation() {
+		let handle = InterruptHandle::default();
+		assert!(!handle.is_interrupted());
+
+		let first = handle.arm();
+		handle.interrupt(first);
+		assert!(handle.is_interrupted());
+		handle.disarm();
+		assert!(!handle.is_interrupted());
+
+		let second = handle.arm();
+		handle.interrupt(first);
+		assert!(!handle.is_interrupted());
+		handle.interrupt(second);
+		assert!(handle.is_interrupted());
+
+		handle.disarm();
+		handle.interrupt(second);
+		assert!(!handle.is_interrupted());
+	}
 }
@@ -56,6 +56,20 @@ sp_core::wasm_export_functions! {
 		b"all ok!".to_vec()
 	}
 
+	fn test_loop_forever() {
+		loop {
+			storage::get(b"foo");
+		}
+	}
+
+	fn test_spin_forever() {
+		let counter = 0u64;
+		loop {
+			// volatile, so that the loop without any host call is kept.
+			unsafe { core::ptr::read_volatile(&counter) };
+		}
+	}
+
 	fn test_clear_prefix(input: Vec<u8>) -> Vec<u8> {
 		storage::clear_prefix(&input);
 		b"all ok!".to_vec()
@@ -22,7 +22,7 @@ use hex_literal::hex;
 use sp_core::{
 	blake2_128, blake2_256, ed25519, sr25519, map, Pair,
 	offchain::{OffchainExt, testing},
-	traits::{Externalities, CallInWasm},
+	traits::{Externalities, CallInWasm, RuntimeCallDeadlineExt},
 };
 use sc_runtime_test::WASM_BINARY;
 use sp_state_machine::TestExternalities as CoreTestExternalities;
@@ -658,3 +658,87 @@ fn parallel_execution(wasm_method: WasmExecutionMethod) {
 		t.join().unwrap();
 	}
 }
+
+#[test_case(WasmExecutionMethod::Interpreted)]
+#[cfg_attr(feature = "wasmtime", test_case(WasmExecutionMethod::Compiled))]
+fn call_exceeding_deadline_should_be_interrupted(wasm_method: WasmExecutionMethod) {
+	let mut ext = TestExternalities::default();
+	ext.register_extension(RuntimeCallDeadlineExt::after(std::time::Duration::from_millis(100)));
+
+	let output = call_in_wasm(
+		"test_loop_forever",
+		&[],
+		wasm_method,
+		&mut ext.ext(),
+	);
+
+	assert_eq!(output.unwrap_err(), crate::error::Error::Interrupted.to_string());
+}
+
+#[test_case(WasmExecutionMethod::Interpreted)]
+#[cfg_attr(feature = "wasmtime", test_case(WasmExecutionMethod::Compiled))]
+fn call_spinning_without_host_calls_should_be_interrupted(wasm_method: WasmExecutionMethod) {
+	let executor = crate::WasmExecutor::new(
+		wasm_method,
+		Some(1024),
+		HostFunctions::host_functions(),
+		8,
+	);
+	let code_hash = blake2_256(WASM_BINARY).to_vec();
+	let call = |method: &str, ext: &mut TestExternalities| executor.call_in_wasm(
+		&WASM_BINARY[..],
+		Some(code_hash.clone()),
+		method,
+		&[],
+		&mut ext.ext(),
+		sp_core::traits::MissingHostFunctions::Allow,
+	);
+
+	let mut ext = TestExternalities::default();
+	ext.register_extension(RuntimeCallDeadlineExt::after(std::time::Duration::from_millis(100)));
+	assert_eq!(
+		call("test_spin_forever", &mut ext).unwrap_err(),
+		crate::error::Error::Interrupted.to_string(),
+	);
+
+	// the instrumented runtime keeps serving the calls with a deadline.
+	let mut ext = TestExternalities::default();
+	ext.register_extension(RuntimeCallDeadlineExt::after(std::time::Duration::from_secs(60)));
+	assert_eq!(call("test_empty_return", &mut ext).unwrap(), vec![0u8; 0]);
+}
+
+#[test_case(WasmExecutionMethod::Interpreted)]
+#[cfg_attr(feature = "wasmtime", test_case(WasmExecutionMethod::Compiled))]
+fn runtime_instances_should_be_reported(wasm_method: WasmExecutionMethod) {
+	let registry = prometheus_endpoint::Registry::new();
+	let executor = crate::WasmExecutor::new(
+		wasm_method,
+		Some(1024),
+		HostFunctions::host_functions(),
+		8,
+	).with_prometheus_registry(&registry).unwrap();
+	let code_hash = blake2_256(WASM_BINARY).to_vec();
+
+	for _ in 0..2 {
+		let mut ext = TestExternalities::default();
+		executor.call_in_wasm(
+			&WASM_BINARY[..],
+			Some(code_hash.clone()),
+			"test_empty_return",
+			&[],
+			&mut ext.ext(),
+			sp_core::traits::MissingHostFunctions::Allow,
+		).unwrap();
+	}
+
+	let metrics = registry.gather();
+	let metric = |name: &str| metrics.iter()
+		.find(|family| family.get_name() == name)
+		.map(|family| family.get_metric()[0].clone())
+		.unwrap();
+	assert_eq!(metric("wasm_runtime_instances_created").get_counter().get_value(), 1.0);
+	assert_eq!(metric("wasm_runtime_instances_reused").get_counter().get_value(), 1.0);
+	assert_eq!(metric("wasm_runtime_instances_overflow").get_counter().get_value(), 0.0);
+	assert!(metric("wasm_runtime_instances_memory_bytes").get_gauge().get_value() > 0.0);
+	assert_eq!(metric("wasm_runtime_compilation_seconds").get_histogram().get_sample_count(), 1);
+}
@@ -0,0 +1,91 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2020 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Prometheus metrics of the cached runtimes and their instances.
+
+use prometheus_endpoint::{
+	register, exponential_buckets, Counter, Gauge, Histogram, HistogramOpts, PrometheusError,
+	Registry, U64,
+};
+
+/// Prometheus metrics of the cached runtimes and their instances.
+pub(crate) struct Metrics {
+	/// Number of instances created for the pool of a runtime.
+	pub instances_created: Counter<U64>,
+	/// Number of calls served by an instance taken from the pool.
+	pub instances_reused: Counter<U64>,
+	/// Number of instances dropped from the pool, after a failed call or with their runtime.
+	pub instances_evicted: Counter<U64>,
+	/// Number of instances created outside of the pool because all the pooled ones were busy.
+	pub instances_overflow: Counter<U64>,
+	/// Size of the linear memory of the pooled instances.
+	pub instances_memory: Gauge<U64>,
+	/// Time taken to compile a runtime and read its version.
+	pub compilation_time: Histogram,
+}
+
+impl Metrics {
+	pub fn register(registry: &Registry) -> Result<Self, PrometheusError> {
+		Ok(Self {
+			instances_created: register(
+				Counter::new(
+					"wasm_runtime_instances_created",
+					"Total number of runtime instances created for the instance pools",
+				)?,
+				registry,
+			)?,
+			instances_reused: register(
+				Counter::new(
+					"wasm_runtime_instances_reused",
+					"Total number of runtime calls served by a pooled instance",
+				)?,
+				registry,
+			)?,
+			instances_evicted: register(
+				Counter::new(
+					"wasm_runtime_instances_evicted",
+					"Total number of runtime instances dropped from the instance pools",
+				)?,
+				registry,
+			)?,
+			instances_overflow: register(
+				Counter::new(
+					"wasm_runtime_instances_overflow",
+					"Total number of runtime instances created while all the pooled ones were busy",
+				)?,
+				registry,
+			)?,
+			instances_memory: register(
+				Gauge::new(
+					"wasm_runtime_instances_memory_bytes",
+					"Size of the linear memory held by the pooled runtime instances",
+				)?,
+				registry,
+			)?,
+			compilation_time: register(
+				Histogram::with_opts(
+					HistogramOpts::new(
+						"wasm_runtime_compilation_seconds",
+						"Time taken to prepare a new runtime, including its compilation",
+					).buckets(exponential_buckets(0.01, 2.0, 12)?),
+				)?,
+				registry,
+			)?,
+		})
+	}
+}
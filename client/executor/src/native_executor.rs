@@ -23,16 +23,140 @@ use crate::{
 use sp_version::{NativeVersion, RuntimeVersion};
 use codec::{Decode, Encode};
 use sp_core::{
-	NativeOrEncoded, traits::{CodeExecutor, Externalities, RuntimeCode, MissingHostFunctions},
+	NativeOrEncoded,
+	traits::{
+		CodeExecutor, Externalities, ExternalitiesExt, RuntimeCode, MissingHostFunctions,
+		RuntimeCallDeadlineExt,
+	},
+};
+use log::{trace, warn};
+use parking_lot::Mutex;
+use std::{
+	cmp::Ordering, collections::BinaryHeap, result, panic::{UnwindSafe, AssertUnwindSafe},
+	sync::{Arc, mpsc}, thread, time::Instant,
 };
-use log::trace;
-use std::{result, panic::{UnwindSafe, AssertUnwindSafe}, sync::Arc};
 use sp_wasm_interface::{HostFunctions, Function};
-use sc_executor_common::wasm_runtime::WasmInstance;
+use sc_executor_common::wasm_runtime::{WasmInstance, InterruptHandle};
+use prometheus_endpoint::{PrometheusError, Registry};
 
 /// Default num of pages for the heap
 const DEFAULT_HEAP_PAGES: u64 = 1024;
 
+/// A wasm call to interrupt once `at` is exceeded.
+struct Deadline {
+	at: Instant,
+	handle: InterruptHandle,
+	generation: u64,
+}
+
+impl PartialEq for Deadline {
+	fn eq(&self, other: &Self) -> bool {
+		self.at == other.at
+	}
+}
+
+impl Eq for Deadline {}
+
+impl PartialOrd for Deadline {
+	fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+		Some(self.cmp(other))
+	}
+}
+
+impl Ord for Deadline {
+	// the earliest deadline comes first out of the `BinaryHeap`.
+	fn cmp(&self, other: &Self) -> Ordering {
+		other.at.cmp(&self.at)
+	}
+}
+
+lazy_static::lazy_static! {
+	/// Sends the deadlines to the thread interrupting the calls that exceed them, if it started.
+	static ref DEADLINES: Mutex<Option<mpsc::Sender<Deadline>>> =
+		Mutex::new(start_deadline_timer());
+}
+
+/// Start the thread interrupting the calls that exceed their deadline.
+///
+/// The calls are not cancelled when they finish in time: their generation has moved on, so they
+/// are not interrupted when their deadline comes.
+fn start_deadline_timer() -> Option<mpsc::Sender<Deadline>> {
+	let (sender, deadlines) = mpsc::channel::<Deadline>();
+	let spawned = thread::Builder::new()
+		.name("runtime-call-deadlines".into())
+		.spawn(move || {
+			let mut pending = BinaryHeap::new();
+			loop {
+				let received = match pending.peek() {
+					Some(Deadline { at, .. }) =>
+						deadlines.recv_timeout(at.saturating_duration_since(Instant::now())),
+					None => deadlines.recv().map_err(|_| mpsc::RecvTimeoutError::Disconnected),
+				};
+				match received {
+					Ok(deadline) => pending.push(deadline),
+					Err(mpsc::RecvTimeoutError::Timeout) => {},
+					Err(mpsc::RecvTimeoutError::Disconnected) => return,
+				}
+
+				let now = Instant::now();
+				while pending.peek().map_or(false, |deadline: &Deadline| deadline.at <= now) {
+					if let Some(deadline) = pending.pop() {
+						deadline.handle.interrupt(deadline.generation);
+					}
+				}
+			}
+		});
+
+	match spawned {
+		Ok(_) => Some(sender),
+		Err(e) => {
+			warn!(target: "executor", "Failed to start the runtime call deadline timer: {}", e);
+			None
+		},
+	}
+}
+
+/// Returns whether a `RuntimeCallDeadlineExt` is registered in `ext`.
+///
+/// The calls with a deadline run in wasm, instrumented with interrupt checks.
+fn has_deadline(mut ext: &mut dyn Externalities) -> bool {
+	ext.extension::<RuntimeCallDeadlineExt>().is_some()
+}
+
+/// Call `method` on the wasm `instance`.
+///
+/// If a `RuntimeCallDeadlineExt` is registered in `ext`, the call is interrupted once the
+/// deadline is exceeded.
+fn call_in_instance(
+	instance: AssertUnwindSafe<&dyn WasmInstance>,
+	mut ext: &mut dyn Externalities,
+	method: &str,
+	data: &[u8],
+) -> Result<Result<Vec<u8>>> {
+	let deadline = ext.extension::<RuntimeCallDeadlineExt>().map(|deadline| deadline.0);
+	if deadline.map_or(false, |deadline| deadline <= Instant::now()) {
+		return Ok(Err(Error::Interrupted));
+	}
+	let handle = deadline.and_then(|_| instance.interrupt_handle());
+	if let (Some(at), Some(handle)) = (deadline, &handle) {
+		let deadline = Deadline { at, handle: handle.clone(), generation: handle.arm() };
+		if let Some(deadlines) = &*DEADLINES.lock() {
+			let _ = deadlines.send(deadline);
+		}
+	}
+
+	let result = with_externalities_safe(ext, move || instance.call(method, data));
+	let interrupted = handle.map_or(false, |handle| {
+		let interrupted = handle.is_interrupted();
+		handle.disarm();
+		interrupted
+	});
+	match result? {
+		Err(_) if interrupted => Ok(Err(Error::Interrupted)),
+		result => Ok(result),
+	}
+}
+
 /// Set up the externalities and safe calling environment to execute runtime calls.
 ///
 /// If the inner closure panics, it will be caught and return an error.
@@ -115,6 +239,19 @@ impl WasmExecutor {
 		}
 	}
 
+	/// Report the metrics of the cached runtimes and their instances to `registry`.
+	///
+	/// This replaces the runtime cache, so it is meant to be called right after `new`.
+	pub fn with_prometheus_registry(
+		mut self,
+		registry: &Registry,
+	) -> Result<Self, PrometheusError> {
+		self.cache = Arc::new(
+			RuntimeCache::with_prometheus_registry(self.max_runtime_instances, registry)?
+		);
+		Ok(self)
+	}
+
 	/// Execute the given closure `f` with the latest runtime (based on `runtime_code`).
 	///
 	/// The closure `f` is expected to return `Err(_)` when there happened a `panic!` in native code
@@ -133,6 +270,7 @@ impl WasmExecutor {
 		runtime_code: &RuntimeCode,
 		ext: &mut dyn Externalities,
 		allow_missing_host_functions: bool,
+		interruptible: bool,
 		f: F,
 	) -> Result<R>
 		where F: FnOnce(
@@ -148,6 +286,7 @@ impl WasmExecutor {
 			self.default_heap_pages,
 			&*self.host_functions,
 			allow_missing_host_functions,
+			interruptible,
 			|instance, version, ext| {
 				let instance = AssertUnwindSafe(instance);
 				let ext = AssertUnwindSafe(ext);
@@ -171,6 +310,7 @@ impl sp_core::traits::CallInWasm for WasmExecutor {
 		missing_host_functions: MissingHostFunctions,
 	) -> std::result::Result<Vec<u8>, String> {
 		let allow_missing_host_functions = missing_host_functions.allowed();
+		let interruptible = has_deadline(ext);
 
 		if let Some(hash) = code_hash {
 			let code = RuntimeCode {
@@ -179,18 +319,30 @@ impl sp_core::traits::CallInWasm for WasmExecutor {
 				heap_pages: None,
 			};
 
-			self.with_instance(&code, ext, allow_missing_host_functions, |instance, _, mut ext| {
-				with_externalities_safe(
-					&mut **ext,
-					move || instance.call(method, call_data),
-				)
-			}).map_err(|e| e.to_string())
+			self.with_instance(
+				&code,
+				ext,
+				allow_missing_host_functions,
+				interruptible,
+				|instance, _, mut ext| call_in_instance(instance, &mut **ext, method, call_data),
+			).map_err(|e| e.to_string())
 		} else {
+			let mut host_functions = self.host_functions.to_vec();
+			let instrumented;
+			let wasm_code = if interruptible {
+				instrumented = crate::wasm_runtime::instrument_interrupt_checks(
+					wasm_code,
+					&mut host_functions,
+				).map_err(|e| format!("Failed to instrument module: {:?}", e))?;
+				&instrumented[..]
+			} else {
+				wasm_code
+			};
 			let module = crate::wasm_runtime::create_wasm_runtime_with_code(
 				self.method,
 				self.default_heap_pages,
-				&wasm_code,
-				self.host_functions.to_vec(),
+				wasm_code,
+				host_functions,
 				allow_missing_host_functions,
 			)
 				.map_err(|e| format!("Failed to create module: {:?}", e))?;
@@ -198,15 +350,9 @@ impl sp_core::traits::CallInWasm for WasmExecutor {
 			let instance = module.new_instance()
 				.map_err(|e| format!("Failed to create instance: {:?}", e))?;
 
-			let instance = AssertUnwindSafe(instance);
-			let mut ext = AssertUnwindSafe(ext);
-
-			with_externalities_safe(
-				&mut **ext,
-				move || instance.call(method, call_data),
-			)
-			.and_then(|r| r)
-			.map_err(|e| e.to_string())
+			call_in_instance(AssertUnwindSafe(&*instance), ext, method, call_data)
+				.and_then(|r| r)
+				.map_err(|e| e.to_string())
 		}
 	}
 }
@@ -253,6 +399,15 @@ impl<D: NativeExecutionDispatch> NativeExecutor<D> {
 			wasm: wasm_executor,
 		}
 	}
+
+	/// Report the metrics of the cached runtimes and their instances to `registry`.
+	pub fn with_prometheus_registry(
+		mut self,
+		registry: &Registry,
+	) -> Result<Self, PrometheusError> {
+		self.wasm = self.wasm.with_prometheus_registry(registry)?;
+		Ok(self)
+	}
 }
 
 impl<D: NativeExecutionDispatch> RuntimeInfo for NativeExecutor<D> {
@@ -269,6 +424,7 @@ impl<D: NativeExecutionDispatch> RuntimeInfo for NativeExecutor<D> {
 			runtime_code,
 			ext,
 			false,
+			false,
 			|_instance, version, _ext|
 				Ok(version.cloned().ok_or_else(|| Error::ApiError("Unknown version".into()))),
 		)
@@ -291,10 +447,14 @@ impl<D: NativeExecutionDispatch + 'static> CodeExecutor for NativeExecutor<D> {
 		native_call: Option<NC>,
 	) -> (Result<NativeOrEncoded<R>>, bool) {
 		let mut used_native = false;
+		// only the wasm execution can be interrupted when the call exceeds its deadline.
+		let interruptible = has_deadline(ext);
+		let use_native = use_native && !interruptible;
 		let result = self.wasm.with_instance(
 			runtime_code,
 			ext,
 			false,
+			interruptible,
 			|instance, onchain_version, mut ext| {
 				let onchain_version = onchain_version.ok_or_else(
 					|| Error::ApiError("Unknown version".into())
@@ -312,16 +472,12 @@ impl<D: NativeExecutionDispatch + 'static> CodeExecutor for NativeExecutor<D> {
 							onchain_version,
 						);
 
-						with_externalities_safe(
-							&mut **ext,
-							move || instance.call(method, data).map(NativeOrEncoded::Encoded)
-						)
+						call_in_instance(instance, &mut **ext, method, data)
+							.map(|r| r.map(NativeOrEncoded::Encoded))
 					}
 					(false, _, _) => {
-						with_externalities_safe(
-							&mut **ext,
-							move || instance.call(method, data).map(NativeOrEncoded::Encoded)
-						)
+						call_in_instance(instance, &mut **ext, method, data)
+							.map(|r| r.map(NativeOrEncoded::Encoded))
 					},
 					(true, true, Some(call)) => {
 						trace!(
@@ -21,14 +21,16 @@
 
 use std::sync::Arc;
 use crate::error::{Error, WasmError};
+use crate::metrics::Metrics;
 use parking_lot::Mutex;
 use codec::Decode;
 use sp_core::traits::{Externalities, RuntimeCode, FetchRuntimeCode};
 use sp_version::RuntimeVersion;
 use std::panic::AssertUnwindSafe;
 use sc_executor_common::wasm_runtime::{WasmModule, WasmInstance};
+use prometheus_endpoint::{PrometheusError, Registry};
 
-use sp_wasm_interface::Function;
+use sp_wasm_interface::{Function, FunctionContext, Signature, Value, ValueType};
 
 /// Specification of different methods of executing the runtime Wasm code.
 #[derive(Debug, PartialEq, Eq, Hash, Copy, Clone)]
@@ -60,6 +62,10 @@ struct VersionedRuntime {
 	version: Option<RuntimeVersion>,
 	/// Cached instance pool.
 	instances: Vec<Mutex<Option<Box<dyn WasmInstance>>>>,
+	/// Metrics of the instances, if enabled.
+	metrics: Option<Arc<Metrics>>,
+	/// Whether the code was instrumented with `instrument_interrupt_checks`.
+	interruptible: bool,
 }
 
 impl VersionedRuntime {
@@ -87,6 +93,15 @@ impl VersionedRuntime {
 					.map(|r| Ok((r, false)))
 					.unwrap_or_else(|| self.module.new_instance().map(|i| (i, true)))?;
 
+				if let Some(metrics) = &self.metrics {
+					if new_inst {
+						metrics.instances_created.inc();
+					} else {
+						metrics.instances_reused.inc();
+						metrics.instances_memory.sub(instance.memory_size());
+					}
+				}
+
 				let result = f(&*instance, self.version.as_ref(), ext);
 				if let Err(e) = &result {
 					if new_inst {
@@ -102,7 +117,13 @@ impl VersionedRuntime {
 							e,
 						);
 					}
+					if let Some(metrics) = &self.metrics {
+						metrics.instances_evicted.inc();
+					}
 				} else {
+					if let Some(metrics) = &self.metrics {
+						metrics.instances_memory.add(instance.memory_size());
+					}
 					*locked = Some(instance);
 
 					if new_inst {
@@ -119,6 +140,9 @@ impl VersionedRuntime {
 			},
 			None => {
 				log::warn!(target: "wasm-runtime", "Ran out of free WASM instances");
+				if let Some(metrics) = &self.metrics {
+					metrics.instances_overflow.inc();
+				}
 
 				// Allocate a new instance
 				let instance = self.module.new_instance()?;
@@ -129,7 +153,19 @@ impl VersionedRuntime {
 	}
 }
 
-const MAX_RUNTIMES: usize = 2;
+impl Drop for VersionedRuntime {
+	fn drop(&mut self) {
+		if let Some(metrics) = &self.metrics {
+			for instance in self.instances.iter_mut().filter_map(|i| i.get_mut().as_ref()) {
+				metrics.instances_evicted.inc();
+				metrics.instances_memory.sub(instance.memory_size());
+			}
+		}
+	}
+}
+
+// Each runtime may be cached twice, as it is instrumented for the calls that can be interrupted.
+const MAX_RUNTIMES: usize = 4;
 
 /// Cache for the runtimes.
 ///
@@ -149,6 +185,8 @@ pub struct RuntimeCache {
 	runtimes: Mutex<[Option<Arc<VersionedRuntime>>; MAX_RUNTIMES]>,
 	/// The size of the instances cache for each runtime.
 	max_runtime_instances: usize,
+	/// Metrics of the runtimes, if enabled.
+	metrics: Option<Arc<Metrics>>,
 }
 
 impl RuntimeCache {
@@ -157,9 +195,22 @@ impl RuntimeCache {
 		RuntimeCache {
 			runtimes: Default::default(),
 			max_runtime_instances,
+			metrics: None,
 		}
 	}
 
+	/// Creates a new instance of a runtimes cache reporting to `registry`.
+	pub fn with_prometheus_registry(
+		max_runtime_instances: usize,
+		registry: &Registry,
+	) -> Result<RuntimeCache, PrometheusError> {
+		Ok(RuntimeCache {
+			runtimes: Default::default(),
+			max_runtime_instances,
+			metrics: Some(Arc::new(Metrics::register(registry)?)),
+		})
+	}
+
 	/// Prepares a WASM module instance and executes given function for it.
 	///
 	/// This uses internal cache to find avaiable instance or create a new one.
@@ -177,6 +228,8 @@ impl RuntimeCache {
 	///
 	/// `allow_missing_func_imports` - Ignore missing function imports.
 	///
+	/// `interruptible` - Use the runtime instrumented with `instrument_interrupt_checks`.
+	///
 	/// `max_runtime_instances` - The size of the instances cache.
 	///
 	/// `f` - Function to execute.
@@ -196,6 +249,7 @@ impl RuntimeCache {
 		default_heap_pages: u64,
 		host_functions: &[&'static dyn Function],
 		allow_missing_func_imports: bool,
+		interruptible: bool,
 		f: F,
 	) -> Result<Result<R, Error>, Error>
 		where F: FnOnce(
@@ -212,7 +266,8 @@ impl RuntimeCache {
 			false,
 			|r| r.wasm_method == wasm_method &&
 				r.code_hash == *code_hash &&
-				r.heap_pages == heap_pages
+				r.heap_pages == heap_pages &&
+				r.interruptible == interruptible
 		));
 
 		let runtime = match pos {
@@ -230,7 +285,9 @@ impl RuntimeCache {
 					heap_pages,
 					host_functions.into(),
 					allow_missing_func_imports,
+					interruptible,
 					self.max_runtime_instances,
+					self.metrics.clone(),
 				);
 				if let Err(ref err) = result {
 					log::warn!(target: "wasm-runtime", "Cannot create a runtime: {:?}", err);
@@ -260,6 +317,48 @@ impl RuntimeCache {
 	}
 }
 
+/// The host function called by the code instrumented with `instrument_interrupt_checks`.
+///
+/// It does nothing: calling the host is enough for an interrupted call to trap.
+struct InterruptCheck;
+
+impl Function for InterruptCheck {
+	fn name(&self) -> &str {
+		"gas"
+	}
+
+	fn signature(&self) -> Signature {
+		Signature::new_with_args(&[ValueType::I32][..])
+	}
+
+	fn execute(
+		&self,
+		_: &mut dyn FunctionContext,
+		_: &mut dyn Iterator<Item = Value>,
+	) -> sp_wasm_interface::Result<Option<Value>> {
+		Ok(None)
+	}
+}
+
+static INTERRUPT_CHECK: InterruptCheck = InterruptCheck;
+
+/// Instrument `code` to call the host at the start of every block, so that a call stuck in a
+/// loop can be interrupted even if the loop never calls a host function.
+///
+/// The host function called by the instrumented code is added to `host_functions`.
+pub fn instrument_interrupt_checks(
+	code: &[u8],
+	host_functions: &mut Vec<&'static dyn Function>,
+) -> Result<Vec<u8>, WasmError> {
+	let module = parity_wasm::deserialize_buffer(code)
+		.map_err(|_| WasmError::CantDeserializeWasm)?;
+	let module = pwasm_utils::inject_gas_counter(module, &Default::default())
+		.map_err(|_| WasmError::Other("failed to instrument the interrupt checks".into()))?;
+	host_functions.push(&INTERRUPT_CHECK);
+
+	parity_wasm::serialize(module).map_err(|e| WasmError::Other(e.to_string()))
+}
+
 /// Create a wasm runtime with the given `code`.
 pub fn create_wasm_runtime_with_code(
 	wasm_method: WasmExecutionMethod,
@@ -312,16 +411,25 @@ fn create_versioned_wasm_runtime(
 	ext: &mut dyn Externalities,
 	wasm_method: WasmExecutionMethod,
 	heap_pages: u64,
-	host_functions: Vec<&'static dyn Function>,
+	mut host_functions: Vec<&'static dyn Function>,
 	allow_missing_func_imports: bool,
+	interruptible: bool,
 	max_instances: usize,
+	metrics: Option<Arc<Metrics>>,
 ) -> Result<VersionedRuntime, WasmError> {
 	#[cfg(not(target_os = "unknown"))]
 	let time = std::time::Instant::now();
+	let instrumented;
+	let code = if interruptible {
+		instrumented = instrument_interrupt_checks(code, &mut host_functions)?;
+		&instrumented[..]
+	} else {
+		code
+	};
 	let mut runtime = create_wasm_runtime_with_code(
 		wasm_method,
 		heap_pages,
-		&code,
+		code,
 		host_functions,
 		allow_missing_func_imports,
 	)?;
@@ -344,12 +452,17 @@ fn create_versioned_wasm_runtime(
 		Err(_) => None,
 	};
 	#[cfg(not(target_os = "unknown"))]
-	log::debug!(
-		target: "wasm-runtime",
-		"Prepared new runtime version {:?} in {} ms.",
-		version,
-		time.elapsed().as_millis(),
-	);
+	{
+		log::debug!(
+			target: "wasm-runtime",
+			"Prepared new runtime version {:?} in {} ms.",
+			version,
+			time.elapsed().as_millis(),
+		);
+		if let Some(metrics) = &metrics {
+			metrics.compilation_time.observe(time.elapsed().as_secs_f64());
+		}
+	}
 
 	let mut instances = Vec::with_capacity(max_instances);
 	instances.resize_with(max_instances, || Mutex::new(None));
@@ -361,6 +474,8 @@ fn create_versioned_wasm_runtime(
 		heap_pages,
 		wasm_method,
 		instances,
+		metrics,
+		interruptible,
 	})
 }
 
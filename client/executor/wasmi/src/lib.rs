@@ -19,7 +19,7 @@
 use std::{str, cell::RefCell, sync::Arc};
 use wasmi::{
 	Module, ModuleInstance, MemoryInstance, MemoryRef, TableRef, ImportsBuilder, ModuleRef,
-	memory_units::Pages,
+	memory_units::{Bytes, Pages},
 	RuntimeValue::{I32, I64, self},
 };
 use codec::{Encode, Decode};
@@ -29,7 +29,7 @@ use sp_wasm_interface::{
 	FunctionContext, Pointer, WordSize, Sandbox, MemoryId, Result as WResult, Function,
 };
 use sp_runtime_interface::unpack_ptr_and_len;
-use sc_executor_common::wasm_runtime::{WasmModule, WasmInstance};
+use sc_executor_common::wasm_runtime::{WasmModule, WasmInstance, InterruptHandle};
 use sc_executor_common::{
 	error::{Error, WasmError},
 	sandbox,
@@ -44,6 +44,7 @@ struct FunctionExecutor<'a> {
 	host_functions: &'a [&'static dyn Function],
 	allow_missing_func_imports: bool,
 	missing_functions: &'a [String],
+	interrupt_handle: &'a InterruptHandle,
 }
 
 impl<'a> FunctionExecutor<'a> {
@@ -54,6 +55,7 @@ impl<'a> FunctionExecutor<'a> {
 		host_functions: &'a [&'static dyn Function],
 		allow_missing_func_imports: bool,
 		missing_functions: &'a [String],
+		interrupt_handle: &'a InterruptHandle,
 	) -> Result<Self, Error> {
 		Ok(FunctionExecutor {
 			sandbox_store: sandbox::Store::new(),
@@ -63,6 +65,7 @@ impl<'a> FunctionExecutor<'a> {
 			host_functions,
 			allow_missing_func_imports,
 			missing_functions,
+			interrupt_handle,
 		})
 	}
 }
@@ -385,6 +388,10 @@ impl<'a> wasmi::Externals for FunctionExecutor<'a> {
 	fn invoke_index(&mut self, index: usize, args: wasmi::RuntimeArgs)
 		-> Result<Option<wasmi::RuntimeValue>, wasmi::Trap>
 	{
+		if self.interrupt_handle.is_interrupted() {
+			return Err(Error::Interrupted.into());
+		}
+
 		let mut args = args.as_ref().iter().copied().map(Into::into);
 
 		if let Some(function) = self.host_functions.get(index) {
@@ -440,6 +447,7 @@ fn call_in_wasm_module(
 	host_functions: &[&'static dyn Function],
 	allow_missing_func_imports: bool,
 	missing_functions: &Vec<String>,
+	interrupt_handle: &InterruptHandle,
 ) -> Result<Vec<u8>, Error> {
 	// Initialize FunctionExecutor.
 	let table: Option<TableRef> = module_instance
@@ -454,6 +462,7 @@ fn call_in_wasm_module(
 		host_functions,
 		allow_missing_func_imports,
 		missing_functions,
+		interrupt_handle,
 	)?;
 
 	// Write the call data
@@ -608,6 +617,7 @@ impl WasmModule for WasmiRuntime {
 			host_functions: self.host_functions.clone(),
 			allow_missing_func_imports: self.allow_missing_func_imports,
 			missing_functions,
+			interrupt_handle: Default::default(),
 		}))
 	}
 }
@@ -672,6 +682,8 @@ pub struct WasmiInstance {
 	allow_missing_func_imports: bool,
 	/// List of missing functions detected during function resolution
 	missing_functions: Vec<String>,
+	/// Handle interrupting the calls to this instance.
+	interrupt_handle: InterruptHandle,
 }
 
 // This is safe because `WasmiInstance` does not leak any references to `self.memory` and `self.instance`
@@ -706,6 +718,7 @@ impl WasmInstance for WasmiInstance {
 			self.host_functions.as_ref(),
 			self.allow_missing_func_imports,
 			self.missing_functions.as_ref(),
+			&self.interrupt_handle,
 		)
 	}
 
@@ -721,4 +734,12 @@ impl WasmInstance for WasmiInstance {
 			None => Ok(None),
 		}
 	}
+
+	fn memory_size(&self) -> u64 {
+		Bytes::from(self.memory.current_size()).0 as u64
+	}
+
+	fn interrupt_handle(&self) -> Option<InterruptHandle> {
+		Some(self.interrupt_handle.clone())
+	}
 }
@@ -24,6 +24,7 @@ use log::trace;
 use codec::{Encode, Decode};
 use sp_allocator::FreeingBumpHeapAllocator;
 use sc_executor_common::error::Result;
+use sc_executor_common::wasm_runtime::InterruptHandle;
 use sc_executor_common::sandbox::{self, SandboxCapabilities, SupervisorFuncIndex};
 use sp_core::sandbox as sandbox_primitives;
 use sp_wasm_interface::{FunctionContext, MemoryId, Pointer, Sandbox, WordSize};
@@ -52,18 +53,29 @@ pub struct HostState {
 	sandbox_store: RefCell<sandbox::Store<SupervisorFuncRef>>,
 	allocator: RefCell<FreeingBumpHeapAllocator>,
 	instance: Rc<InstanceWrapper>,
+	interrupt_handle: InterruptHandle,
 }
 
 impl HostState {
 	/// Constructs a new `HostState`.
-	pub fn new(allocator: FreeingBumpHeapAllocator, instance: Rc<InstanceWrapper>) -> Self {
+	pub fn new(
+		allocator: FreeingBumpHeapAllocator,
+		instance: Rc<InstanceWrapper>,
+		interrupt_handle: InterruptHandle,
+	) -> Self {
 		HostState {
 			sandbox_store: RefCell::new(sandbox::Store::new()),
 			allocator: RefCell::new(allocator),
 			instance,
+			interrupt_handle,
 		}
 	}
 
+	/// Returns whether the call in progress was interrupted.
+	pub fn is_interrupted(&self) -> bool {
+		self.interrupt_handle.is_interrupted()
+	}
+
 	/// Materialize `HostContext` that can be used to invoke a substrate host `dyn Function`.
 	pub fn materialize<'a>(&'a self) -> HostContext<'a> {
 		HostContext(self)
@@ -17,7 +17,7 @@
 // along with this program. If not, see <https://www.gnu.org/licenses/>.
 
 use crate::state_holder;
-use sc_executor_common::error::WasmError;
+use sc_executor_common::error::{Error, WasmError};
 use sp_wasm_interface::{Function, Value, ValueType};
 use std::any::Any;
 use wasmtime::{
@@ -184,6 +184,10 @@ fn call_static(
 		// a `dyn Function` signature of which cannot have a non substrate value by definition.
 		let mut params = wasmtime_params.iter().cloned().map(into_value);
 
+		if host_ctx.is_interrupted() {
+			return Ok(Err(Error::Interrupted.to_string()));
+		}
+
 		std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
 			static_func.execute(&mut host_ctx, &mut params)
 		}))
@@ -25,7 +25,7 @@ use std::rc::Rc;
 use std::sync::Arc;
 use sc_executor_common::{
 	error::{Error, Result, WasmError},
-	wasm_runtime::{WasmModule, WasmInstance},
+	wasm_runtime::{WasmModule, WasmInstance, InterruptHandle},
 };
 use sp_allocator::FreeingBumpHeapAllocator;
 use sp_runtime_interface::unpack_ptr_and_len;
@@ -64,6 +64,7 @@ impl WasmModule for WasmtimeRuntime {
 			globals_snapshot,
 			heap_pages: self.heap_pages,
 			heap_base,
+			interrupt_handle: Default::default(),
 		}))
 	}
 }
@@ -77,6 +78,7 @@ pub struct WasmtimeInstance {
 	imports: Imports,
 	heap_pages: u32,
 	heap_base: u32,
+	interrupt_handle: InterruptHandle,
 }
 
 // This is safe because `WasmtimeInstance` does not leak reference to `self.imports`
@@ -102,6 +104,7 @@ impl WasmInstance for WasmtimeInstance {
 			Rc::clone(&self.instance_wrapper),
 			entrypoint,
 			allocator,
+			self.interrupt_handle.clone(),
 		)
 	}
 
@@ -109,6 +112,14 @@ impl WasmInstance for WasmtimeInstance {
 		let instance = InstanceWrapper::new(&self.module_wrapper, &self.imports, self.heap_pages)?;
 		instance.get_global_val(name)
 	}
+
+	fn memory_size(&self) -> u64 {
+		self.instance_wrapper.memory_size() as u64
+	}
+
+	fn interrupt_handle(&self) -> Option<InterruptHandle> {
+		Some(self.interrupt_handle.clone())
+	}
 }
 
 /// Create a new `WasmtimeRuntime` given the code. This function performs translation from Wasm to
@@ -142,10 +153,11 @@ fn perform_call(
 	instance_wrapper: Rc<InstanceWrapper>,
 	entrypoint: wasmtime::Func,
 	mut allocator: FreeingBumpHeapAllocator,
+	interrupt_handle: InterruptHandle,
 ) -> Result<Vec<u8>> {
 	let (data_ptr, data_len) = inject_input_data(&instance_wrapper, &mut allocator, data)?;
 
-	let host_state = HostState::new(allocator, instance_wrapper.clone());
+	let host_state = HostState::new(allocator, instance_wrapper.clone(), interrupt_handle);
 	let ret = state_holder::with_initialized_state(&host_state, || {
 		match entrypoint.call(&[
 			wasmtime::Val::I32(u32::from(data_ptr) as i32),
@@ -30,6 +30,9 @@ pub enum Error {
 	/// GRANDPA reports voter state with round id or weights larger than 32-bits.
 	#[display(fmt = "GRANDPA reports voter state as unreasonably large")]
 	VoterStateReportsUnreasonablyLargeNumbers,
+	/// Proving finality failed.
+	#[display(fmt = "Failed to prove finality: {}", _0)]
+	ProveFinalityFailed(sp_blockchain::Error),
 }
 
 impl From<Error> for jsonrpc_core::Error {
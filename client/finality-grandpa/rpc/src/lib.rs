@@ -19,14 +19,27 @@
 //! RPC API for GRANDPA.
 #![warn(missing_docs)]
 
-use futures::{FutureExt, TryFutureExt};
+use futures::{FutureExt, TryFutureExt, TryStreamExt, StreamExt};
+use jsonrpc_core::futures::{Future, Sink, Stream};
 use jsonrpc_derive::rpc;
+use jsonrpc_pubsub::{typed::Subscriber, SubscriptionId};
+use log::warn;
+use sc_finality_grandpa::GrandpaJustificationStream;
+use sc_rpc::Subscriptions;
+use sp_runtime::traits::Block as BlockT;
+use std::{marker::PhantomData, sync::Arc};
 
 mod error;
+mod finality;
+mod notification;
 mod report;
 
+use error::Error;
 use report::{ReportAuthoritySet, ReportVoterState, ReportedRoundStates};
 
+pub use finality::{EncodedFinalityProofs, RpcFinalityProofProvider};
+pub use notification::JustificationNotification;
+
 /// Returned when Grandpa RPC endpoint is not ready.
 pub const NOT_READY_ERROR_CODE: i64 = 1;
 
@@ -35,53 +48,159 @@ type FutureResult<T> =
 
 /// Provides RPC methods for interacting with GRANDPA.
 #[rpc]
-pub trait GrandpaApi {
+pub trait GrandpaApi<Notification, Hash> {
+	/// RPC Metadata
+	type Metadata;
+
 	/// Returns the state of the current best round state as well as the
 	/// ongoing background rounds.
 	#[rpc(name = "grandpa_roundState")]
 	fn round_state(&self) -> FutureResult<ReportedRoundStates>;
+
+	/// Prove finality of the best block we have a proof for in the range `(begin; end]`,
+	/// starting with the authority set `authorities_set_id`. Returns `null` if no block after
+	/// `begin` is known to be finalized.
+	#[rpc(name = "grandpa_proveFinality")]
+	fn prove_finality(
+		&self,
+		begin: Hash,
+		end: Hash,
+		authorities_set_id: u64,
+	) -> FutureResult<Option<EncodedFinalityProofs>>;
+
+	/// Returns the justifications of the blocks finalized from now on, encoded. A justification
+	/// is sent for every block finalized by a GRANDPA round or imported with a justification.
+	#[pubsub(
+		subscription = "grandpa_justifications",
+		subscribe,
+		name = "grandpa_subscribeJustifications"
+	)]
+	fn subscribe_justifications(
+		&self,
+		metadata: Self::Metadata,
+		subscriber: Subscriber<Notification>,
+	);
+
+	/// Unsubscribe from the justifications.
+	#[pubsub(
+		subscription = "grandpa_justifications",
+		unsubscribe,
+		name = "grandpa_unsubscribeJustifications"
+	)]
+	fn unsubscribe_justifications(
+		&self,
+		metadata: Option<Self::Metadata>,
+		id: SubscriptionId,
+	) -> jsonrpc_core::Result<bool>;
 }
 
 /// Implements the GrandpaApi RPC trait for interacting with GRANDPA.
-pub struct GrandpaRpcHandler<AuthoritySet, VoterState> {
+pub struct GrandpaRpcHandler<AuthoritySet, VoterState, Block: BlockT, ProofProvider> {
 	authority_set: AuthoritySet,
 	voter_state: VoterState,
+	finality_proof_provider: Arc<ProofProvider>,
+	justification_stream: GrandpaJustificationStream<Block>,
+	subscriptions: Subscriptions,
+	_marker: PhantomData<Block>,
 }
 
-impl<AuthoritySet, VoterState> GrandpaRpcHandler<AuthoritySet, VoterState> {
+impl<AuthoritySet, VoterState, Block: BlockT, ProofProvider>
+	GrandpaRpcHandler<AuthoritySet, VoterState, Block, ProofProvider>
+{
 	/// Creates a new GrandpaRpcHander instance.
-	pub fn new(authority_set: AuthoritySet, voter_state: VoterState) -> Self {
+	pub fn new(
+		authority_set: AuthoritySet,
+		voter_state: VoterState,
+		finality_proof_provider: Arc<ProofProvider>,
+		justification_stream: GrandpaJustificationStream<Block>,
+		subscriptions: Subscriptions,
+	) -> Self {
 		Self {
 			authority_set,
 			voter_state,
+			finality_proof_provider,
+			justification_stream,
+			subscriptions,
+			_marker: PhantomData,
 		}
 	}
 }
 
-impl<AuthoritySet, VoterState> GrandpaApi for GrandpaRpcHandler<AuthoritySet, VoterState>
+impl<AuthoritySet, VoterState, Block, ProofProvider>
+	GrandpaApi<JustificationNotification, Block::Hash>
+	for GrandpaRpcHandler<AuthoritySet, VoterState, Block, ProofProvider>
 where
 	VoterState: ReportVoterState + Send + Sync + 'static,
 	AuthoritySet: ReportAuthoritySet + Send + Sync + 'static,
+	Block: BlockT,
+	ProofProvider: RpcFinalityProofProvider<Block> + Send + Sync + 'static,
 {
+	type Metadata = sc_rpc::Metadata;
+
 	fn round_state(&self) -> FutureResult<ReportedRoundStates> {
 		let round_states = ReportedRoundStates::from(&self.authority_set, &self.voter_state);
 		let future = async move { round_states }.boxed();
 		Box::new(future.map_err(jsonrpc_core::Error::from).compat())
 	}
+
+	fn prove_finality(
+		&self,
+		begin: Block::Hash,
+		end: Block::Hash,
+		authorities_set_id: u64,
+	) -> FutureResult<Option<EncodedFinalityProofs>> {
+		let result = self.finality_proof_provider
+			.rpc_prove_finality(begin, end, authorities_set_id)
+			.map_err(Error::ProveFinalityFailed);
+		let future = async move { result }.boxed();
+		Box::new(future.map_err(jsonrpc_core::Error::from).compat())
+	}
+
+	fn subscribe_justifications(
+		&self,
+		_metadata: Self::Metadata,
+		subscriber: Subscriber<JustificationNotification>,
+	) {
+		let stream = self.justification_stream.subscribe()
+			.map(|justification| Ok::<_, ()>(JustificationNotification::from(justification)))
+			.compat();
+
+		self.subscriptions.add(subscriber, |sink| {
+			sink
+				.sink_map_err(|e| warn!("Error sending notifications: {:?}", e))
+				.send_all(stream.map(Ok))
+				.map(|_| ())
+		});
+	}
+
+	fn unsubscribe_justifications(
+		&self,
+		_metadata: Option<Self::Metadata>,
+		id: SubscriptionId,
+	) -> jsonrpc_core::Result<bool> {
+		Ok(self.subscriptions.cancel(id))
+	}
 }
 
 #[cfg(test)]
 mod tests {
 	use super::*;
-	use jsonrpc_core::IoHandler;
-	use sc_finality_grandpa::{report, AuthorityId};
-	use sp_core::crypto::Public;
+	use jsonrpc_core::{futures::future as future01, MetaIoHandler};
+	use sc_finality_grandpa::{report, AuthorityId, GrandpaJustificationStream};
+	use sp_core::{crypto::Public, H256};
+	use sp_runtime::testing::{Block as RawBlock, ExtrinsicWrapper};
 	use std::{collections::HashSet, convert::TryInto};
 
+	type Block = RawBlock<ExtrinsicWrapper<u64>>;
+
 	struct TestAuthoritySet;
 	struct TestVoterState;
 	struct EmptyVoterState;
 
+	struct TestFinalityProofProvider {
+		finality_proofs: Vec<u8>,
+	}
+
 	fn voters() -> HashSet<AuthorityId> {
 		let voter_id_1 = AuthorityId::from_slice(&[1; 32]);
 		let voter_id_2 = AuthorityId::from_slice(&[2; 32]);
@@ -95,6 +214,58 @@ mod tests {
 		}
 	}
 
+	impl RpcFinalityProofProvider<Block> for TestFinalityProofProvider {
+		fn rpc_prove_finality(
+			&self,
+			_begin: H256,
+			_end: H256,
+			_authorities_set_id: u64,
+		) -> Result<Option<EncodedFinalityProofs>, sp_blockchain::Error> {
+			Ok(Some(EncodedFinalityProofs(self.finality_proofs.clone().into())))
+		}
+	}
+
+	type Boxed01Future01 = Box<dyn future01::Future<Item = (), Error = ()> + Send + 'static>;
+
+	struct TaskExecutor;
+
+	impl future01::Executor<Boxed01Future01> for TaskExecutor {
+		fn execute(
+			&self,
+			future: Boxed01Future01,
+		) -> std::result::Result<(), future01::ExecuteError<Boxed01Future01>> {
+			std::thread::spawn(move || future.wait());
+			Ok(())
+		}
+	}
+
+	fn setup_rpc_handler<VoterState>(
+		voter_state: VoterState,
+	) -> GrandpaRpcHandler<TestAuthoritySet, VoterState, Block, TestFinalityProofProvider> {
+		let finality_proof_provider = Arc::new(TestFinalityProofProvider {
+			finality_proofs: vec![1, 2, 3],
+		});
+		let (_, justification_stream) = GrandpaJustificationStream::channel();
+		GrandpaRpcHandler::new(
+			TestAuthoritySet,
+			voter_state,
+			finality_proof_provider,
+			justification_stream,
+			Subscriptions::new(Arc::new(TaskExecutor)),
+		)
+	}
+
+	fn setup_io_handler<VoterState>(
+		voter_state: VoterState,
+	) -> MetaIoHandler<sc_rpc::Metadata>
+	where
+		VoterState: ReportVoterState + Send + Sync + 'static,
+	{
+		let mut io = MetaIoHandler::default();
+		io.extend_with(GrandpaApi::to_delegate(setup_rpc_handler(voter_state)));
+		io
+	}
+
 	impl ReportVoterState for EmptyVoterState {
 		fn get(&self) -> Option<report::VoterState<AuthorityId>> {
 			None
@@ -135,21 +306,17 @@ mod tests {
 
 	#[test]
 	fn uninitialized_rpc_handler() {
-		let handler = GrandpaRpcHandler::new(TestAuthoritySet, EmptyVoterState);
-		let mut io = IoHandler::new();
-		io.extend_with(GrandpaApi::to_delegate(handler));
+		let io = setup_io_handler(EmptyVoterState);
 
 		let request = r#"{"jsonrpc":"2.0","method":"grandpa_roundState","params":[],"id":1}"#;
 		let response = r#"{"jsonrpc":"2.0","error":{"code":1,"message":"GRANDPA RPC endpoint not ready"},"id":1}"#;
 
-		assert_eq!(Some(response.into()), io.handle_request_sync(request));
+		assert_eq!(Some(response.into()), io.handle_request_sync(request, Default::default()));
 	}
 
 	#[test]
 	fn working_rpc_handler() {
-		let handler = GrandpaRpcHandler::new(TestAuthoritySet, TestVoterState);
-		let mut io = IoHandler::new();
-		io.extend_with(GrandpaApi::to_delegate(handler));
+		let io = setup_io_handler(TestVoterState);
 
 		let request = r#"{"jsonrpc":"2.0","method":"grandpa_roundState","params":[],"id":1}"#;
 		let response = "{\"jsonrpc\":\"2.0\",\"result\":{\
@@ -166,6 +333,36 @@ mod tests {
 			\"setId\":1\
 		},\"id\":1}";
 
-		assert_eq!(io.handle_request_sync(request), Some(response.into()));
+		assert_eq!(io.handle_request_sync(request, Default::default()), Some(response.into()));
+	}
+
+	#[test]
+	fn prove_finality_with_test_finality_proof_provider() {
+		let io = setup_io_handler(TestVoterState);
+
+		let request = "{\"jsonrpc\":\"2.0\",\"method\":\"grandpa_proveFinality\",\"params\":[\
+			\"0x0000000000000000000000000000000000000000000000000000000000000000\",\
+			\"0x0000000000000000000000000000000000000000000000000000000000000001\",\
+			42\
+		],\"id\":1}";
+		let response = r#"{"jsonrpc":"2.0","result":"0x010203","id":1}"#;
+
+		assert_eq!(io.handle_request_sync(request, Default::default()), Some(response.into()));
+	}
+
+	#[test]
+	fn subscribe_and_unsubscribe_to_justifications() {
+		let handler = setup_rpc_handler(TestVoterState);
+		let (subscriber, id, _) = Subscriber::new_test("grandpa_justifications");
+
+		handler.subscribe_justifications(Default::default(), subscriber);
+
+		// the subscription is assigned an id and can be cancelled only once
+		let id = match id.wait() {
+			Ok(Ok(id)) => id,
+			other => panic!("Subscription was not assigned an id: {:?}", other),
+		};
+		assert_eq!(handler.unsubscribe_justifications(None, id.clone()), Ok(true));
+		assert_eq!(handler.unsubscribe_justifications(None, id), Ok(false));
 	}
 }
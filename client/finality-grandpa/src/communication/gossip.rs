@@ -81,6 +81,9 @@
 //! ## Message Validation
 //!
 //! We only send polite messages to peers,
+//!
+//! The signatures of votes are checked in `pre_validate`, which the gossip engine may run on
+//! a pool of worker threads, before the messages are validated against our view.
 
 use sp_runtime::traits::{NumberFor, Block as BlockT, Zero};
 use sc_network_gossip::{MessageIntent, ValidatorContext};
@@ -293,6 +296,21 @@ impl<B: BlockT> KeepTopics<B> {
 	}
 }
 
+/// Check the signature of a vote, which doesn't depend on the state of the validator.
+fn check_vote_signature<B: BlockT>(full: &VoteMessage<B>) -> Result<(), ReputationChange> {
+	sp_finality_grandpa::check_message_signature(
+		&full.message.message,
+		&full.message.id,
+		&full.message.signature,
+		full.round.0,
+		full.set_id.0,
+	).map_err(|()| {
+		debug!(target: "afg", "Bad message signature {}", full.message.id);
+		telemetry!(CONSENSUS_DEBUG; "afg.bad_msg_signature"; "signature" => ?full.message.id);
+		cost::BAD_SIGNATURE
+	})
+}
+
 // topics to send to a neighbor based on their view.
 fn neighbor_topics<B: BlockT>(view: &View<NumberFor<B>>) -> Vec<B::Hash> {
 	let s = view.set_id;
@@ -684,6 +702,12 @@ struct Inner<Block: BlockT> {
 
 type MaybeMessage<Block> = Option<(Vec<PeerId>, NeighborPacket<NumberFor<Block>>)>;
 
+impl crate::Config {
+	fn rebroadcast_after(&self) -> Duration {
+		self.gossip_rebroadcast_period.unwrap_or(REBROADCAST_AFTER)
+	}
+}
+
 impl<Block: BlockT> Inner<Block> {
 	fn new(config: crate::Config) -> Self {
 		let catch_up_config = if config.observer_enabled {
@@ -707,7 +731,7 @@ impl<Block: BlockT> Inner<Block> {
 			local_view: None,
 			peers: Peers::default(),
 			live_topics: KeepTopics::new(),
-			next_rebroadcast: Instant::now() + REBROADCAST_AFTER,
+			next_rebroadcast: Instant::now() + config.rebroadcast_after(),
 			authorities: Vec::new(),
 			pending_catch_up: PendingCatchUp::None,
 			catch_up_config,
@@ -811,14 +835,15 @@ impl<Block: BlockT> Inner<Block> {
 		cost::PAST_REJECTION
 	}
 
-	fn validate_round_message(&self, who: &PeerId, full: &VoteMessage<Block>)
-		-> Action<Block::Hash>
+	/// Check the round, set and voter of a vote, which is cheaper than checking its signature.
+	/// Returns the cost of the vote if it is discarded.
+	fn check_round_message(&self, who: &PeerId, full: &VoteMessage<Block>)
+		-> Result<(), ReputationChange>
 	{
 		match self.consider_vote(full.round, full.set_id) {
-			Consider::RejectFuture => return Action::Discard(Misbehavior::FutureMessage.cost()),
-			Consider::RejectOutOfScope => return Action::Discard(Misbehavior::OutOfScopeMessage.cost()),
-			Consider::RejectPast =>
-				return Action::Discard(self.cost_past_rejection(who, full.round, full.set_id)),
+			Consider::RejectFuture => return Err(Misbehavior::FutureMessage.cost()),
+			Consider::RejectOutOfScope => return Err(Misbehavior::OutOfScopeMessage.cost()),
+			Consider::RejectPast => return Err(self.cost_past_rejection(who, full.round, full.set_id)),
 			Consider::Accept => {},
 		}
 
@@ -826,19 +851,18 @@ impl<Block: BlockT> Inner<Block> {
 		if !self.authorities.contains(&full.message.id) {
 			debug!(target: "afg", "Message from unknown voter: {}", full.message.id);
 			telemetry!(CONSENSUS_DEBUG; "afg.bad_msg_signature"; "signature" => ?full.message.id);
-			return Action::Discard(cost::UNKNOWN_VOTER);
+			return Err(cost::UNKNOWN_VOTER);
 		}
 
-		if let Err(()) = sp_finality_grandpa::check_message_signature(
-			&full.message.message,
-			&full.message.id,
-			&full.message.signature,
-			full.round.0,
-			full.set_id.0,
-		) {
-			debug!(target: "afg", "Bad message signature {}", full.message.id);
-			telemetry!(CONSENSUS_DEBUG; "afg.bad_msg_signature"; "signature" => ?full.message.id);
-			return Action::Discard(cost::BAD_SIGNATURE);
+		Ok(())
+	}
+
+	fn validate_round_message(&self, who: &PeerId, full: &VoteMessage<Block>)
+		-> Action<Block::Hash>
+	{
+		// The signature was checked by `pre_validate`.
+		if let Err(cost) = self.check_round_message(who, full) {
+			return Action::Discard(cost);
 		}
 
 		let topic = super::round_topic::<Block>(full.round.0, full.set_id.0);
@@ -1462,6 +1486,19 @@ impl<Block: BlockT> sc_network_gossip::Validator<Block> for GossipValidator<Bloc
 		self.inner.write().peers.peer_disconnected(who);
 	}
 
+	fn pre_validate(&self, who: &PeerId, mut data: &[u8]) -> Result<(), ReputationChange> {
+		match GossipMessage::<Block>::decode(&mut data) {
+			Ok(GossipMessage::Vote(ref message)) => {
+				// The votes that `validate` would discard anyway don't need their signature to
+				// be checked.
+				self.inner.read().check_round_message(who, message)?;
+				check_vote_signature(message)
+			},
+			// Undecodable messages are reported by `validate`.
+			_ => Ok(()),
+		}
+	}
+
 	fn validate(&self, context: &mut dyn ValidatorContext<Block>, who: &PeerId, data: &[u8])
 		-> sc_network_gossip::ValidationResult<Block::Hash>
 	{
@@ -1502,7 +1539,7 @@ impl<Block: BlockT> sc_network_gossip::Validator<Block> for GossipValidator<Bloc
 			let mut inner = self.inner.write();
 			let now = Instant::now();
 			let do_rebroadcast = if now >= inner.next_rebroadcast {
-				inner.next_rebroadcast = now + REBROADCAST_AFTER;
+				inner.next_rebroadcast = now + inner.config.rebroadcast_after();
 				true
 			} else {
 				false
@@ -1624,7 +1661,9 @@ mod tests {
 	fn config() -> crate::Config {
 		crate::Config {
 			gossip_duration: Duration::from_millis(10),
-			justification_period: 256,
+			justification_period: Some(256),
+			gossip_rebroadcast_period: None,
+			gossip_validation_workers: 0,
 			keystore: None,
 			name: None,
 			is_authority: true,
@@ -1854,7 +1893,7 @@ mod tests {
 			}
 		});
 
-		let bad_sig = inner.validate_round_message(&peer, &VoteMessage {
+		let bad_sig = check_vote_signature(&VoteMessage::<Block> {
 			round: Round(1),
 			set_id: SetId(set_id),
 			message: SignedMessage::<Block> {
@@ -1868,7 +1907,45 @@ mod tests {
 		});
 
 		assert_eq!(unknown_voter, Action::Discard(cost::UNKNOWN_VOTER));
-		assert_eq!(bad_sig, Action::Discard(cost::BAD_SIGNATURE));
+		assert_eq!(bad_sig, Err(cost::BAD_SIGNATURE));
+	}
+
+	#[test]
+	fn cheap_checks_come_before_the_signature() {
+		let (val, _) = GossipValidator::<Block>::new(
+			config(),
+			voter_set_state(),
+			None,
+		);
+		let auth = AuthorityId::from_slice(&[1u8; 32]);
+		let peer = PeerId::random();
+
+		val.note_set(SetId(1), vec![auth.clone()], |_, _| {});
+		val.note_round(Round(1), |_, _| {});
+
+		// all the votes have a bad signature
+		let vote = |round, id| GossipMessage::<Block>::Vote(VoteMessage {
+			round: Round(round),
+			set_id: SetId(1),
+			message: SignedMessage::<Block> {
+				message: finality_grandpa::Message::Prevote(finality_grandpa::Prevote {
+					target_hash: Default::default(),
+					target_number: 10,
+				}),
+				signature: Default::default(),
+				id,
+			},
+		}).encode();
+
+		assert_eq!(val.pre_validate(&peer, &vote(1, auth.clone())), Err(cost::BAD_SIGNATURE));
+		assert_eq!(
+			val.pre_validate(&peer, &vote(1, AuthorityId::from_slice(&[2u8; 32]))),
+			Err(cost::UNKNOWN_VOTER),
+		);
+		assert_eq!(
+			val.pre_validate(&peer, &vote(5, auth)),
+			Err(Misbehavior::FutureMessage.cost()),
+		);
 	}
 
 	#[test]
@@ -143,6 +143,27 @@ pub(crate) fn global_topic<B: BlockT>(set_id: SetIdNumber) -> B::Hash {
 	<<B::Header as HeaderT>::Hashing as HashT>::hash(format!("{}-GLOBAL", set_id).as_bytes())
 }
 
+/// A gossip message decoded by `decode_replayed_message`.
+pub(crate) enum ReplayedMessage<B: BlockT> {
+	/// A vote of a round.
+	Vote { round: RoundNumber, set_id: SetIdNumber, message: SignedMessage<B> },
+	/// The commit of a round.
+	Commit { round: RoundNumber, set_id: SetIdNumber, commit: CompactCommit<B> },
+	/// Any other message, which doesn't change the state of the rounds.
+	Other,
+}
+
+/// Decode a gossip message replayed from a log, `None` if it is not a GRANDPA message.
+pub(crate) fn decode_replayed_message<B: BlockT>(mut message: &[u8]) -> Option<ReplayedMessage<B>> {
+	Some(match GossipMessage::<B>::decode(&mut message).ok()? {
+		GossipMessage::Vote(VoteMessage { round, set_id, message }) =>
+			ReplayedMessage::Vote { round: round.0, set_id: set_id.0, message },
+		GossipMessage::Commit(FullCommitMessage { round, set_id, message }) =>
+			ReplayedMessage::Commit { round: round.0, set_id: set_id.0, commit: message },
+		_ => ReplayedMessage::Other,
+	})
+}
+
 /// Bridge between the underlying network service, gossiping consensus messages and Grandpa
 pub(crate) struct NetworkBridge<B: BlockT, N: Network<B>> {
 	service: N,
@@ -184,6 +205,8 @@ impl<B: BlockT, N: Network<B>> NetworkBridge<B, N> {
 		set_state: crate::environment::SharedVoterSetState<B>,
 		prometheus_registry: Option<&Registry>,
 	) -> Self {
+		let rebroadcast_period = config.gossip_rebroadcast_period;
+		let validation_workers = config.gossip_validation_workers;
 		let (validator, report_stream) = GossipValidator::new(
 			config,
 			set_state.clone(),
@@ -191,12 +214,17 @@ impl<B: BlockT, N: Network<B>> NetworkBridge<B, N> {
 		);
 
 		let validator = Arc::new(validator);
-		let gossip_engine = Arc::new(Mutex::new(GossipEngine::new(
+		let mut gossip_engine = GossipEngine::new(
 			service.clone(),
 			GRANDPA_ENGINE_ID,
 			GRANDPA_PROTOCOL_NAME,
 			validator.clone()
-		)));
+		);
+		if let Some(period) = rebroadcast_period {
+			gossip_engine.set_rebroadcast_interval(period);
+		}
+		gossip_engine.set_validation_workers(validation_workers);
+		let gossip_engine = Arc::new(Mutex::new(gossip_engine));
 
 		{
 			// register all previous votes with the gossip service so that they're
@@ -236,7 +264,7 @@ impl<B: BlockT, N: Network<B>> NetworkBridge<B, N> {
 			}
 		}
 
-		let (neighbor_packet_worker, neighbor_packet_sender) = periodic::NeighborPacketWorker::new();
+		let (neighbor_packet_worker, neighbor_packet_sender) = periodic::NeighborPacketWorker::new(rebroadcast_period);
 
 		NetworkBridge {
 			service,
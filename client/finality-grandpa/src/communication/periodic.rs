@@ -54,6 +54,7 @@ impl<B: BlockT> NeighborPacketSender<B> {
 /// implementation). Periodically it sends out the last packet in cases where no new ones arrive.
 pub(super) struct NeighborPacketWorker<B: BlockT> {
 	last: Option<(Vec<PeerId>, NeighborPacket<NumberFor<B>>)>,
+	rebroadcast_period: Duration,
 	delay: Delay,
 	rx: TracingUnboundedReceiver<(Vec<PeerId>, NeighborPacket<NumberFor<B>>)>,
 }
@@ -61,13 +62,17 @@ pub(super) struct NeighborPacketWorker<B: BlockT> {
 impl<B: BlockT> Unpin for NeighborPacketWorker<B> {}
 
 impl<B: BlockT> NeighborPacketWorker<B> {
-	pub(super) fn new() -> (Self, NeighborPacketSender<B>){
+	/// Create a new worker. `rebroadcast_period` overrides how often the last
+	/// packet is resent when no new packets arrive.
+	pub(super) fn new(rebroadcast_period: Option<Duration>) -> (Self, NeighborPacketSender<B>){
 		let (tx, rx) = tracing_unbounded::<(Vec<PeerId>, NeighborPacket<NumberFor<B>>)>
 			("mpsc_grandpa_neighbor_packet_worker");
-		let delay = Delay::new(REBROADCAST_AFTER);
+		let rebroadcast_period = rebroadcast_period.unwrap_or(REBROADCAST_AFTER);
+		let delay = Delay::new(rebroadcast_period);
 
 		(NeighborPacketWorker {
 			last: None,
+			rebroadcast_period,
 			delay,
 			rx,
 		}, NeighborPacketSender(tx))
@@ -83,7 +88,7 @@ impl <B: BlockT> Stream for NeighborPacketWorker<B> {
 		match this.rx.poll_next_unpin(cx) {
 			Poll::Ready(None) => return Poll::Ready(None),
 			Poll::Ready(Some((to, packet))) => {
-				this.delay.reset(REBROADCAST_AFTER);
+				this.delay.reset(this.rebroadcast_period);
 				this.last = Some((to.clone(), packet.clone()));
 
 				return Poll::Ready(Some((to, GossipMessage::<B>::from(packet))));
@@ -96,7 +101,7 @@ impl <B: BlockT> Stream for NeighborPacketWorker<B> {
 
 		// Getting this far here implies that the timer fired.
 
-		this.delay.reset(REBROADCAST_AFTER);
+		this.delay.reset(this.rebroadcast_period);
 
 		// Make sure the underlying task is scheduled for wake-up.
 		//
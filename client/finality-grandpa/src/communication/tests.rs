@@ -129,7 +129,9 @@ impl Tester {
 fn config() -> crate::Config {
 	crate::Config {
 		gossip_duration: std::time::Duration::from_millis(10),
-		justification_period: 256,
+		justification_period: Some(256),
+		gossip_rebroadcast_period: None,
+		gossip_validation_workers: 0,
 		keystore: None,
 		name: None,
 		is_authority: true,
@@ -38,7 +38,7 @@ use sp_blockchain::{HeaderBackend, HeaderMetadata, Error as ClientError};
 use sp_core::Pair;
 use sp_runtime::generic::BlockId;
 use sp_runtime::traits::{
-	Block as BlockT, Header as HeaderT, NumberFor, One, Zero,
+	AtLeast32Bit, Block as BlockT, Header as HeaderT, NumberFor, One, Zero,
 };
 use sc_telemetry::{telemetry, CONSENSUS_INFO};
 
@@ -53,6 +53,7 @@ use crate::authorities::{AuthoritySet, SharedAuthoritySet};
 use crate::communication::Network as NetworkT;
 use crate::consensus_changes::SharedConsensusChanges;
 use crate::justification::GrandpaJustification;
+use crate::notification::GrandpaJustificationSender;
 use crate::until_imported::UntilVoteTargetImported;
 use crate::voting_rule::VotingRule;
 use sp_finality_grandpa::{
@@ -405,6 +406,7 @@ pub(crate) struct Environment<Backend, Block: BlockT, C, N: NetworkT<Block>, SC,
 	pub(crate) voter_set_state: SharedVoterSetState<Block>,
 	pub(crate) voting_rule: VR,
 	pub(crate) metrics: Option<Metrics>,
+	pub(crate) justification_sender: Option<GrandpaJustificationSender<Block>>,
 	pub(crate) _phantom: PhantomData<Backend>,
 }
 
@@ -1004,11 +1006,12 @@ where
 			self.client.clone(),
 			&self.authority_set,
 			&self.consensus_changes,
-			Some(self.config.justification_period.into()),
+			self.config.justification_period.map(Into::into),
 			hash,
 			number,
 			(round, commit).into(),
 			false,
+			self.justification_sender.as_ref(),
 		)
 	}
 
@@ -1060,6 +1063,18 @@ impl<Block: BlockT> From<GrandpaJustification<Block>> for JustificationOrCommit<
 	}
 }
 
+/// Whether block `number`, finalized after block `last_finalized`, is the first finalized block
+/// past a multiple of `period`, whose justification is kept to prove the finality of the chain to
+/// remote nodes.
+pub(crate) fn crosses_justification_period<N: AtLeast32Bit + Copy>(
+	last_finalized: N,
+	number: N,
+	period: N,
+) -> bool {
+	(!last_finalized.is_zero() || number - last_finalized == period) &&
+		(last_finalized / period != number / period)
+}
+
 /// Finalize the given block and apply any authority set changes. If an
 /// authority set change is enacted then a justification is created (if not
 /// given) and stored with the block when finalizing it.
@@ -1073,6 +1088,7 @@ pub(crate) fn finalize_block<BE, Block, Client>(
 	number: NumberFor<Block>,
 	justification_or_commit: JustificationOrCommit<Block>,
 	initial_sync: bool,
+	justification_sender: Option<&GrandpaJustificationSender<Block>>,
 ) -> Result<(), CommandOrError<Block::Hash, NumberFor<Block>>> where
 	Block:  BlockT,
 	BE: Backend<Block>,
@@ -1143,8 +1159,9 @@ pub(crate) fn finalize_block<BE, Block, Client>(
 		// `N+1`. this assumption is required to make sure we store
 		// justifications for transition blocks which will be requested by
 		// syncing clients.
-		let justification = match justification_or_commit {
-			JustificationOrCommit::Justification(justification) => Some(justification.encode()),
+		let justification_or_commit = match justification_or_commit {
+			JustificationOrCommit::Justification(justification) =>
+				JustificationOrCommit::Justification(justification),
 			JustificationOrCommit::Commit((round_number, commit)) => {
 				let mut justification_required =
 					// justification is always required when block that enacts new authorities
@@ -1157,10 +1174,11 @@ pub(crate) fn finalize_block<BE, Block, Client>(
 				// finalization to remote nodes
 				if !justification_required {
 					if let Some(justification_period) = justification_period {
-						let last_finalized_number = client.info().finalized_number;
-						justification_required =
-							(!last_finalized_number.is_zero() || number - last_finalized_number == justification_period) &&
-							(last_finalized_number / justification_period != number / justification_period);
+						justification_required = crosses_justification_period(
+							client.info().finalized_number,
+							number,
+							justification_period,
+						);
 					}
 				}
 
@@ -1171,12 +1189,16 @@ pub(crate) fn finalize_block<BE, Block, Client>(
 						commit,
 					)?;
 
-					Some(justification.encode())
+					JustificationOrCommit::Justification(justification)
 				} else {
-					None
+					JustificationOrCommit::Commit((round_number, commit))
 				}
 			},
 		};
+		let justification = match justification_or_commit {
+			JustificationOrCommit::Justification(ref justification) => Some(justification.encode()),
+			JustificationOrCommit::Commit(_) => None,
+		};
 
 		debug!(target: "afg", "Finalizing blocks up to ({:?}, {})", number, hash);
 
@@ -1190,6 +1212,18 @@ pub(crate) fn finalize_block<BE, Block, Client>(
 			"number" => ?number, "hash" => ?hash,
 		);
 
+		// the justification is only created for the subscribers if it isn't stored
+		if let Some(sender) = justification_sender {
+			let justification = || match justification_or_commit {
+				JustificationOrCommit::Justification(justification) => Ok(justification),
+				JustificationOrCommit::Commit((round_number, commit)) =>
+					GrandpaJustification::from_commit(&client, round_number, commit),
+			};
+			if let Err(e) = sender.notify(justification) {
+				warn!(target: "afg", "Failed to create justification of block {:?}: {:?}", hash, e);
+			}
+		}
+
 		let new_authorities = if let Some((canon_hash, canon_number)) = status.new_set_block {
 			// the authority set has changed.
 			let (new_id, set_ref) = authority_set.current();
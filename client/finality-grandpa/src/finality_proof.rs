@@ -171,6 +171,32 @@ impl<B, Block: BlockT> FinalityProofProvider<B, Block>
 	}
 }
 
+impl<B, Block> FinalityProofProvider<B, Block>
+	where
+		Block: BlockT,
+		NumberFor<Block>: BlockNumberOps,
+		B: Backend<Block> + Send + Sync + 'static,
+{
+	/// Prove finality of the best block we have a proof for in the range `(begin; end]`,
+	/// starting with the authority set `authorities_set_id`.
+	///
+	/// Returns `None` if no block after `begin` is known to be finalized.
+	pub fn prove_finality(
+		&self,
+		begin: Block::Hash,
+		end: Block::Hash,
+		authorities_set_id: u64,
+	) -> Result<Option<Vec<u8>>, ClientError> {
+		prove_finality::<_, _, GrandpaJustification<Block>>(
+			&*self.backend.blockchain(),
+			&*self.authority_provider,
+			authorities_set_id,
+			begin,
+			end,
+		)
+	}
+}
+
 impl<B, Block> sc_network::config::FinalityProofProvider<Block> for FinalityProofProvider<B, Block>
 	where
 		Block: BlockT,
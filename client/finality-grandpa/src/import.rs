@@ -16,21 +16,22 @@
 // You should have received a copy of the GNU General Public License
 // along with this program. If not, see <https://www.gnu.org/licenses/>.
 
-use std::{sync::Arc, collections::HashMap};
+use std::{sync::Arc, collections::{HashMap, HashSet}};
 
 use log::{debug, trace};
 use parity_scale_codec::Encode;
-use parking_lot::RwLockWriteGuard;
+use parking_lot::{Mutex, RwLockWriteGuard};
 
-use sp_blockchain::{BlockStatus, well_known_cache_keys};
+use sp_blockchain::{BlockStatus, HeaderBackend, HeaderMetadata, well_known_cache_keys};
 use sc_client_api::{backend::Backend, utils::is_descendent_of};
 use sp_utils::mpsc::TracingUnboundedSender;
 use sp_api::{TransactionFor};
+use finality_grandpa::voter_set::VoterSet;
 
 use sp_consensus::{
 	BlockImport, Error as ConsensusError,
 	BlockCheckParams, BlockImportParams, BlockOrigin, ImportResult, JustificationImport,
-	SelectChain,
+	JustificationCheck, SelectChain,
 };
 use sp_finality_grandpa::{ConsensusLog, ScheduledChange, SetId, GRANDPA_ENGINE_ID};
 use sp_runtime::Justification;
@@ -42,9 +43,12 @@ use sp_runtime::traits::{
 use crate::{Error, CommandOrError, NewAuthoritySet, VoterCommand};
 use crate::authorities::{AuthoritySet, SharedAuthoritySet, DelayKind, PendingChange};
 use crate::consensus_changes::SharedConsensusChanges;
-use crate::environment::finalize_block;
-use crate::justification::GrandpaJustification;
+use crate::environment::{crosses_justification_period, finalize_block};
+use crate::justification::{GrandpaJustification, SignatureCheckPool};
+use crate::notification::GrandpaJustificationSender;
+use crate::replay::GrandpaReplay;
 use std::marker::PhantomData;
+use std::time::Duration;
 
 /// A block-import handler for GRANDPA.
 ///
@@ -62,6 +66,10 @@ pub struct GrandpaBlockImport<Backend, Block: BlockT, Client, SC> {
 	send_voter_commands: TracingUnboundedSender<VoterCommand<Block::Hash, NumberFor<Block>>>,
 	consensus_changes: SharedConsensusChanges<Block::Hash, NumberFor<Block>>,
 	authority_set_hard_forks: HashMap<Block::Hash, PendingChange<Block::Hash, NumberFor<Block>>>,
+	justification_sender: GrandpaJustificationSender<Block>,
+	justification_request_period: Option<NumberFor<Block>>,
+	last_justification_request: Arc<Mutex<NumberFor<Block>>>,
+	justification_verification_pool: Option<SignatureCheckPool>,
 	_phantom: PhantomData<Backend>,
 }
 
@@ -76,6 +84,10 @@ impl<Backend, Block: BlockT, Client, SC: Clone> Clone for
 			send_voter_commands: self.send_voter_commands.clone(),
 			consensus_changes: self.consensus_changes.clone(),
 			authority_set_hard_forks: self.authority_set_hard_forks.clone(),
+			justification_sender: self.justification_sender.clone(),
+			justification_request_period: self.justification_request_period,
+			last_justification_request: self.last_justification_request.clone(),
+			justification_verification_pool: self.justification_verification_pool.clone(),
 			_phantom: PhantomData,
 		}
 	}
@@ -212,6 +224,33 @@ fn find_forced_change<B: BlockT>(header: &B::Header)
 	header.digest().convert_first(|l| l.try_to(id).and_then(filter_log))
 }
 
+/// Returns the change of the authority set signaled by the block with `header` and `hash`.
+fn signaled_change<B: BlockT>(
+	header: &B::Header,
+	hash: B::Hash,
+) -> Option<PendingChange<B::Hash, NumberFor<B>>> {
+	// check for forced change.
+	if let Some((median_last_finalized, change)) = find_forced_change::<B>(header) {
+		return Some(PendingChange {
+			next_authorities: change.next_authorities,
+			delay: change.delay,
+			canon_height: *header.number(),
+			canon_hash: hash,
+			delay_kind: DelayKind::Best { median_last_finalized },
+		});
+	}
+
+	// check normal scheduled change.
+	let change = find_scheduled_change::<B>(header)?;
+	Some(PendingChange {
+		next_authorities: change.next_authorities,
+		delay: change.delay,
+		canon_height: *header.number(),
+		canon_hash: hash,
+		delay_kind: DelayKind::Finalized,
+	})
+}
+
 impl<BE, Block: BlockT, Client, SC>
 	GrandpaBlockImport<BE, Block, Client, SC>
 where
@@ -231,26 +270,7 @@ where
 			return Some(change.clone());
 		}
 
-		// check for forced change.
-		if let Some((median_last_finalized, change)) = find_forced_change::<Block>(header) {
-			return Some(PendingChange {
-				next_authorities: change.next_authorities,
-				delay: change.delay,
-				canon_height: *header.number(),
-				canon_hash: hash,
-				delay_kind: DelayKind::Best { median_last_finalized },
-			});
-		}
-
-		// check normal scheduled change.
-		let change = find_scheduled_change::<Block>(header)?;
-		Some(PendingChange {
-			next_authorities: change.next_authorities,
-			delay: change.delay,
-			canon_height: *header.number(),
-			canon_hash: hash,
-			delay_kind: DelayKind::Finalized,
-		})
+		signaled_change::<Block>(header, hash)
 	}
 
 	fn make_authorities_changes(
@@ -530,6 +550,14 @@ impl<BE, Block: BlockT, Client, SC> BlockImport<Block>
 						number,
 					);
 
+					imported_aux.needs_justification = true;
+				} else if self.requests_justification_of(number, self.inner.info().finalized_number) {
+					trace!(
+						target: "afg",
+						"Imported unjustified block #{}, requesting its periodic justification.",
+						number,
+					);
+
 					imported_aux.needs_justification = true;
 				}
 
@@ -560,6 +588,7 @@ impl<Backend, Block: BlockT, Client, SC> GrandpaBlockImport<Backend, Block, Clie
 		send_voter_commands: TracingUnboundedSender<VoterCommand<Block::Hash, NumberFor<Block>>>,
 		consensus_changes: SharedConsensusChanges<Block::Hash, NumberFor<Block>>,
 		authority_set_hard_forks: Vec<(SetId, PendingChange<Block::Hash, NumberFor<Block>>)>,
+		justification_sender: GrandpaJustificationSender<Block>,
 	) -> GrandpaBlockImport<Backend, Block, Client, SC> {
 		// check for and apply any forced authority set hard fork that applies
 		// to the *current* authority set.
@@ -603,9 +632,172 @@ impl<Backend, Block: BlockT, Client, SC> GrandpaBlockImport<Backend, Block, Clie
 			send_voter_commands,
 			consensus_changes,
 			authority_set_hard_forks,
+			justification_sender,
+			justification_request_period: None,
+			last_justification_request: Arc::new(Mutex::new(Zero::zero())),
+			justification_verification_pool: None,
 			_phantom: PhantomData,
 		}
 	}
+
+	/// Request a justification from the peers for the blocks imported without one that the nodes
+	/// keeping a justification every `period` blocks keep one of, e.g. so that a node serving a
+	/// bridge has the justifications of the whole chain. With `None`, the default, only the
+	/// justifications needed to enact authority set changes are requested.
+	pub fn set_justification_request_period(&mut self, period: Option<NumberFor<Block>>) {
+		self.justification_request_period = period.filter(|period| !period.is_zero());
+	}
+
+	/// Check the signatures of the imported justifications on `workers` threads, spawned once and
+	/// shared by the clones of the block import, instead of the thread importing the blocks.
+	/// 0 or 1, the default being 0, doesn't use any other thread.
+	pub fn set_justification_verification_workers(&mut self, workers: usize) {
+		self.justification_verification_pool = if workers > 1 {
+			Some(SignatureCheckPool::new(workers))
+		} else {
+			None
+		};
+	}
+
+	/// Whether to request the justification of block `number`, imported when the last finalized
+	/// block is `finalized_number`.
+	///
+	/// The peers keep the justification of the first block they finalize past each multiple of
+	/// the period, which is the block following the last finalized or requested one that crosses
+	/// the next multiple, assuming that they finalize the blocks one at a time.
+	fn requests_justification_of(
+		&self,
+		number: NumberFor<Block>,
+		finalized_number: NumberFor<Block>,
+	) -> bool {
+		let period = match self.justification_request_period {
+			Some(period) => period,
+			None => return false,
+		};
+
+		let mut last_request = self.last_justification_request.lock();
+		let last = std::cmp::max(*last_request, finalized_number);
+		if number <= last || !crosses_justification_period(last, number, period) {
+			return false;
+		}
+
+		*last_request = number;
+		true
+	}
+
+	/// Returns a check of the justifications of a chain of blocks before they are imported,
+	/// starting from the current authority set.
+	///
+	/// The authority set hard forks aren't applied, so they must not be part of the chain.
+	pub fn justification_check(&self) -> GrandpaJustificationCheck<Block, Client> {
+		GrandpaJustificationCheck {
+			client: self.inner.clone(),
+			authority_set: self.authority_set.inner().read().clone(),
+			chain: HashSet::new(),
+			known_parent: None,
+			pool: self.justification_verification_pool.clone(),
+		}
+	}
+
+	/// Returns a replay of the GRANDPA messages recorded by a node, whose timers follow the
+	/// given gossip duration.
+	pub fn consensus_replay(&self, gossip_duration: Duration) -> GrandpaReplay<Block, Client> {
+		GrandpaReplay::new(
+			self.inner.clone(),
+			self.authority_set.clone(),
+			gossip_duration.as_millis() as u64,
+		)
+	}
+}
+
+/// Checks the GRANDPA justifications of a chain of blocks before they are imported, following
+/// the changes of the authority set signaled by the blocks.
+///
+/// The blocks enacting a standard change of the authority set must have a justification.
+pub struct GrandpaJustificationCheck<Block: BlockT, Client> {
+	client: Arc<Client>,
+	authority_set: AuthoritySet<Block::Hash, NumberFor<Block>>,
+	/// The blocks checked so far.
+	chain: HashSet<Block::Hash>,
+	/// The known parent of the first block checked.
+	known_parent: Option<Block::Hash>,
+	/// The threads checking the signatures of the justifications, if any.
+	pool: Option<SignatureCheckPool>,
+}
+
+impl<Block, Client> JustificationCheck<Block> for GrandpaJustificationCheck<Block, Client>
+where
+	Block: BlockT,
+	Client: HeaderBackend<Block> + HeaderMetadata<Block, Error = sp_blockchain::Error>,
+	NumberFor<Block>: finality_grandpa::BlockNumberOps,
+{
+	fn check_block(
+		&mut self,
+		header: &Block::Header,
+		justification: Option<&Justification>,
+	) -> Result<(), String> {
+		let (hash, number) = (header.hash(), *header.number());
+		let GrandpaJustificationCheck { client, authority_set, chain, known_parent, pool } = self;
+		let known_parent = *known_parent.get_or_insert(*header.parent_hash());
+		chain.insert(hash);
+
+		// the blocks of the chain aren't known to the client yet
+		let client_is_descendent_of = is_descendent_of::<Block, _>(&**client, None);
+		let is_descendent_of = |base: &Block::Hash, block: &Block::Hash| {
+			if !chain.contains(block) {
+				return client_is_descendent_of(base, block);
+			}
+			Ok(base != block && (
+				chain.contains(base) ||
+				*base == known_parent ||
+				client_is_descendent_of(base, &known_parent)?
+			))
+		};
+
+		if let Some(change) = signaled_change::<Block>(header, hash) {
+			authority_set.add_pending_change(change, &is_descendent_of)
+				.map_err(|e| e.to_string())?;
+		}
+		let forced_change_set = authority_set
+			.apply_forced_changes(hash, number, &is_descendent_of, true)
+			.map_err(|e| e.to_string())?;
+		if let Some((_, new_set)) = forced_change_set {
+			*authority_set = new_set;
+		}
+
+		let justification = match justification {
+			Some(justification) => justification,
+			None => {
+				let enacts_change = authority_set
+					.enacts_standard_change(hash, number, &is_descendent_of)
+					.map_err(|e| e.to_string())?;
+				if enacts_change.is_some() {
+					return Err(format!(
+						"Block #{} ({}) enacts a change of the GRANDPA authorities but has no \
+						justification",
+						number,
+						hash,
+					));
+				}
+				return Ok(());
+			},
+		};
+
+		let (set_id, authorities) = authority_set.current();
+		let voters = VoterSet::new(authorities.iter().cloned())
+			.ok_or_else(|| format!("Invalid GRANDPA authority set {}", set_id))?;
+		GrandpaJustification::<Block>::decode_and_verify_finalizes_with(
+			justification,
+			(hash, number),
+			set_id,
+			&voters,
+			pool.as_ref(),
+		).map_err(|e| format!("Invalid justification of block #{} ({}): {}", number, hash, e))?;
+
+		authority_set.apply_standard_changes(hash, number, &is_descendent_of, true)
+			.map_err(|e| e.to_string())?;
+		Ok(())
+	}
 }
 
 impl<BE, Block: BlockT, Client, SC> GrandpaBlockImport<BE, Block, Client, SC>
@@ -627,11 +819,12 @@ where
 		enacts_change: bool,
 		initial_sync: bool,
 	) -> Result<(), ConsensusError> {
-		let justification = GrandpaJustification::decode_and_verify_finalizes(
+		let justification = GrandpaJustification::decode_and_verify_finalizes_with(
 			&justification,
 			(hash, number),
 			self.authority_set.set_id(),
 			&self.authority_set.current_authorities(),
+			self.justification_verification_pool.as_ref(),
 		);
 
 		let justification = match justification {
@@ -648,6 +841,7 @@ where
 			number,
 			justification.into(),
 			initial_sync,
+			Some(&self.justification_sender),
 		);
 
 		match result {
@@ -18,6 +18,13 @@
 
 use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
+use std::thread;
+
+use futures::channel::{mpsc, oneshot};
+use futures::executor::block_on;
+use futures::{future, StreamExt};
+use log::error;
+use parking_lot::Mutex;
 
 use sp_blockchain::{Error as ClientError, HeaderBackend};
 use parity_scale_codec::{Encode, Decode};
@@ -27,7 +34,7 @@ use sp_runtime::generic::BlockId;
 use sp_runtime::traits::{NumberFor, Block as BlockT, Header as HeaderT};
 use sp_finality_grandpa::AuthorityId;
 
-use crate::{Commit, Error};
+use crate::{Commit, Error, SignedPrecommit};
 
 /// A GRANDPA justification for block finality, it includes a commit message and
 /// an ancestry proof including all headers routing all precommit target blocks
@@ -37,7 +44,7 @@ use crate::{Commit, Error};
 ///
 /// This is meant to be stored in the db and passed around the network to other
 /// nodes, and are used by syncing nodes to prove authority set handoffs.
-#[derive(Encode, Decode)]
+#[derive(Clone, Encode, Decode)]
 pub struct GrandpaJustification<Block: BlockT> {
 	round: u64,
 	pub(crate) commit: Commit<Block>,
@@ -97,6 +104,20 @@ impl<Block: BlockT> GrandpaJustification<Block> {
 	) -> Result<GrandpaJustification<Block>, ClientError> where
 		NumberFor<Block>: finality_grandpa::BlockNumberOps,
 	{
+		Self::decode_and_verify_finalizes_with(encoded, finalized_target, set_id, voters, None)
+	}
+
+	/// Like [`GrandpaJustification::decode_and_verify_finalizes`], checking the signatures on the
+	/// threads of `pool`, if any.
+	pub(crate) fn decode_and_verify_finalizes_with(
+		encoded: &[u8],
+		finalized_target: (Block::Hash, NumberFor<Block>),
+		set_id: u64,
+		voters: &VoterSet<AuthorityId>,
+		pool: Option<&SignatureCheckPool>,
+	) -> Result<GrandpaJustification<Block>, ClientError> where
+		NumberFor<Block>: finality_grandpa::BlockNumberOps,
+	{
 
 		let justification = GrandpaJustification::<Block>::decode(&mut &*encoded)
 			.map_err(|_| ClientError::JustificationDecode)?;
@@ -105,12 +126,26 @@ impl<Block: BlockT> GrandpaJustification<Block> {
 			let msg = "invalid commit target in grandpa justification".to_string();
 			Err(ClientError::BadJustification(msg))
 		} else {
-			justification.verify(set_id, voters).map(|_| justification)
+			justification.verify_with(set_id, voters, pool).map(|_| justification)
 		}
 	}
 
 	/// Validate the commit and the votes' ancestry proofs.
 	pub(crate) fn verify(&self, set_id: u64, voters: &VoterSet<AuthorityId>) -> Result<(), ClientError>
+	where
+		NumberFor<Block>: finality_grandpa::BlockNumberOps,
+	{
+		self.verify_with(set_id, voters, None)
+	}
+
+	/// Validate the commit and the votes' ancestry proofs, checking the signatures of the
+	/// precommits on the threads of `pool`, or on the calling thread without one.
+	fn verify_with(
+		&self,
+		set_id: u64,
+		voters: &VoterSet<AuthorityId>,
+		pool: Option<&SignatureCheckPool>,
+	) -> Result<(), ClientError>
 	where
 		NumberFor<Block>: finality_grandpa::BlockNumberOps,
 	{
@@ -130,21 +165,13 @@ impl<Block: BlockT> GrandpaJustification<Block> {
 			}
 		}
 
-		let mut buf = Vec::new();
+		if !self.signatures_are_valid(set_id, pool) {
+			return Err(ClientError::BadJustification(
+				"invalid signature for precommit in grandpa justification".to_string()));
+		}
+
 		let mut visited_hashes = HashSet::new();
 		for signed in self.commit.precommits.iter() {
-			if sp_finality_grandpa::check_message_signature_with_buffer(
-				&finality_grandpa::Message::Precommit(signed.precommit.clone()),
-				&signed.id,
-				&signed.signature,
-				self.round,
-				set_id,
-				&mut buf,
-			).is_err() {
-				return Err(ClientError::BadJustification(
-					"invalid signature for precommit in grandpa justification".to_string()));
-			}
-
 			if self.commit.target_hash == signed.precommit.target_hash {
 				continue;
 			}
@@ -176,6 +203,89 @@ impl<Block: BlockT> GrandpaJustification<Block> {
 
 		Ok(())
 	}
+
+	/// Returns whether the signatures of all the precommits are valid, splitting them between
+	/// the threads of `pool`.
+	fn signatures_are_valid(&self, set_id: u64, pool: Option<&SignatureCheckPool>) -> bool {
+		let round = self.round;
+		let check = move |precommits: &[SignedPrecommit<Block>]| {
+			let mut buf = Vec::new();
+			precommits.iter().all(|signed| sp_finality_grandpa::check_message_signature_with_buffer(
+				&finality_grandpa::Message::Precommit(signed.precommit.clone()),
+				&signed.id,
+				&signed.signature,
+				round,
+				set_id,
+				&mut buf,
+			).is_ok())
+		};
+
+		let precommits = &self.commit.precommits;
+		let pool = match pool {
+			Some(pool) if precommits.len() > 1 => pool,
+			_ => return check(&precommits[..]),
+		};
+
+		let chunk_size = (precommits.len() + pool.workers - 1) / pool.workers;
+		let results = precommits.chunks(chunk_size)
+			.map(|chunk| {
+				let chunk = chunk.to_vec();
+				pool.check(Box::new(move || check(&chunk[..])))
+			})
+			.collect::<Vec<_>>();
+
+		// all the results are awaited, so that no check outlives the verification.
+		block_on(future::join_all(results)).into_iter().all(|valid| valid.unwrap_or(false))
+	}
+}
+
+/// A check of the signatures of a chunk of precommits.
+type SignatureCheck = Box<dyn FnOnce() -> bool + Send>;
+
+/// Threads checking the signatures of the precommits of justifications, shared by the clones of
+/// the pool. The threads stop once the pool and all its clones are dropped.
+#[derive(Clone)]
+pub(crate) struct SignatureCheckPool {
+	checks: mpsc::UnboundedSender<(SignatureCheck, oneshot::Sender<bool>)>,
+	workers: usize,
+}
+
+impl SignatureCheckPool {
+	/// Spawn `workers` threads checking signatures.
+	pub(crate) fn new(workers: usize) -> Self {
+		let (checks, checks_rx) = mpsc::unbounded::<(SignatureCheck, oneshot::Sender<bool>)>();
+		let checks_rx = Arc::new(Mutex::new(checks_rx));
+
+		for index in 0..workers {
+			let checks_rx = checks_rx.clone();
+			let spawned = thread::Builder::new()
+				.name(format!("grandpa-justification-{}", index))
+				.spawn(move || loop {
+					let next = block_on(checks_rx.lock().next());
+					match next {
+						Some((check, result)) => { let _ = result.send(check()); },
+						None => break,
+					}
+				});
+
+			if let Err(e) = spawned {
+				error!(target: "afg", "Failed to spawn a justification verification worker: {}", e);
+			}
+		}
+
+		SignatureCheckPool { checks, workers: workers.max(1) }
+	}
+
+	/// Run `check` on one of the threads, or on the calling thread if there is none.
+	fn check(&self, check: SignatureCheck) -> oneshot::Receiver<bool> {
+		let (result, result_rx) = oneshot::channel();
+		if let Err(e) = self.checks.unbounded_send((check, result)) {
+			let (check, result) = e.into_inner();
+			let _ = result.send(check());
+		}
+
+		result_rx
+	}
 }
 
 /// A utility trait implementing `finality_grandpa::Chain` using a given set of headers.
@@ -113,15 +113,19 @@ mod finality_proof;
 mod import;
 mod justification;
 mod light_import;
+mod notification;
 mod observer;
+mod replay;
 mod until_imported;
 mod voting_rule;
 
 pub use authorities::SharedAuthoritySet;
 pub use finality_proof::{FinalityProofProvider, StorageAndProofProvider};
-pub use import::GrandpaBlockImport;
+pub use import::{GrandpaBlockImport, GrandpaJustificationCheck};
 pub use justification::GrandpaJustification;
 pub use light_import::light_block_import;
+pub use replay::GrandpaReplay;
+pub use notification::{GrandpaJustificationSender, GrandpaJustificationStream, JustificationStream};
 pub use voting_rule::{
 	BeforeBestBlockBy, ThreeQuartersOfTheUnfinalizedChain, VotingRule, VotingRulesBuilder
 };
@@ -171,6 +175,13 @@ pub type Commit<Block> = finality_grandpa::Commit<
 	AuthoritySignature,
 	AuthorityId,
 >;
+/// A signed precommit message for this chain's block type.
+pub type SignedPrecommit<Block> = finality_grandpa::SignedPrecommit<
+	<Block as BlockT>::Hash,
+	NumberFor<Block>,
+	AuthoritySignature,
+	AuthorityId,
+>;
 /// A compact commit message for this chain's block type.
 pub type CompactCommit<Block> = finality_grandpa::CompactCommit<
 	<Block as BlockT>::Hash,
@@ -252,8 +263,9 @@ pub struct Config {
 	pub gossip_duration: Duration,
 	/// Justification generation period (in blocks). GRANDPA will try to generate justifications
 	/// at least every justification_period blocks. There are some other events which might cause
-	/// justification generation.
-	pub justification_period: u32,
+	/// justification generation. `None` if justifications are only generated for these events,
+	/// e.g. authority set changes.
+	pub justification_period: Option<u32>,
 	/// Whether the GRANDPA observer protocol is live on the network and thereby
 	/// a full-node not running as a validator is running the GRANDPA observer
 	/// protocol (we will only issue catch-up requests to authorities when the
@@ -265,6 +277,13 @@ pub struct Config {
 	pub name: Option<String>,
 	/// The keystore that manages the keys of this node.
 	pub keystore: Option<sc_keystore::KeyStorePtr>,
+	/// How long to wait before rebroadcasting gossip messages and neighbor packets
+	/// when nothing new was sent. `None` uses the built-in defaults, which suit
+	/// public networks but may be too aggressive for high-latency links.
+	pub gossip_rebroadcast_period: Option<Duration>,
+	/// Number of threads checking the signatures of incoming gossip messages. With 0, they are
+	/// checked by the task of the gossip engine.
+	pub gossip_validation_workers: usize,
 }
 
 impl Config {
@@ -440,6 +459,8 @@ pub struct LinkHalf<Block: BlockT, C, SC> {
 	select_chain: SC,
 	persistent_data: PersistentData<Block>,
 	voter_commands_rx: TracingUnboundedReceiver<VoterCommand<Block::Hash, NumberFor<Block>>>,
+	justification_sender: GrandpaJustificationSender<Block>,
+	justification_stream: GrandpaJustificationStream<Block>,
 }
 
 impl<Block: BlockT, C, SC> LinkHalf<Block, C, SC> {
@@ -447,6 +468,11 @@ impl<Block: BlockT, C, SC> LinkHalf<Block, C, SC> {
 	pub fn shared_authority_set(&self) -> &SharedAuthoritySet<Block::Hash, NumberFor<Block>> {
 		&self.persistent_data.authority_set
 	}
+
+	/// Get the receiving end of the justification notifications.
+	pub fn justification_stream(&self) -> GrandpaJustificationStream<Block> {
+		self.justification_stream.clone()
+	}
 }
 
 /// Provider for the Grandpa authority set configured on the genesis block.
@@ -545,6 +571,8 @@ where
 
 	let (voter_commands_tx, voter_commands_rx) = tracing_unbounded("mpsc_grandpa_voter_command");
 
+	let (justification_sender, justification_stream) = GrandpaJustificationStream::channel();
+
 	// create pending change objects with 0 delay and enacted on finality
 	// (i.e. standard changes) for each authority set hard fork.
 	let authority_set_hard_forks = authority_set_hard_forks
@@ -571,12 +599,15 @@ where
 			voter_commands_tx,
 			persistent_data.consensus_changes.clone(),
 			authority_set_hard_forks,
+			justification_sender.clone(),
 		),
 		LinkHalf {
 			client,
 			select_chain,
 			persistent_data,
 			voter_commands_rx,
+			justification_sender,
+			justification_stream,
 		},
 	))
 }
@@ -711,6 +742,8 @@ pub fn run_grandpa_voter<Block: BlockT, BE: 'static, C, N, SC, VR>(
 		select_chain,
 		persistent_data,
 		voter_commands_rx,
+		justification_sender,
+		justification_stream: _,
 	} = link;
 
 	let network = NetworkBridge::new(
@@ -759,6 +792,7 @@ pub fn run_grandpa_voter<Block: BlockT, BE: 'static, C, N, SC, VR>(
 		voter_commands_rx,
 		prometheus_registry,
 		shared_voter_state,
+		justification_sender,
 	);
 
 	let voter_work = voter_work
@@ -819,6 +853,7 @@ where
 		voter_commands_rx: TracingUnboundedReceiver<VoterCommand<Block::Hash, NumberFor<Block>>>,
 		prometheus_registry: Option<prometheus_endpoint::Registry>,
 		shared_voter_state: SharedVoterState,
+		justification_sender: GrandpaJustificationSender<Block>,
 	) -> Self {
 		let metrics = match prometheus_registry.as_ref().map(Metrics::register) {
 			Some(Ok(metrics)) => Some(metrics),
@@ -842,6 +877,7 @@ where
 			consensus_changes: persistent_data.consensus_changes.clone(),
 			voter_set_state: persistent_data.set_state,
 			metrics: metrics.as_ref().map(|m| m.environment.clone()),
+			justification_sender: Some(justification_sender),
 			_phantom: PhantomData,
 		});
 
@@ -981,6 +1017,7 @@ where
 					network: self.env.network.clone(),
 					voting_rule: self.env.voting_rule.clone(),
 					metrics: self.env.metrics.clone(),
+					justification_sender: self.env.justification_sender.clone(),
 					_phantom: PhantomData,
 				});
 
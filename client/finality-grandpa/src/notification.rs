@@ -0,0 +1,88 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2020 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Notifications of the GRANDPA justifications created or imported by the node.
+
+use std::sync::Arc;
+
+use parking_lot::Mutex;
+use sp_runtime::traits::Block as BlockT;
+use sp_utils::mpsc::{tracing_unbounded, TracingUnboundedReceiver, TracingUnboundedSender};
+
+use crate::{justification::GrandpaJustification, Error};
+
+/// Stream of the justifications of the blocks finalized by GRANDPA.
+pub type JustificationStream<Block> = TracingUnboundedReceiver<GrandpaJustification<Block>>;
+
+type SharedJustificationSenders<Block> =
+	Arc<Mutex<Vec<TracingUnboundedSender<GrandpaJustification<Block>>>>>;
+
+/// Sending half of the justification notifications, used where blocks are finalized.
+#[derive(Clone)]
+pub struct GrandpaJustificationSender<Block: BlockT> {
+	subscribers: SharedJustificationSenders<Block>,
+}
+
+impl<Block: BlockT> GrandpaJustificationSender<Block> {
+	/// Send the justification returned by `justification` to all the subscribers.
+	///
+	/// Creating a justification may require reading headers from the database, so
+	/// `justification` is only called if there are subscribers.
+	pub(crate) fn notify(
+		&self,
+		justification: impl FnOnce() -> Result<GrandpaJustification<Block>, Error>,
+	) -> Result<(), Error> {
+		let mut subscribers = self.subscribers.lock();
+		subscribers.retain(|subscriber| !subscriber.is_closed());
+		if subscribers.is_empty() {
+			return Ok(());
+		}
+
+		let justification = justification()?;
+		subscribers.retain(|subscriber| subscriber.unbounded_send(justification.clone()).is_ok());
+		Ok(())
+	}
+}
+
+/// Receiving half of the justification notifications, which any number of subscribers can
+/// subscribe to, e.g. an RPC for bridge relayers.
+#[derive(Clone)]
+pub struct GrandpaJustificationStream<Block: BlockT> {
+	subscribers: SharedJustificationSenders<Block>,
+}
+
+impl<Block: BlockT> GrandpaJustificationStream<Block> {
+	/// Create the sending and receiving halves of the justification notifications.
+	pub fn channel() -> (GrandpaJustificationSender<Block>, Self) {
+		let subscribers = SharedJustificationSenders::default();
+		(
+			GrandpaJustificationSender { subscribers: subscribers.clone() },
+			GrandpaJustificationStream { subscribers },
+		)
+	}
+
+	/// Subscribe to the justifications of the blocks finalized from now on.
+	///
+	/// A justification is sent for every block finalized by a GRANDPA round or imported with a
+	/// justification, but not for the blocks finalized along with them.
+	pub fn subscribe(&self) -> JustificationStream<Block> {
+		let (sender, receiver) = tracing_unbounded("mpsc_justification_notification_stream");
+		self.subscribers.lock().push(sender);
+		receiver
+	}
+}
@@ -36,6 +36,7 @@ use sp_blockchain::HeaderMetadata;
 use crate::{
 	global_communication, CommandOrError, CommunicationIn, Config, environment,
 	LinkHalf, Error, aux_schema::PersistentData, VoterCommand, VoterSetState,
+	GrandpaJustificationSender,
 };
 use crate::authorities::SharedAuthoritySet;
 use crate::communication::{Network as NetworkT, NetworkBridge};
@@ -72,6 +73,7 @@ fn grandpa_observer<BE, Block: BlockT, Client, S, F>(
 	last_finalized_number: NumberFor<Block>,
 	commits: S,
 	note_round: F,
+	justification_sender: Option<GrandpaJustificationSender<Block>>,
 ) -> impl Future<Output=Result<(), CommandOrError<Block::Hash, NumberFor<Block>>>> where
 	NumberFor<Block>: BlockNumberOps,
 	S: Stream<
@@ -127,6 +129,7 @@ fn grandpa_observer<BE, Block: BlockT, Client, S, F>(
 				finalized_number,
 				(round, commit).into(),
 				false,
+				justification_sender.as_ref(),
 			) {
 				Ok(_) => {},
 				Err(e) => return future::err(e),
@@ -177,6 +180,7 @@ where
 		select_chain: _,
 		persistent_data,
 		voter_commands_rx,
+		justification_sender,
 		..
 	} = link;
 
@@ -192,7 +196,8 @@ where
 		network,
 		persistent_data,
 		config.keystore,
-		voter_commands_rx
+		voter_commands_rx,
+		Some(justification_sender),
 	);
 
 	let observer_work = observer_work
@@ -213,6 +218,7 @@ struct ObserverWork<B: BlockT, BE, Client, N: NetworkT<B>> {
 	persistent_data: PersistentData<B>,
 	keystore: Option<sc_keystore::KeyStorePtr>,
 	voter_commands_rx: TracingUnboundedReceiver<VoterCommand<B::Hash, NumberFor<B>>>,
+	justification_sender: Option<GrandpaJustificationSender<B>>,
 	_phantom: PhantomData<BE>,
 }
 
@@ -230,6 +236,7 @@ where
 		persistent_data: PersistentData<B>,
 		keystore: Option<sc_keystore::KeyStorePtr>,
 		voter_commands_rx: TracingUnboundedReceiver<VoterCommand<B::Hash, NumberFor<B>>>,
+		justification_sender: Option<GrandpaJustificationSender<B>>,
 	) -> Self {
 
 		let mut work = ObserverWork {
@@ -241,6 +248,7 @@ where
 			persistent_data,
 			keystore,
 			voter_commands_rx,
+			justification_sender,
 			_phantom: PhantomData,
 		};
 		work.rebuild_observer();
@@ -290,6 +298,7 @@ where
 			last_finalized_number,
 			global_in,
 			note_round,
+			self.justification_sender.clone(),
 		);
 
 		self.observer = Box::pin(observer);
@@ -428,6 +437,7 @@ mod tests {
 			persistent_data,
 			None,
 			voter_command_rx,
+			None,
 		);
 
 		// Trigger a reputation change through the gossip validator.
@@ -0,0 +1,388 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2020 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Replay of the GRANDPA messages recorded by a node, see [`GrandpaReplay`].
+
+use std::collections::BTreeMap;
+use std::marker::PhantomData;
+use std::sync::Arc;
+
+use finality_grandpa::{
+	BlockNumberOps, Error as GrandpaError, round::{Round, RoundParams}, voter_set::VoterSet,
+};
+use sp_blockchain::{HeaderBackend, HeaderMetadata};
+use sp_consensus::ConsensusReplay;
+use sp_finality_grandpa::{AuthorityId, AuthoritySignature, GRANDPA_ENGINE_ID};
+use sp_runtime::ConsensusEngineId;
+use sp_runtime::traits::{Block as BlockT, NumberFor, Zero};
+
+use crate::authorities::{AuthoritySet, SharedAuthoritySet};
+use crate::communication::{decode_replayed_message, ReplayedMessage};
+use crate::environment;
+use crate::{Commit, CompactCommit, Message, SignedMessage};
+
+/// The timers of a round, in the order they fire.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Timer {
+	Prevote,
+	Precommit,
+}
+
+impl Timer {
+	/// The number of gossip durations after the start of the round the timer fires at, as in
+	/// the voter.
+	fn gossip_durations(self) -> u64 {
+		match self {
+			Timer::Prevote => 2,
+			Timer::Precommit => 4,
+		}
+	}
+}
+
+struct ReplayChain<'a, Block: BlockT, Client> {
+	client: &'a Arc<Client>,
+	_phantom: PhantomData<Block>,
+}
+
+impl<'a, Block, Client> finality_grandpa::Chain<Block::Hash, NumberFor<Block>>
+	for ReplayChain<'a, Block, Client> where
+		Block: BlockT,
+		Client: HeaderMetadata<Block, Error = sp_blockchain::Error>,
+		NumberFor<Block>: BlockNumberOps,
+{
+	fn ancestry(&self, base: Block::Hash, block: Block::Hash) -> Result<Vec<Block::Hash>, GrandpaError> {
+		environment::ancestry(&self.client, base, block)
+	}
+
+	fn best_chain_containing(&self, _block: Block::Hash) -> Option<(Block::Hash, NumberFor<Block>)> {
+		// only used by voter
+		None
+	}
+}
+
+struct ReplayedRound<Block: BlockT> {
+	round: Round<AuthorityId, Block::Hash, NumberFor<Block>, AuthoritySignature>,
+	/// When the first message of the round was received, or the previous round became
+	/// completable.
+	start: u64,
+	/// The next timer of the round to fire.
+	next_timer: Option<Timer>,
+	completable: bool,
+	finalized: Option<(Block::Hash, NumberFor<Block>)>,
+}
+
+/// Re-drives the GRANDPA rounds with the votes and commits recorded by a node.
+///
+/// The votes are imported into the rounds of the authority set which was current when the replay
+/// was created, or of the authority set which is current when the vote is replayed. The votes of
+/// the other sets are reported as skipped. The prevote and precommit timers of a round fire two
+/// and four gossip durations after its start, as they do in the voter, so that the replay shows
+/// which votes a voter had seen when its timers fired.
+pub struct GrandpaReplay<Block: BlockT, Client> {
+	client: Arc<Client>,
+	/// The authority set when the replay was created.
+	initial_set: AuthoritySet<Block::Hash, NumberFor<Block>>,
+	authority_set: SharedAuthoritySet<Block::Hash, NumberFor<Block>>,
+	/// The gossip duration, in milliseconds.
+	gossip_duration: u64,
+	/// The base of the rounds, the last block finalized in the replay.
+	base: Option<(Block::Hash, NumberFor<Block>)>,
+	rounds: BTreeMap<(u64, u64), ReplayedRound<Block>>,
+}
+
+impl<Block: BlockT, Client> GrandpaReplay<Block, Client> {
+	pub(crate) fn new(
+		client: Arc<Client>,
+		authority_set: SharedAuthoritySet<Block::Hash, NumberFor<Block>>,
+		gossip_duration: u64,
+	) -> Self {
+		GrandpaReplay {
+			client,
+			initial_set: authority_set.inner().read().clone(),
+			authority_set,
+			gossip_duration,
+			base: None,
+			rounds: BTreeMap::new(),
+		}
+	}
+}
+
+impl<Block: BlockT, Client> GrandpaReplay<Block, Client> where
+	Client: HeaderBackend<Block> + HeaderMetadata<Block, Error = sp_blockchain::Error>,
+	NumberFor<Block>: BlockNumberOps,
+{
+	fn voters(&self, set_id: u64) -> Option<VoterSet<AuthorityId>> {
+		if set_id == self.initial_set.set_id {
+			VoterSet::new(self.initial_set.current_authorities.iter().cloned())
+		} else if set_id == self.authority_set.set_id() {
+			Some(self.authority_set.current_authorities())
+		} else {
+			None
+		}
+	}
+
+	fn base(&mut self) -> (Block::Hash, NumberFor<Block>) {
+		let client = &self.client;
+		*self.base.get_or_insert_with(|| (client.info().genesis_hash, Zero::zero()))
+	}
+
+	/// Start the round `(set_id, round)` at `elapsed` if it hasn't started yet.
+	fn start_round(&mut self, elapsed: u64, set_id: u64, round: u64, voters: VoterSet<AuthorityId>) {
+		let base = self.base();
+		self.rounds.entry((set_id, round)).or_insert_with(|| ReplayedRound {
+			round: Round::new(RoundParams { round_number: round, voters, base }),
+			start: elapsed,
+			next_timer: Some(Timer::Prevote),
+			completable: false,
+			finalized: None,
+		});
+	}
+
+	/// Report the changes of the state of the round `(set_id, round)` at `elapsed`.
+	fn round_changed(&mut self, elapsed: u64, set_id: u64, round: u64, events: &mut Vec<(u64, String)>) {
+		let replayed = match self.rounds.get_mut(&(set_id, round)) {
+			Some(replayed) => replayed,
+			None => return,
+		};
+		let state = replayed.round.state();
+
+		if state.finalized.is_some() && state.finalized != replayed.finalized {
+			events.push((elapsed, format!(
+				"round {} of set {}: finalizes {}",
+				round, set_id, describe::<Block>(&state.finalized),
+			)));
+			replayed.finalized = state.finalized;
+		}
+
+		if state.completable && !replayed.completable {
+			replayed.completable = true;
+			events.push((elapsed, format!(
+				"round {} of set {}: completable, estimate {}",
+				round, set_id, describe::<Block>(&state.estimate),
+			)));
+
+			// the voter starts the next round once the round is completable.
+			let voters = replayed.round.voters().clone();
+			self.start_round(elapsed, set_id, round + 1, voters);
+		}
+	}
+
+	fn vote(
+		&mut self,
+		elapsed: u64,
+		round: u64,
+		set_id: u64,
+		vote: SignedMessage<Block>,
+		events: &mut Vec<(u64, String)>,
+	) {
+		let voters = match self.voters(set_id) {
+			Some(voters) => voters,
+			None => {
+				events.push((elapsed, format!(
+					"round {} of set {}: vote of {:?} skipped, the authorities of the set are not known",
+					round, set_id, vote.id,
+				)));
+				return;
+			},
+		};
+
+		if sp_finality_grandpa::check_message_signature(
+			&vote.message,
+			&vote.id,
+			&vote.signature,
+			round,
+			set_id,
+		).is_err() {
+			events.push((elapsed, format!(
+				"round {} of set {}: vote of {:?} has a bad signature",
+				round, set_id, vote.id,
+			)));
+			return;
+		}
+
+		self.start_round(elapsed, set_id, round, voters);
+
+		let chain = ReplayChain { client: &self.client, _phantom: PhantomData };
+		let replayed = self.rounds.get_mut(&(set_id, round)).expect("the round was started above; qed");
+		let (kind, target_hash, target_number) = match &vote.message {
+			Message::Prevote(prevote) => ("prevote", prevote.target_hash, prevote.target_number),
+			Message::Precommit(precommit) => ("precommit", precommit.target_hash, precommit.target_number),
+			Message::PrimaryPropose(propose) => ("primary proposal", propose.target_hash, propose.target_number),
+		};
+		let imported = match vote.message {
+			Message::Prevote(prevote) =>
+				replayed.round.import_prevote(&chain, prevote, vote.id.clone(), vote.signature)
+					.map(|result| (result.valid_voter, result.equivocation.is_some())),
+			Message::Precommit(precommit) =>
+				replayed.round.import_precommit(&chain, precommit, vote.id.clone(), vote.signature)
+					.map(|result| (result.valid_voter, result.equivocation.is_some())),
+			Message::PrimaryPropose(_) => Ok((true, false)),
+		};
+
+		let outcome = match imported {
+			Ok((false, _)) => " from a voter not in the set",
+			Ok((true, true)) => ", an equivocation",
+			Ok((true, false)) => "",
+			Err(_) => ", not a descendant of the base of the round",
+		};
+		events.push((elapsed, format!(
+			"round {} of set {}: {} of {:?} for #{} ({}){}",
+			round, set_id, kind, vote.id, target_number, target_hash, outcome,
+		)));
+
+		self.round_changed(elapsed, set_id, round, events);
+	}
+
+	fn commit(
+		&mut self,
+		elapsed: u64,
+		round: u64,
+		set_id: u64,
+		commit: CompactCommit<Block>,
+		events: &mut Vec<(u64, String)>,
+	) {
+		let voters = match self.voters(set_id) {
+			Some(voters) => voters,
+			None => {
+				events.push((elapsed, format!(
+					"round {} of set {}: commit skipped, the authorities of the set are not known",
+					round, set_id,
+				)));
+				return;
+			},
+		};
+
+		let commit = Commit::<Block>::from(commit);
+		let target = format!("#{} ({})", commit.target_number, commit.target_hash);
+		let bad_signature = commit.precommits.iter().any(|signed| sp_finality_grandpa::check_message_signature(
+			&Message::Precommit(signed.precommit.clone()),
+			&signed.id,
+			&signed.signature,
+			round,
+			set_id,
+		).is_err());
+		if bad_signature {
+			events.push((elapsed, format!(
+				"round {} of set {}: commit for {} has a bad signature",
+				round, set_id, target,
+			)));
+			return;
+		}
+
+		let chain = ReplayChain { client: &self.client, _phantom: PhantomData };
+		let outcome = match finality_grandpa::validate_commit(&commit, &voters, &chain) {
+			Ok(result) if result.ghost().is_some() => "",
+			Ok(_) => ", without a supermajority of precommits",
+			Err(_) => ", not a descendant of its precommits",
+		};
+		events.push((elapsed, format!(
+			"round {} of set {}: commit for {}{}",
+			round, set_id, target, outcome,
+		)));
+	}
+}
+
+impl<Block: BlockT, Client> ConsensusReplay<Block> for GrandpaReplay<Block, Client> where
+	Client: HeaderBackend<Block> + HeaderMetadata<Block, Error = sp_blockchain::Error>,
+	NumberFor<Block>: BlockNumberOps,
+{
+	fn advance(&mut self, elapsed: u64) -> Vec<(u64, String)> {
+		let mut events = Vec::new();
+		let gossip_duration = self.gossip_duration;
+
+		// fire the due timers in the order of their deadlines, and of the rounds.
+		loop {
+			let next = self.rounds.iter()
+				.filter_map(|(key, replayed)| replayed.next_timer.map(|timer|
+					(replayed.start + timer.gossip_durations() * gossip_duration, *key, timer)
+				))
+				.filter(|(deadline, _, _)| *deadline <= elapsed)
+				.min();
+			let (deadline, (set_id, round), timer) = match next {
+				Some(next) => next,
+				None => break,
+			};
+
+			let replayed = self.rounds.get_mut(&(set_id, round)).expect("found above; qed");
+			let state = replayed.round.state();
+			match timer {
+				Timer::Prevote => {
+					replayed.next_timer = Some(Timer::Precommit);
+					events.push((deadline, format!(
+						"round {} of set {}: prevote timer fired, prevote GHOST {}",
+						round, set_id, describe::<Block>(&state.prevote_ghost),
+					)));
+				},
+				Timer::Precommit => {
+					replayed.next_timer = None;
+					events.push((deadline, format!(
+						"round {} of set {}: precommit timer fired, estimate {}{}",
+						round, set_id, describe::<Block>(&state.estimate),
+						if state.completable { "" } else { ", not completable" },
+					)));
+				},
+			}
+		}
+
+		events
+	}
+
+	fn finalized(&mut self, elapsed: u64, hash: Block::Hash) -> Vec<(u64, String)> {
+		let mut events = self.advance(elapsed);
+		match self.client.number(hash) {
+			Ok(Some(number)) => {
+				if number > self.base().1 {
+					self.base = Some((hash, number));
+				}
+			},
+			_ => events.push((elapsed, format!("finalized block {} is not known", hash))),
+		}
+
+		events
+	}
+
+	fn gossip(
+		&mut self,
+		elapsed: u64,
+		engine_id: ConsensusEngineId,
+		message: &[u8],
+	) -> Vec<(u64, String)> {
+		let mut events = self.advance(elapsed);
+		if engine_id != GRANDPA_ENGINE_ID {
+			return events;
+		}
+
+		match decode_replayed_message::<Block>(message) {
+			Some(ReplayedMessage::Vote { round, set_id, message }) =>
+				self.vote(elapsed, round, set_id, message, &mut events),
+			Some(ReplayedMessage::Commit { round, set_id, commit }) =>
+				self.commit(elapsed, round, set_id, commit, &mut events),
+			Some(ReplayedMessage::Other) => {},
+			None => events.push((elapsed, "undecodable GRANDPA message".into())),
+		}
+
+		events
+	}
+}
+
+fn describe<Block: BlockT>(block: &Option<(Block::Hash, NumberFor<Block>)>) -> String {
+	match block {
+		Some((hash, number)) => format!("#{} ({})", number, hash),
+		None => "none".into(),
+	}
+}
@@ -50,6 +50,7 @@ use finality_proof::{
 	FinalityProofProvider, AuthoritySetForFinalityProver, AuthoritySetForFinalityChecker,
 };
 use consensus_changes::ConsensusChanges;
+use justification::SignatureCheckPool;
 use sc_block_builder::BlockBuilderProvider;
 use sc_consensus::LongestChain;
 
@@ -356,7 +357,9 @@ fn run_to_completion_with<F>(
 		let grandpa_params = GrandpaParams {
 			config: Config {
 				gossip_duration: TEST_GOSSIP_DURATION,
-				justification_period: 32,
+				justification_period: Some(32),
+				gossip_rebroadcast_period: None,
+				gossip_validation_workers: 0,
 				keystore: Some(keystore),
 				name: Some(format!("peer#{}", peer_id)),
 				is_authority: true,
@@ -438,6 +441,35 @@ fn finalize_3_voters_no_observers() {
 	);
 }
 
+#[test]
+fn voters_send_the_justifications_of_the_blocks_they_finalize() {
+	let mut runtime = Runtime::new().unwrap();
+	let peers = &[Ed25519Keyring::Alice, Ed25519Keyring::Bob, Ed25519Keyring::Charlie];
+	let voters = make_ids(peers);
+
+	let mut net = GrandpaTestNet::new(TestApi::new(voters.clone()), 3);
+	net.peer(0).push_blocks(20, false);
+	net.block_until_sync();
+
+	let mut justifications = net.peer(0).data.lock().as_ref()
+		.expect("link initialized at startup; qed")
+		.justification_stream()
+		.subscribe();
+
+	let net = Arc::new(Mutex::new(net));
+	run_to_completion(&mut runtime, 20, net.clone(), peers);
+
+	// the rounds finalizing the blocks up to #20 each sent a valid justification
+	let voters = VoterSet::new(voters).unwrap();
+	let mut highest = 0;
+	while let Ok(Some(justification)) = justifications.try_next() {
+		justification.verify(0, &voters).expect("justifications are valid");
+		assert!(justification.commit.target_number > highest);
+		highest = justification.commit.target_number;
+	}
+	assert_eq!(highest, 20);
+}
+
 #[test]
 fn finalize_3_voters_1_full_observer() {
 	let mut runtime = Runtime::new().unwrap();
@@ -488,7 +520,9 @@ fn finalize_3_voters_1_full_observer() {
 		let grandpa_params = GrandpaParams {
 			config: Config {
 				gossip_duration: TEST_GOSSIP_DURATION,
-				justification_period: 32,
+				justification_period: Some(32),
+				gossip_rebroadcast_period: None,
+				gossip_validation_workers: 0,
 				keystore,
 				name: Some(format!("peer#{}", peer_id)),
 				is_authority: true,
@@ -652,7 +686,9 @@ fn transition_3_voters_twice_1_full_observer() {
 		let grandpa_params = GrandpaParams {
 			config: Config {
 				gossip_duration: TEST_GOSSIP_DURATION,
-				justification_period: 32,
+				justification_period: Some(32),
+				gossip_rebroadcast_period: None,
+				gossip_validation_workers: 0,
 				keystore: Some(keystore),
 				name: Some(format!("peer#{}", peer_id)),
 				is_authority: true,
@@ -954,6 +990,171 @@ fn allows_reimporting_change_blocks() {
 	);
 }
 
+#[test]
+fn requests_periodic_justifications() {
+	let peers = &[Ed25519Keyring::Alice];
+	let api = TestApi::new(make_ids(peers));
+	let net = GrandpaTestNet::new(api.clone(), 1);
+
+	let (client, backend) = match net.peer(0).client().clone() {
+		PeersClient::Full(client, backend) => (client, backend),
+		PeersClient::Light(..) => unreachable!("only full clients are used in test"),
+	};
+	let (mut block_import, _) = block_import(
+		client.clone(),
+		&api,
+		LongestChain::new(backend),
+	).unwrap();
+	block_import.set_justification_request_period(Some(2));
+
+	let mut parent = BlockId::Number(0);
+	for number in 1..=4 {
+		let builder = client.new_block_at(&parent, Default::default(), false).unwrap();
+		let block = builder.build().unwrap().block;
+		parent = BlockId::Hash(block.hash());
+
+		let mut import = BlockImportParams::new(BlockOrigin::File, block.header);
+		import.body = Some(block.extrinsics);
+		import.fork_choice = Some(ForkChoiceStrategy::LongestChain);
+
+		let needs_justification = match block_import.import_block(import, HashMap::new()).unwrap() {
+			ImportResult::Imported(aux) => aux.needs_justification,
+			result => panic!("Unexpected import result: {:?}", result),
+		};
+		assert_eq!(needs_justification, number % 2 == 0);
+	}
+}
+
+#[test]
+fn justifications_are_checked_before_importing_the_blocks() {
+	use sp_consensus::JustificationCheck;
+
+	let peers_a = &[Ed25519Keyring::Alice];
+	let peers_b = &[Ed25519Keyring::Bob];
+	let api = TestApi::new(make_ids(peers_a));
+	let net = GrandpaTestNet::new(api.clone(), 1);
+
+	let (client, backend) = match net.peer(0).client().clone() {
+		PeersClient::Full(client, backend) => (client, backend),
+		PeersClient::Light(..) => unreachable!("only full clients are used in test"),
+	};
+	let (block_import, _) = block_import(
+		client.clone(),
+		&api,
+		LongestChain::new(backend),
+	).unwrap();
+
+	let justification = |signer: Ed25519Keyring, set_id, header: &<Block as BlockT>::Header| {
+		let precommit = finality_grandpa::Precommit {
+			target_hash: header.hash(),
+			target_number: *header.number(),
+		};
+		let msg = finality_grandpa::Message::Precommit(precommit.clone());
+		let encoded = sp_finality_grandpa::localized_payload(1, set_id, &msg);
+		let commit = finality_grandpa::Commit {
+			target_hash: header.hash(),
+			target_number: *header.number(),
+			precommits: vec![finality_grandpa::SignedPrecommit {
+				precommit,
+				signature: signer.sign(&encoded[..]).into(),
+				id: signer.public().into(),
+			}],
+		};
+		GrandpaJustification::from_commit(&client, 1, commit).unwrap().encode()
+	};
+
+	// the first block hands the authority set over to Bob
+	let builder = client.new_block_at(&BlockId::Number(0), Default::default(), false).unwrap();
+	let mut block = builder.build().unwrap().block;
+	add_scheduled_change(&mut block, ScheduledChange {
+		next_authorities: make_ids(peers_b),
+		delay: 0,
+	});
+	let first = block.header;
+	let second = <Block as BlockT>::Header::new(
+		2,
+		Default::default(),
+		Default::default(),
+		first.hash(),
+		Default::default(),
+	);
+
+	// the change is only enacted with a justification
+	assert!(block_import.justification_check().check_block(&first, None).is_err());
+	assert!(block_import.justification_check()
+		.check_block(&first, Some(&justification(Ed25519Keyring::Bob, 0, &first)))
+		.is_err());
+
+	let mut check = block_import.justification_check();
+	check.check_block(&first, Some(&justification(Ed25519Keyring::Alice, 0, &first))).unwrap();
+	// the next blocks are justified by the new set
+	assert!(check.check_block(&second, Some(&justification(Ed25519Keyring::Alice, 0, &second)))
+		.is_err());
+	check.check_block(&second, Some(&justification(Ed25519Keyring::Bob, 1, &second))).unwrap();
+
+	// nothing was imported
+	assert_eq!(client.info().best_number, 0);
+}
+
+#[test]
+fn justification_signatures_are_checked_on_workers() {
+	let peers = &[Ed25519Keyring::Alice, Ed25519Keyring::Bob, Ed25519Keyring::Charlie];
+	let voters = VoterSet::new(make_ids(peers).into_iter()).unwrap();
+	let target = (H256::repeat_byte(1), 1);
+	let client = Arc::new(substrate_test_runtime_client::new());
+
+	let justification = |signers: &[Ed25519Keyring]| {
+		let precommit = finality_grandpa::Precommit { target_hash: target.0, target_number: target.1 };
+		let msg = finality_grandpa::Message::Precommit(precommit.clone());
+		let encoded = sp_finality_grandpa::localized_payload(1, 0, &msg);
+		let commit = finality_grandpa::Commit {
+			target_hash: target.0,
+			target_number: target.1,
+			precommits: peers.iter().zip(signers)
+				.map(|(id, signer)| finality_grandpa::SignedPrecommit {
+					precommit: precommit.clone(),
+					signature: signer.sign(&encoded[..]).into(),
+					id: id.public().into(),
+				})
+				.collect(),
+		};
+		GrandpaJustification::<Block>::from_commit(&client, 1, commit).unwrap().encode()
+	};
+
+	let valid = justification(peers);
+	// the last precommit isn't signed by its voter
+	let invalid = justification(&[Ed25519Keyring::Alice, Ed25519Keyring::Bob, Ed25519Keyring::Alice]);
+
+	GrandpaJustification::<Block>::decode_and_verify_finalizes(&valid, target, 0, &voters).unwrap();
+	assert!(
+		GrandpaJustification::<Block>::decode_and_verify_finalizes(&invalid, target, 0, &voters)
+			.is_err(),
+	);
+
+	for &workers in &[1, 2, 3, 8] {
+		let pool = SignatureCheckPool::new(workers);
+		// the same threads check the justifications one after the other
+		for _ in 0..2 {
+			GrandpaJustification::<Block>::decode_and_verify_finalizes_with(
+				&valid,
+				target,
+				0,
+				&voters,
+				Some(&pool),
+			).unwrap();
+			assert!(
+				GrandpaJustification::<Block>::decode_and_verify_finalizes_with(
+					&invalid,
+					target,
+					0,
+					&voters,
+					Some(&pool),
+				).is_err(),
+			);
+		}
+	}
+}
+
 #[test]
 fn test_bad_justification() {
 	let peers_a = &[Ed25519Keyring::Alice, Ed25519Keyring::Bob, Ed25519Keyring::Charlie];
@@ -1077,7 +1278,9 @@ fn voter_persists_its_votes() {
 						let grandpa_params = GrandpaParams {
 							config: Config {
 								gossip_duration: TEST_GOSSIP_DURATION,
-								justification_period: 32,
+								justification_period: Some(32),
+								gossip_rebroadcast_period: None,
+								gossip_validation_workers: 0,
 								keystore: Some(this.keystore.clone()),
 								name: Some(format!("peer#{}", 0)),
 								is_authority: true,
@@ -1135,7 +1338,9 @@ fn voter_persists_its_votes() {
 
 		let config = Config {
 			gossip_duration: TEST_GOSSIP_DURATION,
-			justification_period: 32,
+			justification_period: Some(32),
+			gossip_rebroadcast_period: None,
+			gossip_validation_workers: 0,
 			keystore: Some(keystore),
 			name: Some(format!("peer#{}", 1)),
 			is_authority: true,
@@ -1295,7 +1500,9 @@ fn finalize_3_voters_1_light_observer() {
 			observer::run_grandpa_observer(
 				Config {
 					gossip_duration: TEST_GOSSIP_DURATION,
-					justification_period: 32,
+					justification_period: Some(32),
+					gossip_rebroadcast_period: None,
+					gossip_validation_workers: 0,
 					keystore: None,
 					name: Some("observer".to_string()),
 					is_authority: false,
@@ -1423,7 +1630,9 @@ fn voter_catches_up_to_latest_round_when_behind() {
 		let grandpa_params = GrandpaParams {
 			config: Config {
 				gossip_duration: TEST_GOSSIP_DURATION,
-				justification_period: 32,
+				justification_period: Some(32),
+				gossip_rebroadcast_period: None,
+				gossip_validation_workers: 0,
 				keystore,
 				name: Some(format!("peer#{}", peer_id)),
 				is_authority: true,
@@ -1543,7 +1752,9 @@ fn grandpa_environment_respects_voting_rules() {
 
 		let config = Config {
 			gossip_duration: TEST_GOSSIP_DURATION,
-			justification_period: 32,
+			justification_period: Some(32),
+			gossip_rebroadcast_period: None,
+			gossip_validation_workers: 0,
 			keystore: None,
 			name: None,
 			is_authority: true,
@@ -1569,6 +1780,7 @@ fn grandpa_environment_respects_voting_rules() {
 			network,
 			voting_rule,
 			metrics: None,
+			justification_sender: None,
 			_phantom: PhantomData,
 		}
 	};
@@ -1659,9 +1871,10 @@ fn imports_justification_for_regular_blocks_on_import() {
 	let mut net = GrandpaTestNet::new(api.clone(), 1);
 
 	let client = net.peer(0).client().clone();
-	let (mut block_import, ..) = net.make_block_import::<
+	let (mut block_import, _, _, _, link) = net.make_block_import::<
 		TransactionFor<substrate_test_runtime_client::Backend, Block>
 	>(client.clone());
+	let mut justifications = link.lock().as_ref().unwrap().justification_stream().subscribe();
 
 	let full_client = client.as_full().expect("only full clients are used in test");
 	let builder = full_client.new_block_at(&BlockId::Number(0), Default::default(), false).unwrap();
@@ -1723,4 +1936,11 @@ fn imports_justification_for_regular_blocks_on_import() {
 	assert!(
 		client.justification(&BlockId::Hash(block_hash)).unwrap().is_some(),
 	);
+
+	// and sent to the subscribers of the justification notifications
+	let notified = justifications.try_next().unwrap().unwrap();
+	assert_eq!(
+		Some(notified.encode()),
+		client.justification(&BlockId::Hash(block_hash)).unwrap(),
+	);
 }
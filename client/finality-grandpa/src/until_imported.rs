@@ -31,7 +31,6 @@ use super::{
 };
 
 use log::{debug, warn};
-use sp_utils::mpsc::TracingUnboundedReceiver;
 use futures::prelude::*;
 use futures::stream::{Fuse, StreamExt};
 use futures_timer::Delay;
@@ -40,7 +39,7 @@ use parking_lot::Mutex;
 use prometheus_endpoint::{
 	Gauge, U64, PrometheusError, register, Registry,
 };
-use sc_client_api::{BlockImportNotification, ImportNotifications};
+use sc_client_api::ImportNotifications;
 use sp_finality_grandpa::AuthorityId;
 use sp_runtime::traits::{Block as BlockT, Header as HeaderT, NumberFor};
 
@@ -145,7 +144,7 @@ pub(crate) struct UntilImported<Block, BlockStatus, BlockSyncRequester, I, M> wh
 	I: Stream<Item = M::Blocked> + Unpin,
 	M: BlockUntilImported<Block>,
 {
-	import_notifications: Fuse<TracingUnboundedReceiver<BlockImportNotification<Block>>>,
+	import_notifications: Fuse<ImportNotifications<Block>>,
 	block_sync_requester: BlockSyncRequester,
 	status_check: BlockStatus,
 	incoming_messages: Fuse<I>,
@@ -548,27 +547,28 @@ mod tests {
 	use crate::{CatchUp, CompactCommit};
 	use substrate_test_runtime_client::runtime::{Block, Hash, Header};
 	use sp_consensus::BlockOrigin;
-	use sc_client_api::BlockImportNotification;
+	use sc_client_api::{BlockImportNotification, NotificationSinks};
 	use futures::future::Either;
 	use futures_timer::Delay;
-	use sp_utils::mpsc::{tracing_unbounded, TracingUnboundedSender};
+	use sp_utils::mpsc::tracing_unbounded;
 	use finality_grandpa::Precommit;
 
 	#[derive(Clone)]
 	struct TestChainState {
-		sender: TracingUnboundedSender<BlockImportNotification<Block>>,
+		sinks: Arc<NotificationSinks<BlockImportNotification<Block>>>,
 		known_blocks: Arc<Mutex<HashMap<Hash, u64>>>,
 	}
 
 	impl TestChainState {
 		fn new() -> (Self, ImportNotifications<Block>) {
-			let (tx, rx) = tracing_unbounded("test");
+			let sinks = NotificationSinks::new("test", Default::default(), None);
+			let notifications = sinks.subscribe();
 			let state = TestChainState {
-				sender: tx,
+				sinks: Arc::new(sinks),
 				known_blocks: Arc::new(Mutex::new(HashMap::new())),
 			};
 
-			(state, rx)
+			(state, notifications)
 		}
 
 		fn block_status(&self) -> TestBlockStatus {
@@ -580,13 +580,13 @@ mod tests {
 			let number = header.number().clone();
 
 			self.known_blocks.lock().insert(hash, number);
-			self.sender.unbounded_send(BlockImportNotification {
+			self.sinks.notify(BlockImportNotification {
 				hash,
 				origin: BlockOrigin::File,
 				header,
 				is_new_best: false,
 				retracted: vec![],
-			}).unwrap();
+			});
 		}
 	}
 
@@ -17,7 +17,10 @@
 //! Keystore (and session key management) for ed25519 based chains like Polkadot.
 
 #![warn(missing_docs)]
-use std::{collections::{HashMap, HashSet}, path::PathBuf, fs::{self, File}, io::{self, Write}, sync::Arc};
+use std::{
+	collections::{HashMap, HashSet}, path::{Path, PathBuf}, fs::{self, File}, io::{self, Write},
+	sync::Arc,
+};
 use sp_core::{
 	crypto::{IsWrappedBy, CryptoTypePublicPair, KeyTypeId, Pair as PairT, Protected, Public},
 	traits::{BareCryptoStore, BareCryptoStoreError as TraitError},
@@ -103,6 +106,7 @@ impl Store {
 	pub fn open<T: Into<PathBuf>>(path: T, password: Option<Protected<String>>) -> Result<KeyStorePtr> {
 		let path = path.into();
 		fs::create_dir_all(&path)?;
+		recover_key_files(&path)?;
 
 		let instance = Self { path: Some(path), additional: HashMap::new(), password };
 		Ok(Arc::new(RwLock::new(instance)))
@@ -140,9 +144,7 @@ impl Store {
 	/// Places it into the file system store.
 	fn insert_unknown(&self, key_type: KeyTypeId, suri: &str, public: &[u8]) -> Result<()> {
 		if let Some(path) = self.key_file_path(public, key_type) {
-			let mut file = File::create(path).map_err(Error::Io)?;
-			serde_json::to_writer(&file, &suri).map_err(Error::Json)?;
-			file.flush().map_err(Error::Io)?;
+			write_key_file(&path, suri)?;
 		}
 		Ok(())
 	}
@@ -173,9 +175,7 @@ impl Store {
 	pub fn generate_by_type<Pair: PairT>(&self, key_type: KeyTypeId) -> Result<Pair> {
 		let (pair, phrase, _) = Pair::generate_with_phrase(self.password.as_ref().map(|p| &***p));
 		if let Some(path) = self.key_file_path(pair.public().as_slice(), key_type) {
-			let mut file = File::create(path)?;
-			serde_json::to_writer(&file, &phrase)?;
-			file.flush()?;
+			write_key_file(&path, &phrase)?;
 		}
 		Ok(pair)
 	}
@@ -298,6 +298,70 @@ impl Store {
 	}
 }
 
+/// Write the phrase of a key to its file.
+///
+/// The phrase is written and synced to a temporary file first, which then replaces the key file,
+/// so that a crash never leaves a partially written key file.
+fn write_key_file(path: &Path, phrase: &str) -> Result<()> {
+	let tmp_path = path.with_extension("tmp");
+	let mut file = create_key_file(&tmp_path)?;
+	serde_json::to_writer(&file, phrase)?;
+	file.flush()?;
+	file.sync_all()?;
+	fs::rename(&tmp_path, path)?;
+	#[cfg(unix)]
+	{
+		if let Some(dir) = path.parent() {
+			File::open(dir)?.sync_all()?;
+		}
+	}
+	Ok(())
+}
+
+/// Create or truncate a key file, only readable and writable by its owner.
+#[cfg(unix)]
+fn create_key_file(path: &Path) -> io::Result<File> {
+	use std::os::unix::fs::OpenOptionsExt;
+	fs::OpenOptions::new()
+		.write(true)
+		.create(true)
+		.truncate(true)
+		.mode(0o600)
+		.open(path)
+}
+
+/// Create or truncate a key file.
+#[cfg(not(unix))]
+fn create_key_file(path: &Path) -> io::Result<File> {
+	File::create(path)
+}
+
+/// Remove the temporary files left by interrupted writes, and move the key files that can't be
+/// read, e.g. truncated by a power loss, to `<file>.corrupted`.
+fn recover_key_files(path: &Path) -> Result<()> {
+	for entry in fs::read_dir(path)? {
+		let file = entry?.path();
+		let name = match file.file_name().and_then(|n| n.to_str()) {
+			Some(name) if file.is_file() => name,
+			_ => continue,
+		};
+		if name.ends_with(".tmp") {
+			fs::remove_file(&file)?;
+		} else if hex::decode(name).is_ok() &&
+			serde_json::from_slice::<String>(&fs::read(&file)?).is_err()
+		{
+			let corrupted = file.with_extension("corrupted");
+			log::error!(
+				"Key file {} is corrupted and was moved to {}, the key has to be inserted again",
+				file.display(),
+				corrupted.display(),
+			);
+			fs::rename(&file, &corrupted)?;
+		}
+	}
+	Ok(())
+}
+
 impl BareCryptoStore for Store {
 	fn keys(
 		&self,
@@ -461,6 +525,37 @@ mod tests {
 		assert_eq!(store.read().public_keys::<ed25519::AppPublic>().unwrap()[0], key.public());
 	}
 
+	#[test]
+	fn corrupted_key_files_are_moved_aside() {
+		let temp_dir = TempDir::new().unwrap();
+		let store = Store::open(temp_dir.path(), None).unwrap();
+		let key: ed25519::AppPair = store.write().generate().unwrap();
+		drop(store);
+
+		let key_file = fs::read_dir(temp_dir.path()).unwrap().next().unwrap().unwrap().path();
+		fs::write(&key_file, b"").unwrap();
+		fs::write(key_file.with_extension("tmp"), b"\"partial").unwrap();
+
+		let store = Store::open(temp_dir.path(), None).unwrap();
+		assert!(store.read().public_keys::<ed25519::AppPublic>().unwrap().is_empty());
+		assert!(store.read().key_pair::<ed25519::AppPair>(&key.public()).is_err());
+		assert!(key_file.with_extension("corrupted").is_file());
+		assert!(!key_file.with_extension("tmp").exists());
+	}
+
+	#[cfg(unix)]
+	#[test]
+	fn key_files_are_only_readable_by_their_owner() {
+		use std::os::unix::fs::PermissionsExt;
+
+		let temp_dir = TempDir::new().unwrap();
+		let store = Store::open(temp_dir.path(), None).unwrap();
+		let _: ed25519::AppPair = store.write().generate().unwrap();
+
+		let key_file = fs::read_dir(temp_dir.path()).unwrap().next().unwrap().unwrap().path();
+		assert_eq!(fs::metadata(key_file).unwrap().permissions().mode() & 0o777, 0o600);
+	}
+
 	#[test]
 	fn test_insert_ephemeral_from_seed() {
 		let temp_dir = TempDir::new().unwrap();
@@ -16,6 +16,7 @@
 
 use crate::{Network, Validator};
 use crate::state_machine::{ConsensusGossip, TopicNotification, PERIODIC_MAINTENANCE_INTERVAL};
+use crate::validation_pool::{self, PreValidated, ValidationPool};
 
 use sc_network::{Event, ReputationChange};
 
@@ -23,10 +24,10 @@ use futures::prelude::*;
 use futures::channel::mpsc::{channel, Sender, Receiver};
 use libp2p::PeerId;
 use log::trace;
-use sp_runtime::{traits::Block as BlockT, ConsensusEngineId};
+use sp_runtime::{traits::{Block as BlockT, Hash, HashFor}, ConsensusEngineId};
 use std::{
 	borrow::Cow,
-	collections::{HashMap, VecDeque},
+	collections::{HashMap, HashSet, VecDeque},
 	pin::Pin,
 	sync::Arc,
 	task::{Context, Poll},
@@ -36,6 +37,9 @@ use std::{
 /// top of it.
 pub struct GossipEngine<B: BlockT> {
 	state_machine: ConsensusGossip<B>,
+	validator: Arc<dyn Validator<B>>,
+	/// Pool pre-validating incoming messages, if they aren't pre-validated by the engine itself.
+	validation_pool: Option<ValidationPool>,
 	network: Box<dyn Network<B> + Send>,
 	periodic_maintenance_interval: futures_timer::Delay,
 	engine_id: ConsensusEngineId,
@@ -78,7 +82,9 @@ impl<B: BlockT> GossipEngine<B> {
 		network.register_notifications_protocol(engine_id, protocol_name.into());
 
 		GossipEngine {
-			state_machine: ConsensusGossip::new(validator, engine_id),
+			state_machine: ConsensusGossip::new(validator.clone(), engine_id),
+			validator,
+			validation_pool: None,
 			network: Box::new(network),
 			periodic_maintenance_interval: futures_timer::Delay::new(PERIODIC_MAINTENANCE_INTERVAL),
 			engine_id,
@@ -89,6 +95,26 @@ impl<B: BlockT> GossipEngine<B> {
 		}
 	}
 
+	/// Change the interval at which all known messages are rebroadcast to all
+	/// peers. Defaults to 30 seconds.
+	pub fn set_rebroadcast_interval(&mut self, interval: std::time::Duration) {
+		self.state_machine.set_rebroadcast_interval(interval);
+	}
+
+	/// Pre-validate incoming messages (see [`Validator::pre_validate`]) on a pool of `workers`
+	/// threads rather than in the task of the engine, or in the task of the engine if `workers` is
+	/// 0, which is the default.
+	///
+	/// Incoming messages are queued for the pool, and the engine stops reading messages from the
+	/// network while the queue is full.
+	pub fn set_validation_workers(&mut self, workers: usize) where B: 'static {
+		self.validation_pool = if workers == 0 {
+			None
+		} else {
+			Some(ValidationPool::new(self.validator.clone(), workers))
+		};
+	}
+
 	pub fn report(&self, who: PeerId, reputation: ReputationChange) {
 		self.network.report_peer(who, reputation);
 	}
@@ -170,6 +196,22 @@ impl<B: BlockT> GossipEngine<B> {
 	}
 }
 
+impl<B: BlockT> GossipEngine<B> {
+	/// Report the sender of the messages that didn't pass the pre-validation, and validate the
+	/// others. Returns the messages to forward to the upper layers.
+	fn on_pre_validated(
+		&mut self,
+		pre_validated: PreValidated,
+	) -> Vec<(B::Hash, TopicNotification)> {
+		let PreValidated { sender, messages, reports } = pre_validated;
+		for report in reports {
+			self.network.report_peer(sender.clone(), report);
+		}
+
+		self.state_machine.on_incoming(&mut *self.network, sender, messages)
+	}
+}
+
 impl<B: BlockT> Future for GossipEngine<B> {
 	type Output = ();
 
@@ -179,6 +221,17 @@ impl<B: BlockT> Future for GossipEngine<B> {
 		'outer: loop {
 			match &mut this.forwarding_state {
 				ForwardingState::Idle => {
+					if let Some(pool) = &mut this.validation_pool {
+						if let Poll::Ready(Some(pre_validated)) = pool.poll_next(cx) {
+							let to_forward = this.on_pre_validated(pre_validated);
+							this.forwarding_state = ForwardingState::Busy(to_forward.into());
+							continue;
+						}
+						if pool.poll_ready(cx).is_pending() {
+							break;
+						}
+					}
+
 					match this.network_event_stream.poll_next_unpin(cx) {
 						Poll::Ready(Some(event)) => match event {
 							Event::NotificationStreamOpened { remote, engine_id, role } => {
@@ -194,20 +247,40 @@ impl<B: BlockT> Future for GossipEngine<B> {
 								this.state_machine.peer_disconnected(&mut *this.network, remote);
 							},
 							Event::NotificationsReceived { remote, messages } => {
-								let messages = messages.into_iter().filter_map(|(engine, data)| {
-									if engine == this.engine_id {
-										Some(data.to_vec())
-									} else {
-										None
-									}
-								}).collect();
-
-								let to_forward = this.state_machine.on_incoming(
+								// The copies of a message in the same batch are dropped, so
+								// that it is only pre-validated once.
+								let mut batch = HashSet::new();
+								let (known, unknown) = messages.into_iter()
+									.filter_map(|(engine, data)| {
+										if engine == this.engine_id {
+											Some(data.to_vec())
+										} else {
+											None
+										}
+									})
+									.filter(|message| batch.insert(HashFor::<B>::hash(message)))
+									.partition::<Vec<_>, _>(|message| {
+										this.state_machine.is_known(message)
+									});
+
+								// Only reports the duplicates, no need to pre-validate them.
+								this.state_machine.on_incoming(
 									&mut *this.network,
-									remote,
-									messages,
+									remote.clone(),
+									known,
 								);
 
+								if let Some(pool) = &mut this.validation_pool {
+									pool.queue(remote, unknown);
+									continue;
+								}
+
+								let pre_validated = validation_pool::pre_validate(
+									&*this.validator,
+									remote,
+									unknown,
+								);
+								let to_forward = this.on_pre_validated(pre_validated);
 								this.forwarding_state = ForwardingState::Busy(to_forward.into());
 							},
 							Event::Dht(_) => {}
@@ -65,6 +65,7 @@ use std::{borrow::Cow, pin::Pin, sync::Arc};
 
 mod bridge;
 mod state_machine;
+mod validation_pool;
 mod validator;
 
 /// Abstraction over a network.
@@ -151,6 +151,7 @@ pub struct ConsensusGossip<B: BlockT> {
 	engine_id: ConsensusEngineId,
 	validator: Arc<dyn Validator<B>>,
 	next_broadcast: Instant,
+	rebroadcast_interval: time::Duration,
 }
 
 impl<B: BlockT> ConsensusGossip<B> {
@@ -163,9 +164,16 @@ impl<B: BlockT> ConsensusGossip<B> {
 			engine_id,
 			validator,
 			next_broadcast: Instant::now() + REBROADCAST_INTERVAL,
+			rebroadcast_interval: REBROADCAST_INTERVAL,
 		}
 	}
 
+	/// Change the interval at which all known messages are rebroadcast to all peers.
+	pub fn set_rebroadcast_interval(&mut self, interval: time::Duration) {
+		self.rebroadcast_interval = interval;
+		self.next_broadcast = Instant::now() + interval;
+	}
+
 	/// Handle new connected peer.
 	pub fn new_peer(&mut self, network: &mut dyn Network<B>, who: PeerId, role: ObservedRole) {
 		// light nodes are not valid targets for consensus gossip messages
@@ -227,7 +235,7 @@ impl<B: BlockT> ConsensusGossip<B> {
 		self.collect_garbage();
 		if Instant::now() >= self.next_broadcast {
 			self.rebroadcast(network);
-			self.next_broadcast = Instant::now() + REBROADCAST_INTERVAL;
+			self.next_broadcast = Instant::now() + self.rebroadcast_interval;
 		}
 	}
 
@@ -278,6 +286,11 @@ impl<B: BlockT> ConsensusGossip<B> {
 		})
 	}
 
+	/// Returns whether the given message was already received or sent.
+	pub fn is_known(&self, message: &[u8]) -> bool {
+		self.known_messages.contains(&HashFor::<B>::hash(message))
+	}
+
 	/// Register incoming messages and return the ones that are new and valid (according to a gossip
 	/// validator) and should thus be forwarded to the upper layers.
 	pub fn on_incoming(
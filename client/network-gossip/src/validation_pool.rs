@@ -0,0 +1,177 @@
+// Copyright 2019-2020 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+
+//! Pool of threads running [`Validator::pre_validate`] on incoming messages, so that checking
+//! their signatures doesn't delay the task driving the gossip engine.
+
+use crate::Validator;
+
+use futures::{executor::block_on, prelude::*, channel::mpsc};
+use libp2p::PeerId;
+use log::{error, trace};
+use sc_network::ReputationChange;
+use sp_runtime::traits::Block as BlockT;
+use std::{pin::Pin, sync::{Arc, Mutex}, task::{Context, Poll}, thread};
+
+/// Number of batches of messages that can be queued per worker. Once the queue is full, the
+/// gossip engine stops reading messages from the network until the workers catch up.
+const QUEUE_SIZE_PER_WORKER: usize = 16;
+
+/// Messages received from a peer in a single notification.
+type Batch = (PeerId, Vec<Vec<u8>>);
+
+/// Outcome of the pre-validation of a batch of messages.
+pub(crate) struct PreValidated {
+	/// Peer that sent the messages.
+	pub sender: PeerId,
+	/// Messages that passed the pre-validation, in the order they were received.
+	pub messages: Vec<Vec<u8>>,
+	/// Reputation changes of the sender for the messages that didn't.
+	pub reports: Vec<ReputationChange>,
+}
+
+/// Run [`Validator::pre_validate`] on a batch of messages.
+pub(crate) fn pre_validate<B: BlockT>(
+	validator: &dyn Validator<B>,
+	sender: PeerId,
+	messages: Vec<Vec<u8>>,
+) -> PreValidated {
+	let mut reports = Vec::new();
+	let messages = messages.into_iter()
+		.filter(|message| match validator.pre_validate(&sender, message) {
+			Ok(()) => true,
+			Err(report) => {
+				trace!(target: "gossip", "Discarded message from {}: {}", sender, report.reason);
+				reports.push(report);
+				false
+			},
+		})
+		.collect();
+
+	PreValidated { sender, messages, reports }
+}
+
+/// Threads pre-validating batches of messages.
+///
+/// Batches are handed out to the first idle worker, so batches from different peers may come out
+/// in a different order than they went in. The messages of a batch stay in order.
+pub(crate) struct ValidationPool {
+	batches: mpsc::Sender<Batch>,
+	// Unbounded because the engine drains it before queueing new batches, so it never holds more
+	// than the number of queued batches.
+	results: mpsc::UnboundedReceiver<PreValidated>,
+}
+
+impl ValidationPool {
+	/// Spawn `workers` threads pre-validating messages with `validator`. The threads stop once
+	/// the pool is dropped.
+	pub(crate) fn new<B: BlockT>(validator: Arc<dyn Validator<B>>, workers: usize) -> Self {
+		let (batches, batches_rx) = mpsc::channel(workers * QUEUE_SIZE_PER_WORKER);
+		let (results_tx, results) = mpsc::unbounded();
+		let batches_rx = Arc::new(Mutex::new(batches_rx));
+
+		for index in 0..workers {
+			let validator = validator.clone();
+			let batches_rx = batches_rx.clone();
+			let results_tx = results_tx.clone();
+			let spawned = thread::Builder::new()
+				.name(format!("gossip-validation-{}", index))
+				.spawn(move || loop {
+					let batch = {
+						let mut batches_rx = batches_rx.lock().unwrap_or_else(|e| e.into_inner());
+						block_on(batches_rx.next())
+					};
+					let (sender, messages) = match batch {
+						Some(batch) => batch,
+						None => break,
+					};
+					let result = pre_validate(&*validator, sender, messages);
+					if results_tx.unbounded_send(result).is_err() {
+						break;
+					}
+				});
+
+			if let Err(e) = spawned {
+				error!(target: "gossip", "Failed to spawn a gossip validation worker: {}", e);
+			}
+		}
+
+		ValidationPool { batches, results }
+	}
+
+	/// Returns `Poll::Ready` once a batch can be queued with [`ValidationPool::queue`].
+	pub(crate) fn poll_ready(&mut self, cx: &mut Context) -> Poll<()> {
+		// An error means the workers are gone, `queue` then drops the batches.
+		self.batches.poll_ready(cx).map(|_| ())
+	}
+
+	/// Queue a batch of messages for pre-validation. Must only be called after
+	/// [`ValidationPool::poll_ready`] returned `Poll::Ready`.
+	pub(crate) fn queue(&mut self, sender: PeerId, messages: Vec<Vec<u8>>) {
+		if let Err(e) = self.batches.start_send((sender, messages)) {
+			error!(target: "gossip", "Dropped incoming gossip messages: {}", e);
+		}
+	}
+
+	/// Poll the next pre-validated batch.
+	pub(crate) fn poll_next(&mut self, cx: &mut Context) -> Poll<Option<PreValidated>> {
+		Pin::new(&mut self.results).poll_next(cx)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{ValidationResult, ValidatorContext};
+	use futures::future::poll_fn;
+	use substrate_test_runtime_client::runtime::Block;
+
+	struct RejectOdd;
+
+	impl Validator<Block> for RejectOdd {
+		fn pre_validate(&self, _sender: &PeerId, data: &[u8]) -> Result<(), ReputationChange> {
+			if data[0] % 2 == 1 {
+				Err(ReputationChange::new(-1, "Odd message"))
+			} else {
+				Ok(())
+			}
+		}
+
+		fn validate(
+			&self,
+			_context: &mut dyn ValidatorContext<Block>,
+			_sender: &PeerId,
+			_data: &[u8],
+		) -> ValidationResult<<Block as BlockT>::Hash> {
+			ValidationResult::ProcessAndKeep(Default::default())
+		}
+	}
+
+	#[test]
+	fn pre_validates_batches_on_workers() {
+		let mut pool = ValidationPool::new::<Block>(Arc::new(RejectOdd), 2);
+		let sender = PeerId::random();
+
+		block_on(poll_fn(|cx| pool.poll_ready(cx)));
+		pool.queue(sender.clone(), vec![vec![1], vec![2], vec![3], vec![4]]);
+
+		let pre_validated = block_on(poll_fn(|cx| pool.poll_next(cx))).unwrap();
+		assert_eq!(pre_validated.sender, sender);
+		assert_eq!(pre_validated.messages, vec![vec![2], vec![4]]);
+		assert_eq!(pre_validated.reports.len(), 2);
+	}
+}
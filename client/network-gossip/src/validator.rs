@@ -16,7 +16,7 @@
 // You should have received a copy of the GNU General Public License
 // along with this program. If not, see <https://www.gnu.org/licenses/>.
 
-use sc_network::{ObservedRole, PeerId};
+use sc_network::{ObservedRole, PeerId, ReputationChange};
 use sp_runtime::traits::Block as BlockT;
 
 /// Validates consensus messages.
@@ -29,7 +29,19 @@ pub trait Validator<B: BlockT>: Send + Sync {
 	fn peer_disconnected(&self, _context: &mut dyn ValidatorContext<B>, _who: &PeerId) {
 	}
 
+	/// Check the parts of a message that don't depend on the other messages, typically its
+	/// signature, before it is passed to [`Validator::validate`]. Messages for which this returns
+	/// an error are discarded, and their sender is reported with the given reputation change.
+	///
+	/// This may be called on a thread of the validation pool of the gossip engine, concurrently
+	/// with the other methods.
+	fn pre_validate(&self, _sender: &PeerId, _data: &[u8]) -> Result<(), ReputationChange> {
+		Ok(())
+	}
+
 	/// Validate consensus message.
+	///
+	/// Only called for messages that passed [`Validator::pre_validate`].
 	fn validate(
 		&self,
 		context: &mut dyn ValidatorContext<B>,
@@ -16,12 +16,13 @@
 
 use crate::{
 	config::{ProtocolId, Role}, block_requests, light_client_handler, finality_requests,
-	debug_info, discovery::{DiscoveryBehaviour, DiscoveryConfig, DiscoveryOut},
+	request_responses, debug_info, discovery::{DiscoveryBehaviour, DiscoveryConfig, DiscoveryOut},
 	protocol::{message::{self, Roles}, CustomMessageOutcome, Protocol},
 	Event, ObservedRole, DhtEvent, ExHashT,
 };
 
 use codec::Encode as _;
+use futures::channel::oneshot;
 use libp2p::NetworkBehaviour;
 use libp2p::core::{Multiaddr, PeerId, PublicKey};
 use libp2p::kad::record;
@@ -48,6 +49,8 @@ pub struct Behaviour<B: BlockT, H: ExHashT> {
 	finality_proof_requests: finality_requests::FinalityProofRequests<B>,
 	/// Light client request handling.
 	light_client_handler: light_client_handler::LightClientHandler<B>,
+	/// Request-response protocols registered through the configuration.
+	request_responses: request_responses::RequestResponsesBehaviour,
 
 	/// Queue of events to produce for the outside.
 	#[behaviour(ignore)]
@@ -109,6 +112,7 @@ impl<B: BlockT, H: ExHashT> Behaviour<B, H> {
 		block_requests: block_requests::BlockRequests<B>,
 		finality_proof_requests: finality_requests::FinalityProofRequests<B>,
 		light_client_handler: light_client_handler::LightClientHandler<B>,
+		request_responses: request_responses::RequestResponsesBehaviour,
 		disco_config: DiscoveryConfig,
 	) -> Self {
 		Behaviour {
@@ -118,6 +122,7 @@ impl<B: BlockT, H: ExHashT> Behaviour<B, H> {
 			block_requests,
 			finality_proof_requests,
 			light_client_handler,
+			request_responses,
 			events: VecDeque::new(),
 			role,
 		}
@@ -157,6 +162,11 @@ impl<B: BlockT, H: ExHashT> Behaviour<B, H> {
 		self.debug_info.node(peer_id)
 	}
 
+	/// Returns the addresses of the local node that remote peers have recently reached us on.
+	pub fn confirmed_addresses(&self) -> impl Iterator<Item = &Multiaddr> {
+		self.debug_info.confirmed_addresses()
+	}
+
 	/// Registers a new notifications protocol.
 	///
 	/// After that, you can call `write_notifications`.
@@ -211,6 +221,18 @@ impl<B: BlockT, H: ExHashT> Behaviour<B, H> {
 	pub fn light_client_request(&mut self, r: light_client_handler::Request<B>) -> Result<(), light_client_handler::Error> {
 		self.light_client_handler.request(r)
 	}
+
+	/// Sends a request on one of the request-response protocols registered through the
+	/// configuration. The outcome is sent to `pending_response`.
+	pub fn send_request(
+		&mut self,
+		target: &PeerId,
+		protocol: &str,
+		request: Vec<u8>,
+		pending_response: oneshot::Sender<Result<Vec<u8>, request_responses::RequestFailure>>,
+	) {
+		self.request_responses.send_request(target, protocol, request, pending_response)
+	}
 }
 
 fn reported_roles_to_observed_role(local_role: &Role, remote: &PeerId, roles: Roles) -> ObservedRole {
@@ -355,6 +377,42 @@ impl<B: BlockT, H: ExHashT> NetworkBehaviourEventProcess<finality_requests::Even
 	}
 }
 
+impl<B: BlockT, H: ExHashT> NetworkBehaviourEventProcess<request_responses::Event> for Behaviour<B, H> {
+	fn inject_event(&mut self, event: request_responses::Event) {
+		match event {
+			request_responses::Event::InboundRequest { peer, protocol, result: Ok(build_time) } => {
+				self.events.push_back(BehaviourOut::AnsweredRequest {
+					peer,
+					protocol: protocol.to_vec(),
+					build_time,
+				});
+			}
+			request_responses::Event::InboundRequest { peer, protocol, result: Err(err) } => {
+				debug!(
+					target: "sub-libp2p",
+					"Failed to answer {:?} request from {}: {:?}",
+					String::from_utf8_lossy(&protocol),
+					peer,
+					err,
+				);
+			}
+			request_responses::Event::RequestStarted { peer, protocol } => {
+				self.events.push_back(BehaviourOut::RequestStarted {
+					peer,
+					protocol: protocol.to_vec(),
+				});
+			}
+			request_responses::Event::RequestFinished { peer, protocol, request_duration, .. } => {
+				self.events.push_back(BehaviourOut::RequestFinished {
+					peer,
+					protocol: protocol.to_vec(),
+					request_duration,
+				});
+			}
+		}
+	}
+}
+
 impl<B: BlockT, H: ExHashT> NetworkBehaviourEventProcess<debug_info::DebugInfoEvent>
 	for Behaviour<B, H> {
 	fn inject_event(&mut self, event: debug_info::DebugInfoEvent) {
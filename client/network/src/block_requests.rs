@@ -171,6 +171,12 @@ impl Config {
 		self
 	}
 
+	/// Set the max. duration we wait for the response to one of our block requests.
+	pub fn set_request_timeout(&mut self, v: Duration) -> &mut Self {
+		self.request_timeout = v;
+		self
+	}
+
 	/// Set protocol to use for upgrade negotiation.
 	pub fn set_protocol(&mut self, id: &ProtocolId) -> &mut Self {
 		let mut v = Vec::new();
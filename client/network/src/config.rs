@@ -23,6 +23,7 @@
 
 pub use crate::chain::{Client, FinalityProofProvider};
 pub use crate::on_demand_layer::{AlwaysBadChecker, OnDemand};
+pub use crate::request_responses::{IncomingRequest, RequestResponseConfig};
 pub use libp2p::{identity, core::PublicKey, wasm_ext::ExtTransport, build_multiaddr};
 
 // Note: this re-export shouldn't be part of the public API of the crate and will be removed in
@@ -49,6 +50,7 @@ use std::{
 	net::Ipv4Addr,
 	path::{Path, PathBuf},
 	sync::Arc,
+	time::Duration,
 };
 use zeroize::Zeroize;
 
@@ -407,10 +409,18 @@ pub struct NetworkConfiguration {
 	/// List of notifications protocols that the node supports. Must also include a
 	/// `ConsensusEngineId` for backwards-compatibility.
 	pub notifications_protocols: Vec<(ConsensusEngineId, Cow<'static, [u8]>)>,
+	/// Request-response protocols that the node supports, in addition to the built-in ones.
+	/// Requests are sent with [`NetworkService::request`](crate::NetworkService::request).
+	pub request_response_protocols: Vec<RequestResponseConfig>,
 	/// Maximum allowed number of incoming connections.
 	pub in_peers: u32,
 	/// Number of outgoing connections we're trying to maintain.
 	pub out_peers: u32,
+	/// Maximum number of light client peers, which count towards `in_peers` and `out_peers`.
+	/// `None` if light client peers aren't limited separately.
+	pub max_light_peers: Option<u32>,
+	/// Which peers to keep connected while performing a major sync.
+	pub sync_peer_strategy: SyncPeerStrategy,
 	/// List of reserved node addresses.
 	pub reserved_nodes: Vec<MultiaddrWithPeerId>,
 	/// The non-reserved peer mode.
@@ -423,11 +433,35 @@ pub struct NetworkConfiguration {
 	pub transport: TransportConfig,
 	/// Maximum number of peers to ask the same blocks in parallel.
 	pub max_parallel_downloads: u32,
+	/// Maximum number of blocks to ask for in a single block request.
+	///
+	/// Peers cap the number of blocks in their responses, so raising this above what they are
+	/// willing to serve has no effect.
+	pub max_blocks_per_request: u32,
+	/// Time after which a block request without response is considered failed and the peer
+	/// is disconnected.
+	pub block_request_timeout: Duration,
 	/// Should we insert non-global addresses into the DHT?
 	pub allow_non_globals_in_dht: bool,
 	/// If true, uses the `/<chainid>/block-requests/<version>` experimental protocol rather than
 	/// the legacy substream. This option is meant to be hard-wired to `true` in the future.
 	pub use_new_block_requests_protocol: bool,
+	/// Period after which consensus gossip protocols rebroadcast their messages to peers when
+	/// nothing new has been gossiped. `None` keeps the default of each gossip protocol.
+	///
+	/// Raising this is useful on high-latency links where periodic rebroadcasts would
+	/// otherwise compete with fresh messages. Block announcements are sent as soon as a block
+	/// is imported and have no period to configure.
+	pub gossip_rebroadcast_period: Option<Duration>,
+	/// Number of threads checking the signatures of incoming consensus gossip messages. With 0,
+	/// they are checked by the task of each gossip protocol.
+	pub gossip_validation_workers: usize,
+	/// Maximum number of light client requests served to a single peer per second. Requests
+	/// above this rate are ignored and lower the reputation of the peer.
+	pub max_light_requests_per_sec: u32,
+	/// Maximum size in bytes of a response served to a light client. Larger responses are not
+	/// sent.
+	pub max_light_response_size: usize,
 }
 
 impl NetworkConfiguration {
@@ -445,8 +479,11 @@ impl NetworkConfiguration {
 			boot_nodes: Vec::new(),
 			node_key,
 			notifications_protocols: Vec::new(),
+			request_response_protocols: Vec::new(),
 			in_peers: 25,
 			out_peers: 75,
+			max_light_peers: None,
+			sync_peer_strategy: SyncPeerStrategy::Any,
 			reserved_nodes: Vec::new(),
 			non_reserved_mode: NonReservedPeerMode::Accept,
 			client_version: client_version.into(),
@@ -458,8 +495,14 @@ impl NetworkConfiguration {
 				use_yamux_flow_control: false,
 			},
 			max_parallel_downloads: 5,
+			max_blocks_per_request: 128,
+			block_request_timeout: Duration::from_secs(40),
 			allow_non_globals_in_dht: false,
 			use_new_block_requests_protocol: true,
+			gossip_rebroadcast_period: None,
+			gossip_validation_workers: 0,
+			max_light_requests_per_sec: 32,
+			max_light_response_size: 16 * 1024 * 1024,
 		}
 	}
 }
@@ -555,6 +598,16 @@ impl NonReservedPeerMode {
 	}
 }
 
+/// Which peers to keep connected while performing a major sync.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SyncPeerStrategy {
+	/// Keep all peers connected. This is the default.
+	Any,
+	/// Disconnect peers that aren't ahead of us, freeing their slots for peers that we can
+	/// download blocks from. Reserved nodes and boot nodes are always kept.
+	PreferAhead,
+}
+
 /// The configuration of a node's secret key, describing the type of key
 /// and how it is obtained. A node's identity keypair is the result of
 /// the evaluation of the node key configuration.
@@ -618,20 +671,59 @@ impl NodeKeyConfig {
 			Ed25519(Secret::Input(k)) =>
 				Ok(Keypair::Ed25519(k.into())),
 
-			Ed25519(Secret::File(f)) =>
+			Ed25519(Secret::File(f)) => {
+				if let Some(previous) = previous_ed25519_secret(&f)? {
+					return Ok(previous);
+				}
 				get_secret(f,
 					|mut b| ed25519::SecretKey::from_bytes(&mut b),
 					ed25519::SecretKey::generate,
 					|b| b.as_ref().to_vec())
 				.map(ed25519::Keypair::from)
-				.map(Keypair::Ed25519),
+				.map(Keypair::Ed25519)
+			},
 		}
 	}
 }
 
+/// Returns the previous key of a rotated Ed25519 secret key `file`, if its grace period isn't
+/// over.
+fn previous_ed25519_secret(file: &Path) -> io::Result<Option<Keypair>> {
+	let grace_end = match fs::read_to_string(with_suffix(file, ".rotation")) {
+		Ok(grace_end) => grace_end.trim().parse::<u64>()
+			.map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?,
+		Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+		Err(e) => return Err(e),
+	};
+	if unix_time() >= grace_end {
+		return Ok(None);
+	}
+
+	let mut sk_bytes = fs::read(with_suffix(file, ".previous"))?;
+	let sk = ed25519::SecretKey::from_bytes(&mut sk_bytes)
+		.map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e));
+	sk_bytes.zeroize();
+	log::info!(
+		"Using the previous key of {} until the end of its grace period, in {}s",
+		file.display(),
+		grace_end - unix_time(),
+	);
+	sk.map(|sk| Some(Keypair::Ed25519(sk.into())))
+}
+
+/// Returns the number of seconds since the Unix epoch.
+fn unix_time() -> u64 {
+	std::time::SystemTime::now()
+		.duration_since(std::time::UNIX_EPOCH)
+		.map_or(0, |time| time.as_secs())
+}
+
 /// Load a secret key from a file, if it exists, or generate a
 /// new secret key and write it to that file. In either case,
 /// the secret key is returned.
+///
+/// An empty file, as left by a power loss before the key was written, is moved to
+/// `<file>.corrupted` and replaced with a new key.
 fn get_secret<P, F, G, E, W, K>(file: P, parse: F, generate: G, serialize: W) -> io::Result<K>
 where
 	P: AsRef<Path>,
@@ -640,31 +732,133 @@ where
 	E: Error + Send + Sync + 'static,
 	W: Fn(&K) -> Vec<u8>,
 {
-	std::fs::read(&file)
-		.and_then(|mut sk_bytes|
-			parse(&mut sk_bytes)
-				.map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e)))
-		.or_else(|e| {
-			if e.kind() == io::ErrorKind::NotFound {
-				file.as_ref().parent().map_or(Ok(()), fs::create_dir_all)?;
-				let sk = generate();
-				let mut sk_vec = serialize(&sk);
-				write_secret_file(file, &sk_vec)?;
-				sk_vec.zeroize();
-				Ok(sk)
-			} else {
-				Err(e)
-			}
-		})
+	let file = file.as_ref();
+	let sk_bytes = match fs::read(file) {
+		Ok(ref sk_bytes) if sk_bytes.is_empty() => {
+			let corrupted_file = with_suffix(file, ".corrupted");
+			log::warn!(
+				"Secret key file {} is empty, moving it to {} and generating a new key",
+				file.display(),
+				corrupted_file.display(),
+			);
+			fs::rename(file, &corrupted_file)?;
+			None
+		},
+		Ok(sk_bytes) => Some(sk_bytes),
+		Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+		Err(e) => return Err(e),
+	};
+
+	match sk_bytes {
+		Some(mut sk_bytes) => parse(&mut sk_bytes)
+			.map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e)),
+		None => {
+			file.parent().map_or(Ok(()), fs::create_dir_all)?;
+			let sk = generate();
+			let mut sk_vec = serialize(&sk);
+			write_secret_file(file, &sk_vec)?;
+			sk_vec.zeroize();
+			Ok(sk)
+		},
+	}
 }
 
-/// Write secret bytes to a file.
+/// Replace the Ed25519 secret key stored in `file` with a newly generated one, returning the
+/// previous keypair, if any, and the new one.
+///
+/// The previous key is kept in `<file>.previous`, and is the one loaded from `file` until
+/// `grace_period` is over, which is recorded in `<file>.rotation`. The new key atomically
+/// replaces the previous one, so that `file` contains a valid key at any time.
+pub fn rotate_ed25519_secret_file(
+	file: &Path,
+	grace_period: Duration,
+) -> io::Result<(Option<Keypair>, Keypair)> {
+	let previous = match fs::read(file) {
+		Ok(mut sk_bytes) => {
+			// an invalid key isn't kept as the previous one
+			let mut sk_copy = sk_bytes.clone();
+			let sk = ed25519::SecretKey::from_bytes(&mut sk_copy)
+				.map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e));
+			sk_copy.zeroize();
+			let sk = match sk {
+				Ok(sk) => sk,
+				Err(e) => {
+					sk_bytes.zeroize();
+					return Err(e);
+				},
+			};
+			let written = write_secret_file(&with_suffix(file, ".previous"), &sk_bytes);
+			sk_bytes.zeroize();
+			written?;
+			Some(Keypair::Ed25519(sk.into()))
+		},
+		Err(e) if e.kind() == io::ErrorKind::NotFound => {
+			file.parent().map_or(Ok(()), fs::create_dir_all)?;
+			None
+		},
+		Err(e) => return Err(e),
+	};
+
+	let rotation_file = with_suffix(file, ".rotation");
+	if previous.is_some() {
+		let grace_end = unix_time().saturating_add(grace_period.as_secs());
+		write_secret_file(&rotation_file, grace_end.to_string().as_bytes())?;
+	} else {
+		remove_if_exists(&rotation_file)?;
+	}
+
+	let sk = ed25519::SecretKey::generate();
+	write_secret_file(file, sk.as_ref())?;
+
+	Ok((previous, Keypair::Ed25519(sk.into())))
+}
+
+/// Returns `file` with `suffix` appended to its name.
+fn with_suffix(file: &Path, suffix: &str) -> PathBuf {
+	let mut path = file.as_os_str().to_owned();
+	path.push(suffix);
+	PathBuf::from(path)
+}
+
+/// Remove `path`, unless it doesn't exist.
+fn remove_if_exists(path: &Path) -> io::Result<()> {
+	match fs::remove_file(path) {
+		Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+		result => result,
+	}
+}
+
+/// Write secret bytes to a file, replacing it if it exists.
+///
+/// The bytes are written and synced to `<path>.tmp` first, which is then renamed to `path`, so
+/// that a crash never leaves `path` partially written.
 fn write_secret_file<P>(path: P, sk_bytes: &[u8]) -> io::Result<()>
 where
 	P: AsRef<Path>
 {
-	let mut file = open_secret_file(&path)?;
-	file.write_all(sk_bytes)
+	let path = path.as_ref();
+	let tmp_path = with_suffix(path, ".tmp");
+	remove_if_exists(&tmp_path)?;
+	let mut file = open_secret_file(&tmp_path)?;
+	file.write_all(sk_bytes)?;
+	file.sync_all()?;
+	fs::rename(&tmp_path, path)?;
+	sync_parent_dir(path)
+}
+
+/// Sync the directory containing `path`, making the creation or renaming of `path` durable.
+#[cfg(unix)]
+fn sync_parent_dir(path: &Path) -> io::Result<()> {
+	match path.parent() {
+		Some(dir) if !dir.as_os_str().is_empty() => fs::File::open(dir)?.sync_all(),
+		_ => Ok(()),
+	}
+}
+
+/// Sync the directory containing `path`, making the creation or renaming of `path` durable.
+#[cfg(not(unix))]
+fn sync_parent_dir(_path: &Path) -> io::Result<()> {
+	Ok(())
 }
 
 /// Opens a file containing a secret key in write mode.
@@ -720,6 +914,63 @@ mod tests {
 		assert!(file.is_file() && secret_bytes(&kp1) == secret_bytes(&kp2))
 	}
 
+	#[test]
+	fn test_rotate_secret_file() {
+		let tmp = tempdir_with_prefix("x");
+		let file = tmp.path().join("x").join("secret");
+		let (previous, kp1) = rotate_ed25519_secret_file(&file, Duration::from_secs(0)).unwrap();
+		assert!(previous.is_none());
+		let (previous, kp2) = rotate_ed25519_secret_file(&file, Duration::from_secs(0)).unwrap();
+		assert_eq!(secret_bytes(&previous.unwrap()), secret_bytes(&kp1));
+		assert!(secret_bytes(&kp1) != secret_bytes(&kp2));
+
+		let stored = NodeKeyConfig::Ed25519(Secret::File(file.clone())).into_keypair().unwrap();
+		assert_eq!(secret_bytes(&stored), secret_bytes(&kp2));
+		let previous_file = tmp.path().join("x").join("secret.previous");
+		let stored = NodeKeyConfig::Ed25519(Secret::File(previous_file)).into_keypair().unwrap();
+		assert_eq!(secret_bytes(&stored), secret_bytes(&kp1));
+	}
+
+	#[test]
+	fn test_previous_secret_is_used_during_grace_period() {
+		let tmp = tempdir_with_prefix("x");
+		let file = tmp.path().join("secret");
+		let kp1 = NodeKeyConfig::Ed25519(Secret::File(file.clone())).into_keypair().unwrap();
+
+		let (_, kp2) = rotate_ed25519_secret_file(&file, Duration::from_secs(3600)).unwrap();
+		let stored = NodeKeyConfig::Ed25519(Secret::File(file.clone())).into_keypair().unwrap();
+		assert_eq!(secret_bytes(&stored), secret_bytes(&kp1));
+
+		// the grace period is over
+		fs::write(tmp.path().join("secret.rotation"), unix_time().to_string()).unwrap();
+		let stored = NodeKeyConfig::Ed25519(Secret::File(file)).into_keypair().unwrap();
+		assert_eq!(secret_bytes(&stored), secret_bytes(&kp2));
+	}
+
+	#[test]
+	fn test_invalid_secret_file_is_not_rotated() {
+		let tmp = tempdir_with_prefix("x");
+		let file = tmp.path().join("secret");
+		fs::write(&file, b"invalid").unwrap();
+		assert!(rotate_ed25519_secret_file(&file, Duration::from_secs(0)).is_err());
+		assert!(!tmp.path().join("secret.previous").exists());
+		assert_eq!(fs::read(&file).unwrap(), b"invalid");
+	}
+
+	#[test]
+	fn test_empty_secret_file_is_replaced() {
+		let tmp = tempdir_with_prefix("x");
+		let file = tmp.path().join("secret");
+		fs::write(&file, b"").unwrap();
+		let kp1 = NodeKeyConfig::Ed25519(Secret::File(file.clone())).into_keypair().unwrap();
+		let kp2 = NodeKeyConfig::Ed25519(Secret::File(file.clone())).into_keypair().unwrap();
+		assert_eq!(secret_bytes(&kp1), secret_bytes(&kp2));
+		assert!(tmp.path().join("secret.corrupted").is_file());
+
+		fs::write(&file, b"invalid").unwrap();
+		assert!(NodeKeyConfig::Ed25519(Secret::File(file)).into_keypair().is_err());
+	}
+
 	#[test]
 	fn test_secret_input() {
 		let sk = ed25519::SecretKey::generate();
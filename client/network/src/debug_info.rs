@@ -23,7 +23,7 @@ use libp2p::swarm::{IntoProtocolsHandler, IntoProtocolsHandlerSelect, ProtocolsH
 use libp2p::swarm::{NetworkBehaviour, NetworkBehaviourAction, PollParameters};
 use libp2p::identify::{Identify, IdentifyEvent, IdentifyInfo};
 use libp2p::ping::{Ping, PingConfig, PingEvent, PingSuccess};
-use log::{debug, trace, error};
+use log::{debug, trace, error, warn};
 use smallvec::SmallVec;
 use std::{error, io};
 use std::collections::hash_map::Entry;
@@ -37,6 +37,11 @@ use crate::utils::interval;
 const CACHE_EXPIRE: Duration = Duration::from_secs(10 * 60);
 /// Interval at which we perform garbage collection on the node info.
 const GARBAGE_COLLECT_INTERVAL: Duration = Duration::from_secs(2 * 60);
+/// Time after which an address that hasn't been confirmed again is no longer considered
+/// confirmed.
+const CONFIRMATION_EXPIRE: Duration = Duration::from_secs(30 * 60);
+/// Time after startup before we warn that none of our addresses has been confirmed.
+const UNCONFIRMED_WARNING_DELAY: Duration = Duration::from_secs(10 * 60);
 
 /// Implementation of `NetworkBehaviour` that holds information about nodes in cache for diagnostic
 /// purposes.
@@ -49,6 +54,11 @@ pub struct DebugInfoBehaviour {
 	nodes_info: FnvHashMap<PeerId, NodeInfo>,
 	/// Interval at which we perform garbage collection in `nodes_info`.
 	garbage_collect: Pin<Box<dyn Stream<Item = ()> + Send>>,
+	/// Addresses of the local node that remote peers have reached us on, with the time of the
+	/// latest confirmation.
+	confirmed_addresses: FnvHashMap<Multiaddr, Instant>,
+	/// When we will warn if none of our addresses is confirmed, or `None` if we already did.
+	unconfirmed_warning: Option<Instant>,
 }
 
 /// Information about a node we're connected to.
@@ -94,9 +104,16 @@ impl DebugInfoBehaviour {
 			identify,
 			nodes_info: FnvHashMap::default(),
 			garbage_collect: Box::pin(interval(GARBAGE_COLLECT_INTERVAL)),
+			confirmed_addresses: FnvHashMap::default(),
+			unconfirmed_warning: Some(Instant::now() + UNCONFIRMED_WARNING_DELAY),
 		}
 	}
 
+	/// Returns the addresses of the local node that remote peers have recently reached us on.
+	pub fn confirmed_addresses(&self) -> impl Iterator<Item = &Multiaddr> {
+		self.confirmed_addresses.keys()
+	}
+
 	/// Borrows `self` and returns a struct giving access to the information about a node.
 	///
 	/// Returns `None` if we don't know anything about this node. Always returns `Some` for nodes
@@ -129,6 +146,20 @@ impl DebugInfoBehaviour {
 				"Received pong from node we're not connected to {:?}", peer_id);
 		}
 	}
+
+	/// Notes the address a remote observed for us. It confirms that we can be reached on that
+	/// address if the remote has dialed us, rather than the other way around.
+	fn handle_observed_addr(&mut self, peer_id: &PeerId, observed_addr: Multiaddr) {
+		let dialed_us = self.nodes_info.get(peer_id)
+			.map_or(false, |entry| entry.endpoints.iter().all(ConnectedPoint::is_listener));
+		if !dialed_us {
+			return
+		}
+
+		if self.confirmed_addresses.insert(observed_addr.clone(), Instant::now()).is_none() {
+			debug!(target: "sub-libp2p", "Address {} confirmed by {:?}", observed_addr, peer_id);
+		}
+	}
 }
 
 /// Gives access to the information about a node.
@@ -315,8 +346,9 @@ impl NetworkBehaviour for DebugInfoBehaviour {
 				Poll::Pending => break,
 				Poll::Ready(NetworkBehaviourAction::GenerateEvent(event)) => {
 					match event {
-						IdentifyEvent::Received { peer_id, info, .. } => {
+						IdentifyEvent::Received { peer_id, info, observed_addr } => {
 							self.handle_identify_report(&peer_id, &info);
+							self.handle_observed_addr(&peer_id, observed_addr);
 							let event = DebugInfoEvent::Identified { peer_id, info };
 							return Poll::Ready(NetworkBehaviourAction::GenerateEvent(event));
 						}
@@ -344,6 +376,23 @@ impl NetworkBehaviour for DebugInfoBehaviour {
 			self.nodes_info.retain(|_, node| {
 				node.info_expire.as_ref().map(|exp| *exp >= Instant::now()).unwrap_or(true)
 			});
+			self.confirmed_addresses.retain(|_, confirmed| {
+				*confirmed + CONFIRMATION_EXPIRE >= Instant::now()
+			});
+
+			if !self.confirmed_addresses.is_empty() {
+				self.unconfirmed_warning = Some(Instant::now() + UNCONFIRMED_WARNING_DELAY);
+			} else if self.unconfirmed_warning.map_or(false, |at| at <= Instant::now()) &&
+				params.listened_addresses().next().is_some()
+			{
+				warn!(
+					target: "sub-libp2p",
+					"No remote peer has reached us on any of our addresses. Other nodes might \
+					be unable to connect to this node; check the --listen-addr and \
+					--public-addr options and the firewall.",
+				);
+				self.unconfirmed_warning = None;
+			}
 		}
 
 		Poll::Pending
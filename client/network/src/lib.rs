@@ -213,13 +213,17 @@
 //! notifications protocol.
 //!
 //! At the moment, for backwards-compatibility, notification protocols are tied to the legacy
-//! Substrate substream. Additionally, the handshake message is hardcoded to be a single 8-bits
-//! integer representing the role of the node:
+//! Substrate substream. Additionally, the handshake message defaults to a single 8-bits integer
+//! representing the role of the node:
 //!
 //! - 1 for a full node.
 //! - 2 for a light node.
 //! - 4 for an authority.
 //!
+//! A protocol can send a handshake of its own instead by calling
+//! `NetworkService::set_notifications_handshake`. The notifications received on a protocol can
+//! be consumed per peer through `NetworkService::notifications_streams`.
+//!
 //! In the future, though, these restrictions will be removed.
 //!
 //! # Usage
@@ -251,6 +255,7 @@ mod finality_requests;
 mod light_client_handler;
 mod on_demand_layer;
 mod protocol;
+mod request_responses;
 mod schema;
 mod service;
 mod transport;
@@ -260,10 +265,11 @@ pub mod config;
 pub mod error;
 pub mod network_state;
 
-pub use service::{NetworkService, NetworkWorker};
-pub use protocol::PeerInfo;
+pub use service::{NetworkService, NetworkWorker, NotificationsStreams, PeerNotifications};
+pub use protocol::{PeerInfo, PeerStrikes, PeerSyncDetails};
 pub use protocol::event::{Event, DhtEvent, ObservedRole};
 pub use protocol::sync::SyncState;
+pub use request_responses::RequestFailure;
 pub use libp2p::{Multiaddr, PeerId};
 #[doc(inline)]
 pub use libp2p::multiaddr;
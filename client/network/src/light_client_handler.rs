@@ -54,6 +54,9 @@ use libp2p::{
 	}
 };
 use nohash_hasher::IntMap;
+use prometheus_endpoint::{
+	register, CounterVec, HistogramOpts, HistogramVec, Opts, PrometheusError, Registry, U64,
+};
 use prost::Message;
 use sc_client_api::{
 	StorageProof,
@@ -74,7 +77,7 @@ use sp_runtime::{
 	generic::BlockId,
 };
 use std::{
-	collections::{BTreeMap, VecDeque, HashMap},
+	collections::{BTreeMap, VecDeque, HashMap, HashSet},
 	iter,
 	io,
 	sync::Arc,
@@ -87,6 +90,9 @@ use wasm_timer::Instant;
 /// Reputation change for a peer when a request timed out.
 pub(crate) const TIMEOUT_REPUTATION_CHANGE: i32 = -(1 << 8);
 
+/// Reputation change for a peer sending us more requests than we are willing to serve.
+const RATE_LIMIT_REPUTATION_CHANGE: i32 = -(1 << 6);
+
 /// Configuration options for `LightClientHandler` behaviour.
 #[derive(Debug, Clone)]
 pub struct Config {
@@ -95,6 +101,8 @@ pub struct Config {
 	max_pending_requests: usize,
 	inactivity_timeout: Duration,
 	request_timeout: Duration,
+	max_served_requests_per_sec: u32,
+	max_served_response_size: usize,
 	light_protocol: Bytes,
 	block_protocol: Bytes,
 }
@@ -107,6 +115,8 @@ impl Config {
 	/// - max. pending requests = 128
 	/// - inactivity timeout = 15s
 	/// - request timeout = 15s
+	/// - max. requests served per peer and second = 32
+	/// - max. size of a served response = 16 MiB
 	pub fn new(id: &ProtocolId) -> Self {
 		let mut c = Config {
 			max_request_size: 1 * 1024 * 1024,
@@ -114,6 +124,8 @@ impl Config {
 			max_pending_requests: 128,
 			inactivity_timeout: Duration::from_secs(15),
 			request_timeout: Duration::from_secs(15),
+			max_served_requests_per_sec: 32,
+			max_served_response_size: 16 * 1024 * 1024,
 			light_protocol: Bytes::new(),
 			block_protocol: Bytes::new(),
 		};
@@ -151,6 +163,18 @@ impl Config {
 		self
 	}
 
+	/// Limit the max. number of requests served to a single peer per second.
+	pub fn set_max_served_requests_per_sec(&mut self, v: u32) -> &mut Self {
+		self.max_served_requests_per_sec = v;
+		self
+	}
+
+	/// Limit the max. length in bytes of a response served to a remote.
+	pub fn set_max_served_response_size(&mut self, v: usize) -> &mut Self {
+		self.max_served_response_size = v;
+		self
+	}
+
 	/// Set protocol to use for upgrade negotiation.
 	pub fn set_protocol(&mut self, id: &ProtocolId) -> &mut Self {
 		let mut vl = Vec::new();
@@ -187,6 +211,34 @@ pub enum Error {
 	/// Encoding or decoding of some data failed.
 	#[error("codec error: {0}")]
 	Codec(#[from] codec::Error),
+	/// The response to a request would exceed the max. size of the served responses.
+	#[error("response too large")]
+	ResponseTooLarge,
+}
+
+/// Returns the proof of `keys` made with `prove`, one key at a time.
+///
+/// Fails with `Error::ResponseTooLarge` as soon as the proof exceeds `max_size` bytes, without
+/// proving the remaining keys.
+fn prove_keys<'a>(
+	keys: impl IntoIterator<Item = &'a [u8]>,
+	max_size: usize,
+	mut prove: impl FnMut(&[u8]) -> Result<StorageProof, ClientError>,
+) -> Result<StorageProof, Error> {
+	let mut nodes = HashSet::new();
+	let mut size = 0;
+	for key in keys {
+		for node in prove(key)?.iter_nodes() {
+			if !nodes.contains(&node) {
+				size += node.len();
+				nodes.insert(node);
+			}
+		}
+		if size > max_size {
+			return Err(Error::ResponseTooLarge)
+		}
+	}
+	Ok(StorageProof::new(nodes.into_iter().collect()))
 }
 
 /// The possible light client requests we support.
@@ -259,6 +311,10 @@ struct PeerInfo<B: Block> {
 	connections: SmallVec<[(ConnectionId, Multiaddr); crate::MAX_CONNECTIONS_PER_PEER]>,
 	best_block: Option<NumberFor<B>>,
 	status: PeerStatus,
+	/// Start of the current one second window of requests served to this peer.
+	served_since: Option<Instant>,
+	/// Number of requests of this peer in the current window.
+	served_count: u32,
 }
 
 impl<B: Block> Default for PeerInfo<B> {
@@ -267,7 +323,69 @@ impl<B: Block> Default for PeerInfo<B> {
 			connections: SmallVec::new(),
 			best_block: None,
 			status: PeerStatus::Idle,
+			served_since: None,
+			served_count: 0,
+		}
+	}
+}
+
+impl<B: Block> PeerInfo<B> {
+	/// Count a request of this peer received at `now`.
+	///
+	/// Returns `false` if the peer has sent more than `max_per_sec` requests within the last
+	/// second.
+	fn note_request(&mut self, now: Instant, max_per_sec: u32) -> bool {
+		match self.served_since {
+			Some(since) if now.duration_since(since) < Duration::from_secs(1) => {}
+			_ => {
+				self.served_since = Some(now);
+				self.served_count = 0;
+			}
 		}
+		self.served_count = self.served_count.saturating_add(1);
+		self.served_count <= max_per_sec
+	}
+}
+
+/// Prometheus metrics about the requests served to light clients.
+struct Metrics {
+	requests_served: CounterVec<U64>,
+	response_sizes: HistogramVec,
+}
+
+impl Metrics {
+	fn register(registry: &Registry) -> Result<Self, PrometheusError> {
+		Ok(Metrics {
+			requests_served: register(CounterVec::new(
+				Opts::new(
+					"sub_libp2p_light_requests_served_total",
+					"Total number of light client requests received, by kind and outcome"
+				),
+				&["kind", "outcome"]
+			)?, registry)?,
+			response_sizes: register(HistogramVec::new(
+				HistogramOpts {
+					common_opts: Opts::new(
+						"sub_libp2p_light_response_sizes",
+						"Sizes of the responses sent to light clients"
+					),
+					buckets: prometheus_endpoint::exponential_buckets(64.0, 4.0, 10)
+						.expect("parameters are always valid values; qed"),
+				},
+				&["kind"]
+			)?, registry)?,
+		})
+	}
+}
+
+/// Name of the kind of a light client request, as reported in the metrics.
+fn request_kind(request: &schema::v1::light::request::Request) -> &'static str {
+	match request {
+		schema::v1::light::request::Request::RemoteCallRequest(_) => "call",
+		schema::v1::light::request::Request::RemoteReadRequest(_) => "read",
+		schema::v1::light::request::Request::RemoteHeaderRequest(_) => "header",
+		schema::v1::light::request::Request::RemoteReadChildRequest(_) => "read_child",
+		schema::v1::light::request::Request::RemoteChangesRequest(_) => "changes",
 	}
 }
 
@@ -302,6 +420,8 @@ pub struct LightClientHandler<B: Block> {
 	next_request_id: RequestId,
 	/// Handle to use for reporting misbehaviour of peers.
 	peerset: sc_peerset::PeersetHandle,
+	/// Prometheus metrics, if enabled.
+	metrics: Option<Metrics>,
 }
 
 impl<B> LightClientHandler<B>
@@ -325,6 +445,20 @@ where
 			outstanding: IntMap::default(),
 			next_request_id: 1,
 			peerset,
+			metrics: None,
+		}
+	}
+
+	/// Register the metrics of the requests served to light clients in `registry`.
+	pub fn register_metrics(&mut self, registry: &Registry) -> Result<(), PrometheusError> {
+		self.metrics = Some(Metrics::register(registry)?);
+		Ok(())
+	}
+
+	/// Count a served request of the given kind in the metrics.
+	fn note_served(&self, kind: &str, outcome: &str) {
+		if let Some(metrics) = &self.metrics {
+			metrics.requests_served.with_label_values(&[kind, outcome]).inc();
 		}
 	}
 
@@ -584,8 +718,14 @@ where
 
 		let block = Decode::decode(&mut request.block.as_ref())?;
 
-		let proof = match self.chain.read_proof(&BlockId::Hash(block), &mut request.keys.iter().map(AsRef::as_ref)) {
+		let proof = prove_keys(
+			request.keys.iter().map(AsRef::as_ref),
+			self.config.max_served_response_size,
+			|key| self.chain.read_proof(&BlockId::Hash(block), &mut iter::once(key)),
+		);
+		let proof = match proof {
 			Ok(proof) => proof,
+			Err(Error::ResponseTooLarge) => return Err(Error::ResponseTooLarge),
 			Err(error) => {
 				log::trace!("remote read request from {} ({} at {:?}) failed with: {}",
 					peer,
@@ -626,14 +766,16 @@ where
 		let prefixed_key = PrefixedStorageKey::new_ref(&request.storage_key);
 		let child_info = match ChildType::from_prefixed_key(prefixed_key) {
 			Some((ChildType::ParentKeyId, storage_key)) => Ok(ChildInfo::new_default(storage_key)),
-			None => Err("Invalid child storage key".into()),
+			None => Err(Error::Client("Invalid child storage key".into())),
 		};
-		let proof = match child_info.and_then(|child_info| self.chain.read_child_proof(
-			&BlockId::Hash(block),
-			&child_info,
-			&mut request.keys.iter().map(AsRef::as_ref)
-		)) {
+		let proof = child_info.and_then(|child_info| prove_keys(
+			request.keys.iter().map(AsRef::as_ref),
+			self.config.max_served_response_size,
+			|key| self.chain.read_child_proof(&BlockId::Hash(block), &child_info, &mut iter::once(key)),
+		));
+		let proof = match proof {
 			Ok(proof) => proof,
+			Err(Error::ResponseTooLarge) => return Err(Error::ResponseTooLarge),
 			Err(error) => {
 				log::trace!("remote read child request from {} ({} {} at {:?}) failed with: {}",
 					peer,
@@ -821,29 +963,60 @@ where
 			// An incoming request from remote has been received.
 			Event::Request(request, mut stream) => {
 				log::trace!("incoming request from {}", peer);
-				let result = match &request.request {
-					Some(schema::v1::light::request::Request::RemoteCallRequest(r)) =>
+				let request = match request.request {
+					Some(request) => request,
+					None => {
+						log::debug!("ignoring request without request data from peer {}", peer);
+						return
+					}
+				};
+				let kind = request_kind(&request);
+
+				let max_per_sec = self.config.max_served_requests_per_sec;
+				let allowed = self.peers.get_mut(&peer)
+					.map_or(true, |info| info.note_request(Instant::now(), max_per_sec));
+				if !allowed {
+					log::debug!("ignoring {} request from peer {}: too many requests", kind, peer);
+					self.note_served(kind, "rate_limited");
+					self.peerset.report_peer(
+						peer,
+						ReputationChange::new(RATE_LIMIT_REPUTATION_CHANGE, "too many requests"),
+					);
+					return
+				}
+
+				let result = match &request {
+					schema::v1::light::request::Request::RemoteCallRequest(r) =>
 						self.on_remote_call_request(&peer, r),
-					Some(schema::v1::light::request::Request::RemoteReadRequest(r)) =>
+					schema::v1::light::request::Request::RemoteReadRequest(r) =>
 						self.on_remote_read_request(&peer, r),
-					Some(schema::v1::light::request::Request::RemoteHeaderRequest(r)) =>
+					schema::v1::light::request::Request::RemoteHeaderRequest(r) =>
 						self.on_remote_header_request(&peer, r),
-					Some(schema::v1::light::request::Request::RemoteReadChildRequest(r)) =>
+					schema::v1::light::request::Request::RemoteReadChildRequest(r) =>
 						self.on_remote_read_child_request(&peer, r),
-					Some(schema::v1::light::request::Request::RemoteChangesRequest(r)) =>
+					schema::v1::light::request::Request::RemoteChangesRequest(r) =>
 						self.on_remote_changes_request(&peer, r),
-					None => {
-						log::debug!("ignoring request without request data from peer {}", peer);
-						return
-					}
 				};
 				match result {
 					Ok(response) => {
-						log::trace!("enqueueing response for peer {}", peer);
 						let mut data = Vec::new();
 						if let Err(e) = response.encode(&mut data) {
-							log::debug!("error encoding response for peer {}: {}", peer, e)
+							log::debug!("error encoding response for peer {}: {}", peer, e);
+							self.note_served(kind, "failed");
+						} else if data.len() > self.config.max_served_response_size {
+							log::debug!(
+								"not sending {} response of {} bytes to peer {}: too large",
+								kind, data.len(), peer,
+							);
+							self.note_served(kind, "too_large");
 						} else {
+							log::trace!("enqueueing response for peer {}", peer);
+							self.note_served(kind, "served");
+							if let Some(metrics) = &self.metrics {
+								metrics.response_sizes
+									.with_label_values(&[kind])
+									.observe(data.len() as f64);
+							}
 							let future = async move {
 								if let Err(e) = write_one(&mut stream, data).await {
 									log::debug!("error writing response: {}", e)
@@ -852,11 +1025,19 @@ where
 							self.responses.push(future.boxed())
 						}
 					}
+					Err(Error::ResponseTooLarge) => {
+						log::debug!("not sending {} response to peer {}: too large", kind, peer);
+						self.note_served(kind, "too_large");
+					}
 					Err(Error::BadRequest(_)) => {
+						self.note_served(kind, "bad_request");
 						self.remove_peer(&peer);
 						self.peerset.report_peer(peer, ReputationChange::new(-(1 << 12), "bad request"))
 					}
-					Err(e) => log::debug!("error handling request from peer {}: {}", peer, e)
+					Err(e) => {
+						log::debug!("error handling request from peer {}: {}", peer, e);
+						self.note_served(kind, "failed");
+					}
 				}
 			}
 			// A response to one of our own requests has been received.
@@ -1532,6 +1713,17 @@ mod tests {
 		assert_eq!(0, behaviour.peers.len())
 	}
 
+	#[test]
+	fn limits_requests_served_per_second() {
+		let mut info = super::PeerInfo::<Block>::default();
+		let start = Instant::now();
+
+		assert!(info.note_request(start, 2));
+		assert!(info.note_request(start + Duration::from_millis(500), 2));
+		assert!(!info.note_request(start + Duration::from_millis(900), 2));
+		assert!(info.note_request(start + Duration::from_millis(1000), 2));
+	}
+
 	#[test]
 	fn disconnects_from_peer_if_request_times_out() {
 		let peer0 = PeerId::random();
@@ -2036,4 +2228,26 @@ mod tests {
 		assert_eq!(vec![(100, 2)], task::block_on(chan.1).unwrap().unwrap());
 		//              ^--- from `DummyFetchChecker::check_changes_proof`
 	}
+
+	#[test]
+	fn read_proofs_stop_once_too_large() {
+		let keys = vec![vec![1], vec![2], vec![3]];
+		let prove = |max_size| {
+			let mut proven = Vec::new();
+			let proof = prove_keys(keys.iter().map(AsRef::as_ref), max_size, |key| {
+				proven.push(key.to_vec());
+				Ok(StorageProof::new(vec![vec![0; 10], key.to_vec()]))
+			});
+			(proof, proven)
+		};
+
+		// the node shared by the proofs of the keys is only counted once
+		let (proof, proven) = prove(100);
+		assert_eq!(proof.unwrap().iter_nodes().count(), 4);
+		assert_eq!(proven, keys);
+
+		let (proof, proven) = prove(11);
+		assert_matches!(proof, Err(super::Error::ResponseTooLarge));
+		assert_eq!(proven, vec![vec![1], vec![2]]);
+	}
 }
@@ -39,6 +39,9 @@ pub struct NetworkState {
 	pub listened_addresses: HashSet<Multiaddr>,
 	/// List of addresses the node knows it can be reached as.
 	pub external_addresses: HashSet<Multiaddr>,
+	/// List of addresses that remote nodes have recently reached the node on.
+	#[serde(default)]
+	pub confirmed_addresses: HashSet<Multiaddr>,
 	/// List of node we're connected to.
 	pub connected_peers: HashMap<String, Peer>,
 	/// List of node that we know of but that we're not connected to.
@@ -19,7 +19,10 @@
 use crate::{
 	ExHashT,
 	chain::{Client, FinalityProofProvider},
-	config::{BoxFinalityProofRequestBuilder, ProtocolId, TransactionPool, TransactionImportFuture, TransactionImport},
+	config::{
+		BoxFinalityProofRequestBuilder, ProtocolId, SyncPeerStrategy, TransactionPool,
+		TransactionImportFuture, TransactionImport,
+	},
 	error,
 	utils::interval
 };
@@ -56,6 +59,7 @@ use std::sync::Arc;
 use std::fmt::Write;
 use std::{cmp, io, num::NonZeroUsize, pin::Pin, task::Poll, time};
 use log::{log, Level, trace, debug, warn, error};
+use lru::LruCache;
 use sc_client_api::{ChangesProof, StorageProof};
 use util::LruHashSet;
 use wasm_timer::Instant;
@@ -69,7 +73,8 @@ pub mod sync;
 
 pub use generic_proto::LegacyConnectionKillError;
 
-const REQUEST_TIMEOUT_SEC: u64 = 40;
+/// Time after which we give up on a handshake.
+const HANDSHAKE_TIMEOUT: time::Duration = time::Duration::from_secs(40);
 /// Interval at which we perform time based maintenance
 const TICK_TIMEOUT: time::Duration = time::Duration::from_millis(1100);
 /// Interval at which we propagate extrinsics;
@@ -83,6 +88,11 @@ const MAX_KNOWN_EXTRINSICS: usize = 4096; // ~128kb per peer + overhead
 /// Maximim number of transaction validation request we keep at any moment.
 const MAX_PENDING_TRANSACTIONS: usize = 8192;
 
+/// Number of recent block response times kept for each peer.
+const MAX_RESPONSE_TIMES: usize = 8;
+/// Maximum number of peers whose strikes are remembered, including disconnected ones.
+const MAX_PEERS_STRIKES: usize = 1024;
+
 /// Current protocol version.
 pub(crate) const CURRENT_VERSION: u32 = 6;
 /// Lowest version we support
@@ -106,6 +116,8 @@ mod rep {
 	pub const UNEXPECTED_STATUS: Rep = Rep::new(-(1 << 20), "Unexpected status message");
 	/// Reputation change when we are a light client and a peer is behind us.
 	pub const PEER_BEHIND_US_LIGHT: Rep = Rep::new(-(1 << 8), "Useless for a light peer");
+	/// Reputation change when we are performing a major sync and a peer isn't ahead of us.
+	pub const PEER_BEHIND_US_MAJOR_SYNC: Rep = Rep::new(-(1 << 8), "Useless for major sync");
 	/// Reputation change when a peer sends us any extrinsic.
 	///
 	/// This forces node to verify it, thus the negative value here. Once extrinsic is verified,
@@ -229,6 +241,8 @@ pub struct Protocol<B: BlockT, H: ExHashT> {
 	/// List of nodes for which we perform additional logging because they are important for the
 	/// user.
 	important_peers: HashSet<PeerId>,
+	/// Failed block requests of the recently seen peers, kept across reconnections.
+	strikes: LruCache<PeerId, PeerStrikes>,
 	// Connected peers pending Status message.
 	handshaking_peers: HashMap<PeerId, HandshakingPeer>,
 	/// Used to report reputation changes.
@@ -283,6 +297,8 @@ struct Peer<B: BlockT, H: ExHashT> {
 	known_blocks: LruHashSet<B::Hash>,
 	/// Request counter,
 	next_request_id: message::RequestId,
+	/// Time it took to answer the last block requests, oldest first.
+	response_times: VecDeque<time::Duration>,
 }
 
 /// Info about a peer's known state.
@@ -298,6 +314,33 @@ pub struct PeerInfo<B: BlockT> {
 	pub best_number: <B::Header as HeaderT>::Number,
 }
 
+/// Number of block requests a peer failed to answer properly.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PeerStrikes {
+	/// Requests the peer didn't answer in time.
+	pub timeouts: u32,
+	/// Requests the peer answered with invalid or unexpected blocks.
+	pub bad_responses: u32,
+}
+
+/// Diagnostics of the block requests made to a peer, used to find out why sync is stuck.
+#[derive(Clone, Debug)]
+pub struct PeerSyncDetails<B: BlockT> {
+	/// Info about the peer's known state.
+	pub info: PeerInfo<B>,
+	/// Best block known to both us and the peer, if the peer takes part in syncing.
+	pub common_number: Option<NumberFor<B>>,
+	/// What we are downloading from the peer, if it takes part in syncing.
+	pub sync_state: Option<String>,
+	/// Number of block requests sent to the peer and not answered yet, including the ones we are
+	/// no longer interested in.
+	pub requests_in_flight: usize,
+	/// Time it took to answer the last block requests, oldest first.
+	pub response_times: Vec<time::Duration>,
+	/// Block requests the peer failed to answer properly, including before it reconnected.
+	pub strikes: PeerStrikes,
+}
+
 /// Data necessary to create a context.
 struct ContextData<B: BlockT, H: ExHashT> {
 	// All connected peers
@@ -313,6 +356,14 @@ pub struct ProtocolConfig {
 	pub roles: Roles,
 	/// Maximum number of peers to ask the same blocks in parallel.
 	pub max_parallel_downloads: u32,
+	/// Maximum number of blocks to ask for in a single block request.
+	pub max_blocks_per_request: u32,
+	/// Time after which a block request without response is considered failed.
+	pub block_request_timeout: time::Duration,
+	/// Maximum number of light client peers. `None` if not limited.
+	pub max_light_peers: Option<u32>,
+	/// Which peers to keep connected while performing a major sync.
+	pub sync_peer_strategy: SyncPeerStrategy,
 }
 
 impl Default for ProtocolConfig {
@@ -320,6 +371,10 @@ impl Default for ProtocolConfig {
 		ProtocolConfig {
 			roles: Roles::FULL,
 			max_parallel_downloads: 5,
+			max_blocks_per_request: 128,
+			block_request_timeout: time::Duration::from_secs(40),
+			max_light_peers: None,
+			sync_peer_strategy: SyncPeerStrategy::Any,
 		}
 	}
 }
@@ -385,6 +440,7 @@ impl<B: BlockT, H: ExHashT> Protocol<B, H> {
 			finality_proof_request_builder,
 			block_announce_validator,
 			config.max_parallel_downloads,
+			config.max_blocks_per_request,
 		);
 
 		let important_peers = {
@@ -444,6 +500,7 @@ impl<B: BlockT, H: ExHashT> Protocol<B, H> {
 			sync,
 			handshaking_peers: HashMap::new(),
 			important_peers,
+			strikes: LruCache::new(MAX_PEERS_STRIKES),
 			transaction_pool,
 			finality_proof_provider,
 			peerset_handle: peerset_handle.clone(),
@@ -563,6 +620,35 @@ impl<B: BlockT, H: ExHashT> Protocol<B, H> {
 		self.context_data.peers.iter().map(|(id, peer)| (id, &peer.info))
 	}
 
+	/// Returns the diagnostics of the block requests made to all the peers we are connected to.
+	pub fn peers_sync_details(&self) -> Vec<(PeerId, PeerSyncDetails<B>)> {
+		self.context_data.peers.iter().map(|(id, peer)| {
+			let sync_info = self.sync.peer_info(id);
+			let details = PeerSyncDetails {
+				info: peer.info.clone(),
+				common_number: sync_info.as_ref().map(|info| info.common_number),
+				sync_state: sync_info.map(|info| format!("{:?}", info.state)),
+				requests_in_flight: peer.obsolete_requests.len()
+					+ peer.block_request.iter().count(),
+				response_times: peer.response_times.iter().cloned().collect(),
+				strikes: self.strikes.peek(id).cloned().unwrap_or_default(),
+			};
+			(id.clone(), details)
+		}).collect()
+	}
+
+	/// Count a failed block request of `who` in its strikes.
+	fn note_strike(&mut self, who: &PeerId, strike: impl FnOnce(&mut PeerStrikes)) {
+		match self.strikes.get_mut(who) {
+			Some(strikes) => strike(strikes),
+			None => {
+				let mut strikes = PeerStrikes::default();
+				strike(&mut strikes);
+				self.strikes.put(who.clone(), strikes);
+			},
+		}
+	}
+
 	pub fn on_custom_message(
 		&mut self,
 		who: PeerId,
@@ -832,7 +918,13 @@ impl<B: BlockT, H: ExHashT> Protocol<B, H> {
 			}
 			// Clear the request. If the response is invalid peer will be disconnected anyway.
 			match p.block_request.take() {
-				Some((_, request)) if request.id == response.id => request,
+				Some((sent, request)) if request.id == response.id => {
+					if p.response_times.len() == MAX_RESPONSE_TIMES {
+						p.response_times.pop_front();
+					}
+					p.response_times.push_back(Instant::now() - sent);
+					request
+				},
 				Some(_) =>  {
 					trace!(target: "sync", "Ignoring obsolete block response packet from {} ({})", peer, response.id);
 					return CustomMessageOutcome::None;
@@ -872,6 +964,7 @@ impl<B: BlockT, H: ExHashT> Protocol<B, H> {
 				Ok(sync::OnBlockJustification::Import { peer, hash, number, justification }) =>
 					CustomMessageOutcome::JustificationImport(peer, hash, number, justification),
 				Err(sync::BadPeer(id, repu)) => {
+					self.note_strike(&id, |strikes| strikes.bad_responses += 1);
 					self.behaviour.disconnect_peer(&id);
 					self.peerset_handle.report_peer(id, repu);
 					CustomMessageOutcome::None
@@ -880,12 +973,14 @@ impl<B: BlockT, H: ExHashT> Protocol<B, H> {
 		} else {
 			// Validate fields against the request.
 			if request.fields.contains(message::BlockAttributes::HEADER) && response.blocks.iter().any(|b| b.header.is_none()) {
+				self.note_strike(&peer, |strikes| strikes.bad_responses += 1);
 				self.behaviour.disconnect_peer(&peer);
 				self.peerset_handle.report_peer(peer, rep::BAD_RESPONSE);
 				trace!(target: "sync", "Missing header for a block");
 				return CustomMessageOutcome::None
 			}
 			if request.fields.contains(message::BlockAttributes::BODY) && response.blocks.iter().any(|b| b.body.is_none()) {
+				self.note_strike(&peer, |strikes| strikes.bad_responses += 1);
 				self.behaviour.disconnect_peer(&peer);
 				self.peerset_handle.report_peer(peer, rep::BAD_RESPONSE);
 				trace!(target: "sync", "Missing body for a block");
@@ -908,6 +1003,7 @@ impl<B: BlockT, H: ExHashT> Protocol<B, H> {
 					}
 				}
 				Err(sync::BadPeer(id, repu)) => {
+					self.note_strike(&id, |strikes| strikes.bad_responses += 1);
 					self.behaviour.disconnect_peer(&id);
 					self.peerset_handle.report_peer(id, repu);
 					CustomMessageOutcome::None
@@ -921,6 +1017,7 @@ impl<B: BlockT, H: ExHashT> Protocol<B, H> {
 		&mut self,
 		peer: &PeerId,
 	) {
+		self.note_strike(peer, |strikes| strikes.timeouts += 1);
 		self.peerset_handle.report_peer(peer.clone(), rep::TIMEOUT);
 		self.behaviour.disconnect_peer(peer);
 	}
@@ -935,17 +1032,18 @@ impl<B: BlockT, H: ExHashT> Protocol<B, H> {
 
 	fn maintain_peers(&mut self) {
 		let tick = Instant::now();
+		let request_timeout = self.config.block_request_timeout;
 		let mut aborting = Vec::new();
 		{
 			for (who, peer) in self.context_data.peers.iter() {
-				if peer.block_request.as_ref().map_or(false, |(t, _)| (tick - *t).as_secs() > REQUEST_TIMEOUT_SEC) {
+				if peer.block_request.as_ref().map_or(false, |(t, _)| tick - *t > request_timeout) {
 					log!(
 						target: "sync",
 						if self.important_peers.contains(who) { Level::Warn } else { Level::Trace },
 						"Request timeout {}", who
 					);
 					aborting.push(who.clone());
-				} else if peer.obsolete_requests.values().any(|t| (tick - *t).as_secs() > REQUEST_TIMEOUT_SEC) {
+				} else if peer.obsolete_requests.values().any(|t| tick - *t > request_timeout) {
 					log!(
 						target: "sync",
 						if self.important_peers.contains(who) { Level::Warn } else { Level::Trace },
@@ -955,7 +1053,7 @@ impl<B: BlockT, H: ExHashT> Protocol<B, H> {
 				}
 			}
 			for (who, _) in self.handshaking_peers.iter()
-				.filter(|(_, handshaking)| (tick - handshaking.timestamp).as_secs() > REQUEST_TIMEOUT_SEC)
+				.filter(|(_, handshaking)| tick - handshaking.timestamp > HANDSHAKE_TIMEOUT)
 			{
 				log!(
 					target: "sync",
@@ -967,6 +1065,7 @@ impl<B: BlockT, H: ExHashT> Protocol<B, H> {
 		}
 
 		for p in aborting {
+			self.note_strike(&p, |strikes| strikes.timeouts += 1);
 			self.behaviour.disconnect_peer(&p);
 			self.peerset_handle.report_peer(p, rep::TIMEOUT);
 		}
@@ -1045,6 +1144,32 @@ impl<B: BlockT, H: ExHashT> Protocol<B, H> {
 				}
 			}
 
+			let max_light_peers = self.config.max_light_peers.filter(|_| status.roles.is_light());
+			if let Some(max_light_peers) = max_light_peers {
+				let light_peers = self.context_data.peers.values()
+					.filter(|peer| peer.info.roles.is_light())
+					.count();
+				if light_peers >= max_light_peers as usize && !self.important_peers.contains(&who) {
+					debug!(target: "sync", "No light peer slot left for {}", who);
+					self.behaviour.disconnect_peer(&who);
+					return CustomMessageOutcome::None;
+				}
+			}
+
+			// we aren't interested in peers that we can't download blocks from during major sync
+			if self.config.sync_peer_strategy == SyncPeerStrategy::PreferAhead
+				&& self.sync.status().state == SyncState::Downloading
+				&& !self.important_peers.contains(&who)
+			{
+				let self_best_block = self.context_data.chain.info().best_number;
+				if !status.roles.is_full() || status.best_number <= self_best_block {
+					debug!(target: "sync", "Peer {} isn't ahead of us during major sync", who);
+					self.peerset_handle.report_peer(who.clone(), rep::PEER_BEHIND_US_MAJOR_SYNC);
+					self.behaviour.disconnect_peer(&who);
+					return CustomMessageOutcome::None;
+				}
+			}
+
 			let info = match self.handshaking_peers.remove(&who) {
 				Some(_handshaking) => {
 					PeerInfo {
@@ -1069,6 +1194,7 @@ impl<B: BlockT, H: ExHashT> Protocol<B, H> {
 					.expect("Constant is nonzero")),
 				next_request_id: 0,
 				obsolete_requests: HashMap::new(),
+				response_times: VecDeque::with_capacity(MAX_RESPONSE_TIMES),
 			};
 			self.context_data.peers.insert(who.clone(), peer);
 
@@ -1156,6 +1282,24 @@ impl<B: BlockT, H: ExHashT> Protocol<B, H> {
 			.map(|(peer_id, peer)| (peer_id, peer.info.roles))
 	}
 
+	/// Modifies the handshake message sent when opening a substream of the given notifications
+	/// protocol.
+	pub fn set_notifications_handshake(
+		&mut self,
+		engine_id: ConsensusEngineId,
+		handshake_message: Vec<u8>,
+	) {
+		if let Some(protocol_name) = self.protocol_name_by_engine.get(&engine_id) {
+			self.behaviour.set_notif_protocol_handshake(protocol_name, handshake_message);
+		} else {
+			error!(
+				target: "sub-libp2p",
+				"Setting the handshake of a protocol that wasn't registered: {:?}",
+				engine_id
+			);
+		}
+	}
+
 	/// Called when peer sends us new extrinsics
 	fn on_extrinsics(
 		&mut self,
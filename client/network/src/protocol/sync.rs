@@ -53,9 +53,6 @@ use std::{fmt, ops::Range, collections::{HashMap, HashSet, VecDeque}, sync::Arc}
 mod blocks;
 mod extra_requests;
 
-/// Maximum blocks to request in a single packet.
-const MAX_BLOCKS_TO_REQUEST: usize = 128;
-
 /// Maximum blocks to store in the import queue.
 const MAX_IMPORTING_BLOCKS: usize = 2048;
 
@@ -189,6 +186,8 @@ pub struct ChainSync<B: BlockT> {
 	block_announce_validator: Box<dyn BlockAnnounceValidator<B> + Send>,
 	/// Maximum number of peers to ask the same blocks in parallel.
 	max_parallel_downloads: u32,
+	/// Maximum number of blocks to ask for in a single block request.
+	max_blocks_per_request: u32,
 	/// Total number of processed blocks (imported or failed).
 	processed_blocks: usize,
 }
@@ -217,7 +216,11 @@ pub struct PeerInfo<B: BlockT> {
 	/// Their best block hash.
 	pub best_hash: B::Hash,
 	/// Their best block number.
-	pub best_number: NumberFor<B>
+	pub best_number: NumberFor<B>,
+	/// The best block we know to be common to our chains.
+	pub common_number: NumberFor<B>,
+	/// What we are downloading from them.
+	pub state: PeerSyncState<B>,
 }
 
 struct ForkTarget<B: BlockT> {
@@ -351,6 +354,7 @@ impl<B: BlockT> ChainSync<B> {
 		request_builder: Option<BoxFinalityProofRequestBuilder<B>>,
 		block_announce_validator: Box<dyn BlockAnnounceValidator<B> + Send>,
 		max_parallel_downloads: u32,
+		max_blocks_per_request: u32,
 	) -> Self {
 		let mut required_block_attributes = BlockAttributes::HEADER | BlockAttributes::JUSTIFICATION;
 
@@ -375,6 +379,7 @@ impl<B: BlockT> ChainSync<B> {
 			pending_requests: Default::default(),
 			block_announce_validator,
 			max_parallel_downloads,
+			max_blocks_per_request,
 			processed_blocks: 0,
 		}
 	}
@@ -383,7 +388,12 @@ impl<B: BlockT> ChainSync<B> {
 	///
 	/// Returns `None` if the peer is unknown.
 	pub fn peer_info(&self, who: &PeerId) -> Option<PeerInfo<B>> {
-		self.peers.get(who).map(|p| PeerInfo { best_hash: p.best_hash, best_number: p.best_number })
+		self.peers.get(who).map(|p| PeerInfo {
+			best_hash: p.best_hash,
+			best_number: p.best_number,
+			common_number: p.common_number,
+			state: p.state,
+		})
 	}
 
 	/// Returns the current sync status.
@@ -658,6 +668,7 @@ impl<B: BlockT> ChainSync<B> {
 		let queue = &self.queue_blocks;
 		let pending_requests = self.pending_requests.take();
 		let max_parallel = if major_sync { 1 } else { self.max_parallel_downloads };
+		let max_blocks_per_request = self.max_blocks_per_request;
 		let iter = self.peers.iter_mut().filter_map(move |(id, peer)| {
 			if !peer.state.is_available() || !pending_requests.contains(id) {
 				return None
@@ -669,6 +680,7 @@ impl<B: BlockT> ChainSync<B> {
 				blocks,
 				attrs,
 				max_parallel,
+				max_blocks_per_request,
 				last_finalized,
 				best_queued,
 			) {
@@ -1411,6 +1423,7 @@ fn peer_block_request<B: BlockT>(
 	blocks: &mut BlockCollection<B>,
 	attrs: &message::BlockAttributes,
 	max_parallel_downloads: u32,
+	max_blocks_per_request: u32,
 	finalized: NumberFor<B>,
 	best_num: NumberFor<B>,
 ) -> Option<(Range<NumberFor<B>>, BlockRequest<B>)> {
@@ -1427,7 +1440,7 @@ fn peer_block_request<B: BlockT>(
 	}
 	if let Some(range) = blocks.needed_blocks(
 		id.clone(),
-		MAX_BLOCKS_TO_REQUEST,
+		max_blocks_per_request as usize,
 		peer.best_number,
 		peer.common_number,
 		max_parallel_downloads,
@@ -1573,6 +1586,7 @@ mod test {
 			None,
 			block_announce_validator,
 			1,
+			128,
 		);
 
 		let (a1_hash, a1_number) = {
@@ -0,0 +1,717 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+//
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! `NetworkBehaviour` implementation of the request-response protocols registered by the node
+//! implementation through [`NetworkConfiguration::request_response_protocols`].
+//!
+//! Every request is sent on a separate connection substream which gets closed after the
+//! response has been sent back. Requests and responses are opaque bytes, prefixed with their
+//! length on the wire. Responses start with a status byte telling whether the request has been
+//! answered, or refused because the remote is busy or its handler dropped the request.
+//!
+//! Incoming requests are forwarded to the channel of the protocol's [`RequestResponseConfig`]
+//! as [`IncomingRequest`]s, and answered by sending the response back through their
+//! `pending_response` sender.
+//!
+//! > **Important**: Requests must only be sent to peers known to support the protocol. As for
+//! > the other request protocols, the connection to a peer that fails to negotiate the protocol
+//! > is closed.
+//!
+//! [`NetworkConfiguration::request_response_protocols`]:
+//! crate::config::NetworkConfiguration::request_response_protocols
+
+use bytes::Bytes;
+use futures::{
+	channel::{mpsc, oneshot},
+	future::{self, BoxFuture, Either},
+	prelude::*,
+	stream::FuturesUnordered,
+};
+use futures_timer::Delay;
+use libp2p::{
+	core::{
+		ConnectedPoint,
+		Multiaddr,
+		PeerId,
+		connection::ConnectionId,
+		upgrade::{InboundUpgrade, OutboundUpgrade, ReadOneError, UpgradeInfo},
+		upgrade::{read_one, write_one},
+	},
+	swarm::{
+		NegotiatedSubstream,
+		NetworkBehaviour,
+		NetworkBehaviourAction,
+		NotifyHandler,
+		OneShotHandler,
+		OneShotHandlerConfig,
+		PollParameters,
+		SubstreamProtocol,
+	},
+};
+use std::{
+	borrow::Cow,
+	collections::{HashMap, HashSet, VecDeque},
+	io,
+	iter,
+	pin::Pin,
+	time::Duration,
+	task::{Context, Poll},
+	vec,
+};
+use wasm_timer::Instant;
+
+/// Status of a response whose payload follows.
+const RESPONSE_OK: u8 = 0;
+/// Status of the response to a request refused because the inbound queue is full.
+const RESPONSE_BUSY: u8 = 1;
+/// Status of the response to a request the handler dropped without answering it.
+const RESPONSE_REFUSED: u8 = 2;
+
+/// Configuration of a request-response protocol.
+#[derive(Debug, Clone)]
+pub struct RequestResponseConfig {
+	name: Cow<'static, str>,
+	max_request_size: usize,
+	max_response_size: usize,
+	request_timeout: Duration,
+	inbound_queue: Option<mpsc::Sender<IncomingRequest>>,
+	allowed_peers: Option<HashSet<PeerId>>,
+}
+
+impl RequestResponseConfig {
+	/// Create a fresh configuration for the protocol with the given name on the wire (e.g.
+	/// `/foo/bar/1`) and the following options:
+	///
+	/// - max. request size = 1 MiB
+	/// - max. response size = 16 MiB
+	/// - request timeout = 20s
+	/// - no inbound queue, meaning that incoming requests are answered with a busy status
+	/// - the requests of all the peers are accepted
+	pub fn new(name: impl Into<Cow<'static, str>>) -> Self {
+		RequestResponseConfig {
+			name: name.into(),
+			max_request_size: 1024 * 1024,
+			max_response_size: 16 * 1024 * 1024,
+			request_timeout: Duration::from_secs(20),
+			inbound_queue: None,
+			allowed_peers: None,
+		}
+	}
+
+	/// Returns the name of the protocol on the wire.
+	pub fn name(&self) -> &str {
+		&self.name
+	}
+
+	/// Limit the max. length in bytes of incoming requests.
+	pub fn set_max_request_size(&mut self, v: usize) -> &mut Self {
+		self.max_request_size = v;
+		self
+	}
+
+	/// Limit the max. length in bytes of incoming responses.
+	pub fn set_max_response_size(&mut self, v: usize) -> &mut Self {
+		self.max_response_size = v;
+		self
+	}
+
+	/// Set the time after which an outgoing request fails, or an incoming request that hasn't
+	/// been answered is dropped.
+	pub fn set_request_timeout(&mut self, v: Duration) -> &mut Self {
+		self.request_timeout = v;
+		self
+	}
+
+	/// Set the channel incoming requests are sent to.
+	///
+	/// Incoming requests are answered with a busy status while the channel is full.
+	pub fn set_inbound_queue(&mut self, v: mpsc::Sender<IncomingRequest>) -> &mut Self {
+		self.inbound_queue = Some(v);
+		self
+	}
+
+	/// Only accept the requests of the given peers. The requests of the other peers are refused
+	/// before reaching the inbound queue, so that they can't fill it.
+	pub fn set_allowed_peers(&mut self, v: impl IntoIterator<Item = PeerId>) -> &mut Self {
+		self.allowed_peers = Some(v.into_iter().collect());
+		self
+	}
+}
+
+/// A request coming from a remote, to be answered through `pending_response`.
+#[derive(Debug)]
+pub struct IncomingRequest {
+	/// Peer which has sent the request.
+	pub peer: PeerId,
+	/// Request sent by the remote.
+	pub payload: Vec<u8>,
+	/// Channel to send the response to. Dropping it without sending anything refuses the
+	/// request.
+	pub pending_response: oneshot::Sender<Vec<u8>>,
+}
+
+/// Reason why sending a request failed.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum RequestFailure {
+	/// We are not connected to the target.
+	#[error("not connected to the requested peer")]
+	NotConnected,
+	/// No request-response protocol with this name has been registered.
+	#[error("unknown protocol")]
+	UnknownProtocol,
+	/// The remote is too busy to handle the request.
+	#[error("remote is busy")]
+	Busy,
+	/// The remote refused the request, or closed the substream or the connection without
+	/// answering.
+	#[error("remote refused the request")]
+	Refused,
+	/// The remote didn't answer in time.
+	#[error("request timed out")]
+	Timeout,
+	/// The network worker has shut down.
+	#[error("network has shut down")]
+	Network,
+}
+
+/// Reason why answering an incoming request failed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ResponseFailure {
+	/// There is no inbound queue for the protocol, or it is full.
+	Busy,
+	/// The request handler dropped the request without answering it.
+	Refused,
+	/// The request handler didn't answer in time.
+	Timeout,
+	/// Writing the response to the remote failed.
+	Network,
+}
+
+/// Event generated by the request-responses behaviour.
+#[derive(Debug)]
+pub enum Event {
+	/// An incoming request has been answered, or failed to be.
+	InboundRequest {
+		/// Peer which has emitted the request.
+		peer: PeerId,
+		/// Protocol name of the request.
+		protocol: Bytes,
+		/// Time elapsed between when we received the request and when we sent back the
+		/// response, or why the request couldn't be answered.
+		result: Result<Duration, ResponseFailure>,
+	},
+
+	/// A request has been sent to a peer.
+	RequestStarted {
+		peer: PeerId,
+		/// Protocol name of the request.
+		protocol: Bytes,
+	},
+
+	/// A request started with `send_request` has finished, successfully or not.
+	RequestFinished {
+		peer: PeerId,
+		/// Protocol name of the request.
+		protocol: Bytes,
+		/// Time elapsed between the start of the request and the response or the failure.
+		request_duration: Duration,
+		/// Whether the request succeeded.
+		result: Result<(), RequestFailure>,
+	},
+}
+
+/// Identifier of an outgoing request.
+type RequestId = u64;
+
+/// Behaviour handling all the request-response protocols registered through the configuration.
+pub struct RequestResponsesBehaviour {
+	/// Registered protocols, indexed by their name on the wire.
+	protocols: HashMap<Bytes, RequestResponseConfig>,
+	/// List of all active connections.
+	peers: HashMap<PeerId, Vec<ConnectionId>>,
+	/// Requests we've sent and haven't got a response to yet.
+	pending_requests: HashMap<RequestId, PendingRequest>,
+	/// Identifier of the next outgoing request.
+	next_request_id: RequestId,
+	/// Futures waiting for the answer to an incoming request and sending it back. Returns the
+	/// `PeerId` and protocol of the request, and the outcome.
+	pending_responses: FuturesUnordered<
+		BoxFuture<'static, (PeerId, Bytes, Result<Duration, ResponseFailure>)>
+	>,
+	/// Events to return as soon as possible from `poll`.
+	pending_events: VecDeque<NetworkBehaviourAction<OutboundProtocol, Event>>,
+}
+
+/// Outgoing request waiting for its response.
+struct PendingRequest {
+	peer: PeerId,
+	connection: ConnectionId,
+	protocol: Bytes,
+	/// `Instant` when the request has been emitted. Used for diagnostic purposes.
+	emitted: Instant,
+	timeout: Delay,
+	pending_response: oneshot::Sender<Result<Vec<u8>, RequestFailure>>,
+}
+
+impl RequestResponsesBehaviour {
+	/// Creates a new behaviour handling the given protocols.
+	pub fn new(protocols: impl IntoIterator<Item = RequestResponseConfig>) -> Self {
+		let protocols = protocols.into_iter()
+			.map(|config| (Bytes::copy_from_slice(config.name.as_bytes()), config))
+			.collect();
+
+		RequestResponsesBehaviour {
+			protocols,
+			peers: HashMap::new(),
+			pending_requests: HashMap::new(),
+			next_request_id: 0,
+			pending_responses: FuturesUnordered::new(),
+			pending_events: VecDeque::new(),
+		}
+	}
+
+	/// Sends a request to `target` on the given protocol.
+	///
+	/// The response, or the reason why the request failed, is sent to `pending_response`.
+	pub fn send_request(
+		&mut self,
+		target: &PeerId,
+		protocol: &str,
+		request: Vec<u8>,
+		pending_response: oneshot::Sender<Result<Vec<u8>, RequestFailure>>,
+	) {
+		let protocol = Bytes::copy_from_slice(protocol.as_bytes());
+		let (max_response_size, request_timeout) = match self.protocols.get(&protocol) {
+			Some(config) => (config.max_response_size, config.request_timeout),
+			None => {
+				let _ = pending_response.send(Err(RequestFailure::UnknownProtocol));
+				return;
+			}
+		};
+
+		let connection = match self.peers.get(target).and_then(|connections| connections.first()) {
+			Some(connection) => *connection,
+			None => {
+				let _ = pending_response.send(Err(RequestFailure::NotConnected));
+				return;
+			}
+		};
+
+		let request_id = self.next_request_id;
+		self.next_request_id += 1;
+
+		self.pending_requests.insert(request_id, PendingRequest {
+			peer: target.clone(),
+			connection,
+			protocol: protocol.clone(),
+			emitted: Instant::now(),
+			timeout: Delay::new(request_timeout),
+			pending_response,
+		});
+
+		log::trace!(
+			target: "sub-libp2p",
+			"Enqueueing {:?} request to {:?}",
+			String::from_utf8_lossy(&protocol),
+			target,
+		);
+		self.pending_events.push_back(NetworkBehaviourAction::NotifyHandler {
+			peer_id: target.clone(),
+			handler: NotifyHandler::One(connection),
+			event: OutboundProtocol {
+				request,
+				request_id,
+				max_response_size,
+				protocol: protocol.clone(),
+			},
+		});
+		self.pending_events.push_back(NetworkBehaviourAction::GenerateEvent(Event::RequestStarted {
+			peer: target.clone(),
+			protocol,
+		}));
+	}
+
+	/// Removes the given pending request and reports `result` to whoever sent it.
+	fn finish_request(&mut self, request_id: RequestId, result: Result<Vec<u8>, RequestFailure>) {
+		let request = match self.pending_requests.remove(&request_id) {
+			Some(request) => request,
+			None => return,
+		};
+
+		let ev = Event::RequestFinished {
+			peer: request.peer,
+			protocol: request.protocol,
+			request_duration: request.emitted.elapsed(),
+			result: result.as_ref().map(|_| ()).map_err(Clone::clone),
+		};
+		let _ = request.pending_response.send(result);
+		self.pending_events.push_back(NetworkBehaviourAction::GenerateEvent(ev));
+	}
+}
+
+impl NetworkBehaviour for RequestResponsesBehaviour {
+	type ProtocolsHandler = OneShotHandler<InboundProtocol, OutboundProtocol, HandlerEvent<NegotiatedSubstream>>;
+	type OutEvent = Event;
+
+	fn new_handler(&mut self) -> Self::ProtocolsHandler {
+		let p = InboundProtocol {
+			protocols: self.protocols.iter()
+				.map(|(name, config)| (name.clone(), config.max_request_size))
+				.collect(),
+		};
+		let mut cfg = OneShotHandlerConfig::default();
+		if let Some(timeout) = self.protocols.values().map(|config| config.request_timeout).max() {
+			cfg.substream_timeout = timeout;
+		}
+		OneShotHandler::new(SubstreamProtocol::new(p), cfg)
+	}
+
+	fn addresses_of_peer(&mut self, _: &PeerId) -> Vec<Multiaddr> {
+		Vec::new()
+	}
+
+	fn inject_connected(&mut self, _peer: &PeerId) {
+	}
+
+	fn inject_disconnected(&mut self, _peer: &PeerId) {
+	}
+
+	fn inject_connection_established(&mut self, peer_id: &PeerId, id: &ConnectionId, _: &ConnectedPoint) {
+		self.peers.entry(peer_id.clone()).or_default().push(*id);
+	}
+
+	fn inject_connection_closed(&mut self, peer_id: &PeerId, id: &ConnectionId, _: &ConnectedPoint) {
+		if let Some(connections) = self.peers.get_mut(peer_id) {
+			connections.retain(|c| c != id);
+			if connections.is_empty() {
+				self.peers.remove(peer_id);
+			}
+		}
+
+		let cancelled = self.pending_requests.iter()
+			.filter(|(_, request)| request.connection == *id)
+			.map(|(request_id, _)| *request_id)
+			.collect::<Vec<_>>();
+		for request_id in cancelled {
+			log::debug!(
+				target: "sub-libp2p",
+				"Connection {:?} with {} closed with ongoing request",
+				id,
+				peer_id,
+			);
+			self.finish_request(request_id, Err(RequestFailure::Refused));
+		}
+	}
+
+	fn inject_event(
+		&mut self,
+		peer: PeerId,
+		_: ConnectionId,
+		event: HandlerEvent<NegotiatedSubstream>
+	) {
+		match event {
+			HandlerEvent::Request { protocol, payload, mut substream, handling_start } => {
+				let config = match self.protocols.get_mut(&protocol) {
+					Some(config) => config,
+					None => return,
+				};
+
+				let (tx, rx) = oneshot::channel();
+				let allowed = config.allowed_peers.as_ref()
+					.map_or(true, |allowed_peers| allowed_peers.contains(&peer));
+				let response = if allowed {
+					let request = IncomingRequest {
+						peer: peer.clone(),
+						payload,
+						pending_response: tx,
+					};
+					let queued = config.inbound_queue.as_mut()
+						.map_or(false, |queue| queue.try_send(request).is_ok());
+					// The remote is told it is busy when the queue is full.
+					if queued { Some(rx) } else { None }
+				} else {
+					// Dropping the sender refuses the request.
+					drop(tx);
+					Some(rx)
+				};
+
+				let timeout = Delay::new(config.request_timeout);
+				self.pending_responses.push(async move {
+					let result = answer_request(&mut substream, response, timeout, handling_start)
+						.await;
+					(peer, protocol, result)
+				}.boxed());
+			}
+			HandlerEvent::Response { request_id, result } => {
+				if !self.pending_requests.contains_key(&request_id) {
+					// Requests are removed when they time out, so this can legitimately happen.
+					log::trace!(
+						target: "sub-libp2p",
+						"Response from {} discarded because it concerns an obsolete request",
+						peer,
+					);
+					return;
+				}
+				self.finish_request(request_id, result);
+			}
+		}
+	}
+
+	fn poll(&mut self, cx: &mut Context, _: &mut impl PollParameters)
+		-> Poll<NetworkBehaviourAction<OutboundProtocol, Event>>
+	{
+		// Check the request timeouts.
+		let timed_out = self.pending_requests.iter_mut()
+			.filter_map(|(request_id, request)| {
+				match Pin::new(&mut request.timeout).poll(cx) {
+					Poll::Ready(()) => Some(*request_id),
+					Poll::Pending => None,
+				}
+			})
+			.collect::<Vec<_>>();
+		for request_id in timed_out {
+			self.finish_request(request_id, Err(RequestFailure::Timeout));
+		}
+
+		if let Some(ev) = self.pending_events.pop_front() {
+			return Poll::Ready(ev);
+		}
+
+		let next_response = self.pending_responses.poll_next_unpin(cx);
+		if let Poll::Ready(Some((peer, protocol, result))) = next_response {
+			let ev = Event::InboundRequest { peer, protocol, result };
+			return Poll::Ready(NetworkBehaviourAction::GenerateEvent(ev));
+		}
+
+		Poll::Pending
+	}
+}
+
+/// Output type of inbound and outbound substream upgrades.
+#[derive(Debug)]
+pub enum HandlerEvent<T> {
+	/// Incoming request from remote on the given protocol, substream to use for the response,
+	/// and when we started handling this request.
+	Request {
+		protocol: Bytes,
+		payload: Vec<u8>,
+		substream: T,
+		handling_start: Instant,
+	},
+	/// Incoming response from remote to the given request.
+	Response {
+		request_id: RequestId,
+		result: Result<Vec<u8>, RequestFailure>,
+	},
+}
+
+/// Waits for the answer to an incoming request and writes it to `substream`.
+///
+/// Without `response`, the request is refused because the remote is busy.
+async fn answer_request<T: AsyncWrite + Unpin>(
+	substream: &mut T,
+	response: Option<oneshot::Receiver<Vec<u8>>>,
+	timeout: Delay,
+	handling_start: Instant,
+) -> Result<Duration, ResponseFailure> {
+	let (status, payload, result) = match response {
+		None => (RESPONSE_BUSY, Vec::new(), Err(ResponseFailure::Busy)),
+		Some(response) => match future::select(response, timeout).await {
+			Either::Left((Ok(payload), _)) => (RESPONSE_OK, payload, Ok(())),
+			Either::Left((Err(_), _)) =>
+				(RESPONSE_REFUSED, Vec::new(), Err(ResponseFailure::Refused)),
+			// The remote has given up on the request as well.
+			Either::Right(_) => return Err(ResponseFailure::Timeout),
+		},
+	};
+
+	let mut message = Vec::with_capacity(1 + payload.len());
+	message.push(status);
+	message.extend_from_slice(&payload);
+	let written = write_one(substream, message).await;
+	if let Err(ref e) = written {
+		log::debug!(target: "sub-libp2p", "Error writing response: {}", e);
+	}
+
+	match (result, written) {
+		(Ok(()), Ok(())) => Ok(handling_start.elapsed()),
+		(Ok(()), Err(_)) => Err(ResponseFailure::Network),
+		(Err(failure), _) => Err(failure),
+	}
+}
+
+/// Reads the response to a request, of at most `max_size` bytes besides its status.
+async fn read_response<T: AsyncRead + Unpin>(
+	substream: &mut T,
+	max_size: usize,
+) -> Result<Result<Vec<u8>, RequestFailure>, ReadOneError> {
+	let mut message = read_one(substream, max_size.saturating_add(1)).await?;
+	if message.is_empty() {
+		return Err(ReadOneError::Io(io::Error::new(io::ErrorKind::InvalidData, "empty response")));
+	}
+
+	let payload = message.split_off(1);
+	Ok(match message[0] {
+		RESPONSE_OK => Ok(payload),
+		RESPONSE_BUSY => Err(RequestFailure::Busy),
+		_ => Err(RequestFailure::Refused),
+	})
+}
+
+/// Substream upgrade protocol.
+///
+/// Accepts a request on any of the registered protocols, which will be handled by the
+/// `RequestResponsesBehaviour`.
+#[derive(Debug, Clone)]
+pub struct InboundProtocol {
+	/// The protocols to accept during upgrade negotiation, with the max. request length in
+	/// bytes of each.
+	protocols: Vec<(Bytes, usize)>,
+}
+
+impl UpgradeInfo for InboundProtocol {
+	type Info = Bytes;
+	type InfoIter = vec::IntoIter<Self::Info>;
+
+	fn protocol_info(&self) -> Self::InfoIter {
+		self.protocols.iter().map(|(name, _)| name.clone()).collect::<Vec<_>>().into_iter()
+	}
+}
+
+impl<T> InboundUpgrade<T> for InboundProtocol
+where
+	T: AsyncRead + AsyncWrite + Unpin + Send + 'static
+{
+	type Output = HandlerEvent<T>;
+	type Error = ReadOneError;
+	type Future = BoxFuture<'static, Result<Self::Output, Self::Error>>;
+
+	fn upgrade_inbound(self, mut s: T, protocol: Self::Info) -> Self::Future {
+		// This `Instant` will be passed around until the processing of this request is done.
+		let handling_start = Instant::now();
+		let max_request_len = self.protocols.iter()
+			.find(|(name, _)| *name == protocol)
+			.map(|(_, len)| *len);
+
+		async move {
+			let max_request_len = max_request_len.ok_or_else(|| {
+				ReadOneError::Io(io::Error::new(io::ErrorKind::Other, "unknown protocol"))
+			})?;
+			let payload = read_one(&mut s, max_request_len).await?;
+			Ok(HandlerEvent::Request { protocol, payload, substream: s, handling_start })
+		}.boxed()
+	}
+}
+
+/// Substream upgrade protocol.
+///
+/// Sends a request to remote and awaits the response.
+#[derive(Debug, Clone)]
+pub struct OutboundProtocol {
+	/// The request to send.
+	request: Vec<u8>,
+	/// Identifier of the request. Passed back through the API when the response comes back.
+	request_id: RequestId,
+	/// The max. response length in bytes.
+	max_response_size: usize,
+	/// The protocol to use for upgrade negotiation.
+	protocol: Bytes,
+}
+
+impl UpgradeInfo for OutboundProtocol {
+	type Info = Bytes;
+	type InfoIter = iter::Once<Self::Info>;
+
+	fn protocol_info(&self) -> Self::InfoIter {
+		iter::once(self.protocol.clone())
+	}
+}
+
+impl<T> OutboundUpgrade<T> for OutboundProtocol
+where
+	T: AsyncRead + AsyncWrite + Unpin + Send + 'static
+{
+	type Output = HandlerEvent<T>;
+	type Error = ReadOneError;
+	type Future = BoxFuture<'static, Result<Self::Output, Self::Error>>;
+
+	fn upgrade_outbound(self, mut s: T, _: Self::Info) -> Self::Future {
+		async move {
+			write_one(&mut s, &self.request).await?;
+			let result = read_response(&mut s, self.max_response_size).await?;
+			Ok(HandlerEvent::Response { request_id: self.request_id, result })
+		}.boxed()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use futures::{executor::block_on, io::Cursor};
+
+	/// Answers a request with `response` and returns what the remote reads.
+	fn answer(
+		response: Option<oneshot::Receiver<Vec<u8>>>,
+	) -> (Result<Duration, ResponseFailure>, Result<Vec<u8>, RequestFailure>) {
+		let mut substream = Cursor::new(Vec::new());
+		let timeout = Delay::new(Duration::from_secs(10));
+		let result = block_on(answer_request(&mut substream, response, timeout, Instant::now()));
+
+		let mut substream = Cursor::new(substream.into_inner());
+		let read = block_on(read_response(&mut substream, 1024)).unwrap();
+		(result, read)
+	}
+
+	#[test]
+	fn answers_are_sent_back() {
+		let (tx, rx) = oneshot::channel();
+		tx.send(b"pong".to_vec()).unwrap();
+
+		let (result, read) = answer(Some(rx));
+		assert!(result.is_ok());
+		assert_eq!(read, Ok(b"pong".to_vec()));
+	}
+
+	#[test]
+	fn busy_remote_answers_it_is_busy() {
+		let (result, read) = answer(None);
+		assert_eq!(result, Err(ResponseFailure::Busy));
+		assert_eq!(read, Err(RequestFailure::Busy));
+	}
+
+	#[test]
+	fn dropped_requests_are_refused() {
+		let (tx, rx) = oneshot::channel();
+		drop(tx);
+
+		let (result, read) = answer(Some(rx));
+		assert_eq!(result, Err(ResponseFailure::Refused));
+		assert_eq!(read, Err(RequestFailure::Refused));
+	}
+
+	#[test]
+	fn oversized_responses_are_rejected() {
+		let (tx, rx) = oneshot::channel();
+		tx.send(vec![0; 2048]).unwrap();
+
+		let mut substream = Cursor::new(Vec::new());
+		let timeout = Delay::new(Duration::from_secs(10));
+		block_on(answer_request(&mut substream, Some(rx), timeout, Instant::now())).unwrap();
+
+		let mut substream = Cursor::new(substream.into_inner());
+		assert!(block_on(read_response(&mut substream, 1024)).is_err());
+	}
+}
@@ -37,11 +37,14 @@ use crate::{
 		NetworkState, NotConnectedPeer as NetworkStateNotConnectedPeer, Peer as NetworkStatePeer,
 	},
 	on_demand_layer::AlwaysBadChecker,
-	light_client_handler, block_requests, finality_requests,
-	protocol::{self, event::Event, LegacyConnectionKillError, sync::SyncState, PeerInfo, Protocol},
+	light_client_handler, block_requests, finality_requests, request_responses,
+	protocol::{
+		self, event::Event, LegacyConnectionKillError, sync::SyncState, PeerInfo, PeerSyncDetails,
+		Protocol,
+	},
 	transport, ReputationChange,
 };
-use futures::prelude::*;
+use futures::{channel::oneshot, prelude::*};
 use libp2p::{PeerId, Multiaddr};
 use libp2p::core::{ConnectedPoint, Executor, connection::{ConnectionError, PendingConnectionError}, either::EitherError};
 use libp2p::kad::record;
@@ -74,10 +77,13 @@ use std::{
 	task::Poll,
 };
 
+mod notifications;
 mod out_events;
 #[cfg(test)]
 mod tests;
 
+pub use notifications::{NotificationsStreams, PeerNotifications};
+
 /// Substrate network service. Handles network IO and manages connectivity.
 pub struct NetworkService<B: BlockT + 'static, H: ExHashT> {
 	/// Number of peers we're connected to.
@@ -86,6 +92,8 @@ pub struct NetworkService<B: BlockT + 'static, H: ExHashT> {
 	external_addresses: Arc<Mutex<Vec<Multiaddr>>>,
 	/// Are we actively catching up with the chain?
 	is_major_syncing: Arc<AtomicBool>,
+	/// Has the node been put into maintenance mode? Notifications aren't sent while it is.
+	is_in_maintenance: Arc<AtomicBool>,
 	/// Local copy of the `PeerId` of the local node.
 	local_peer_id: PeerId,
 	/// Bandwidth logging system. Can be queried to know the average bandwidth consumed.
@@ -211,6 +219,10 @@ impl<B: BlockT + 'static, H: ExHashT> NetworkWorker<B, H> {
 			protocol::ProtocolConfig {
 				roles: From::from(&params.role),
 				max_parallel_downloads: params.network_config.max_parallel_downloads,
+				max_blocks_per_request: params.network_config.max_blocks_per_request,
+				block_request_timeout: params.network_config.block_request_timeout,
+				max_light_peers: params.network_config.max_light_peers,
+				sync_peer_strategy: params.network_config.sync_peer_strategy,
 			},
 			local_peer_id.clone(),
 			params.chain.clone(),
@@ -234,7 +246,8 @@ impl<B: BlockT + 'static, H: ExHashT> NetworkWorker<B, H> {
 				params.network_config.node_name
 			);
 			let block_requests = {
-				let config = block_requests::Config::new(&params.protocol_id);
+				let mut config = block_requests::Config::new(&params.protocol_id);
+				config.set_request_timeout(params.network_config.block_request_timeout);
 				block_requests::BlockRequests::new(config, params.chain.clone())
 			};
 			let finality_proof_requests = {
@@ -242,15 +255,27 @@ impl<B: BlockT + 'static, H: ExHashT> NetworkWorker<B, H> {
 				finality_requests::FinalityProofRequests::new(config, params.finality_proof_provider.clone())
 			};
 			let light_client_handler = {
-				let config = light_client_handler::Config::new(&params.protocol_id);
-				light_client_handler::LightClientHandler::new(
+				let network_config = &params.network_config;
+				let mut config = light_client_handler::Config::new(&params.protocol_id);
+				config
+					.set_max_served_requests_per_sec(network_config.max_light_requests_per_sec)
+					.set_max_served_response_size(network_config.max_light_response_size);
+				let mut handler = light_client_handler::LightClientHandler::new(
 					config,
 					params.chain,
 					checker,
 					peerset_handle.clone(),
-				)
+				);
+				if let Some(registry) = &params.metrics_registry {
+					handler.register_metrics(registry)?;
+				}
+				handler
 			};
 
+			let request_responses = request_responses::RequestResponsesBehaviour::new(
+				params.network_config.request_response_protocols.iter().cloned()
+			);
+
 			let discovery_config = {
 				let mut config = DiscoveryConfig::new(local_public.clone());
 				config.with_user_defined(known_addresses);
@@ -280,6 +305,7 @@ impl<B: BlockT + 'static, H: ExHashT> NetworkWorker<B, H> {
 				block_requests,
 				finality_proof_requests,
 				light_client_handler,
+				request_responses,
 				discovery_config
 			);
 
@@ -329,6 +355,7 @@ impl<B: BlockT + 'static, H: ExHashT> NetworkWorker<B, H> {
 			external_addresses: external_addresses.clone(),
 			num_connected: num_connected.clone(),
 			is_major_syncing: is_major_syncing.clone(),
+			is_in_maintenance: Arc::new(AtomicBool::new(false)),
 			peerset: peerset_handle,
 			local_peer_id,
 			to_worker,
@@ -488,6 +515,7 @@ impl<B: BlockT + 'static, H: ExHashT> NetworkWorker<B, H> {
 			peer_id: Swarm::<B, H>::local_peer_id(&swarm).to_base58(),
 			listened_addresses: Swarm::<B, H>::listeners(&swarm).cloned().collect(),
 			external_addresses: Swarm::<B, H>::external_addresses(&swarm).cloned().collect(),
+			confirmed_addresses: swarm.confirmed_addresses().cloned().collect(),
 			average_download_per_sec: self.service.bandwidth.average_download_per_sec(),
 			average_upload_per_sec: self.service.bandwidth.average_upload_per_sec(),
 			connected_peers,
@@ -504,6 +532,11 @@ impl<B: BlockT + 'static, H: ExHashT> NetworkWorker<B, H> {
 			.collect()
 	}
 
+	/// Get the diagnostics of the block requests made to the currently connected peers.
+	pub fn peers_sync_details(&mut self) -> Vec<(PeerId, PeerSyncDetails<B>)> {
+		self.network_service.user_protocol_mut().peers_sync_details()
+	}
+
 	/// Removes a `PeerId` from the list of reserved peers.
 	pub fn remove_reserved_peer(&self, peer: PeerId) {
 		self.service.remove_reserved_peer(peer);
@@ -533,7 +566,13 @@ impl<B: BlockT + 'static, H: ExHashT> NetworkService<B, H> {
 	///
 	/// The protocol must have been registered with `register_notifications_protocol`.
 	///
+	/// The message is dropped while the node is in maintenance mode.
+	///
 	pub fn write_notification(&self, target: PeerId, engine_id: ConsensusEngineId, message: Vec<u8>) {
+		if self.is_in_maintenance() {
+			return;
+		}
+
 		let _ = self.to_worker.unbounded_send(ServiceToWorkerMsg::WriteNotification {
 			target,
 			engine_id,
@@ -557,6 +596,36 @@ impl<B: BlockT + 'static, H: ExHashT> NetworkService<B, H> {
 		rx
 	}
 
+	/// Returns a stream of the peers we open a substream with on the given notifications
+	/// protocol, each together with the stream of notifications it sends us.
+	///
+	/// The name passed is used to identify the channel in the Prometheus metrics, as for
+	/// [`event_stream`](NetworkService::event_stream).
+	pub fn notifications_streams(
+		&self,
+		engine_id: ConsensusEngineId,
+		name: &'static str,
+	) -> NotificationsStreams {
+		let (tx, rx) = out_events::channel(name);
+		let _ = self.to_worker.unbounded_send(ServiceToWorkerMsg::EventStream(tx));
+		NotificationsStreams::new(engine_id, rx)
+	}
+
+	/// Sets the handshake message sent to peers when opening a substream of the given
+	/// notifications protocol. By default, this is the encoded role of the local node.
+	///
+	/// Substreams that are already open keep the handshake they were opened with.
+	pub fn set_notifications_handshake(
+		&self,
+		engine_id: ConsensusEngineId,
+		handshake_message: Vec<u8>,
+	) {
+		let _ = self.to_worker.unbounded_send(ServiceToWorkerMsg::SetNotifHandshake {
+			engine_id,
+			handshake_message,
+		});
+	}
+
 	/// Registers a new notifications protocol.
 	///
 	/// After a protocol has been registered, you can call `write_notifications`.
@@ -633,6 +702,25 @@ impl<B: BlockT + 'static, H: ExHashT> NetworkService<B, H> {
 		self.is_major_syncing.load(Ordering::Relaxed)
 	}
 
+	/// Puts the node into maintenance mode, or takes it out of it.
+	///
+	/// In maintenance mode, the node keeps syncing and answering requests but it doesn't author
+	/// blocks nor sends notifications, such as the gossip messages of the consensus engines.
+	pub fn set_maintenance(&self, enabled: bool) {
+		if self.is_in_maintenance.swap(enabled, Ordering::Relaxed) != enabled {
+			if enabled {
+				info!(target: "sub-libp2p", "Entering maintenance mode");
+			} else {
+				info!(target: "sub-libp2p", "Leaving maintenance mode");
+			}
+		}
+	}
+
+	/// Is the node in maintenance mode?
+	pub fn is_in_maintenance(&self) -> bool {
+		self.is_in_maintenance.load(Ordering::Relaxed)
+	}
+
 	/// Start getting a value from the DHT.
 	///
 	/// This will generate either a `ValueFound` or a `ValueNotFound` event and pass it as an
@@ -653,6 +741,32 @@ impl<B: BlockT + 'static, H: ExHashT> NetworkService<B, H> {
 			.unbounded_send(ServiceToWorkerMsg::PutValue(key, value));
 	}
 
+	/// Sends a single request to `target` on a request-response protocol registered through
+	/// [`NetworkConfiguration::request_response_protocols`](crate::config::NetworkConfiguration)
+	/// and waits for the response.
+	///
+	/// The target must be connected and support the protocol. The connection to a target that
+	/// doesn't support it is closed.
+	pub async fn request(
+		&self,
+		target: PeerId,
+		protocol: impl Into<Cow<'static, str>>,
+		request: Vec<u8>,
+	) -> Result<Vec<u8>, request_responses::RequestFailure> {
+		let (tx, rx) = oneshot::channel();
+		let _ = self.to_worker.unbounded_send(ServiceToWorkerMsg::Request {
+			target,
+			protocol: protocol.into(),
+			request,
+			pending_response: tx,
+		});
+
+		match rx.await {
+			Ok(result) => result,
+			Err(_) => Err(request_responses::RequestFailure::Network),
+		}
+	}
+
 	/// Connect to unreserved peers and allow unreserved peers to connect.
 	pub fn accept_unreserved_peers(&self) {
 		self.peerset.set_reserved_only(false);
@@ -725,6 +839,10 @@ impl<B: BlockT + 'static, H: ExHashT> sp_consensus::SyncOracle
 	fn is_offline(&mut self) -> bool {
 		self.num_connected.load(Ordering::Relaxed) == 0
 	}
+
+	fn is_in_maintenance(&mut self) -> bool {
+		NetworkService::is_in_maintenance(self)
+	}
 }
 
 impl<'a, B: BlockT + 'static, H: ExHashT> sp_consensus::SyncOracle
@@ -737,6 +855,10 @@ impl<'a, B: BlockT + 'static, H: ExHashT> sp_consensus::SyncOracle
 	fn is_offline(&mut self) -> bool {
 		self.num_connected.load(Ordering::Relaxed) == 0
 	}
+
+	fn is_in_maintenance(&mut self) -> bool {
+		NetworkService::is_in_maintenance(self)
+	}
 }
 
 impl<B, H> NetworkStateInfo for NetworkService<B, H>
@@ -777,7 +899,17 @@ enum ServiceToWorkerMsg<B: BlockT, H: ExHashT> {
 		engine_id: ConsensusEngineId,
 		protocol_name: Cow<'static, [u8]>,
 	},
+	SetNotifHandshake {
+		engine_id: ConsensusEngineId,
+		handshake_message: Vec<u8>,
+	},
 	DisconnectPeer(PeerId),
+	Request {
+		target: PeerId,
+		protocol: Cow<'static, str>,
+		request: Vec<u8>,
+		pending_response: oneshot::Sender<Result<Vec<u8>, request_responses::RequestFailure>>,
+	},
 }
 
 /// Main network worker. Must be polled in order for the network to advance.
@@ -1104,8 +1236,15 @@ impl<B: BlockT + 'static, H: ExHashT> Future for NetworkWorker<B, H> {
 					this.network_service
 						.register_notifications_protocol(engine_id, protocol_name);
 				},
+				ServiceToWorkerMsg::SetNotifHandshake { engine_id, handshake_message } =>
+					this.network_service.user_protocol_mut()
+						.set_notifications_handshake(engine_id, handshake_message),
 				ServiceToWorkerMsg::DisconnectPeer(who) =>
 					this.network_service.user_protocol_mut().disconnect_peer(&who),
+				ServiceToWorkerMsg::Request { target, protocol, request, pending_response } => {
+					this.network_service
+						.send_request(&target, &protocol, request, pending_response);
+				},
 			}
 		}
 
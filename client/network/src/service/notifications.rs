@@ -0,0 +1,141 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2020 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Per-peer streams of the notifications received on a notifications protocol.
+//!
+//! [`NotificationsStreams`] splits the events of a network event stream that concern a single
+//! notifications protocol into one [`PeerNotifications`] stream per peer we have a substream
+//! with.
+
+use crate::{Event, ObservedRole};
+use super::out_events;
+
+use bytes::Bytes;
+use futures::{prelude::*, channel::mpsc, ready};
+use libp2p::PeerId;
+use sp_runtime::ConsensusEngineId;
+use std::{collections::HashMap, pin::Pin, task::{Context, Poll}};
+
+/// Stream of the peers we open a substream with on a notifications protocol, together with
+/// their role and the notifications they send us.
+///
+/// The notifications are only dispatched to the [`PeerNotifications`] streams while this stream
+/// is being polled.
+pub struct NotificationsStreams {
+	engine_id: ConsensusEngineId,
+	events: out_events::Receiver,
+	peers: HashMap<PeerId, mpsc::UnboundedSender<Bytes>>,
+}
+
+impl NotificationsStreams {
+	pub(super) fn new(engine_id: ConsensusEngineId, events: out_events::Receiver) -> Self {
+		NotificationsStreams {
+			engine_id,
+			events,
+			peers: HashMap::new(),
+		}
+	}
+}
+
+impl Stream for NotificationsStreams {
+	type Item = (PeerId, ObservedRole, PeerNotifications);
+
+	fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+		let this = &mut *self;
+		loop {
+			let event = match ready!(this.events.poll_next_unpin(cx)) {
+				Some(event) => event,
+				None => return Poll::Ready(None),
+			};
+
+			match event {
+				Event::NotificationStreamOpened { remote, engine_id, role }
+					if engine_id == this.engine_id =>
+				{
+					let (tx, rx) = mpsc::unbounded();
+					this.peers.insert(remote.clone(), tx);
+					return Poll::Ready(Some((remote, role, PeerNotifications { inner: rx })));
+				}
+				Event::NotificationStreamClosed { remote, engine_id }
+					if engine_id == this.engine_id =>
+				{
+					this.peers.remove(&remote);
+				}
+				Event::NotificationsReceived { remote, messages } => {
+					if let Some(tx) = this.peers.get(&remote) {
+						for (engine_id, message) in messages {
+							if engine_id == this.engine_id {
+								let _ = tx.unbounded_send(message);
+							}
+						}
+					}
+				}
+				_ => {}
+			}
+		}
+	}
+}
+
+/// Notifications received from a single peer. Ends when the substream with the peer is closed.
+pub struct PeerNotifications {
+	inner: mpsc::UnboundedReceiver<Bytes>,
+}
+
+impl Stream for PeerNotifications {
+	type Item = Bytes;
+
+	fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Bytes>> {
+		self.inner.poll_next_unpin(cx)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn splits_notifications_per_peer() {
+		let (tx, rx) = out_events::channel("test");
+		let mut channels = out_events::OutChannels::new(None).unwrap();
+		channels.push(tx);
+
+		let remote = PeerId::random();
+		channels.send(Event::NotificationStreamOpened {
+			remote: remote.clone(),
+			engine_id: *b"test",
+			role: ObservedRole::Full,
+		});
+		channels.send(Event::NotificationsReceived {
+			remote: remote.clone(),
+			messages: vec![
+				(*b"test", Bytes::from_static(b"foo")),
+				(*b"othr", Bytes::from_static(b"bar")),
+			],
+		});
+		channels.send(Event::NotificationStreamClosed { remote: remote.clone(), engine_id: *b"test" });
+
+		let mut streams = NotificationsStreams::new(*b"test", rx);
+		let (peer, _, notifications) = futures::executor::block_on(streams.next()).unwrap();
+		assert_eq!(peer, remote);
+
+		drop(channels);
+		assert!(futures::executor::block_on(streams.next()).is_none());
+		let received = futures::executor::block_on(notifications.collect::<Vec<_>>());
+		assert_eq!(received, vec![Bytes::from_static(b"foo")]);
+	}
+}
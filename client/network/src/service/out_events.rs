@@ -38,6 +38,7 @@ use super::maybe_utf8_bytes_to_string;
 use futures::{prelude::*, channel::mpsc, ready};
 use parking_lot::Mutex;
 use prometheus_endpoint::{register, CounterVec, GaugeVec, Opts, PrometheusError, Registry, U64};
+use sp_utils::memory::{self, MemoryCharge, Subsystem};
 use std::{
 	convert::TryFrom as _,
 	fmt, pin::Pin, sync::Arc,
@@ -63,7 +64,7 @@ pub fn channel(name: &'static str) -> (Sender, Receiver) {
 /// implement the `Clone` trait e.g. in Order to not complicate the logic keeping the metrics in
 /// sync on drop. If someone adds a `#[derive(Clone)]` below, it is **wrong**.
 pub struct Sender {
-	inner: mpsc::UnboundedSender<Event>,
+	inner: mpsc::UnboundedSender<(Event, Option<Arc<MemoryCharge>>)>,
 	name: &'static str,
 	/// Clone of [`Receiver::metrics`].
 	metrics: Arc<Mutex<Option<Arc<Option<Metrics>>>>>,
@@ -86,7 +87,7 @@ impl Drop for Sender {
 
 /// Receiving side of a channel.
 pub struct Receiver {
-	inner: mpsc::UnboundedReceiver<Event>,
+	inner: mpsc::UnboundedReceiver<(Event, Option<Arc<MemoryCharge>>)>,
 	name: &'static str,
 	/// Initially contains `None`, and will be set to a value once the corresponding [`Sender`]
 	/// is assigned to an instance of [`OutChannels`].
@@ -97,7 +98,7 @@ impl Stream for Receiver {
 	type Item = Event;
 
 	fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Event>> {
-		if let Some(ev) = ready!(Pin::new(&mut self.inner).poll_next(cx)) {
+		if let Some((ev, _charge)) = ready!(Pin::new(&mut self.inner).poll_next(cx)) {
 			let metrics = self.metrics.lock().clone();
 			match metrics.as_ref().map(|m| m.as_ref()) {
 				Some(Some(metrics)) => metrics.event_out(&ev, self.name),
@@ -163,8 +164,19 @@ impl OutChannels {
 
 	/// Sends an event.
 	pub fn send(&mut self, event: Event) {
+		// Notifications are accounted to the network buffers until they are received by all the
+		// listeners. Their buffers are shared by the clones of the event, so they are accounted
+		// once for all the listeners.
+		let charge = match &event {
+			Event::NotificationsReceived { messages, .. } => {
+				let bytes = messages.iter().map(|(_, message)| message.len() as u64).sum();
+				Some(Arc::new(memory::charge(Subsystem::NetworkBuffers, bytes)))
+			},
+			_ => None,
+		};
+
 		self.event_streams.retain(|sender| {
-			sender.inner.unbounded_send(event.clone()).is_ok()
+			sender.inner.unbounded_send((event.clone(), charge.clone())).is_ok()
 		});
 
 		if let Some(metrics) = &*self.metrics {
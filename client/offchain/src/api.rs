@@ -31,6 +31,7 @@ use sp_core::offchain::{
 	OpaqueNetworkState, OpaquePeerId, OpaqueMultiaddr, StorageKind,
 };
 pub use sp_offchain::STORAGE_PREFIX;
+use crate::HttpConfig;
 
 #[cfg(not(target_os = "unknown"))]
 mod http;
@@ -260,8 +261,9 @@ impl AsyncApi {
 		db: S,
 		network_state: Arc<dyn NetworkStateInfo + Send + Sync>,
 		is_validator: bool,
+		http_config: HttpConfig,
 	) -> (Api<S>, AsyncApi) {
-		let (http_api, http_worker) = http::http();
+		let (http_api, http_worker) = http::http(http_config);
 
 		let api = Api {
 			db,
@@ -313,6 +315,7 @@ mod tests {
 			db,
 			mock,
 			false,
+			HttpConfig::default(),
 		)
 	}
 
@@ -25,17 +25,29 @@
 //! (i.e.: the socket should continue being processed) in the background even if the runtime isn't
 //! actively calling any function.
 
-use crate::api::timestamp;
+use crate::{HttpConfig, api::timestamp};
 use bytes::buf::ext::{Reader, BufExt};
 use fnv::FnvHashMap;
 use futures::{prelude::*, future, channel::mpsc};
-use log::error;
+use futures_timer::Delay;
+use hyper_proxy::{Intercept, Proxy, ProxyConnector};
+use log::{error, warn};
 use sp_core::offchain::{HttpRequestId, Timestamp, HttpRequestStatus, HttpError};
 use std::{convert::TryFrom, fmt, io::Read as _, pin::Pin, task::{Context, Poll}};
 use sp_utils::mpsc::{tracing_unbounded, TracingUnboundedSender, TracingUnboundedReceiver};
 
 /// Creates a pair of [`HttpApi`] and [`HttpWorker`].
-pub fn http() -> (HttpApi, HttpWorker) {
+///
+/// If the proxy of the `config` can't be set up, all the requests fail rather than being sent
+/// without it.
+pub fn http(config: HttpConfig) -> (HttpApi, HttpWorker) {
+	let (connector, proxy_failed) = match connector(config.proxy) {
+		Ok(connector) => (connector, false),
+		Err(err) => {
+			error!("Failed to set up offchain HTTP proxy, HTTP requests will fail: {:?}", err);
+			(ProxyConnector::unsecured(hyper_rustls::HttpsConnector::new()), true)
+		},
+	};
 	let (to_worker, from_api) = tracing_unbounded("mpsc_ocw_to_worker");
 	let (to_api, from_worker) = tracing_unbounded("mpsc_ocw_to_api");
 
@@ -46,18 +58,44 @@ pub fn http() -> (HttpApi, HttpWorker) {
 		// writing runtime code with hardcoded IDs.
 		next_id: HttpRequestId(rand::random::<u16>() % 2000),
 		requests: FnvHashMap::default(),
+		allowed_hosts: config.allowed_hosts,
+		max_requests: config.max_requests,
+		proxy_failed,
 	};
 
 	let engine = HttpWorker {
 		to_api,
 		from_api,
-		http_client: hyper::Client::builder().build(hyper_rustls::HttpsConnector::new()),
+		http_client: hyper::Client::builder().build(connector),
 		requests: Vec::new(),
+		request_timeout: config.request_timeout,
+		deadlines: FnvHashMap::default(),
 	};
 
 	(api, engine)
 }
 
+/// Creates the connector used by the HTTP client, routing all requests through `proxy` if any.
+fn connector(proxy: Option<hyper::Uri>) -> std::io::Result<Connector> {
+	let https = hyper_rustls::HttpsConnector::new();
+	match proxy {
+		Some(proxy) => ProxyConnector::from_proxy(https, Proxy::new(Intercept::All, proxy)),
+		None => Ok(ProxyConnector::unsecured(https)),
+	}
+}
+
+/// Connector used by the HTTP client of offchain workers.
+type Connector = ProxyConnector<hyper_rustls::HttpsConnector<hyper::client::HttpConnector>>;
+
+/// Reason an HTTP request failed in the [`HttpWorker`].
+#[derive(Debug)]
+enum HttpFailure {
+	/// Error reported by the HTTP client.
+	Hyper(hyper::Error),
+	/// The request didn't complete within the configured timeout.
+	Timeout,
+}
+
 /// Provides HTTP capabilities.
 ///
 /// Since this struct is a helper for offchain workers, its API is mimicking the API provided
@@ -72,6 +110,12 @@ pub struct HttpApi {
 	next_id: HttpRequestId,
 	/// List of HTTP requests in preparation or in progress.
 	requests: FnvHashMap<HttpRequestId, HttpApiRequest>,
+	/// Hosts that requests may be sent to, or `None` if any host is allowed.
+	allowed_hosts: Option<Vec<String>>,
+	/// Maximum number of requests that may be in `requests` at once.
+	max_requests: Option<usize>,
+	/// True if the configured proxy couldn't be set up, in which case no request is sent.
+	proxy_failed: bool,
 }
 
 /// One active request within `HttpApi`.
@@ -86,7 +130,7 @@ enum HttpApiRequest {
 	/// A request has been dispatched but the worker notified us of an error. We report this
 	/// failure to the user as an `IoError` and remove the request from the list as soon as
 	/// possible.
-	Fail(hyper::Error),
+	Fail(HttpFailure),
 }
 
 /// A request within `HttpApi` that has received a response.
@@ -104,7 +148,7 @@ struct HttpApiRequestRp {
 	/// Elements extracted from the channel are first put into `current_read_chunk`.
 	/// If the channel produces an error, then that is translated into an `IoError` and the request
 	/// is removed from the list.
-	body: stream::Fuse<mpsc::Receiver<Result<hyper::body::Bytes, hyper::Error>>>,
+	body: stream::Fuse<mpsc::Receiver<Result<hyper::body::Bytes, HttpFailure>>>,
 	/// Chunk that has been extracted from the channel and that is currently being read.
 	/// Reading data from the response should read from this field in priority.
 	current_read_chunk: Option<Reader<hyper::body::Bytes>>,
@@ -125,6 +169,24 @@ impl HttpApi {
 		*request.method_mut() = hyper::Method::from_bytes(method.as_bytes()).map_err(|_| ())?;
 		*request.uri_mut() = hyper::Uri::from_maybe_shared(uri.to_owned()).map_err(|_| ())?;
 
+		if self.proxy_failed {
+			warn!("Offchain worker HTTP request to {} rejected: the proxy isn't available", uri);
+			return Err(());
+		}
+
+		if let Some(allowed_hosts) = &self.allowed_hosts {
+			let host = request.uri().host().unwrap_or_default();
+			if !allowed_hosts.iter().any(|allowed| allowed.eq_ignore_ascii_case(host)) {
+				warn!("Offchain worker HTTP request to {} rejected: host not allowed", uri);
+				return Err(());
+			}
+		}
+
+		if self.max_requests.map_or(false, |max| self.requests.len() >= max) {
+			warn!("Offchain worker HTTP request to {} rejected: too many requests", uri);
+			return Err(());
+		}
+
 		let new_id = self.next_id;
 		debug_assert!(!self.requests.contains_key(&new_id));
 		match self.next_id.0.checked_add(1) {
@@ -533,14 +595,14 @@ enum WorkerToApi {
 		/// the next item.
 		/// Can also be used to send an error, in case an error happend on the HTTP socket. After
 		/// an error is sent, the channel will close.
-		body: mpsc::Receiver<Result<hyper::body::Bytes, hyper::Error>>,
+		body: mpsc::Receiver<Result<hyper::body::Bytes, HttpFailure>>,
 	},
 	/// A request has failed because of an error. The request is then no longer valid.
 	Fail {
 		/// The ID that was passed to the worker.
 		id: HttpRequestId,
 		/// Error that happened.
-		error: hyper::Error,
+		error: HttpFailure,
 	},
 }
 
@@ -551,9 +613,13 @@ pub struct HttpWorker {
 	/// Used to receive messages from the `HttpApi`.
 	from_api: TracingUnboundedReceiver<ApiToWorker>,
 	/// The engine that runs HTTP requests.
-	http_client: hyper::Client<hyper_rustls::HttpsConnector<hyper::client::HttpConnector>, hyper::Body>,
+	http_client: hyper::Client<Connector, hyper::Body>,
 	/// HTTP requests that are being worked on by the engine.
 	requests: Vec<(HttpRequestId, HttpWorkerRequest)>,
+	/// Time after which a request that hasn't completed is aborted.
+	request_timeout: Option<std::time::Duration>,
+	/// Timers of the requests in `requests` that abort them once `request_timeout` elapsed.
+	deadlines: FnvHashMap<HttpRequestId, Delay>,
 }
 
 /// HTTP request being processed by the worker.
@@ -565,7 +631,7 @@ enum HttpWorkerRequest {
 		/// Body to read `Chunk`s from. Only used if the channel is ready to accept data.
 		body: hyper::Body,
 		/// Channel to the [`HttpApi`] where we send the chunks to.
-		tx: mpsc::Sender<Result<hyper::body::Bytes, hyper::Error>>,
+		tx: mpsc::Sender<Result<hyper::body::Bytes, HttpFailure>>,
 	},
 }
 
@@ -582,6 +648,23 @@ impl Future for HttpWorker {
 		// We remove each element from `requests` one by one and add them back only if necessary.
 		for n in (0..me.requests.len()).rev() {
 			let (id, request) = me.requests.swap_remove(n);
+
+			let timed_out = me.deadlines.get_mut(&id)
+				.map_or(false, |deadline| Future::poll(Pin::new(deadline), cx).is_ready());
+			if timed_out {
+				me.deadlines.remove(&id);
+				match request {
+					HttpWorkerRequest::Dispatched(_) => {
+						let error = HttpFailure::Timeout;
+						let _ = me.to_api.unbounded_send(WorkerToApi::Fail { id, error });
+					},
+					HttpWorkerRequest::ReadBody { mut tx, .. } => {
+						let _ = tx.try_send(Err(HttpFailure::Timeout));
+					},
+				}
+				continue;		// don't insert the request back
+			}
+
 			match request {
 				HttpWorkerRequest::Dispatched(mut future) => {
 					// Check for an HTTP response from the Internet.
@@ -592,7 +675,9 @@ impl Future for HttpWorker {
 						},
 						Poll::Ready(Ok(response)) => response,
 						Poll::Ready(Err(error)) => {
+							let error = HttpFailure::Hyper(error);
 							let _ = me.to_api.unbounded_send(WorkerToApi::Fail { id, error });
+							me.deadlines.remove(&id);
 							continue;		// don't insert the request back
 						}
 					};
@@ -619,7 +704,10 @@ impl Future for HttpWorker {
 					// a new chunk.
 					match tx.poll_ready(cx) {
 						Poll::Ready(Ok(())) => {}
-						Poll::Ready(Err(_)) => continue,  // don't insert the request back
+						Poll::Ready(Err(_)) => {
+							me.deadlines.remove(&id);
+							continue  // don't insert the request back
+						},
 						Poll::Pending => {
 							me.requests.push((id, HttpWorkerRequest::ReadBody { body, tx }));
 							continue
@@ -634,10 +722,13 @@ impl Future for HttpWorker {
 							cx.waker().wake_by_ref();	// reschedule in order to continue reading
 						}
 						Poll::Ready(Some(Err(err))) => {
-							let _ = tx.start_send(Err(err));
+							let _ = tx.start_send(Err(HttpFailure::Hyper(err)));
+							me.deadlines.remove(&id);
 							// don't insert the request back
 						},
-						Poll::Ready(None) => {}		// EOF; don't insert the request back
+						Poll::Ready(None) => {		// EOF; don't insert the request back
+							me.deadlines.remove(&id);
+						},
 						Poll::Pending => {
 							me.requests.push((id, HttpWorkerRequest::ReadBody { body, tx }));
 						},
@@ -654,6 +745,9 @@ impl Future for HttpWorker {
 				let future = me.http_client.request(request);
 				debug_assert!(me.requests.iter().all(|(i, _)| *i != id));
 				me.requests.push((id, HttpWorkerRequest::Dispatched(future)));
+				if let Some(timeout) = me.request_timeout {
+					me.deadlines.insert(id, Delay::new(timeout));
+				}
 				cx.waker().wake_by_ref();	// reschedule the task to poll the request
 			}
 		}
@@ -686,20 +780,22 @@ mod tests {
 	use core::convert::Infallible;
 	use crate::api::timestamp;
 	use super::http;
+	use crate::HttpConfig;
 	use sp_core::offchain::{HttpError, HttpRequestId, HttpRequestStatus, Duration};
 	use futures::future;
 
 	// Returns an `HttpApi` whose worker is ran in the background, and a `SocketAddr` to an HTTP
 	// server that runs in the background as well.
 	macro_rules! build_api_server {
-		() => {{
+		() => { build_api_server!(HttpConfig::default()) };
+		($config:expr) => {{
 			// We spawn quite a bit of HTTP servers here due to how async API
 			// works for offchain workers, so be sure to raise the FD limit
 			// (particularly useful for macOS where the default soft limit may
 			// not be enough).
 			fdlimit::raise_fd_limit();
 
-			let (api, worker) = http();
+			let (api, worker) = http($config);
 
 			let (addr_tx, addr_rx) = std::sync::mpsc::channel();
 			std::thread::spawn(move || {
@@ -723,6 +819,30 @@ mod tests {
 		}};
 	}
 
+	#[test]
+	fn rejects_hosts_not_allowed() {
+		let (mut api, addr) = build_api_server!(HttpConfig {
+			allowed_hosts: Some(vec!["localhost".into()]),
+			..Default::default()
+		});
+
+		assert!(api.request_start("GET", &format!("http://{}", addr)).is_err());
+		assert!(api.request_start("GET", "http://localhost").is_ok());
+		assert!(api.request_start("GET", "http://LOCALHOST:1234/foo").is_ok());
+	}
+
+	#[test]
+	fn rejects_requests_above_limit() {
+		let (mut api, addr) = build_api_server!(HttpConfig {
+			max_requests: Some(2),
+			..Default::default()
+		});
+
+		assert!(api.request_start("GET", &format!("http://{}", addr)).is_ok());
+		assert!(api.request_start("GET", &format!("http://{}", addr)).is_ok());
+		assert!(api.request_start("GET", &format!("http://{}", addr)).is_err());
+	}
+
 	#[test]
 	fn basic_localhost() {
 		let deadline = timestamp::now().add(Duration::from_millis(10_000));
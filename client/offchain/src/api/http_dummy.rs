@@ -16,11 +16,12 @@
 
 //! Contains the same API as the `http` module, except that everything returns an error.
 
+use crate::HttpConfig;
 use sp_core::offchain::{HttpRequestId, Timestamp, HttpRequestStatus, HttpError};
 use std::{future::Future, pin::Pin, task::Context, task::Poll};
 
 /// Creates a pair of [`HttpApi`] and [`HttpWorker`].
-pub fn http() -> (HttpApi, HttpWorker) {
+pub fn http(_: HttpConfig) -> (HttpApi, HttpWorker) {
 	(HttpApi, HttpWorker)
 }
 
@@ -33,12 +33,13 @@
 
 #![warn(missing_docs)]
 
-use std::{fmt, marker::PhantomData, sync::Arc};
+use std::{fmt, marker::PhantomData, sync::Arc, time::Duration};
 
 use parking_lot::Mutex;
 use threadpool::ThreadPool;
 use sp_api::{ApiExt, ProvideRuntimeApi};
-use futures::future::Future;
+use futures::{future::{self, Future, FutureExt}, pin_mut};
+use futures_timer::Delay;
 use log::{debug, warn};
 use sc_network::NetworkStateInfo;
 use sp_core::{offchain::{self, OffchainStorage}, ExecutionContext};
@@ -48,12 +49,28 @@ mod api;
 
 pub use sp_offchain::{OffchainWorkerApi, STORAGE_PREFIX};
 
+/// Configuration of the HTTP client available to offchain workers.
+#[derive(Debug, Clone, Default)]
+pub struct HttpConfig {
+	/// Time after which a request is aborted, whatever deadline the runtime waits with.
+	pub request_timeout: Option<Duration>,
+	/// Maximum number of requests a single offchain worker run may have in progress at once.
+	pub max_requests: Option<usize>,
+	/// Hosts that requests may be sent to, or `None` to allow any host.
+	pub allowed_hosts: Option<Vec<String>>,
+	/// Proxy that all requests are sent through. No request is sent if it can't be set up.
+	#[cfg(not(target_os = "unknown"))]
+	pub proxy: Option<hyper::Uri>,
+}
+
 /// An offchain workers manager.
 pub struct OffchainWorkers<Client, Storage, Block: traits::Block> {
 	client: Arc<Client>,
 	db: Storage,
 	_block: PhantomData<Block>,
 	thread_pool: Mutex<ThreadPool>,
+	http_config: HttpConfig,
+	timeout: Option<Duration>,
 }
 
 impl<Client, Storage, Block: traits::Block> OffchainWorkers<Client, Storage, Block> {
@@ -63,9 +80,35 @@ impl<Client, Storage, Block: traits::Block> OffchainWorkers<Client, Storage, Blo
 			client,
 			db,
 			_block: PhantomData,
-			thread_pool: Mutex::new(ThreadPool::new(num_cpus::get())),
+			thread_pool: Mutex::new(thread_pool(num_cpus::get())),
+			http_config: HttpConfig::default(),
+			timeout: None,
 		}
 	}
+
+	/// Run offchain workers on a pool of `threads` threads instead of one thread per CPU.
+	///
+	/// Blocks imported while all threads are busy queue up until a thread is available.
+	pub fn with_thread_pool_size(mut self, threads: usize) -> Self {
+		self.thread_pool = Mutex::new(thread_pool(threads.max(1)));
+		self
+	}
+
+	/// Abort the asynchronous part of an offchain worker run, i.e. its pending HTTP
+	/// requests, if it doesn't finish within `timeout`.
+	///
+	/// The runtime call itself can't be interrupted, but any HTTP request it still waits
+	/// for fails once the timeout is hit, which makes well-behaved workers return.
+	pub fn with_timeout(mut self, timeout: Duration) -> Self {
+		self.timeout = Some(timeout);
+		self
+	}
+
+	/// Use the given configuration for the HTTP client of offchain workers.
+	pub fn with_http_config(mut self, http_config: HttpConfig) -> Self {
+		self.http_config = http_config;
+		self
+	}
 }
 
 impl<Client, Storage, Block: traits::Block> fmt::Debug for OffchainWorkers<
@@ -119,10 +162,12 @@ impl<Client, Storage, Block> OffchainWorkers<
 				self.db.clone(),
 				network_state.clone(),
 				is_validator,
+				self.http_config.clone(),
 			);
 			debug!("Spawning offchain workers at {:?}", at);
 			let header = header.clone();
 			let client = self.client.clone();
+			let timeout_at = at.clone();
 			self.spawn_worker(move || {
 				let runtime = client.runtime_api();
 				let api = Box::new(api);
@@ -142,9 +187,9 @@ impl<Client, Storage, Block> OffchainWorkers<
 					log::error!("Error running offchain workers at {:?}: {:?}", at, e);
 				}
 			});
-			futures::future::Either::Left(runner.process())
+			future::Either::Left(with_timeout(runner.process(), self.timeout, timeout_at))
 		} else {
-			futures::future::Either::Right(futures::future::ready(()))
+			future::Either::Right(future::ready(()))
 		}
 	}
 
@@ -156,8 +201,44 @@ impl<Client, Storage, Block> OffchainWorkers<
 	///
 	/// Note that we should avoid that if we switch to future-based runtime in the future,
 	/// alternatively:
+	///
+	/// Panics of the worker are caught and logged, so they don't take down the node.
 	fn spawn_worker(&self, f: impl FnOnce() -> () + Send + 'static) {
-		self.thread_pool.lock().execute(f);
+		self.thread_pool.lock().execute(move || {
+			let _guard = sp_panic_handler::AbortGuard::never_abort();
+			if std::panic::catch_unwind(std::panic::AssertUnwindSafe(f)).is_err() {
+				log::error!("Offchain worker panicked");
+			}
+		});
+	}
+}
+
+fn thread_pool(threads: usize) -> ThreadPool {
+	ThreadPool::with_name("offchain-worker".into(), threads)
+}
+
+/// Run `process` until it finishes or `timeout` elapses.
+async fn with_timeout<B: fmt::Debug>(
+	process: impl Future<Output = ()>,
+	timeout: Option<Duration>,
+	at: B,
+) {
+	let timeout = match timeout {
+		Some(timeout) => timeout,
+		None => return process.await,
+	};
+
+	let process = process.fuse();
+	let delay = Delay::new(timeout).fuse();
+	pin_mut!(process, delay);
+
+	futures::select! {
+		_ = process => {},
+		_ = delay => warn!(
+			"Offchain workers at {:?} didn't finish within {:?}, aborting their HTTP requests",
+			at,
+			timeout,
+		),
 	}
 }
 
@@ -222,4 +303,16 @@ mod tests {
 		assert_eq!(pool.0.status().ready, 1);
 		assert_eq!(pool.0.ready().next().unwrap().is_propagable(), false);
 	}
+
+	#[test]
+	fn should_catch_worker_panics() {
+		let client = Arc::new(substrate_test_runtime_client::new());
+		let db = sc_client_db::offchain::LocalStorage::new_test();
+		let offchain = OffchainWorkers::<_, _, Block>::new(client, db).with_thread_pool_size(1);
+
+		offchain.spawn_worker(|| panic!("offchain worker failure"));
+		offchain.thread_pool.lock().join();
+
+		assert_eq!(offchain.thread_pool.lock().panic_count(), 0);
+	}
 }
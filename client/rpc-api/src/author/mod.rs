@@ -39,6 +39,11 @@ pub trait AuthorApi<Hash, BlockHash> {
 	#[rpc(name = "author_submitExtrinsic")]
 	fn submit_extrinsic(&self, extrinsic: Bytes) -> FutureResult<Hash>;
 
+	/// Submit hex-encoded extrinsic for inclusion in block, without broadcasting it to other
+	/// nodes: it is only included if this node authors a block.
+	#[rpc(name = "author_submitLocalExtrinsic")]
+	fn submit_local_extrinsic(&self, extrinsic: Bytes) -> FutureResult<Hash>;
+
 	/// Insert a key into the keystore.
 	#[rpc(name = "author_insertKey")]
 	fn insert_key(
@@ -60,6 +65,12 @@ pub trait AuthorApi<Hash, BlockHash> {
 	#[rpc(name = "author_hasSessionKeys")]
 	fn has_session_keys(&self, session_keys: Bytes) -> Result<bool>;
 
+	/// Decode the given session public keys into the individual public keys and their key type.
+	///
+	/// `session_keys` is the SCALE encoded session keys object from the runtime.
+	#[rpc(name = "author_decodeSessionKeys")]
+	fn decode_session_keys(&self, session_keys: Bytes) -> Result<Vec<(Bytes, String)>>;
+
 	/// Checks if the keystore has private keys for the given public key and key type.
 	///
 	/// Returns `true` if a private key could be found.
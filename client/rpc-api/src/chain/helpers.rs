@@ -0,0 +1,39 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2020 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Substrate blockchain API helpers.
+
+use serde::{Serialize, Deserialize};
+
+/// A branch of the block tree that the best chain doesn't contain.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Fork<Number, Hash> {
+	/// Hash of the leaf of the branch.
+	pub leaf: Hash,
+	/// Number of the leaf.
+	pub number: Number,
+	/// Hash of the last block the branch has in common with the best chain.
+	pub common_ancestor: Hash,
+	/// Number of the common ancestor.
+	pub common_ancestor_number: Number,
+	/// Number of blocks of the branch after the common ancestor.
+	pub length: Number,
+	/// Number of blocks of the best chain after the common ancestor.
+	pub best_length: Number,
+}
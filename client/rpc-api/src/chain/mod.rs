@@ -19,6 +19,7 @@
 //! Substrate blockchain API.
 
 pub mod error;
+pub mod helpers;
 
 use jsonrpc_core::Result as RpcResult;
 use jsonrpc_core::futures::Future;
@@ -28,6 +29,7 @@ use sp_rpc::{number::NumberOrHex, list::ListOrValue};
 use self::error::{FutureResult, Result};
 
 pub use self::gen_client::Client as ChainClient;
+pub use self::helpers::Fork;
 
 /// Substrate blockchain API
 #[rpc]
@@ -56,9 +58,26 @@ pub trait ChainApi<Number, Hash, Header, SignedBlock> {
 	#[rpc(name = "chain_getFinalizedHead", alias("chain_getFinalisedHead"))]
 	fn finalized_head(&self) -> Result<Hash>;
 
+	/// Get the hashes of the leaves of the block tree, the best block first.
+	#[rpc(name = "chain_getLeaves")]
+	fn leaves(&self) -> Result<Vec<Hash>>;
+
+	/// Get the branches of the block tree that the best chain doesn't contain, with the length
+	/// of each of them and of the best chain since their common ancestor.
+	#[rpc(name = "chain_getForks")]
+	fn forks(&self) -> Result<Vec<Fork<Number, Hash>>>;
+
 	/// All head subscription
+	///
+	/// If `since` is the hash of a header recently announced to this kind of subscription, the
+	/// headers announced after it are sent first rather than the current head.
 	#[pubsub(subscription = "chain_allHead", subscribe, name = "chain_subscribeAllHeads")]
-	fn subscribe_all_heads(&self, metadata: Self::Metadata, subscriber: Subscriber<Header>);
+	fn subscribe_all_heads(
+		&self,
+		metadata: Self::Metadata,
+		subscriber: Subscriber<Header>,
+		since: Option<Hash>,
+	);
 
 	/// Unsubscribe from all head subscription.
 	#[pubsub(subscription = "chain_allHead", unsubscribe, name = "chain_unsubscribeAllHeads")]
@@ -75,7 +94,12 @@ pub trait ChainApi<Number, Hash, Header, SignedBlock> {
 		name = "chain_subscribeNewHeads",
 		alias("subscribe_newHead", "chain_subscribeNewHead")
 	)]
-	fn subscribe_new_heads(&self, metadata: Self::Metadata, subscriber: Subscriber<Header>);
+	fn subscribe_new_heads(
+		&self,
+		metadata: Self::Metadata,
+		subscriber: Subscriber<Header>,
+		since: Option<Hash>,
+	);
 
 	/// Unsubscribe from new head subscription.
 	#[pubsub(
@@ -97,7 +121,12 @@ pub trait ChainApi<Number, Hash, Header, SignedBlock> {
 		name = "chain_subscribeFinalizedHeads",
 		alias("chain_subscribeFinalisedHeads")
 	)]
-	fn subscribe_finalized_heads(&self, metadata: Self::Metadata, subscriber: Subscriber<Header>);
+	fn subscribe_finalized_heads(
+		&self,
+		metadata: Self::Metadata,
+		subscriber: Subscriber<Header>,
+		since: Option<Hash>,
+	);
 
 	/// Unsubscribe from finalized head subscription.
 	#[pubsub(
@@ -20,7 +20,7 @@
 
 use jsonrpc_derive::rpc;
 use sp_core::storage::{StorageKey, PrefixedStorageKey, StorageData};
-use crate::state::error::FutureResult;
+use crate::state::{error::FutureResult, ReadProof};
 
 pub use self::gen_client::Client as ChildStateClient;
 
@@ -68,4 +68,15 @@ pub trait ChildStateApi<Hash> {
 		key: StorageKey,
 		hash: Option<Hash>
 	) -> FutureResult<Option<u64>>;
+
+	/// Returns proof of child storage entries at a specific block's state.
+	///
+	/// The same limits as for `state_getReadProof` apply.
+	#[rpc(name = "childstate_getReadProof")]
+	fn read_proof(
+		&self,
+		child_storage_key: PrefixedStorageKey,
+		keys: Vec<StorageKey>,
+		hash: Option<Hash>
+	) -> FutureResult<ReadProof<Hash>>;
 }
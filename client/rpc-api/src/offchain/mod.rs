@@ -36,4 +36,8 @@ pub trait OffchainApi {
 	/// Get offchain local storage under given key and prefix.
 	#[rpc(name = "offchain_localStorageGet")]
 	fn get_local_storage(&self, kind: StorageKind, key: Bytes) -> Result<Option<Bytes>>;
+
+	/// Remove offchain local storage under given key and prefix.
+	#[rpc(name = "offchain_localStorageClear")]
+	fn clear_local_storage(&self, kind: StorageKind, key: Bytes) -> Result<()>;
 }
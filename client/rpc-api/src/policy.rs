@@ -19,7 +19,8 @@
 //! Policy-related types.
 //!
 //! Contains a `DenyUnsafe` type that can be used to deny potentially unsafe
-//! RPC when accessed externally.
+//! RPC when accessed externally, and a `HeadPolicy` type choosing the blocks
+//! served by default.
 
 use jsonrpc_core as rpc;
 
@@ -60,3 +61,29 @@ impl From<UnsafeRpcError> for rpc::Error {
 		rpc::Error::method_not_found()
 	}
 }
+
+/// Signifies which head of the chain the RPCs serve when no block is specified.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HeadPolicy {
+	/// Serve the best block, which may still be reverted by a reorg.
+	Best,
+	/// Serve the latest finalized block, and hide the blocks that aren't finalized
+	/// yet unless they are requested by hash.
+	Finalized,
+}
+
+impl HeadPolicy {
+	/// Returns `best` or `finalized` according to the policy.
+	pub fn select<T>(self, best: T, finalized: T) -> T {
+		match self {
+			HeadPolicy::Best => best,
+			HeadPolicy::Finalized => finalized,
+		}
+	}
+}
+
+impl Default for HeadPolicy {
+	fn default() -> HeadPolicy {
+		HeadPolicy::Best
+	}
+}
@@ -51,12 +51,26 @@ pub enum Error {
 		/// Maximum allowed value
 		max: u32,
 	},
+	/// The generated proof exceeds the maximum size.
+	#[display(fmt = "proof exceeds maximum size. size: {}, max: {}", size, max)]
+	ProofTooLarge {
+		/// Size of the proof in bytes.
+		size: usize,
+		/// Maximum allowed size in bytes.
+		max: usize,
+	},
+	/// Call to an unsafe RPC was denied.
+	UnsafeRpcCalled(crate::policy::UnsafeRpcError),
+	/// The subscription isn't served by the node.
+	#[display(fmt = "Subscription unavailable: {}", _0)]
+	SubscriptionUnavailable(String),
 }
 
 impl std::error::Error for Error {
 	fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
 		match self {
 			Error::Client(ref err) => Some(&**err),
+			Error::UnsafeRpcCalled(ref err) => Some(err),
 			_ => None,
 		}
 	}
@@ -78,6 +92,17 @@ impl From<Error> for rpc::Error {
 				message: format!("{}", e),
 				data: None,
 			},
+			Error::ProofTooLarge { .. } => rpc::Error {
+				code: rpc::ErrorCode::ServerError(BASE_ERROR + 3),
+				message: format!("{}", e),
+				data: None,
+			},
+			Error::SubscriptionUnavailable(_) => rpc::Error {
+				code: rpc::ErrorCode::ServerError(BASE_ERROR + 4),
+				message: format!("{}", e),
+				data: None,
+			},
+			Error::UnsafeRpcCalled(e) => e.into(),
 			e => errors::internal(e),
 		}
 	}
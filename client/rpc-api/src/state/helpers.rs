@@ -18,7 +18,7 @@
 
 //! Substrate state API helpers.
 
-use sp_core::Bytes;
+use sp_core::{Bytes, storage::{StorageData, StorageKey}};
 use serde::{Serialize, Deserialize};
 
 /// ReadProof struct returned by the RPC
@@ -30,3 +30,65 @@ pub struct ReadProof<Hash> {
 	/// A proof used to prove that storage entries are included in the storage trie
 	pub proof: Vec<Bytes>,
 }
+
+/// Changes of the top and child storages made by a block.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StorageDiff<Hash> {
+	/// Hash of the block.
+	pub block: Hash,
+	/// Changes of the top storage. Removed keys have no value.
+	pub changes: Vec<(StorageKey, Option<StorageData>)>,
+	/// Changes of the default child storages, by storage key of the child storage.
+	pub child_changes: Vec<(StorageKey, Vec<(StorageKey, Option<StorageData>)>)>,
+}
+
+/// Events of a block, decoded with the runtime metadata of the block.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BlockEvents<Hash> {
+	/// The decoded events, in the order they were emitted.
+	pub events: Vec<DecodedEvent<Hash>>,
+	/// The SCALE encoded event records following the last decoded event, which couldn't be
+	/// decoded with the metadata. `None` if all the events were decoded.
+	pub undecoded: Option<Bytes>,
+}
+
+/// An event of a block.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DecodedEvent<Hash> {
+	/// Phase of the block in which the event was emitted.
+	pub phase: EventPhase,
+	/// Name of the pallet emitting the event.
+	pub pallet: String,
+	/// Name of the event.
+	pub event: String,
+	/// Arguments of the event.
+	pub args: Vec<EventArg>,
+	/// Topics of the event.
+	pub topics: Vec<Hash>,
+}
+
+/// Phase of a block in which an event is emitted.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum EventPhase {
+	/// Applying the extrinsic with the given index.
+	ApplyExtrinsic(u32),
+	/// Finalizing the block.
+	Finalization,
+	/// Initializing the block.
+	Initialization,
+}
+
+/// An argument of an event.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct EventArg {
+	/// Type of the argument, as named in the metadata.
+	#[serde(rename = "type")]
+	pub ty: String,
+	/// The decoded argument. Integers wider than 32 bits are decimal strings, and byte arrays
+	/// hexadecimal strings.
+	pub value: serde_json::Value,
+}
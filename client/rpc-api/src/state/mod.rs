@@ -31,7 +31,9 @@ use sp_version::RuntimeVersion;
 use self::error::FutureResult;
 
 pub use self::gen_client::Client as StateClient;
-pub use self::helpers::ReadProof;
+pub use self::helpers::{
+	BlockEvents, DecodedEvent, EventArg, EventPhase, ReadProof, StorageDiff,
+};
 
 /// Substrate state API
 #[rpc]
@@ -80,6 +82,18 @@ pub trait StateApi<Hash> {
 	#[rpc(name = "state_getMetadata")]
 	fn metadata(&self, hash: Option<Hash>) -> FutureResult<Bytes>;
 
+	/// Returns the events of a block, decoded with the runtime metadata of the block.
+	///
+	/// The metadata doesn't describe the types of the arguments of the events, only the primitive
+	/// types and the aliases commonly used by the runtimes are decoded: `AccountId`, `Hash` and
+	/// the like are assumed to be 32 bytes long, `Balance` a `u128`, `Moment` a `u64`, and
+	/// `BlockNumber` and the indices `u32`s. An alias is only assumed if the values of its type
+	/// found in the metadata, e.g. the constants, have the same size.
+	///
+	/// The events following the first one that can't be decoded are returned SCALE encoded.
+	#[rpc(name = "state_getEvents")]
+	fn events(&self, hash: Option<Hash>) -> FutureResult<BlockEvents<Hash>>;
+
 	/// Get the runtime version.
 	#[rpc(name = "state_getRuntimeVersion", alias("chain_getRuntimeVersion"))]
 	fn runtime_version(&self, hash: Option<Hash>) -> FutureResult<RuntimeVersion>;
@@ -105,9 +119,33 @@ pub trait StateApi<Hash> {
 	) -> FutureResult<Vec<StorageChangeSet<Hash>>>;
 
 	/// Returns proof of storage entries at a specific block's state.
+	///
+	/// At most 1000 keys can be proven at once, and the proof can't exceed 4 MiB.
 	#[rpc(name = "state_getReadProof")]
 	fn read_proof(&self, keys: Vec<StorageKey>, hash: Option<Hash>) -> FutureResult<ReadProof<Hash>>;
 
+	/// Returns whether the node has the state of a block, i.e. the block is known and its state
+	/// isn't pruned.
+	///
+	/// Unlike the other methods, this doesn't fail when the state is unavailable. See
+	/// `system_statePruning` for the blocks whose state the node keeps.
+	#[rpc(name = "state_availableAt")]
+	fn available_at(&self, hash: Hash) -> FutureResult<bool>;
+
+	/// Keep the state of a block from being pruned for `duration` seconds, e.g. while paging
+	/// through its storage. Defaults to a minute, and is capped at ten minutes.
+	///
+	/// Returns the number of seconds the state is kept for. Pin the block again to keep it longer,
+	/// up to an hour in total. This is an unsafe RPC, as every pinned state takes up space.
+	#[rpc(name = "state_pinBlock")]
+	fn pin_block(&self, hash: Hash, duration: Option<u64>) -> FutureResult<u64>;
+
+	/// Allow pruning the state of a block pinned with `state_pinBlock` again.
+	///
+	/// Returns `false` if the block wasn't pinned. This is an unsafe RPC, like `state_pinBlock`.
+	#[rpc(name = "state_unpinBlock")]
+	fn unpin_block(&self, hash: Hash) -> FutureResult<bool>;
+
 	/// New runtime version subscription
 	#[pubsub(
 		subscription = "state_runtimeVersion",
@@ -127,9 +165,18 @@ pub trait StateApi<Hash> {
 	fn unsubscribe_runtime_version(&self, metadata: Option<Self::Metadata>, id: SubscriptionId) -> RpcResult<bool>;
 
 	/// New storage subscription
+	///
+	/// If `since` is the hash of the block of the last change set a reconnecting client received,
+	/// the first change set only holds the given keys whose value changed after that block,
+	/// rather than all of them. Only full nodes still having the state of that block resume
+	/// subscriptions.
 	#[pubsub(subscription = "state_storage", subscribe, name = "state_subscribeStorage")]
 	fn subscribe_storage(
-		&self, metadata: Self::Metadata, subscriber: Subscriber<StorageChangeSet<Hash>>, keys: Option<Vec<StorageKey>>
+		&self,
+		metadata: Self::Metadata,
+		subscriber: Subscriber<StorageChangeSet<Hash>>,
+		keys: Option<Vec<StorageKey>>,
+		since: Option<Hash>,
 	);
 
 	/// Unsubscribe from storage subscription
@@ -137,4 +184,29 @@ pub trait StateApi<Hash> {
 	fn unsubscribe_storage(
 		&self, metadata: Option<Self::Metadata>, id: SubscriptionId
 	) -> RpcResult<bool>;
+
+	/// Subscribe to the changes of the top and child storages made by each imported block.
+	///
+	/// The diffs are only sent by full nodes started with `--storage-diffs`, and the subscription
+	/// is rejected otherwise. When the node serves the finalized blocks, the diff of a block is
+	/// sent once it is finalized, for the blocks imported after subscribing. The subscription
+	/// ends if the subscriber doesn't keep up with the imported blocks.
+	#[pubsub(
+		subscription = "state_storageDiff",
+		subscribe,
+		name = "state_subscribeStorageDiffs"
+	)]
+	fn subscribe_storage_diffs(
+		&self, metadata: Self::Metadata, subscriber: Subscriber<StorageDiff<Hash>>
+	);
+
+	/// Unsubscribe from storage diffs subscription
+	#[pubsub(
+		subscription = "state_storageDiff",
+		unsubscribe,
+		name = "state_unsubscribeStorageDiffs"
+	)]
+	fn unsubscribe_storage_diffs(
+		&self, metadata: Option<Self::Metadata>, id: SubscriptionId
+	) -> RpcResult<bool>;
 }
@@ -29,12 +29,16 @@ pub struct SystemInfo {
 	pub impl_name: String,
 	/// Implementation version.
 	pub impl_version: String,
+	/// Name of the node.
+	pub node_name: String,
 	/// Chain name.
 	pub chain_name: String,
 	/// A custom set of properties defined in the chain spec.
 	pub properties: Properties,
 	/// The type of this chain.
 	pub chain_type: ChainType,
+	/// Which states the node keeps.
+	pub state_pruning: StatePruning,
 }
 
 /// Health struct returned by the RPC
@@ -49,11 +53,17 @@ pub struct Health {
 	///
 	/// Might be false for local chains or when running without discovery.
 	pub should_have_peers: bool,
+	/// Is the node in maintenance mode
+	///
+	/// The node doesn't author blocks nor takes part in the gossip while it is.
+	pub is_in_maintenance: bool,
 }
 
 impl fmt::Display for Health {
 	fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
-		write!(fmt, "{} peers ({})", self.peers, if self.is_syncing {
+		write!(fmt, "{} peers ({})", self.peers, if self.is_in_maintenance {
+			"maintenance"
+		} else if self.is_syncing {
 			"syncing"
 		} else { "idle" })
 	}
@@ -75,6 +85,30 @@ pub struct PeerInfo<Hash, Number> {
 	pub best_number: Number,
 }
 
+/// Diagnostics of the block requests made to a peer
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PeerSyncDetails<Hash, Number> {
+	/// Peer ID
+	pub peer_id: String,
+	/// Peer best block hash
+	pub best_hash: Hash,
+	/// Peer best block number
+	pub best_number: Number,
+	/// Best block known to both the node and the peer, if the peer takes part in syncing
+	pub common_number: Option<Number>,
+	/// What the node is downloading from the peer, if it takes part in syncing
+	pub sync_state: Option<String>,
+	/// Number of block requests sent to the peer and not answered yet
+	pub requests_in_flight: usize,
+	/// Time it took the peer to answer the last block requests, in milliseconds, oldest first
+	pub response_times_ms: Vec<u64>,
+	/// Number of block requests the peer didn't answer in time
+	pub timeouts: u32,
+	/// Number of block requests the peer answered with invalid or unexpected blocks
+	pub bad_responses: u32,
+}
+
 /// The role the node is running as
 #[derive(Debug, PartialEq, Serialize, Deserialize)]
 pub enum NodeRole {
@@ -88,6 +122,23 @@ pub enum NodeRole {
 	Sentry,
 }
 
+/// Which states the node keeps, and can thus serve historical queries at.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", tag = "mode")]
+pub enum StatePruning {
+	/// The states of all the blocks are kept.
+	Archive,
+	/// The states of all the finalized blocks are kept, and of the non-finalized ones until they
+	/// are discarded.
+	ArchiveCanonical,
+	/// Only the states of the non-finalized blocks and of the last finalized blocks are kept.
+	Constrained {
+		/// Number of finalized blocks whose state is kept, counting back from the last finalized
+		/// one.
+		window: u32,
+	},
+}
+
 #[cfg(test)]
 mod tests {
 	use super::*;
@@ -99,8 +150,9 @@ mod tests {
 				peers: 1,
 				is_syncing: false,
 				should_have_peers: true,
+				is_in_maintenance: false,
 			}).unwrap(),
-			r#"{"peers":1,"isSyncing":false,"shouldHavePeers":true}"#,
+			r#"{"peers":1,"isSyncing":false,"shouldHavePeers":true,"isInMaintenance":false}"#,
 		);
 	}
 
@@ -117,4 +169,38 @@ mod tests {
 			r#"{"peerId":"2","roles":"a","protocolVersion":2,"bestHash":5,"bestNumber":6}"#,
 		);
 	}
+
+	#[test]
+	fn should_serialize_peer_sync_details() {
+		assert_eq!(
+			::serde_json::to_string(&PeerSyncDetails {
+				peer_id: "2".into(),
+				best_hash: 5u32,
+				best_number: 6u32,
+				common_number: Some(4),
+				sync_state: Some("Available".into()),
+				requests_in_flight: 1,
+				response_times_ms: vec![120, 80],
+				timeouts: 2,
+				bad_responses: 0,
+			}).unwrap(),
+			concat!(
+				r#"{"peerId":"2","bestHash":5,"bestNumber":6,"commonNumber":4,"#,
+				r#""syncState":"Available","requestsInFlight":1,"responseTimesMs":[120,80],"#,
+				r#""timeouts":2,"badResponses":0}"#,
+			),
+		);
+	}
+
+	#[test]
+	fn should_serialize_state_pruning() {
+		assert_eq!(
+			::serde_json::to_string(&StatePruning::Archive).unwrap(),
+			r#"{"mode":"archive"}"#,
+		);
+		assert_eq!(
+			::serde_json::to_string(&StatePruning::Constrained { window: 256 }).unwrap(),
+			r#"{"mode":"constrained","window":256}"#,
+		);
+	}
 }
@@ -27,7 +27,7 @@ use futures::{future::BoxFuture, compat::Compat};
 
 use self::error::Result as SystemResult;
 
-pub use self::helpers::{SystemInfo, Health, PeerInfo, NodeRole};
+pub use self::helpers::{SystemInfo, Health, PeerInfo, PeerSyncDetails, NodeRole, StatePruning};
 pub use self::gen_client::Client as SystemClient;
 
 /// Substrate system RPC API
@@ -41,6 +41,10 @@ pub trait SystemApi<Hash, Number> {
 	#[rpc(name = "system_version")]
 	fn system_version(&self) -> SystemResult<String>;
 
+	/// Get the name of the node, as reported to telemetry.
+	#[rpc(name = "system_nodeName")]
+	fn system_node_name(&self) -> SystemResult<String>;
+
 	/// Get the chain's name. Given as a string identifier.
 	#[rpc(name = "system_chain")]
 	fn system_chain(&self) -> SystemResult<String>;
@@ -53,6 +57,12 @@ pub trait SystemApi<Hash, Number> {
 	#[rpc(name = "system_properties")]
 	fn system_properties(&self) -> SystemResult<sp_chain_spec::Properties>;
 
+	/// Get which states the node keeps, i.e. the blocks historical state queries can be made at.
+	///
+	/// Use `state_availableAt` to check a given block.
+	#[rpc(name = "system_statePruning")]
+	fn system_state_pruning(&self) -> SystemResult<StatePruning>;
+
 	/// Return health status of the node.
 	///
 	/// Node is considered healthy if it is:
@@ -77,6 +87,15 @@ pub trait SystemApi<Hash, Number> {
 	fn system_peers(&self)
 		-> Compat<BoxFuture<'static, jsonrpc_core::Result<Vec<PeerInfo<Hash, Number>>>>>;
 
+	/// Returns, for each connected peer, the state of the block requests made to it: how many are
+	/// in flight, how long the last ones took to be answered, and how many timed out or were
+	/// answered with invalid blocks, including before the peer reconnected.
+	///
+	/// Meant to find out why sync is stuck at a given height.
+	#[rpc(name = "sync_peerDetails", returns = "Vec<PeerSyncDetails<Hash, Number>>")]
+	fn sync_peer_details(&self)
+		-> Compat<BoxFuture<'static, jsonrpc_core::Result<Vec<PeerSyncDetails<Hash, Number>>>>>;
+
 	/// Returns current state of the network.
 	///
 	/// **Warning**: This API is not stable.
@@ -103,4 +122,13 @@ pub trait SystemApi<Hash, Number> {
 	/// Returns the roles the node is running as.
 	#[rpc(name = "system_nodeRoles", returns = "Vec<NodeRole>")]
 	fn system_node_roles(&self) -> Receiver<Vec<NodeRole>>;
+
+	/// Puts the node into maintenance mode, or takes it out of it.
+	///
+	/// In maintenance mode, the node keeps syncing and serving the RPC requests but it doesn't
+	/// author blocks nor takes part in the gossip of the consensus engines, so that it can be
+	/// stopped without missing votes halfway. The mode is reported by `system_health`.
+	#[rpc(name = "system_setMaintenance", returns = "()")]
+	fn system_set_maintenance(&self, enabled: bool)
+		-> Compat<BoxFuture<'static, Result<(), jsonrpc_core::Error>>>;
 }
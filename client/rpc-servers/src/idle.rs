@@ -0,0 +1,217 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2017-2020 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Closing the WS connections which are idle for too long, and pinging the quiet ones.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, Weak};
+use std::time::{Duration, Instant};
+
+use jsonrpc_core::futures::{future::Either, sync::mpsc, Future, Stream};
+use jsonrpc_core::middleware::{Middleware, NoopCallFuture};
+use jsonrpc_core::{FutureResponse, Request, Response};
+use log::{debug, error};
+use pubsub::{PubSubMetadata, Session};
+
+use crate::RpcHandler;
+
+/// Notification sent on the connections on which nothing was sent for half the idle timeout,
+/// so that the proxies in between don't close them.
+const PING: &str = r#"{"jsonrpc":"2.0","method":"rpc_ping","params":[]}"#;
+
+/// Activity of a connection.
+struct Activity {
+	/// Last time a request was received or a subscription notification was sent.
+	last_activity: Instant,
+	/// Last time anything, pings included, was sent.
+	last_sent: Instant,
+}
+
+impl Activity {
+	fn new() -> Arc<Mutex<Self>> {
+		let now = Instant::now();
+		Arc::new(Mutex::new(Activity { last_activity: now, last_sent: now }))
+	}
+}
+
+fn note_activity(activity: &Mutex<Activity>) {
+	if let Ok(mut activity) = activity.lock() {
+		let now = Instant::now();
+		activity.last_activity = now;
+		activity.last_sent = now;
+	}
+}
+
+struct Connection {
+	out: ws::Sender,
+	/// Keeps the address of the pub/sub session, by which the requests are matched to the
+	/// connection, from being reused while the connection is open.
+	_session: Option<Weak<Session>>,
+	activity: Arc<Mutex<Activity>>,
+}
+
+#[derive(Default)]
+struct Connections {
+	by_id: HashMap<ws::SessionId, Connection>,
+	/// Connections by address of their pub/sub session.
+	by_session: HashMap<usize, ws::SessionId>,
+}
+
+fn session_key(session: &Arc<Session>) -> usize {
+	&**session as *const Session as usize
+}
+
+/// The open connections of a WS server, closed once idle for too long.
+#[derive(Clone)]
+pub(crate) struct IdleConnections(Arc<Mutex<Connections>>);
+
+impl IdleConnections {
+	/// Start a thread closing the connections idle for longer than `timeout`, and pinging the
+	/// ones on which nothing was sent for half of it. The thread stops once the server, which
+	/// owns the `IdleConnections`, is dropped.
+	pub(crate) fn start(timeout: Duration) -> Self {
+		let connections = Arc::new(Mutex::new(Connections::default()));
+		let weak = Arc::downgrade(&connections);
+		let spawned = std::thread::Builder::new()
+			.name("rpc-ws-idle".into())
+			.spawn(move || close_idle_connections(weak, timeout));
+		if let Err(e) = spawned {
+			error!("Failed to spawn the thread closing idle RPC connections: {}", e);
+		}
+		IdleConnections(connections)
+	}
+
+	/// Returns the metadata of a new connection, whose subscription notifications count as
+	/// activity.
+	pub(crate) fn open<M>(&self, context: &ws::RequestContext) -> M
+	where
+		M: PubSubMetadata + From<mpsc::Sender<String>>,
+	{
+		let activity = Activity::new();
+		let (sender, receiver) = mpsc::channel(1);
+		let out = context.out.clone();
+		let notifications_activity = activity.clone();
+		context.executor.spawn(receiver.for_each(move |notification| {
+			note_activity(&notifications_activity);
+			out.send(notification).map_err(|e| debug!("Failed to send a notification: {:?}", e))
+		}));
+
+		let meta = M::from(sender);
+		let session = meta.session();
+		if let Ok(mut connections) = self.0.lock() {
+			if let Some(ref session) = session {
+				connections.by_session.insert(session_key(session), context.session_id);
+			}
+			connections.by_id.insert(context.session_id, Connection {
+				out: context.out.clone(),
+				_session: session.as_ref().map(Arc::downgrade),
+				activity,
+			});
+		}
+		meta
+	}
+
+	fn note_request(&self, session: &Arc<Session>) {
+		let connections = match self.0.lock() {
+			Ok(connections) => connections,
+			Err(_) => return,
+		};
+		let connection = connections.by_session.get(&session_key(session))
+			.and_then(|id| connections.by_id.get(id));
+		if let Some(connection) = connection {
+			note_activity(&connection.activity);
+		}
+	}
+
+	fn remove(&self, id: ws::SessionId) {
+		if let Ok(mut connections) = self.0.lock() {
+			connections.by_id.remove(&id);
+			connections.by_session.retain(|_, session_id| *session_id != id);
+		}
+	}
+}
+
+fn close_idle_connections(connections: Weak<Mutex<Connections>>, timeout: Duration) {
+	loop {
+		std::thread::sleep(timeout / 4);
+		let connections = match connections.upgrade() {
+			Some(connections) => connections,
+			None => return,
+		};
+		let connections = match connections.lock() {
+			Ok(connections) => connections,
+			Err(_) => return,
+		};
+		let now = Instant::now();
+		for connection in connections.by_id.values() {
+			let mut activity = match connection.activity.lock() {
+				Ok(activity) => activity,
+				Err(_) => continue,
+			};
+			if now.duration_since(activity.last_activity) >= timeout {
+				// The connection is removed once the server notices it's closed.
+				let _ = connection.out.close(ws::ws::CloseCode::Away);
+			} else if now.duration_since(activity.last_sent) >= timeout / 2 {
+				activity.last_sent = now;
+				let _ = connection.out.send(PING);
+			}
+		}
+	}
+}
+
+/// Removes the closed connections from the `IdleConnections`.
+pub(crate) struct SessionStats(pub(crate) Option<IdleConnections>);
+
+impl ws::SessionStats for SessionStats {
+	fn open_session(&self, _id: ws::SessionId) {}
+
+	fn close_session(&self, id: ws::SessionId) {
+		if let Some(ref connections) = self.0 {
+			connections.remove(id);
+		}
+	}
+}
+
+/// Middleware noting the requests received on each connection as activity, before passing them
+/// to the handler of the server.
+pub(crate) struct TrackRequests<M: PubSubMetadata> {
+	io: RpcHandler<M>,
+	connections: Option<IdleConnections>,
+}
+
+impl<M: PubSubMetadata> TrackRequests<M> {
+	pub(crate) fn new(io: RpcHandler<M>, connections: Option<IdleConnections>) -> Self {
+		TrackRequests { io, connections }
+	}
+}
+
+impl<M: PubSubMetadata> Middleware<M> for TrackRequests<M> {
+	type Future = FutureResponse;
+	type CallFuture = NoopCallFuture;
+
+	fn on_request<F, X>(&self, request: Request, meta: M, _next: F) -> Either<Self::Future, X>
+	where
+		F: Fn(Request, M) -> X + Send + Sync,
+		X: Future<Item = Option<Response>, Error = ()> + Send + 'static,
+	{
+		if let (Some(connections), Some(session)) = (&self.connections, meta.session()) {
+			connections.note_request(&session);
+		}
+		Either::A(Box::new(self.io.handle_rpc_request(request, meta)))
+	}
+}
@@ -20,6 +20,9 @@
 
 #![warn(missing_docs)]
 
+#[cfg(not(target_os = "unknown"))]
+mod idle;
+
 use std::io;
 use jsonrpc_core::IoHandlerExtension;
 use log::error;
@@ -61,6 +64,9 @@ pub fn rpc_handler<M: PubSubMetadata>(
 #[cfg(not(target_os = "unknown"))]
 mod inner {
 	use super::*;
+	use std::time::Duration;
+	use jsonrpc_core::MetaIoHandler;
+	use crate::idle::{IdleConnections, SessionStats, TrackRequests};
 
 	/// Type alias for http server
 	pub type HttpServer = http::Server;
@@ -73,36 +79,52 @@ mod inner {
 	pub fn start_http<M: pubsub::PubSubMetadata + Default>(
 		addr: &std::net::SocketAddr,
 		cors: Option<&Vec<String>>,
+		allowed_hosts: Option<&Vec<String>>,
 		io: RpcHandler<M>,
 	) -> io::Result<http::Server> {
 		http::ServerBuilder::new(io)
 			.threads(4)
 			.health_api(("/health", "system_health"))
-			.allowed_hosts(hosts_filtering(cors.is_some()))
+			.allowed_hosts(map_domains::<http::Host>(allowed_hosts))
 			.rest_api(if cors.is_some() {
 				http::RestApi::Secure
 			} else {
 				http::RestApi::Unsecure
 			})
-			.cors(map_cors::<http::AccessControlAllowOrigin>(cors))
+			.cors(map_domains::<http::AccessControlAllowOrigin>(cors))
 			.max_request_body_size(MAX_PAYLOAD)
 			.start_http(addr)
 	}
 
 	/// Start WS server listening on given address.
 	///
+	/// If `idle_timeout` is set, the connections on which no request is received and no
+	/// subscription notification is sent for that long are closed, and the connections on which
+	/// nothing is sent for half of it are sent a ping notification.
+	///
 	/// **Note**: Only available if `not(target_os = "unknown")`.
-	pub fn start_ws<M: pubsub::PubSubMetadata + From<jsonrpc_core::futures::sync::mpsc::Sender<String>>> (
+	pub fn start_ws<M: PubSubMetadata + From<jsonrpc_core::futures::sync::mpsc::Sender<String>>> (
 		addr: &std::net::SocketAddr,
 		max_connections: Option<usize>,
 		cors: Option<&Vec<String>>,
+		allowed_hosts: Option<&Vec<String>>,
+		idle_timeout: Option<Duration>,
 		io: RpcHandler<M>,
 	) -> io::Result<ws::Server> {
-		ws::ServerBuilder::with_meta_extractor(io, |context: &ws::RequestContext| context.sender().into())
+		let connections = idle_timeout.map(IdleConnections::start);
+		let stats = SessionStats(connections.clone());
+		let io = MetaIoHandler::with_middleware(TrackRequests::new(io, connections.clone()));
+		let meta_extractor = move |context: &ws::RequestContext| match connections {
+			Some(ref connections) => connections.open(context),
+			None => M::from(context.sender()),
+		};
+
+		ws::ServerBuilder::with_meta_extractor(io, meta_extractor)
+			.session_stats(stats)
 			.max_payload(MAX_PAYLOAD)
 			.max_connections(max_connections.unwrap_or(WS_MAX_CONNECTIONS))
-			.allowed_origins(map_cors(cors))
-			.allowed_hosts(hosts_filtering(cors.is_some()))
+			.allowed_origins(map_domains(cors))
+			.allowed_hosts(map_domains(allowed_hosts))
 			.start(addr)
 			.map_err(|err| match err {
 				ws::Error::Io(io) => io,
@@ -114,21 +136,12 @@ mod inner {
 			})
 	}
 
-	fn map_cors<T: for<'a> From<&'a str>>(
-		cors: Option<&Vec<String>>
+	// NOTE For the allowed hosts, the listening address is whitelisted by default.
+	// An empty vector enables the validation and allows only the listening address.
+	fn map_domains<T: for<'a> From<&'a str>>(
+		domains: Option<&Vec<String>>
 	) -> http::DomainsValidation<T> {
-		cors.map(|x| x.iter().map(AsRef::as_ref).map(Into::into).collect::<Vec<_>>()).into()
-	}
-
-	fn hosts_filtering(enable: bool) -> http::DomainsValidation<http::Host> {
-		if enable {
-			// NOTE The listening address is whitelisted by default.
-			// Setting an empty vector here enables the validation
-			// and allows only the listening address.
-			http::DomainsValidation::AllowOnly(vec![])
-		} else {
-			http::DomainsValidation::Disabled
-		}
+		domains.map(|x| x.iter().map(AsRef::as_ref).map(Into::into).collect::<Vec<_>>()).into()
 	}
 }
 
@@ -135,6 +135,21 @@ impl<P, Client> AuthorApi<TxHash<P>, BlockHash<P>> for Author<P, Client>
 		Ok(self.keystore.read().has_keys(&keys))
 	}
 
+	fn decode_session_keys(&self, session_keys: Bytes) -> Result<Vec<(Bytes, String)>> {
+		let best_block_hash = self.client.info().best_hash;
+		let keys = self.client.runtime_api().decode_session_keys(
+			&generic::BlockId::Hash(best_block_hash),
+			session_keys.to_vec(),
+		).map_err(|e| Error::Client(Box::new(e)))?
+			.ok_or_else(|| Error::InvalidSessionKeys)?;
+
+		Ok(keys.into_iter()
+			.map(|(public, key_type)| {
+				(public.into(), String::from_utf8_lossy(&key_type.0).into_owned())
+			})
+			.collect())
+	}
+
 	fn has_key(&self, public_key: Bytes, key_type: String) -> Result<bool> {
 		self.deny_unsafe.check_if_safe()?;
 
@@ -157,6 +172,25 @@ impl<P, Client> AuthorApi<TxHash<P>, BlockHash<P>> for Author<P, Client>
 		)
 	}
 
+	fn submit_local_extrinsic(&self, ext: Bytes) -> FutureResult<TxHash<P>> {
+		if let Err(err) = self.deny_unsafe.check_if_safe() {
+			return Box::new(result(Err(err.into())));
+		}
+
+		let xt = match Decode::decode(&mut &ext[..]) {
+			Ok(xt) => xt,
+			Err(err) => return Box::new(result(Err(err.into()))),
+		};
+		let best_block_hash = self.client.info().best_hash;
+		Box::new(self.pool
+			.submit_local(&generic::BlockId::hash(best_block_hash), xt)
+			.compat()
+			.map_err(|e| e.into_pool_error()
+				.map(Into::into)
+				.unwrap_or_else(|e| error::Error::Verification(Box::new(e)).into()))
+		)
+	}
+
 	fn pending_extrinsics(&self) -> Result<Vec<Bytes>> {
 		Ok(self.pool.ready().map(|tx| tx.data().encode().into()).collect())
 	}
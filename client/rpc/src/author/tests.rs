@@ -118,6 +118,35 @@ fn submit_rich_transaction_should_not_cause_error() {
 	);
 }
 
+#[test]
+fn submit_local_transaction_should_not_propagate_it() {
+	let setup = TestSetup::default();
+	let p = setup.author();
+	let xt = uxt(AccountKeyring::Alice, 0).encode();
+	let h: H256 = blake2_256(&xt).into();
+
+	assert_matches!(
+		AuthorApi::submit_local_extrinsic(&p, xt.into()).wait(),
+		Ok(h2) if h == h2
+	);
+	let ready = setup.pool.ready().collect::<Vec<_>>();
+	assert_eq!(ready.len(), 1);
+	assert!(!ready[0].is_propagable());
+}
+
+#[test]
+fn submit_local_transaction_should_be_unsafe() {
+	let setup = TestSetup::default();
+	let p = Author { deny_unsafe: DenyUnsafe::Yes, ..setup.author() };
+	let xt = uxt(AccountKeyring::Alice, 0).encode();
+
+	assert_matches!(
+		AuthorApi::submit_local_extrinsic(&p, xt.into()).wait(),
+		Err(Error::UnsafeRpcCalled(_))
+	);
+	assert_eq!(setup.pool.status().ready, 0);
+}
+
 #[test]
 fn should_watch_extrinsic() {
 	//given
@@ -244,6 +273,26 @@ fn should_rotate_keys() {
 	assert!(sr25519_public_keys.contains(&CryptoTypePublicPair(sr25519::CRYPTO_ID, session_keys.sr25519.to_raw_vec())));
 }
 
+#[test]
+fn should_decode_session_keys() {
+	let setup = TestSetup::default();
+	let p = setup.author();
+
+	let public_keys = p.rotate_keys().expect("Rotates the keys");
+	let session_keys = SessionKeys::decode(&mut &public_keys[..])
+		.expect("SessionKeys decode successfully");
+
+	assert_eq!(p.decode_session_keys(public_keys).unwrap(), vec![
+		(session_keys.ed25519.to_raw_vec().into(), "ed25".to_string()),
+		(session_keys.sr25519.to_raw_vec().into(), "sr25".to_string()),
+		(session_keys.ecdsa.to_raw_vec().into(), "ecds".to_string()),
+	]);
+	assert_matches!(
+		p.decode_session_keys(vec![1, 2, 3].into()),
+		Err(Error::InvalidSessionKeys)
+	);
+}
+
 #[test]
 fn test_has_session_keys() {
 	let setup = TestSetup::default();
@@ -19,13 +19,15 @@
 use std::sync::Arc;
 use rpc::futures::future::result;
 
-use sc_rpc_api::Subscriptions;
+use sc_rpc_api::{HeadPolicy, Subscriptions};
 use sc_client_api::{BlockchainEvents, BlockBackend};
 use sp_runtime::{generic::{BlockId, SignedBlock}, traits::{Block as BlockT}};
 
-use super::{ChainBackend, client_err, error::FutureResult};
+use sc_rpc_api::chain::Fork;
+use sp_runtime::traits::NumberFor;
+use super::{ChainBackend, HeadsReplayBuffer, client_err, error::{Error, FutureResult, Result}};
 use std::marker::PhantomData;
-use sp_blockchain::HeaderBackend;
+use sp_blockchain::{HeaderBackend, HeaderMetadata};
 
 /// Blockchain API backend for full nodes. Reads all the data from local database.
 pub struct FullChain<Block: BlockT, Client> {
@@ -33,16 +35,27 @@ pub struct FullChain<Block: BlockT, Client> {
 	client: Arc<Client>,
 	/// Current subscriptions.
 	subscriptions: Subscriptions,
+	/// Head served when no block is specified.
+	head_policy: HeadPolicy,
+	/// Recent heads replayed to the resumed subscriptions.
+	replay_buffer: Option<Arc<HeadsReplayBuffer<Block>>>,
 	/// phantom member to pin the block type
 	_phantom: PhantomData<Block>,
 }
 
 impl<Block: BlockT, Client> FullChain<Block, Client> {
 	/// Create new Chain API RPC handler.
-	pub fn new(client: Arc<Client>, subscriptions: Subscriptions) -> Self {
+	pub fn new(
+		client: Arc<Client>,
+		subscriptions: Subscriptions,
+		head_policy: HeadPolicy,
+		replay_buffer: Option<Arc<HeadsReplayBuffer<Block>>>,
+	) -> Self {
 		Self {
 			client,
 			subscriptions,
+			head_policy,
+			replay_buffer,
 			_phantom: PhantomData,
 		}
 	}
@@ -50,7 +63,8 @@ impl<Block: BlockT, Client> FullChain<Block, Client> {
 
 impl<Block, Client> ChainBackend<Client, Block> for FullChain<Block, Client> where
 	Block: BlockT + 'static,
-	Client: BlockBackend<Block> + HeaderBackend<Block> + BlockchainEvents<Block> + 'static,
+	Client: BlockBackend<Block> + HeaderBackend<Block> + BlockchainEvents<Block>
+		+ HeaderMetadata<Block, Error = sp_blockchain::Error> + 'static,
 {
 	fn client(&self) -> &Arc<Client> {
 		&self.client
@@ -60,6 +74,14 @@ impl<Block, Client> ChainBackend<Client, Block> for FullChain<Block, Client> whe
 		&self.subscriptions
 	}
 
+	fn head_policy(&self) -> HeadPolicy {
+		self.head_policy
+	}
+
+	fn replay_buffer(&self) -> Option<&Arc<HeadsReplayBuffer<Block>>> {
+		self.replay_buffer.as_ref()
+	}
+
 	fn header(&self, hash: Option<Block::Hash>) -> FutureResult<Option<Block::Header>> {
 		Box::new(result(self.client
 			.header(BlockId::Hash(self.unwrap_or_best(hash)))
@@ -75,4 +97,20 @@ impl<Block, Client> ChainBackend<Client, Block> for FullChain<Block, Client> whe
 			.map_err(client_err)
 		))
 	}
+
+	fn leaves(&self) -> Result<Vec<Block::Hash>> {
+		// the leaves are mostly blocks that aren't finalized yet.
+		if self.head_policy == HeadPolicy::Finalized {
+			return Err(Error::Other("Leaves aren't served when only finalized data is".into()));
+		}
+
+		let best_hash = self.client.info().best_hash;
+		let mut leaves = self.client.leaves().map_err(client_err)?;
+		leaves.sort_by_key(|leaf| *leaf != best_hash);
+		Ok(leaves)
+	}
+
+	fn forks(&self) -> Result<Vec<Fork<NumberFor<Block>, Block::Hash>>> {
+		super::forks(&*self.client, self.leaves()?)
+	}
 }
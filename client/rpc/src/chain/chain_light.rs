@@ -20,14 +20,16 @@ use std::sync::Arc;
 use futures::{future::ready, FutureExt, TryFutureExt};
 use rpc::futures::future::{result, Future, Either};
 
-use sc_rpc_api::Subscriptions;
+use sc_rpc_api::{HeadPolicy, Subscriptions};
 use sc_client_api::light::{Fetcher, RemoteBodyRequest, RemoteBlockchain};
 use sp_runtime::{
 	generic::{BlockId, SignedBlock},
 	traits::{Block as BlockT},
 };
 
-use super::{ChainBackend, client_err, error::FutureResult};
+use sc_rpc_api::chain::Fork;
+use sp_runtime::traits::NumberFor;
+use super::{ChainBackend, HeadsReplayBuffer, client_err, error::{FutureResult, Result}};
 use sp_blockchain::HeaderBackend;
 use sc_client_api::BlockchainEvents;
 
@@ -42,6 +44,10 @@ pub struct LightChain<Block: BlockT, Client, F> {
 	remote_blockchain: Arc<dyn RemoteBlockchain<Block>>,
 	/// Remote fetcher reference.
 	fetcher: Arc<F>,
+	/// Head served when no block is specified.
+	head_policy: HeadPolicy,
+	/// Recent heads replayed to the resumed subscriptions.
+	replay_buffer: Option<Arc<HeadsReplayBuffer<Block>>>,
 }
 
 impl<Block: BlockT, Client, F: Fetcher<Block>> LightChain<Block, Client, F> {
@@ -51,12 +57,16 @@ impl<Block: BlockT, Client, F: Fetcher<Block>> LightChain<Block, Client, F> {
 		subscriptions: Subscriptions,
 		remote_blockchain: Arc<dyn RemoteBlockchain<Block>>,
 		fetcher: Arc<F>,
+		head_policy: HeadPolicy,
+		replay_buffer: Option<Arc<HeadsReplayBuffer<Block>>>,
 	) -> Self {
 		Self {
 			client,
 			subscriptions,
 			remote_blockchain,
 			fetcher,
+			head_policy,
+			replay_buffer,
 		}
 	}
 }
@@ -74,6 +84,14 @@ impl<Block, Client, F> ChainBackend<Client, Block> for LightChain<Block, Client,
 		&self.subscriptions
 	}
 
+	fn head_policy(&self) -> HeadPolicy {
+		self.head_policy
+	}
+
+	fn replay_buffer(&self) -> Option<&Arc<HeadsReplayBuffer<Block>>> {
+		self.replay_buffer.as_ref()
+	}
+
 	fn header(&self, hash: Option<Block::Hash>) -> FutureResult<Option<Block::Header>> {
 		let hash = self.unwrap_or_best(hash);
 
@@ -113,4 +131,12 @@ impl<Block, Client, F> ChainBackend<Client, Block> for LightChain<Block, Client,
 
 		Box::new(block)
 	}
+
+	fn leaves(&self) -> Result<Vec<Block::Hash>> {
+		Err(client_err(sp_blockchain::Error::NotAvailableOnLightClient))
+	}
+
+	fn forks(&self) -> Result<Vec<Fork<NumberFor<Block>, Block::Hash>>> {
+		Err(client_err(sp_blockchain::Error::NotAvailableOnLightClient))
+	}
 }
@@ -20,11 +20,12 @@
 
 mod chain_full;
 mod chain_light;
+mod replay;
 
 #[cfg(test)]
 mod tests;
 
-use std::sync::Arc;
+use std::{collections::HashSet, sync::Arc};
 use futures::{future, StreamExt, TryStreamExt};
 use log::warn;
 use rpc::{
@@ -32,7 +33,7 @@ use rpc::{
 	futures::{stream, Future, Sink, Stream},
 };
 
-use sc_rpc_api::Subscriptions;
+use sc_rpc_api::{HeadPolicy, Subscriptions};
 use sc_client_api::{BlockchainEvents, light::{Fetcher, RemoteBlockchain}};
 use jsonrpc_pubsub::{typed::Subscriber, SubscriptionId};
 use sp_rpc::{number::NumberOrHex, list::ListOrValue};
@@ -44,7 +45,8 @@ use sp_runtime::{
 use self::error::{Result, Error, FutureResult};
 
 pub use sc_rpc_api::chain::*;
-use sp_blockchain::HeaderBackend;
+pub use self::replay::{HeadsKind, HeadsReplayBuffer, record_heads};
+use sp_blockchain::{HeaderBackend, HeaderMetadata, lowest_common_ancestor};
 use sc_client_api::BlockBackend;
 
 /// Blockchain backend API
@@ -59,10 +61,46 @@ trait ChainBackend<Client, Block: BlockT>: Send + Sync + 'static
 	/// Get subscriptions reference.
 	fn subscriptions(&self) -> &Subscriptions;
 
-	/// Tries to unwrap passed block hash, or uses best block hash otherwise.
+	/// Get the head served when no block is specified.
+	fn head_policy(&self) -> HeadPolicy;
+
+	/// Get the buffer of the recent heads replayed to the resumed subscriptions, if any.
+	fn replay_buffer(&self) -> Option<&Arc<HeadsReplayBuffer<Block>>>;
+
+	/// Get the heads of the given kind announced after the header `since`, if any.
+	///
+	/// Fails if the header isn't in the replay buffer anymore, since the heads that the
+	/// subscriber missed can't be told then.
+	fn replayed_heads(
+		&self,
+		kind: HeadsKind,
+		since: Option<Block::Hash>,
+	) -> Result<Option<Vec<Block::Header>>> {
+		let since = match since {
+			Some(since) => since,
+			None => return Ok(None),
+		};
+		let replay_buffer = self.replay_buffer().ok_or_else(|| Error::Other(
+			"The subscriptions can't be resumed, the node doesn't keep the recent heads".into()
+		))?;
+		replay_buffer.since(kind, &since)
+			.map(Some)
+			.ok_or_else(|| Error::Other(format!(
+				"The subscription can't be resumed from {}, which isn't among the recent heads",
+				since,
+			)))
+	}
+
+	/// Get the hash of the head served when no block is specified.
+	fn head(&self) -> Block::Hash {
+		let info = self.client().info();
+		self.head_policy().select(info.best_hash, info.finalized_hash)
+	}
+
+	/// Tries to unwrap passed block hash, or uses the head otherwise.
 	fn unwrap_or_best(&self, hash: Option<Block::Hash>) -> Block::Hash {
 		match hash.into() {
-			None => self.client().info().best_hash,
+			None => self.head(),
 			Some(hash) => hash,
 		}
 	}
@@ -75,17 +113,26 @@ trait ChainBackend<Client, Block: BlockT>: Send + Sync + 'static
 
 	/// Get hash of the n-th block in the canon chain.
 	///
-	/// By default returns latest block hash.
+	/// By default returns the hash of the head. The blocks that aren't finalized yet
+	/// are hidden when only the finalized blocks are served.
 	fn block_hash(
 		&self,
 		number: Option<NumberOrHex<NumberFor<Block>>>,
 	) -> Result<Option<Block::Hash>> {
 		Ok(match number {
-			None => Some(self.client().info().best_hash),
-			Some(num_or_hex) => self.client()
-				.header(BlockId::number(num_or_hex.to_number()?))
-				.map_err(client_err)?
-				.map(|h| h.hash()),
+			None => Some(self.head()),
+			Some(num_or_hex) => {
+				let number = num_or_hex.to_number()?;
+				if self.head_policy() == HeadPolicy::Finalized &&
+					number > self.client().info().finalized_number
+				{
+					return Ok(None)
+				}
+				self.client()
+					.header(BlockId::number(number))
+					.map_err(client_err)?
+					.map(|h| h.hash())
+			},
 		})
 	}
 
@@ -94,20 +141,28 @@ trait ChainBackend<Client, Block: BlockT>: Send + Sync + 'static
 		Ok(self.client().info().finalized_hash)
 	}
 
+	/// Get the hashes of the leaves of the block tree, the best block first.
+	fn leaves(&self) -> Result<Vec<Block::Hash>>;
+
+	/// Get the branches of the block tree that the best chain doesn't contain.
+	fn forks(&self) -> Result<Vec<Fork<NumberFor<Block>, Block::Hash>>>;
+
 	/// All new head subscription
 	fn subscribe_all_heads(
 		&self,
 		_metadata: crate::metadata::Metadata,
 		subscriber: Subscriber<Block::Header>,
+		since: Option<Block::Hash>,
 	) {
 		subscribe_headers(
 			self.client(),
 			self.subscriptions(),
 			subscriber,
 			|| self.client().info().best_hash,
-			|| self.client().import_notification_stream()
+			|| self.client().external_import_notification_stream()
 				.map(|notification| Ok::<_, ()>(notification.header))
 				.compat(),
+			|| self.replayed_heads(HeadsKind::All, since),
 		)
 	}
 
@@ -121,20 +176,28 @@ trait ChainBackend<Client, Block: BlockT>: Send + Sync + 'static
 	}
 
 	/// New best head subscription
+	///
+	/// Follows the finalized heads when only the finalized blocks are served.
 	fn subscribe_new_heads(
 		&self,
-		_metadata: crate::metadata::Metadata,
+		metadata: crate::metadata::Metadata,
 		subscriber: Subscriber<Block::Header>,
+		since: Option<Block::Hash>,
 	) {
+		if self.head_policy() == HeadPolicy::Finalized {
+			return self.subscribe_finalized_heads(metadata, subscriber, since)
+		}
+
 		subscribe_headers(
 			self.client(),
 			self.subscriptions(),
 			subscriber,
 			|| self.client().info().best_hash,
-			|| self.client().import_notification_stream()
+			|| self.client().external_import_notification_stream()
 				.filter(|notification| future::ready(notification.is_new_best))
 				.map(|notification| Ok::<_, ()>(notification.header))
 				.compat(),
+			|| self.replayed_heads(HeadsKind::New, since),
 		)
 	}
 
@@ -152,15 +215,17 @@ trait ChainBackend<Client, Block: BlockT>: Send + Sync + 'static
 		&self,
 		_metadata: crate::metadata::Metadata,
 		subscriber: Subscriber<Block::Header>,
+		since: Option<Block::Hash>,
 	) {
 		subscribe_headers(
 			self.client(),
 			self.subscriptions(),
 			subscriber,
 			|| self.client().info().finalized_hash,
-			|| self.client().finality_notification_stream()
+			|| self.client().external_finality_notification_stream()
 				.map(|notification| Ok::<_, ()>(notification.header))
 				.compat(),
+			|| self.replayed_heads(HeadsKind::Finalized, since),
 		)
 	}
 
@@ -175,25 +240,41 @@ trait ChainBackend<Client, Block: BlockT>: Send + Sync + 'static
 }
 
 /// Create new state API that works on full node.
+///
+/// The requests that don't specify a block are served the head chosen by `head_policy`. The
+/// heads subscriptions are resumed from `replay_buffer`, if any.
 pub fn new_full<Block: BlockT, Client>(
 	client: Arc<Client>,
 	subscriptions: Subscriptions,
+	head_policy: HeadPolicy,
+	replay_buffer: Option<Arc<HeadsReplayBuffer<Block>>>,
 ) -> Chain<Block, Client>
 	where
 		Block: BlockT + 'static,
-		Client: BlockBackend<Block> + HeaderBackend<Block> + BlockchainEvents<Block> + 'static,
+		Client: BlockBackend<Block> + HeaderBackend<Block> + BlockchainEvents<Block>
+			+ HeaderMetadata<Block, Error = sp_blockchain::Error> + 'static,
 {
 	Chain {
-		backend: Box::new(self::chain_full::FullChain::new(client, subscriptions)),
+		backend: Box::new(self::chain_full::FullChain::new(
+			client,
+			subscriptions,
+			head_policy,
+			replay_buffer,
+		)),
 	}
 }
 
 /// Create new state API that works on light node.
+///
+/// The requests that don't specify a block are served the head chosen by `head_policy`. The
+/// heads subscriptions are resumed from `replay_buffer`, if any.
 pub fn new_light<Block: BlockT, Client, F: Fetcher<Block>>(
 	client: Arc<Client>,
 	subscriptions: Subscriptions,
 	remote_blockchain: Arc<dyn RemoteBlockchain<Block>>,
 	fetcher: Arc<F>,
+	head_policy: HeadPolicy,
+	replay_buffer: Option<Arc<HeadsReplayBuffer<Block>>>,
 ) -> Chain<Block, Client>
 	where
 		Block: BlockT + 'static,
@@ -206,6 +287,8 @@ pub fn new_light<Block: BlockT, Client, F: Fetcher<Block>>(
 			subscriptions,
 			remote_blockchain,
 			fetcher,
+			head_policy,
+			replay_buffer,
 		)),
 	}
 }
@@ -251,24 +334,47 @@ impl<Block, Client> ChainApi<NumberFor<Block>, Block::Hash, Block::Header, Signe
 		self.backend.finalized_head()
 	}
 
-	fn subscribe_all_heads(&self, metadata: Self::Metadata, subscriber: Subscriber<Block::Header>) {
-		self.backend.subscribe_all_heads(metadata, subscriber)
+	fn leaves(&self) -> Result<Vec<Block::Hash>> {
+		self.backend.leaves()
+	}
+
+	fn forks(&self) -> Result<Vec<Fork<NumberFor<Block>, Block::Hash>>> {
+		self.backend.forks()
+	}
+
+	fn subscribe_all_heads(
+		&self,
+		metadata: Self::Metadata,
+		subscriber: Subscriber<Block::Header>,
+		since: Option<Block::Hash>,
+	) {
+		self.backend.subscribe_all_heads(metadata, subscriber, since)
 	}
 
 	fn unsubscribe_all_heads(&self, metadata: Option<Self::Metadata>, id: SubscriptionId) -> RpcResult<bool> {
 		self.backend.unsubscribe_all_heads(metadata, id)
 	}
 
-	fn subscribe_new_heads(&self, metadata: Self::Metadata, subscriber: Subscriber<Block::Header>) {
-		self.backend.subscribe_new_heads(metadata, subscriber)
+	fn subscribe_new_heads(
+		&self,
+		metadata: Self::Metadata,
+		subscriber: Subscriber<Block::Header>,
+		since: Option<Block::Hash>,
+	) {
+		self.backend.subscribe_new_heads(metadata, subscriber, since)
 	}
 
 	fn unsubscribe_new_heads(&self, metadata: Option<Self::Metadata>, id: SubscriptionId) -> RpcResult<bool> {
 		self.backend.unsubscribe_new_heads(metadata, id)
 	}
 
-	fn subscribe_finalized_heads(&self, metadata: Self::Metadata, subscriber: Subscriber<Block::Header>) {
-		self.backend.subscribe_finalized_heads(metadata, subscriber)
+	fn subscribe_finalized_heads(
+		&self,
+		metadata: Self::Metadata,
+		subscriber: Subscriber<Block::Header>,
+		since: Option<Block::Hash>,
+	) {
+		self.backend.subscribe_finalized_heads(metadata, subscriber, since)
 	}
 
 	fn unsubscribe_finalized_heads(&self, metadata: Option<Self::Metadata>, id: SubscriptionId) -> RpcResult<bool> {
@@ -277,38 +383,63 @@ impl<Block, Client> ChainApi<NumberFor<Block>, Block::Hash, Block::Header, Signe
 }
 
 /// Subscribe to new headers.
-fn subscribe_headers<Block, Client, F, G, S, ERR>(
+///
+/// The subscription starts with the headers returned by `replayed` if the subscription is
+/// resumed, and with the current head otherwise. It is rejected if `replayed` fails.
+fn subscribe_headers<Block, Client, F, G, R, S, ERR>(
 	client: &Arc<Client>,
 	subscriptions: &Subscriptions,
 	subscriber: Subscriber<Block::Header>,
 	best_block_hash: G,
 	stream: F,
+	replayed: R,
 ) where
 	Block: BlockT + 'static,
 	Client: HeaderBackend<Block> + 'static,
 	F: FnOnce() -> S,
 	G: FnOnce() -> Block::Hash,
+	R: FnOnce() -> Result<Option<Vec<Block::Header>>>,
 	ERR: ::std::fmt::Debug,
 	S: Stream<Item=Block::Header, Error=ERR> + Send + 'static,
 {
+	// subscribe before reading the replayed headers, so that none is missed in between.
+	let stream = stream();
+	let replayed = match replayed() {
+		Ok(replayed) => replayed,
+		Err(err) => {
+			let _ = subscriber.reject(err.into());
+			return;
+		},
+	};
+
 	subscriptions.add(subscriber, |sink| {
-		// send current head right at the start.
-		let header = client.header(BlockId::Hash(best_block_hash()))
-			.map_err(client_err)
-			.and_then(|header| {
-				header.ok_or_else(|| "Best header missing.".to_owned().into())
-			})
-			.map_err(Into::into);
+		let (initial, replayed_hashes) = match replayed {
+			Some(headers) => {
+				let hashes = headers.iter().map(|header| header.hash()).collect::<HashSet<_>>();
+				(headers.into_iter().map(|header| Ok(Ok(header))).collect(), hashes)
+			},
+			None => {
+				// send current head right at the start.
+				let header = client.header(BlockId::Hash(best_block_hash()))
+					.map_err(client_err)
+					.and_then(|header| {
+						header.ok_or_else(|| "Best header missing.".to_owned().into())
+					})
+					.map_err(Into::into);
+				(vec![Ok(header)], HashSet::new())
+			},
+		};
 
 		// send further subscriptions
-		let stream = stream()
+		let stream = stream
+			.filter(move |header| !replayed_hashes.contains(&header.hash()))
 			.map(|res| Ok(res))
 			.map_err(|e| warn!("Block notification stream error: {:?}", e));
 
 		sink
 			.sink_map_err(|e| warn!("Error sending notifications: {:?}", e))
 			.send_all(
-				stream::iter_result(vec![Ok(header)])
+				stream::iter_result(initial)
 					.chain(stream)
 			)
 			// we ignore the resulting Stream (if the first stream is over we are unsubscribed)
@@ -316,6 +447,33 @@ fn subscribe_headers<Block, Client, F, G, S, ERR>(
 	});
 }
 
+/// Returns the branches ending with the given `leaves` that the best chain doesn't contain.
+fn forks<Block, Client>(
+	client: &Client,
+	leaves: Vec<Block::Hash>,
+) -> Result<Vec<Fork<NumberFor<Block>, Block::Hash>>> where
+	Block: BlockT,
+	Client: HeaderBackend<Block> + HeaderMetadata<Block, Error = sp_blockchain::Error>,
+{
+	let info = client.info();
+	leaves.into_iter()
+		.filter(|leaf| *leaf != info.best_hash)
+		.map(|leaf| {
+			let number = client.header_metadata(leaf).map_err(client_err)?.number;
+			let common_ancestor = lowest_common_ancestor(client, leaf, info.best_hash)
+				.map_err(client_err)?;
+			Ok(Fork {
+				leaf,
+				number,
+				common_ancestor: common_ancestor.hash,
+				common_ancestor_number: common_ancestor.number,
+				length: number - common_ancestor.number,
+				best_length: info.best_number - common_ancestor.number,
+			})
+		})
+		.collect()
+}
+
 fn client_err(err: sp_blockchain::Error) -> Error {
 	Error::Client(Box::new(err))
 }
@@ -0,0 +1,112 @@
+// Copyright 2019-2020 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Replay of the recent heads to the clients resuming a heads subscription.
+
+use std::{collections::{HashMap, VecDeque}, sync::Arc, time::{Duration, Instant}};
+use futures::{future, stream, Future, StreamExt};
+use parking_lot::Mutex;
+use sc_client_api::BlockchainEvents;
+use sp_runtime::traits::{Block as BlockT, Header};
+
+/// Maximum number of headers kept for each kind of heads subscription.
+const MAX_REPLAYED_HEADS: usize = 1024;
+
+/// Kind of heads subscription.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum HeadsKind {
+	/// Heads of all the imported blocks.
+	All,
+	/// Heads of the new best blocks.
+	New,
+	/// Heads of the finalized blocks.
+	Finalized,
+}
+
+/// Headers recently announced to each kind of heads subscription, replayed to the clients that
+/// resume a subscription after reconnecting.
+///
+/// The headers are kept for the retention period, and at most `MAX_REPLAYED_HEADS` of each kind.
+pub struct HeadsReplayBuffer<Block: BlockT> {
+	retention: Duration,
+	heads: Mutex<HashMap<HeadsKind, VecDeque<(Instant, Block::Header)>>>,
+}
+
+impl<Block: BlockT> HeadsReplayBuffer<Block> {
+	/// Create a buffer keeping the headers for `retention`.
+	pub fn new(retention: Duration) -> Self {
+		HeadsReplayBuffer {
+			retention,
+			heads: Default::default(),
+		}
+	}
+
+	/// Record a header announced to the subscriptions of the given kind.
+	pub fn note(&self, kind: HeadsKind, header: Block::Header) {
+		let now = Instant::now();
+		let mut heads = self.heads.lock();
+		let heads = heads.entry(kind).or_default();
+		heads.push_back((now, header));
+		if heads.len() > MAX_REPLAYED_HEADS {
+			heads.pop_front();
+		}
+		self.prune(heads, now);
+	}
+
+	/// Returns the headers announced to the subscriptions of the given kind after the header
+	/// whose hash is `since`, or `None` if that header isn't in the buffer.
+	pub fn since(&self, kind: HeadsKind, since: &Block::Hash) -> Option<Vec<Block::Header>> {
+		let mut heads = self.heads.lock();
+		let heads = heads.get_mut(&kind)?;
+		self.prune(heads, Instant::now());
+		let position = heads.iter().position(|(_, header)| &header.hash() == since)?;
+		Some(heads.iter().skip(position + 1).map(|(_, header)| header.clone()).collect())
+	}
+
+	/// Drop the headers kept for longer than the retention period.
+	fn prune(&self, heads: &mut VecDeque<(Instant, Block::Header)>, now: Instant) {
+		let retention = self.retention;
+		while heads.front().map_or(false, |(noted, _)| now.duration_since(*noted) > retention) {
+			heads.pop_front();
+		}
+	}
+}
+
+/// Returns a future recording the heads announced by `client` into `buffer`.
+pub fn record_heads<Block, Client>(
+	buffer: Arc<HeadsReplayBuffer<Block>>,
+	client: &Client,
+) -> impl Future<Output = ()> where
+	Block: BlockT,
+	Client: BlockchainEvents<Block>,
+{
+	let imported = client.import_notification_stream().map(|notification| {
+		let mut heads = vec![(HeadsKind::All, notification.header.clone())];
+		if notification.is_new_best {
+			heads.push((HeadsKind::New, notification.header));
+		}
+		heads
+	});
+	let finalized = client.finality_notification_stream()
+		.map(|notification| vec![(HeadsKind::Finalized, notification.header)]);
+
+	stream::select(imported, finalized).for_each(move |heads| {
+		for (kind, header) in heads {
+			buffer.note(kind, header);
+		}
+		future::ready(())
+	})
+}
@@ -31,7 +31,12 @@ use crate::testing::TaskExecutor;
 #[test]
 fn should_return_header() {
 	let client = Arc::new(substrate_test_runtime_client::new());
-	let api = new_full(client.clone(), Subscriptions::new(Arc::new(TaskExecutor)));
+	let api = new_full(
+		client.clone(),
+		Subscriptions::new(Arc::new(TaskExecutor)),
+		HeadPolicy::Best,
+		None,
+	);
 
 	assert_matches!(
 		api.header(Some(client.genesis_hash()).into()).wait(),
@@ -63,7 +68,12 @@ fn should_return_header() {
 #[test]
 fn should_return_a_block() {
 	let mut client = Arc::new(substrate_test_runtime_client::new());
-	let api = new_full(client.clone(), Subscriptions::new(Arc::new(TaskExecutor)));
+	let api = new_full(
+		client.clone(),
+		Subscriptions::new(Arc::new(TaskExecutor)),
+		HeadPolicy::Best,
+		None,
+	);
 
 	let block = client.new_block(Default::default()).unwrap().build().unwrap().block;
 	let block_hash = block.hash();
@@ -114,7 +124,12 @@ fn should_return_a_block() {
 #[test]
 fn should_return_block_hash() {
 	let mut client = Arc::new(substrate_test_runtime_client::new());
-	let api = new_full(client.clone(), Subscriptions::new(Arc::new(TaskExecutor)));
+	let api = new_full(
+		client.clone(),
+		Subscriptions::new(Arc::new(TaskExecutor)),
+		HeadPolicy::Best,
+		None,
+	);
 
 	assert_matches!(
 		api.block_hash(None.into()),
@@ -158,7 +173,12 @@ fn should_return_block_hash() {
 #[test]
 fn should_return_finalized_hash() {
 	let mut client = Arc::new(substrate_test_runtime_client::new());
-	let api = new_full(client.clone(), Subscriptions::new(Arc::new(TaskExecutor)));
+	let api = new_full(
+		client.clone(),
+		Subscriptions::new(Arc::new(TaskExecutor)),
+		HeadPolicy::Best,
+		None,
+	);
 
 	assert_matches!(
 		api.finalized_head(),
@@ -182,15 +202,107 @@ fn should_return_finalized_hash() {
 	);
 }
 
+#[test]
+fn should_only_serve_finalized_blocks() {
+	let mut client = Arc::new(substrate_test_runtime_client::new());
+	let api = new_full(
+		client.clone(),
+		Subscriptions::new(Arc::new(TaskExecutor)),
+		HeadPolicy::Finalized,
+		None,
+	);
+
+	let block = client.new_block(Default::default()).unwrap().build().unwrap().block;
+	let block_hash = block.hash();
+	client.import(BlockOrigin::Own, block).unwrap();
+
+	// the best block isn't finalized yet
+	assert_matches!(
+		api.header(None.into()).wait(),
+		Ok(Some(ref x)) if x.number == 0
+	);
+	assert_matches!(
+		api.block_hash(None.into()),
+		Ok(ListOrValue::Value(Some(ref x))) if x == &client.genesis_hash()
+	);
+	assert_matches!(
+		api.block_hash(Some(ListOrValue::Value(1u64.into())).into()),
+		Ok(ListOrValue::Value(None))
+	);
+	// unless requested by hash
+	assert_matches!(
+		api.header(Some(block_hash).into()).wait(),
+		Ok(Some(ref x)) if x.number == 1
+	);
+
+	client.finalize_block(BlockId::number(1), None).unwrap();
+	assert_matches!(
+		api.header(None.into()).wait(),
+		Ok(Some(ref x)) if x.number == 1
+	);
+	assert_matches!(
+		api.block_hash(Some(ListOrValue::Value(1u64.into())).into()),
+		Ok(ListOrValue::Value(Some(ref x))) if x == &block_hash
+	);
+}
+
+#[test]
+fn should_return_leaves_and_forks() {
+	let mut client = Arc::new(substrate_test_runtime_client::new());
+	let api = new_full(
+		client.clone(),
+		Subscriptions::new(Arc::new(TaskExecutor)),
+		HeadPolicy::Best,
+		None,
+	);
+
+	// genesis -> 1 -> 2 is the best chain, genesis -> 1' is a fork
+	let block1 = client.new_block(Default::default()).unwrap().build().unwrap().block;
+	client.import(BlockOrigin::Own, block1).unwrap();
+	let block2 = client.new_block(Default::default()).unwrap().build().unwrap().block;
+	let block2_hash = block2.hash();
+	client.import(BlockOrigin::Own, block2).unwrap();
+
+	let genesis = BlockId::Hash(client.genesis_hash());
+	let mut builder = client.new_block_at(&genesis, Default::default(), false).unwrap();
+	builder.push_storage_change(vec![1], Some(vec![1])).unwrap();
+	let fork = builder.build().unwrap().block;
+	let fork_hash = fork.hash();
+	client.import(BlockOrigin::Own, fork).unwrap();
+
+	assert_eq!(api.leaves().unwrap(), vec![block2_hash, fork_hash]);
+	assert_eq!(api.forks().unwrap(), vec![Fork {
+		leaf: fork_hash,
+		number: 1,
+		common_ancestor: client.genesis_hash(),
+		common_ancestor_number: 0,
+		length: 1,
+		best_length: 2,
+	}]);
+
+	let api = new_full(
+		client.clone(),
+		Subscriptions::new(Arc::new(TaskExecutor)),
+		HeadPolicy::Finalized,
+		None,
+	);
+	assert!(api.leaves().is_err());
+}
+
 #[test]
 fn should_notify_about_latest_block() {
 	let (subscriber, id, transport) = Subscriber::new_test("test");
 
 	{
 		let mut client = Arc::new(substrate_test_runtime_client::new());
-		let api = new_full(client.clone(), Subscriptions::new(Arc::new(TaskExecutor)));
+		let api = new_full(
+			client.clone(),
+			Subscriptions::new(Arc::new(TaskExecutor)),
+			HeadPolicy::Best,
+			None,
+		);
 
-		api.subscribe_all_heads(Default::default(), subscriber);
+		api.subscribe_all_heads(Default::default(), subscriber, None);
 
 		// assert id assigned
 		assert_eq!(executor::block_on(id.compat()), Ok(Ok(SubscriptionId::Number(1))));
@@ -215,9 +327,14 @@ fn should_notify_about_best_block() {
 
 	{
 		let mut client = Arc::new(substrate_test_runtime_client::new());
-		let api = new_full(client.clone(), Subscriptions::new(Arc::new(TaskExecutor)));
+		let api = new_full(
+			client.clone(),
+			Subscriptions::new(Arc::new(TaskExecutor)),
+			HeadPolicy::Best,
+			None,
+		);
 
-		api.subscribe_new_heads(Default::default(), subscriber);
+		api.subscribe_new_heads(Default::default(), subscriber, None);
 
 		// assert id assigned
 		assert_eq!(executor::block_on(id.compat()), Ok(Ok(SubscriptionId::Number(1))));
@@ -242,9 +359,14 @@ fn should_notify_about_finalized_block() {
 
 	{
 		let mut client = Arc::new(substrate_test_runtime_client::new());
-		let api = new_full(client.clone(), Subscriptions::new(Arc::new(TaskExecutor)));
+		let api = new_full(
+			client.clone(),
+			Subscriptions::new(Arc::new(TaskExecutor)),
+			HeadPolicy::Best,
+			None,
+		);
 
-		api.subscribe_finalized_heads(Default::default(), subscriber);
+		api.subscribe_finalized_heads(Default::default(), subscriber, None);
 
 		// assert id assigned
 		assert_eq!(executor::block_on(id.compat()), Ok(Ok(SubscriptionId::Number(1))));
@@ -263,3 +385,64 @@ fn should_notify_about_finalized_block() {
 	// no more notifications on this channel
 	assert_eq!(executor::block_on(next.into_future().compat()).unwrap().0, None);
 }
+
+#[test]
+fn should_replay_missed_heads_to_resumed_subscription() {
+	let (subscriber, id, transport) = Subscriber::new_test("test");
+
+	{
+		let mut client = Arc::new(substrate_test_runtime_client::new());
+		let genesis = client.header(&BlockId::Number(0)).unwrap().unwrap();
+		let buffer = Arc::new(HeadsReplayBuffer::new(std::time::Duration::from_secs(60)));
+		buffer.note(HeadsKind::All, genesis.clone());
+
+		let block = client.new_block(Default::default()).unwrap().build().unwrap().block;
+		let missed = block.header.clone();
+		client.import(BlockOrigin::Own, block).unwrap();
+		buffer.note(HeadsKind::All, missed.clone());
+
+		let api = new_full(
+			client.clone(),
+			Subscriptions::new(Arc::new(TaskExecutor)),
+			HeadPolicy::Best,
+			Some(buffer),
+		);
+
+		api.subscribe_all_heads(Default::default(), subscriber, Some(client.genesis_hash()));
+
+		// assert id assigned
+		assert_eq!(executor::block_on(id.compat()), Ok(Ok(SubscriptionId::Number(1))));
+	}
+
+	// assert the missed head is replayed rather than the current head sent.
+	let (notification, next) = executor::block_on(transport.into_future().compat()).unwrap();
+	assert!(notification.unwrap().contains("\"number\":\"0x1\""));
+	// no more notifications on this channel
+	assert_eq!(executor::block_on(next.into_future().compat()).unwrap().0, None);
+}
+
+#[test]
+fn should_refuse_to_resume_subscription_from_unknown_head() {
+	let client = Arc::new(substrate_test_runtime_client::new());
+	let buffer = Arc::new(HeadsReplayBuffer::new(std::time::Duration::from_secs(60)));
+	let api = new_full(
+		client.clone(),
+		Subscriptions::new(Arc::new(TaskExecutor)),
+		HeadPolicy::Best,
+		Some(buffer),
+	);
+	let (subscriber, id, _transport) = Subscriber::new_test("test");
+	api.subscribe_new_heads(Default::default(), subscriber, Some(H256::repeat_byte(1)));
+	assert!(executor::block_on(id.compat()).unwrap().is_err());
+
+	// the subscriptions can't be resumed without a replay buffer
+	let api = new_full(
+		client.clone(),
+		Subscriptions::new(Arc::new(TaskExecutor)),
+		HeadPolicy::Best,
+		None,
+	);
+	let (subscriber, id, _transport) = Subscriber::new_test("test");
+	api.subscribe_all_heads(Default::default(), subscriber, Some(client.genesis_hash()));
+	assert!(executor::block_on(id.compat()).unwrap().is_err());
+}
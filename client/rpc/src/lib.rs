@@ -24,7 +24,7 @@
 
 mod metadata;
 
-pub use sc_rpc_api::{DenyUnsafe, Subscriptions};
+pub use sc_rpc_api::{DenyUnsafe, HeadPolicy, Subscriptions};
 pub use self::metadata::Metadata;
 pub use rpc::IoHandlerExtension as RpcExtension;
 
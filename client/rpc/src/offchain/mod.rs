@@ -73,4 +73,16 @@ impl<T: OffchainStorage + 'static> OffchainApi for Offchain<T> {
 		};
 		Ok(self.storage.read().get(prefix, &*key).map(Into::into))
 	}
+
+	/// Remove offchain local storage under given key and prefix.
+	fn clear_local_storage(&self, kind: StorageKind, key: Bytes) -> Result<()> {
+		self.deny_unsafe.check_if_safe()?;
+
+		let prefix = match kind {
+			StorageKind::PERSISTENT => sp_offchain::STORAGE_PREFIX,
+			StorageKind::LOCAL => return Err(Error::UnavailableStorageKind),
+		};
+		self.storage.write().remove(prefix, &*key);
+		Ok(())
+	}
 }
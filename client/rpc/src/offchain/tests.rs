@@ -32,9 +32,17 @@ fn local_storage_should_work() {
 		Ok(())
 	);
 	assert_matches!(
-		offchain.get_local_storage(StorageKind::PERSISTENT, key),
+		offchain.get_local_storage(StorageKind::PERSISTENT, key.clone()),
 		Ok(Some(ref v)) if *v == value
 	);
+	assert_matches!(
+		offchain.clear_local_storage(StorageKind::PERSISTENT, key.clone()),
+		Ok(())
+	);
+	assert_matches!(
+		offchain.get_local_storage(StorageKind::PERSISTENT, key),
+		Ok(None)
+	);
 }
 
 #[test]
@@ -49,7 +57,11 @@ fn offchain_calls_considered_unsafe() {
 		Err(Error::UnsafeRpcCalled(_))
 	);
 	assert_matches!(
-		offchain.get_local_storage(StorageKind::PERSISTENT, key),
+		offchain.get_local_storage(StorageKind::PERSISTENT, key.clone()),
+		Err(Error::UnsafeRpcCalled(_))
+	);
+	assert_matches!(
+		offchain.clear_local_storage(StorageKind::PERSISTENT, key),
 		Err(Error::UnsafeRpcCalled(_))
 	);
 }
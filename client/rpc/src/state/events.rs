@@ -0,0 +1,538 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2020 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Decoding of the events of a block with the runtime metadata.
+//!
+//! The events are read from the `Events` storage of the system pallet, a vector of
+//! `EventRecord`s. The metadata names the pallets, the events and the types of their arguments,
+//! but it doesn't describe these types: only the primitive types and the aliases commonly used
+//! by the runtimes are known, e.g. `AccountId` is expected to be 32 bytes long. Decoding stops
+//! at the first event with an argument of another type.
+//!
+//! The size of an alias is checked against the values of that type found in the metadata, the
+//! constants and the default values of the storage, e.g. the `ExistentialDeposit` of the
+//! balances pallet for `Balance`. An alias the runtime defines with another size is unknown.
+
+use std::collections::HashMap;
+
+use codec::{Compact, Decode};
+use frame_metadata::{
+	DecodeDifferent, EventMetadata, ModuleMetadata, RuntimeMetadata, RuntimeMetadataPrefixed,
+	StorageEntryModifier, StorageEntryType,
+};
+use rpc::futures::Future;
+use serde_json::{json, Value};
+use sp_core::{Bytes, bytes::to_hex, hashing::twox_128, storage::{StorageData, StorageKey}};
+use sc_rpc_api::state::{BlockEvents, DecodedEvent, EventArg, EventPhase};
+
+use super::error::FutureResult;
+
+/// Aliases of `u32` commonly used by the runtimes.
+const U32_ALIASES: &[&str] = &[
+	"AccountIndex", "AssetId", "BlockNumber", "EraIndex", "MemberCount", "PropIndex",
+	"ProposalIndex", "ReferendumIndex", "RegistrarIndex", "SessionIndex",
+];
+
+/// Aliases of 32 bytes long types commonly used by the runtimes.
+const BYTES32_ALIASES: &[&str] = &["AccountId", "AuthorityId", "CallHash", "Hash", "H256"];
+
+/// Sizes of the encoded values of the types named in the metadata, by name as in `type_name`.
+type TypeSizes = HashMap<String, usize>;
+
+/// Returns the key of the events of the current block in the storage of the system pallet.
+pub(super) fn events_key() -> StorageKey {
+	let mut key = twox_128(b"System").to_vec();
+	key.extend_from_slice(&twox_128(b"Events"));
+	StorageKey(key)
+}
+
+/// Decode the `events` read from the storage with the `metadata` of the same block.
+pub(super) fn decode<Hash: Decode + Send + 'static>(
+	metadata: FutureResult<Bytes>,
+	events: FutureResult<Option<StorageData>>,
+) -> FutureResult<BlockEvents<Hash>> {
+	Box::new(metadata.join(events).map(|(metadata, events)| decode_events(&metadata, events)))
+}
+
+/// Decode the SCALE encoded `events` of a block with its SCALE encoded `metadata`.
+fn decode_events<Hash: Decode>(metadata: &[u8], events: Option<StorageData>) -> BlockEvents<Hash> {
+	let events = match events {
+		Some(events) => events.0,
+		None => return BlockEvents { events: Vec::new(), undecoded: None },
+	};
+	let (pallets, sizes) = match pallets_events(metadata) {
+		Some(pallets) => pallets,
+		None => return BlockEvents { events: Vec::new(), undecoded: Some(Bytes(events)) },
+	};
+
+	let mut input = &events[..];
+	let count = match Compact::<u32>::decode(&mut input) {
+		Ok(count) => count.0,
+		Err(_) => return BlockEvents { events: Vec::new(), undecoded: Some(Bytes(events)) },
+	};
+
+	let mut decoded = Vec::new();
+	for _ in 0..count {
+		let mut remaining = input;
+		match decode_event(&pallets, &sizes, &mut remaining) {
+			Some(event) => decoded.push(event),
+			None => return BlockEvents { events: decoded, undecoded: Some(Bytes(input.to_vec())) },
+		}
+		input = remaining;
+	}
+
+	BlockEvents { events: decoded, undecoded: None }
+}
+
+/// Returns the names and the events of the pallets declaring events, by index in the outer
+/// event enum of the runtime, and the sizes of the types found in the metadata. `None` if the
+/// metadata can't be decoded.
+fn pallets_events(metadata: &[u8]) -> Option<(Vec<(String, Vec<EventMetadata>)>, TypeSizes)> {
+	let modules = match RuntimeMetadataPrefixed::decode(&mut &metadata[..]).ok()?.1 {
+		RuntimeMetadata::V11(metadata) => decoded(metadata.modules)?,
+		_ => return None,
+	};
+
+	let mut sizes = TypeSizes::new();
+	for module in &modules {
+		add_type_sizes(module, &mut sizes);
+	}
+	let pallets = modules
+		.into_iter()
+		.filter_map(|module| Some((decoded(module.name)?, decoded(module.event?)?)))
+		.collect();
+	Some((pallets, sizes))
+}
+
+/// Add the sizes of the types of the constants of `module`, and of the default values of its
+/// storage. A type found with different sizes, e.g. generic, is given the size `0`.
+fn add_type_sizes(module: &ModuleMetadata, sizes: &mut TypeSizes) {
+	let mut add = |ty: &str, value: &[u8]| {
+		let name = type_name(ty);
+		// e.g. `Vec<T::AccountId>`, whose values don't have a fixed size.
+		if !name.chars().all(|c| c.is_alphanumeric() || c == '_') {
+			return;
+		}
+		let size = sizes.entry(name.to_owned()).or_insert(value.len());
+		if *size != value.len() {
+			*size = 0;
+		}
+	};
+
+	if let DecodeDifferent::Decoded(constants) = &module.constants {
+		for constant in constants {
+			if let (DecodeDifferent::Decoded(ty), DecodeDifferent::Decoded(value)) =
+				(&constant.ty, &constant.value)
+			{
+				add(ty, value);
+			}
+		}
+	}
+
+	let entries = match &module.storage {
+		Some(DecodeDifferent::Decoded(storage)) => match &storage.entries {
+			DecodeDifferent::Decoded(entries) => entries,
+			DecodeDifferent::Encode(_) => return,
+		},
+		_ => return,
+	};
+	for entry in entries {
+		// the default values of the optional entries are `None`.
+		if let (
+			StorageEntryModifier::Default,
+			StorageEntryType::Plain(DecodeDifferent::Decoded(ty)),
+			DecodeDifferent::Decoded(value),
+		) = (&entry.modifier, &entry.ty, &entry.default) {
+			add(ty, value);
+		}
+	}
+}
+
+/// Returns the decoded variant of `value`, which is the only one of the decoded metadata.
+fn decoded<B: 'static, O: 'static>(value: DecodeDifferent<B, O>) -> Option<O> {
+	match value {
+		DecodeDifferent::Decoded(value) => Some(value),
+		DecodeDifferent::Encode(_) => None,
+	}
+}
+
+/// Decode an `EventRecord`, `None` if it can't be.
+fn decode_event<Hash: Decode>(
+	pallets: &[(String, Vec<EventMetadata>)],
+	sizes: &TypeSizes,
+	input: &mut &[u8],
+) -> Option<DecodedEvent<Hash>> {
+	let phase = match u8::decode(input).ok()? {
+		0 => EventPhase::ApplyExtrinsic(u32::decode(input).ok()?),
+		1 => EventPhase::Finalization,
+		2 => EventPhase::Initialization,
+		_ => return None,
+	};
+
+	let (pallet, events) = pallets.get(u8::decode(input).ok()? as usize)?;
+	let event = events.get(u8::decode(input).ok()? as usize)?;
+	let name = match &event.name {
+		DecodeDifferent::Decoded(name) => name.clone(),
+		DecodeDifferent::Encode(_) => return None,
+	};
+	let arguments = match &event.arguments {
+		DecodeDifferent::Decoded(arguments) => arguments,
+		DecodeDifferent::Encode(_) => return None,
+	};
+
+	let args = arguments.iter()
+		.map(|ty| Some(EventArg { ty: ty.clone(), value: decode_value(ty, sizes, input)? }))
+		.collect::<Option<Vec<_>>>()?;
+	let topics = Vec::<Hash>::decode(input).ok()?;
+
+	Some(DecodedEvent { phase, pallet: pallet.clone(), event: name, args, topics })
+}
+
+/// Decode a value of the type named `ty` in the metadata, `None` if the type is unknown or the
+/// value can't be decoded. The aliases whose size in `sizes` differs from the expected one are
+/// unknown.
+fn decode_value(ty: &str, sizes: &TypeSizes, input: &mut &[u8]) -> Option<Value> {
+	let ty = ty.trim();
+
+	if let Some(item) = generic_argument(ty, "Vec") {
+		let len = Compact::<u32>::decode(input).ok()?.0;
+		if item == "u8" {
+			return decode_bytes(len as usize, input);
+		}
+		return (0..len)
+			.map(|_| decode_value(item, sizes, input))
+			.collect::<Option<_>>()
+			.map(Value::Array);
+	}
+	if let Some(value) = generic_argument(ty, "Option") {
+		return match u8::decode(input).ok()? {
+			0 => Some(Value::Null),
+			1 => decode_value(value, sizes, input),
+			_ => None,
+		};
+	}
+	if ty.starts_with('(') && ty.ends_with(')') {
+		return split_tuple(&ty[1..ty.len() - 1])
+			.into_iter()
+			.map(|item| decode_value(item, sizes, input))
+			.collect::<Option<_>>()
+			.map(Value::Array);
+	}
+
+	let name = type_name(ty);
+	if let (Some(size), Some(alias_size)) = (sizes.get(name), alias_size(name)) {
+		if *size != alias_size {
+			return None;
+		}
+	}
+	let value = match name {
+		"bool" => json!(bool::decode(input).ok()?),
+		"u8" => json!(u8::decode(input).ok()?),
+		"u16" => json!(u16::decode(input).ok()?),
+		"u32" => json!(u32::decode(input).ok()?),
+		"i8" => json!(i8::decode(input).ok()?),
+		"i16" => json!(i16::decode(input).ok()?),
+		"i32" => json!(i32::decode(input).ok()?),
+		"u64" | "Moment" => json!(u64::decode(input).ok()?.to_string()),
+		"i64" => json!(i64::decode(input).ok()?.to_string()),
+		"u128" | "Balance" | "BalanceOf" => json!(u128::decode(input).ok()?.to_string()),
+		"i128" => json!(i128::decode(input).ok()?.to_string()),
+		"Bytes" => {
+			let len = Compact::<u32>::decode(input).ok()?.0;
+			decode_bytes(len as usize, input)?
+		},
+		"DispatchInfo" => decode_dispatch_info(input)?,
+		"DispatchError" => decode_dispatch_error(input)?,
+		"DispatchResult" => match u8::decode(input).ok()? {
+			0 => json!({ "ok": null }),
+			1 => json!({ "err": decode_dispatch_error(input)? }),
+			_ => return None,
+		},
+		name if U32_ALIASES.contains(&name) => json!(u32::decode(input).ok()?),
+		name if BYTES32_ALIASES.contains(&name) => decode_bytes(32, input)?,
+		_ => return None,
+	};
+	Some(value)
+}
+
+/// Returns the name of the type `ty`: `T::Balance` and `<T as Trait>::Balance` are `Balance`,
+/// and `BalanceOf<T>` is `Balance` too.
+fn type_name(ty: &str) -> &str {
+	let name = ty.trim().rsplit("::").next().unwrap_or(ty);
+	name.split('<').next().unwrap_or(name)
+}
+
+/// Returns the size of the values of `name` if it is an alias commonly used by the runtimes.
+fn alias_size(name: &str) -> Option<usize> {
+	match name {
+		"Moment" => Some(8),
+		"Balance" | "BalanceOf" => Some(16),
+		name if U32_ALIASES.contains(&name) => Some(4),
+		name if BYTES32_ALIASES.contains(&name) => Some(32),
+		_ => None,
+	}
+}
+
+/// Returns the argument of `ty` if it is the generic type `name` with a single argument.
+fn generic_argument<'a>(ty: &'a str, name: &str) -> Option<&'a str> {
+	if ty.starts_with(name) && ty[name.len()..].starts_with('<') && ty.ends_with('>') {
+		Some(ty[name.len() + 1..ty.len() - 1].trim())
+	} else {
+		None
+	}
+}
+
+/// Split the types of a tuple at its top level commas.
+fn split_tuple(types: &str) -> Vec<&str> {
+	let mut items = Vec::new();
+	let mut depth = 0;
+	let mut start = 0;
+	for (i, c) in types.char_indices() {
+		match c {
+			'<' | '(' | '[' => depth += 1,
+			'>' | ')' | ']' => depth -= 1,
+			',' if depth == 0 => {
+				items.push(types[start..i].trim());
+				start = i + 1;
+			},
+			_ => {},
+		}
+	}
+	let last = types[start..].trim();
+	if !last.is_empty() {
+		items.push(last);
+	}
+	items
+}
+
+fn decode_bytes(len: usize, input: &mut &[u8]) -> Option<Value> {
+	if input.len() < len {
+		return None;
+	}
+	let (bytes, remaining) = input.split_at(len);
+	*input = remaining;
+	Some(json!(to_hex(bytes, false)))
+}
+
+fn decode_dispatch_info(input: &mut &[u8]) -> Option<Value> {
+	let weight = u64::decode(input).ok()?;
+	let class = match u8::decode(input).ok()? {
+		0 => "normal",
+		1 => "operational",
+		2 => "mandatory",
+		_ => return None,
+	};
+	let pays_fee = match u8::decode(input).ok()? {
+		0 => true,
+		1 => false,
+		_ => return None,
+	};
+	Some(json!({ "weight": weight.to_string(), "class": class, "paysFee": pays_fee }))
+}
+
+fn decode_dispatch_error(input: &mut &[u8]) -> Option<Value> {
+	let error = match u8::decode(input).ok()? {
+		0 => json!("other"),
+		1 => json!("cannotLookup"),
+		2 => json!("badOrigin"),
+		3 => json!({
+			"module": {
+				"index": u8::decode(input).ok()?,
+				"error": u8::decode(input).ok()?,
+			},
+		}),
+		_ => return None,
+	};
+	Some(error)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use codec::Encode;
+	use frame_metadata::{
+		DefaultByte, DefaultByteGetter, ExtrinsicMetadata, FnEncode, ModuleConstantMetadata,
+		RuntimeMetadataV11, META_RESERVED,
+	};
+	use sp_core::H256;
+
+	fn system_events() -> &'static [EventMetadata] {
+		&[EventMetadata {
+			name: DecodeDifferent::Encode("ExtrinsicFailed"),
+			arguments: DecodeDifferent::Encode(&["DispatchError", "DispatchInfo"]),
+			documentation: DecodeDifferent::Encode(&[]),
+		}]
+	}
+
+	fn balances_events() -> &'static [EventMetadata] {
+		&[
+			EventMetadata {
+				name: DecodeDifferent::Encode("Transfer"),
+				arguments: DecodeDifferent::Encode(&["AccountId", "AccountId", "Balance"]),
+				documentation: DecodeDifferent::Encode(&[]),
+			},
+			EventMetadata {
+				name: DecodeDifferent::Encode("Unknown"),
+				arguments: DecodeDifferent::Encode(&["Opaque"]),
+				documentation: DecodeDifferent::Encode(&[]),
+			},
+		]
+	}
+
+	struct ConstantValue(&'static [u8]);
+
+	impl DefaultByte for ConstantValue {
+		fn default_byte(&self) -> Vec<u8> {
+			self.0.to_vec()
+		}
+	}
+
+	fn balances_constants() -> &'static [ModuleConstantMetadata] {
+		&[ModuleConstantMetadata {
+			name: DecodeDifferent::Encode("ExistentialDeposit"),
+			ty: DecodeDifferent::Encode("T::Balance"),
+			value: DecodeDifferent::Encode(DefaultByteGetter(&ConstantValue(&[1; 16]))),
+			documentation: DecodeDifferent::Encode(&[]),
+		}]
+	}
+
+	fn u64_balances_constants() -> &'static [ModuleConstantMetadata] {
+		&[ModuleConstantMetadata {
+			name: DecodeDifferent::Encode("ExistentialDeposit"),
+			ty: DecodeDifferent::Encode("T::Balance"),
+			value: DecodeDifferent::Encode(DefaultByteGetter(&ConstantValue(&[1; 8]))),
+			documentation: DecodeDifferent::Encode(&[]),
+		}]
+	}
+
+	fn module(
+		name: &'static str,
+		events: Option<fn() -> &'static [EventMetadata]>,
+		constants: fn() -> &'static [ModuleConstantMetadata],
+	) -> ModuleMetadata {
+		ModuleMetadata {
+			name: DecodeDifferent::Encode(name),
+			storage: None,
+			calls: None,
+			event: events.map(|events| DecodeDifferent::Encode(FnEncode(events))),
+			constants: DecodeDifferent::Encode(FnEncode(constants)),
+			errors: DecodeDifferent::Encode(FnEncode(|| &[])),
+		}
+	}
+
+	fn metadata() -> Vec<u8> {
+		metadata_with_balances_constants(balances_constants)
+	}
+
+	fn metadata_with_balances_constants(
+		balances_constants: fn() -> &'static [ModuleConstantMetadata],
+	) -> Vec<u8> {
+		let modules = vec![
+			module("System", Some(system_events), || &[]),
+			module("Timestamp", None, || &[]),
+			module("Balances", Some(balances_events), balances_constants),
+		];
+		RuntimeMetadataPrefixed(META_RESERVED, RuntimeMetadata::V11(RuntimeMetadataV11 {
+			modules: DecodeDifferent::Encode(Box::leak(modules.into_boxed_slice())),
+			extrinsic: ExtrinsicMetadata { version: 4, signed_extensions: Vec::new() },
+		})).encode()
+	}
+
+	#[test]
+	fn decodes_events_up_to_an_unknown_argument() {
+		let failed = (0u8, 1u32, 0u8, 0u8, (3u8, 2u8, 5u8), (10u64, 1u8, 0u8), Vec::<H256>::new());
+		let transfer = (1u8, 1u8, 0u8, [1u8; 32], [2u8; 32], 100u128, vec![H256::repeat_byte(3)]);
+		let unknown = (2u8, 1u8, 1u8, 7u32, Vec::<H256>::new()).encode();
+		let mut events = Compact(3u32).encode();
+		failed.encode_to(&mut events);
+		transfer.encode_to(&mut events);
+		events.extend(&unknown);
+
+		let decoded = decode_events::<H256>(&metadata(), Some(StorageData(events.clone())));
+		assert_eq!(decoded.undecoded, Some(Bytes(unknown)));
+		assert_eq!(
+			serde_json::to_value(&decoded.events).unwrap(),
+			json!([
+				{
+					"phase": { "applyExtrinsic": 1 },
+					"pallet": "System",
+					"event": "ExtrinsicFailed",
+					"args": [
+						{
+							"type": "DispatchError",
+							"value": { "module": { "index": 2, "error": 5 } },
+						},
+						{
+							"type": "DispatchInfo",
+							"value": { "weight": "10", "class": "operational", "paysFee": true },
+						},
+					],
+					"topics": [],
+				},
+				{
+					"phase": "finalization",
+					"pallet": "Balances",
+					"event": "Transfer",
+					"args": [
+						{ "type": "AccountId", "value": to_hex(&[1; 32], false) },
+						{ "type": "AccountId", "value": to_hex(&[2; 32], false) },
+						{ "type": "Balance", "value": "100" },
+					],
+					"topics": [H256::repeat_byte(3)],
+				},
+			]),
+		);
+
+		// without a metadata, nothing is decoded
+		let decoded = decode_events::<H256>(&[], Some(StorageData(events.clone())));
+		assert_eq!(decoded, BlockEvents { events: Vec::new(), undecoded: Some(Bytes(events)) });
+	}
+
+	#[test]
+	fn aliases_defined_with_another_size_by_the_runtime_are_unknown() {
+		// the `Balance` of the runtime is a `u64`
+		let transfer = (1u8, 1u8, 0u8, [1u8; 32], [2u8; 32], 100u64, Vec::<H256>::new()).encode();
+		let mut events = Compact(1u32).encode();
+		events.extend(&transfer);
+
+		let metadata = metadata_with_balances_constants(u64_balances_constants);
+		let decoded = decode_events::<H256>(&metadata, Some(StorageData(events)));
+		assert_eq!(decoded, BlockEvents { events: Vec::new(), undecoded: Some(Bytes(transfer)) });
+
+		let mut sizes = TypeSizes::new();
+		sizes.insert("Balance".into(), 8);
+		assert_eq!(decode_value("T::Balance", &sizes, &mut &100u64.encode()[..]), None);
+		assert_eq!(decode_value("T::BlockNumber", &sizes, &mut &1u32.encode()[..]), Some(json!(1)));
+	}
+
+	#[test]
+	fn decodes_composite_values() {
+		let sizes = TypeSizes::new();
+		let mut input = &(vec![Some(1u32), None], (true, 2u64)).encode()[..];
+		assert_eq!(
+			decode_value("Vec<Option<T::BlockNumber>>", &sizes, &mut input),
+			Some(json!([1, null])),
+		);
+		assert_eq!(decode_value("(bool, u64)", &sizes, &mut input), Some(json!([true, "2"])));
+		assert!(input.is_empty());
+
+		let mut input = &vec![1u8, 2].encode()[..];
+		assert_eq!(decode_value("Vec<u8>", &sizes, &mut input), Some(json!("0x0102")));
+		assert_eq!(decode_value("Unknown", &sizes, &mut &[0u8][..]), None);
+	}
+}
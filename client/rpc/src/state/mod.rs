@@ -18,33 +18,81 @@
 
 //! Substrate state API.
 
+mod events;
 mod state_full;
 mod state_light;
 
 #[cfg(test)]
 mod tests;
 
-use std::sync::Arc;
+use std::{sync::Arc, time::Duration};
 use jsonrpc_pubsub::{typed::Subscriber, SubscriptionId};
 use rpc::{Result as RpcResult, futures::{Future, future::result}};
 
-use sc_rpc_api::{Subscriptions, state::ReadProof};
+use sc_rpc_api::{
+	DenyUnsafe, HeadPolicy, Subscriptions, state::{BlockEvents, ReadProof, StorageDiff},
+};
 use sc_client_api::light::{RemoteBlockchain, Fetcher};
 use sp_core::{Bytes, storage::{StorageKey, PrefixedStorageKey, StorageData, StorageChangeSet}};
 use sp_version::RuntimeVersion;
 use sp_runtime::traits::Block as BlockT;
 
 use sp_api::{Metadata, ProvideRuntimeApi, CallApiAt};
+use prometheus_endpoint::{
+	register, exponential_buckets, CounterVec, Histogram, HistogramOpts, Opts, PrometheusError,
+	Registry, U64,
+};
 
 use self::error::{Error, FutureResult};
 
 pub use sc_rpc_api::state::*;
 pub use sc_rpc_api::child_state::*;
-use sc_client_api::{ExecutorProvider, StorageProvider, BlockchainEvents, Backend, ProofProvider};
+use sc_client_api::{
+	ExecutorProvider, StorageProvider, BlockchainEvents, Backend, ProofProvider, StatePinning,
+};
 use sp_blockchain::{HeaderMetadata, HeaderBackend};
 
 const STORAGE_KEYS_PAGED_MAX_COUNT: u32 = 1000;
 
+/// Number of seconds a block is pinned for by default.
+const PIN_BLOCK_DEFAULT_DURATION: u64 = 60;
+
+/// Maximum number of seconds a block is pinned for at once.
+const PIN_BLOCK_MAX_DURATION: u64 = 600;
+
+/// Maximum number of keys proven by a single read proof.
+const READ_PROOF_MAX_KEYS: u32 = 1000;
+
+/// Maximum size in bytes of a read proof.
+const READ_PROOF_MAX_SIZE: usize = 4 * 1024 * 1024;
+
+/// Prometheus metrics of the read proofs served over RPC.
+#[derive(Clone)]
+pub struct ReadProofMetrics {
+	proofs: CounterVec<U64>,
+	proof_size: Histogram,
+}
+
+impl ReadProofMetrics {
+	/// Register the metrics in `registry`.
+	pub fn register(registry: &Registry) -> Result<Self, PrometheusError> {
+		Ok(ReadProofMetrics {
+			proofs: register(CounterVec::new(
+				Opts::new("rpc_read_proofs", "Number of read proofs requested over RPC"),
+				&["trie", "outcome"], // top | child, served | too_large | failed
+			)?, registry)?,
+			proof_size: register(Histogram::with_opts(HistogramOpts {
+				common_opts: Opts::new(
+					"rpc_read_proof_size",
+					"Size in bytes of the read proofs generated for RPC requests",
+				),
+				buckets: exponential_buckets(256.0, 4.0, 9)
+					.expect("parameters are always valid values; qed"),
+			})?, registry)?,
+		})
+	}
+}
+
 /// State backend API.
 pub trait StateBackend<Block: BlockT, Client>: Send + Sync + 'static
 	where
@@ -109,6 +157,11 @@ pub trait StateBackend<Block: BlockT, Client>: Send + Sync + 'static
 	/// Returns the runtime metadata as an opaque blob.
 	fn metadata(&self, block: Option<Block::Hash>) -> FutureResult<Bytes>;
 
+	/// Returns the events of a block, decoded with the runtime metadata.
+	fn events(&self, block: Option<Block::Hash>) -> FutureResult<BlockEvents<Block::Hash>> {
+		events::decode(self.metadata(block), self.storage(block, events::events_key()))
+	}
+
 	/// Get the runtime version.
 	fn runtime_version(&self, block: Option<Block::Hash>) -> FutureResult<RuntimeVersion>;
 
@@ -137,6 +190,16 @@ pub trait StateBackend<Block: BlockT, Client>: Send + Sync + 'static
 		keys: Vec<StorageKey>,
 	) -> FutureResult<ReadProof<Block::Hash>>;
 
+	/// Returns whether the state of a block is available.
+	fn available_at(&self, block: Block::Hash) -> FutureResult<bool>;
+
+	/// Keep the state of a block from being pruned for the given duration. Returns the time it
+	/// is actually kept for.
+	fn pin_block(&self, block: Block::Hash, duration: Duration) -> FutureResult<Duration>;
+
+	/// Allow pruning the state of a pinned block again.
+	fn unpin_block(&self, block: Block::Hash) -> FutureResult<bool>;
+
 	/// New runtime version subscription
 	fn subscribe_runtime_version(
 		&self,
@@ -151,12 +214,13 @@ pub trait StateBackend<Block: BlockT, Client>: Send + Sync + 'static
 		id: SubscriptionId,
 	) -> RpcResult<bool>;
 
-	/// New storage subscription
+	/// New storage subscription, resumed after the block `since` if any.
 	fn subscribe_storage(
 		&self,
 		_meta: crate::metadata::Metadata,
 		subscriber: Subscriber<StorageChangeSet<Block::Hash>>,
 		keys: Option<Vec<StorageKey>>,
+		since: Option<Block::Hash>,
 	);
 
 	/// Unsubscribe from storage subscription
@@ -165,35 +229,71 @@ pub trait StateBackend<Block: BlockT, Client>: Send + Sync + 'static
 		_meta: Option<crate::metadata::Metadata>,
 		id: SubscriptionId,
 	) -> RpcResult<bool>;
+
+	/// New storage diffs subscription
+	fn subscribe_storage_diffs(
+		&self,
+		_meta: crate::metadata::Metadata,
+		subscriber: Subscriber<StorageDiff<Block::Hash>>,
+	);
+
+	/// Unsubscribe from storage diffs subscription
+	fn unsubscribe_storage_diffs(
+		&self,
+		_meta: Option<crate::metadata::Metadata>,
+		id: SubscriptionId,
+	) -> RpcResult<bool>;
 }
 
 /// Create new state API that works on full node.
+///
+/// The read proofs are reported to `read_proof_metrics`, if any. The requests that don't specify
+/// a block are served the head chosen by `head_policy`. The runtime calls are executed in wasm
+/// and interrupted after `call_timeout`, if any.
 pub fn new_full<BE, Block: BlockT, Client>(
 	client: Arc<Client>,
 	subscriptions: Subscriptions,
+	read_proof_metrics: Option<ReadProofMetrics>,
+	head_policy: HeadPolicy,
+	call_timeout: Option<Duration>,
+	deny_unsafe: DenyUnsafe,
 ) -> (State<Block, Client>, ChildState<Block, Client>)
 	where
 		Block: BlockT + 'static,
 		BE: Backend<Block> + 'static,
 		Client: ExecutorProvider<Block> + StorageProvider<Block, BE> + ProofProvider<Block> + HeaderBackend<Block>
 			+ HeaderMetadata<Block, Error = sp_blockchain::Error> + BlockchainEvents<Block>
-			+ CallApiAt<Block, Error = sp_blockchain::Error>
+			+ CallApiAt<Block, Error = sp_blockchain::Error> + StatePinning<Block>
 			+ ProvideRuntimeApi<Block> + Send + Sync + 'static,
 		Client::Api: Metadata<Block, Error = sp_blockchain::Error>,
 {
-	let child_backend = Box::new(
-		self::state_full::FullState::new(client.clone(), subscriptions.clone())
-	);
-	let backend = Box::new(self::state_full::FullState::new(client, subscriptions));
-	(State { backend }, ChildState { backend: child_backend })
+	let child_backend = Box::new(self::state_full::FullState::new(
+		client.clone(),
+		subscriptions.clone(),
+		read_proof_metrics.clone(),
+		head_policy,
+		call_timeout,
+	));
+	let backend = Box::new(self::state_full::FullState::new(
+		client,
+		subscriptions,
+		read_proof_metrics,
+		head_policy,
+		call_timeout,
+	));
+	(State { backend, deny_unsafe }, ChildState { backend: child_backend })
 }
 
 /// Create new state API that works on light node.
+///
+/// The requests that don't specify a block are served the head chosen by `head_policy`.
 pub fn new_light<BE, Block: BlockT, Client, F: Fetcher<Block>>(
 	client: Arc<Client>,
 	subscriptions: Subscriptions,
 	remote_blockchain: Arc<dyn RemoteBlockchain<Block>>,
 	fetcher: Arc<F>,
+	head_policy: HeadPolicy,
+	deny_unsafe: DenyUnsafe,
 ) -> (State<Block, Client>, ChildState<Block, Client>)
 	where
 		Block: BlockT + 'static,
@@ -209,6 +309,7 @@ pub fn new_light<BE, Block: BlockT, Client, F: Fetcher<Block>>(
 			subscriptions.clone(),
 			remote_blockchain.clone(),
 			fetcher.clone(),
+			head_policy,
 	));
 
 	let backend = Box::new(self::state_light::LightState::new(
@@ -216,13 +317,16 @@ pub fn new_light<BE, Block: BlockT, Client, F: Fetcher<Block>>(
 			subscriptions,
 			remote_blockchain,
 			fetcher,
+			head_policy,
 	));
-	(State { backend }, ChildState { backend: child_backend })
+	(State { backend, deny_unsafe }, ChildState { backend: child_backend })
 }
 
 /// State API with subscriptions support.
 pub struct State<Block, Client> {
 	backend: Box<dyn StateBackend<Block, Client>>,
+	/// Whether to deny unsafe calls
+	deny_unsafe: DenyUnsafe,
 }
 
 impl<Block, Client> StateApi<Block::Hash> for State<Block, Client>
@@ -286,6 +390,10 @@ impl<Block, Client> StateApi<Block::Hash> for State<Block, Client>
 		self.backend.metadata(block)
 	}
 
+	fn events(&self, block: Option<Block::Hash>) -> FutureResult<BlockEvents<Block::Hash>> {
+		self.backend.events(block)
+	}
+
 	fn query_storage(
 		&self,
 		keys: Vec<StorageKey>,
@@ -304,22 +412,63 @@ impl<Block, Client> StateApi<Block::Hash> for State<Block, Client>
 	}
 
 	fn read_proof(&self, keys: Vec<StorageKey>, block: Option<Block::Hash>) -> FutureResult<ReadProof<Block::Hash>> {
+		if let Err(e) = check_read_proof_keys(&keys) {
+			return Box::new(result(Err(e)));
+		}
 		self.backend.read_proof(block, keys)
 	}
 
+	fn available_at(&self, hash: Block::Hash) -> FutureResult<bool> {
+		self.backend.available_at(hash)
+	}
+
+	fn pin_block(&self, hash: Block::Hash, duration: Option<u64>) -> FutureResult<u64> {
+		if let Err(e) = self.deny_unsafe.check_if_safe() {
+			return Box::new(result(Err(e.into())));
+		}
+
+		let duration = duration.unwrap_or(PIN_BLOCK_DEFAULT_DURATION).min(PIN_BLOCK_MAX_DURATION);
+		Box::new(self.backend.pin_block(hash, Duration::from_secs(duration)).map(|kept| kept.as_secs()))
+	}
+
+	fn unpin_block(&self, hash: Block::Hash) -> FutureResult<bool> {
+		if let Err(e) = self.deny_unsafe.check_if_safe() {
+			return Box::new(result(Err(e.into())));
+		}
+
+		self.backend.unpin_block(hash)
+	}
+
 	fn subscribe_storage(
 		&self,
 		meta: Self::Metadata,
 		subscriber: Subscriber<StorageChangeSet<Block::Hash>>,
-		keys: Option<Vec<StorageKey>>
+		keys: Option<Vec<StorageKey>>,
+		since: Option<Block::Hash>,
 	) {
-		self.backend.subscribe_storage(meta, subscriber, keys);
+		self.backend.subscribe_storage(meta, subscriber, keys, since);
 	}
 
 	fn unsubscribe_storage(&self, meta: Option<Self::Metadata>, id: SubscriptionId) -> RpcResult<bool> {
 		self.backend.unsubscribe_storage(meta, id)
 	}
 
+	fn subscribe_storage_diffs(
+		&self,
+		meta: Self::Metadata,
+		subscriber: Subscriber<StorageDiff<Block::Hash>>,
+	) {
+		self.backend.subscribe_storage_diffs(meta, subscriber);
+	}
+
+	fn unsubscribe_storage_diffs(
+		&self,
+		meta: Option<Self::Metadata>,
+		id: SubscriptionId,
+	) -> RpcResult<bool> {
+		self.backend.unsubscribe_storage_diffs(meta, id)
+	}
+
 	fn runtime_version(&self, at: Option<Block::Hash>) -> FutureResult<RuntimeVersion> {
 		self.backend.runtime_version(at)
 	}
@@ -378,6 +527,14 @@ pub trait ChildStateBackend<Block: BlockT, Client>: Send + Sync + 'static
 		Box::new(self.storage(block, storage_key, key)
 			.map(|x| x.map(|x| x.0.len() as u64)))
 	}
+
+	/// Returns proof of child storage entries at a specific block's state.
+	fn read_proof(
+		&self,
+		block: Option<Block::Hash>,
+		storage_key: PrefixedStorageKey,
+		keys: Vec<StorageKey>,
+	) -> FutureResult<ReadProof<Block::Hash>>;
 }
 
 /// Child state API with subscriptions support.
@@ -427,6 +584,25 @@ impl<Block, Client> ChildStateApi<Block::Hash> for ChildState<Block, Client>
 	) -> FutureResult<Option<u64>> {
 		self.backend.storage_size(block, storage_key, key)
 	}
+
+	fn read_proof(
+		&self,
+		storage_key: PrefixedStorageKey,
+		keys: Vec<StorageKey>,
+		block: Option<Block::Hash>
+	) -> FutureResult<ReadProof<Block::Hash>> {
+		if let Err(e) = check_read_proof_keys(&keys) {
+			return Box::new(result(Err(e)));
+		}
+		self.backend.read_proof(block, storage_key, keys)
+	}
+}
+
+fn check_read_proof_keys(keys: &[StorageKey]) -> Result<(), Error> {
+	if keys.len() > READ_PROOF_MAX_KEYS as usize {
+		return Err(Error::InvalidCount { value: keys.len() as u32, max: READ_PROOF_MAX_KEYS });
+	}
+	Ok(())
 }
 
 fn client_err(err: sp_blockchain::Error) -> Error {
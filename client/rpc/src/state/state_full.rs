@@ -16,32 +16,42 @@
 
 //! State API backend for full nodes.
 
-use std::collections::{BTreeMap, HashMap};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::sync::Arc;
 use std::ops::Range;
+use std::time::Duration;
 use futures::{future, StreamExt as _, TryStreamExt as _};
 use log::warn;
 use jsonrpc_pubsub::{typed::Subscriber, SubscriptionId};
 use rpc::{Result as RpcResult, futures::{stream, Future, Sink, Stream, future::result}};
 
-use sc_rpc_api::{Subscriptions, state::ReadProof};
+use sc_rpc_api::{HeadPolicy, Subscriptions, state::{BlockEvents, ReadProof, StorageDiff}};
 use sc_client_api::backend::Backend;
 use sp_blockchain::{Result as ClientResult, Error as ClientError, HeaderMetadata, CachedHeaderMetadata, HeaderBackend};
-use sc_client_api::BlockchainEvents;
+use sc_client_api::{BlockchainEvents, IndexedBlock, IndexerNotification};
 use sp_core::{
 	Bytes, storage::{well_known_keys, StorageKey, StorageData, StorageChangeSet,
 	ChildInfo, ChildType, PrefixedStorageKey},
+	traits::RuntimeCallDeadlineExt,
 };
+use sp_externalities::Extensions;
+use sp_state_machine::ExecutionStrategy;
 use sp_version::RuntimeVersion;
 use sp_runtime::{
-	generic::BlockId, traits::{Block as BlockT, NumberFor, SaturatedConversion, CheckedSub},
+	generic::BlockId,
+	traits::{Block as BlockT, Header as HeaderT, NumberFor, SaturatedConversion, CheckedSub},
 };
 
 use sp_api::{Metadata, ProvideRuntimeApi, CallApiAt};
 
-use super::{StateBackend, ChildStateBackend, error::{FutureResult, Error, Result}, client_err};
+use super::{
+	StateBackend, ChildStateBackend, ReadProofMetrics, READ_PROOF_MAX_SIZE,
+	error::{FutureResult, Error, Result}, client_err, events,
+};
 use std::marker::PhantomData;
-use sc_client_api::{CallExecutor, StorageProvider, ExecutorProvider, ProofProvider};
+use sc_client_api::{
+	CallExecutor, StorageProvider, ExecutorProvider, ProofProvider, StatePinning, StorageProof,
+};
 
 /// Ranges to query in state_queryStorage.
 struct QueryStorageRange<Block: BlockT> {
@@ -61,6 +71,9 @@ struct QueryStorageRange<Block: BlockT> {
 pub struct FullState<BE, Block: BlockT, Client> {
 	client: Arc<Client>,
 	subscriptions: Subscriptions,
+	read_proof_metrics: Option<ReadProofMetrics>,
+	head_policy: HeadPolicy,
+	call_timeout: Option<Duration>,
 	_phantom: PhantomData<(BE, Block)>
 }
 
@@ -72,13 +85,81 @@ impl<BE, Block: BlockT, Client> FullState<BE, Block, Client>
 		Block: BlockT + 'static,
 {
 	/// Create new state API backend for full nodes.
-	pub fn new(client: Arc<Client>, subscriptions: Subscriptions) -> Self {
-		Self { client, subscriptions, _phantom: PhantomData }
+	pub fn new(
+		client: Arc<Client>,
+		subscriptions: Subscriptions,
+		read_proof_metrics: Option<ReadProofMetrics>,
+		head_policy: HeadPolicy,
+		call_timeout: Option<Duration>,
+	) -> Self {
+		Self {
+			client,
+			subscriptions,
+			read_proof_metrics,
+			head_policy,
+			call_timeout,
+			_phantom: PhantomData,
+		}
 	}
 
-	/// Returns given block hash or best block hash if None is passed.
+	/// Returns the hash of the head served when no block is specified.
+	fn head(&self) -> Block::Hash {
+		let info = self.client.info();
+		self.head_policy.select(info.best_hash, info.finalized_hash)
+	}
+
+	/// Returns given block hash or the head hash if None is passed.
 	fn block_or_best(&self, hash: Option<Block::Hash>) -> ClientResult<Block::Hash> {
-		Ok(hash.unwrap_or_else(|| self.client.info().best_hash))
+		Ok(hash.unwrap_or_else(|| self.head()))
+	}
+
+	/// Generates a read proof of `keys` in the `trie` (`top` or `child`) at given block or best
+	/// block, proving the keys one by one with `prove`, and reports it to the metrics.
+	///
+	/// The size of the proof is checked after every key, so that no more than a key's worth of
+	/// nodes is collected beyond the maximum size.
+	fn read_proof_at(
+		&self,
+		block: Option<Block::Hash>,
+		trie: &str,
+		keys: &[StorageKey],
+		prove: impl Fn(&BlockId<Block>, &[u8]) -> ClientResult<StorageProof>,
+	) -> Result<ReadProof<Block::Hash>> {
+		let proof = self.block_or_best(block).map_err(client_err).and_then(|block| {
+			let mut nodes = HashSet::new();
+			let mut size = 0;
+			for key in keys {
+				let key_proof = prove(&BlockId::Hash(block), &key.0).map_err(client_err)?;
+				for node in key_proof.iter_nodes() {
+					if !nodes.contains(&node) {
+						size += node.len();
+						nodes.insert(node);
+					}
+				}
+				if size > READ_PROOF_MAX_SIZE {
+					break;
+				}
+			}
+
+			if let Some(metrics) = &self.read_proof_metrics {
+				metrics.proof_size.observe(size as f64);
+			}
+			if size > READ_PROOF_MAX_SIZE {
+				return Err(Error::ProofTooLarge { size, max: READ_PROOF_MAX_SIZE });
+			}
+			Ok(ReadProof { at: block, proof: nodes.into_iter().map(Into::into).collect() })
+		});
+
+		if let Some(metrics) = &self.read_proof_metrics {
+			let outcome = match proof {
+				Ok(_) => "served",
+				Err(Error::ProofTooLarge { .. }) => "too_large",
+				Err(_) => "failed",
+			};
+			metrics.proofs.with_label_values(&[trie, outcome]).inc();
+		}
+
+		proof
 	}
 
 	/// Splits the `query_storage` block range into 'filtered' and 'unfiltered' subranges.
@@ -222,7 +303,7 @@ impl<BE, Block, Client> StateBackend<Block, Client> for FullState<BE, Block, Cli
 	Client: ExecutorProvider<Block> + StorageProvider<Block, BE> + ProofProvider<Block> + HeaderBackend<Block>
 		+ HeaderMetadata<Block, Error = sp_blockchain::Error> + BlockchainEvents<Block>
 		+ CallApiAt<Block, Error = sp_blockchain::Error> + ProvideRuntimeApi<Block>
-		+ Send + Sync + 'static,
+		+ StatePinning<Block> + Send + Sync + 'static,
 	Client::Api: Metadata<Block, Error = sp_blockchain::Error>,
 {
 	fn call(
@@ -231,6 +312,15 @@ impl<BE, Block, Client> StateBackend<Block, Client> for FullState<BE, Block, Cli
 		method: String,
 		call_data: Bytes,
 	) -> FutureResult<Bytes> {
+		// only the wasm execution can be interrupted once the call exceeds its timeout.
+		let (strategy, extensions) = match self.call_timeout {
+			Some(timeout) => {
+				let mut extensions = Extensions::new();
+				extensions.register(RuntimeCallDeadlineExt::after(timeout));
+				(ExecutionStrategy::AlwaysWasm, Some(extensions))
+			},
+			None => (self.client.execution_extensions().strategies().other, None),
+		};
 		let r = self.block_or_best(block)
 			.and_then(|block| self
 				.client
@@ -239,8 +329,8 @@ impl<BE, Block, Client> StateBackend<Block, Client> for FullState<BE, Block, Cli
 					&BlockId::Hash(block),
 					&method,
 					&*call_data,
-					self.client.execution_extensions().strategies().other,
-					None,
+					strategy,
+					extensions,
 				)
 				.map(Into::into)
 			).map_err(client_err);
@@ -318,6 +408,15 @@ impl<BE, Block, Client> StateBackend<Block, Client> for FullState<BE, Block, Cli
 				.map_err(client_err)))
 	}
 
+	fn events(&self, block: Option<Block::Hash>) -> FutureResult<BlockEvents<Block::Hash>> {
+		// the metadata and the events are read at the same block, whatever the best block is
+		let block = match self.block_or_best(block) {
+			Ok(block) => Some(block),
+			Err(e) => return Box::new(result(Err(client_err(e)))),
+		};
+		events::decode(self.metadata(block), self.storage(block, events::events_key()))
+	}
+
 	fn runtime_version(&self, block: Option<Block::Hash>) -> FutureResult<RuntimeVersion> {
 		Box::new(result(
 			self.block_or_best(block)
@@ -347,7 +446,7 @@ impl<BE, Block, Client> StateBackend<Block, Client> for FullState<BE, Block, Cli
 		keys: Vec<StorageKey>,
 		at: Option<Block::Hash>
 	) -> FutureResult<Vec<StorageChangeSet<Block::Hash>>> {
-		let at = at.unwrap_or_else(|| self.client.info().best_hash);
+		let at = at.unwrap_or_else(|| self.head());
 		self.query_storage(at, Some(at), keys)
 	}
 
@@ -356,19 +455,21 @@ impl<BE, Block, Client> StateBackend<Block, Client> for FullState<BE, Block, Cli
 		block: Option<Block::Hash>,
 		keys: Vec<StorageKey>,
 	) -> FutureResult<ReadProof<Block::Hash>> {
-		Box::new(result(
-			self.block_or_best(block)
-				.and_then(|block| {
-					self.client
-						.read_proof(
-							&BlockId::Hash(block),
-							&mut keys.iter().map(|key| key.0.as_ref()),
-						)
-						.map(|proof| proof.iter_nodes().map(|node| node.into()).collect())
-						.map(|proof| ReadProof { at: block, proof })
-				})
-				.map_err(client_err),
-		))
+		Box::new(result(self.read_proof_at(block, "top", &keys, |block, key| {
+			self.client.read_proof(block, &mut std::iter::once(key))
+		})))
+	}
+
+	fn available_at(&self, block: Block::Hash) -> FutureResult<bool> {
+		Box::new(result(self.client.has_state_at(&BlockId::Hash(block)).map_err(client_err)))
+	}
+
+	fn pin_block(&self, block: Block::Hash, duration: Duration) -> FutureResult<Duration> {
+		Box::new(result(self.client.pin_state(block, duration).map_err(client_err)))
+	}
+
+	fn unpin_block(&self, block: Block::Hash) -> FutureResult<bool> {
+		Box::new(result(Ok(self.client.unpin_state(&block))))
 	}
 
 	fn subscribe_runtime_version(
@@ -376,15 +477,24 @@ impl<BE, Block, Client> StateBackend<Block, Client> for FullState<BE, Block, Cli
 		_meta: crate::metadata::Metadata,
 		subscriber: Subscriber<RuntimeVersion>,
 	) {
-		let stream = match self.client.storage_changes_notification_stream(
-			Some(&[StorageKey(well_known_keys::CODE.to_vec())]),
-			None,
-		) {
-			Ok(stream) => stream,
-			Err(err) => {
-				let _ = subscriber.reject(Error::from(client_err(err)).into());
-				return;
-			}
+		// the blocks whose runtime version may have changed
+		let stream = match self.head_policy {
+			HeadPolicy::Best => match self.client.storage_changes_notification_stream(
+				Some(&[StorageKey(well_known_keys::CODE.to_vec())]),
+				None,
+			) {
+				Ok(stream) => {
+					let client = self.client.clone();
+					stream.map(move |_| client.info().best_hash).boxed()
+				},
+				Err(err) => {
+					let _ = subscriber.reject(Error::from(client_err(err)).into());
+					return;
+				}
+			},
+			HeadPolicy::Finalized => self.client.finality_notification_stream()
+				.map(|notification| notification.hash)
+				.boxed(),
 		};
 
 		self.subscriptions.add(subscriber, |sink| {
@@ -396,10 +506,9 @@ impl<BE, Block, Client> StateBackend<Block, Client> for FullState<BE, Block, Cli
 			let mut previous_version = version.clone();
 
 			let stream = stream
-				.filter_map(move |_| {
-					let info = client.info();
+				.filter_map(move |block| {
 					let version = client
-						.runtime_version_at(&BlockId::hash(info.best_hash))
+						.runtime_version_at(&BlockId::hash(block))
 						.map_err(client_err)
 						.map_err(Into::into);
 					if previous_version != version {
@@ -435,43 +544,87 @@ impl<BE, Block, Client> StateBackend<Block, Client> for FullState<BE, Block, Cli
 		_meta: crate::metadata::Metadata,
 		subscriber: Subscriber<StorageChangeSet<Block::Hash>>,
 		keys: Option<Vec<StorageKey>>,
+		since: Option<Block::Hash>,
 	) {
 		let keys = Into::<Option<Vec<_>>>::into(keys);
-		let stream = match self.client.storage_changes_notification_stream(
-			keys.as_ref().map(|x| &**x),
-			None
-		) {
-			Ok(stream) => stream,
-			Err(err) => {
-				let _ = subscriber.reject(client_err(err).into());
+		if since.is_some() && keys.is_none() {
+			let _ = subscriber.reject(Error::SubscriptionUnavailable(
+				"The subscriptions to all the storage changes can't be resumed".into(),
+			).into());
+			return;
+		}
+		let stream = match (self.head_policy, keys.as_ref()) {
+			(HeadPolicy::Best, keys) => match self.client.storage_changes_notification_stream(
+				keys.map(|x| &**x),
+				None
+			) {
+				Ok(stream) => stream
+					.map(|(block, changes)| StorageChangeSet {
+						block,
+						changes: changes.iter()
+							.filter_map(|(o_sk, k, v)| if o_sk.is_none() {
+								Some((k.clone(),v.cloned()))
+							} else { None }).collect(),
+					})
+					.boxed(),
+				Err(err) => {
+					let _ = subscriber.reject(client_err(err).into());
+					return;
+				},
+			},
+			(HeadPolicy::Finalized, Some(keys)) => {
+				// the changes are the values differing from the ones at the previous
+				// finalized block
+				let client = self.client.clone();
+				let head = self.head();
+				let mut values = keys.iter()
+					.map(|key| (key.clone(), client.storage(&BlockId::Hash(head), key).ok().flatten()))
+					.collect::<HashMap<_, _>>();
+				self.client.finality_notification_stream()
+					.filter_map(move |notification| {
+						let block = notification.hash;
+						let changes = values.iter_mut()
+							.filter_map(|(key, value)| {
+								let new_value = client.storage(&BlockId::Hash(block), key).ok().flatten();
+								if *value == new_value {
+									return None;
+								}
+								*value = new_value.clone();
+								Some((key.clone(), new_value))
+							})
+							.collect::<Vec<_>>();
+						future::ready(if changes.is_empty() {
+							None
+						} else {
+							Some(StorageChangeSet { block, changes })
+						})
+					})
+					.boxed()
+			},
+			(HeadPolicy::Finalized, None) => {
+				let _ = subscriber.reject(Error::SubscriptionUnavailable(
+					"The changes of all the storage can't be followed on the finalized blocks, \
+					the keys must be given".into(),
+				).into());
 				return;
 			},
 		};
 
-		// initial values
-		let initial = stream::iter_result(keys
-			.map(|keys| {
-				let block = self.client.info().best_hash;
-				let changes = keys
-					.into_iter()
-					.map(|key| StateBackend::storage(self, Some(block.clone()).into(), key.clone())
-						.map(|val| (key.clone(), val))
-						.wait()
-						.unwrap_or_else(|_| (key, None))
-					)
-					.collect();
-				vec![Ok(Ok(StorageChangeSet { block, changes }))]
-			}).unwrap_or_default());
+		// initial values, or the ones which changed after `since`
+		let block = self.head();
+		let initial = match keys.map(|keys| resumed_changes(&*self.client, block, since, keys)) {
+			None => Vec::new(),
+			Some(Ok(changes)) => vec![Ok(Ok(StorageChangeSet { block, changes }))],
+			Some(Err(err)) => {
+				let _ = subscriber.reject(err.into());
+				return;
+			},
+		};
+		let initial = stream::iter_result(initial);
 
 		self.subscriptions.add(subscriber, |sink| {
 			let stream = stream
-				.map(|(block, changes)| Ok::<_, ()>(Ok(StorageChangeSet {
-					block,
-					changes: changes.iter()
-						.filter_map(|(o_sk, k, v)| if o_sk.is_none() {
-							Some((k.clone(),v.cloned()))
-						} else { None }).collect(),
-				})))
+				.map(|changes| Ok::<_, ()>(Ok(changes)))
 				.compat();
 
 			sink
@@ -489,15 +642,122 @@ impl<BE, Block, Client> StateBackend<Block, Client> for FullState<BE, Block, Cli
 	) -> RpcResult<bool> {
 		Ok(self.subscriptions.cancel(id))
 	}
+
+	fn subscribe_storage_diffs(
+		&self,
+		_meta: crate::metadata::Metadata,
+		subscriber: Subscriber<StorageDiff<Block::Hash>>,
+	) {
+		if !self.client.storage_diffs_enabled() {
+			let _ = subscriber.reject(Error::SubscriptionUnavailable(
+				"The storage diffs are only sent by the nodes started with `--storage-diffs`".into(),
+			).into());
+			return;
+		}
+
+		let head_policy = self.head_policy;
+		// the diffs of the imported blocks, until they are finalized or pruned
+		let mut unfinalized = HashMap::new();
+		let stream = self.client.indexer_notification_stream()
+			.filter_map(move |notification| future::ready(match (head_policy, notification) {
+				(HeadPolicy::Best, IndexerNotification::Imported(block)) => storage_diff_of(&block),
+				(HeadPolicy::Best, IndexerNotification::Finalized(_)) => None,
+				(HeadPolicy::Finalized, IndexerNotification::Imported(block)) => {
+					if let Some(diff) = storage_diff_of(&block) {
+						unfinalized.insert(block.hash, (*block.header.number(), diff));
+					}
+					None
+				},
+				(HeadPolicy::Finalized, IndexerNotification::Finalized(finalized)) => {
+					let diff = unfinalized.remove(&finalized.hash).map(|(_, diff)| diff);
+					// the other blocks at or below the finalized one are never finalized
+					let number = *finalized.header.number();
+					unfinalized.retain(|_, (block_number, _)| *block_number > number);
+					diff
+				},
+			}));
+
+		self.subscriptions.add(subscriber, |sink| {
+			let stream = stream
+				.map(|diff| Ok::<_, ()>(Ok(diff)))
+				.compat();
+
+			sink
+				.sink_map_err(|e| warn!("Error sending notifications: {:?}", e))
+				.send_all(stream)
+				// we ignore the resulting Stream (if the first stream is over we are unsubscribed)
+				.map(|_| ())
+		});
+	}
+
+	fn unsubscribe_storage_diffs(
+		&self,
+		_meta: Option<crate::metadata::Metadata>,
+		id: SubscriptionId,
+	) -> RpcResult<bool> {
+		Ok(self.subscriptions.cancel(id))
+	}
+}
+
+/// Returns the values of `keys` at `block` which differ from the ones at `since`, or all of them
+/// if `since` is `None`.
+fn resumed_changes<BE, Block, Client>(
+	client: &Client,
+	block: Block::Hash,
+	since: Option<Block::Hash>,
+	keys: Vec<StorageKey>,
+) -> Result<Vec<(StorageKey, Option<StorageData>)>> where
+	BE: Backend<Block>,
+	Block: BlockT,
+	Client: StorageProvider<Block, BE>,
+{
+	let since_values = match since {
+		Some(since) => Some(keys.iter()
+			.map(|key| client.storage(&BlockId::Hash(since), key))
+			.collect::<ClientResult<Vec<_>>>()
+			.map_err(|err| Error::SubscriptionUnavailable(format!(
+				"The subscription can't be resumed from block {}: {}",
+				since,
+				err,
+			)))?),
+		None => None,
+	};
+
+	let mut changes = Vec::with_capacity(keys.len());
+	for (index, key) in keys.into_iter().enumerate() {
+		let value = client.storage(&BlockId::Hash(block), &key).unwrap_or(None);
+		if since_values.as_ref().map_or(true, |since_values| since_values[index] != value) {
+			changes.push((key, value));
+		}
+	}
+	Ok(changes)
+}
+
+/// Returns the diff of an imported block as sent to the RPC subscribers, if its changes are known.
+fn storage_diff_of<Block: BlockT>(block: &IndexedBlock<Block>) -> Option<StorageDiff<Block::Hash>> {
+	block.storage_changes.as_ref().map(|(changes, child_changes)| StorageDiff {
+		block: block.hash,
+		changes: storage_diff(changes),
+		child_changes: child_changes.iter()
+			.map(|(key, changes)| (StorageKey(key.clone()), storage_diff(changes)))
+			.collect(),
+	})
+}
+
+/// Returns the changes of a storage as sent to the RPC subscribers.
+fn storage_diff(changes: &[(Vec<u8>, Option<Vec<u8>>)]) -> Vec<(StorageKey, Option<StorageData>)> {
+	changes.iter()
+		.map(|(key, value)| (StorageKey(key.clone()), value.clone().map(StorageData)))
+		.collect()
 }
 
 impl<BE, Block, Client> ChildStateBackend<Block, Client> for FullState<BE, Block, Client> where
 	Block: BlockT + 'static,
 	BE: Backend<Block> + 'static,
-	Client: ExecutorProvider<Block> + StorageProvider<Block, BE> + HeaderBackend<Block>
-		+ HeaderMetadata<Block, Error = sp_blockchain::Error> + BlockchainEvents<Block>
-		+ CallApiAt<Block, Error = sp_blockchain::Error> + ProvideRuntimeApi<Block>
-		+ Send + Sync + 'static,
+	Client: ExecutorProvider<Block> + StorageProvider<Block, BE> + ProofProvider<Block>
+		+ HeaderBackend<Block> + HeaderMetadata<Block, Error = sp_blockchain::Error>
+		+ BlockchainEvents<Block> + CallApiAt<Block, Error = sp_blockchain::Error>
+		+ ProvideRuntimeApi<Block> + Send + Sync + 'static,
 	Client::Api: Metadata<Block, Error = sp_blockchain::Error>,
 {
 	fn storage_keys(
@@ -565,6 +825,21 @@ impl<BE, Block, Client> ChildStateBackend<Block, Client> for FullState<BE, Block
 				})
 				.map_err(client_err)))
 	}
+
+	fn read_proof(
+		&self,
+		block: Option<Block::Hash>,
+		storage_key: PrefixedStorageKey,
+		keys: Vec<StorageKey>,
+	) -> FutureResult<ReadProof<Block::Hash>> {
+		let child_info = match ChildType::from_prefixed_key(&storage_key) {
+			Some((ChildType::ParentKeyId, storage_key)) => ChildInfo::new_default(storage_key),
+			None => return Box::new(result(Err(client_err("Invalid child storage key".into())))),
+		};
+		Box::new(result(self.read_proof_at(block, "child", &keys, |block, key| {
+			self.client.read_child_proof(block, &child_info, &mut std::iter::once(key))
+		})))
+	}
 }
 
 /// Splits passed range into two subranges where:
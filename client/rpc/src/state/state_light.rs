@@ -19,6 +19,7 @@
 use std::{
 	sync::Arc,
 	collections::{HashSet, HashMap, hash_map::Entry},
+	time::Duration,
 };
 use codec::Decode;
 use futures::{
@@ -38,7 +39,7 @@ use rpc::{
 	futures::stream::Stream,
 };
 
-use sc_rpc_api::{Subscriptions, state::ReadProof};
+use sc_rpc_api::{HeadPolicy, Subscriptions, state::{ReadProof, StorageDiff}};
 use sp_blockchain::{Error as ClientError, HeaderBackend};
 use sc_client_api::{
 	BlockchainEvents,
@@ -68,6 +69,7 @@ pub struct LightState<Block: BlockT, F: Fetcher<Block>, Client> {
 	storage_subscriptions: Arc<Mutex<StorageSubscriptions<Block>>>,
 	remote_blockchain: Arc<dyn RemoteBlockchain<Block>>,
 	fetcher: Arc<F>,
+	head_policy: HeadPolicy,
 }
 
 /// Shared requests container.
@@ -146,6 +148,7 @@ impl<Block: BlockT, F: Fetcher<Block> + 'static, Client> LightState<Block, F, Cl
 		subscriptions: Subscriptions,
 		remote_blockchain: Arc<dyn RemoteBlockchain<Block>>,
 		fetcher: Arc<F>,
+		head_policy: HeadPolicy,
 	) -> Self {
 		Self {
 			client,
@@ -158,12 +161,16 @@ impl<Block: BlockT, F: Fetcher<Block> + 'static, Client> LightState<Block, F, Cl
 			})),
 			remote_blockchain,
 			fetcher,
+			head_policy,
 		}
 	}
 
-	/// Returns given block hash or best block hash if None is passed.
+	/// Returns given block hash or the head hash if None is passed.
 	fn block_or_best(&self, hash: Option<Block::Hash>) -> Block::Hash {
-		hash.unwrap_or_else(|| self.client.info().best_hash)
+		hash.unwrap_or_else(|| {
+			let info = self.client.info();
+			self.head_policy.select(info.best_hash, info.finalized_hash)
+		})
 	}
 }
 
@@ -287,12 +294,32 @@ impl<Block, F, Client> StateBackend<Block, Client> for LightState<Block, F, Clie
 		Box::new(result(Err(client_err(ClientError::NotAvailableOnLightClient))))
 	}
 
+	fn available_at(&self, _block: Block::Hash) -> FutureResult<bool> {
+		Box::new(result(Err(client_err(ClientError::NotAvailableOnLightClient))))
+	}
+
+	fn pin_block(&self, _block: Block::Hash, _duration: Duration) -> FutureResult<Duration> {
+		Box::new(result(Err(client_err(ClientError::NotAvailableOnLightClient))))
+	}
+
+	fn unpin_block(&self, _block: Block::Hash) -> FutureResult<bool> {
+		Box::new(result(Err(client_err(ClientError::NotAvailableOnLightClient))))
+	}
+
 	fn subscribe_storage(
 		&self,
 		_meta: crate::metadata::Metadata,
 		subscriber: Subscriber<StorageChangeSet<Block::Hash>>,
-		keys: Option<Vec<StorageKey>>
+		keys: Option<Vec<StorageKey>>,
+		since: Option<Block::Hash>,
 	) {
+		if since.is_some() {
+			let _ = subscriber.reject(Error::SubscriptionUnavailable(
+				"The storage subscriptions can't be resumed on light clients".into(),
+			).into());
+			return;
+		}
+
 		let keys = match keys {
 			Some(keys) if !keys.is_empty() => keys,
 			_ => {
@@ -313,7 +340,7 @@ impl<Block, F, Client> StateBackend<Block, Client> for LightState<Block, F, Clie
 			let changes_stream = subscription_stream::<Block, _, _, _, _, _, _, _, _>(
 				storage_subscriptions.clone(),
 				self.client
-					.import_notification_stream()
+					.external_import_notification_stream()
 					.map(|notification| Ok::<_, ()>(notification.hash))
 					.compat(),
 				display_error(storage(
@@ -410,6 +437,22 @@ impl<Block, F, Client> StateBackend<Block, Client> for LightState<Block, F, Clie
 		Ok(true)
 	}
 
+	fn subscribe_storage_diffs(
+		&self,
+		_meta: crate::metadata::Metadata,
+		subscriber: Subscriber<StorageDiff<Block::Hash>>,
+	) {
+		let _ = subscriber.reject(client_err(ClientError::NotAvailableOnLightClient).into());
+	}
+
+	fn unsubscribe_storage_diffs(
+		&self,
+		_meta: Option<crate::metadata::Metadata>,
+		_id: SubscriptionId,
+	) -> RpcResult<bool> {
+		Ok(false)
+	}
+
 	fn subscribe_runtime_version(
 		&self,
 		_meta: crate::metadata::Metadata,
@@ -424,7 +467,7 @@ impl<Block, F, Client> StateBackend<Block, Client> for LightState<Block, F, Clie
 			let versions_stream = subscription_stream::<Block, _, _, _, _, _, _, _, _>(
 				version_subscriptions,
 				self.client
-					.import_notification_stream()
+					.external_import_notification_stream()
 					.map(|notification| Ok::<_, ()>(notification.hash))
 					.compat(),
 				display_error(runtime_version(
@@ -472,6 +515,15 @@ impl<Block, F, Client> ChildStateBackend<Block, Client> for LightState<Block, F,
 		Client: BlockchainEvents<Block> + HeaderBackend<Block> + Send + Sync + 'static,
 		F: Fetcher<Block> + 'static
 {
+	fn read_proof(
+		&self,
+		_block: Option<Block::Hash>,
+		_storage_key: PrefixedStorageKey,
+		_keys: Vec<StorageKey>,
+	) -> FutureResult<ReadProof<Block::Hash>> {
+		Box::new(result(Err(client_err(ClientError::NotAvailableOnLightClient))))
+	}
+
 	fn storage_keys(
 		&self,
 		_block: Option<Block::Hash>,
@@ -22,9 +22,11 @@ use self::error::Error;
 
 use std::sync::Arc;
 use assert_matches::assert_matches;
+use codec::{Decode, Encode};
 use futures01::stream::Stream;
 use sp_core::{storage::ChildInfo, ChangesTrieConfiguration};
 use sp_core::hash::H256;
+use sp_core::hexdisplay::HexDisplay;
 use sc_block_builder::BlockBuilderProvider;
 use sp_io::hashing::blake2_256;
 use substrate_test_runtime_client::{
@@ -32,7 +34,10 @@ use substrate_test_runtime_client::{
 	sp_consensus::BlockOrigin,
 	runtime,
 };
-use sp_runtime::generic::BlockId;
+use sp_runtime::{
+	generic::BlockId,
+	transaction_validity::{TransactionSource, TransactionValidity},
+};
 use crate::testing::TaskExecutor;
 use futures::{executor, compat::Future01CompatExt};
 
@@ -55,7 +60,14 @@ fn should_return_storage() {
 		.add_extra_child_storage(&child_info, KEY.to_vec(), CHILD_VALUE.to_vec())
 		.build();
 	let genesis_hash = client.genesis_hash();
-	let (client, child) = new_full(Arc::new(client), Subscriptions::new(Arc::new(TaskExecutor)));
+	let (client, child) = new_full(
+		Arc::new(client),
+		Subscriptions::new(Arc::new(TaskExecutor)),
+		None,
+		HeadPolicy::Best,
+		None,
+		DenyUnsafe::No,
+	);
 	let key = StorageKey(KEY.to_vec());
 
 	assert_eq!(
@@ -90,7 +102,14 @@ fn should_return_child_storage() {
 		.add_child_storage(&child_info, "key", vec![42_u8])
 		.build());
 	let genesis_hash = client.genesis_hash();
-	let (_client, child) = new_full(client, Subscriptions::new(Arc::new(TaskExecutor)));
+	let (_client, child) = new_full(
+		client,
+		Subscriptions::new(Arc::new(TaskExecutor)),
+		None,
+		HeadPolicy::Best,
+		None,
+		DenyUnsafe::No,
+	);
 	let child_key = prefixed_storage_key();
 	let key = StorageKey(b"key".to_vec());
 
@@ -121,11 +140,74 @@ fn should_return_child_storage() {
 	);
 }
 
+#[test]
+fn should_return_read_proofs() {
+	let child_info = ChildInfo::new_default(STORAGE_KEY);
+	let client = Arc::new(substrate_test_runtime_client::TestClientBuilder::new()
+		.add_extra_storage(b":mock".to_vec(), b"hello world".to_vec())
+		.add_child_storage(&child_info, "key", vec![42_u8])
+		.build());
+	let genesis_hash = client.genesis_hash();
+	let (api, child) = new_full(
+		client,
+		Subscriptions::new(Arc::new(TaskExecutor)),
+		None,
+		HeadPolicy::Best,
+		None,
+		DenyUnsafe::No,
+	);
+	let key = StorageKey(b"key".to_vec());
+
+	let proof = api.read_proof(vec![StorageKey(b":mock".to_vec())], None).wait().unwrap();
+	assert_eq!(proof.at, genesis_hash);
+	assert!(!proof.proof.is_empty());
+
+	let proof = child.read_proof(prefixed_storage_key(), vec![key.clone()], None).wait().unwrap();
+	assert_eq!(proof.at, genesis_hash);
+	assert!(!proof.proof.is_empty());
+
+	assert_matches!(
+		api.read_proof(vec![key; 1001], None).wait(),
+		Err(Error::InvalidCount { value: 1001, max: 1000 })
+	);
+}
+
+#[test]
+fn should_refuse_too_large_read_proofs() {
+	let client = Arc::new(substrate_test_runtime_client::TestClientBuilder::new()
+		.add_extra_storage(b":large".to_vec(), vec![42; READ_PROOF_MAX_SIZE])
+		.add_extra_storage(b":mock".to_vec(), b"hello world".to_vec())
+		.build());
+	let (api, _child) = new_full(
+		client,
+		Subscriptions::new(Arc::new(TaskExecutor)),
+		None,
+		HeadPolicy::Best,
+		None,
+		DenyUnsafe::No,
+	);
+	let large = StorageKey(b":large".to_vec());
+	let mock = StorageKey(b":mock".to_vec());
+
+	assert!(api.read_proof(vec![mock.clone()], None).wait().is_ok());
+	assert_matches!(
+		api.read_proof(vec![large, mock], None).wait(),
+		Err(Error::ProofTooLarge { max: READ_PROOF_MAX_SIZE, .. })
+	);
+}
+
 #[test]
 fn should_call_contract() {
 	let client = Arc::new(substrate_test_runtime_client::new());
 	let genesis_hash = client.genesis_hash();
-	let (client, _child) = new_full(client, Subscriptions::new(Arc::new(TaskExecutor)));
+	let (client, _child) = new_full(
+		client,
+		Subscriptions::new(Arc::new(TaskExecutor)),
+		None,
+		HeadPolicy::Best,
+		None,
+		DenyUnsafe::No,
+	);
 
 	assert_matches!(
 		client.call("balanceOf".into(), Bytes(vec![1,2,3]), Some(genesis_hash).into()).wait(),
@@ -133,15 +215,55 @@ fn should_call_contract() {
 	)
 }
 
+#[test]
+fn should_dry_run_extrinsic_within_call_timeout() {
+	let client = Arc::new(substrate_test_runtime_client::new());
+	let genesis_hash = client.genesis_hash();
+	let extrinsic = runtime::Transfer {
+		from: AccountKeyring::Alice.into(),
+		to: AccountKeyring::Bob.into(),
+		amount: 5,
+		nonce: 0,
+	}.into_signed_tx();
+	let dry_run = |timeout| {
+		let (api, _child) = new_full(
+			client.clone(),
+			Subscriptions::new(Arc::new(TaskExecutor)),
+			None,
+			HeadPolicy::Best,
+			Some(timeout),
+			DenyUnsafe::No,
+		);
+		api.call(
+			"TaggedTransactionQueue_validate_transaction".into(),
+			Bytes((TransactionSource::External, extrinsic.clone()).encode()),
+			Some(genesis_hash).into(),
+		).wait()
+	};
+
+	assert_matches!(
+		dry_run(Duration::from_secs(60)).map(|r| TransactionValidity::decode(&mut &r[..])),
+		Ok(Ok(Ok(_)))
+	);
+	assert_matches!(dry_run(Duration::from_secs(0)), Err(Error::Client(_)));
+}
+
 #[test]
 fn should_notify_about_storage_changes() {
 	let (subscriber, id, transport) = Subscriber::new_test("test");
 
 	{
 		let mut client = Arc::new(substrate_test_runtime_client::new());
-		let (api, _child) = new_full(client.clone(), Subscriptions::new(Arc::new(TaskExecutor)));
+		let (api, _child) = new_full(
+			client.clone(),
+			Subscriptions::new(Arc::new(TaskExecutor)),
+			None,
+			HeadPolicy::Best,
+			None,
+			DenyUnsafe::No,
+		);
 
-		api.subscribe_storage(Default::default(), subscriber, None.into());
+		api.subscribe_storage(Default::default(), subscriber, None.into(), None);
 
 		// assert id assigned
 		assert_eq!(executor::block_on(id.compat()), Ok(Ok(SubscriptionId::Number(1))));
@@ -164,19 +286,160 @@ fn should_notify_about_storage_changes() {
 	assert_eq!(executor::block_on(next.into_future().compat()).unwrap().0, None);
 }
 
+fn client_with_storage_diffs() -> Arc<TestClient> {
+	Arc::new(TestClientBuilder::new()
+		.set_client_config(substrate_test_runtime_client::client::ClientConfig {
+			storage_diffs: true,
+			..Default::default()
+		})
+		.build())
+}
+
+/// Imports a block transferring from Alice to Ferdie, and returns its hash and the values of
+/// their balances it changed.
+fn import_transfer(
+	client: &mut Arc<TestClient>,
+) -> (H256, Vec<(StorageKey, Option<StorageData>)>) {
+	let mut builder = client.new_block(Default::default()).unwrap();
+	builder.push_transfer(runtime::Transfer {
+		from: AccountKeyring::Alice.into(),
+		to: AccountKeyring::Ferdie.into(),
+		amount: 42,
+		nonce: 0,
+	}).unwrap();
+	let block = builder.build().unwrap().block;
+	let hash = block.hash();
+	client.import(BlockOrigin::Own, block).unwrap();
+
+	let balances = [AccountKeyring::Alice, AccountKeyring::Ferdie].iter()
+		.map(|account| {
+			let key = StorageKey(blake2_256(&runtime::system::balance_of_key((*account).into())).to_vec());
+			let value = client.storage(&BlockId::Hash(hash), &key).unwrap();
+			(key, value)
+		})
+		.collect();
+	(hash, balances)
+}
+
+fn storage_diff_of_notification(notification: &str) -> StorageDiff<H256> {
+	let notification: serde_json::Value = serde_json::from_str(notification).unwrap();
+	serde_json::from_value(notification["params"]["result"].clone()).unwrap()
+}
+
+#[test]
+fn should_notify_about_storage_diffs() {
+	let (subscriber, id, transport) = Subscriber::new_test("test");
+	let (hash, balances);
+
+	{
+		let mut client = client_with_storage_diffs();
+		let (api, _child) = new_full(
+			client.clone(),
+			Subscriptions::new(Arc::new(TaskExecutor)),
+			None,
+			HeadPolicy::Best,
+			None,
+			DenyUnsafe::No,
+		);
+
+		api.subscribe_storage_diffs(Default::default(), subscriber);
+
+		// assert id assigned
+		assert_eq!(executor::block_on(id.compat()), Ok(Ok(SubscriptionId::Number(1))));
+
+		let (block_hash, block_balances) = import_transfer(&mut client);
+		hash = block_hash;
+		balances = block_balances;
+	}
+
+	// assert notification sent to transport
+	let (notification, next) = executor::block_on(transport.into_future().compat()).unwrap();
+	let diff = storage_diff_of_notification(&notification.unwrap());
+	assert_eq!(diff.block, hash);
+	for balance in &balances {
+		assert!(diff.changes.contains(balance), "{:?} isn't in the diff", balance);
+	}
+	assert!(diff.child_changes.is_empty());
+	// no more notifications on this channel
+	assert_eq!(executor::block_on(next.into_future().compat()).unwrap().0, None);
+}
+
+#[test]
+fn should_notify_about_finalized_storage_diffs() {
+	let (subscriber, id, transport) = Subscriber::new_test("test");
+	let (hash, balances);
+
+	{
+		let mut client = client_with_storage_diffs();
+		let (api, _child) = new_full(
+			client.clone(),
+			Subscriptions::new(Arc::new(TaskExecutor)),
+			None,
+			HeadPolicy::Finalized,
+			None,
+			DenyUnsafe::No,
+		);
+
+		api.subscribe_storage_diffs(Default::default(), subscriber);
+		assert_eq!(executor::block_on(id.compat()), Ok(Ok(SubscriptionId::Number(1))));
+
+		let (block_hash, block_balances) = import_transfer(&mut client);
+		hash = block_hash;
+		balances = block_balances;
+
+		// the diff of the best block isn't sent until it is finalized
+		let block = client.new_block(Default::default()).unwrap().build().unwrap().block;
+		client.import(BlockOrigin::Own, block).unwrap();
+		client.finalize_block(BlockId::Hash(hash), None).unwrap();
+	}
+
+	let (notification, next) = executor::block_on(transport.into_future().compat()).unwrap();
+	let diff = storage_diff_of_notification(&notification.unwrap());
+	assert_eq!(diff.block, hash);
+	for balance in &balances {
+		assert!(diff.changes.contains(balance), "{:?} isn't in the diff", balance);
+	}
+	assert_eq!(executor::block_on(next.into_future().compat()).unwrap().0, None);
+}
+
+#[test]
+fn should_reject_storage_diffs_subscription_when_disabled() {
+	let (subscriber, id, _transport) = Subscriber::new_test("test");
+	let client = Arc::new(substrate_test_runtime_client::new());
+	let (api, _child) = new_full(
+		client,
+		Subscriptions::new(Arc::new(TaskExecutor)),
+		None,
+		HeadPolicy::Best,
+		None,
+		DenyUnsafe::No,
+	);
+
+	api.subscribe_storage_diffs(Default::default(), subscriber);
+
+	assert!(executor::block_on(id.compat()).unwrap().is_err());
+}
+
 #[test]
 fn should_send_initial_storage_changes_and_notifications() {
 	let (subscriber, id, transport) = Subscriber::new_test("test");
 
 	{
 		let mut client = Arc::new(substrate_test_runtime_client::new());
-		let (api, _child) = new_full(client.clone(), Subscriptions::new(Arc::new(TaskExecutor)));
+		let (api, _child) = new_full(
+			client.clone(),
+			Subscriptions::new(Arc::new(TaskExecutor)),
+			None,
+			HeadPolicy::Best,
+			None,
+			DenyUnsafe::No,
+		);
 
 		let alice_balance_key = blake2_256(&runtime::system::balance_of_key(AccountKeyring::Alice.into()));
 
 		api.subscribe_storage(Default::default(), subscriber, Some(vec![
 			StorageKey(alice_balance_key.to_vec()),
-		]).into());
+		]).into(), None);
 
 		// assert id assigned
 		assert_eq!(executor::block_on(id.compat()), Ok(Ok(SubscriptionId::Number(1))));
@@ -202,10 +465,156 @@ fn should_send_initial_storage_changes_and_notifications() {
 	assert_eq!(executor::block_on(next.into_future().compat()).unwrap().0, None);
 }
 
+#[test]
+fn should_resume_storage_subscription() {
+	let (subscriber, id, transport) = Subscriber::new_test("test");
+	let (unknown_subscriber, unknown_id, _unknown_transport) = Subscriber::new_test("test");
+	let alice_balance_key = blake2_256(&runtime::system::balance_of_key(AccountKeyring::Alice.into()));
+	let bob_balance_key = blake2_256(&runtime::system::balance_of_key(AccountKeyring::Bob.into()));
+
+	{
+		let mut client = Arc::new(substrate_test_runtime_client::new());
+		let (api, _child) = new_full(
+			client.clone(),
+			Subscriptions::new(Arc::new(TaskExecutor)),
+			None,
+			HeadPolicy::Best,
+			None,
+			DenyUnsafe::No,
+		);
+
+		// the client missed the transfer of the block after genesis
+		let mut builder = client.new_block(Default::default()).unwrap();
+		builder.push_transfer(runtime::Transfer {
+			from: AccountKeyring::Alice.into(),
+			to: AccountKeyring::Ferdie.into(),
+			amount: 42,
+			nonce: 0,
+		}).unwrap();
+		let block = builder.build().unwrap().block;
+		client.import(BlockOrigin::Own, block).unwrap();
+
+		let keys = vec![
+			StorageKey(alice_balance_key.to_vec()),
+			StorageKey(bob_balance_key.to_vec()),
+		];
+		api.subscribe_storage(
+			Default::default(),
+			subscriber,
+			Some(keys.clone()).into(),
+			Some(client.genesis_hash()),
+		);
+		assert_eq!(executor::block_on(id.compat()), Ok(Ok(SubscriptionId::Number(1))));
+
+		api.subscribe_storage(
+			Default::default(),
+			unknown_subscriber,
+			Some(keys).into(),
+			Some(H256::repeat_byte(1)),
+		);
+		assert!(executor::block_on(unknown_id.compat()).unwrap().is_err());
+	}
+
+	// only the value which changed after the genesis block is sent
+	let (notification, next) = executor::block_on(transport.into_future().compat()).unwrap();
+	let notification = notification.unwrap();
+	assert!(notification.contains(&format!("0x{}", HexDisplay::from(&alice_balance_key))));
+	assert!(!notification.contains(&format!("0x{}", HexDisplay::from(&bob_balance_key))));
+	assert_eq!(executor::block_on(next.into_future().compat()).unwrap().0, None);
+}
+
+#[test]
+fn should_notify_about_finalized_storage_changes() {
+	let (subscriber, id, transport) = Subscriber::new_test("test");
+	let (all_subscriber, all_id, _all_transport) = Subscriber::new_test("test");
+	let block_hash;
+
+	{
+		let mut client = Arc::new(substrate_test_runtime_client::new());
+		let (api, _child) = new_full(
+			client.clone(),
+			Subscriptions::new(Arc::new(TaskExecutor)),
+			None,
+			HeadPolicy::Finalized,
+			None,
+			DenyUnsafe::No,
+		);
+
+		let alice_balance_key = blake2_256(&runtime::system::balance_of_key(AccountKeyring::Alice.into()));
+
+		api.subscribe_storage(Default::default(), subscriber, Some(vec![
+			StorageKey(alice_balance_key.to_vec()),
+		]).into(), None);
+		assert_eq!(executor::block_on(id.compat()), Ok(Ok(SubscriptionId::Number(1))));
+
+		// the changes of all the storage can't be followed
+		api.subscribe_storage(Default::default(), all_subscriber, None.into(), None);
+		assert!(executor::block_on(all_id.compat()).unwrap().is_err());
+
+		let mut builder = client.new_block(Default::default()).unwrap();
+		builder.push_transfer(runtime::Transfer {
+			from: AccountKeyring::Alice.into(),
+			to: AccountKeyring::Ferdie.into(),
+			amount: 42,
+			nonce: 0,
+		}).unwrap();
+		let block = builder.build().unwrap().block;
+		block_hash = block.hash();
+		client.import(BlockOrigin::Own, block).unwrap();
+
+		// an empty block changing nothing is finalized
+		let block = client.new_block(Default::default()).unwrap().build().unwrap().block;
+		client.import(BlockOrigin::Own, block).unwrap();
+		client.finalize_block(BlockId::number(1), None).unwrap();
+		client.finalize_block(BlockId::number(2), None).unwrap();
+	}
+
+	// assert initial values of the genesis block sent to transport
+	let (notification, next) = executor::block_on(transport.into_future().compat()).unwrap();
+	assert!(notification.is_some());
+	// the change is only notified once its block is finalized
+	let (notification, next) = executor::block_on(next.into_future().compat()).unwrap();
+	assert!(notification.unwrap().contains(&format!("{:?}", block_hash)));
+	// no more notifications on this channel
+	assert_eq!(executor::block_on(next.into_future().compat()).unwrap().0, None);
+}
+
+#[test]
+fn should_notify_about_finalized_runtime_version() {
+	let (subscriber, id, transport) = Subscriber::new_test("test");
+
+	{
+		let client = Arc::new(substrate_test_runtime_client::new());
+		let (api, _child) = new_full(
+			client.clone(),
+			Subscriptions::new(Arc::new(TaskExecutor)),
+			None,
+			HeadPolicy::Finalized,
+			None,
+			DenyUnsafe::No,
+		);
+
+		api.subscribe_runtime_version(Default::default(), subscriber);
+		assert_eq!(executor::block_on(id.compat()), Ok(Ok(SubscriptionId::Number(1))));
+	}
+
+	// the version of the finalized genesis block is sent
+	let (notification, next) = executor::block_on(transport.into_future().compat()).unwrap();
+	assert!(notification.unwrap().contains("\"specVersion\":"));
+	assert_eq!(executor::block_on(next.into_future().compat()).unwrap().0, None);
+}
+
 #[test]
 fn should_query_storage() {
 	fn run_tests(mut client: Arc<TestClient>, has_changes_trie_config: bool) {
-		let (api, _child) = new_full(client.clone(), Subscriptions::new(Arc::new(TaskExecutor)));
+		let (api, _child) = new_full(
+			client.clone(),
+			Subscriptions::new(Arc::new(TaskExecutor)),
+			None,
+			HeadPolicy::Best,
+			None,
+			DenyUnsafe::No,
+		);
 
 		let mut add_block = |nonce| {
 			let mut builder = client.new_block(Default::default()).unwrap();
@@ -409,6 +818,61 @@ fn should_query_storage() {
 	);
 }
 
+#[test]
+fn should_return_state_availability() {
+	let client = Arc::new(substrate_test_runtime_client::new());
+	let genesis_hash = client.genesis_hash();
+	let (api, _child) = new_full(
+		client,
+		Subscriptions::new(Arc::new(TaskExecutor)),
+		None,
+		HeadPolicy::Best,
+		None,
+		DenyUnsafe::No,
+	);
+
+	assert_eq!(api.available_at(genesis_hash).wait().unwrap(), true);
+	assert_eq!(api.available_at(H256::repeat_byte(1)).wait().unwrap(), false);
+}
+
+#[test]
+fn should_pin_blocks() {
+	let client = Arc::new(substrate_test_runtime_client::new());
+	let genesis_hash = client.genesis_hash();
+	let (api, _child) = new_full(
+		client,
+		Subscriptions::new(Arc::new(TaskExecutor)),
+		None,
+		HeadPolicy::Best,
+		None,
+		DenyUnsafe::No,
+	);
+
+	assert_eq!(api.pin_block(genesis_hash, None).wait().unwrap(), 60);
+	assert_eq!(api.pin_block(genesis_hash, Some(3600)).wait().unwrap(), 600);
+	assert_matches!(api.pin_block(H256::repeat_byte(1), None).wait(), Err(Error::Client(_)));
+
+	assert_eq!(api.unpin_block(genesis_hash).wait().unwrap(), true);
+	assert_eq!(api.unpin_block(genesis_hash).wait().unwrap(), false);
+}
+
+#[test]
+fn should_deny_pinning_blocks_over_unsafe_rpc() {
+	let client = Arc::new(substrate_test_runtime_client::new());
+	let genesis_hash = client.genesis_hash();
+	let (api, _child) = new_full(
+		client,
+		Subscriptions::new(Arc::new(TaskExecutor)),
+		None,
+		HeadPolicy::Best,
+		None,
+		DenyUnsafe::Yes,
+	);
+
+	assert_matches!(api.pin_block(genesis_hash, None).wait(), Err(Error::UnsafeRpcCalled(_)));
+	assert_matches!(api.unpin_block(genesis_hash).wait(), Err(Error::UnsafeRpcCalled(_)));
+}
+
 #[test]
 fn should_split_ranges() {
 	assert_eq!(split_range(1, None), (0..1, None));
@@ -422,7 +886,14 @@ fn should_split_ranges() {
 #[test]
 fn should_return_runtime_version() {
 	let client = Arc::new(substrate_test_runtime_client::new());
-	let (api, _child) = new_full(client.clone(), Subscriptions::new(Arc::new(TaskExecutor)));
+	let (api, _child) = new_full(
+		client.clone(),
+		Subscriptions::new(Arc::new(TaskExecutor)),
+		None,
+		HeadPolicy::Best,
+		None,
+		DenyUnsafe::No,
+	);
 
 	let result = "{\"specName\":\"test\",\"implName\":\"parity-test\",\"authoringVersion\":1,\
 		\"specVersion\":2,\"implVersion\":2,\"apis\":[[\"0xdf6acb689907609b\",3],\
@@ -445,7 +916,14 @@ fn should_notify_on_runtime_version_initially() {
 
 	{
 		let client = Arc::new(substrate_test_runtime_client::new());
-		let (api, _child) = new_full(client.clone(), Subscriptions::new(Arc::new(TaskExecutor)));
+		let (api, _child) = new_full(
+			client.clone(),
+			Subscriptions::new(Arc::new(TaskExecutor)),
+			None,
+			HeadPolicy::Best,
+			None,
+			DenyUnsafe::No,
+		);
 
 		api.subscribe_runtime_version(Default::default(), subscriber);
 
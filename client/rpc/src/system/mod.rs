@@ -30,7 +30,7 @@ use sp_runtime::traits::{self, Header as HeaderT};
 use self::error::Result;
 
 pub use sc_rpc_api::system::*;
-pub use self::helpers::{SystemInfo, Health, PeerInfo, NodeRole};
+pub use self::helpers::{SystemInfo, Health, PeerInfo, PeerSyncDetails, NodeRole, StatePruning};
 pub use self::gen_client::Client as SystemClient;
 
 macro_rules! bail_if_unsafe {
@@ -59,6 +59,8 @@ pub enum Request<B: traits::Block> {
 	LocalListenAddresses(oneshot::Sender<Vec<String>>),
 	/// Must return information about the peers we are connected to.
 	Peers(oneshot::Sender<Vec<PeerInfo<B::Hash, <B::Header as HeaderT>::Number>>>),
+	/// Must return the diagnostics of the block requests made to the peers we are connected to.
+	PeerSyncDetails(oneshot::Sender<Vec<PeerSyncDetails<B::Hash, <B::Header as HeaderT>::Number>>>),
 	/// Must return the state of the network.
 	NetworkState(oneshot::Sender<rpc::Value>),
 	/// Must return any potential parse error.
@@ -66,7 +68,9 @@ pub enum Request<B: traits::Block> {
 	/// Must return any potential parse error.
 	NetworkRemoveReservedPeer(String, oneshot::Sender<Result<()>>),
 	/// Must return the node role.
-	NodeRoles(oneshot::Sender<Vec<NodeRole>>)
+	NodeRoles(oneshot::Sender<Vec<NodeRole>>),
+	/// Must put the node into maintenance mode, or take it out of it.
+	SetMaintenance(bool, oneshot::Sender<()>),
 }
 
 impl<B: traits::Block> System<B> {
@@ -96,6 +100,10 @@ impl<B: traits::Block> SystemApi<B::Hash, <B::Header as HeaderT>::Number> for Sy
 		Ok(self.info.impl_version.clone())
 	}
 
+	fn system_node_name(&self) -> Result<String> {
+		Ok(self.info.node_name.clone())
+	}
+
 	fn system_chain(&self) -> Result<String> {
 		Ok(self.info.chain_name.clone())
 	}
@@ -108,6 +116,10 @@ impl<B: traits::Block> SystemApi<B::Hash, <B::Header as HeaderT>::Number> for Sy
 		Ok(self.info.properties.clone())
 	}
 
+	fn system_state_pruning(&self) -> Result<StatePruning> {
+		Ok(self.info.state_pruning.clone())
+	}
+
 	fn system_health(&self) -> Receiver<Health> {
 		let (tx, rx) = oneshot::channel();
 		let _ = self.send_back.unbounded_send(Request::Health(tx));
@@ -139,6 +151,19 @@ impl<B: traits::Block> SystemApi<B::Hash, <B::Header as HeaderT>::Number> for Sy
 		}.boxed().compat()
 	}
 
+	fn sync_peer_details(&self)
+		-> Compat<BoxFuture<'static, rpc::Result<Vec<PeerSyncDetails<B::Hash, <B::Header as HeaderT>::Number>>>>>
+	{
+		bail_if_unsafe!(self.deny_unsafe);
+
+		let (tx, rx) = oneshot::channel();
+		let _ = self.send_back.unbounded_send(Request::PeerSyncDetails(tx));
+
+		async move {
+			rx.await.map_err(|_| rpc::Error::internal_error())
+		}.boxed().compat()
+	}
+
 	fn system_network_state(&self)
 		-> Compat<BoxFuture<'static, rpc::Result<rpc::Value>>>
 	{
@@ -189,4 +214,16 @@ impl<B: traits::Block> SystemApi<B::Hash, <B::Header as HeaderT>::Number> for Sy
 		let _ = self.send_back.unbounded_send(Request::NodeRoles(tx));
 		Receiver(Compat::new(rx))
 	}
+
+	fn system_set_maintenance(&self, enabled: bool)
+		-> Compat<BoxFuture<'static, std::result::Result<(), rpc::Error>>>
+	{
+		bail_if_unsafe!(self.deny_unsafe);
+
+		let (tx, rx) = oneshot::channel();
+		let _ = self.send_back.unbounded_send(Request::SetMaintenance(enabled, tx));
+		async move {
+			rx.await.map_err(|_| rpc::Error::internal_error())
+		}.boxed().compat()
+	}
 }
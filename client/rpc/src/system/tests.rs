@@ -48,6 +48,7 @@ fn api<T: Into<Option<Status>>>(sync: T) -> System<Block> {
 	let should_have_peers = !status.is_dev;
 	let (tx, rx) = mpsc::unbounded();
 	thread::spawn(move || {
+		let mut is_in_maintenance = false;
 		futures::executor::block_on(rx.for_each(move |request| {
 			match request {
 				Request::Health(sender) => {
@@ -55,6 +56,7 @@ fn api<T: Into<Option<Status>>>(sync: T) -> System<Block> {
 						peers: status.peers,
 						is_syncing: status.is_syncing,
 						should_have_peers,
+						is_in_maintenance,
 					});
 				},
 				Request::LocalPeerId(sender) => {
@@ -79,11 +81,29 @@ fn api<T: Into<Option<Status>>>(sync: T) -> System<Block> {
 					}
 					let _ = sender.send(peers);
 				}
+				Request::PeerSyncDetails(sender) => {
+					let mut peers = vec![];
+					for _peer in 0..status.peers {
+						peers.push(PeerSyncDetails {
+							peer_id: status.peer_id.to_base58(),
+							best_hash: Default::default(),
+							best_number: 1,
+							common_number: Some(1),
+							sync_state: Some("Available".into()),
+							requests_in_flight: 0,
+							response_times_ms: vec![100],
+							timeouts: 1,
+							bad_responses: 0,
+						});
+					}
+					let _ = sender.send(peers);
+				}
 				Request::NetworkState(sender) => {
 					let _ = sender.send(serde_json::to_value(&sc_network::network_state::NetworkState {
 						peer_id: String::new(),
 						listened_addresses: Default::default(),
 						external_addresses: Default::default(),
+						confirmed_addresses: Default::default(),
 						connected_peers: Default::default(),
 						not_connected_peers: Default::default(),
 						average_download_per_sec: 0,
@@ -106,6 +126,10 @@ fn api<T: Into<Option<Status>>>(sync: T) -> System<Block> {
 				Request::NodeRoles(sender) => {
 					let _ = sender.send(vec![NodeRole::Authority]);
 				}
+				Request::SetMaintenance(enabled, sender) => {
+					is_in_maintenance = enabled;
+					let _ = sender.send(());
+				}
 			};
 
 			future::ready(())
@@ -115,9 +139,11 @@ fn api<T: Into<Option<Status>>>(sync: T) -> System<Block> {
 		SystemInfo {
 			impl_name: "testclient".into(),
 			impl_version: "0.2.0".into(),
+			node_name: "testnode".into(),
 			chain_name: "testchain".into(),
 			properties: Default::default(),
 			chain_type: Default::default(),
+			state_pruning: StatePruning::Constrained { window: 256 },
 		},
 		tx,
 		sc_rpc_api::DenyUnsafe::No
@@ -137,6 +163,14 @@ fn system_name_works() {
 	);
 }
 
+#[test]
+fn system_node_name_works() {
+	assert_eq!(
+		api(None).system_node_name().unwrap(),
+		"testnode".to_owned(),
+	);
+}
+
 #[test]
 fn system_version_works() {
 	assert_eq!(
@@ -161,6 +195,14 @@ fn system_properties_works() {
 	);
 }
 
+#[test]
+fn system_state_pruning_works() {
+	assert_eq!(
+		api(None).system_state_pruning().unwrap(),
+		StatePruning::Constrained { window: 256 },
+	);
+}
+
 #[test]
 fn system_type_works() {
 	assert_eq!(
@@ -177,6 +219,7 @@ fn system_health() {
 			peers: 0,
 			is_syncing: false,
 			should_have_peers: true,
+			is_in_maintenance: false,
 		}
 	);
 
@@ -191,6 +234,7 @@ fn system_health() {
 			peers: 5,
 			is_syncing: true,
 			should_have_peers: false,
+			is_in_maintenance: false,
 		}
 	);
 
@@ -205,6 +249,7 @@ fn system_health() {
 			peers: 5,
 			is_syncing: false,
 			should_have_peers: true,
+			is_in_maintenance: false,
 		}
 	);
 
@@ -219,6 +264,7 @@ fn system_health() {
 			peers: 0,
 			is_syncing: false,
 			should_have_peers: false,
+			is_in_maintenance: false,
 		}
 	);
 }
@@ -267,6 +313,35 @@ fn system_peers() {
 	);
 }
 
+#[test]
+fn sync_peer_details() {
+	let mut runtime = tokio::runtime::current_thread::Runtime::new().unwrap();
+
+	let peer_id = PeerId::random();
+	let req = api(Status {
+		peer_id: peer_id.clone(),
+		peers: 1,
+		is_syncing: true,
+		is_dev: true,
+	}).sync_peer_details();
+	let res = runtime.block_on(req).unwrap();
+
+	assert_eq!(
+		res,
+		vec![PeerSyncDetails {
+			peer_id: peer_id.to_base58(),
+			best_hash: Default::default(),
+			best_number: 1u64,
+			common_number: Some(1),
+			sync_state: Some("Available".into()),
+			requests_in_flight: 0,
+			response_times_ms: vec![100],
+			timeouts: 1,
+			bad_responses: 0,
+		}]
+	);
+}
+
 #[test]
 fn system_network_state() {
 	let mut runtime = tokio::runtime::current_thread::Runtime::new().unwrap();
@@ -279,6 +354,7 @@ fn system_network_state() {
 			peer_id: String::new(),
 			listened_addresses: Default::default(),
 			external_addresses: Default::default(),
+			confirmed_addresses: Default::default(),
 			connected_peers: Default::default(),
 			not_connected_peers: Default::default(),
 			average_download_per_sec: 0,
@@ -296,6 +372,18 @@ fn system_node_roles() {
 	);
 }
 
+#[test]
+fn system_set_maintenance() {
+	let mut runtime = tokio::runtime::current_thread::Runtime::new().unwrap();
+	let api = api(None);
+
+	assert!(!wait_receiver(api.system_health()).is_in_maintenance);
+	assert_eq!(runtime.block_on(api.system_set_maintenance(true)), Ok(()));
+	assert!(wait_receiver(api.system_health()).is_in_maintenance);
+	assert_eq!(runtime.block_on(api.system_set_maintenance(false)), Ok(()));
+	assert!(!wait_receiver(api.system_health()).is_in_maintenance);
+}
+
 #[test]
 fn system_network_add_reserved() {
 	let good_peer_id = "/ip4/198.51.100.19/tcp/30333/p2p/QmSk5HQbn6LhUwDiNMseVUjuRYhEtYj4aUZ6WfWoGURpdV";
@@ -0,0 +1,229 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2020 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Admin RPC calls tunneled over the peer-to-peer network.
+//!
+//! Nodes exposing no RPC server can still be operated by the admin peers of their configuration:
+//! these peers send JSON-RPC requests for a restricted set of methods over a request-response
+//! protocol, and the node answers them as its RPC servers would. The admin peers are
+//! authenticated by the `PeerId` of their connection.
+
+use std::collections::HashSet;
+
+use futures::{channel::mpsc, compat::Future01CompatExt, Future, StreamExt};
+use log::debug;
+use sc_network::{
+	config::{IncomingRequest, RequestResponseConfig},
+	ExHashT, NetworkService, PeerId, RequestFailure,
+};
+use serde_json::{json, Value};
+use sp_runtime::traits::Block as BlockT;
+
+/// Name of the request-response protocol on the wire.
+pub const PROTOCOL_NAME: &str = "/substrate/admin-rpc/1";
+
+/// Methods the admin peers are allowed to call.
+pub const ALLOWED_METHODS: &[&str] = &[
+	"system_health",
+	"system_peers",
+	"system_setMaintenance",
+	"author_rotateKeys",
+];
+
+/// Number of requests of the admin peers waiting to be answered before new ones are refused.
+const INBOUND_QUEUE_SIZE: usize = 8;
+
+/// JSON-RPC error code of the requests for methods not allowed over the protocol.
+const METHOD_NOT_ALLOWED: i64 = -32601;
+
+/// JSON-RPC error code of the requests the RPC handlers didn't answer, e.g. notifications.
+const NO_RESPONSE: i64 = -32603;
+
+/// JSON-RPC error code of the requests of peers which aren't admin peers.
+const NOT_ADMIN_PEER: i64 = -32000;
+
+/// Returns the configuration of the protocol, and the incoming requests of the admin peers if
+/// there are any.
+///
+/// The protocol is registered by every node so that any node can act as an admin peer, but only
+/// the nodes with admin peers answer requests. The requests of the other peers are refused by
+/// the network, without taking a slot of the inbound queue.
+pub(crate) fn protocol_config(
+	admin_peers: &[PeerId],
+) -> (RequestResponseConfig, Option<mpsc::Receiver<IncomingRequest>>) {
+	let mut config = RequestResponseConfig::new(PROTOCOL_NAME);
+	if admin_peers.is_empty() {
+		return (config, None);
+	}
+
+	let (tx, rx) = mpsc::channel(INBOUND_QUEUE_SIZE);
+	config.set_inbound_queue(tx);
+	config.set_allowed_peers(admin_peers.iter().cloned());
+	(config, Some(rx))
+}
+
+/// Returns the JSON-RPC error response to the request `id`.
+fn error_response(id: &Value, code: i64, message: &str) -> Vec<u8> {
+	json!({
+		"jsonrpc": "2.0",
+		"error": { "code": code, "message": message },
+		"id": id,
+	}).to_string().into_bytes()
+}
+
+/// Returns the id of `request`, or `null` if it has none.
+fn request_id(request: &[u8]) -> Value {
+	serde_json::from_slice::<Value>(request).ok()
+		.and_then(|value| value.get("id").cloned())
+		.unwrap_or(Value::Null)
+}
+
+/// Checks that `request` is a single call of an allowed method, returning the JSON-RPC error
+/// response to send back otherwise.
+fn check_request(request: &[u8]) -> Result<String, Vec<u8>> {
+	let value = match serde_json::from_slice::<Value>(request) {
+		Ok(value @ Value::Object(_)) => value,
+		_ => return Err(error_response(
+			&Value::Null,
+			METHOD_NOT_ALLOWED,
+			"Only single JSON-RPC calls are accepted",
+		)),
+	};
+	match value.get("method").and_then(Value::as_str) {
+		Some(method) if ALLOWED_METHODS.contains(&method) => Ok(value.to_string()),
+		_ => Err(error_response(
+			value.get("id").unwrap_or(&Value::Null),
+			METHOD_NOT_ALLOWED,
+			"Method not allowed over the admin protocol",
+		)),
+	}
+}
+
+/// Answer the requests of the `admin_peers` with `rpc_handlers`, refusing the other peers.
+pub(crate) fn serve(
+	mut requests: mpsc::Receiver<IncomingRequest>,
+	admin_peers: Vec<PeerId>,
+	rpc_handlers: sc_rpc_server::RpcHandler<sc_rpc::Metadata>,
+) -> impl Future<Output = ()> {
+	let admin_peers = admin_peers.into_iter().collect::<HashSet<_>>();
+
+	async move {
+		while let Some(request) = requests.next().await {
+			let IncomingRequest { peer, payload, pending_response } = request;
+			if !admin_peers.contains(&peer) {
+				// The network only queues the requests of the admin peers.
+				debug!("Refused an admin RPC request from {}, which isn't an admin peer", peer);
+				let id = request_id(&payload);
+				let _ = pending_response.send(error_response(&id, NOT_ADMIN_PEER, "Not an admin peer"));
+				continue;
+			}
+
+			let response = match check_request(&payload) {
+				Ok(request) => {
+					debug!("Answering the admin RPC request of {}: {}", peer, request);
+					rpc_handlers.handle_request(&request, Default::default())
+						.compat()
+						.await
+						.ok()
+						.flatten()
+						.map(String::into_bytes)
+						.unwrap_or_else(|| error_response(
+							&request_id(&payload),
+							NO_RESPONSE,
+							"The request got no response",
+						))
+				},
+				Err(error) => error,
+			};
+			let _ = pending_response.send(response);
+		}
+	}
+}
+
+/// Send the JSON-RPC `request` to `peer` through the admin protocol and return its response.
+///
+/// `peer` must be connected, and have the local node among its admin peers.
+pub async fn request<B: BlockT + 'static, H: ExHashT>(
+	network: &NetworkService<B, H>,
+	peer: PeerId,
+	request: String,
+) -> Result<String, RequestFailure> {
+	let response = network.request(peer, PROTOCOL_NAME, request.into_bytes()).await?;
+	Ok(String::from_utf8_lossy(&response).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use futures::{channel::oneshot, executor::block_on};
+
+	#[test]
+	fn only_allowed_methods_are_forwarded() {
+		let health = br#"{"jsonrpc":"2.0","method":"system_health","params":[],"id":1}"#;
+		assert!(check_request(health).is_ok());
+
+		let submit = br#"{"jsonrpc":"2.0","method":"author_submitExtrinsic","params":[],"id":2}"#;
+		let error = serde_json::from_slice::<Value>(&check_request(submit).unwrap_err()).unwrap();
+		assert_eq!(error["id"], 2);
+		assert_eq!(error["error"]["code"], METHOD_NOT_ALLOWED);
+
+		let batch = format!("[{}]", String::from_utf8_lossy(health));
+		assert!(check_request(batch.as_bytes()).is_err());
+		assert!(check_request(b"not json").is_err());
+	}
+
+	#[test]
+	fn requests_are_answered() {
+		let (admin, other) = (PeerId::random(), PeerId::random());
+		let mut rpc_handlers = sc_rpc_server::RpcHandler::<sc_rpc::Metadata>::default();
+		rpc_handlers.add_method("system_health", |_| Ok(json!({ "peers": 3 })));
+
+		let (mut requests, requests_rx) = mpsc::channel(INBOUND_QUEUE_SIZE);
+		let mut send = |peer: &PeerId, payload: &[u8]| {
+			let (pending_response, response) = oneshot::channel();
+			let request = IncomingRequest {
+				peer: peer.clone(),
+				payload: payload.to_vec(),
+				pending_response,
+			};
+			requests.try_send(request).unwrap();
+			response
+		};
+		let health = br#"{"jsonrpc":"2.0","method":"system_health","params":[],"id":1}"#;
+		let answered = send(&admin, health);
+		let not_allowed = send(&admin, br#"{"jsonrpc":"2.0","method":"author_submitExtrinsic","id":2}"#);
+		let notification = send(&admin, br#"{"jsonrpc":"2.0","method":"system_health","params":[]}"#);
+		let not_admin = send(&other, health);
+		drop(send);
+		drop(requests);
+
+		block_on(serve(requests_rx, vec![admin], rpc_handlers));
+
+		let response = |response: oneshot::Receiver<Vec<u8>>| {
+			serde_json::from_slice::<Value>(&block_on(response).unwrap()).unwrap()
+		};
+		let answered = response(answered);
+		assert_eq!(answered["id"], 1);
+		assert_eq!(answered["result"]["peers"], 3);
+		assert_eq!(response(not_allowed)["error"]["code"], METHOD_NOT_ALLOWED);
+		assert_eq!(response(notification)["error"]["code"], NO_RESPONSE);
+		let not_admin = response(not_admin);
+		assert_eq!(not_admin["id"], 1);
+		assert_eq!(not_admin["error"]["code"], NOT_ADMIN_PEER);
+	}
+}
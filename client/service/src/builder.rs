@@ -19,18 +19,25 @@
 use crate::{Service, NetworkStatus, NetworkState, error::Error, DEFAULT_PROTOCOL_ID, MallocSizeOfWasm};
 use crate::{start_rpc_servers, build_network_future, TransactionPoolAdapter, TaskManager, SpawnTaskHandle};
 use crate::status_sinks;
-use crate::config::{Configuration, KeystoreConfig, PrometheusConfig, OffchainWorkerConfig};
+use crate::config::{
+	Configuration, KeystoreConfig, PrometheusConfig, OffchainWorkerConfig, OffchainWorkerTrigger,
+	PruningMode,
+};
 use crate::metrics::MetricsService;
 use sc_client_api::{
 	self, BlockchainEvents, backend::RemoteBackend, light::RemoteBlockchain, execution_extensions::ExtensionsFactory,
 	ExecutorProvider, CallExecutor, ForkBlocks, BadBlocks, CloneableSpawn, UsageProvider,
 };
-use crate::client::{Client, ClientConfig};
+use crate::client::{
+	Client, ClientConfig,
+	genesis::{BuildGenesisBlock, DefaultGenesisBlockBuilder},
+};
 use sp_utils::mpsc::{tracing_unbounded, TracingUnboundedSender};
 use sc_chain_spec::get_extension;
 use sp_consensus::{
 	block_validation::{BlockAnnounceValidator, DefaultBlockAnnounceValidator},
 	import_queue::ImportQueue,
+	ConsensusReplay, ForkChoiceRule, JustificationCheck,
 };
 use futures::{
 	Future, FutureExt, StreamExt,
@@ -43,7 +50,7 @@ use sc_network::{NetworkService, NetworkStateInfo};
 use parking_lot::{Mutex, RwLock};
 use sp_runtime::generic::BlockId;
 use sp_runtime::traits::{
-	Block as BlockT, NumberFor, SaturatedConversion, HashFor,
+	Block as BlockT, Header as HeaderT, NumberFor, SaturatedConversion, HashFor,
 };
 use sp_api::ProvideRuntimeApi;
 use sc_executor::{NativeExecutor, NativeExecutionDispatch, RuntimeInfo};
@@ -53,12 +60,12 @@ use std::{
 	marker::PhantomData, sync::Arc, pin::Pin
 };
 use wasm_timer::SystemTime;
-use sc_telemetry::{telemetry, SUBSTRATE_INFO};
-use sp_transaction_pool::{MaintainedTransactionPool, ChainEvent};
+use sc_telemetry::{telemetry, TelemetryFields, SUBSTRATE_INFO};
+use sp_transaction_pool::{MaintainedTransactionPool, ChainEvent, TransactionSelector};
 use sp_blockchain;
 use prometheus_endpoint::Registry;
 use sc_client_db::{Backend, DatabaseSettings};
-use sp_core::traits::CodeExecutor;
+use sp_core::{crypto::KeyTypeId, traits::{BareCryptoStore, CodeExecutor}};
 use sp_runtime::BuildStorage;
 use sc_client_api::execution_extensions::ExecutionExtensions;
 use sp_core::storage::Storage;
@@ -87,7 +94,7 @@ pub struct ServiceBuilder<TBl, TRtApi, TCl, TFchr, TSc, TImpQu, TFprb, TFpp,
 {
 	config: Configuration,
 	pub (crate) client: Arc<TCl>,
-	backend: Arc<Backend>,
+	pub (crate) backend: Arc<Backend>,
 	task_manager: TaskManager,
 	keystore: Arc<RwLock<Keystore>>,
 	fetcher: Option<TFchr>,
@@ -100,6 +107,9 @@ pub struct ServiceBuilder<TBl, TRtApi, TCl, TFchr, TSc, TImpQu, TFprb, TFpp,
 	remote_backend: Option<Arc<dyn RemoteBlockchain<TBl>>>,
 	marker: PhantomData<(TBl, TRtApi)>,
 	block_announce_validator_builder: Option<Box<dyn FnOnce(Arc<TCl>) -> Box<dyn BlockAnnounceValidator<TBl> + Send> + Send>>,
+	transaction_selector: Option<Arc<dyn TransactionSelector<TExPool>>>,
+	pub (crate) justification_check: Option<Box<dyn Fn() -> Box<dyn JustificationCheck<TBl>> + Send + Sync>>,
+	pub (crate) consensus_replay: Option<Box<dyn Fn() -> Box<dyn ConsensusReplay<TBl>> + Send + Sync>>,
 }
 
 /// A utility trait for building an RPC extension given a `DenyUnsafe` instance.
@@ -211,11 +221,12 @@ pub fn new_full_client<TBl, TRtApi, TExecDisp>(
 	TBl: BlockT,
 	TExecDisp: NativeExecutionDispatch + 'static,
 {
-	new_full_parts(config).map(|parts| parts.0)
+	new_full_parts(config, &DefaultGenesisBlockBuilder).map(|parts| parts.0)
 }
 
 fn new_full_parts<TBl, TRtApi, TExecDisp>(
 	config: &Configuration,
+	genesis_block_builder: &dyn BuildGenesisBlock<TBl>,
 ) -> Result<TFullParts<TBl, TRtApi, TExecDisp>,	Error> where
 	TBl: BlockT,
 	TExecDisp: NativeExecutionDispatch + 'static,
@@ -238,6 +249,10 @@ fn new_full_parts<TBl, TRtApi, TExecDisp>(
 		config.default_heap_pages,
 		config.max_runtime_instances,
 	);
+	let executor = match config.prometheus_config.as_ref() {
+		Some(prometheus_config) => executor.with_prometheus_registry(&prometheus_config.registry)?,
+		None => executor,
+	};
 
 	let chain_spec = &config.chain_spec;
 	let fork_blocks = get_extension::<ForkBlocks<TBl>>(chain_spec.extensions())
@@ -248,13 +263,25 @@ fn new_full_parts<TBl, TRtApi, TExecDisp>(
 		.cloned()
 		.unwrap_or_default();
 
+	let code_substitutes = chain_spec.code_substitutes()
+		.into_iter()
+		.map(|(number, code)| number.parse::<u64>()
+			.map(|number| (number, code))
+			.map_err(|_| Error::Other(format!("Invalid code substitute block number: {}", number)))
+		)
+		.collect::<Result<_, _>>()?;
+
 	let (client, backend) = {
 		let db_config = sc_client_db::DatabaseSettings {
-			state_cache_size: config.state_cache_size,
+			trie_cache_size: config.trie_cache_size,
+			value_cache_size: config.value_cache_size,
 			state_cache_child_ratio:
 			config.state_cache_child_ratio.map(|v| (v, 100)),
 			pruning: config.pruning.clone(),
 			source: config.database.clone(),
+			cold_storage: config.cold_storage.clone(),
+			read_only: config.database_read_only,
+			block_compression: config.block_compression,
 		};
 
 		let extensions = sc_client_api::execution_extensions::ExecutionExtensions::new(
@@ -266,6 +293,7 @@ fn new_full_parts<TBl, TRtApi, TExecDisp>(
 			db_config,
 			executor,
 			chain_spec.as_storage_builder(),
+			genesis_block_builder,
 			fork_blocks,
 			bad_blocks,
 			extensions,
@@ -274,6 +302,10 @@ fn new_full_parts<TBl, TRtApi, TExecDisp>(
 			ClientConfig {
 				offchain_worker_enabled : config.offchain_worker.enabled ,
 				offchain_indexing_api: config.offchain_worker.indexing_enabled,
+				code_substitutes,
+				notifications: config.client_notifications,
+				signature_verification: config.signature_verification,
+				storage_diffs: config.storage_diffs,
 			},
 		)?
 	};
@@ -281,12 +313,21 @@ fn new_full_parts<TBl, TRtApi, TExecDisp>(
 	Ok((client, backend, keystore, task_manager))
 }
 
+/// Returns the key types out of `key_types` that the keystore holds no key of.
+fn missing_session_keys(keystore: &RwLock<Keystore>, key_types: &[KeyTypeId]) -> Vec<KeyTypeId> {
+	let keystore = keystore.read();
+	key_types.iter()
+		.filter(|key_type| keystore.keys(**key_type).map_or(true, |keys| keys.is_empty()))
+		.cloned()
+		.collect()
+}
 
 /// Create an instance of db-backed client.
 pub fn new_client<E, Block, RA>(
 	settings: DatabaseSettings,
 	executor: E,
 	genesis_storage: &dyn BuildStorage,
+	genesis_block_builder: &dyn BuildGenesisBlock<Block>,
 	fork_blocks: ForkBlocks<Block>,
 	bad_blocks: BadBlocks<Block>,
 	execution_extensions: ExecutionExtensions<Block>,
@@ -317,6 +358,7 @@ pub fn new_client<E, Block, RA>(
 			backend.clone(),
 			executor,
 			genesis_storage,
+			genesis_block_builder,
 			fork_blocks,
 			bad_blocks,
 			execution_extensions,
@@ -344,7 +386,33 @@ impl ServiceBuilder<(), (), (), (), (), (), (), (), (), (), ()> {
 		(),
 		TFullBackend<TBl>,
 	>, Error> {
-		let (client, backend, keystore, task_manager) = new_full_parts(&config)?;
+		Self::new_full_with_genesis_block_builder(config, &DefaultGenesisBlockBuilder)
+	}
+
+	/// Start the service builder with a configuration, building the genesis block with
+	/// `genesis_block_builder` if the database doesn't contain the chain yet.
+	pub fn new_full_with_genesis_block_builder<
+		TBl: BlockT,
+		TRtApi,
+		TExecDisp: NativeExecutionDispatch + 'static,
+	>(
+		config: Configuration,
+		genesis_block_builder: &dyn BuildGenesisBlock<TBl>,
+	) -> Result<ServiceBuilder<
+		TBl,
+		TRtApi,
+		TFullClient<TBl, TRtApi, TExecDisp>,
+		Arc<OnDemand<TBl>>,
+		(),
+		(),
+		BoxFinalityProofRequestBuilder<TBl>,
+		Arc<dyn FinalityProofProvider<TBl>>,
+		(),
+		(),
+		TFullBackend<TBl>,
+	>, Error> {
+		let (client, backend, keystore, task_manager) =
+			new_full_parts(&config, genesis_block_builder)?;
 
 		let client = Arc::new(client);
 
@@ -363,6 +431,9 @@ impl ServiceBuilder<(), (), (), (), (), (), (), (), (), (), ()> {
 			rpc_extensions_builder: Box::new(|_| ()),
 			remote_backend: None,
 			block_announce_validator_builder: None,
+			transaction_selector: None,
+			justification_check: None,
+			consensus_replay: None,
 			marker: PhantomData,
 		})
 	}
@@ -382,6 +453,31 @@ impl ServiceBuilder<(), (), (), (), (), (), (), (), (), (), ()> {
 		(),
 		(),
 		TLightBackend<TBl>,
+	>, Error> {
+		Self::new_light_with_genesis_block_builder(config, &DefaultGenesisBlockBuilder)
+	}
+
+	/// Start the service builder with a configuration, building the genesis block with
+	/// `genesis_block_builder` if the database doesn't contain the chain yet.
+	pub fn new_light_with_genesis_block_builder<
+		TBl: BlockT,
+		TRtApi,
+		TExecDisp: NativeExecutionDispatch + 'static,
+	>(
+		config: Configuration,
+		genesis_block_builder: &dyn BuildGenesisBlock<TBl>,
+	) -> Result<ServiceBuilder<
+		TBl,
+		TRtApi,
+		TLightClient<TBl, TRtApi, TExecDisp>,
+		Arc<OnDemand<TBl>>,
+		(),
+		(),
+		BoxFinalityProofRequestBuilder<TBl>,
+		Arc<dyn FinalityProofProvider<TBl>>,
+		(),
+		(),
+		TLightBackend<TBl>,
 	>, Error> {
 		let task_manager = {
 			let registry = config.prometheus_config.as_ref().map(|cfg| &cfg.registry);
@@ -401,14 +497,23 @@ impl ServiceBuilder<(), (), (), (), (), (), (), (), (), (), ()> {
 			config.default_heap_pages,
 			config.max_runtime_instances,
 		);
+		let executor = match config.prometheus_config.as_ref() {
+			Some(prometheus_config) =>
+				executor.with_prometheus_registry(&prometheus_config.registry)?,
+			None => executor,
+		};
 
 		let db_storage = {
 			let db_settings = sc_client_db::DatabaseSettings {
-				state_cache_size: config.state_cache_size,
+				trie_cache_size: config.trie_cache_size,
+				value_cache_size: config.value_cache_size,
 				state_cache_child_ratio:
 					config.state_cache_child_ratio.map(|v| (v, 100)),
 				pruning: config.pruning.clone(),
 				source: config.database.clone(),
+				cold_storage: None,
+				read_only: config.database_read_only,
+				block_compression: config.block_compression,
 			};
 			sc_client_db::light::LightStorage::new(db_settings)?
 		};
@@ -426,6 +531,7 @@ impl ServiceBuilder<(), (), (), (), (), (), (), (), (), (), ()> {
 		let client = Arc::new(crate::client::light::new_light(
 			backend.clone(),
 			config.chain_spec.as_storage_builder(),
+			genesis_block_builder,
 			executor,
 			Box::new(task_manager.spawn_handle()),
 			config.prometheus_config.as_ref().map(|config| config.registry.clone()),
@@ -446,6 +552,9 @@ impl ServiceBuilder<(), (), (), (), (), (), (), (), (), (), ()> {
 			rpc_extensions_builder: Box::new(|_| ()),
 			remote_backend: Some(remote_blockchain),
 			block_announce_validator_builder: None,
+			transaction_selector: None,
+			justification_check: None,
+			consensus_replay: None,
 			marker: PhantomData,
 		})
 	}
@@ -480,6 +589,11 @@ impl<TBl, TRtApi, TCl, TFchr, TSc, TImpQu, TFprb, TFpp, TExPool, TRpc, Backend>
 		self.transaction_pool.clone()
 	}
 
+	/// Returns a handle to spawn tasks that are stopped along with the service.
+	pub fn spawn_handle(&self) -> SpawnTaskHandle {
+		self.task_manager.spawn_handle()
+	}
+
 	/// Returns a reference to the fetcher, only available if builder
 	/// was created with `new_light`.
 	pub fn fetcher(&self) -> Option<TFchr>
@@ -519,6 +633,9 @@ impl<TBl, TRtApi, TCl, TFchr, TSc, TImpQu, TFprb, TFpp, TExPool, TRpc, Backend>
 			rpc_extensions_builder: self.rpc_extensions_builder,
 			remote_backend: self.remote_backend,
 			block_announce_validator_builder: self.block_announce_validator_builder,
+			transaction_selector: self.transaction_selector,
+			justification_check: self.justification_check,
+			consensus_replay: self.consensus_replay,
 			marker: self.marker,
 		})
 	}
@@ -564,6 +681,9 @@ impl<TBl, TRtApi, TCl, TFchr, TSc, TImpQu, TFprb, TFpp, TExPool, TRpc, Backend>
 			rpc_extensions_builder: self.rpc_extensions_builder,
 			remote_backend: self.remote_backend,
 			block_announce_validator_builder: self.block_announce_validator_builder,
+			transaction_selector: self.transaction_selector,
+			justification_check: self.justification_check,
+			consensus_replay: self.consensus_replay,
 			marker: self.marker,
 		})
 	}
@@ -602,6 +722,9 @@ impl<TBl, TRtApi, TCl, TFchr, TSc, TImpQu, TFprb, TFpp, TExPool, TRpc, Backend>
 			rpc_extensions_builder: self.rpc_extensions_builder,
 			remote_backend: self.remote_backend,
 			block_announce_validator_builder: self.block_announce_validator_builder,
+			transaction_selector: self.transaction_selector,
+			justification_check: self.justification_check,
+			consensus_replay: self.consensus_replay,
 			marker: self.marker,
 		})
 	}
@@ -668,6 +791,9 @@ impl<TBl, TRtApi, TCl, TFchr, TSc, TImpQu, TFprb, TFpp, TExPool, TRpc, Backend>
 			rpc_extensions_builder: self.rpc_extensions_builder,
 			remote_backend: self.remote_backend,
 			block_announce_validator_builder: self.block_announce_validator_builder,
+			transaction_selector: self.transaction_selector,
+			justification_check: self.justification_check,
+			consensus_replay: self.consensus_replay,
 			marker: self.marker,
 		})
 	}
@@ -732,6 +858,9 @@ impl<TBl, TRtApi, TCl, TFchr, TSc, TImpQu, TFprb, TFpp, TExPool, TRpc, Backend>
 			rpc_extensions_builder: self.rpc_extensions_builder,
 			remote_backend: self.remote_backend,
 			block_announce_validator_builder: self.block_announce_validator_builder,
+			transaction_selector: None,
+			justification_check: self.justification_check,
+			consensus_replay: self.consensus_replay,
 			marker: self.marker,
 		})
 	}
@@ -769,6 +898,9 @@ impl<TBl, TRtApi, TCl, TFchr, TSc, TImpQu, TFprb, TFpp, TExPool, TRpc, Backend>
 			rpc_extensions_builder: Box::new(rpc_extensions_builder),
 			remote_backend: self.remote_backend,
 			block_announce_validator_builder: self.block_announce_validator_builder,
+			transaction_selector: self.transaction_selector,
+			justification_check: self.justification_check,
+			consensus_replay: self.consensus_replay,
 			marker: self.marker,
 		})
 	}
@@ -814,9 +946,55 @@ impl<TBl, TRtApi, TCl, TFchr, TSc, TImpQu, TFprb, TFpp, TExPool, TRpc, Backend>
 			rpc_extensions_builder: self.rpc_extensions_builder,
 			remote_backend: self.remote_backend,
 			block_announce_validator_builder: Some(Box::new(block_announce_validator_builder)),
+			transaction_selector: self.transaction_selector,
+			justification_check: self.justification_check,
+			consensus_replay: self.consensus_replay,
 			marker: self.marker,
 		})
 	}
+
+	/// Defines the `TransactionSelector` choosing which ready transactions are included in the
+	/// blocks authored by the node, all of them by priority otherwise.
+	///
+	/// Must be called after `with_transaction_pool`, which resets it.
+	pub fn with_transaction_selector(
+		self,
+		transaction_selector:
+			impl FnOnce(&Self) -> Result<Arc<dyn TransactionSelector<TExPool>>, Error>,
+	) -> Result<ServiceBuilder<TBl, TRtApi, TCl, TFchr, TSc, TImpQu, TFprb, TFpp,
+		TExPool, TRpc, Backend>, Error>
+	where TSc: Clone, TFchr: Clone {
+		let transaction_selector = transaction_selector(&self)?;
+
+		Ok(ServiceBuilder {
+			transaction_selector: Some(transaction_selector),
+			..self
+		})
+	}
+
+	/// Defines how the justifications of the blocks read by `validate_blocks` are checked,
+	/// before the blocks are imported. `justification_check` is called to check each input.
+	pub fn with_justification_check(
+		self,
+		justification_check: impl Fn() -> Box<dyn JustificationCheck<TBl>> + Send + Sync + 'static,
+	) -> Result<Self, Error> {
+		Ok(ServiceBuilder {
+			justification_check: Some(Box::new(justification_check)),
+			..self
+		})
+	}
+
+	/// Defines how the consensus inputs recorded with the replay log are replayed by
+	/// `consensus_replay`. `consensus_replay` is called for each replay.
+	pub fn with_consensus_replay(
+		self,
+		consensus_replay: impl Fn() -> Box<dyn ConsensusReplay<TBl>> + Send + Sync + 'static,
+	) -> Result<Self, Error> {
+		Ok(ServiceBuilder {
+			consensus_replay: Some(Box::new(consensus_replay)),
+			..self
+		})
+	}
 }
 
 /// Implemented on `ServiceBuilder`. Allows running block commands, such as import/export/validate
@@ -826,6 +1004,8 @@ pub trait ServiceBuilderCommand {
 	type Block: BlockT;
 	/// Native execution dispatch required by some commands.
 	type NativeDispatch: NativeExecutionDispatch + 'static;
+	/// Offchain storage of the node.
+	type OffchainStorage: sp_core::offchain::OffchainStorage;
 	/// Starts the process of importing blocks.
 	fn import_blocks(
 		self,
@@ -834,6 +1014,20 @@ pub trait ServiceBuilderCommand {
 		binary: bool,
 	) -> Pin<Box<dyn Future<Output = Result<(), Error>> + Send>>;
 
+	/// Checks that the blocks of `input` follow each other, starting from a known block, and that
+	/// their extrinsics roots match their bodies, without importing them.
+	///
+	/// With `check_justifications`, their justifications are checked as well, as defined with
+	/// `ServiceBuilder::with_justification_check`.
+	///
+	/// Returns the number of blocks checked.
+	fn validate_blocks(
+		&self,
+		input: impl Read + Seek + Send + 'static,
+		binary: bool,
+		check_justifications: bool,
+	) -> Result<u64, Error>;
+
 	/// Performs the blocks export.
 	fn export_blocks(
 		self,
@@ -861,6 +1055,66 @@ pub trait ServiceBuilderCommand {
 		&self,
 		block: Option<BlockId<Self::Block>>,
 	) -> Result<Storage, Error>;
+
+	/// Call `f` with each entry of the state at the given `block`, reading them one at a time.
+	/// If `block` is `None`, the best block will be used.
+	///
+	/// The entries of the default child tries are passed with the storage key of their trie, and
+	/// come right after the entry of the trie in the top trie.
+	fn for_each_state_entry(
+		&self,
+		block: Option<BlockId<Self::Block>>,
+		f: &mut dyn FnMut(Option<&[u8]>, &[u8], &[u8]),
+	) -> Result<(), Error>;
+
+	/// Export a light client sync checkpoint at the last finalized block, including the
+	/// auxiliary storage entries stored under `aux_keys`.
+	fn export_sync_state(
+		&self,
+		aux_keys: &[&[u8]],
+	) -> Result<sc_chain_spec::LightSyncState, Error>;
+
+	/// Returns the offchain storage of the node, if the backend supports it.
+	fn offchain_storage(&self) -> Option<Self::OffchainStorage>;
+
+	/// Returns a replay of the recorded consensus inputs against the blocks of the chain, as
+	/// defined with `ServiceBuilder::with_consensus_replay`, if any.
+	fn consensus_replay(&self) -> Option<Box<dyn ConsensusReplay<Self::Block>>>;
+
+	/// Re-execute the known blocks `from..=to` on top of their parent states, timing each one.
+	///
+	/// Nothing is written to the database. `on_block` is called with the result for each block.
+	fn benchmark_blocks(
+		&self,
+		from: NumberFor<Self::Block>,
+		to: NumberFor<Self::Block>,
+		on_block: &mut dyn FnMut(crate::chain_ops::BlockBenchmark) -> Result<(), Error>,
+	) -> Result<(), Error>;
+
+	/// Check the headers, bodies and justifications of all canonical blocks and the state trie
+	/// of the best block for missing or inconsistent entries.
+	///
+	/// With `repair`, the chain is reverted to the last consistent block. Finalized blocks are
+	/// never reverted.
+	fn check_database(&self, repair: bool) -> Result<crate::chain_ops::DatabaseCheck, Error>;
+
+	/// Re-execute the known block `block` on top of its parent state in two different ways, and
+	/// compare the storage changes they make.
+	///
+	/// Nothing is written to the database.
+	fn compare_block_execution(
+		&self,
+		block: BlockId<Self::Block>,
+		first: &crate::chain_ops::BlockExecution,
+		second: &crate::chain_ops::BlockExecution,
+	) -> Result<crate::chain_ops::BlockExecutionDiff, Error>;
+
+	/// Returns the best, finalized and genesis block of the chain.
+	fn chain_info(&self) -> sp_blockchain::Info<Self::Block>;
+
+	/// Returns the SCALE encoded runtime metadata at the given `block`. If `block` is `None`,
+	/// the genesis block will be used.
+	fn runtime_metadata(&self, block: Option<BlockId<Self::Block>>) -> Result<Vec<u8>, Error>;
 }
 
 impl<TBl, TRtApi, TBackend, TExec, TSc, TImpQu, TExPool, TRpc>
@@ -901,6 +1155,16 @@ ServiceBuilder<
 		Ok(self)
 	}
 
+	/// Defines the rule choosing the best block among the blocks imported with
+	/// `ForkChoiceStrategy::LongestChain`, which otherwise is the longest chain rule.
+	pub fn with_fork_choice_rule(
+		self,
+		fork_choice_rule: Arc<dyn ForkChoiceRule<TBl>>,
+	) -> Result<Self, Error> {
+		self.client.set_fork_choice_rule(fork_choice_rule);
+		Ok(self)
+	}
+
 	/// Builds the service.
 	pub fn build(self) -> Result<Service<
 		TBl,
@@ -933,6 +1197,9 @@ ServiceBuilder<
 			rpc_extensions_builder,
 			remote_backend,
 			block_announce_validator_builder,
+			transaction_selector,
+			justification_check: _,
+			consensus_replay: _,
 		} = self;
 
 		sp_session::generate_initial_session_keys(
@@ -941,6 +1208,29 @@ ServiceBuilder<
 			config.dev_key_seed.clone().map(|s| vec![s]).unwrap_or_default(),
 		)?;
 
+		// An authority without session keys silently doesn't take part in consensus. The check
+		// is only advisory, so a runtime that can't tell the key types doesn't prevent startup.
+		let session_key_types = if config.role.is_authority() {
+			let best = BlockId::Hash(client.chain_info().best_hash);
+			sp_session::session_key_types(&*client, &best).unwrap_or_else(|e| {
+				warn!("Unable to check the session keys of the keystore: {:?}", e);
+				Vec::new()
+			})
+		} else {
+			Vec::new()
+		};
+		let missing = missing_session_keys(&keystore, &session_key_types);
+		if !missing.is_empty() {
+			warn!(
+				"⚠️  The node runs as an authority, but the keystore has no session keys of \
+				type {}. It will not take part in consensus until they are inserted.",
+				missing.iter()
+					.map(|key_type| String::from_utf8_lossy(&key_type.0).into_owned())
+					.collect::<Vec<_>>()
+					.join(", "),
+			);
+		}
+
 		// A side-channel for essential tasks to communicate shutdown.
 		let (essential_failed_tx, essential_failed_rx) = tracing_unbounded("mpsc_essential_tasks");
 
@@ -986,6 +1276,11 @@ ServiceBuilder<
 			Box::new(DefaultBlockAnnounceValidator::new(client.clone()))
 		};
 
+		let mut network_config = config.network.clone();
+		let (admin_rpc_protocol, admin_rpc_requests) =
+			crate::admin_rpc::protocol_config(&config.admin_rpc_peers);
+		network_config.request_response_protocols.push(admin_rpc_protocol);
+
 		let network_params = sc_network::config::Params {
 			role: config.role.clone(),
 			executor: {
@@ -994,7 +1289,7 @@ ServiceBuilder<
 					spawn_handle.spawn("libp2p-node", fut);
 				}))
 			},
-			network_config: config.network.clone(),
+			network_config,
 			chain: client.clone(),
 			finality_proof_provider,
 			finality_proof_request_builder,
@@ -1013,8 +1308,16 @@ ServiceBuilder<
 
 		let offchain_storage = backend.offchain_storage();
 		let offchain_workers = match (config.offchain_worker.clone(), offchain_storage.clone()) {
-			(OffchainWorkerConfig {enabled: true, .. }, Some(db)) => {
-				Some(Arc::new(sc_offchain::OffchainWorkers::new(client.clone(), db)))
+			(OffchainWorkerConfig {enabled: true, threads, timeout, .. }, Some(db)) => {
+				let mut offchain_workers = sc_offchain::OffchainWorkers::new(client.clone(), db)
+					.with_http_config(config.offchain_worker.http.clone());
+				if let Some(threads) = threads {
+					offchain_workers = offchain_workers.with_thread_pool_size(threads);
+				}
+				if let Some(timeout) = timeout {
+					offchain_workers = offchain_workers.with_timeout(timeout);
+				}
+				Some(Arc::new(offchain_workers))
 			},
 			(OffchainWorkerConfig {enabled: true, .. }, None) => {
 				warn!("Offchain workers disabled, due to lack of offchain storage support in backend.");
@@ -1025,49 +1328,76 @@ ServiceBuilder<
 
 		let spawn_handle = task_manager.spawn_handle();
 
+		if let Some(path) = &config.replay_log {
+			let recording = crate::replay_log::record(path, client.clone(), network.clone())
+				.map_err(|e| Error::Other(format!(
+					"Unable to create the replay log {}: {}", path.display(), e,
+				)))?;
+			spawn_handle.spawn_blocking("replay-log", recording);
+		}
+
 		{
 			// block notifications
 			let txpool = Arc::downgrade(&transaction_pool);
 			let offchain = offchain_workers.as_ref().map(Arc::downgrade);
 			let notifications_spawn_handle = task_manager.spawn_handle();
 			let network_state_info: Arc<dyn NetworkStateInfo + Send + Sync> = network.clone();
+			let sync_network = network.clone();
 			let is_validator = config.role.is_authority();
+			let offchain_config = config.offchain_worker.clone();
 
 			let (import_stream, finality_stream) = (
-				client.import_notification_stream().map(|n| ChainEvent::NewBlock {
+				client.import_notification_stream().map(|n| (ChainEvent::NewBlock {
 					id: BlockId::Hash(n.hash),
 					header: n.header,
 					retracted: n.retracted,
 					is_new_best: n.is_new_best,
-				}),
-				client.finality_notification_stream().map(|n| ChainEvent::Finalized {
+				}, None)),
+				client.finality_notification_stream().map(|n| (ChainEvent::Finalized {
 					hash: n.hash
-				})
+				}, Some(n.header)))
 			);
 			let events = futures::stream::select(import_stream, finality_stream)
-				.for_each(move |event| {
-					// offchain worker is only interested in block import events
-					if let ChainEvent::NewBlock { ref header, is_new_best, .. } = event {
-						let offchain = offchain.as_ref().and_then(|o| o.upgrade());
-						match offchain {
-							Some(offchain) if is_new_best => {
-								notifications_spawn_handle.spawn(
-									"offchain-on-block",
-									offchain.on_block_imported(
-										&header,
-										network_state_info.clone(),
-										is_validator,
-									),
-								);
-							},
-							Some(_) => log::debug!(
-									target: "sc_offchain",
-									"Skipping offchain workers for non-canon block: {:?}",
+				.for_each(move |(event, finalized_header)| {
+					let offchain_header = match (&event, offchain_config.trigger) {
+						(
+							ChainEvent::NewBlock { header, is_new_best, .. },
+							OffchainWorkerTrigger::BestBlock,
+						) => if *is_new_best {
+							Some(header)
+						} else {
+							log::debug!(
+								target: "sc_offchain",
+								"Skipping offchain workers for non-canon block: {:?}",
+								header,
+							);
+							None
+						},
+						(ChainEvent::Finalized { .. }, OffchainWorkerTrigger::FinalizedBlock) =>
+							finalized_header.as_ref(),
+						_ => None,
+					};
+
+					let offchain = offchain.as_ref().and_then(|o| o.upgrade());
+					if let (Some(offchain), Some(header)) = (offchain, offchain_header) {
+						let number = (*header.number()).saturated_into::<u64>();
+						if offchain_config.should_run(number, sync_network.is_major_syncing()) {
+							notifications_spawn_handle.spawn(
+								"offchain-on-block",
+								offchain.on_block_imported(
 									header,
+									network_state_info.clone(),
+									is_validator,
 								),
-							_ => {},
+							);
+						} else {
+							log::debug!(
+								target: "sc_offchain",
+								"Skipping offchain workers for block #{} due to the scheduling policy",
+								number,
+							);
 						}
-					};
+					}
 
 					let txpool = txpool.upgrade();
 					if let Some(txpool) = txpool.as_ref() {
@@ -1110,7 +1440,9 @@ ServiceBuilder<
 		}
 
 		// Prometheus metrics.
-		let mut metrics_service = if let Some(PrometheusConfig { port, registry }) = config.prometheus_config.clone() {
+		let mut metrics_service = if let Some(PrometheusConfig { port, registry, push_gateway }) =
+			config.prometheus_config.clone()
+		{
 			// Set static metrics.
 
 
@@ -1128,17 +1460,36 @@ ServiceBuilder<
 			)?;
 			spawn_handle.spawn(
 				"prometheus-endpoint",
-				prometheus_endpoint::init_prometheus(port, registry).map(drop)
+				prometheus_endpoint::init_prometheus(port, registry.clone()).map(drop)
 			);
 
+			if let Some(push) = push_gateway {
+				let instance = push.instance.unwrap_or_else(|| config.network.node_name.clone());
+				spawn_handle.spawn(
+					"prometheus-push",
+					prometheus_endpoint::push_metrics(
+						push.url,
+						push.job,
+						instance,
+						push.interval,
+						registry,
+					).map(|result| if let Err(e) = result {
+						error!("Stopped pushing metrics to Prometheus Pushgateway: {}", e);
+					}),
+				);
+			}
+
 			metrics
 		} else {
 			MetricsService::new()
 		};
 
 		// Periodically notify the telemetry.
+		let telemetry_fields = TelemetryFields::default();
+		let telemetry_fields_ = telemetry_fields.clone();
 		let transaction_pool_ = transaction_pool.clone();
 		let client_ = client.clone();
+		let keystore_ = keystore.clone();
 		let (state_tx, state_rx) = tracing_unbounded::<(NetworkStatus<_>, NetworkState)>("mpsc_netstat1");
 		network_status_sinks.lock().push(std::time::Duration::from_millis(5000), state_tx);
 		let tel_task = state_rx.for_each(move |(net_status, _)| {
@@ -1147,7 +1498,12 @@ ServiceBuilder<
 				&info,
 				&transaction_pool_.status(),
 				&net_status,
+				&telemetry_fields_,
 			);
+			if !session_key_types.is_empty() {
+				let missing = missing_session_keys(&keystore_, &session_key_types);
+				metrics_service.set_missing_session_keys(missing.len() as u64);
+			}
 			ready(())
 		});
 
@@ -1172,8 +1528,51 @@ ServiceBuilder<
 			tel_task_2,
 		);
 
+		// Watch the free space on the volume of the database.
+		if let (Some(watchdog), Some(path)) =
+			(config.disk_space_watchdog.clone(), config.database.path())
+		{
+			let metrics = config.prometheus_config.as_ref()
+				.map(|config| crate::disk_space::Metrics::register(&config.registry))
+				.transpose()?;
+			spawn_handle.spawn(
+				"disk-space-watchdog",
+				crate::disk_space::watch(
+					watchdog,
+					path.to_path_buf(),
+					transaction_pool.clone(),
+					metrics,
+					essential_failed_tx.clone(),
+				),
+			);
+		}
+
+		// Adapt the limits of the transaction pool to the memory used.
+		if let Some(limits) = config.pool_memory_limits.clone() {
+			spawn_handle.spawn(
+				"pool-memory-watchdog",
+				crate::pool_memory::watch(limits, transaction_pool.clone()),
+			);
+		}
+
 		// RPC
 		let (system_rpc_tx, system_rpc_rx) = tracing_unbounded("mpsc_system_rpc");
+		let read_proof_metrics = config.prometheus_config.as_ref()
+			.map(|config| sc_rpc::state::ReadProofMetrics::register(&config.registry))
+			.transpose()?;
+		let head_policy = if config.rpc_finalized_only {
+			sc_rpc::HeadPolicy::Finalized
+		} else {
+			sc_rpc::HeadPolicy::Best
+		};
+		let replay_buffer = config.rpc_subscription_replay.map(|retention| {
+			let buffer = Arc::new(sc_rpc::chain::HeadsReplayBuffer::new(retention));
+			spawn_handle.spawn(
+				"rpc-heads-replay",
+				sc_rpc::chain::record_heads(buffer.clone(), &*client),
+			);
+			buffer
+		});
 		let gen_handler = |deny_unsafe: sc_rpc::DenyUnsafe| {
 			use sc_rpc::{chain, state, author, system, offchain};
 
@@ -1181,8 +1580,16 @@ ServiceBuilder<
 				chain_name: chain_spec.name().into(),
 				impl_name: config.impl_name.into(),
 				impl_version: config.impl_version.into(),
+				node_name: config.network.node_name.clone(),
 				properties: chain_spec.properties().clone(),
 				chain_type: chain_spec.chain_type().clone(),
+				state_pruning: match config.pruning {
+					PruningMode::ArchiveAll => system::StatePruning::Archive,
+					PruningMode::ArchiveCanonical => system::StatePruning::ArchiveCanonical,
+					PruningMode::Constrained(ref constraints) => system::StatePruning::Constrained {
+						window: constraints.max_blocks.unwrap_or(0),
+					},
+				},
 			};
 
 			let subscriptions = sc_rpc::Subscriptions::new(Arc::new(task_manager.spawn_handle()));
@@ -1194,20 +1601,36 @@ ServiceBuilder<
 					client.clone(),
 					subscriptions.clone(),
 					remote_backend.clone(),
-					on_demand.clone()
+					on_demand.clone(),
+					head_policy,
+					replay_buffer.clone(),
 				);
 				let (state, child_state) = sc_rpc::state::new_light(
 					client.clone(),
 					subscriptions.clone(),
 					remote_backend.clone(),
-					on_demand.clone()
+					on_demand.clone(),
+					head_policy,
+					deny_unsafe,
 				);
 				(chain, state, child_state)
 
 			} else {
 				// Full nodes
-				let chain = sc_rpc::chain::new_full(client.clone(), subscriptions.clone());
-				let (state, child_state) = sc_rpc::state::new_full(client.clone(), subscriptions.clone());
+				let chain = sc_rpc::chain::new_full(
+					client.clone(),
+					subscriptions.clone(),
+					head_policy,
+					replay_buffer.clone(),
+				);
+				let (state, child_state) = sc_rpc::state::new_full(
+					client.clone(),
+					subscriptions.clone(),
+					read_proof_metrics.clone(),
+					head_policy,
+					config.rpc_call_timeout,
+					deny_unsafe,
+				);
 				(chain, state, child_state)
 			};
 
@@ -1243,6 +1666,18 @@ ServiceBuilder<
 		// This is used internally, so don't restrict access to unsafe RPC
 		let rpc_handlers = gen_handler(sc_rpc::DenyUnsafe::No);
 
+		// Only the allowed methods are forwarded to these handlers.
+		if let Some(requests) = admin_rpc_requests {
+			spawn_handle.spawn(
+				"admin-rpc",
+				crate::admin_rpc::serve(
+					requests,
+					config.admin_rpc_peers.clone(),
+					gen_handler(sc_rpc::DenyUnsafe::No),
+				),
+			);
+		}
+
 		// The network worker is responsible for gathering all network messages and processing
 		// them. This is quite a heavy task, and at the time of the writing of this comment it
 		// frequently happens that this future takes several seconds or in some situations
@@ -1273,10 +1708,16 @@ ServiceBuilder<
 			let impl_name = config.impl_name.to_owned();
 			let version = version.clone();
 			let chain_name = config.chain_spec.name().to_owned();
+			let labels = TelemetryFields::default();
+			for (key, value) in &config.node_labels {
+				labels.set(key.clone(), value);
+			}
+			let security = config.security_summary();
 			let telemetry_connection_sinks_ = telemetry_connection_sinks.clone();
 			let telemetry = sc_telemetry::init_telemetry(sc_telemetry::TelemetryConfig {
 				endpoints,
 				wasm_external_transport: config.telemetry_external_transport.take(),
+				auth: config.telemetry_auth.clone(),
 			});
 			let startup_time = SystemTime::UNIX_EPOCH.elapsed()
 				.map(|dur| dur.as_millis())
@@ -1294,7 +1735,16 @@ ServiceBuilder<
 						"chain" => chain_name.clone(),
 						"authority" => is_authority,
 						"startup_time" => startup_time,
-						"network_id" => network_id.clone()
+						"network_id" => network_id.clone(),
+						"labels" => labels.clone()
+					);
+					telemetry!(SUBSTRATE_INFO; "system.security";
+						"role" => security.role.clone(),
+						"pruning" => security.pruning.clone(),
+						"rpc" => security.rpc.join(", "),
+						"unsafe_rpc_external" => security.unsafe_rpc_external,
+						"keystore" => security.keystore.clone(),
+						"telemetry" => security.telemetry.join(", ")
 					);
 
 					telemetry_connection_sinks_.lock().retain(|sink| {
@@ -1338,7 +1788,9 @@ ServiceBuilder<
 			_telemetry_on_connect_sinks: telemetry_connection_sinks.clone(),
 			keystore,
 			marker: PhantomData::<TBl>,
-			prometheus_registry: config.prometheus_config.map(|config| config.registry)
+			prometheus_registry: config.prometheus_config.map(|config| config.registry),
+			transaction_selector,
+			telemetry_fields,
 		})
 	}
 }
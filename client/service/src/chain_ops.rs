@@ -21,24 +21,38 @@
 use crate::error;
 use crate::builder::{ServiceBuilderCommand, ServiceBuilder};
 use crate::error::Error;
-use sc_chain_spec::ChainSpec;
+use sc_chain_spec::{ChainSpec, LightSyncState};
 use log::{warn, info};
 use futures::{future, prelude::*};
 use sp_runtime::traits::{
 	Block as BlockT, NumberFor, One, Zero, Header, SaturatedConversion, MaybeSerializeDeserialize,
+	HashFor, Hash as HashT,
 };
-use sp_runtime::generic::{BlockId, SignedBlock};
+use sp_runtime::generic::{BlockId, DigestItem, SignedBlock};
 use codec::{Decode, Encode, IoReader as CodecIoReader};
 use crate::client::{Client, LocalCallExecutor};
 use sp_consensus::{
-	BlockOrigin,
+	BlockOrigin, ConsensusReplay,
 	import_queue::{IncomingBlock, Link, BlockImportError, BlockImportResult, ImportQueue},
 };
-use sc_executor::{NativeExecutor, NativeExecutionDispatch};
-use sp_core::storage::{StorageKey, well_known_keys, ChildInfo, Storage, StorageChild, StorageMap};
-use sc_client_api::{StorageProvider, BlockBackend, UsageProvider};
+use sc_executor::{
+	CallInWasm, NativeExecutor, NativeExecutionDispatch, WasmExecutionMethod, WasmExecutor,
+	sp_wasm_interface::HostFunctions,
+};
+use sp_core::{
+	Bytes, hashing::twox_128, traits::MissingHostFunctions,
+	storage::{StorageKey, well_known_keys, ChildInfo, Storage, StorageChild, StorageMap},
+};
+use sc_client_api::{
+	StorageProvider, BlockBackend, UsageProvider, ProofProvider, CallExecutor, ExecutorProvider,
+};
+use sp_blockchain::HeaderBackend;
+use sp_state_machine::{
+	Backend as StateBackend, BasicExternalities, ExecutionStrategy, OverlayedChanges,
+};
+use serde::Serialize;
 
-use std::{io::{Read, Write, Seek}, pin::Pin, collections::HashMap};
+use std::{io::{Read, Write, Seek}, pin::Pin, collections::{BTreeMap, BTreeSet, HashMap}};
 use std::time::{Duration, Instant};
 use futures_timer::Delay;
 use std::task::Poll;
@@ -60,6 +74,111 @@ pub fn build_spec(spec: &dyn ChainSpec, raw: bool) -> error::Result<String> {
 	spec.as_json(raw).map_err(Into::into)
 }
 
+/// Build a raw chain spec json and check that it reproduces the genesis state of `spec`.
+///
+/// The genesis block hash only depends on the genesis state root, so matching roots guarantee
+/// that the raw spec yields the same genesis hash as the original one. The roots are computed
+/// with the hashing of the blocks of the chain.
+pub fn build_raw_spec_checked<B: BlockT>(spec: &dyn ChainSpec) -> error::Result<String> {
+	let expected_root = genesis_state_root::<B>(spec.build_storage()?);
+	let json = spec.as_json(true)?;
+	let raw_storage = sc_chain_spec::raw_genesis_storage_from_json(json.as_bytes())?;
+	let raw_root = genesis_state_root::<B>(raw_storage);
+
+	if expected_root != raw_root {
+		return Err(Error::Other(format!(
+			"Raw chain spec genesis state root {} doesn't match the original genesis state root {}",
+			raw_root,
+			expected_root,
+		)));
+	}
+
+	info!("Raw chain spec verified, genesis state root: {}", raw_root);
+	Ok(json)
+}
+
+/// Calculate the state root of the given genesis storage.
+fn genesis_state_root<B: BlockT>(storage: Storage) -> B::Hash {
+	let backend = sp_state_machine::InMemoryBackend::<HashFor<B>>::from(storage);
+	sp_state_machine::Backend::storage_root(&backend, std::iter::empty()).0
+}
+
+/// Build the genesis storage by calling into the genesis builder of the runtime wasm `code`.
+///
+/// The runtime has to implement the `GenesisBuilder` runtime api, which is passed `config`
+/// as-is. The runtime code itself is always part of the returned storage.
+pub fn build_genesis_storage_from_wasm(
+	code: &[u8],
+	config: &[u8],
+	wasm_method: WasmExecutionMethod,
+	heap_pages: Option<u64>,
+) -> error::Result<Storage> {
+	let executor = WasmExecutor::new(
+		wasm_method,
+		heap_pages,
+		sp_io::SubstrateHostFunctions::host_functions(),
+		1,
+	);
+	let mut ext = BasicExternalities::new(Storage {
+		top: std::iter::once((well_known_keys::CODE.to_vec(), code.to_vec())).collect(),
+		children_default: Default::default(),
+	});
+
+	let result = executor.call_in_wasm(
+		code,
+		None,
+		sp_genesis_builder::BUILD_GENESIS,
+		&config.encode(),
+		&mut ext,
+		MissingHostFunctions::Disallow,
+	).map_err(|e| Error::Other(format!("Error building genesis storage: {}", e)))?;
+
+	Result::<(), Vec<u8>>::decode(&mut &result[..])
+		.map_err(|e| Error::Other(format!("Invalid genesis builder result: {}", e)))?
+		.map_err(|e| Error::Other(
+			format!("Invalid genesis config: {}", String::from_utf8_lossy(&e))
+		))?;
+
+	Ok(ext.into_storages())
+}
+
+/// Execute the migrations of the runtime wasm `code` on `storage`.
+///
+/// The runtime code in `storage` is replaced by `code` first, and the migrations are executed
+/// through the `TryRuntime` runtime api, which `code` has to implement. The resulting state is
+/// only compared to the original one.
+pub fn try_runtime_upgrade(
+	mut storage: Storage,
+	code: &[u8],
+	wasm_method: WasmExecutionMethod,
+	heap_pages: Option<u64>,
+) -> error::Result<RuntimeUpgradeDryRun> {
+	let executor = WasmExecutor::new(
+		wasm_method,
+		heap_pages,
+		sp_io::SubstrateHostFunctions::host_functions(),
+		1,
+	);
+	storage.top.insert(well_known_keys::CODE.to_vec(), code.to_vec());
+	let mut ext = BasicExternalities::new(storage.clone());
+
+	let result = executor.call_in_wasm(
+		code,
+		None,
+		sp_try_runtime::ON_RUNTIME_UPGRADE,
+		&[],
+		&mut ext,
+		MissingHostFunctions::Disallow,
+	).map_err(|e| Error::Other(format!("Error executing the runtime upgrade: {}", e)))?;
+	let weight = u64::decode(&mut &result[..])
+		.map_err(|e| Error::Other(format!("Invalid runtime upgrade weight: {}", e)))?;
+
+	Ok(RuntimeUpgradeDryRun {
+		weight,
+		changes: StorageDiff::new(&storage, &ext.into_storages()),
+	})
+}
+
 
 /// Helper enum that wraps either a binary decoder (from parity-scale-codec), or a JSON decoder (from serde_json).
 /// Implements the Iterator Trait, calling `next()` will decode the next SignedBlock and return it.
@@ -179,6 +298,41 @@ fn import_block_to_queue<TBl, TImpQu>(
 	]);
 }
 
+/// Checks that `block` is the child of `parent`, the number and hash of the block before it, and
+/// that its extrinsics root matches its body.
+fn check_block_integrity<B: BlockT>(
+	block: &B,
+	parent: (NumberFor<B>, B::Hash),
+) -> Result<(), String> {
+	let header = block.header();
+	let (parent_number, parent_hash) = parent;
+	if *header.number() != parent_number + One::one() || *header.parent_hash() != parent_hash {
+		return Err(format!(
+			"Block #{} ({}) with parent {} doesn't follow block #{} ({})",
+			header.number(),
+			header.hash(),
+			header.parent_hash(),
+			parent_number,
+			parent_hash,
+		));
+	}
+
+	let extrinsics_root = HashFor::<B>::ordered_trie_root(
+		block.extrinsics().iter().map(Encode::encode).collect(),
+	);
+	if extrinsics_root != *header.extrinsics_root() {
+		return Err(format!(
+			"Block #{} ({}) has extrinsics root {} but its body has root {}",
+			header.number(),
+			header.hash(),
+			header.extrinsics_root(),
+			extrinsics_root,
+		));
+	}
+
+	Ok(())
+}
+
 /// Returns true if we have imported every block we were supposed to import, else returns false.
 fn importing_is_done(
 	num_expected_blocks: Option<u64>,
@@ -299,10 +453,12 @@ impl<
 	TExecDisp: 'static + NativeExecutionDispatch,
 	TImpQu: 'static + ImportQueue<TBl>,
 	TRtApi: 'static + Send + Sync,
+	Backend: sc_client_api::backend::Backend<TBl>,
 	Self: Send + 'static,
 {
 	type Block = TBl;
 	type NativeDispatch = TExecDisp;
+	type OffchainStorage = Backend::OffchainStorage;
 
 	fn import_blocks(
 		mut self,
@@ -469,6 +625,63 @@ impl<
 		Box::pin(import)
 	}
 
+	fn validate_blocks(
+		&self,
+		input: impl Read + Seek + Send + 'static,
+		binary: bool,
+		check_justifications: bool,
+	) -> Result<u64, Error> {
+		let mut justification_check = match (check_justifications, &self.justification_check) {
+			(false, _) => None,
+			(true, Some(justification_check)) => Some(justification_check()),
+			(true, None) => return Err(Error::Other(
+				"The justifications of the chain can't be checked before importing the blocks".into(),
+			)),
+		};
+		let block_iter = BlockIter::<_, TBl>::new(input, binary).map_err(Error::Other)?;
+		let mut parent = None;
+		let mut validated = 0;
+
+		for signed_block in block_iter {
+			let SignedBlock { block, justification } = signed_block
+				.map_err(|e| format!("Error reading block #{}: {}", validated + 1, e))?;
+			let header = block.header();
+
+			let expected_parent = match parent {
+				Some(parent) => parent,
+				None if header.number().is_zero() => {
+					if self.client.hash(Zero::zero())? != Some(header.hash()) {
+						return Err(format!(
+							"The genesis block {} of the input isn't the one of the chain",
+							header.hash(),
+						).into());
+					}
+					parent = Some((*header.number(), header.hash()));
+					validated += 1;
+					continue;
+				},
+				None => match self.client.header(BlockId::Hash(*header.parent_hash()))? {
+					Some(parent_header) => (*parent_header.number(), parent_header.hash()),
+					None => return Err(format!(
+						"The parent {} of the first block #{} of the input isn't known",
+						header.parent_hash(),
+						header.number(),
+					).into()),
+				},
+			};
+
+			check_block_integrity(&block, expected_parent)?;
+			if let Some(justification_check) = &mut justification_check {
+				justification_check.check_block(header, justification.as_ref())?;
+			}
+			parent = Some((*header.number(), header.hash()));
+			validated += 1;
+		}
+
+		info!("✅ Validated {} blocks", validated);
+		Ok(validated)
+	}
+
 	fn export_blocks(
 		self,
 		mut output: impl Write + 'static,
@@ -611,4 +824,630 @@ impl<
 		let top = top_storage.into_iter().map(|(k, v)| (k.0, v.0)).collect();
 		Ok(Storage { top, children_default })
 	}
+
+	fn for_each_state_entry(
+		&self,
+		block: Option<BlockId<Self::Block>>,
+		f: &mut dyn FnMut(Option<&[u8]>, &[u8], &[u8]),
+	) -> Result<(), Error> {
+		let block = block.unwrap_or_else(
+			|| BlockId::Hash(self.client.usage_info().chain.best_hash)
+		);
+		let state = self.client.state_at(&block)?;
+		let state_error = |e| Error::Other(format!("Error reading the state: {}", e));
+
+		let mut key = Vec::new();
+		while let Some(next) = state.next_storage_key(&key).map_err(state_error)? {
+			key = next;
+			let value = match state.storage(&key).map_err(state_error)? {
+				Some(value) => value,
+				None => continue,
+			};
+			f(None, &key, &value);
+
+			if !key.starts_with(well_known_keys::DEFAULT_CHILD_STORAGE_KEY_PREFIX) {
+				continue;
+			}
+			let storage_key = &key[well_known_keys::DEFAULT_CHILD_STORAGE_KEY_PREFIX.len()..];
+			let child_info = ChildInfo::new_default(storage_key);
+			let mut child_key = Vec::new();
+			while let Some(next) = state.next_child_storage_key(&child_info, &child_key)
+				.map_err(state_error)?
+			{
+				child_key = next;
+				let value = state.child_storage(&child_info, &child_key).map_err(state_error)?;
+				if let Some(value) = value {
+					f(Some(storage_key), &child_key, &value);
+				}
+			}
+		}
+		Ok(())
+	}
+
+	fn export_sync_state(
+		&self,
+		aux_keys: &[&[u8]],
+	) -> Result<LightSyncState, Error> {
+		let finalized_hash = self.client.usage_info().chain.finalized_hash;
+		let finalized_header = self.client.header(&BlockId::Hash(finalized_hash))?
+			.ok_or_else(|| Error::Other(format!("Missing header of finalized block {}", finalized_hash)))?;
+
+		let mut consensus_data = Vec::new();
+		for key in aux_keys {
+			if let Some(value) = self.backend.get_aux(key)? {
+				consensus_data.push((Bytes(key.to_vec()), Bytes(value)));
+			}
+		}
+
+		Ok(LightSyncState {
+			finalized_block_header: Bytes(finalized_header.encode()),
+			consensus_data,
+		})
+	}
+
+	fn chain_info(&self) -> sp_blockchain::Info<TBl> {
+		self.client.chain_info()
+	}
+
+	fn runtime_metadata(&self, block: Option<BlockId<TBl>>) -> Result<Vec<u8>, Error> {
+		let block = block.unwrap_or_else(|| BlockId::Number(Zero::zero()));
+		let metadata = self.client.executor().call(
+			&block,
+			"Metadata_metadata",
+			&[],
+			ExecutionStrategy::NativeElseWasm,
+			None,
+		)?;
+
+		// The runtime returns the metadata as `OpaqueMetadata`, which is encoded as `Vec<u8>`.
+		Vec::<u8>::decode(&mut &metadata[..])
+			.map_err(|e| Error::Other(format!("Invalid runtime metadata: {}", e)))
+	}
+
+	fn offchain_storage(&self) -> Option<Self::OffchainStorage> {
+		self.backend.offchain_storage()
+	}
+
+	fn consensus_replay(&self) -> Option<Box<dyn ConsensusReplay<TBl>>> {
+		self.consensus_replay.as_ref().map(|consensus_replay| consensus_replay())
+	}
+
+	fn benchmark_blocks(
+		&self,
+		from: NumberFor<TBl>,
+		to: NumberFor<TBl>,
+		on_block: &mut dyn FnMut(BlockBenchmark) -> Result<(), Error>,
+	) -> Result<(), Error> {
+		// The genesis block has no parent state to execute on.
+		let mut number = from.max(One::one());
+		while number <= to {
+			let block = self.client.block(&BlockId::Number(number))?
+				.ok_or_else(|| Error::Other(format!("Missing block #{:?}", number)))?
+				.block;
+			let hash = block.header().hash();
+			let parent_hash = *block.header().parent_hash();
+			let parent = BlockId::Hash(parent_hash);
+
+			let num_extrinsics = block.extrinsics().len();
+			let encoded = unsealed_block(block).encode();
+
+			let started = Instant::now();
+			let (_, changes) = self.client.executor().call_with_changes(
+				&parent,
+				"Core_execute_block",
+				&encoded,
+				ExecutionStrategy::NativeElseWasm,
+				None,
+			)?;
+			let execution = started.elapsed();
+
+			let state = self.client.state_at(&parent)?;
+			let started = Instant::now();
+			changes.into_storage_changes::<_, HashFor<TBl>, NumberFor<TBl>>(
+				&state,
+				None,
+				parent_hash,
+				Default::default(),
+			).map_err(Error::Other)?;
+			let storage = started.elapsed();
+
+			let started = Instant::now();
+			let (_, proof) = self.client.execution_proof(&parent, "Core_execute_block", &encoded)?;
+			let proof_time = started.elapsed();
+
+			on_block(BlockBenchmark {
+				number: number.saturated_into(),
+				hash: format!("{:?}", hash),
+				extrinsics: num_extrinsics,
+				execution_micros: execution.as_micros() as u64,
+				storage_micros: storage.as_micros() as u64,
+				proof_micros: proof_time.as_micros() as u64,
+				proof_size: proof.iter_nodes().map(|node| node.len()).sum(),
+			})?;
+
+			number += One::one();
+		}
+
+		Ok(())
+	}
+
+	fn check_database(&self, repair: bool) -> Result<DatabaseCheck, Error> {
+		let info = self.client.chain_info();
+		let mut check = DatabaseCheck::default();
+		let mut parent_hash = None;
+		let mut number: NumberFor<TBl> = Zero::zero();
+
+		while number <= info.best_number {
+			let hash = match self.client.hash(number) {
+				Ok(Some(hash)) => Some(hash),
+				Ok(None) => {
+					check.issues.push(format!("#{}: missing canonical hash", number));
+					None
+				},
+				Err(e) => {
+					check.issues.push(format!("#{}: error reading canonical hash: {}", number, e));
+					None
+				},
+			};
+
+			if let Some(hash) = hash {
+				let id = BlockId::Hash(hash);
+				match self.client.header(&id) {
+					Ok(Some(header)) => {
+						if header.hash() != hash {
+							check.issues.push(format!("#{}: header hash mismatch", number));
+						}
+						if parent_hash.map_or(false, |parent| parent != *header.parent_hash()) {
+							check.issues.push(format!("#{}: parent hash mismatch", number));
+						}
+
+						match self.client.body(&id) {
+							Ok(Some(extrinsics)) => {
+								let root = HashFor::<TBl>::ordered_trie_root(
+									extrinsics.iter().map(Encode::encode).collect(),
+								);
+								if root != *header.extrinsics_root() {
+									check.issues.push(format!("#{}: extrinsics root mismatch", number));
+								}
+							},
+							Ok(None) => check.issues.push(format!("#{}: missing body", number)),
+							Err(e) => check.issues.push(format!("#{}: error reading body: {}", number, e)),
+						}
+					},
+					Ok(None) => check.issues.push(format!("#{}: missing header", number)),
+					Err(e) => check.issues.push(format!("#{}: error reading header: {}", number, e)),
+				}
+
+				if let Err(e) = self.client.justification(&id) {
+					check.issues.push(format!("#{}: error reading justification: {}", number, e));
+				}
+			}
+
+			if check.issues.is_empty() {
+				check.last_consistent = Some(number.saturated_into());
+			}
+			check.checked_blocks += 1;
+			parent_hash = hash;
+			number += One::one();
+		}
+
+		// A state trie with missing or corrupted nodes doesn't produce all of its entries, so
+		// the root of the entries that can be read doesn't match the root in the header.
+		let best = BlockId::Hash(info.best_hash);
+		let state_issue = match (self.client.header(&best), self.export_raw_state(Some(best))) {
+			(Ok(Some(header)), Ok(storage)) => {
+				let backend = sp_state_machine::InMemoryBackend::<HashFor<TBl>>::from(storage);
+				let root = sp_state_machine::Backend::storage_root(&backend, std::iter::empty()).0;
+				if root != *header.state_root() {
+					Some(format!("#{}: state root mismatch", info.best_number))
+				} else {
+					None
+				}
+			},
+			(_, Err(e)) => Some(format!("#{}: error reading state: {}", info.best_number, e)),
+			_ => None,
+		};
+		if let Some(issue) = state_issue {
+			check.issues.push(issue);
+			let best_number: u64 = info.best_number.saturated_into();
+			check.last_consistent = check.last_consistent.map(|n| n.min(best_number.saturating_sub(1)));
+		}
+
+		if repair && !check.issues.is_empty() {
+			let last_consistent = check.last_consistent.ok_or_else(||
+				Error::Other("Database is inconsistent at genesis and can't be repaired".into())
+			)?;
+			let blocks = info.best_number.saturated_into::<u64>() - last_consistent;
+			check.reverted = self.client.revert(blocks.saturated_into())?.saturated_into();
+			if check.reverted < blocks {
+				warn!(
+					"Only {} of {} blocks could be reverted, finalized blocks are never reverted.",
+					check.reverted,
+					blocks,
+				);
+			}
+		}
+
+		Ok(check)
+	}
+
+	fn compare_block_execution(
+		&self,
+		block: BlockId<TBl>,
+		first: &BlockExecution,
+		second: &BlockExecution,
+	) -> Result<BlockExecutionDiff, Error> {
+		compare_block_execution(&self.client, block, first, second)
+	}
+}
+
+/// Re-execute the known block `block` on top of its parent state in two different ways, and
+/// compare the storage changes they make.
+///
+/// Nothing is written to the database.
+pub fn compare_block_execution<TBl, TBackend, TExecDisp, TRtApi>(
+	client: &Client<TBackend, LocalCallExecutor<TBackend, NativeExecutor<TExecDisp>>, TBl, TRtApi>,
+	block: BlockId<TBl>,
+	first: &BlockExecution,
+	second: &BlockExecution,
+) -> Result<BlockExecutionDiff, Error> where
+	TBl: BlockT,
+	TBackend: sc_client_api::backend::Backend<TBl>,
+	TExecDisp: NativeExecutionDispatch + 'static,
+{
+	let block = client.block(&block)?
+		.ok_or_else(|| Error::Other(format!("Unknown block {:?}", block)))?
+		.block;
+	if block.header().number().is_zero() {
+		return Err("The genesis block has no parent state to execute on".into());
+	}
+	let number = *block.header().number();
+	let hash = block.header().hash();
+	let block = unsealed_block(block);
+
+	let (first_changes, first) = execute_block_with(client, &block, first);
+	let (second_changes, second) = execute_block_with(client, &block, second);
+
+	let events_key = [twox_128(b"System"), twox_128(b"Events")].concat();
+	let mut diff = BlockExecutionDiff {
+		number: number.saturated_into(),
+		hash: format!("{:?}", hash),
+		first,
+		second,
+		storage: Vec::new(),
+		events: None,
+	};
+	let keys = first_changes.keys().chain(second_changes.keys()).collect::<BTreeSet<_>>();
+	for key in keys {
+		let change = |changes: &BTreeMap<Vec<u8>, Option<Vec<u8>>>| match changes.get(key) {
+			None => StorageChange::Unchanged,
+			Some(None) => StorageChange::Deleted,
+			Some(Some(value)) => StorageChange::Set(value.clone().into()),
+		};
+		let (first, second) = (change(&first_changes), change(&second_changes));
+		if first != second {
+			let change_diff = StorageChangeDiff { key: key.clone().into(), first, second };
+			if *key == events_key {
+				diff.events = Some(change_diff);
+			} else {
+				diff.storage.push(change_diff);
+			}
+		}
+	}
+
+	Ok(diff)
+}
+
+/// Returns `block` without its consensus seal, which is added after execution and has to be
+/// removed to re-execute the block.
+fn unsealed_block<TBl: BlockT>(block: TBl) -> TBl {
+	let (mut header, extrinsics) = block.deconstruct();
+	if let Some(DigestItem::Seal(..)) = header.digest().logs().last() {
+		header.digest_mut().pop();
+	}
+	TBl::new(header, extrinsics)
+}
+
+/// Execute `block` on top of its parent state, extrinsic by extrinsic so that the execution
+/// doesn't stop at the check of the state root, and return the storage changes it made.
+fn execute_block_with<TBl, TBackend, TExecDisp, TRtApi>(
+	client: &Client<TBackend, LocalCallExecutor<TBackend, NativeExecutor<TExecDisp>>, TBl, TRtApi>,
+	block: &TBl,
+	execution: &BlockExecution,
+) -> (BTreeMap<Vec<u8>, Option<Vec<u8>>>, ExecutionOutcome) where
+	TBl: BlockT,
+	TBackend: sc_client_api::backend::Backend<TBl>,
+	TExecDisp: NativeExecutionDispatch + 'static,
+{
+	let (strategy, code) = match execution {
+		BlockExecution::Native => (ExecutionStrategy::NativeWhenPossible, None),
+		BlockExecution::Wasm => (ExecutionStrategy::AlwaysWasm, None),
+		BlockExecution::CustomWasm(code) => (ExecutionStrategy::AlwaysWasm, Some(&code[..])),
+	};
+	let parent_hash = *block.header().parent_hash();
+	let parent = BlockId::Hash(parent_hash);
+
+	let result = (|| {
+		let mut changes = OverlayedChanges::default();
+		let mut call = |method, data: &[u8]| client.executor().call_on_changes(
+			&parent,
+			method,
+			data,
+			strategy,
+			None,
+			code,
+			&mut changes,
+		);
+		call("Core_initialize_block", &block.header().encode())?;
+		for extrinsic in block.extrinsics() {
+			call("BlockBuilder_apply_extrinsic", &extrinsic.encode())?;
+		}
+		call("BlockBuilder_finalize_block", &[])?;
+
+		let state = client.state_at(&parent)?;
+		changes.into_storage_changes::<_, HashFor<TBl>, NumberFor<TBl>>(
+			&state,
+			None,
+			parent_hash,
+			Default::default(),
+		).map_err(Error::Other)
+	})();
+
+	match result {
+		Ok(storage_changes) => {
+			let mut changes = storage_changes.main_storage_changes
+				.into_iter()
+				.collect::<BTreeMap<_, _>>();
+			for (storage_key, child_changes) in storage_changes.child_storage_changes {
+				let prefix = ChildInfo::new_default(&storage_key)
+					.prefixed_storage_key()
+					.into_inner();
+				changes.extend(child_changes.into_iter()
+					.map(|(key, value)| ([&prefix[..], &key[..]].concat(), value)));
+			}
+			let outcome = ExecutionOutcome {
+				state_root: Some(format!("{:?}", storage_changes.transaction_storage_root)),
+				error: None,
+			};
+			(changes, outcome)
+		},
+		Err(e) => {
+			let outcome = ExecutionOutcome { state_root: None, error: Some(e.to_string()) };
+			(BTreeMap::new(), outcome)
+		},
+	}
+}
+
+/// Result of a database consistency check.
+#[derive(Debug, Clone, Default)]
+pub struct DatabaseCheck {
+	/// Number of blocks whose header, body and justification were checked.
+	pub checked_blocks: u64,
+	/// The last block up to which the database is consistent, `None` if it isn't even
+	/// consistent at genesis.
+	pub last_consistent: Option<u64>,
+	/// Missing or inconsistent entries.
+	pub issues: Vec<String>,
+	/// Number of blocks that were reverted to repair the database.
+	pub reverted: u64,
+}
+
+/// Result of the execution of the migrations of a runtime upgrade.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RuntimeUpgradeDryRun {
+	/// Weight consumed by the migrations.
+	pub weight: u64,
+	/// Changes made to the storage by the migrations.
+	pub changes: StorageDiff,
+}
+
+/// Differences between two states.
+///
+/// Entries of child tries are keyed by the prefixed storage key of their trie followed by their
+/// key in the trie.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StorageDiff {
+	/// Entries that are only in the new state, with their value.
+	pub added: Vec<(Bytes, Bytes)>,
+	/// Entries whose value changed, with their old and new value.
+	pub changed: Vec<(Bytes, Bytes, Bytes)>,
+	/// Entries that are only in the old state, with their value.
+	pub removed: Vec<(Bytes, Bytes)>,
+}
+
+impl StorageDiff {
+	/// Compute the differences between the `old` and the `new` state.
+	pub fn new(old: &Storage, new: &Storage) -> Self {
+		let old = flatten_storage(old);
+		let mut new = flatten_storage(new);
+		let mut diff = StorageDiff::default();
+
+		for (key, old_value) in old {
+			match new.remove(&key) {
+				Some(new_value) if new_value != old_value => diff.changed.push(
+					(key.into(), old_value.clone().into(), new_value.clone().into())
+				),
+				Some(_) => {},
+				None => diff.removed.push((key.into(), old_value.clone().into())),
+			}
+		}
+		diff.added = new.into_iter()
+			.map(|(key, value)| (key.into(), value.clone().into()))
+			.collect();
+
+		diff
+	}
+
+	/// Returns `true` if both states are the same.
+	pub fn is_empty(&self) -> bool {
+		self.added.is_empty() && self.changed.is_empty() && self.removed.is_empty()
+	}
+}
+
+/// All the entries of `storage`, including those of the child tries, ordered by key.
+fn flatten_storage(storage: &Storage) -> BTreeMap<Vec<u8>, &Vec<u8>> {
+	let mut entries = storage.top.iter()
+		.map(|(key, value)| (key.clone(), value))
+		.collect::<BTreeMap<_, _>>();
+	for child in storage.children_default.values() {
+		let prefix = child.child_info.prefixed_storage_key().into_inner();
+		entries.extend(child.data.iter().map(|(key, value)| ([&prefix[..], key].concat(), value)));
+	}
+
+	entries
+}
+
+/// How to execute a block in [`ServiceBuilderCommand::compare_block_execution`].
+#[derive(Debug, Clone)]
+pub enum BlockExecution {
+	/// With the native runtime if it can execute the block, with the on-chain wasm otherwise.
+	Native,
+	/// With the on-chain runtime wasm.
+	Wasm,
+	/// With the given runtime wasm instead of the on-chain one.
+	CustomWasm(Vec<u8>),
+}
+
+/// Outcome of one of the executions of [`ServiceBuilderCommand::compare_block_execution`].
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExecutionOutcome {
+	/// State root after the execution, `None` if it failed.
+	pub state_root: Option<String>,
+	/// Why the execution failed.
+	pub error: Option<String>,
+}
+
+/// Change of a storage entry made by the execution of a block.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum StorageChange {
+	/// The entry isn't modified.
+	Unchanged,
+	/// The entry is deleted.
+	Deleted,
+	/// The entry is set to the given value.
+	Set(Bytes),
+}
+
+/// A storage entry that two executions of a block change differently.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StorageChangeDiff {
+	/// Key of the entry, prefixed by the prefixed storage key of its child trie if any.
+	pub key: Bytes,
+	/// Change made by the first execution.
+	pub first: StorageChange,
+	/// Change made by the second execution.
+	pub second: StorageChange,
+}
+
+/// Comparison of two executions of the same block.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BlockExecutionDiff {
+	/// Number of the block.
+	pub number: u64,
+	/// Hash of the block.
+	pub hash: String,
+	/// Outcome of the first execution.
+	pub first: ExecutionOutcome,
+	/// Outcome of the second execution.
+	pub second: ExecutionOutcome,
+	/// Storage entries changed differently, except for the events.
+	pub storage: Vec<StorageChangeDiff>,
+	/// The events deposited by the executions, if they differ.
+	///
+	/// They are read from the `System::Events` entry of FRAME runtimes, SCALE encoded.
+	pub events: Option<StorageChangeDiff>,
+}
+
+impl BlockExecutionDiff {
+	/// Returns `true` if both executions made the same changes.
+	pub fn is_empty(&self) -> bool {
+		self.first.state_root.is_some()
+			&& self.first.state_root == self.second.state_root
+			&& self.storage.is_empty()
+			&& self.events.is_none()
+	}
+}
+
+/// Timings of the re-execution of a single block.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BlockBenchmark {
+	/// Number of the block.
+	pub number: u64,
+	/// Hash of the block.
+	pub hash: String,
+	/// Number of extrinsics in the block.
+	pub extrinsics: usize,
+	/// Time taken to execute the block, including storage reads.
+	pub execution_micros: u64,
+	/// Time taken to compute the storage root and database changes of the block.
+	pub storage_micros: u64,
+	/// Time taken to execute the block again while recording a storage proof.
+	pub proof_micros: u64,
+	/// Size of the storage proof in bytes.
+	pub proof_size: usize,
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn storage_diff_reports_all_changes() {
+		let storage = |entries: &[(&[u8], &[u8])]| Storage {
+			top: entries.iter().map(|(key, value)| (key.to_vec(), value.to_vec())).collect(),
+			children_default: Default::default(),
+		};
+		let bytes = |value: &[u8]| Bytes(value.to_vec());
+
+		let old = storage(&[(b"changed", b"1"), (b"removed", b"2"), (b"same", b"3")]);
+		let new = storage(&[(b"added", b"4"), (b"changed", b"5"), (b"same", b"3")]);
+		let diff = StorageDiff::new(&old, &new);
+
+		assert_eq!(diff.added, vec![(bytes(b"added"), bytes(b"4"))]);
+		assert_eq!(diff.changed, vec![(bytes(b"changed"), bytes(b"1"), bytes(b"5"))]);
+		assert_eq!(diff.removed, vec![(bytes(b"removed"), bytes(b"2"))]);
+		assert!(StorageDiff::new(&old, &old).is_empty());
+	}
+
+	#[test]
+	fn block_integrity_checks_parent_and_extrinsics_root() {
+		use substrate_test_runtime_client::{
+			AccountKeyring,
+			runtime::{Block, Header, Transfer},
+		};
+		use sp_runtime::traits::{Block as _, Header as _};
+
+		let extrinsics = vec![Transfer {
+			amount: 1,
+			nonce: 0,
+			from: AccountKeyring::Alice.into(),
+			to: AccountKeyring::Bob.into(),
+		}.into_signed_tx()];
+		let extrinsics_root = HashFor::<Block>::ordered_trie_root(
+			extrinsics.iter().map(Encode::encode).collect(),
+		);
+		let parent_hash = [1; 32].into();
+		let header = |extrinsics_root| Header::new(
+			5,
+			extrinsics_root,
+			Default::default(),
+			parent_hash,
+			Default::default(),
+		);
+
+		let block = Block::new(header(extrinsics_root), extrinsics.clone());
+		assert!(check_block_integrity(&block, (4, parent_hash)).is_ok());
+		assert!(check_block_integrity(&block, (4, [2; 32].into())).is_err());
+		assert!(check_block_integrity(&block, (3, parent_hash)).is_err());
+
+		let corrupted = Block::new(header(Default::default()), extrinsics);
+		assert!(check_block_integrity(&corrupted, (4, parent_hash)).is_err());
+	}
 }
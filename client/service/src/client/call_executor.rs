@@ -16,10 +16,15 @@
 // You should have received a copy of the GNU General Public License
 // along with this program. If not, see <https://www.gnu.org/licenses/>.
 
-use std::{sync::Arc, panic::UnwindSafe, result, cell::RefCell};
+use std::{sync::Arc, panic::UnwindSafe, result, cell::RefCell, collections::HashMap};
 use codec::{Encode, Decode};
+use parking_lot::Mutex;
 use sp_runtime::{
-	generic::BlockId, traits::{Block as BlockT, HashFor, NumberFor},
+	generic::BlockId,
+	traits::{
+		Block as BlockT, HashFor, Header as HeaderT, NumberFor, SaturatedConversion,
+		UniqueSaturatedInto,
+	},
 };
 use sp_state_machine::{
 	self, OverlayedChanges, Ext, ExecutionManager, StateMachine, ExecutionStrategy,
@@ -27,10 +32,16 @@ use sp_state_machine::{
 };
 use sc_executor::{RuntimeVersion, RuntimeInfo, NativeVersion};
 use sp_externalities::Extensions;
-use sp_core::{NativeOrEncoded, NeverNativeValue, traits::CodeExecutor, offchain::storage::OffchainOverlayedChanges};
+use sp_core::{
+	NativeOrEncoded, NeverNativeValue, offchain::storage::OffchainOverlayedChanges,
+	traits::{CodeExecutor, RuntimeCode, WrappedRuntimeCode},
+};
+use sp_blockchain::HeaderBackend;
 use sp_api::{ProofRecorder, InitializeBlock, StorageTransactionCache};
 use sc_client_api::{backend, call_executor::CallExecutor, CloneableSpawn};
+use futures::{executor, task::{FutureObj, Spawn, SpawnError}};
 use super::client::ClientConfig;
+use crate::config::SignatureVerification;
 
 /// Call executor that executes methods locally, querying all required
 /// data from local backend.
@@ -39,6 +50,57 @@ pub struct LocalCallExecutor<B, E> {
 	executor: E,
 	spawn_handle: Box<dyn CloneableSpawn>,
 	client_config: ClientConfig,
+	code_substitutes: Arc<Vec<CodeSubstitute>>,
+}
+
+/// Spawner running the tasks to completion on the calling thread.
+///
+/// Given to the runtime host when the signatures batched by the runtime must be verified
+/// sequentially.
+#[derive(Clone)]
+struct InlineSpawn;
+
+impl Spawn for InlineSpawn {
+	fn spawn_obj(&self, future: FutureObj<'static, ()>) -> Result<(), SpawnError> {
+		executor::block_on(future);
+		Ok(())
+	}
+}
+
+impl CloneableSpawn for InlineSpawn {
+	fn clone(&self) -> Box<dyn CloneableSpawn> {
+		Box::new(InlineSpawn)
+	}
+}
+
+/// Runtime code that is used instead of the on-chain code from a given block number on, as long
+/// as the on-chain code is the one of the ancestor with that number.
+struct CodeSubstitute {
+	/// Number of the block the substitute is used from.
+	number: u64,
+	code: WrappedRuntimeCode<'static>,
+	hash: Vec<u8>,
+	/// Storage hash of the on-chain code replaced by the substitute, by encoded hash of the block
+	/// the substitute is used from, read from the state of that block.
+	replaced: Mutex<HashMap<Vec<u8>, Vec<u8>>>,
+}
+
+/// Returns the substitute to use at a block with the given `number` and on-chain code hash
+/// `code_hash`, among the `substitutes` sorted by descending block number.
+///
+/// The substitute of the highest ancestor at or below `number` whose on-chain code is still
+/// used is selected. Once the on-chain runtime is upgraded, its code is used again.
+/// `replaced_code_hash` returns the hash of the on-chain code at the ancestor a substitute is
+/// used from, `None` if it is unknown.
+fn select_code_substitute<'a>(
+	substitutes: &'a [CodeSubstitute],
+	number: u64,
+	code_hash: &[u8],
+	replaced_code_hash: impl Fn(&CodeSubstitute) -> Option<Vec<u8>>,
+) -> Option<&'a CodeSubstitute> {
+	substitutes.iter()
+		.filter(|substitute| substitute.number <= number)
+		.find(|substitute| replaced_code_hash(substitute).as_deref() == Some(code_hash))
 }
 
 impl<B, E> LocalCallExecutor<B, E> {
@@ -49,45 +111,191 @@ impl<B, E> LocalCallExecutor<B, E> {
 		spawn_handle: Box<dyn CloneableSpawn>,
 		client_config: ClientConfig,
 	) -> Self {
+		let code_substitutes = client_config.code_substitutes.iter()
+			.rev()
+			.map(|(number, code)| CodeSubstitute {
+				number: *number,
+				hash: sp_core::blake2_256(code).to_vec(),
+				code: WrappedRuntimeCode(code.clone().into()),
+				replaced: Mutex::new(HashMap::new()),
+			})
+			.collect();
+		let spawn_handle = match client_config.signature_verification {
+			SignatureVerification::Parallel => spawn_handle,
+			SignatureVerification::Sequential => Box::new(InlineSpawn),
+		};
+
 		LocalCallExecutor {
 			backend,
 			executor,
 			spawn_handle,
 			client_config,
+			code_substitutes: Arc::new(code_substitutes),
 		}
 	}
 }
 
-impl<B, E> Clone for LocalCallExecutor<B, E> where E: Clone {
-	fn clone(&self) -> Self {
-		LocalCallExecutor {
-			backend: self.backend.clone(),
-			executor: self.executor.clone(),
-			spawn_handle: self.spawn_handle.clone(),
-			client_config: self.client_config.clone(),
+impl<B, E> LocalCallExecutor<B, E> where E: CodeExecutor + RuntimeInfo + Clone + 'static {
+	/// Returns the runtime code to use for calls at the given block, whose state is `state`.
+	///
+	/// A code substitute registered for block number `N` replaces the on-chain code for calls at
+	/// the descendants of a block `A` with number `N`, and at `A` itself, as long as the on-chain
+	/// code is the one of `A`. The runtime isn't executed to compare the code.
+	fn runtime_code<'a, Block>(
+		&'a self,
+		id: &BlockId<Block>,
+		onchain_code: RuntimeCode<'a>,
+	) -> sp_blockchain::Result<RuntimeCode<'a>> where
+		B: backend::Backend<Block>,
+		Block: BlockT,
+	{
+		// The substitutes are sorted by descending block number.
+		let lowest = match self.code_substitutes.last() {
+			Some(lowest) => lowest.number,
+			None => return Ok(onchain_code),
+		};
+		let blockchain = self.backend.blockchain();
+		let number: u64 = blockchain.expect_block_number_from_id(id)?.unique_saturated_into();
+		if number < lowest {
+			return Ok(onchain_code)
+		}
+		let hash = blockchain.expect_block_hash_from_id(id)?;
+
+		let replaced_code_hash = |substitute: &CodeSubstitute| {
+			self.replaced_code_hash::<Block>(substitute, hash)
+		};
+		match select_code_substitute(
+			&self.code_substitutes,
+			number,
+			&onchain_code.hash,
+			replaced_code_hash,
+		) {
+			Some(substitute) => Ok(RuntimeCode {
+				code_fetcher: &substitute.code,
+				heap_pages: onchain_code.heap_pages,
+				hash: substitute.hash.clone(),
+			}),
+			None => Ok(onchain_code),
 		}
 	}
-}
 
-impl<B, E, Block> CallExecutor<Block> for LocalCallExecutor<B, E>
-where
-	B: backend::Backend<Block>,
-	E: CodeExecutor + RuntimeInfo + Clone + 'static,
-	Block: BlockT,
-{
-	type Error = E::Error;
+	/// Returns the hash of the on-chain code replaced by `substitute` at the block `hash`, which
+	/// is the hash of the on-chain code at the ancestor of `hash` the substitute is used from.
+	///
+	/// Returns `None` if the code can't be read, e.g. because the state of the ancestor has been
+	/// pruned before the substitute was first used.
+	fn replaced_code_hash<Block>(
+		&self,
+		substitute: &CodeSubstitute,
+		hash: Block::Hash,
+	) -> Option<Vec<u8>> where
+		B: backend::Backend<Block>,
+		Block: BlockT,
+	{
+		let ancestor = match self.ancestor_at::<Block>(hash, substitute.number.saturated_into()) {
+			Ok(ancestor) => ancestor?,
+			Err(e) => {
+				log::warn!("Can't find the ancestor of {} at #{}: {:?}", hash, substitute.number, e);
+				return None
+			},
+		};
 
-	type Backend = B;
+		let mut replaced = substitute.replaced.lock();
+		if let Some(code_hash) = replaced.get(ancestor.as_ref()) {
+			return Some(code_hash.clone())
+		}
 
-	fn call(
+		let code_hash = self.backend.state_at(BlockId::Hash(ancestor)).and_then(|state| {
+			Ok(sp_state_machine::backend::BackendRuntimeCode::new(&state).runtime_code()?.hash)
+		});
+		match code_hash {
+			Ok(code_hash) => {
+				replaced.insert(ancestor.as_ref().to_vec(), code_hash.clone());
+				Some(code_hash)
+			},
+			Err(e) => {
+				log::warn!(
+					"Code substitute of block #{} is ignored at {}, the code it replaces can't be \
+					read: {:?}",
+					substitute.number,
+					hash,
+					e,
+				);
+				None
+			},
+		}
+	}
+
+	/// Returns the hash of the ancestor of the block `hash` with the given `number`, or of the
+	/// block itself. The ancestors of the canonical blocks are looked up by number.
+	fn ancestor_at<Block>(
+		&self,
+		mut hash: Block::Hash,
+		number: NumberFor<Block>,
+	) -> sp_blockchain::Result<Option<Block::Hash>> where
+		B: backend::Backend<Block>,
+		Block: BlockT,
+	{
+		let blockchain = self.backend.blockchain();
+		loop {
+			let header = match blockchain.header(BlockId::Hash(hash))? {
+				Some(header) => header,
+				None => return Ok(None),
+			};
+			if *header.number() <= number {
+				return Ok(Some(hash).filter(|_| *header.number() == number))
+			}
+			if blockchain.hash(*header.number())? == Some(hash) {
+				return blockchain.hash(number)
+			}
+			hash = *header.parent_hash();
+		}
+	}
+
+	/// Execute a call on top of the state of the given block and return the result together
+	/// with the storage changes the call made. The changes are not applied.
+	pub(crate) fn call_with_changes<Block>(
 		&self,
 		id: &BlockId<Block>,
 		method: &str,
 		call_data: &[u8],
 		strategy: ExecutionStrategy,
 		extensions: Option<Extensions>,
-	) -> sp_blockchain::Result<Vec<u8>> {
+	) -> sp_blockchain::Result<(Vec<u8>, OverlayedChanges)> where
+		B: backend::Backend<Block>,
+		Block: BlockT,
+	{
 		let mut changes = OverlayedChanges::default();
+		let return_data = self.call_on_changes(
+			id,
+			method,
+			call_data,
+			strategy,
+			extensions,
+			None,
+			&mut changes,
+		)?;
+
+		Ok((return_data, changes))
+	}
+
+	/// Execute a call on top of the state of the given block and of `changes`, and add the
+	/// storage changes of the call to `changes` on success.
+	///
+	/// If `code` is given, it is executed instead of the runtime code of the state.
+	pub(crate) fn call_on_changes<Block>(
+		&self,
+		id: &BlockId<Block>,
+		method: &str,
+		call_data: &[u8],
+		strategy: ExecutionStrategy,
+		extensions: Option<Extensions>,
+		code: Option<&[u8]>,
+		changes: &mut OverlayedChanges,
+	) -> sp_blockchain::Result<Vec<u8>> where
+		B: backend::Backend<Block>,
+		Block: BlockT,
+	{
 		let mut offchain_changes = if self.client_config.offchain_indexing_api {
 			OffchainOverlayedChanges::enabled()
 		} else {
@@ -98,24 +306,69 @@ where
 		)?;
 		let state = self.backend.state_at(*id)?;
 		let state_runtime_code = sp_state_machine::backend::BackendRuntimeCode::new(&state);
+		let custom_code = code.map(|code| WrappedRuntimeCode(code.into()));
+		let runtime_code = match (&custom_code, code) {
+			(Some(code_fetcher), Some(code)) => RuntimeCode {
+				code_fetcher,
+				heap_pages: state_runtime_code.runtime_code()?.heap_pages,
+				hash: sp_core::blake2_256(code).to_vec(),
+			},
+			_ => self.runtime_code(id, state_runtime_code.runtime_code()?)?,
+		};
 		let return_data = StateMachine::new(
 			&state,
 			changes_trie,
-			&mut changes,
+			changes,
 			&mut offchain_changes,
 			&self.executor,
 			method,
 			call_data,
 			extensions.unwrap_or_default(),
-			&state_runtime_code.runtime_code()?,
+			&runtime_code,
 			self.spawn_handle.clone(),
 		).execute_using_consensus_failure_handler::<_, NeverNativeValue, fn() -> _>(
 			strategy.get_manager(),
 			None,
 		)?;
+		changes.commit_prospective();
 
 		Ok(return_data.into_encoded())
 	}
+}
+
+impl<B, E> Clone for LocalCallExecutor<B, E> where E: Clone {
+	fn clone(&self) -> Self {
+		LocalCallExecutor {
+			backend: self.backend.clone(),
+			executor: self.executor.clone(),
+			spawn_handle: self.spawn_handle.clone(),
+			client_config: self.client_config.clone(),
+			code_substitutes: self.code_substitutes.clone(),
+		}
+	}
+}
+
+impl<B, E, Block> CallExecutor<Block> for LocalCallExecutor<B, E>
+where
+	B: backend::Backend<Block>,
+	E: CodeExecutor + RuntimeInfo + Clone + 'static,
+	Block: BlockT,
+{
+	type Error = E::Error;
+
+	type Backend = B;
+
+	fn call(
+		&self,
+		id: &BlockId<Block>,
+		method: &str,
+		call_data: &[u8],
+		strategy: ExecutionStrategy,
+		extensions: Option<Extensions>,
+	) -> sp_blockchain::Result<Vec<u8>> {
+		self.call_with_changes(id, method, call_data, strategy, extensions)
+			.map(|(return_data, _)| return_data)
+	}
 
 	fn contextual_call<
 		'a,
@@ -170,7 +423,7 @@ where
 				let state_runtime_code = sp_state_machine::backend::BackendRuntimeCode::new(&trie_state);
 				// It is important to extract the runtime code here before we create the proof
 				// recorder.
-				let runtime_code = state_runtime_code.runtime_code()?;
+				let runtime_code = self.runtime_code(at, state_runtime_code.runtime_code()?)?;
 
 				let backend = sp_state_machine::ProvingBackend::new_with_recorder(
 					trie_state,
@@ -195,7 +448,7 @@ where
 			},
 			None => {
 				let state_runtime_code = sp_state_machine::backend::BackendRuntimeCode::new(&state);
-				let runtime_code = state_runtime_code.runtime_code()?;
+				let runtime_code = self.runtime_code(at, state_runtime_code.runtime_code()?)?;
 				let mut state_machine = StateMachine::new(
 					&state,
 					changes_trie_state,
@@ -231,7 +484,8 @@ where
 			None,
 		);
 		let state_runtime_code = sp_state_machine::backend::BackendRuntimeCode::new(&state);
-		self.executor.runtime_version(&mut ext, &state_runtime_code.runtime_code()?)
+		let runtime_code = self.runtime_code(id, state_runtime_code.runtime_code()?)?;
+		self.executor.runtime_version(&mut ext, &runtime_code)
 			.map_err(|e| sp_blockchain::Error::VersionInvalid(format!("{:?}", e)).into())
 	}
 
@@ -276,3 +530,67 @@ impl<B, E, Block> sp_version::GetRuntimeVersion<Block> for LocalCallExecutor<B,
 		CallExecutor::runtime_version(self, at).map_err(|e| format!("{:?}", e))
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn substitute(number: u64) -> CodeSubstitute {
+		CodeSubstitute {
+			number,
+			code: WrappedRuntimeCode(vec![number as u8].into()),
+			hash: vec![number as u8],
+			replaced: Mutex::new(HashMap::new()),
+		}
+	}
+
+	// the substitute of block `10 * n` replaces the on-chain code with hash `[n]`
+	fn replaced_code_hash(substitute: &CodeSubstitute) -> Option<Vec<u8>> {
+		Some(vec![substitute.number as u8 / 10])
+	}
+
+	#[test]
+	fn code_substitute_is_used_until_the_runtime_is_upgraded() {
+		let substitutes = vec![substitute(20), substitute(10)];
+		let selected = |number, code_hash: &[u8]| {
+			select_code_substitute(&substitutes, number, code_hash, replaced_code_hash)
+				.map(|substitute| substitute.hash.clone())
+		};
+
+		assert_eq!(selected(9, &[1]), None);
+		assert_eq!(selected(10, &[1]), Some(vec![10]));
+		assert_eq!(selected(15, &[1]), Some(vec![10]));
+		// the on-chain runtime has been upgraded
+		assert_eq!(selected(16, &[3]), None);
+
+		// the substitute isn't used if the code it replaces at the ancestor is unknown
+		assert!(select_code_substitute(&substitutes, 15, &[1], |_| None).is_none());
+	}
+
+	#[test]
+	fn latest_matching_code_substitute_is_used() {
+		let substitutes = vec![substitute(20), substitute(10)];
+		let selected = |number, code_hash: &[u8]| {
+			select_code_substitute(&substitutes, number, code_hash, replaced_code_hash)
+				.map(|substitute| substitute.hash.clone())
+		};
+
+		assert_eq!(selected(25, &[1]), Some(vec![10]));
+		assert_eq!(selected(25, &[2]), Some(vec![20]));
+		assert_eq!(selected(25, &[3]), None);
+	}
+
+	#[test]
+	fn inline_spawn_runs_the_tasks_on_the_calling_thread() {
+		let thread = std::thread::current().id();
+		let (sender, receiver) = std::sync::mpsc::channel();
+
+		let spawn = CloneableSpawn::clone(&InlineSpawn);
+		spawn.spawn_obj(FutureObj::new(Box::new(async move {
+			sender.send(std::thread::current().id()).unwrap();
+		}))).unwrap();
+
+		// the task is over once spawned
+		assert_eq!(receiver.try_recv(), Ok(thread));
+	}
+}
@@ -21,7 +21,7 @@
 use std::{
 	marker::PhantomData,
 	collections::{HashSet, BTreeMap, HashMap},
-	sync::Arc, panic::UnwindSafe, result,
+	sync::Arc, panic::UnwindSafe, result, time::{Duration, Instant},
 };
 use log::{info, trace, warn};
 use parking_lot::{Mutex, RwLock};
@@ -48,7 +48,7 @@ use sp_state_machine::{
 use sc_executor::RuntimeVersion;
 use sp_consensus::{
 	Error as ConsensusError, BlockStatus, BlockImportParams, BlockCheckParams,
-	ImportResult, BlockOrigin, ForkChoiceStrategy, RecordProof,
+	ImportResult, BlockOrigin, ForkChoiceStrategy, ForkChoiceRule, RecordProof,
 };
 use sp_blockchain::{
 	self as blockchain,
@@ -73,14 +73,18 @@ use sc_client_api::{
 	client::{
 		ImportNotifications, FinalityNotification, FinalityNotifications, BlockImportNotification,
 		ClientInfo, BlockchainEvents, BlockBackend, ProvideUncles, BadBlocks, ForkBlocks,
-		BlockOf,
+		BlockOf, ReorgNotification, ReorgNotifications, FinalityBatchNotification,
+		FinalityBatchNotifications, IndexedBlock, IndexerNotification, IndexerNotifications,
+		StatePinning,
 	},
 	execution_extensions::ExecutionExtensions,
 	notifications::{StorageNotifications, StorageEventStream},
+	notification_sinks::{
+		NotificationConfig, NotificationMetrics, NotificationSinks, SlowSubscriberPolicy,
+	},
 	KeyIterator, CallExecutor, ExecutorProvider, ProofProvider,
 	cht, UsageProvider
 };
-use sp_utils::mpsc::tracing_unbounded;
 use sp_blockchain::Error;
 use prometheus_endpoint::Registry;
 use super::{
@@ -88,7 +92,6 @@ use super::{
 	light::{call_executor::prove_execution, fetcher::ChangesProof},
 	block_rules::{BlockRules, LookupResult as BlockLookupResult},
 };
-use futures::channel::mpsc;
 use rand::Rng;
 
 #[cfg(feature="test-helpers")]
@@ -99,7 +102,21 @@ use {
 	super::call_executor::LocalCallExecutor,
 };
 
-type NotificationSinks<T> = Mutex<Vec<mpsc::UnboundedSender<T>>>;
+/// Maximum number of blocks whose state is pinned at the same time.
+const MAX_PINNED_STATES: usize = 128;
+
+/// Maximum time the state of a block is kept pinned, however many times it is pinned again.
+const MAX_PINNED_STATE_LIFETIME: Duration = Duration::from_secs(60 * 60);
+
+/// State of a block kept from pruning.
+struct PinnedState {
+	/// The state, which the backend doesn't prune while it is referenced.
+	_state: Box<dyn Send>,
+	/// When the block was first pinned.
+	pinned_at: Instant,
+	/// When the pin expires.
+	pinned_until: Instant,
+}
 
 /// Substrate Client
 pub struct Client<B, E, Block, RA> where Block: BlockT {
@@ -108,9 +125,17 @@ pub struct Client<B, E, Block, RA> where Block: BlockT {
 	storage_notifications: Mutex<StorageNotifications<Block>>,
 	import_notification_sinks: NotificationSinks<BlockImportNotification<Block>>,
 	finality_notification_sinks: NotificationSinks<FinalityNotification<Block>>,
+	reorg_notification_sinks: NotificationSinks<ReorgNotification<Block>>,
+	finality_batch_notification_sinks: NotificationSinks<FinalityBatchNotification<Block>>,
+	indexer_notification_sinks: NotificationSinks<IndexerNotification<Block>>,
+	// states kept from pruning while they are referenced, with the time until which they are kept
+	pinned_states: Mutex<HashMap<Block::Hash, PinnedState>>,
 	// holds the block hash currently being imported. TODO: replace this with block queue
 	importing_block: RwLock<Option<Block::Hash>>,
 	block_rules: BlockRules<Block>,
+	// rule choosing the best block among the blocks imported with the longest chain strategy,
+	// if it isn't the longest chain itself
+	fork_choice_rule: RwLock<Option<Arc<dyn ForkChoiceRule<Block>>>>,
 	execution_extensions: ExecutionExtensions<Block>,
 	config: ClientConfig,
 	_phantom: PhantomData<RA>,
@@ -180,6 +205,16 @@ pub struct ClientConfig {
 	pub offchain_worker_enabled: bool,
 	/// If true, allows access from the runtime to write into offchain worker db.
 	pub offchain_indexing_api: bool,
+	/// Runtime code to use instead of the on-chain code, keyed by the number of the block it is
+	/// used from.
+	pub code_substitutes: BTreeMap<u64, Vec<u8>>,
+	/// Capacity of the block import and finality notification streams of external subscribers,
+	/// and what to do for the ones that don't keep up.
+	pub notifications: NotificationConfig,
+	/// How the signatures batched by the runtime are verified.
+	pub signature_verification: crate::config::SignatureVerification,
+	/// Send the storage changes of the imported blocks to the indexers.
+	pub storage_diffs: bool,
 }
 
 /// Create a client with the explicitly provided backend.
@@ -206,6 +241,7 @@ pub fn new_with_backend<B, E, Block, S, RA>(
 		backend,
 		call_executor,
 		build_genesis_storage,
+		&genesis::DefaultGenesisBlockBuilder,
 		Default::default(),
 		Default::default(),
 		extensions,
@@ -240,15 +276,30 @@ impl<B, E, Block, RA> LockImportRun<Block, B> for Client<B, E, Block, RA>
 				op: self.backend.begin_operation()?,
 				notify_imported: None,
 				notify_finalized: Vec::new(),
+				index_imported: None,
+				index_finalized: Vec::new(),
 			};
 
 			let r = f(&mut op)?;
 
-			let ClientImportOperation { op, notify_imported, notify_finalized } = op;
+			let ClientImportOperation {
+				op,
+				notify_imported,
+				notify_finalized,
+				index_imported,
+				index_finalized,
+			} = op;
 			self.backend.commit_operation(op)?;
 
+			let indexer_notifications = self.indexer_notifications(
+				notify_imported.as_ref().or(index_imported.as_ref()),
+				&index_finalized,
+			)?;
 			self.notify_finalized(notify_finalized)?;
 			self.notify_imported(notify_imported)?;
+			for notification in indexer_notifications {
+				self.indexer_notification_sinks.notify(notification);
+			}
 
 			Ok(r)
 		};
@@ -286,6 +337,7 @@ impl<B, E, Block, RA> Client<B, E, Block, RA> where
 		backend: Arc<B>,
 		executor: E,
 		build_genesis_storage: &dyn BuildStorage,
+		genesis_block_builder: &dyn genesis::BuildGenesisBlock<Block>,
 		fork_blocks: ForkBlocks<Block>,
 		bad_blocks: BadBlocks<Block>,
 		execution_extensions: ExecutionExtensions<Block>,
@@ -297,7 +349,7 @@ impl<B, E, Block, RA> Client<B, E, Block, RA> where
 			let mut op = backend.begin_operation()?;
 			backend.begin_state_operation(&mut op, BlockId::Hash(Default::default()))?;
 			let state_root = op.reset_storage(genesis_storage)?;
-			let genesis_block = genesis::construct_genesis_block::<Block>(state_root.into());
+			let genesis_block = genesis_block_builder.build_genesis_block(state_root.into())?;
 			info!("🔨 Initializing Genesis block/state (state: {}, header-hash: {})",
 				genesis_block.header().state_root(),
 				genesis_block.header().hash()
@@ -311,14 +363,31 @@ impl<B, E, Block, RA> Client<B, E, Block, RA> where
 			backend.commit_operation(op)?;
 		}
 
+		let metrics = prometheus_registry.as_ref()
+			.and_then(|registry| NotificationMetrics::register(registry).ok());
+		let sinks = |name| NotificationSinks::new(name, config.notifications, metrics.clone());
+
 		Ok(Client {
 			backend,
 			executor,
 			storage_notifications: Mutex::new(StorageNotifications::new(prometheus_registry)),
-			import_notification_sinks: Default::default(),
-			finality_notification_sinks: Default::default(),
+			import_notification_sinks: sinks("mpsc_import_notification_stream"),
+			finality_notification_sinks: sinks("mpsc_finality_notification_stream"),
+			reorg_notification_sinks: sinks("mpsc_reorg_notification_stream"),
+			finality_batch_notification_sinks: sinks("mpsc_finality_batch_notification_stream"),
+			// indexers are disconnected rather than missing notifications.
+			indexer_notification_sinks: NotificationSinks::new(
+				"mpsc_indexer_notification_stream",
+				NotificationConfig {
+					slow_subscriber_policy: SlowSubscriberPolicy::Disconnect,
+					..config.notifications
+				},
+				metrics.clone(),
+			),
+			pinned_states: Default::default(),
 			importing_block: Default::default(),
 			block_rules: BlockRules::new(fork_blocks, bad_blocks),
+			fork_choice_rule: Default::default(),
 			execution_extensions,
 			config,
 			_phantom: Default::default(),
@@ -337,6 +406,24 @@ impl<B, E, Block, RA> Client<B, E, Block, RA> where
 		&self.finality_notification_sinks
 	}
 
+	/// returns a reference to the reorg notification sinks
+	/// useful for test environments.
+	pub fn reorg_notification_sinks(&self) -> &NotificationSinks<ReorgNotification<Block>> {
+		&self.reorg_notification_sinks
+	}
+
+	/// Set the rule deciding whether the blocks imported with `ForkChoiceStrategy::LongestChain`
+	/// become the best block, instead of the longest chain rule.
+	pub fn set_fork_choice_rule(&self, rule: Arc<dyn ForkChoiceRule<Block>>) {
+		*self.fork_choice_rule.write() = Some(rule);
+	}
+
+	/// Release the pinned states whose pin has expired.
+	fn unpin_expired_states(&self) {
+		let now = Instant::now();
+		self.pinned_states.lock().retain(|_, pinned| pinned.pinned_until > now);
+	}
+
 	/// Get a reference to the state at a given block.
 	pub fn state_at(&self, block: &BlockId<Block>) -> sp_blockchain::Result<B::State> {
 		self.backend.state_at(*block)
@@ -775,7 +862,15 @@ impl<B, E, Block, RA> Client<B, E, Block, RA> where
 		};
 
 		let is_new_best = finalized || match fork_choice {
-			ForkChoiceStrategy::LongestChain => import_headers.post().number() > &info.best_number,
+			ForkChoiceStrategy::LongestChain => match &*self.fork_choice_rule.read() {
+				Some(rule) => {
+					let best = self.backend.blockchain()
+						.expect_header(BlockId::Hash(info.best_hash))?;
+					rule.is_new_best(&best, import_headers.post())
+						.map_err(sp_blockchain::Error::Consensus)?
+				},
+				None => import_headers.post().number() > &info.best_number,
+			},
 			ForkChoiceStrategy::Custom(v) => v,
 		};
 
@@ -787,15 +882,20 @@ impl<B, E, Block, RA> Client<B, E, Block, RA> where
 			NewBlockState::Normal
 		};
 
-		let retracted = if is_new_best {
+		let (retracted, enacted) = if is_new_best {
 			let route_from_best = sp_blockchain::tree_route(
 				self.backend.blockchain(),
 				info.best_hash,
 				parent_hash,
 			)?;
-			route_from_best.retracted().iter().rev().map(|e| e.hash.clone()).collect()
+			let retracted = route_from_best.retracted().iter().rev().map(|e| e.hash.clone()).collect();
+			let enacted = route_from_best.enacted().iter()
+				.map(|e| e.hash.clone())
+				.chain(std::iter::once(hash))
+				.collect();
+			(retracted, enacted)
 		} else {
-			Vec::default()
+			(Vec::default(), Vec::default())
 		};
 
 		trace!(
@@ -815,19 +915,29 @@ impl<B, E, Block, RA> Client<B, E, Block, RA> where
 
 		operation.op.insert_aux(aux)?;
 
+		// the indexers are notified of every block, whatever its origin.
+		let has_indexers = self.has_indexers();
+		if finalized && has_indexers {
+			operation.index_finalized.push(hash);
+		}
+
+		let summary = ImportSummary {
+			hash,
+			origin,
+			header: import_headers.into_post(),
+			is_new_best,
+			storage_changes,
+			retracted,
+			enacted,
+		};
 		if make_notifications {
 			if finalized {
 				operation.notify_finalized.push(hash);
 			}
 
-			operation.notify_imported = Some(ImportSummary {
-				hash,
-				origin,
-				header: import_headers.into_post(),
-				is_new_best,
-				storage_changes,
-				retracted,
-			})
+			operation.notify_imported = Some(summary);
+		} else if has_indexers {
+			operation.index_imported = Some(summary);
 		}
 
 		Ok(ImportResult::imported(is_new_best))
@@ -950,6 +1060,10 @@ impl<B, E, Block, RA> Client<B, E, Block, RA> where
 		assert_eq!(enacted.last().map(|e| e.hash), Some(block));
 		operation.op.mark_finalized(BlockId::Hash(block), justification)?;
 
+		if self.has_indexers() {
+			operation.index_finalized.extend(enacted.iter().map(|e| e.hash));
+		}
+
 		if notify {
 			// sometimes when syncing, tons of blocks can be finalized at once.
 			// we'll send notifications spuriously in that case.
@@ -968,13 +1082,12 @@ impl<B, E, Block, RA> Client<B, E, Block, RA> where
 		&self,
 		notify_finalized: Vec<Block::Hash>,
 	) -> sp_blockchain::Result<()> {
-		let mut sinks = self.finality_notification_sinks.lock();
-
 		if notify_finalized.is_empty() {
 			// cleanup any closed finality notification sinks
 			// since we won't be running the loop below which
 			// would also remove any closed sinks.
-			sinks.retain(|sink| !sink.is_closed());
+			self.finality_notification_sinks.remove_closed();
+			self.finality_batch_notification_sinks.remove_closed();
 
 			return Ok(());
 		}
@@ -994,28 +1107,88 @@ impl<B, E, Block, RA> Client<B, E, Block, RA> where
 			);
 		}
 
-		for finalized_hash in notify_finalized {
-			let header = self.header(&BlockId::Hash(finalized_hash))?
+		let mut last_header = None;
+		for finalized_hash in &notify_finalized {
+			let header = self.header(&BlockId::Hash(*finalized_hash))?
 				.expect(
 					"Header already known to exist in DB because it is \
 					 indicated in the tree route; qed"
 				);
 
 			let notification = FinalityNotification {
+				header: header.clone(),
+				hash: *finalized_hash,
+			};
+
+			self.finality_notification_sinks.notify(notification);
+			last_header = Some(header);
+		}
+
+		if let Some(header) = last_header {
+			let notification = FinalityBatchNotification {
+				finalized: notify_finalized,
 				header,
-				hash: finalized_hash,
 			};
 
-			sinks.retain(|sink| sink.unbounded_send(notification.clone()).is_ok());
+			self.finality_batch_notification_sinks.notify(notification);
 		}
 
 		Ok(())
 	}
 
+	/// Whether any indexer is subscribed.
+	fn has_indexers(&self) -> bool {
+		self.indexer_notification_sinks.remove_closed();
+		self.indexer_notification_sinks.len() > 0
+	}
+
+	/// Returns the notifications of the indexers for a committed import operation: the imported
+	/// block first, and then the finalized blocks. Nothing is returned without any indexer.
+	fn indexer_notifications(
+		&self,
+		notify_imported: Option<&ImportSummary<Block>>,
+		notify_finalized: &[Block::Hash],
+	) -> sp_blockchain::Result<Vec<IndexerNotification<Block>>> {
+		if !self.has_indexers() {
+			return Ok(Vec::new());
+		}
+
+		let mut notifications = Vec::new();
+		if let Some(summary) = notify_imported {
+			notifications.push(IndexerNotification::Imported(Arc::new(IndexedBlock {
+				hash: summary.hash,
+				header: summary.header.clone(),
+				body: self.body(&BlockId::Hash(summary.hash))?,
+				origin: summary.origin,
+				is_new_best: summary.is_new_best,
+				storage_changes: if self.config.storage_diffs {
+					summary.storage_changes.clone()
+				} else {
+					None
+				},
+			})));
+		}
+		for hash in notify_finalized {
+			let header = self.header(&BlockId::Hash(*hash))?
+				.expect(
+					"Header already known to exist in DB because it is \
+					 indicated in the tree route; qed"
+				);
+			notifications.push(IndexerNotification::Finalized(FinalityNotification {
+				hash: *hash,
+				header,
+			}));
+		}
+
+		Ok(notifications)
+	}
+
 	fn notify_imported(
 		&self,
 		notify_import: Option<ImportSummary<Block>>,
 	) -> sp_blockchain::Result<()> {
+		self.unpin_expired_states();
+
 		let notify_import = match notify_import {
 			Some(notify_import) => notify_import,
 			None => {
@@ -1025,9 +1198,8 @@ impl<B, E, Block, RA> Client<B, E, Block, RA> where
 				// won't send any import notifications which could lead to a
 				// temporary leak of closed/discarded notification sinks (e.g.
 				// from consensus code).
-				self.import_notification_sinks
-					.lock()
-					.retain(|sink| !sink.is_closed());
+				self.import_notification_sinks.remove_closed();
+				self.reorg_notification_sinks.remove_closed();
 
 				return Ok(());
 			}
@@ -1043,6 +1215,18 @@ impl<B, E, Block, RA> Client<B, E, Block, RA> where
 				);
 		}
 
+		if notify_import.retracted.is_empty() {
+			self.reorg_notification_sinks.remove_closed();
+		} else {
+			let notification = ReorgNotification::<Block> {
+				new_best: notify_import.hash,
+				retracted: notify_import.retracted.clone(),
+				enacted: notify_import.enacted,
+			};
+
+			self.reorg_notification_sinks.notify(notification);
+		}
+
 		let notification = BlockImportNotification::<Block> {
 			hash: notify_import.hash,
 			origin: notify_import.origin,
@@ -1051,8 +1235,7 @@ impl<B, E, Block, RA> Client<B, E, Block, RA> where
 			retracted: notify_import.retracted,
 		};
 
-		self.import_notification_sinks.lock()
-			.retain(|sink| sink.unbounded_send(notification.clone()).is_ok());
+		self.import_notification_sinks.notify(notification);
 
 		Ok(())
 	}
@@ -1483,6 +1666,13 @@ impl<B, E, Block, RA> StorageProvider<Block, B> for Client<B, E, Block, RA> wher
 
 		Ok(result)
 	}
+
+	fn has_state_at(&self, id: &BlockId<Block>) -> sp_blockchain::Result<bool> {
+		Ok(match self.header(id)? {
+			Some(header) => self.backend.have_state_at(&header.hash(), *header.number()),
+			None => false,
+		})
+	}
 }
 
 impl<B, E, Block, RA> HeaderMetadata<Block> for Client<B, E, Block, RA> where
@@ -1856,15 +2046,39 @@ where
 {
 	/// Get block import event stream.
 	fn import_notification_stream(&self) -> ImportNotifications<Block> {
-		let (sink, stream) = tracing_unbounded("mpsc_import_notification_stream");
-		self.import_notification_sinks.lock().push(sink);
-		stream
+		self.import_notification_sinks.subscribe_unbounded()
 	}
 
 	fn finality_notification_stream(&self) -> FinalityNotifications<Block> {
-		let (sink, stream) = tracing_unbounded("mpsc_finality_notification_stream");
-		self.finality_notification_sinks.lock().push(sink);
-		stream
+		self.finality_notification_sinks.subscribe_unbounded()
+	}
+
+	fn external_import_notification_stream(&self) -> ImportNotifications<Block> {
+		self.import_notification_sinks.subscribe()
+	}
+
+	fn external_finality_notification_stream(&self) -> FinalityNotifications<Block> {
+		self.finality_notification_sinks.subscribe()
+	}
+
+	fn reorg_notification_stream(&self) -> ReorgNotifications<Block> {
+		self.reorg_notification_sinks.subscribe_unbounded()
+	}
+
+	fn finality_batch_notification_stream(&self) -> FinalityBatchNotifications<Block> {
+		self.finality_batch_notification_sinks.subscribe_unbounded()
+	}
+
+	fn indexer_notification_stream(&self) -> IndexerNotifications<Block> {
+		self.indexer_notification_sinks.subscribe()
+	}
+
+	fn unbounded_indexer_notification_stream(&self) -> IndexerNotifications<Block> {
+		self.indexer_notification_sinks.subscribe_unbounded()
+	}
+
+	fn storage_diffs_enabled(&self) -> bool {
+		self.config.storage_diffs
 	}
 
 	/// Get storage changes event stream.
@@ -1877,6 +2091,44 @@ where
 	}
 }
 
+impl<B, E, Block, RA> StatePinning<Block> for Client<B, E, Block, RA>
+	where
+		B: backend::Backend<Block> + 'static,
+		E: CallExecutor<Block>,
+		Block: BlockT,
+{
+	fn pin_state(&self, hash: Block::Hash, duration: Duration) -> sp_blockchain::Result<Duration> {
+		self.unpin_expired_states();
+
+		let now = Instant::now();
+		let mut pinned_states = self.pinned_states.lock();
+		if let Some(pinned) = pinned_states.get_mut(&hash) {
+			let max_pinned_until = pinned.pinned_at + MAX_PINNED_STATE_LIFETIME;
+			pinned.pinned_until = std::cmp::min(now + duration, max_pinned_until);
+			return Ok(pinned.pinned_until.saturating_duration_since(now));
+		}
+		if pinned_states.len() >= MAX_PINNED_STATES {
+			return Err(sp_blockchain::Error::Msg(
+				format!("Can't pin more than {} blocks", MAX_PINNED_STATES),
+			));
+		}
+
+		let duration = std::cmp::min(duration, MAX_PINNED_STATE_LIFETIME);
+		let state = self.state_at(&BlockId::Hash(hash))?;
+		trace!("Pinned the state of block {:?} for {:?}", hash, duration);
+		pinned_states.insert(hash, PinnedState {
+			_state: Box::new(state),
+			pinned_at: now,
+			pinned_until: now + duration,
+		});
+		Ok(duration)
+	}
+
+	fn unpin_state(&self, hash: &Block::Hash) -> bool {
+		self.pinned_states.lock().remove(hash).is_some()
+	}
+}
+
 impl<B, E, Block, RA> BlockBackend<Block> for Client<B, E, Block, RA>
 	where
 		B: backend::Backend<Block>,
@@ -1925,6 +2177,10 @@ impl<B, E, Block, RA> BlockBackend<Block> for Client<B, E, Block, RA>
 	fn justification(&self, id: &BlockId<Block>) -> sp_blockchain::Result<Option<Justification>> {
 		self.backend.blockchain().justification(*id)
 	}
+
+	fn leaves(&self) -> sp_blockchain::Result<Vec<Block::Hash>> {
+		self.backend.blockchain().leaves()
+	}
 }
 
 impl<B, E, Block, RA> backend::AuxStore for Client<B, E, Block, RA>
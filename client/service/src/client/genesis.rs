@@ -20,6 +20,36 @@
 
 use sp_runtime::traits::{Block as BlockT, Header as HeaderT, Hash as HashT, Zero};
 
+/// Builds the genesis block of a chain, given the root of its genesis state.
+///
+/// Chains deriving their genesis from an external snapshot can provide their own implementation
+/// to the service builder, e.g. to check the state root against a precomputed one or to add
+/// digest items to the genesis header. Every node of the chain must use the same builder, as it
+/// determines the genesis hash.
+pub trait BuildGenesisBlock<Block: BlockT> {
+	/// Build the genesis block, given the root of the genesis state.
+	fn build_genesis_block(&self, state_root: Block::Hash) -> sp_blockchain::Result<Block>;
+}
+
+/// Builds the genesis block with `construct_genesis_block`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DefaultGenesisBlockBuilder;
+
+impl<Block: BlockT> BuildGenesisBlock<Block> for DefaultGenesisBlockBuilder {
+	fn build_genesis_block(&self, state_root: Block::Hash) -> sp_blockchain::Result<Block> {
+		Ok(construct_genesis_block(state_root))
+	}
+}
+
+impl<Block, F> BuildGenesisBlock<Block> for F where
+	Block: BlockT,
+	F: Fn(Block::Hash) -> sp_blockchain::Result<Block>,
+{
+	fn build_genesis_block(&self, state_root: Block::Hash) -> sp_blockchain::Result<Block> {
+		(self)(state_root)
+	}
+}
+
 /// Create a genesis block, given the initial storage.
 pub fn construct_genesis_block<
 	Block: BlockT
@@ -34,6 +34,7 @@ use prometheus_endpoint::Registry;
 
 use super::call_executor::LocalCallExecutor;
 use super::client::{Client,ClientConfig};
+use super::genesis::BuildGenesisBlock;
 use sc_client_api::{
 	light::Storage as BlockchainStorage, CloneableSpawn,
 };
@@ -60,6 +61,7 @@ pub fn new_light_backend<B, S>(blockchain: Arc<Blockchain<S>>) -> Arc<Backend<S,
 pub fn new_light<B, S, RA, E>(
 	backend: Arc<Backend<S, HashFor<B>>>,
 	genesis_storage: &dyn BuildStorage,
+	genesis_block_builder: &dyn BuildGenesisBlock<B>,
 	code_executor: E,
 	spawn_handle: Box<dyn CloneableSpawn>,
 	prometheus_registry: Option<Registry>,
@@ -85,6 +87,7 @@ pub fn new_light<B, S, RA, E>(
 		backend,
 		executor,
 		genesis_storage,
+		genesis_block_builder,
 		Default::default(),
 		Default::default(),
 		Default::default(),
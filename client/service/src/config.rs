@@ -18,17 +18,25 @@
 
 //! Service configuration.
 
-pub use sc_client_db::{Database, PruningMode, DatabaseSettingsSrc as DatabaseConfig};
-pub use sc_network::Multiaddr;
+pub use sc_client_db::{
+	Database, PruningMode, DatabaseSettingsSrc as DatabaseConfig, ColdStorageSettings as ColdStorageConfig,
+	BlockCompression,
+};
+pub use sc_network::{Multiaddr, PeerId};
 pub use sc_network::config::{ExtTransport, MultiaddrWithPeerId, NetworkConfiguration, Role, NodeKeyConfig};
 pub use sc_executor::WasmExecutionMethod;
 use sc_client_api::execution_extensions::ExecutionStrategies;
+pub use sc_client_api::{NotificationConfig, SlowSubscriberPolicy};
 
-use std::{future::Future, path::{PathBuf, Path}, pin::Pin, net::SocketAddr, sync::Arc};
+use std::{
+	future::Future, path::{PathBuf, Path}, pin::Pin, net::SocketAddr, sync::Arc, time::Duration,
+	num::NonZeroU32,
+};
 pub use sc_transaction_pool::txpool::Options as TransactionPoolOptions;
+pub use sc_offchain::HttpConfig as OffchainHttpConfig;
 use sc_chain_spec::ChainSpec;
 use sp_core::crypto::Protected;
-pub use sc_telemetry::TelemetryEndpoints;
+pub use sc_telemetry::{TelemetryAuth, TelemetryEndpoints};
 use prometheus_endpoint::Registry;
 
 /// Service configuration.
@@ -49,9 +57,24 @@ pub struct Configuration {
 	pub keystore: KeystoreConfig,
 	/// Configuration for the database.
 	pub database: DatabaseConfig,
-	/// Size of internal state cache in Bytes
-	pub state_cache_size: usize,
-	/// Size in percent of cache size dedicated to child tries
+	/// Cold storage for the bodies of old finalized blocks. Ignored by light clients.
+	pub cold_storage: Option<ColdStorageConfig>,
+	/// Open the existing database without ever writing to it.
+	pub database_read_only: bool,
+	/// Compression of the block bodies and justifications written to the database.
+	pub block_compression: BlockCompression,
+	/// Labels of the node, attached to its metrics and sent to the telemetry.
+	pub node_labels: Vec<(String, String)>,
+	/// Free space limits of the volume of the database. `None` if the free space isn't watched.
+	pub disk_space_watchdog: Option<DiskSpaceWatchdogConfig>,
+	/// Memory limits of the process above which the transaction pool limits are reduced.
+	/// `None` if the pool limits are fixed.
+	pub pool_memory_limits: Option<PoolMemoryLimitsConfig>,
+	/// Size of the trie node cache in Bytes
+	pub trie_cache_size: usize,
+	/// Size of the storage value cache in Bytes
+	pub value_cache_size: usize,
+	/// Size in percent of value cache size dedicated to child tries
 	pub state_cache_child_ratio: Option<usize>,
 	/// Pruning settings.
 	pub pruning: PruningMode,
@@ -61,16 +84,44 @@ pub struct Configuration {
 	pub wasm_method: WasmExecutionMethod,
 	/// Execution strategies.
 	pub execution_strategies: ExecutionStrategies,
+	/// How the signatures batched by the runtime during block execution are verified.
+	pub signature_verification: SignatureVerification,
+	/// Capacity and slow subscriber policy of the notification streams of RPC subscriptions.
+	pub client_notifications: NotificationConfig,
+	/// Send the storage changes of the imported blocks to the indexers and the storage diff
+	/// subscribers.
+	pub storage_diffs: bool,
+	/// Record the consensus inputs of the node to this file, see
+	/// [`replay_log`](crate::replay_log). `None` if disabled.
+	pub replay_log: Option<PathBuf>,
 	/// RPC over HTTP binding address. `None` if disabled.
 	pub rpc_http: Option<SocketAddr>,
 	/// RPC over Websockets binding address. `None` if disabled.
 	pub rpc_ws: Option<SocketAddr>,
 	/// Maximum number of connections for WebSockets RPC server. `None` if default.
 	pub rpc_ws_max_connections: Option<usize>,
+	/// Time after which WebSockets RPC connections on which no request was received and no
+	/// subscription notification was sent are closed. `None` if they are never closed.
+	pub rpc_ws_idle_timeout: Option<Duration>,
 	/// CORS settings for HTTP & WS servers. `None` if all origins are allowed.
 	pub rpc_cors: Option<Vec<String>>,
+	/// Hosts allowed in the `Host` header of the requests to the HTTP & WS servers, in addition
+	/// to the listening address. `None` if all hosts are allowed.
+	pub rpc_allowed_hosts: Option<Vec<String>>,
 	/// RPC methods to expose (by default only a safe subset or all of them).
 	pub rpc_methods: RpcMethods,
+	/// Serve the latest finalized block rather than the best block to the RPC requests that don't
+	/// specify a block, and hide the blocks that aren't finalized yet unless requested by hash.
+	pub rpc_finalized_only: bool,
+	/// How long the heads announced to the RPC subscriptions are kept, to be replayed to the
+	/// clients resuming a subscription. `None` if disabled.
+	pub rpc_subscription_replay: Option<Duration>,
+	/// Time after which the runtime calls made on behalf of RPC requests are interrupted. `None`
+	/// if unlimited.
+	pub rpc_call_timeout: Option<Duration>,
+	/// Peers allowed to call the admin RPC methods over the network, see
+	/// [`admin_rpc`](crate::admin_rpc).
+	pub admin_rpc_peers: Vec<PeerId>,
 	/// Prometheus endpoint configuration. `None` if disabled.
 	pub prometheus_config: Option<PrometheusConfig>,
 	/// Telemetry service URL. `None` if disabled.
@@ -78,14 +129,33 @@ pub struct Configuration {
 	/// External WASM transport for the telemetry. If `Some`, when connection to a telemetry
 	/// endpoint, this transport will be tried in priority before all others.
 	pub telemetry_external_transport: Option<ExtTransport>,
+	/// Authentication towards private telemetry servers.
+	pub telemetry_auth: TelemetryAuth,
 	/// The default number of 64KB pages to allocate for Wasm execution
 	pub default_heap_pages: Option<u64>,
 	/// Should offchain workers be executed.
 	pub offchain_worker: OffchainWorkerConfig,
 	/// Enable authoring even when offline.
 	pub force_authoring: bool,
+	/// Clock of the slots and of the timestamp inherent, or `None` for the system clock.
+	///
+	/// Tests replace it to run nodes on a virtual clock, see `testing::DeterministicExecutor`.
+	pub clock: Option<Arc<dyn sp_timestamp::Clock>>,
 	/// Disable GRANDPA when running in validator mode
 	pub disable_grandpa: bool,
+	/// Period in blocks after which GRANDPA generates and stores a justification, even if the
+	/// authority set doesn't change. `None` if justifications are only generated when required,
+	/// e.g. by authority set changes.
+	pub grandpa_justification_period: Option<u32>,
+	/// Request from the peers the periodic GRANDPA justifications of the blocks imported without
+	/// one.
+	pub grandpa_request_justifications: bool,
+	/// Number of threads checking the signatures of the imported GRANDPA justifications. With 0,
+	/// they are checked by the thread importing the blocks.
+	pub grandpa_justification_workers: usize,
+	/// Duration of a GRANDPA gossip round step. Prevotes are cast after twice this duration
+	/// and precommits after four times this duration.
+	pub grandpa_gossip_duration: Duration,
 	/// Development key seed.
 	///
 	/// When running in development mode, the seed will be used to generate authority keys by the keystore.
@@ -142,7 +212,74 @@ pub struct OffchainWorkerConfig {
 	/// If this is allowed.
 	pub enabled: bool,
 	/// allow writes from the runtime to the offchain worker database.
+	///
+	/// Independent of `enabled`.
 	pub indexing_enabled: bool,
+	/// Configuration of the HTTP client available to offchain workers.
+	pub http: OffchainHttpConfig,
+	/// Number of threads offchain workers run on, or `None` for one per CPU.
+	pub threads: Option<usize>,
+	/// Time after which the pending HTTP requests of an offchain worker run are aborted.
+	pub timeout: Option<Duration>,
+	/// Blocks that trigger an offchain worker run.
+	pub trigger: OffchainWorkerTrigger,
+	/// Only run offchain workers on blocks whose number is a multiple of this.
+	pub block_interval: Option<NonZeroU32>,
+	/// Don't run offchain workers while the node is major syncing.
+	pub only_when_synced: bool,
+}
+
+impl OffchainWorkerConfig {
+	/// Returns whether offchain workers should run for the block with the given number.
+	///
+	/// Whether the block is of the kind selected by `trigger` is checked by the caller.
+	pub fn should_run(&self, number: u64, is_major_syncing: bool) -> bool {
+		if self.only_when_synced && is_major_syncing {
+			return false;
+		}
+
+		self.block_interval.map_or(true, |interval| number % interval.get() as u64 == 0)
+	}
+}
+
+/// Blocks that trigger an offchain worker run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OffchainWorkerTrigger {
+	/// Every block that is imported as the new best block.
+	BestBlock,
+	/// Every block that is finalized.
+	FinalizedBlock,
+}
+
+impl Default for OffchainWorkerTrigger {
+	fn default() -> Self {
+		OffchainWorkerTrigger::BestBlock
+	}
+}
+
+/// Free space limits of the volume of the database, checked periodically.
+#[derive(Debug, Clone)]
+pub struct DiskSpaceWatchdogConfig {
+	/// Free space in bytes below which a warning is logged.
+	pub soft_limit: u64,
+	/// Free space in bytes below which the transaction pool stops accepting transactions.
+	pub hard_limit: u64,
+	/// Stop the node when the free space is below `hard_limit`.
+	pub halt: bool,
+	/// Time between two checks.
+	pub interval: Duration,
+}
+
+/// Memory limits of the process above which the transaction pool enforces only part of its
+/// limits, checked periodically.
+#[derive(Debug, Clone)]
+pub struct PoolMemoryLimitsConfig {
+	/// Memory used in bytes above which half of the pool limits are enforced.
+	pub soft_limit: u64,
+	/// Memory used in bytes above which a tenth of the pool limits are enforced.
+	pub hard_limit: u64,
+	/// Time between two checks.
+	pub interval: Duration,
 }
 
 /// Configuration of the Prometheus endpoint.
@@ -152,6 +289,21 @@ pub struct PrometheusConfig {
 	pub port: SocketAddr,
 	/// A metrics registry to use. Useful for setting the metric prefix.
 	pub registry: Registry,
+	/// Push the metrics to a Pushgateway, for nodes that can't be scraped.
+	pub push_gateway: Option<PrometheusPushConfig>,
+}
+
+/// Configuration of pushing metrics to a Prometheus Pushgateway.
+#[derive(Clone, Debug)]
+pub struct PrometheusPushConfig {
+	/// URL of the Pushgateway.
+	pub url: String,
+	/// Value of the `job` label of the pushed metrics.
+	pub job: String,
+	/// Value of the `instance` label of the pushed metrics. Defaults to the node name.
+	pub instance: Option<String>,
+	/// Time between two pushes.
+	pub interval: Duration,
 }
 
 impl PrometheusConfig {
@@ -162,9 +314,32 @@ impl PrometheusConfig {
 		Self {
 			port,
 			registry: Registry::new_custom(Some("substrate".into()), None)
-				.expect("this can only fail if the prefix is empty")
+				.expect("this can only fail if the prefix is empty"),
+			push_gateway: None,
 		}
 	}
+
+	/// Replace the registry with a default one labelling all metrics with `labels`.
+	///
+	/// The label names must be valid Prometheus label names. The registry is kept if there is no
+	/// label.
+	pub fn with_labels(mut self, labels: impl IntoIterator<Item = (String, String)>) -> Self {
+		let labels: std::collections::HashMap<_, _> = labels.into_iter().collect();
+		if labels.is_empty() {
+			return self;
+		}
+		self.registry = Registry::new_custom(Some("substrate".into()), Some(labels))
+			.expect("the prefix is valid and the label names are checked by the caller; qed");
+		self
+	}
+
+	/// Replace the registry with a default one labelling all metrics with `labels` and `chain`,
+	/// which tells apart the metrics of the chains run by the same process.
+	pub fn with_chain_label(self, chain: &str, labels: &[(String, String)]) -> Self {
+		self.with_labels(
+			labels.iter().cloned().chain(std::iter::once(("chain".to_owned(), chain.to_owned())))
+		)
+	}
 }
 
 impl Configuration {
@@ -172,8 +347,148 @@ impl Configuration {
 	pub fn display_role(&self) -> String {
 		self.role.to_string()
 	}
+
+	/// Returns the chain spec extension field with the given name, decoded as `T`.
+	///
+	/// See [`sc_chain_spec::get_extension_field`] for details.
+	pub fn chain_spec_extension<T: serde::de::DeserializeOwned>(
+		&self,
+		name: &str,
+	) -> Result<Option<T>, String> {
+		sc_chain_spec::get_extension_field(&*self.chain_spec, name)
+	}
+
+	/// Returns warnings about combinations of settings that are valid on their own, but are
+	/// most likely a mistake.
+	pub fn sanity_check(&self) -> Vec<String> {
+		let mut warnings = Vec::new();
+		let is_public = |addr: &Option<SocketAddr>| addr.map_or(false, |addr| !addr.ip().is_loopback());
+		let rpc_is_public = is_public(&self.rpc_http) || is_public(&self.rpc_ws);
+
+		if self.role.is_authority() {
+			if let KeystoreConfig::InMemory = self.keystore {
+				warnings.push(
+					"Validator keys are kept in an in-memory keystore and are lost on restart".into(),
+				);
+			}
+			if rpc_is_public {
+				warnings.push("RPC of a validator is exposed on a public interface".into());
+			}
+		}
+
+		let cache_size = self.trie_cache_size + self.value_cache_size;
+		if self.pruning.is_archive() && cache_size < MIN_ARCHIVE_CACHE_SIZE {
+			warnings.push(format!(
+				"Archive node with a state cache of less than {} MiB will be slow to serve historic state",
+				MIN_ARCHIVE_CACHE_SIZE / 1024 / 1024,
+			));
+		}
+
+		if let RpcMethods::Unsafe = self.rpc_methods {
+			if rpc_is_public {
+				warnings.push("Unsafe RPC methods are exposed on a public interface".into());
+			}
+		}
+
+		if self.prometheus_config.as_ref().map_or(false, |config| config.port.ip().is_unspecified()) {
+			warnings.push("Prometheus metrics are exposed on all interfaces".into());
+		}
+
+		warnings
+	}
+
+	/// Returns a summary of the settings that matter for the security of the node, so that
+	/// misconfigurations are visible at a glance.
+	pub fn security_summary(&self) -> SecuritySummary {
+		let (rpc, unsafe_rpc_external) = rpc_summary(
+			&[("HTTP", self.rpc_http), ("WebSocket", self.rpc_ws)],
+			self.rpc_methods,
+		);
+
+		let keystore = match self.keystore {
+			KeystoreConfig::Path { password: Some(_), .. } => "on disk, password protected",
+			KeystoreConfig::Path { password: None, .. } => "on disk, unencrypted",
+			KeystoreConfig::InMemory => "in memory",
+		};
+
+		let telemetry = self.telemetry_endpoints.as_ref()
+			.map(|endpoints| endpoints.endpoints().iter()
+				.map(|(address, verbosity)| format!("{} (verbosity {})", address, verbosity))
+				.collect()
+			)
+			.unwrap_or_default();
+
+		SecuritySummary {
+			role: self.display_role(),
+			pruning: pruning_summary(&self.pruning),
+			rpc,
+			unsafe_rpc_external,
+			keystore: keystore.into(),
+			telemetry,
+		}
+	}
+}
+
+/// Describes which states are kept with `pruning`.
+fn pruning_summary(pruning: &PruningMode) -> String {
+	match pruning {
+		PruningMode::ArchiveAll => "archive".into(),
+		PruningMode::ArchiveCanonical => "archive of the canonical chain".into(),
+		PruningMode::Constrained(constraints) => match constraints.max_blocks {
+			Some(max_blocks) => format!("last {} finalized blocks", max_blocks),
+			None => "finalized blocks pruned without limit of blocks".into(),
+		},
+	}
 }
 
+/// Describes the RPC `servers`, by name and address, serving `rpc_methods`. Returns as well
+/// whether unsafe methods are served on an interface other than loopback.
+fn rpc_summary(
+	servers: &[(&str, Option<SocketAddr>)],
+	rpc_methods: RpcMethods,
+) -> (Vec<String>, bool) {
+	let mut rpc = Vec::new();
+	let mut unsafe_rpc_external = false;
+	for (name, address) in servers {
+		if let Some(address) = address {
+			let external = !address.ip().is_loopback();
+			let serves_unsafe = rpc_methods.serves_unsafe(address);
+			unsafe_rpc_external |= external && serves_unsafe;
+			rpc.push(format!(
+				"{} on {} ({}, {} methods)",
+				name,
+				address,
+				if external { "external" } else { "local" },
+				if serves_unsafe { "unsafe" } else { "safe" },
+			));
+		}
+	}
+	(rpc, unsafe_rpc_external)
+}
+
+/// Summary of the settings that matter for the security of a node.
+///
+/// See [`Configuration::security_summary`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct SecuritySummary {
+	/// Role of the node.
+	pub role: String,
+	/// Which states the node keeps.
+	pub pruning: String,
+	/// The RPC servers, their address and the methods they serve.
+	pub rpc: Vec<String>,
+	/// Whether unsafe RPC methods are served on an interface other than loopback.
+	pub unsafe_rpc_external: bool,
+	/// Where and how the keys are kept.
+	pub keystore: String,
+	/// The telemetry endpoints the node reports to.
+	pub telemetry: Vec<String>,
+}
+
+/// Combined size of the state caches below which `Configuration::sanity_check` warns about
+/// archive nodes.
+const MIN_ARCHIVE_CACHE_SIZE: usize = 64 * 1024 * 1024;
+
 /// Available RPC methods.
 #[derive(Debug, Copy, Clone)]
 pub enum RpcMethods {
@@ -186,8 +501,69 @@ pub enum RpcMethods {
 	Unsafe,
 }
 
+impl RpcMethods {
+	/// Returns whether unsafe RPC methods are served by an RPC server listening on `address`.
+	pub fn serves_unsafe(&self, address: &SocketAddr) -> bool {
+		match self {
+			RpcMethods::Unsafe => true,
+			RpcMethods::Auto => address.ip().is_loopback(),
+			RpcMethods::Safe => false,
+		}
+	}
+}
+
 impl Default for RpcMethods {
 	fn default() -> RpcMethods {
 		RpcMethods::Auto
 	}
 }
+
+/// How the signatures batched by the runtime, e.g. while executing the extrinsics of a block,
+/// are verified.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum SignatureVerification {
+	/// Verify the signatures on the threads of the task executor, in parallel with the execution.
+	Parallel,
+	/// Verify the signatures one after the other on the thread executing the runtime.
+	Sequential,
+}
+
+impl Default for SignatureVerification {
+	fn default() -> SignatureVerification {
+		SignatureVerification::Parallel
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn summarizes_pruning() {
+		assert_eq!(pruning_summary(&PruningMode::ArchiveAll), "archive");
+		assert_eq!(pruning_summary(&PruningMode::keep_blocks(256)), "last 256 finalized blocks");
+	}
+
+	#[test]
+	fn summarizes_rpc_servers() {
+		let local = "127.0.0.1:9933".parse().ok();
+		let external = "0.0.0.0:9944".parse().ok();
+
+		let (rpc, unsafe_rpc_external) = rpc_summary(
+			&[("HTTP", local), ("WebSocket", external)],
+			RpcMethods::Auto,
+		);
+		assert_eq!(rpc, vec![
+			"HTTP on 127.0.0.1:9933 (local, unsafe methods)".to_string(),
+			"WebSocket on 0.0.0.0:9944 (external, safe methods)".to_string(),
+		]);
+		assert!(!unsafe_rpc_external);
+
+		let (rpc, unsafe_rpc_external) = rpc_summary(
+			&[("HTTP", None), ("WebSocket", external)],
+			RpcMethods::Unsafe,
+		);
+		assert_eq!(rpc, vec!["WebSocket on 0.0.0.0:9944 (external, unsafe methods)".to_string()]);
+		assert!(unsafe_rpc_external);
+	}
+}
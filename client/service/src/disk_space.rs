@@ -0,0 +1,165 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2020 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Watchdog of the free space on the volume of the database.
+
+use std::{path::{Path, PathBuf}, sync::Arc};
+
+use futures::Future;
+use log::{debug, error, info, warn};
+use prometheus_endpoint::{register, Gauge, PrometheusError, Registry, U64};
+use sc_telemetry::{telemetry, SUBSTRATE_INFO};
+use sp_transaction_pool::TransactionPool;
+use sp_utils::mpsc::TracingUnboundedSender;
+use sysinfo::{DiskExt, SystemExt};
+
+use crate::config::DiskSpaceWatchdogConfig;
+
+/// How the free space compares to the limits of the watchdog.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Level {
+	/// Above the soft limit.
+	Sufficient,
+	/// Below the soft limit.
+	Low,
+	/// Below the hard limit.
+	Critical,
+}
+
+impl Level {
+	fn of(available: u64, config: &DiskSpaceWatchdogConfig) -> Self {
+		if available < config.hard_limit {
+			Level::Critical
+		} else if available < config.soft_limit {
+			Level::Low
+		} else {
+			Level::Sufficient
+		}
+	}
+}
+
+/// Prometheus metrics of the watchdog.
+pub(crate) struct Metrics {
+	available: Gauge<U64>,
+}
+
+impl Metrics {
+	pub(crate) fn register(registry: &Registry) -> Result<Self, PrometheusError> {
+		Ok(Metrics {
+			available: register(
+				Gauge::new(
+					"database_volume_available_bytes",
+					"Free space on the volume of the database",
+				)?,
+				registry,
+			)?,
+		})
+	}
+}
+
+/// Returns the space available on the volume containing `path`, if it is found.
+fn available_space(system: &mut sysinfo::System, path: &Path) -> Option<u64> {
+	let path = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+	system.refresh_disks_list();
+	system.get_disks().iter()
+		.filter(|disk| path.starts_with(disk.get_mount_point()))
+		.max_by_key(|disk| disk.get_mount_point().as_os_str().len())
+		.map(|disk| disk.get_available_space())
+}
+
+/// Periodically check the free space on the volume of the database at `path`.
+///
+/// Below the soft limit a warning is logged. Below the hard limit, `pool` stops accepting
+/// transactions until the space is freed, and the node is stopped through `halt` if configured.
+pub(crate) fn watch<P: TransactionPool>(
+	config: DiskSpaceWatchdogConfig,
+	path: PathBuf,
+	pool: Arc<P>,
+	metrics: Option<Metrics>,
+	halt: TracingUnboundedSender<()>,
+) -> impl Future<Output = ()> {
+	let mut system = sysinfo::System::new();
+	let mut level = Level::Sufficient;
+
+	async move {
+		loop {
+			let available = match available_space(&mut system, &path) {
+				Some(available) => available,
+				None => {
+					debug!("Unable to find the volume of the database at {}", path.display());
+					futures_timer::Delay::new(config.interval).await;
+					continue;
+				},
+			};
+			if let Some(metrics) = &metrics {
+				metrics.available.set(available);
+			}
+			telemetry!(SUBSTRATE_INFO; "system.disk_space"; "available" => available);
+
+			let new_level = Level::of(available, &config);
+			if new_level != level {
+				match new_level {
+					Level::Sufficient => info!(
+						"💽 Free space on the database volume is back to {} MiB",
+						available >> 20,
+					),
+					Level::Low => warn!(
+						"💽 Free space on the database volume is low: {} MiB left",
+						available >> 20,
+					),
+					Level::Critical => error!(
+						"💽 Free space on the database volume is critically low: {} MiB left, \
+						no new transactions are accepted",
+						available >> 20,
+					),
+				}
+				pool.set_paused(new_level == Level::Critical);
+				level = new_level;
+			}
+
+			if level == Level::Critical && config.halt {
+				error!("💽 Stopping the node before the database volume is full");
+				let _ = halt.unbounded_send(());
+				return;
+			}
+
+			futures_timer::Delay::new(config.interval).await;
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::time::Duration;
+
+	#[test]
+	fn levels_follow_the_limits() {
+		let config = DiskSpaceWatchdogConfig {
+			soft_limit: 100,
+			hard_limit: 10,
+			halt: false,
+			interval: Duration::from_secs(1),
+		};
+
+		assert_eq!(Level::of(1000, &config), Level::Sufficient);
+		assert_eq!(Level::of(100, &config), Level::Sufficient);
+		assert_eq!(Level::of(99, &config), Level::Low);
+		assert_eq!(Level::of(9, &config), Level::Critical);
+	}
+}
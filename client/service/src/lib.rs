@@ -22,6 +22,7 @@
 #![warn(missing_docs)]
 #![recursion_limit="128"]
 
+pub mod admin_rpc;
 pub mod config;
 #[macro_use]
 pub mod chain_ops;
@@ -29,12 +30,17 @@ pub mod error;
 
 mod metrics;
 mod builder;
+mod disk_space;
+mod pool_memory;
+pub mod replay_log;
+mod node_handle;
 #[cfg(feature = "test-helpers")]
 pub mod client;
 #[cfg(not(feature = "test-helpers"))]
 mod client;
 mod status_sinks;
 mod task_manager;
+pub mod testing;
 
 use std::{io, pin::Pin};
 use std::marker::PhantomData;
@@ -61,17 +67,23 @@ use parity_util_mem::MallocSizeOf;
 use sp_utils::mpsc::{tracing_unbounded, TracingUnboundedReceiver,  TracingUnboundedSender};
 
 pub use self::error::Error;
+pub use self::client::genesis::{BuildGenesisBlock, DefaultGenesisBlockBuilder};
 pub use self::builder::{
 	new_full_client, new_client,
 	ServiceBuilder, ServiceBuilderCommand, TFullClient, TLightClient, TFullBackend, TLightBackend,
 	TFullCallExecutor, TLightCallExecutor, RpcExtensionBuilder,
 };
-pub use config::{Configuration, DatabaseConfig, PruningMode, Role, RpcMethods, TaskType};
+pub use config::{
+	Configuration, DatabaseConfig, PruningMode, Role, RpcMethods, SecuritySummary, TaskType,
+	DiskSpaceWatchdogConfig, PoolMemoryLimitsConfig,
+};
 pub use sc_chain_spec::{
 	ChainSpec, GenericChainSpec, Properties, RuntimeGenesis, Extension as ChainSpecExtension,
-	NoExtension, ChainType,
+	NoExtension, ChainType, LightSyncState,
+};
+pub use sp_transaction_pool::{
+	TransactionPool, InPoolTransaction, TransactionSelector, error::IntoPoolError,
 };
-pub use sp_transaction_pool::{TransactionPool, InPoolTransaction, error::IntoPoolError};
 pub use sc_transaction_pool::txpool::Options as TransactionPoolOptions;
 pub use sc_rpc::Metadata as RpcMetadata;
 pub use sc_executor::NativeExecutionDispatch;
@@ -84,6 +96,7 @@ pub use sc_network::config::{
 };
 pub use sc_tracing::TracingReceiver;
 pub use task_manager::SpawnTaskHandle;
+pub use node_handle::NodeHandle;
 use task_manager::TaskManager;
 use sp_blockchain::{HeaderBackend, HeaderMetadata};
 use sp_api::{ApiExt, ConstructRuntimeApi, ApiErrorExt};
@@ -127,6 +140,8 @@ pub struct Service<TBl, TCl, TSc, TNetStatus, TNet, TTxPool, TOc> {
 	keystore: sc_keystore::KeyStorePtr,
 	marker: PhantomData<TBl>,
 	prometheus_registry: Option<prometheus_endpoint::Registry>,
+	transaction_selector: Option<Arc<dyn TransactionSelector<TTxPool>>>,
+	telemetry_fields: sc_telemetry::TelemetryFields,
 }
 
 impl<TBl, TCl, TSc, TNetStatus, TNet, TTxPool, TOc> Unpin for Service<TBl, TCl, TSc, TNetStatus, TNet, TTxPool, TOc> {}
@@ -210,6 +225,13 @@ pub trait AbstractService: Future<Output = Result<(), Error>> + Send + Unpin + S
 
 	/// Get the prometheus metrics registry, if available.
 	fn prometheus_registry(&self) -> Option<prometheus_endpoint::Registry>;
+
+	/// Get the selection of the ready transactions to include in the authored blocks, if it was
+	/// customized.
+	fn transaction_selector(&self) -> Option<Arc<dyn TransactionSelector<Self::TransactionPool>>>;
+
+	/// Get a handle to the custom fields sent with the periodic telemetry messages.
+	fn telemetry_fields(&self) -> sc_telemetry::TelemetryFields;
 }
 
 impl<TBl, TBackend, TExec, TRtApi, TSc, TExPool, TOc> AbstractService for
@@ -310,6 +332,14 @@ where
 	fn prometheus_registry(&self) -> Option<prometheus_endpoint::Registry> {
 		self.prometheus_registry.clone()
 	}
+
+	fn transaction_selector(&self) -> Option<Arc<dyn TransactionSelector<Self::TransactionPool>>> {
+		self.transaction_selector.clone()
+	}
+
+	fn telemetry_fields(&self) -> sc_telemetry::TelemetryFields {
+		self.telemetry_fields.clone()
+	}
 }
 
 impl<TBl, TCl, TSc, TNetStatus, TNet, TTxPool, TOc> Future for
@@ -392,6 +422,7 @@ fn build_network_future<
 						peers: network.peers_debug_info().len(),
 						is_syncing: network.service().is_major_syncing(),
 						should_have_peers,
+						is_in_maintenance: network.service().is_in_maintenance(),
 					});
 				},
 				sc_rpc::system::Request::LocalPeerId(sender) => {
@@ -416,6 +447,23 @@ fn build_network_future<
 						}
 					).collect());
 				}
+				sc_rpc::system::Request::PeerSyncDetails(sender) => {
+					let _ = sender.send(network.peers_sync_details().into_iter().map(|(peer_id, p)|
+						sc_rpc::system::PeerSyncDetails {
+							peer_id: peer_id.to_base58(),
+							best_hash: p.info.best_hash,
+							best_number: p.info.best_number,
+							common_number: p.common_number,
+							sync_state: p.sync_state,
+							requests_in_flight: p.requests_in_flight,
+							response_times_ms: p.response_times.iter()
+								.map(|time| time.as_millis() as u64)
+								.collect(),
+							timeouts: p.strikes.timeouts,
+							bad_responses: p.strikes.bad_responses,
+						}
+					).collect());
+				}
 				sc_rpc::system::Request::NetworkState(sender) => {
 					if let Some(network_state) = serde_json::to_value(&network.network_state()).ok() {
 						let _ = sender.send(network_state);
@@ -449,6 +497,10 @@ fn build_network_future<
 
 					let _ = sender.send(vec![node_role]);
 				}
+				sc_rpc::system::Request::SetMaintenance(enabled, sender) => {
+					network.service().set_maintenance(enabled);
+					let _ = sender.send(());
+				}
 			};
 		}
 
@@ -555,11 +607,10 @@ fn start_rpc_servers<H: FnMut(sc_rpc::DenyUnsafe) -> sc_rpc_server::RpcHandler<s
 	}
 
 	fn deny_unsafe(addr: &SocketAddr, methods: &RpcMethods) -> sc_rpc::DenyUnsafe {
-		let is_exposed_addr = !addr.ip().is_loopback();
-		match (is_exposed_addr, methods) {
-			| (_, RpcMethods::Unsafe)
-			| (false, RpcMethods::Auto) => sc_rpc::DenyUnsafe::No,
-			_ => sc_rpc::DenyUnsafe::Yes
+		if methods.serves_unsafe(addr) {
+			sc_rpc::DenyUnsafe::No
+		} else {
+			sc_rpc::DenyUnsafe::Yes
 		}
 	}
 
@@ -569,6 +620,7 @@ fn start_rpc_servers<H: FnMut(sc_rpc::DenyUnsafe) -> sc_rpc_server::RpcHandler<s
 			|address| sc_rpc_server::start_http(
 				address,
 				config.rpc_cors.as_ref(),
+				config.rpc_allowed_hosts.as_ref(),
 				gen_handler(deny_unsafe(&address, &config.rpc_methods)),
 			),
 		)?.map(|s| waiting::HttpServer(Some(s))),
@@ -578,6 +630,8 @@ fn start_rpc_servers<H: FnMut(sc_rpc::DenyUnsafe) -> sc_rpc_server::RpcHandler<s
 				address,
 				config.rpc_ws_max_connections,
 				config.rpc_cors.as_ref(),
+				config.rpc_allowed_hosts.as_ref(),
+				config.rpc_ws_idle_timeout,
 				gen_handler(deny_unsafe(&address, &config.rpc_methods)),
 			),
 		)?.map(|s| waiting::WsServer(Some(s))),
@@ -19,11 +19,12 @@
 use std::convert::TryFrom;
 
 use crate::NetworkStatus;
+use log::debug;
 use prometheus_endpoint::{register, Gauge, U64, F64, Registry, PrometheusError, Opts, GaugeVec};
-use sc_telemetry::{telemetry, SUBSTRATE_INFO};
+use sc_telemetry::{telemetry, TelemetryFields, SUBSTRATE_INFO};
 use sp_runtime::traits::{NumberFor, Block, SaturatedConversion, UniqueSaturatedInto};
 use sp_transaction_pool::PoolStatus;
-use sp_utils::metrics::register_globals;
+use sp_utils::{memory::{self, Subsystem}, metrics::register_globals};
 use sc_client_api::ClientInfo;
 
 use sysinfo::{self, ProcessExt, SystemExt};
@@ -57,7 +58,13 @@ struct PrometheusMetrics {
 	network_per_sec_bytes: GaugeVec<U64>,
 	database_cache: Gauge<U64>,
 	state_cache: Gauge<U64>,
+	trie_cache: Gauge<U64>,
+	state_cache_reads: GaugeVec<U64>,
 	state_db: GaugeVec<U64>,
+	subsystem_memory: GaugeVec<U64>,
+
+	// authoring
+	missing_session_keys: Gauge<U64>,
 }
 
 impl PrometheusMetrics {
@@ -138,10 +145,33 @@ impl PrometheusMetrics {
 			state_cache: register(Gauge::new(
 				"state_cache_bytes", "State cache size in bytes",
 			)?, registry)?,
+			trie_cache: register(Gauge::new(
+				"trie_cache_bytes", "Trie node cache size in bytes",
+			)?, registry)?,
+			state_cache_reads: register(GaugeVec::new(
+				Opts::new(
+					"state_cache_reads",
+					"State reads since the last measurement, by cache and cache hit or miss",
+				),
+				&["cache", "result"]
+			)?, registry)?,
 			state_db: register(GaugeVec::new(
 				Opts::new("state_db_cache_bytes", "State DB cache in bytes"),
 				&["subtype"]
 			)?, registry)?,
+			subsystem_memory: register(GaugeVec::new(
+				Opts::new(
+					"subsystem_memory_usage_bytes",
+					"Memory held by each subsystem, as accounted by the subsystem itself",
+				),
+				&["subsystem"]
+			)?, registry)?,
+
+			// authoring
+			missing_session_keys: register(Gauge::new(
+				"missing_session_keys",
+				"Number of session key types expected by the runtime without a key in the keystore",
+			)?, registry)?,
 		})
 	}
 }
@@ -265,6 +295,13 @@ impl MetricsService {
 		Self::inner_new(None)
 	}
 
+	/// Report the number of session key types the keystore of this authority has no key for.
+	pub fn set_missing_session_keys(&self, count: u64) {
+		if let Some(metrics) = self.metrics.as_ref() {
+			metrics.missing_session_keys.set(count);
+		}
+	}
+
 	#[cfg(all(any(unix, windows), not(target_os = "android")))]
 	fn process_info_for(&mut self, pid: &sysinfo::Pid) -> ProcessInfo {
 		let mut info = ProcessInfo::default();
@@ -315,6 +352,7 @@ impl MetricsService {
 		info: &ClientInfo<T>,
 		txpool_status: &PoolStatus,
 		net_status: &NetworkStatus<T>,
+		telemetry_fields: &TelemetryFields,
 	) {
 
 		let best_number = info.chain.best_number.saturated_into::<u64>();
@@ -327,6 +365,22 @@ impl MetricsService {
 			.map(|num: NumberFor<T>| num.unique_saturated_into() as u64);
 		let process_info = self.process_info();
 
+		memory::set_usage(
+			Subsystem::TransactionPool,
+			(txpool_status.ready_bytes + txpool_status.future_bytes) as u64,
+		);
+		if let Some(usage) = info.usage.as_ref() {
+			memory::set_usage(Subsystem::StateCache, usage.memory.state_cache.as_bytes() as u64);
+		}
+		debug!(
+			target: "memory",
+			"Memory usage by subsystem: {}",
+			Subsystem::ALL.iter()
+				.map(|subsystem| format!("{}={}", subsystem, memory::usage(*subsystem)))
+				.collect::<Vec<_>>()
+				.join(", "),
+		);
+
 		telemetry!(
 			SUBSTRATE_INFO;
 			"system.interval";
@@ -352,6 +406,7 @@ impl MetricsService {
 			"disk_write_per_sec" => info.usage.as_ref()
 				.map(|usage| usage.io.bytes_written)
 				.unwrap_or(0),
+			"custom" => telemetry_fields,
 		);
 
 		if let Some(metrics) = self.metrics.as_ref() {
@@ -389,6 +444,11 @@ impl MetricsService {
 
 			metrics.ready_transactions_number.set(txpool_status.ready as u64);
 
+			for subsystem in Subsystem::ALL.iter() {
+				metrics.subsystem_memory.with_label_values(&[subsystem.name()])
+					.set(memory::usage(*subsystem));
+			}
+
 			if let Some(best_seen_block) = best_seen_block {
 				metrics.block_height.with_label_values(&["sync_target"]).set(best_seen_block);
 			}
@@ -396,6 +456,17 @@ impl MetricsService {
 			if let Some(info) = info.usage.as_ref() {
 				metrics.database_cache.set(info.memory.database_cache.as_bytes() as u64);
 				metrics.state_cache.set(info.memory.state_cache.as_bytes() as u64);
+				metrics.trie_cache.set(info.memory.trie_cache.as_bytes() as u64);
+
+				let reads = [
+					("value", info.io.state_reads_cache, info.io.state_reads),
+					("trie", info.io.trie_node_reads_cache, info.io.trie_node_reads),
+				];
+				for (cache, hits, total) in reads.iter() {
+					metrics.state_cache_reads.with_label_values(&[cache, "hit"]).set(*hits);
+					metrics.state_cache_reads.with_label_values(&[cache, "miss"])
+						.set(total.saturating_sub(*hits));
+				}
 
 				metrics.state_db.with_label_values(&["non_canonical"]).set(
 					info.memory.state_db.non_canonical.as_bytes() as u64,
@@ -0,0 +1,220 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2020 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Handle of a node embedded in an application, without going through the CLI.
+
+use std::{pin::Pin, sync::Arc, task::Poll, thread};
+
+use futures::{
+	Future, FutureExt, SinkExt, Stream, StreamExt, channel::{mpsc, oneshot}, stream,
+	future::{self, poll_fn}, compat::{Compat, Future01CompatExt, Stream01CompatExt},
+};
+use jsonrpc_core_client::{RpcChannel, RpcError, transports::duplex};
+use log::debug;
+use sc_client_api::{BlockchainEvents, FinalityNotifications, ImportNotifications};
+use sc_network::NetworkService;
+use sp_runtime::traits::Block as BlockT;
+use sp_utils::mpsc::{tracing_unbounded, TracingUnboundedSender};
+
+use crate::{AbstractService, Configuration, Error, RpcSession, SpawnTaskHandle};
+
+/// Number of notifications of a subscription buffered before the node waits for them to be read.
+const SUBSCRIPTION_BUFFER: usize = 16;
+
+/// RPC request sent to the thread polling the service.
+struct RpcMessage {
+	request: String,
+	session: RpcSession,
+	send_back: oneshot::Sender<Pin<Box<dyn Future<Output = Option<String>> + Send>>>,
+}
+
+/// Handle of a full or light node running in-process.
+///
+/// The service is polled on a dedicated thread, while its tasks are spawned with the
+/// `task_executor` of its configuration. The node is stopped when the handle is dropped.
+pub struct NodeHandle<S: AbstractService> {
+	client: Arc<S::Client>,
+	network: Arc<NetworkService<S::Block, <S::Block as BlockT>::Hash>>,
+	transaction_pool: Arc<S::TransactionPool>,
+	rpc_tx: TracingUnboundedSender<RpcMessage>,
+	spawn_handle: SpawnTaskHandle,
+	stop_tx: Option<oneshot::Sender<()>>,
+	thread: Option<thread::JoinHandle<Result<(), Error>>>,
+}
+
+impl<S: AbstractService> NodeHandle<S> {
+	/// Build the service of `config` with `build`, e.g. the `new_full` or `new_light` function of
+	/// a node, and start it.
+	pub fn start(
+		config: Configuration,
+		build: impl FnOnce(Configuration) -> Result<S, Error>,
+	) -> Result<Self, Error> {
+		let mut service = build(config)?;
+		let client = service.client();
+		let network = service.network();
+		let transaction_pool = service.transaction_pool();
+		let spawn_handle = service.spawn_task_handle();
+
+		let (rpc_tx, mut rpc_rx) = tracing_unbounded::<RpcMessage>("mpsc_node_handle_rpc");
+		let (stop_tx, mut stop_rx) = oneshot::channel();
+		let thread = thread::Builder::new()
+			.name("node-service".into())
+			.spawn(move || futures::executor::block_on(poll_fn(move |cx| {
+				if let Poll::Ready(_) = stop_rx.poll_unpin(cx) {
+					debug!("Stopping the embedded node");
+					return Poll::Ready(Ok(()));
+				}
+
+				while let Poll::Ready(Some(message)) = Pin::new(&mut rpc_rx).poll_next(cx) {
+					let response = service.rpc_query(&message.session, &message.request);
+					let _ = message.send_back.send(response);
+				}
+
+				Pin::new(&mut service).poll(cx)
+			})))?;
+
+		Ok(NodeHandle {
+			client,
+			network,
+			transaction_pool,
+			rpc_tx,
+			spawn_handle,
+			stop_tx: Some(stop_tx),
+			thread: Some(thread),
+		})
+	}
+
+	/// Get the client of the node.
+	pub fn client(&self) -> Arc<S::Client> {
+		self.client.clone()
+	}
+
+	/// Get the network service of the node.
+	pub fn network(&self) -> Arc<NetworkService<S::Block, <S::Block as BlockT>::Hash>> {
+		self.network.clone()
+	}
+
+	/// Get the transaction pool of the node.
+	pub fn transaction_pool(&self) -> Arc<S::TransactionPool> {
+		self.transaction_pool.clone()
+	}
+
+	/// Get a stream of the blocks imported by the node.
+	pub fn import_notification_stream(&self) -> ImportNotifications<S::Block> {
+		self.client.import_notification_stream()
+	}
+
+	/// Get a stream of the blocks finalized by the node.
+	pub fn finality_notification_stream(&self) -> FinalityNotifications<S::Block> {
+		self.client.finality_notification_stream()
+	}
+
+	/// Send a JSON-RPC request to the node, without going through an RPC server.
+	///
+	/// Notifications of the subscriptions started by the request are sent through `session`.
+	/// Returns the response, or `None` for a notification or if the node has stopped.
+	pub fn rpc_query(
+		&self,
+		session: &RpcSession,
+		request: &str,
+	) -> impl Future<Output = Option<String>> + Send + 'static {
+		query(&self.rpc_tx, session, request)
+	}
+
+	/// Get a typed JSON-RPC client of the node, e.g. `sc_rpc_api::chain::ChainClient`.
+	///
+	/// The calls are handed to the RPC handlers of the node directly, without any RPC server. The
+	/// subscriptions made with the client are cancelled once it is dropped.
+	pub fn rpc_client<C: From<RpcChannel>>(&self) -> C {
+		let (requests_tx, requests_rx) = mpsc::unbounded::<String>();
+		let (responses_tx, responses_rx) = mpsc::unbounded::<String>();
+		let (notifications_tx, notifications_rx) =
+			futures01::sync::mpsc::channel(SUBSCRIPTION_BUFFER);
+		let session = RpcSession::new(notifications_tx);
+		let rpc_tx = self.rpc_tx.clone();
+
+		let dispatch = requests_rx.for_each_concurrent(None, move |request| {
+			let response = query(&rpc_tx, &session, &request);
+			let responses_tx = responses_tx.clone();
+			async move {
+				if let Some(response) = response.await {
+					let _ = responses_tx.unbounded_send(response);
+				}
+			}
+		});
+		// the notifications of the subscriptions are read along the responses.
+		let notifications = notifications_rx.compat().filter_map(|n| future::ready(n.ok()));
+		let (transport, channel) = duplex(
+			Compat::new(requests_tx.sink_map_err(|e| RpcError::Other(e.into()))),
+			Compat::new(stream::select(responses_rx, notifications).map(Ok::<_, RpcError>)),
+		);
+		self.spawn_handle.spawn(
+			"node-handle-rpc-client",
+			future::select(dispatch, transport.compat()).map(drop),
+		);
+
+		C::from(channel)
+	}
+
+	/// Stop the node and wait for its service to be dropped.
+	///
+	/// Returns the error the service failed with, if it stopped by itself.
+	pub fn stop(mut self) -> Result<(), Error> {
+		self.stop_and_join()
+	}
+
+	fn stop_and_join(&mut self) -> Result<(), Error> {
+		if let Some(stop_tx) = self.stop_tx.take() {
+			let _ = stop_tx.send(());
+		}
+		match self.thread.take().map(thread::JoinHandle::join) {
+			Some(Ok(result)) => result,
+			Some(Err(_)) => Err(Error::Other("The embedded node service panicked".into())),
+			None => Ok(()),
+		}
+	}
+}
+
+impl<S: AbstractService> Drop for NodeHandle<S> {
+	fn drop(&mut self) {
+		let _ = self.stop_and_join();
+	}
+}
+
+/// Send the JSON-RPC request `request` to the thread polling the service through `rpc_tx`.
+///
+/// Returns the response, or `None` for a notification or if the node has stopped.
+fn query(
+	rpc_tx: &TracingUnboundedSender<RpcMessage>,
+	session: &RpcSession,
+	request: &str,
+) -> impl Future<Output = Option<String>> + Send + 'static {
+	let (send_back, response) = oneshot::channel();
+	let _ = rpc_tx.unbounded_send(RpcMessage {
+		request: request.into(),
+		session: session.clone(),
+		send_back,
+	});
+
+	async move {
+		match response.await {
+			Ok(response) => response.await,
+			Err(_) => None,
+		}
+	}
+}
@@ -0,0 +1,151 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2020 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Adaptation of the limits of the transaction pool to the memory used by the process.
+
+use std::sync::Arc;
+
+use futures::Future;
+use log::{info, warn};
+use sp_transaction_pool::TransactionPool;
+use sysinfo::{ProcessExt, SystemExt};
+
+use crate::config::PoolMemoryLimitsConfig;
+
+/// Percentage of the pool limits enforced above the soft limit.
+const SOFT_LIMITS_PERCENT: usize = 50;
+/// Percentage of the pool limits enforced above the hard limit.
+const HARD_LIMITS_PERCENT: usize = 10;
+/// Percentage of a memory limit the process has to go below before the pool limits are raised,
+/// so that they don't flap while the memory used hovers around the limit.
+const RECOVERY_PERCENT: u64 = 90;
+
+/// How the memory used by the process compares to the limits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Level {
+	/// Below the soft limit.
+	Normal,
+	/// Above the soft limit.
+	High,
+	/// Above the hard limit.
+	Critical,
+}
+
+impl Level {
+	fn of(memory: u64, config: &PoolMemoryLimitsConfig, current: Level) -> Self {
+		let level = |memory| if memory >= config.hard_limit {
+			Level::Critical
+		} else if memory >= config.soft_limit {
+			Level::High
+		} else {
+			Level::Normal
+		};
+
+		let new = level(memory);
+		if new < current {
+			level(memory.saturating_mul(100) / RECOVERY_PERCENT).min(current)
+		} else {
+			new
+		}
+	}
+
+	fn limits_percent(self) -> usize {
+		match self {
+			Level::Normal => 100,
+			Level::High => SOFT_LIMITS_PERCENT,
+			Level::Critical => HARD_LIMITS_PERCENT,
+		}
+	}
+}
+
+/// Periodically check the memory used by the process, and reduce the limits of `pool` while it
+/// is above the limits of `config`.
+pub(crate) fn watch<P: TransactionPool>(
+	config: PoolMemoryLimitsConfig,
+	pool: Arc<P>,
+) -> impl Future<Output = ()> {
+	let mut system = sysinfo::System::new();
+	let pid = sysinfo::get_current_pid().ok();
+	let mut level = Level::Normal;
+
+	async move {
+		let pid = match pid {
+			Some(pid) => pid,
+			None => {
+				warn!("Unable to find the node process, the pool limits won't follow its memory");
+				return;
+			},
+		};
+
+		loop {
+			// the memory is reported in KiB.
+			let memory = if system.refresh_process(pid) {
+				system.get_process(pid).map(|process| process.memory() * 1024)
+			} else {
+				None
+			};
+
+			if let Some(memory) = memory {
+				let new_level = Level::of(memory, &config, level);
+				if new_level != level {
+					let percent = new_level.limits_percent();
+					match new_level {
+						Level::Normal => info!(
+							"🧠 Memory used is back to {} MiB, restoring the pool limits",
+							memory >> 20,
+						),
+						_ => warn!(
+							"🧠 Memory used is {} MiB, reducing the pool limits to {}%",
+							memory >> 20,
+							percent,
+						),
+					}
+					pool.set_limits_percent(percent);
+					level = new_level;
+				}
+			}
+
+			futures_timer::Delay::new(config.interval).await;
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::time::Duration;
+
+	#[test]
+	fn levels_follow_the_limits_with_hysteresis() {
+		let config = PoolMemoryLimitsConfig {
+			soft_limit: 1000,
+			hard_limit: 2000,
+			interval: Duration::from_secs(1),
+		};
+
+		assert_eq!(Level::of(999, &config, Level::Normal), Level::Normal);
+		assert_eq!(Level::of(1000, &config, Level::Normal), Level::High);
+		assert_eq!(Level::of(2500, &config, Level::Normal), Level::Critical);
+
+		// lowered only once clearly below the limit
+		assert_eq!(Level::of(1900, &config, Level::Critical), Level::Critical);
+		assert_eq!(Level::of(1700, &config, Level::Critical), Level::High);
+		assert_eq!(Level::of(950, &config, Level::High), Level::High);
+		assert_eq!(Level::of(500, &config, Level::Critical), Level::Normal);
+	}
+}
@@ -0,0 +1,197 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2020 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Log of the consensus inputs of a node, to replay them offline.
+//!
+//! The log records, in order, the blocks imported by the node, the blocks it finalized with
+//! their justifications, and the gossip messages it received, such as the GRANDPA votes. Each
+//! entry is SCALE-encoded as a length-prefixed byte vector and flushed right away, so that the
+//! log of a node that crashed is complete up to its last entry.
+//!
+//! A node restarted with the same log appends to it. The times of the entries restart from zero
+//! with each recording, which is how the recordings are told apart in the log.
+
+use std::{fs::OpenOptions, io::{self, BufWriter, Write}, path::Path, sync::Arc, time::Instant};
+
+use codec::{Decode, Encode};
+use futures::{Future, StreamExt, stream};
+use log::{error, warn};
+use sc_client_api::{BlockBackend, BlockchainEvents, IndexerNotification};
+use sc_network::{Event, ExHashT, NetworkService};
+use sp_runtime::{ConsensusEngineId, Justification, generic::{BlockId, SignedBlock}};
+use sp_runtime::traits::Block as BlockT;
+
+/// An entry of the replay log. `elapsed` is the number of milliseconds since the recording
+/// started.
+#[derive(Debug, Clone, PartialEq, Eq, Encode, Decode)]
+pub enum ReplayEntry<Block, Hash> {
+	/// A block has been imported.
+	Imported {
+		/// Time of the import.
+		elapsed: u64,
+		/// The block, with its justification if it was imported with one.
+		block: SignedBlock<Block>,
+	},
+	/// A block has been finalized.
+	Finalized {
+		/// Time of the finalization.
+		elapsed: u64,
+		/// Hash of the block.
+		hash: Hash,
+		/// Justification of the finalization, if one is stored.
+		justification: Option<Justification>,
+	},
+	/// A gossip message has been received.
+	Gossip {
+		/// Time of the reception.
+		elapsed: u64,
+		/// Encoded `PeerId` of the sender.
+		peer: Vec<u8>,
+		/// Engine the message is for.
+		engine_id: ConsensusEngineId,
+		/// The message.
+		message: Vec<u8>,
+	},
+}
+
+impl<Block, Hash> ReplayEntry<Block, Hash> {
+	/// Returns the number of milliseconds between the start of the recording and the entry.
+	pub fn elapsed(&self) -> u64 {
+		match self {
+			ReplayEntry::Imported { elapsed, .. }
+			| ReplayEntry::Finalized { elapsed, .. }
+			| ReplayEntry::Gossip { elapsed, .. } => *elapsed,
+		}
+	}
+}
+
+/// Decode the entries of a replay log.
+///
+/// Decoding stops at the first malformed entry, which is expected to be the last one of a log
+/// whose node stopped while writing it. The number of bytes left undecoded is returned with the
+/// entries.
+pub fn decode_entries<Block: Decode, Hash: Decode>(
+	mut log: &[u8],
+) -> (Vec<ReplayEntry<Block, Hash>>, usize) {
+	let mut entries = Vec::new();
+	while !log.is_empty() {
+		let mut remaining = log;
+		let entry = Vec::<u8>::decode(&mut remaining)
+			.and_then(|entry| ReplayEntry::decode(&mut &entry[..]));
+		match entry {
+			Ok(entry) => entries.push(entry),
+			Err(_) => break,
+		}
+		log = remaining;
+	}
+
+	(entries, log.len())
+}
+
+/// Record the consensus inputs of the node to the log at `path`, appending to it if it exists.
+///
+/// The blocks are taken from the notifications of the indexers, which are sent for every block
+/// imported or finalized, including during the initial sync. The recording stops when the client
+/// or the network shut down. The entries that can't be written are reported and skipped.
+pub(crate) fn record<B, C, H>(
+	path: &Path,
+	client: Arc<C>,
+	network: Arc<NetworkService<B, H>>,
+) -> io::Result<impl Future<Output = ()>> where
+	B: BlockT,
+	C: BlockchainEvents<B> + BlockBackend<B> + Send + Sync + 'static,
+	H: ExHashT,
+{
+	let mut log = BufWriter::new(OpenOptions::new().create(true).append(true).open(path)?);
+	let start = Instant::now();
+
+	enum Input<B: BlockT> {
+		Chain(IndexerNotification<B>),
+		Network(Event),
+	}
+
+	let inputs = stream::select(
+		client.unbounded_indexer_notification_stream().map(Input::<B>::Chain),
+		network.event_stream("replay-log").map(Input::Network),
+	);
+
+	Ok(inputs.for_each(move |input| {
+		let elapsed = start.elapsed().as_millis() as u64;
+		let entries = match input {
+			Input::Chain(IndexerNotification::Imported(block)) =>
+				match client.block(&BlockId::hash(block.hash)) {
+					Ok(Some(block)) => vec![ReplayEntry::Imported { elapsed, block }],
+					_ => {
+						warn!("Unable to record the import of {}: block not found", block.hash);
+						Vec::new()
+					},
+				},
+			Input::Chain(IndexerNotification::Finalized(notification)) => vec![ReplayEntry::Finalized {
+				elapsed,
+				hash: notification.hash,
+				justification: client.justification(&BlockId::hash(notification.hash)).ok().flatten(),
+			}],
+			Input::Network(Event::NotificationsReceived { remote, messages }) => messages
+				.into_iter()
+				.map(|(engine_id, message)| ReplayEntry::Gossip {
+					elapsed,
+					peer: remote.as_bytes().to_vec(),
+					engine_id,
+					message: message.to_vec(),
+				})
+				.collect(),
+			Input::Network(_) => Vec::new(),
+		};
+
+		for entry in entries {
+			let written = log.write_all(&entry.encode().encode()).and_then(|()| log.flush());
+			if let Err(e) = written {
+				error!("Unable to write to the replay log: {}", e);
+			}
+		}
+
+		futures::future::ready(())
+	}))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn decodes_entries_up_to_a_truncated_one() {
+		let entries: Vec<ReplayEntry<u32, u64>> = vec![
+			ReplayEntry::Imported {
+				elapsed: 1,
+				block: SignedBlock { block: 7, justification: None },
+			},
+			ReplayEntry::Finalized { elapsed: 2, hash: 7, justification: Some(vec![1, 2]) },
+			ReplayEntry::Gossip {
+				elapsed: 3,
+				peer: vec![4],
+				engine_id: *b"FRNK",
+				message: vec![5, 6],
+			},
+		];
+		let mut log = entries.iter().flat_map(|entry| entry.encode().encode()).collect::<Vec<_>>();
+		assert_eq!(decode_entries::<u32, u64>(&log), (entries.clone(), 0));
+
+		log.extend(&entries[0].encode().encode()[..3]);
+		assert_eq!(decode_entries::<u32, u64>(&log), (entries, 3));
+	}
+}
@@ -0,0 +1,359 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2020 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Utilities for running services reproducibly in tests.
+//!
+//! [`DeterministicExecutor`] runs every task of a service on the thread that drives it, in the
+//! order in which they are woken up, and provides a virtual clock that only moves when the test
+//! says so:
+//!
+//! ```
+//! # use sc_service::testing::DeterministicExecutor;
+//! # use std::time::Duration;
+//! let executor = DeterministicExecutor::new();
+//! // Pass `executor.task_executor()` as the task executor of the service's configuration.
+//!
+//! let clock = executor.clone();
+//! executor.block_on(async move {
+//! 	clock.sleep(Duration::from_secs(6)).await;
+//! });
+//! assert_eq!(executor.now(), Duration::from_secs(6));
+//! ```
+//!
+//! Only timers created through [`DeterministicExecutor::sleep`] follow the virtual clock, as do
+//! the slots and timestamps of the nodes whose `Configuration::clock` is the executor. Timers and
+//! I/O of other libraries still wake tasks up at non-deterministic moments.
+//!
+//! [`TestNetwork`] runs several nodes built from a chain spec on such an executor, connected to
+//! each other, and waits for them to produce and finalize blocks.
+
+mod network;
+
+pub use network::{TestNetwork, TestNode};
+
+use crate::config::TaskType;
+use futures::{future::BoxFuture, task::{waker, ArcWake}, Future, FutureExt};
+use parking_lot::{Condvar, Mutex};
+use std::{
+	collections::{BTreeMap, HashMap, VecDeque},
+	pin::Pin,
+	sync::{Arc, atomic::{AtomicBool, Ordering}},
+	task::{Context, Poll, Waker},
+	time::Duration,
+};
+
+/// Single-threaded executor with a virtual clock.
+///
+/// Clones share the same tasks and clock. Only one thread at a time should drive the executor.
+#[derive(Clone)]
+pub struct DeterministicExecutor {
+	shared: Arc<Shared>,
+}
+
+struct Shared {
+	tasks: Mutex<Tasks>,
+	/// Tasks that have been woken up, in order.
+	ready: Mutex<VecDeque<u64>>,
+	/// Notified whenever a task or the future passed to `block_on` is woken up.
+	wakeup: Condvar,
+	clock: Mutex<Clock>,
+}
+
+struct Tasks {
+	/// Unfinished tasks, by id. Tasks that are being polled are taken out.
+	pending: HashMap<u64, BoxFuture<'static, ()>>,
+	/// Id of the next spawned task. Ids aren't reused, so that the wakers of a finished task
+	/// can't wake up another one.
+	next_id: u64,
+}
+
+struct Clock {
+	now: Duration,
+	/// Wakers of pending `Sleep`s, ordered by deadline and then by creation.
+	timers: BTreeMap<(Duration, u64), Option<Waker>>,
+	next_timer_id: u64,
+}
+
+impl DeterministicExecutor {
+	/// Create an executor without tasks, with the virtual clock at zero.
+	pub fn new() -> Self {
+		DeterministicExecutor {
+			shared: Arc::new(Shared {
+				tasks: Mutex::new(Tasks { pending: HashMap::new(), next_id: 0 }),
+				ready: Mutex::new(VecDeque::new()),
+				wakeup: Condvar::new(),
+				clock: Mutex::new(Clock {
+					now: Duration::from_secs(0),
+					timers: BTreeMap::new(),
+					next_timer_id: 0,
+				}),
+			}),
+		}
+	}
+
+	/// Returns a task executor to put in the `Configuration`, e.g. through
+	/// `CliConfiguration::create_configuration`.
+	///
+	/// Blocking tasks run on the executor too, so they must not block for long.
+	pub fn task_executor(
+		&self,
+	) -> Arc<dyn Fn(Pin<Box<dyn Future<Output = ()> + Send>>, TaskType) + Send + Sync> {
+		let executor = self.clone();
+		Arc::new(move |future: Pin<Box<dyn Future<Output = ()> + Send>>, _| executor.spawn(future))
+	}
+
+	/// Add a task to the executor. It is first polled by the next `run_until_stalled`.
+	pub fn spawn(&self, future: impl Future<Output = ()> + Send + 'static) {
+		let id = {
+			let mut tasks = self.shared.tasks.lock();
+			let id = tasks.next_id;
+			tasks.next_id += 1;
+			tasks.pending.insert(id, future.boxed());
+			id
+		};
+		self.shared.wake(id);
+	}
+
+	/// Poll the tasks that have been woken up until none is left, and return the number of polls.
+	pub fn run_until_stalled(&self) -> usize {
+		let mut polls = 0;
+		loop {
+			let id = match self.shared.ready.lock().pop_front() {
+				Some(id) => id,
+				None => return polls,
+			};
+			// Tasks may be woken up multiple times before being polled, or after finishing.
+			let mut task = match self.shared.tasks.lock().pending.remove(&id) {
+				Some(task) => task,
+				None => continue,
+			};
+
+			let waker = waker(Arc::new(TaskWaker { id, shared: self.shared.clone() }));
+			polls += 1;
+			if task.poll_unpin(&mut Context::from_waker(&waker)).is_pending() {
+				self.shared.tasks.lock().pending.insert(id, task);
+			}
+		}
+	}
+
+	/// Run the tasks until `future` completes.
+	///
+	/// Whenever all tasks are stalled, the virtual clock jumps to the next pending timer. Without
+	/// timers, the thread waits for a wake-up from outside of the executor.
+	pub fn block_on<F: Future>(&self, future: F) -> F::Output {
+		futures::pin_mut!(future);
+		let main_waker = Arc::new(MainWaker {
+			woken: AtomicBool::new(true),
+			shared: self.shared.clone(),
+		});
+		let waker = waker(main_waker.clone());
+
+		loop {
+			if main_waker.woken.swap(false, Ordering::SeqCst) {
+				let mut cx = Context::from_waker(&waker);
+				if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+					return output;
+				}
+			}
+
+			if self.run_until_stalled() > 0 || main_waker.woken.load(Ordering::SeqCst) {
+				continue;
+			}
+
+			if self.advance_to_next_timer() {
+				continue;
+			}
+
+			let mut ready = self.shared.ready.lock();
+			if ready.is_empty() && !main_waker.woken.load(Ordering::SeqCst) {
+				self.shared.wakeup.wait(&mut ready);
+			}
+		}
+	}
+
+	/// Time elapsed on the virtual clock.
+	pub fn now(&self) -> Duration {
+		self.shared.clock.lock().now
+	}
+
+	/// Returns a future that completes once the virtual clock has advanced by `duration`.
+	pub fn sleep(&self, duration: Duration) -> Sleep {
+		let mut clock = self.shared.clock.lock();
+		let key = (clock.now + duration, clock.next_timer_id);
+		clock.next_timer_id += 1;
+		clock.timers.insert(key, None);
+
+		Sleep { key, shared: self.shared.clone() }
+	}
+
+	/// Advance the virtual clock by `duration` and wake up the timers that have expired.
+	pub fn advance_time(&self, duration: Duration) {
+		let now = self.shared.clock.lock().now + duration;
+		self.set_time(now);
+	}
+
+	/// Advance the virtual clock to the deadline of the next timer, and wake it up.
+	///
+	/// Returns `false` if there is no pending timer.
+	pub fn advance_to_next_timer(&self) -> bool {
+		let next = self.shared.clock.lock().timers.keys().next().map(|(deadline, _)| *deadline);
+		match next {
+			Some(deadline) => {
+				self.set_time(deadline);
+				true
+			}
+			None => false,
+		}
+	}
+
+	fn set_time(&self, now: Duration) {
+		let expired = {
+			let mut clock = self.shared.clock.lock();
+			let now = std::cmp::max(clock.now, now);
+			clock.now = now;
+			let pending = clock.timers.split_off(&(now, u64::max_value()));
+			std::mem::replace(&mut clock.timers, pending)
+		};
+
+		for waker in expired.into_iter().filter_map(|(_, waker)| waker) {
+			waker.wake();
+		}
+	}
+}
+
+impl sp_timestamp::Clock for DeterministicExecutor {
+	fn now(&self) -> Duration {
+		DeterministicExecutor::now(self)
+	}
+
+	fn delay(&self, duration: Duration) -> Pin<Box<dyn Future<Output = ()> + Send>> {
+		Box::pin(self.sleep(duration))
+	}
+}
+
+impl Default for DeterministicExecutor {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl Shared {
+	fn wake(&self, id: u64) {
+		self.ready.lock().push_back(id);
+		self.wakeup.notify_all();
+	}
+}
+
+struct TaskWaker {
+	id: u64,
+	shared: Arc<Shared>,
+}
+
+impl ArcWake for TaskWaker {
+	fn wake_by_ref(arc_self: &Arc<Self>) {
+		arc_self.shared.wake(arc_self.id);
+	}
+}
+
+struct MainWaker {
+	woken: AtomicBool,
+	shared: Arc<Shared>,
+}
+
+impl ArcWake for MainWaker {
+	fn wake_by_ref(arc_self: &Arc<Self>) {
+		// Hold the lock so that the wake-up can't get lost while `block_on` goes to sleep.
+		let _ready = arc_self.shared.ready.lock();
+		arc_self.woken.store(true, Ordering::SeqCst);
+		arc_self.shared.wakeup.notify_all();
+	}
+}
+
+/// Future returned by [`DeterministicExecutor::sleep`].
+pub struct Sleep {
+	key: (Duration, u64),
+	shared: Arc<Shared>,
+}
+
+impl Future for Sleep {
+	type Output = ();
+
+	fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<()> {
+		let mut clock = self.shared.clock.lock();
+		if clock.now >= self.key.0 {
+			return Poll::Ready(());
+		}
+
+		clock.timers.insert(self.key, Some(cx.waker().clone()));
+		Poll::Pending
+	}
+}
+
+impl Drop for Sleep {
+	fn drop(&mut self) {
+		self.shared.clock.lock().timers.remove(&self.key);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn runs_tasks_in_wake_up_order_on_virtual_time() {
+		let executor = DeterministicExecutor::new();
+		let order = Arc::new(Mutex::new(Vec::new()));
+
+		for &(name, delay) in &[("slow", 10), ("fast", 5), ("immediate", 0)] {
+			let (clock, order) = (executor.clone(), order.clone());
+			executor.task_executor()(async move {
+				clock.sleep(Duration::from_secs(delay)).await;
+				order.lock().push(name);
+			}.boxed(), TaskType::Async);
+		}
+
+		executor.run_until_stalled();
+		assert_eq!(*order.lock(), vec!["immediate"]);
+
+		executor.advance_time(Duration::from_secs(7));
+		executor.run_until_stalled();
+		assert_eq!(*order.lock(), vec!["immediate", "fast"]);
+
+		let clock = executor.clone();
+		executor.block_on(clock.sleep(Duration::from_secs(60)));
+		assert_eq!(*order.lock(), vec!["immediate", "fast", "slow"]);
+		assert_eq!(executor.now(), Duration::from_secs(67));
+	}
+
+	#[test]
+	fn finished_tasks_are_freed() {
+		let executor = DeterministicExecutor::new();
+		let (sender, receiver) = futures::channel::oneshot::channel::<()>();
+		executor.spawn(async move { let _ = receiver.await; });
+		for _ in 0..100 {
+			executor.spawn(async {});
+		}
+
+		executor.run_until_stalled();
+		assert_eq!(executor.shared.tasks.lock().pending.len(), 1);
+
+		sender.send(()).unwrap();
+		executor.run_until_stalled();
+		assert!(executor.shared.tasks.lock().pending.is_empty());
+	}
+}
@@ -0,0 +1,214 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2020 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Networks of in-process nodes connected through an in-memory transport.
+
+use super::DeterministicExecutor;
+use crate::{AbstractService, ChainSpec, Configuration, Error, Role};
+use crate::config::{DatabaseConfig, KeystoreConfig, WasmExecutionMethod};
+use futures::{future, FutureExt};
+use sc_client_db::DbHash;
+use sc_network::{multiaddr, Multiaddr};
+use sc_network::config::{NetworkConfiguration, TransportConfig};
+use sp_blockchain::HeaderBackend;
+use sp_database::MemDb;
+use sp_runtime::traits::SaturatedConversion;
+use std::{sync::Arc, task::Poll, time::Duration};
+
+/// A node of a [`TestNetwork`].
+pub struct TestNode<S> {
+	/// The service of the node.
+	pub service: S,
+	/// Address of the node, including its peer id.
+	pub address: Multiaddr,
+}
+
+/// Nodes running in the current process, all connected to each other.
+///
+/// The nodes only make progress while one of the `run_until` or `wait_for_*` methods runs. All of
+/// them run on the same [`DeterministicExecutor`].
+pub struct TestNetwork<S> {
+	executor: DeterministicExecutor,
+	nodes: Vec<TestNode<S>>,
+}
+
+impl<S: AbstractService> TestNetwork<S> {
+	/// Start a node for each of the `authorities` key seeds (e.g. `//Alice`) followed by
+	/// `full_nodes` full nodes, using `build` to turn their configurations into services.
+	///
+	/// The nodes use in-memory databases and keystores, produce their slots on the virtual clock
+	/// of the executor and are added to each other's reserved peers.
+	pub fn new(
+		chain_spec: &dyn ChainSpec,
+		authorities: &[&str],
+		full_nodes: usize,
+		build: impl Fn(Configuration) -> Result<S, Error>,
+	) -> Result<Self, Error> {
+		let executor = DeterministicExecutor::new();
+		let nodes_roles = authorities.iter()
+			.map(|seed| (Role::Authority { sentry_nodes: Vec::new() }, Some(seed.to_string())))
+			.chain((0..full_nodes).map(|_| (Role::Full, None)));
+
+		let mut nodes = Vec::<TestNode<S>>::new();
+		for (index, (role, key_seed)) in nodes_roles.enumerate() {
+			let config = node_config(index, chain_spec, role, key_seed, &executor);
+			let listen_address = config.network.listen_addresses[0].clone();
+			let service = build(config)?;
+
+			for node in &nodes {
+				service.network()
+					.add_reserved_peer(node.address.to_string())
+					.map_err(Error::Other)?;
+			}
+
+			let peer_id = service.network().local_peer_id().clone();
+			let address = listen_address.with(multiaddr::Protocol::P2p(peer_id.into()));
+			nodes.push(TestNode { service, address });
+		}
+
+		Ok(TestNetwork { executor, nodes })
+	}
+
+	/// The nodes of the network, authorities first.
+	pub fn nodes(&self) -> &[TestNode<S>] {
+		&self.nodes
+	}
+
+	/// The executor that runs the nodes, to spawn additional tasks or control time.
+	pub fn executor(&self) -> &DeterministicExecutor {
+		&self.executor
+	}
+
+	/// Run the nodes until `condition` returns `true`.
+	///
+	/// Fails if `timeout` passes first on the virtual clock, or if a node stops.
+	pub fn run_until(
+		&mut self,
+		timeout: Duration,
+		mut condition: impl FnMut(&[TestNode<S>]) -> bool,
+	) -> Result<(), Error> {
+		let nodes = &mut self.nodes;
+		let mut deadline = self.executor.sleep(timeout);
+
+		self.executor.block_on(future::poll_fn(move |cx| {
+			for node in nodes.iter_mut() {
+				if let Poll::Ready(result) = node.service.poll_unpin(cx) {
+					return Poll::Ready(result.and_then(|()| Err("A test node stopped".into())));
+				}
+			}
+
+			if condition(nodes) {
+				return Poll::Ready(Ok(()));
+			}
+
+			deadline.poll_unpin(cx).map(|()| {
+				Err(Error::Other(format!("Test network condition not met within {:?}", timeout)))
+			})
+		}))
+	}
+
+	/// Run the nodes until all of them have imported block `number` as part of their best chain.
+	pub fn wait_for_blocks(&mut self, number: u64, timeout: Duration) -> Result<(), Error> {
+		self.run_until(timeout, |nodes| nodes.iter().all(|node| {
+			node.service.client().info().best_number.saturated_into::<u64>() >= number
+		}))
+	}
+
+	/// Run the nodes until all of them have finalized block `number`.
+	pub fn wait_for_finality(&mut self, number: u64, timeout: Duration) -> Result<(), Error> {
+		self.run_until(timeout, |nodes| nodes.iter().all(|node| {
+			node.service.client().info().finalized_number.saturated_into::<u64>() >= number
+		}))
+	}
+}
+
+fn node_config(
+	index: usize,
+	chain_spec: &dyn ChainSpec,
+	role: Role,
+	key_seed: Option<String>,
+	executor: &DeterministicExecutor,
+) -> Configuration {
+	let mut network = NetworkConfiguration::new(
+		format!("Test node {}", index),
+		"test-network/0.1",
+		Default::default(),
+		None,
+	);
+	network.listen_addresses = vec![multiaddr::Protocol::Memory(rand::random()).into()];
+	network.transport = TransportConfig::MemoryOnly;
+	network.allow_non_globals_in_dht = true;
+
+	Configuration {
+		impl_name: "test-network",
+		impl_version: "0.1",
+		role,
+		task_executor: executor.task_executor(),
+		transaction_pool: Default::default(),
+		network,
+		keystore: KeystoreConfig::InMemory,
+		database: DatabaseConfig::Custom(Arc::new(MemDb::<DbHash>::new())),
+		cold_storage: None,
+		database_read_only: false,
+		block_compression: Default::default(),
+		trie_cache_size: 16 * 1024 * 1024,
+		value_cache_size: 16 * 1024 * 1024,
+		state_cache_child_ratio: None,
+		pruning: Default::default(),
+		chain_spec: chain_spec.cloned_box(),
+		wasm_method: WasmExecutionMethod::Interpreted,
+		execution_strategies: Default::default(),
+		signature_verification: Default::default(),
+		client_notifications: Default::default(),
+		storage_diffs: false,
+		replay_log: None,
+		rpc_http: None,
+		rpc_ws: None,
+		rpc_ws_max_connections: None,
+		rpc_ws_idle_timeout: None,
+		rpc_cors: None,
+		rpc_allowed_hosts: None,
+		rpc_methods: Default::default(),
+		rpc_finalized_only: false,
+		rpc_subscription_replay: None,
+		rpc_call_timeout: None,
+		admin_rpc_peers: Vec::new(),
+		node_labels: Vec::new(),
+		disk_space_watchdog: None,
+		pool_memory_limits: None,
+		prometheus_config: None,
+		telemetry_endpoints: None,
+		telemetry_external_transport: None,
+		telemetry_auth: Default::default(),
+		default_heap_pages: None,
+		offchain_worker: Default::default(),
+		force_authoring: false,
+		disable_grandpa: false,
+		grandpa_justification_period: Some(512),
+		grandpa_request_justifications: false,
+		grandpa_justification_workers: 0,
+		// the GRANDPA timers aren't on the virtual clock, so they are kept short
+		grandpa_gossip_duration: Duration::from_millis(10),
+		clock: Some(Arc::new(executor.clone())),
+		dev_key_seed: key_seed,
+		tracing_targets: None,
+		tracing_receiver: Default::default(),
+		max_runtime_instances: 8,
+		announce_block: true,
+	}
+}
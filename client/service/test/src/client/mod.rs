@@ -29,9 +29,9 @@ use substrate_test_runtime_client::{
 	BlockBuilderExt, DefaultTestClientBuilderExt, TestClientBuilderExt, ClientExt,
 };
 use sc_client_api::{
-	StorageProvider, BlockBackend, in_mem, BlockchainEvents,
+	StorageProvider, BlockBackend, in_mem, BlockchainEvents, IndexerNotification,
 };
-use sc_client_db::{Backend, DatabaseSettings, DatabaseSettingsSrc, PruningMode};
+use sc_client_db::{Backend, BlockCompression, DatabaseSettings, DatabaseSettingsSrc, PruningMode};
 use sc_block_builder::BlockBuilderProvider;
 use sc_service::client::{self, Client, LocalCallExecutor, new_in_mem};
 use sp_runtime::traits::{
@@ -1194,6 +1194,211 @@ fn finalizing_diverged_block_should_trigger_reorg() {
 	);
 }
 
+#[test]
+fn importing_longer_fork_should_notify_reorg() {
+	let mut client = substrate_test_runtime_client::new();
+	let mut reorgs = client.reorg_notification_stream();
+
+	// G -> A1 -> A2
+	//   \
+	//    -> B1 -> B2 -> B3
+	let a1 = client.new_block_at(
+		&BlockId::Number(0),
+		Default::default(),
+		false,
+	).unwrap().build().unwrap().block;
+	client.import(BlockOrigin::Own, a1.clone()).unwrap();
+
+	let a2 = client.new_block_at(
+		&BlockId::Hash(a1.hash()),
+		Default::default(),
+		false,
+	).unwrap().build().unwrap().block;
+	client.import(BlockOrigin::Own, a2.clone()).unwrap();
+
+	let mut b1 = client.new_block_at(
+		&BlockId::Number(0),
+		Default::default(),
+		false,
+	).unwrap();
+	// needed to make sure B1 gets a different hash from A1
+	b1.push_transfer(Transfer {
+		from: AccountKeyring::Alice.into(),
+		to: AccountKeyring::Ferdie.into(),
+		amount: 1,
+		nonce: 0,
+	}).unwrap();
+	let b1 = b1.build().unwrap().block;
+	client.import(BlockOrigin::Own, b1.clone()).unwrap();
+
+	let b2 = client.new_block_at(
+		&BlockId::Hash(b1.hash()),
+		Default::default(),
+		false,
+	).unwrap().build().unwrap().block;
+	client.import(BlockOrigin::Own, b2.clone()).unwrap();
+
+	// no reorg happened so far
+	assert!(reorgs.try_next().is_err());
+
+	let b3 = client.new_block_at(
+		&BlockId::Hash(b2.hash()),
+		Default::default(),
+		false,
+	).unwrap().build().unwrap().block;
+	client.import(BlockOrigin::Own, b3.clone()).unwrap();
+
+	let reorg = reorgs.try_next().unwrap().unwrap();
+	assert_eq!(reorg.new_best, b3.hash());
+	assert_eq!(reorg.retracted, vec![a1.hash(), a2.hash()]);
+	assert_eq!(reorg.enacted, vec![b1.hash(), b2.hash(), b3.hash()]);
+}
+
+#[test]
+fn fork_choice_rule_selects_the_best_block() {
+	let mut client = substrate_test_runtime_client::new();
+	// never reorg: a block becomes the best block only if it builds on the current one.
+	client.set_fork_choice_rule(Arc::new(
+		|best: &Header, imported: &Header| -> Result<bool, ConsensusError> {
+			Ok(imported.parent_hash() == &best.hash())
+		}
+	));
+
+	// G -> A1
+	//   \
+	//    -> B1 -> B2 -> B3
+	let a1 = client.new_block_at(
+		&BlockId::Number(0),
+		Default::default(),
+		false,
+	).unwrap().build().unwrap().block;
+	client.import(BlockOrigin::Own, a1.clone()).unwrap();
+	assert_eq!(client.chain_info().best_hash, a1.hash());
+
+	let mut b1 = client.new_block_at(
+		&BlockId::Number(0),
+		Default::default(),
+		false,
+	).unwrap();
+	// needed to make sure B1 gets a different hash from A1
+	b1.push_transfer(Transfer {
+		from: AccountKeyring::Alice.into(),
+		to: AccountKeyring::Ferdie.into(),
+		amount: 1,
+		nonce: 0,
+	}).unwrap();
+	let b1 = b1.build().unwrap().block;
+	client.import(BlockOrigin::Own, b1.clone()).unwrap();
+
+	let b2 = client.new_block_at(
+		&BlockId::Hash(b1.hash()),
+		Default::default(),
+		false,
+	).unwrap().build().unwrap().block;
+	client.import(BlockOrigin::Own, b2.clone()).unwrap();
+
+	// the longest chain rule would have picked B2
+	assert_eq!(client.chain_info().best_hash, a1.hash());
+
+	// the choice of the consensus engine is kept
+	let b3 = client.new_block_at(
+		&BlockId::Hash(b2.hash()),
+		Default::default(),
+		false,
+	).unwrap().build().unwrap().block;
+	client.import_as_best(BlockOrigin::Own, b3.clone()).unwrap();
+	assert_eq!(client.chain_info().best_hash, b3.hash());
+}
+
+#[test]
+fn finalizing_multiple_blocks_should_notify_single_batch() {
+	let mut client = substrate_test_runtime_client::new();
+	let mut batches = client.finality_batch_notification_stream();
+
+	let a1 = client.new_block(Default::default()).unwrap().build().unwrap().block;
+	client.import(BlockOrigin::Own, a1.clone()).unwrap();
+
+	let a2 = client.new_block(Default::default()).unwrap().build().unwrap().block;
+	client.import(BlockOrigin::Own, a2.clone()).unwrap();
+
+	ClientExt::finalize_block(&client, BlockId::Hash(a2.hash()), None).unwrap();
+
+	let batch = batches.try_next().unwrap().unwrap();
+	assert_eq!(batch.finalized, vec![a1.hash(), a2.hash()]);
+	assert_eq!(batch.header.hash(), a2.hash());
+	assert!(batches.try_next().is_err());
+}
+
+#[test]
+fn indexers_are_notified_of_imported_and_finalized_blocks() {
+	let mut client = TestClientBuilder::new()
+		.set_client_config(client::ClientConfig { storage_diffs: true, ..Default::default() })
+		.build();
+	let mut notifications = client.indexer_notification_stream();
+
+	let mut builder = client.new_block(Default::default()).unwrap();
+	builder.push_transfer(Transfer {
+		from: AccountKeyring::Alice.into(),
+		to: AccountKeyring::Ferdie.into(),
+		amount: 42,
+		nonce: 0,
+	}).unwrap();
+	let a1 = builder.build().unwrap().block;
+	client.import(BlockOrigin::Own, a1.clone()).unwrap();
+	ClientExt::finalize_block(&client, BlockId::Hash(a1.hash()), None).unwrap();
+
+	match notifications.try_next().unwrap().unwrap() {
+		IndexerNotification::Imported(block) => {
+			assert_eq!(block.hash, a1.hash());
+			assert_eq!(block.body, Some(a1.extrinsics().to_vec()));
+			assert!(block.is_new_best);
+			assert!(!block.storage_changes.as_ref().unwrap().0.is_empty());
+		},
+		notification => panic!("Unexpected notification {:?}", notification),
+	}
+	match notifications.try_next().unwrap().unwrap() {
+		IndexerNotification::Finalized(finalized) => assert_eq!(finalized.hash, a1.hash()),
+		notification => panic!("Unexpected notification {:?}", notification),
+	}
+	assert!(notifications.try_next().is_err());
+}
+
+#[test]
+fn indexers_are_notified_of_the_blocks_of_the_initial_sync() {
+	let mut client = substrate_test_runtime_client::new();
+	let mut imported = client.import_notification_stream();
+	let mut notifications = client.indexer_notification_stream();
+
+	let a1 = client.new_block(Default::default()).unwrap().build().unwrap().block;
+	client.import(BlockOrigin::NetworkInitialSync, a1.clone()).unwrap();
+	let a2 = client.new_block_at(&BlockId::Hash(a1.hash()), Default::default(), false).unwrap()
+		.build().unwrap().block;
+	client.import_as_final(BlockOrigin::NetworkInitialSync, a2.clone()).unwrap();
+
+	// no import notification is made during the initial sync
+	assert!(imported.try_next().is_err());
+
+	let mut next = || notifications.try_next().unwrap().unwrap();
+	match next() {
+		IndexerNotification::Imported(block) => {
+			assert_eq!(block.hash, a1.hash());
+			assert_eq!(block.origin, BlockOrigin::NetworkInitialSync);
+		},
+		notification => panic!("Unexpected notification {:?}", notification),
+	}
+	match next() {
+		IndexerNotification::Imported(block) => assert_eq!(block.hash, a2.hash()),
+		notification => panic!("Unexpected notification {:?}", notification),
+	}
+	for hash in &[a1.hash(), a2.hash()] {
+		match next() {
+			IndexerNotification::Finalized(finalized) => assert_eq!(finalized.hash, *hash),
+			notification => panic!("Unexpected notification {:?}", notification),
+		}
+	}
+	assert!(notifications.try_next().is_err());
+}
+
 #[test]
 fn get_header_by_block_number_doesnt_panic() {
 	let client = substrate_test_runtime_client::new();
@@ -1273,13 +1478,17 @@ fn doesnt_import_blocks_that_revert_finality() {
 	// states
 	let backend = Arc::new(Backend::new(
 		DatabaseSettings {
-			state_cache_size: 1 << 20,
+			trie_cache_size: 1 << 20,
+			value_cache_size: 1 << 20,
 			state_cache_child_ratio: None,
 			pruning: PruningMode::ArchiveAll,
 			source: DatabaseSettingsSrc::RocksDb {
 				path: tmp.path().into(),
 				cache_size: 1024,
 			},
+			cold_storage: None,
+			read_only: false,
+			block_compression: BlockCompression::None,
 		},
 		u64::max_value(),
 	).unwrap());
@@ -1465,6 +1674,21 @@ fn respects_block_rules() {
 	run_test(false, &mut known_bad, &mut fork_rules);
 }
 
+#[test]
+fn builds_genesis_block_with_given_builder() {
+	let client = TestClientBuilder::new()
+		.set_genesis_block_builder(|state_root: Hash| -> sp_blockchain::Result<Block> {
+			let mut block = client::genesis::construct_genesis_block::<Block>(state_root);
+			block.header.digest.push(DigestItem::Other(b"snapshot".to_vec()));
+			Ok(block)
+		})
+		.build();
+
+	let genesis = client.header(&BlockId::Number(0)).unwrap().unwrap();
+	assert_eq!(genesis.digest().logs(), &[DigestItem::Other(b"snapshot".to_vec())]);
+	assert_eq!(client.chain_info().genesis_hash, genesis.hash());
+}
+
 #[test]
 fn returns_status_for_pruned_blocks() {
 	let _ = env_logger::try_init();
@@ -1474,13 +1698,17 @@ fn returns_status_for_pruned_blocks() {
 	// states
 	let backend = Arc::new(Backend::new(
 		DatabaseSettings {
-			state_cache_size: 1 << 20,
+			trie_cache_size: 1 << 20,
+			value_cache_size: 1 << 20,
 			state_cache_child_ratio: None,
 			pruning: PruningMode::keep_blocks(1),
 			source: DatabaseSettingsSrc::RocksDb {
 				path: tmp.path().into(),
 				cache_size: 1024,
 			},
+			cold_storage: None,
+			read_only: false,
+			block_compression: BlockCompression::None,
 		},
 		u64::max_value(),
 	).unwrap());
@@ -1779,8 +2007,8 @@ fn cleans_up_closed_notification_sinks_on_block_import() {
 	// after importing a block we should still have 4 notification sinks
 	// (2 import + 2 finality)
 	bake_and_import_block(&mut client, BlockOrigin::Own);
-	assert_eq!(client.import_notification_sinks().lock().len(), 2);
-	assert_eq!(client.finality_notification_sinks().lock().len(), 2);
+	assert_eq!(client.import_notification_sinks().len(), 2);
+	assert_eq!(client.finality_notification_sinks().len(), 2);
 
 	// if we drop one import notification receiver and one finality
 	// notification receiver
@@ -1789,8 +2017,8 @@ fn cleans_up_closed_notification_sinks_on_block_import() {
 
 	// the sinks should be cleaned up after block import
 	bake_and_import_block(&mut client, BlockOrigin::Own);
-	assert_eq!(client.import_notification_sinks().lock().len(), 1);
-	assert_eq!(client.finality_notification_sinks().lock().len(), 1);
+	assert_eq!(client.import_notification_sinks().len(), 1);
+	assert_eq!(client.finality_notification_sinks().len(), 1);
 
 	// the same thing should happen if block import happens during initial
 	// sync
@@ -1798,7 +2026,52 @@ fn cleans_up_closed_notification_sinks_on_block_import() {
 	drop(finality_notif1);
 
 	bake_and_import_block(&mut client, BlockOrigin::NetworkInitialSync);
-	assert_eq!(client.import_notification_sinks().lock().len(), 0);
-	assert_eq!(client.finality_notification_sinks().lock().len(), 0);
+	assert_eq!(client.import_notification_sinks().len(), 0);
+	assert_eq!(client.finality_notification_sinks().len(), 0);
 }
 
+
+#[test]
+fn compares_executions_of_a_block() {
+	use sc_service::chain_ops::{compare_block_execution, BlockExecution, StorageChange};
+
+	let mut client = substrate_test_runtime_client::new();
+	let mut builder = client.new_block(Default::default()).unwrap();
+	builder.push_transfer(Transfer {
+		from: AccountKeyring::Alice.into(),
+		to: AccountKeyring::Ferdie.into(),
+		amount: 42,
+		nonce: 0,
+	}).unwrap();
+	client.import(BlockOrigin::Own, builder.build().unwrap().block).unwrap();
+	let state_root = *client.header(&BlockId::Number(1)).unwrap().unwrap().state_root();
+
+	let diff = compare_block_execution(
+		&client,
+		BlockId::Number(1),
+		&BlockExecution::Native,
+		&BlockExecution::Wasm,
+	).unwrap();
+	assert!(diff.is_empty(), "{:?}", diff);
+	assert_eq!(diff.first.state_root, Some(format!("{:?}", state_root)));
+
+	let diff = compare_block_execution(
+		&client,
+		BlockId::Number(1),
+		&BlockExecution::Wasm,
+		&BlockExecution::CustomWasm(b"not a runtime".to_vec()),
+	).unwrap();
+	assert!(!diff.is_empty());
+	assert!(diff.first.error.is_none());
+	assert!(diff.second.error.is_some());
+	// the changes of the first execution aren't made by the failed one
+	assert!(!diff.storage.is_empty());
+	assert!(diff.storage.iter().all(|change| change.second == StorageChange::Unchanged));
+
+	assert!(compare_block_execution(
+		&client,
+		BlockId::Number(0),
+		&BlockExecution::Native,
+		&BlockExecution::Wasm,
+	).is_err());
+}
@@ -39,11 +39,14 @@ use sc_service::{
 	Role,
 	Error,
 	TaskType,
+	NodeHandle,
+	RpcSession,
 };
 use sp_blockchain::HeaderBackend;
 use sc_network::{multiaddr, Multiaddr};
 use sc_network::config::{NetworkConfiguration, TransportConfig};
-use sp_runtime::{generic::BlockId, traits::Block as BlockT};
+use sp_runtime::{generic::BlockId, traits::{Block as BlockT, Header as HeaderT, NumberFor}};
+use sc_rpc_api::{chain::ChainClient, system::SystemClient};
 use sp_transaction_pool::TransactionPool;
 
 #[cfg(test)]
@@ -187,24 +190,47 @@ fn node_config<G: RuntimeGenesis + 'static, E: ChainSpecExtension + Clone + 'sta
 			path: root.join("db"),
 			cache_size: 128,
 		},
-		state_cache_size: 16777216,
+		cold_storage: None,
+		database_read_only: false,
+		block_compression: Default::default(),
+		trie_cache_size: 16777216,
+		value_cache_size: 16777216,
 		state_cache_child_ratio: None,
 		pruning: Default::default(),
 		chain_spec: Box::new((*spec).clone()),
 		wasm_method: sc_service::config::WasmExecutionMethod::Interpreted,
 		execution_strategies: Default::default(),
+		signature_verification: Default::default(),
+		client_notifications: Default::default(),
+		storage_diffs: false,
+		replay_log: None,
 		rpc_http: None,
 		rpc_ws: None,
 		rpc_ws_max_connections: None,
+		rpc_ws_idle_timeout: None,
 		rpc_cors: None,
+		rpc_allowed_hosts: None,
 		rpc_methods: Default::default(),
+		rpc_finalized_only: false,
+		rpc_subscription_replay: None,
+		rpc_call_timeout: None,
+		admin_rpc_peers: Vec::new(),
+		node_labels: Vec::new(),
+		disk_space_watchdog: None,
+		pool_memory_limits: None,
 		prometheus_config: None,
 		telemetry_endpoints: None,
 		telemetry_external_transport: None,
+		telemetry_auth: Default::default(),
 		default_heap_pages: None,
 		offchain_worker: Default::default(),
 		force_authoring: false,
 		disable_grandpa: false,
+		grandpa_justification_period: Some(512),
+		grandpa_request_justifications: false,
+		grandpa_justification_workers: 0,
+		grandpa_gossip_duration: Duration::from_millis(333),
+		clock: None,
 		dev_key_seed: key_seed,
 		tracing_targets: None,
 		tracing_receiver: Default::default(),
@@ -557,3 +583,67 @@ pub fn consensus<G, E, Fb, F, Lb, L>(
 			service.get().client().info().best_number >= (NUM_BLOCKS as u32).into(),
 	);
 }
+
+pub fn embedded_node<G, E, Fb, F>(
+	spec: GenericChainSpec<G, E>,
+	full_builder: Fb,
+) where
+	Fb: Fn(Configuration) -> Result<F, Error>,
+	F: AbstractService,
+	E: ChainSpecExtension + Clone + 'static + Send,
+	G: RuntimeGenesis + 'static,
+{
+	const GENESIS_HASH_REQUEST: &str =
+		r#"{"jsonrpc":"2.0","method":"chain_getBlockHash","params":[0],"id":1}"#;
+
+	let _ = env_logger::try_init();
+	let temp = tempdir_with_prefix("substrate-embedded-node-test");
+	let runtime = Runtime::new().expect("Error creating tokio runtime");
+	let start = || {
+		let executor = runtime.executor();
+		let task_executor = Arc::new(
+			move |fut: Pin<Box<dyn futures::Future<Output = ()> + Send>>, _| {
+				executor.spawn(fut.unit_error().compat())
+			}
+		);
+		let config = node_config(0, &spec, Role::Full, task_executor, None, 30700, &temp);
+		NodeHandle::start(config, &full_builder).expect("Error starting the embedded node")
+	};
+
+	info!("Checking the RPC of the embedded node");
+	let node = start();
+	let genesis_hash = node.client().info().genesis_hash;
+	let session = RpcSession::new(futures01::sync::mpsc::channel(1).0);
+	let response = futures::executor::block_on(node.rpc_query(&session, GENESIS_HASH_REQUEST));
+	assert_eq!(
+		response,
+		Some(format!(r#"{{"jsonrpc":"2.0","result":"{:?}","id":1}}"#, genesis_hash)),
+	);
+
+	info!("Checking the typed RPC clients of the embedded node");
+	let system: SystemClient<<F::Block as BlockT>::Hash, NumberFor<F::Block>> = node.rpc_client();
+	let name = system.system_name().wait().expect("Error calling system_name");
+	assert_eq!(name, "network-test-impl");
+	// the blocks are never fetched, so they don't need to be decoded.
+	let chain: ChainClient<
+		NumberFor<F::Block>,
+		<F::Block as BlockT>::Hash,
+		<F::Block as BlockT>::Header,
+		(),
+	> = node.rpc_client();
+	let finalized_heads = chain.subscribe_finalized_heads(None).wait()
+		.expect("Error subscribing to the finalized heads");
+	let (finalized_head, _) = finalized_heads.into_future().wait()
+		.map_err(|(e, _)| e)
+		.expect("Error reading the finalized heads");
+	assert_eq!(finalized_head.map(|header| header.hash()), Some(genesis_hash));
+	node.stop().expect("Error stopping the embedded node");
+
+	info!("Checking the embedded node can be started again once stopped");
+	let node = start();
+	assert_eq!(node.client().info().genesis_hash, genesis_hash);
+	drop(node);
+
+	runtime.shutdown_now().wait().expect("Error shutting down runtime");
+	temp.close().expect("Error removing temp dir");
+}
@@ -0,0 +1,129 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2020 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Custom fields attached to the periodic telemetry messages.
+
+use log::warn;
+use parking_lot::Mutex;
+use serde::{Serialize, Serializer, ser::SerializeMap};
+use serde_json::Value as JsonValue;
+use slog::{SerdeValue, Value};
+use std::{collections::BTreeMap, sync::Arc};
+
+/// Key/value fields set by the node implementation and sent with the periodic `system.interval`
+/// telemetry messages, under the `custom` key.
+///
+/// Cloning returns a handle to the same fields, so that they can be updated at any time.
+#[derive(Debug, Clone, Default)]
+pub struct TelemetryFields {
+	fields: Arc<Mutex<BTreeMap<String, JsonValue>>>,
+}
+
+impl TelemetryFields {
+	/// Set the field `key` to `value`, replacing its previous value.
+	pub fn set(&self, key: impl Into<String>, value: impl Serialize) {
+		let key = key.into();
+		match serde_json::to_value(value) {
+			Ok(value) => {
+				self.fields.lock().insert(key, value);
+			},
+			Err(err) => {
+				warn!(target: "telemetry", "Failed to serialize telemetry field {}: {}", key, err);
+			},
+		}
+	}
+
+	/// Remove the field `key`, returning whether it was set.
+	pub fn remove(&self, key: &str) -> bool {
+		self.fields.lock().remove(key).is_some()
+	}
+
+	/// Returns the current value of the field `key`.
+	pub fn get(&self, key: &str) -> Option<JsonValue> {
+		self.fields.lock().get(key).cloned()
+	}
+
+	/// Returns whether no field is set.
+	pub fn is_empty(&self) -> bool {
+		self.fields.lock().is_empty()
+	}
+
+	/// Returns a copy of the fields that isn't affected by later updates.
+	fn snapshot(&self) -> Self {
+		TelemetryFields { fields: Arc::new(Mutex::new(self.fields.lock().clone())) }
+	}
+}
+
+impl Serialize for TelemetryFields {
+	fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+		where S: Serializer,
+	{
+		let fields = self.fields.lock();
+		let mut map = serializer.serialize_map(Some(fields.len()))?;
+		for (k, v) in fields.iter() {
+			map.serialize_entry(k, v)?;
+		}
+		map.end()
+	}
+}
+
+impl SerdeValue for TelemetryFields {
+	fn as_serde(&self) -> &dyn erased_serde::Serialize {
+		self
+	}
+
+	fn to_sendable(&self) -> Box<dyn SerdeValue + Send + 'static> {
+		Box::new(self.snapshot())
+	}
+}
+
+impl Value for TelemetryFields {
+	fn serialize(
+		&self,
+		_record: &slog::Record,
+		key: slog::Key,
+		ser: &mut dyn slog::Serializer,
+	) -> slog::Result {
+		ser.emit_serde(key, self)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn serializes_the_fields_set() {
+		let fields = TelemetryFields::default();
+		let handle = fields.clone();
+		handle.set("parachains", 3);
+		handle.set("relay", "kusama");
+		handle.set("removed", true);
+		assert!(handle.remove("removed"));
+		assert!(!handle.remove("removed"));
+
+		let snapshot = fields.snapshot();
+		handle.set("parachains", 4);
+
+		assert_eq!(
+			serde_json::to_value(&snapshot).unwrap(),
+			serde_json::json!({ "parachains": 3, "relay": "kusama" }),
+		);
+		assert_eq!(fields.get("parachains"), Some(serde_json::json!(4)));
+	}
+}
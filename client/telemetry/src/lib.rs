@@ -34,6 +34,10 @@
 //!
 //! > **Note**: Cloning the [`Telemetry`] and polling from multiple clones has an unspecified behaviour.
 //!
+//! Every message carries the `schema_version` of the format of the messages, see
+//! [`TELEMETRY_SCHEMA_VERSION`]. Node implementations can attach their own fields to the periodic
+//! messages with [`TelemetryFields`].
+//!
 //! # Example
 //!
 //! ```no_run
@@ -46,6 +50,8 @@
 //! 	]).expect("Invalid URL or multiaddr provided"),
 //! 	// Can be used to pass an external implementation of WebSockets.
 //! 	wasm_external_transport: None,
+//! 	// Authentication towards private telemetry servers.
+//! 	auth: Default::default(),
 //! });
 //!
 //! // The `telemetry` object implements `Stream` and must be processed.
@@ -71,10 +77,17 @@ use wasm_timer::Instant;
 pub use libp2p::wasm_ext::ExtTransport;
 pub use slog_scope::with_logger;
 pub use slog;
+pub use fields::TelemetryFields;
 
 mod async_record;
+mod fields;
 mod worker;
 
+/// Version of the format of the telemetry messages, sent as `schema_version` with each of them.
+///
+/// Version 2 added the `schema_version` itself and the `custom` fields of the periodic messages.
+pub const TELEMETRY_SCHEMA_VERSION: u32 = 2;
+
 /// Configuration for telemetry.
 pub struct TelemetryConfig {
 	/// Collection of telemetry WebSocket servers with a corresponding verbosity level.
@@ -91,6 +104,36 @@ pub struct TelemetryConfig {
 	/// >                internal buffering going on. In the context of WebSockets, each `write`
 	/// >                must be one individual WebSockets frame.
 	pub wasm_external_transport: Option<wasm_ext::ExtTransport>,
+
+	/// Authentication towards private telemetry servers.
+	pub auth: TelemetryAuth,
+}
+
+/// Authentication towards private telemetry servers.
+#[derive(Debug, Clone, Default)]
+pub struct TelemetryAuth {
+	/// DER encoded certificates of additional certificate authorities to trust when connecting
+	/// to `wss` endpoints, e.g. for servers using self-signed certificates.
+	///
+	/// Like the rest of the authentication, only used by the native transport: the
+	/// `wasm_external_transport` handles TLS and the WebSocket handshake itself.
+	pub trusted_certificates: Vec<Vec<u8>>,
+
+	/// Certificate to present to the `wss` endpoints requiring mutual TLS.
+	pub client_certificate: Option<TelemetryClientCertificate>,
+
+	/// Bearer token to authenticate with at all endpoints, sent in the `Authorization` header
+	/// of the WebSocket handshake.
+	pub bearer_token: Option<String>,
+}
+
+/// Certificate of the node for mutual TLS with private telemetry servers.
+#[derive(Debug, Clone)]
+pub struct TelemetryClientCertificate {
+	/// DER encoded certificate chain, the certificate of the node first.
+	pub certificates: Vec<Vec<u8>>,
+	/// DER encoded PKCS#8 or RSA private key of the certificate of the node.
+	pub private_key: Vec<u8>,
 }
 
 /// List of telemetry servers we want to talk to. Contains the URL of the server, and the
@@ -121,6 +164,11 @@ impl TelemetryEndpoints {
 			.collect();
 		endpoints.map(Self)
 	}
+
+	/// Returns the addresses of the endpoints and the maximum verbosity of what is sent to them.
+	pub fn endpoints(&self) -> &[(Multiaddr, u8)] {
+		&self.0
+	}
 }
 
 /// Parses a WebSocket URL into a libp2p `Multiaddr`.
@@ -191,11 +239,18 @@ pub fn init_telemetry(config: TelemetryConfig) -> Telemetry {
 	let (sender, receiver) = mpsc::channel(16);
 	let guard = {
 		let logger = TelemetryDrain { sender: std::panic::AssertUnwindSafe(sender) };
-		let root = slog::Logger::root(slog::Drain::fuse(logger), slog::o!());
+		let root = slog::Logger::root(
+			slog::Drain::fuse(logger),
+			slog::o!("schema_version" => TELEMETRY_SCHEMA_VERSION),
+		);
 		slog_scope::set_global_logger(root)
 	};
 
-	let worker = match worker::TelemetryWorker::new(endpoints, wasm_external_transport) {
+	let worker = match worker::TelemetryWorker::new(
+		endpoints,
+		wasm_external_transport,
+		&config.auth,
+	) {
 		Ok(w) => Some(w),
 		Err(err) => {
 			error!(target: "telemetry", "Failed to initialize telemetry worker: {:?}", err);
@@ -34,7 +34,10 @@ use libp2p::{core::transport::OptionalTransport, Multiaddr, Transport, wasm_ext}
 use log::{trace, warn, error};
 use slog::Drain;
 use std::{io, pin::Pin, task::Context, task::Poll, time};
+use crate::TelemetryAuth;
 
+#[cfg(not(target_os = "unknown"))]
+mod auth;
 mod node;
 
 /// Timeout after which a connection attempt is considered failed. Includes the WebSocket HTTP
@@ -74,21 +77,32 @@ impl TelemetryWorker {
 	/// The endpoints must be a list of targets, plus a verbosity level. When you send a message
 	/// to the telemetry, only the targets whose verbosity is higher than the verbosity of the
 	/// message will receive it.
+	///
+	/// The native transport authenticates at the endpoints with `auth`.
 	pub fn new(
 		endpoints: impl IntoIterator<Item = (Multiaddr, u8)>,
-		wasm_external_transport: impl Into<Option<wasm_ext::ExtTransport>>
+		wasm_external_transport: impl Into<Option<wasm_ext::ExtTransport>>,
+		auth: &TelemetryAuth,
 	) -> Result<Self, io::Error> {
 		let transport = match wasm_external_transport.into() {
 			Some(t) => OptionalTransport::some(t),
 			None => OptionalTransport::none()
 		}.map((|inner, _| StreamSink::from(inner)) as fn(_, _) -> _);
 
+		#[cfg(target_os = "unknown")]
+		let _ = auth;
+		#[cfg(not(target_os = "unknown"))]
+		let (authentication, endpoints) = auth::Authentication::new(auth, endpoints)?;
+
 		// The main transport is the `wasm_external_transport`, but if we're on desktop we add
 		// support for TCP+WebSocket+DNS as a fallback. In practice, you're not expected to pass
 		// an external transport on desktop and the fallback is used all the time.
 		#[cfg(not(target_os = "unknown"))]
 		let transport = transport.or_transport({
-			let inner = libp2p::dns::DnsConfig::new(libp2p::tcp::TcpConfig::new())?;
+			let inner = libp2p::dns::DnsConfig::new(libp2p::tcp::TcpConfig::new())?
+				.and_then(move |stream, endpoint| {
+					authentication.clone().connect(stream, endpoint.get_remote_address().clone())
+				});
 			libp2p::websocket::framed::WsConfig::new(inner)
 				.and_then(|connec, _| {
 					let connec = connec
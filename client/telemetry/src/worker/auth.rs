@@ -0,0 +1,259 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2020 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Authentication at private telemetry servers over the native transport.
+//!
+//! The TLS of the `wss` endpoints is handled here rather than by the WebSocket transport, so
+//! that the client certificate can be presented and the bearer token can be sent in the
+//! `Authorization` header of the WebSocket handshake.
+
+use crate::TelemetryAuth;
+use async_tls::{client::TlsStream, TlsConnector};
+use futures::{future::Either, prelude::*, ready};
+use libp2p::{Multiaddr, multiaddr::Protocol};
+use rustls::{Certificate, ClientConfig, PrivateKey};
+use std::{collections::HashSet, io, pin::Pin, sync::Arc, task::{Context, Poll}};
+
+/// Authentication at the endpoints of the telemetry.
+#[derive(Clone)]
+pub(crate) struct Authentication {
+	/// Connector of the `wss` endpoints.
+	tls: TlsConnector,
+	/// TCP addresses of the `wss` endpoints.
+	tls_addresses: Arc<HashSet<Multiaddr>>,
+	/// Value of the `Authorization` header of the WebSocket handshakes.
+	authorization: Option<Arc<str>>,
+}
+
+impl Authentication {
+	/// Returns the authentication described by `auth`, and `endpoints` with their `wss`
+	/// endpoints turned into `ws` ones whose TLS is handled by the authentication.
+	pub(crate) fn new(
+		auth: &TelemetryAuth,
+		endpoints: impl IntoIterator<Item = (Multiaddr, u8)>,
+	) -> io::Result<(Self, Vec<(Multiaddr, u8)>)> {
+		let invalid_input = |e| io::Error::new(io::ErrorKind::InvalidInput, e);
+
+		let mut config = ClientConfig::new();
+		config.root_store.add_server_trust_anchors(&webpki_roots::TLS_SERVER_ROOTS);
+		for certificate in &auth.trusted_certificates {
+			config.root_store.add(&Certificate(certificate.clone()))
+				.map_err(|e| invalid_input(format!("Invalid trusted certificate: {:?}", e)))?;
+		}
+		if let Some(ref client) = auth.client_certificate {
+			config.set_single_client_cert(
+				client.certificates.iter().cloned().map(Certificate).collect(),
+				PrivateKey(client.private_key.clone()),
+			);
+		}
+
+		let authorization = match auth.bearer_token {
+			Some(ref token) if token.contains(|c: char| c.is_control()) =>
+				return Err(invalid_input("Invalid telemetry bearer token".into())),
+			Some(ref token) => Some(format!("Bearer {}", token).into()),
+			None => None,
+		};
+
+		let mut tls_addresses = HashSet::new();
+		let endpoints = endpoints.into_iter()
+			.map(|(addr, verbosity)| {
+				let mut tcp = addr.clone();
+				match tcp.pop() {
+					Some(Protocol::Wss(path)) => {
+						tls_addresses.insert(tcp.clone());
+						(tcp.with(Protocol::Ws(path)), verbosity)
+					},
+					_ => (addr, verbosity),
+				}
+			})
+			.collect();
+
+		let authentication = Authentication {
+			tls: TlsConnector::from(Arc::new(config)),
+			tls_addresses: Arc::new(tls_addresses),
+			authorization,
+		};
+		Ok((authentication, endpoints))
+	}
+
+	/// Authenticate over the TCP `stream` opened to `address`, before the WebSocket handshake.
+	pub(crate) async fn connect<S>(
+		self,
+		stream: S,
+		address: Multiaddr,
+	) -> io::Result<WithAuthorization<Either<TlsStream<S>, S>>>
+	where
+		S: AsyncRead + AsyncWrite + Unpin,
+	{
+		let stream = if self.tls_addresses.contains(&address) {
+			let host = match address.iter().next() {
+				Some(Protocol::Dns4(host)) | Some(Protocol::Dns6(host)) => host,
+				_ => return Err(io::Error::new(
+					io::ErrorKind::InvalidInput,
+					format!("No DNS name to verify the certificate of {}", address),
+				)),
+			};
+			Either::Left(self.tls.connect(&*host, stream).await?)
+		} else {
+			Either::Right(stream)
+		};
+		Ok(WithAuthorization::new(stream, self.authorization))
+	}
+}
+
+/// Stream inserting an `Authorization` header in the WebSocket handshake written to it.
+pub(crate) struct WithAuthorization<S> {
+	inner: S,
+	/// Value of the header, until it is inserted.
+	authorization: Option<Arc<str>>,
+	/// Beginning of the handshake, buffered until its end is written.
+	handshake: Vec<u8>,
+	/// Bytes to write to `inner` before anything else, and the number of them written.
+	pending: (Vec<u8>, usize),
+}
+
+impl<S> WithAuthorization<S> {
+	fn new(inner: S, authorization: Option<Arc<str>>) -> Self {
+		WithAuthorization {
+			inner,
+			authorization,
+			handshake: Vec::new(),
+			pending: (Vec::new(), 0),
+		}
+	}
+}
+
+impl<S: AsyncWrite + Unpin> WithAuthorization<S> {
+	fn poll_pending(&mut self, cx: &mut Context) -> Poll<io::Result<()>> {
+		let (pending, written) = &mut self.pending;
+		while *written < pending.len() {
+			match ready!(Pin::new(&mut self.inner).poll_write(cx, &pending[*written..]))? {
+				0 => return Poll::Ready(Err(io::ErrorKind::WriteZero.into())),
+				n => *written += n,
+			}
+		}
+		pending.clear();
+		*written = 0;
+		Poll::Ready(Ok(()))
+	}
+}
+
+impl<S: AsyncRead + Unpin> AsyncRead for WithAuthorization<S> {
+	fn poll_read(
+		mut self: Pin<&mut Self>,
+		cx: &mut Context,
+		buf: &mut [u8],
+	) -> Poll<io::Result<usize>> {
+		Pin::new(&mut self.inner).poll_read(cx, buf)
+	}
+}
+
+impl<S: AsyncWrite + Unpin> AsyncWrite for WithAuthorization<S> {
+	fn poll_write(
+		mut self: Pin<&mut Self>,
+		cx: &mut Context,
+		buf: &[u8],
+	) -> Poll<io::Result<usize>> {
+		let this = &mut *self;
+		ready!(this.poll_pending(cx))?;
+
+		let authorization = match this.authorization {
+			Some(ref authorization) => authorization.clone(),
+			None => return Pin::new(&mut this.inner).poll_write(cx, buf),
+		};
+		this.handshake.extend_from_slice(buf);
+		if let Some(end) = this.handshake.windows(4).position(|w| w == b"\r\n\r\n") {
+			// the header goes right before the empty line ending the handshake
+			let mut handshake = std::mem::replace(&mut this.handshake, Vec::new());
+			let rest = handshake.split_off(end + 2);
+			handshake.extend_from_slice(format!("Authorization: {}\r\n", authorization).as_bytes());
+			handshake.extend_from_slice(&rest);
+			this.pending = (handshake, 0);
+			this.authorization = None;
+		}
+		Poll::Ready(Ok(buf.len()))
+	}
+
+	fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<io::Result<()>> {
+		ready!(self.poll_pending(cx))?;
+		Pin::new(&mut self.inner).poll_flush(cx)
+	}
+
+	fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<io::Result<()>> {
+		ready!(self.poll_pending(cx))?;
+		Pin::new(&mut self.inner).poll_close(cx)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::TelemetryClientCertificate;
+
+	#[test]
+	fn authorization_is_sent_in_the_handshake() {
+		let mut stream = WithAuthorization::new(Vec::new(), Some("Bearer secret".into()));
+		futures::executor::block_on(async {
+			stream.write_all(b"GET /submit HTTP/1.1\r\nHost: telemetry").await.unwrap();
+			stream.write_all(b"\r\n\r\nframe").await.unwrap();
+			stream.write_all(b"frame").await.unwrap();
+			stream.flush().await.unwrap();
+		});
+
+		assert_eq!(
+			String::from_utf8(stream.inner).unwrap(),
+			"GET /submit HTTP/1.1\r\nHost: telemetry\r\nAuthorization: Bearer secret\r\n\r\n\
+			frameframe",
+		);
+	}
+
+	#[test]
+	fn tls_of_wss_endpoints_is_handled() {
+		let wss: Multiaddr = "/dns4/telemetry.io/tcp/443/wss/submit".parse().unwrap();
+		let ws: Multiaddr = "/dns4/localhost/tcp/8000/ws".parse().unwrap();
+		let auth = TelemetryAuth {
+			bearer_token: Some("secret".into()),
+			client_certificate: Some(TelemetryClientCertificate {
+				certificates: Vec::new(),
+				private_key: Vec::new(),
+			}),
+			..Default::default()
+		};
+
+		let (authentication, endpoints) = Authentication::new(
+			&auth,
+			vec![(wss, 1), (ws.clone(), 2)],
+		).unwrap();
+
+		assert_eq!(endpoints, vec![
+			("/dns4/telemetry.io/tcp/443/ws/submit".parse().unwrap(), 1),
+			(ws, 2),
+		]);
+		assert_eq!(
+			*authentication.tls_addresses,
+			std::iter::once("/dns4/telemetry.io/tcp/443".parse::<Multiaddr>().unwrap()).collect(),
+		);
+		assert_eq!(authentication.authorization.as_deref(), Some("Bearer secret"));
+	}
+
+	#[test]
+	fn tokens_with_line_breaks_are_rejected() {
+		let auth = TelemetryAuth { bearer_token: Some("secret\r\n".into()), ..Default::default() };
+		assert!(Authentication::new(&auth, Vec::new()).is_err());
+	}
+}
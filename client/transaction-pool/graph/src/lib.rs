@@ -41,5 +41,5 @@ pub use self::pool::{
 	Pool,
 	Options, ChainApi, EventStream, ExtrinsicFor,
 	BlockHash, ExHash, NumberFor, TransactionFor,
-	ValidatedTransaction,
+	ValidatedTransaction, PriorityPolicy,
 };
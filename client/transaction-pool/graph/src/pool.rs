@@ -19,12 +19,13 @@
 use std::{
 	hash,
 	collections::HashMap,
+	path::PathBuf,
 	sync::Arc,
 };
 
 use crate::base_pool as base;
 use crate::watcher::Watcher;
-use serde::Serialize;
+use serde::{Serialize, de::DeserializeOwned};
 
 use futures::{Future, FutureExt};
 use sp_runtime::{
@@ -32,6 +33,7 @@ use sp_runtime::{
 	traits::{self, SaturatedConversion},
 	transaction_validity::{
 		TransactionValidity, TransactionTag as Tag, TransactionValidityError, TransactionSource,
+		TransactionPriority,
 	},
 };
 use sp_transaction_pool::error;
@@ -66,7 +68,7 @@ pub trait ChainApi: Send + Sync {
 	/// Block type.
 	type Block: traits::Block;
 	/// Transaction Hash type
-	type Hash: hash::Hash + Eq + traits::Member + Serialize;
+	type Hash: hash::Hash + Eq + traits::Member + Serialize + DeserializeOwned;
 	/// Error type.
 	type Error: From<error::Error> + error::IntoPoolError;
 	/// Validate transaction future.
@@ -95,6 +97,18 @@ pub trait ChainApi: Send + Sync {
 	fn block_body(&self, at: &BlockId<Self::Block>) -> Self::BodyFuture;
 }
 
+/// Hook overriding the priority of the transactions entering the pool, e.g. to give precedence to
+/// the transactions of some accounts or to the local ones.
+pub trait PriorityPolicy<B: ChainApi>: Send + Sync {
+	/// Returns the priority of a transaction that has just been validated.
+	///
+	/// `transaction.priority` is the priority returned by the runtime.
+	fn priority(
+		&self,
+		transaction: &base::Transaction<ExHash<B>, ExtrinsicFor<B>>,
+	) -> TransactionPriority;
+}
+
 /// Pool configuration options.
 #[derive(Debug, Clone)]
 pub struct Options {
@@ -104,6 +118,9 @@ pub struct Options {
 	pub future: base::Limit,
 	/// Reject future transactions.
 	pub reject_future_transactions: bool,
+	/// File the banned transactions are saved to, so that they remain banned after a restart.
+	/// `None` if they aren't saved.
+	pub banned_transactions_path: Option<PathBuf>,
 }
 
 impl Default for Options {
@@ -118,6 +135,7 @@ impl Default for Options {
 				total_bytes: 1 * 1024 * 1024,
 			},
 			reject_future_transactions: false,
+			banned_transactions_path: None,
 		}
 	}
 }
@@ -156,6 +174,11 @@ impl<B: ChainApi> Pool<B> {
 	) -> Result<Vec<Result<ExHash<B>, B::Error>>, B::Error> where
 		T: IntoIterator<Item=ExtrinsicFor<B>>,
 	{
+		// the transactions retracted with their blocks are forced back, even into a paused pool.
+		if !force && self.validated_pool.is_paused() {
+			return Ok(xts.into_iter().map(|_| Err(error::Error::Paused.into())).collect());
+		}
+
 		let validated_pool = self.validated_pool.clone();
 		let xts = xts.into_iter().map(|xt| (source, xt));
 		self.verify(at, xts, force)
@@ -181,6 +204,23 @@ impl<B: ChainApi> Pool<B> {
 			.await
 	}
 
+	/// Imports one unverified extrinsic to the pool, never propagating it to other peers.
+	///
+	/// The extrinsic is validated as a local one.
+	pub async fn submit_local(
+		&self,
+		at: &BlockId<B::Block>,
+		xt: ExtrinsicFor<B>,
+	) -> Result<ExHash<B>, B::Error> {
+		let hash = self.hash_of(&xt);
+		self.validated_pool.mark_local_only(hash.clone());
+		let result = self.submit_one(at, TransactionSource::Local, xt).await;
+		if result.is_err() {
+			self.validated_pool.unmark_local_only(&hash);
+		}
+		result
+	}
+
 	/// Import a single extrinsic and starts to watch their progress in the pool.
 	pub async fn submit_and_watch(
 		&self,
@@ -188,6 +228,9 @@ impl<B: ChainApi> Pool<B> {
 		source: TransactionSource,
 		xt: ExtrinsicFor<B>,
 	) -> Result<Watcher<ExHash<B>, BlockHash<B>>, B::Error> {
+		if self.validated_pool.is_paused() {
+			return Err(error::Error::Paused.into());
+		}
 		let block_number = self.resolve_block_number(at)?;
 		let (_, tx) = self.verify_one(
 			at, block_number, source, xt, false
@@ -597,6 +640,175 @@ mod tests {
 		assert_matches!(res.unwrap_err(), error::Error::TemporarilyBanned);
 	}
 
+	#[test]
+	fn should_reject_while_paused() {
+		// given
+		let pool = pool();
+		let uxt = uxt(Transfer {
+			from: AccountId::from_h256(H256::from_low_u64_be(1)),
+			to: AccountId::from_h256(H256::from_low_u64_be(2)),
+			amount: 5,
+			nonce: 0,
+		});
+
+		// when
+		pool.validated_pool().set_paused(true);
+		let res = block_on(pool.submit_one(&BlockId::Number(0), SOURCE, uxt.clone()));
+
+		// then
+		assert_matches!(res.unwrap_err(), error::Error::Paused);
+		assert_eq!(pool.validated_pool().status().ready, 0);
+
+		pool.validated_pool().set_paused(false);
+		block_on(pool.submit_one(&BlockId::Number(0), SOURCE, uxt)).unwrap();
+		assert_eq!(pool.validated_pool().status().ready, 1);
+	}
+
+	#[test]
+	fn should_shrink_and_recover_limits() {
+		// given
+		let limit = Limit {
+			count: 4,
+			total_bytes: 1000,
+		};
+		let pool = Pool::new(Options {
+			ready: limit.clone(),
+			future: limit,
+			..Default::default()
+		}, TestApi::default().into());
+		for nonce in 0..4 {
+			block_on(pool.submit_one(&BlockId::Number(0), SOURCE, uxt(Transfer {
+				from: AccountId::from_h256(H256::from_low_u64_be(1)),
+				to: AccountId::from_h256(H256::from_low_u64_be(2)),
+				amount: 5,
+				nonce,
+			}))).unwrap();
+		}
+		assert_eq!(pool.validated_pool().status().ready, 4);
+
+		// when
+		pool.validated_pool().set_limits_percent(50);
+
+		// then the oldest transaction is dropped, with the ones depending on it, without being
+		// banned
+		assert_eq!(pool.validated_pool().status().ready, 0);
+		let hash = pool.hash_of(&uxt(Transfer {
+			from: AccountId::from_h256(H256::from_low_u64_be(1)),
+			to: AccountId::from_h256(H256::from_low_u64_be(2)),
+			amount: 5,
+			nonce: 0,
+		}));
+		assert!(!pool.validated_pool().is_banned(&hash));
+
+		// when the same transactions are submitted again
+		pool.validated_pool().set_limits_percent(100);
+		for nonce in 0..4 {
+			block_on(pool.submit_one(&BlockId::Number(0), SOURCE, uxt(Transfer {
+				from: AccountId::from_h256(H256::from_low_u64_be(1)),
+				to: AccountId::from_h256(H256::from_low_u64_be(2)),
+				amount: 5,
+				nonce,
+			}))).unwrap();
+		}
+
+		// then
+		assert_eq!(pool.validated_pool().status().ready, 4);
+	}
+
+	#[test]
+	fn should_not_propagate_local_only_transactions() {
+		// given
+		let pool = pool();
+
+		// when
+		let hash1 = block_on(pool.submit_local(&BlockId::Number(0), uxt(Transfer {
+			from: AccountId::from_h256(H256::from_low_u64_be(1)),
+			to: AccountId::from_h256(H256::from_low_u64_be(2)),
+			amount: 5,
+			nonce: 0,
+		}))).unwrap();
+		let hash2 = block_on(pool.submit_one(&BlockId::Number(0), SOURCE, uxt(Transfer {
+			from: AccountId::from_h256(H256::from_low_u64_be(1)),
+			to: AccountId::from_h256(H256::from_low_u64_be(2)),
+			amount: 5,
+			nonce: 1,
+		}))).unwrap();
+
+		// then
+		let ready = pool.validated_pool().ready()
+			.map(|tx| (tx.hash, tx.propagate))
+			.collect::<Vec<_>>();
+		assert_eq!(ready, vec![(hash1, false), (hash2, true)]);
+	}
+
+	#[test]
+	fn should_forget_local_only_mark_of_rejected_transactions() {
+		// given
+		let pool = pool();
+		let xt = uxt(Transfer {
+			from: AccountId::from_h256(H256::from_low_u64_be(1)),
+			to: AccountId::from_h256(H256::from_low_u64_be(2)),
+			amount: 5,
+			nonce: 0,
+		});
+
+		// when
+		// the transaction is stale at block 1
+		assert!(block_on(pool.submit_local(&BlockId::Number(1), xt.clone())).is_err());
+		let hash = block_on(pool.submit_one(&BlockId::Number(0), SOURCE, xt)).unwrap();
+
+		// then
+		let ready = pool.validated_pool().ready()
+			.map(|tx| (tx.hash, tx.propagate))
+			.collect::<Vec<_>>();
+		assert_eq!(ready, vec![(hash, true)]);
+	}
+
+	#[test]
+	fn should_apply_priority_policy() {
+		struct Boost(AccountId);
+		impl PriorityPolicy<TestApi> for Boost {
+			fn priority(
+				&self,
+				transaction: &base::Transaction<u64, Extrinsic>,
+			) -> TransactionPriority {
+				if transaction.data.transfer().from == self.0 {
+					transaction.priority + 10
+				} else {
+					transaction.priority
+				}
+			}
+		}
+
+		// given
+		let api = TestApi::default();
+		let pool = Pool::new(Default::default(), api.clone().into());
+		let boosted = AccountId::from_h256(H256::from_low_u64_be(2));
+		pool.validated_pool().set_priority_policy(Some(Arc::new(Boost(boosted.clone()))));
+		let uxt1 = uxt(Transfer {
+			from: AccountId::from_h256(H256::from_low_u64_be(1)),
+			to: AccountId::from_h256(H256::from_low_u64_be(3)),
+			amount: 5,
+			nonce: 0,
+		});
+		let uxt2 = uxt(Transfer {
+			from: boosted,
+			to: AccountId::from_h256(H256::from_low_u64_be(3)),
+			amount: 5,
+			nonce: 1,
+		});
+		api.clear_requirements.lock().insert(pool.hash_of(&uxt2));
+
+		// when
+		let hash1 = block_on(pool.submit_one(&BlockId::Number(0), SOURCE, uxt1)).unwrap();
+		let hash2 = block_on(pool.submit_one(&BlockId::Number(0), SOURCE, uxt2)).unwrap();
+
+		// then
+		let ready = pool.validated_pool().ready().collect::<Vec<_>>();
+		assert_eq!(ready.iter().map(|tx| tx.hash).collect::<Vec<_>>(), vec![hash2, hash1]);
+		assert_eq!(ready.iter().map(|tx| tx.priority).collect::<Vec<_>>(), vec![14, 4]);
+	}
+
 	#[test]
 	fn should_notify_about_pool_events() {
 		let stream = {
@@ -23,11 +23,15 @@
 
 use std::{
 	collections::HashMap,
+	fs,
 	hash,
+	io,
 	iter,
+	path::Path,
 	time::Duration,
 };
 use parking_lot::RwLock;
+use serde::{Serialize, de::DeserializeOwned};
 use wasm_timer::Instant;
 
 use crate::base_pool::Transaction;
@@ -63,10 +67,16 @@ impl<Hash: hash::Hash + Eq + Clone> PoolRotator<Hash> {
 
 	/// Bans given set of hashes.
 	pub fn ban(&self, now: &Instant, hashes: impl IntoIterator<Item=Hash>) {
+		let ban_time = self.ban_time;
+		self.ban_for(now, hashes.into_iter().map(|hash| (hash, ban_time)))
+	}
+
+	/// Bans given set of hashes, each for the given time but at most for the ban time.
+	pub fn ban_for(&self, now: &Instant, bans: impl IntoIterator<Item=(Hash, Duration)>) {
 		let mut banned = self.banned_until.write();
 
-		for hash in hashes {
-			banned.insert(hash, *now + self.ban_time);
+		for (hash, time) in bans {
+			banned.insert(hash, *now + time.min(self.ban_time));
 		}
 
 		if banned.len() > 2 * EXPECTED_SIZE {
@@ -97,6 +107,38 @@ impl<Hash: hash::Hash + Eq + Clone> PoolRotator<Hash> {
 
 		banned.retain(|_, &mut v| v >= *now);
 	}
+
+	/// Returns the currently banned hashes, with the time left until they are unbanned.
+	pub fn banned(&self, now: &Instant) -> Vec<(Hash, Duration)> {
+		self.banned_until.read()
+			.iter()
+			.filter(|(_, until)| **until > *now)
+			.map(|(hash, until)| (hash.clone(), *until - *now))
+			.collect()
+	}
+}
+
+impl<Hash: hash::Hash + Eq + Clone + Serialize + DeserializeOwned> PoolRotator<Hash> {
+	/// Bans the hashes saved to `path` by `save` for the time they had left, if the file exists.
+	pub fn load(&self, now: &Instant, path: &Path) -> io::Result<()> {
+		let bans: Vec<(Hash, Duration)> = match fs::read(path) {
+			Ok(bytes) => serde_json::from_slice(&bytes)?,
+			Err(ref e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+			Err(e) => return Err(e),
+		};
+
+		self.ban_for(now, bans);
+		Ok(())
+	}
+
+	/// Save the currently banned hashes to `path`, with the time they have left.
+	pub fn save(&self, now: &Instant, path: &Path) -> io::Result<()> {
+		let bans = serde_json::to_vec(&self.banned(now))?;
+		// Write to a temporary file first, so that the previous file isn't lost on failure.
+		let temp_path = path.with_extension("tmp");
+		fs::write(&temp_path, bans)?;
+		fs::rename(temp_path, path)
+	}
 }
 
 #[cfg(test)]
@@ -214,4 +256,34 @@ mod tests {
 		assert!(rotator.ban_if_stale(&now, past_block, &tx));
 		assert_eq!(rotator.banned_until.read().len(), EXPECTED_SIZE);
 	}
+
+	#[test]
+	fn should_save_and_load_bans_with_time_left() {
+		// given
+		let dir = tempfile::tempdir().unwrap();
+		let path = dir.path().join("banned.json");
+		let new_rotator = || PoolRotator { ban_time: Duration::from_secs(60), ..Default::default() };
+		let rotator = new_rotator();
+		let now = Instant::now();
+		rotator.ban(&now, vec![1, 2]);
+		rotator.ban_for(&now, vec![(3, Duration::from_secs(30)), (4, Duration::from_secs(120))]);
+
+		// when
+		let later = now + Duration::from_secs(45);
+		rotator.save(&later, &path).unwrap();
+		let loaded = new_rotator();
+		loaded.load(&later, &path).unwrap();
+
+		// then
+		let mut banned = loaded.banned(&later);
+		banned.sort();
+		let left = Duration::from_secs(15);
+		assert_eq!(banned, vec![(1, left), (2, left), (4, left)]);
+		assert!(!loaded.is_banned(&3));
+
+		// a missing file bans nothing
+		let empty = PoolRotator::<Hash>::default();
+		empty.load(&later, &dir.path().join("missing.json")).unwrap();
+		assert!(empty.banned(&later).is_empty());
+	}
 }
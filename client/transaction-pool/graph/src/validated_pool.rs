@@ -19,7 +19,8 @@
 use std::{
 	collections::{HashSet, HashMap},
 	hash,
-	sync::Arc,
+	sync::{Arc, atomic::{AtomicBool, AtomicUsize, Ordering}},
+	time::Duration,
 };
 
 use crate::{base_pool as base, BlockHash};
@@ -39,7 +40,12 @@ use wasm_timer::Instant;
 use sp_utils::mpsc::{tracing_unbounded, TracingUnboundedSender};
 
 use crate::base_pool::PruneStatus;
-use crate::pool::{EventStream, Options, ChainApi, ExHash, ExtrinsicFor, TransactionFor};
+use crate::pool::{
+	EventStream, Options, ChainApi, ExHash, ExtrinsicFor, TransactionFor, PriorityPolicy,
+};
+
+/// Minimal interval between two saves of the banned transactions.
+const SAVE_BANNED_INTERVAL: Duration = Duration::from_secs(60);
 
 /// Pre-validated transaction. Validated pool only accepts transactions wrapped in this enum.
 #[derive(Debug)]
@@ -98,6 +104,12 @@ pub struct ValidatedPool<B: ChainApi> {
 	>>,
 	import_notification_sinks: Mutex<Vec<TracingUnboundedSender<ExHash<B>>>>,
 	rotator: PoolRotator<ExHash<B>>,
+	banned_saved_at: Mutex<Instant>,
+	priority_policy: RwLock<Option<Arc<dyn PriorityPolicy<B>>>>,
+	local_only: Mutex<HashSet<ExHash<B>>>,
+	paused: AtomicBool,
+	// percentage of the configured limits that is enforced
+	limits_percent: AtomicUsize,
 }
 
 #[cfg(not(target_os = "unknown"))]
@@ -116,13 +128,122 @@ impl<B: ChainApi> ValidatedPool<B> {
 	/// Create a new transaction pool.
 	pub fn new(options: Options, api: Arc<B>) -> Self {
 		let base_pool = base::BasePool::new(options.reject_future_transactions);
+		let rotator = PoolRotator::default();
+		let now = Instant::now();
+		if let Some(ref path) = options.banned_transactions_path {
+			if let Err(e) = rotator.load(&now, path) {
+				log::warn!(
+					target: "txpool",
+					"Failed to load the banned transactions from {}: {}", path.display(), e,
+				);
+			}
+		}
+
 		ValidatedPool {
 			options,
 			listener: Default::default(),
 			api,
 			pool: RwLock::new(base_pool),
 			import_notification_sinks: Default::default(),
-			rotator: Default::default(),
+			rotator,
+			banned_saved_at: Mutex::new(now),
+			priority_policy: Default::default(),
+			local_only: Default::default(),
+			paused: AtomicBool::new(false),
+			limits_percent: AtomicUsize::new(100),
+		}
+	}
+
+	/// Stop or resume accepting new transactions.
+	pub fn set_paused(&self, paused: bool) {
+		if self.paused.swap(paused, Ordering::Relaxed) != paused {
+			log::info!(
+				target: "txpool",
+				"{} accepting new transactions", if paused { "Stopped" } else { "Resumed" },
+			);
+		}
+	}
+
+	/// Returns true if the pool doesn't accept new transactions.
+	pub fn is_paused(&self) -> bool {
+		self.paused.load(Ordering::Relaxed)
+	}
+
+	/// Enforce only `percent` of the configured count and size limits of the pool, at most 100.
+	///
+	/// The transactions exceeding the new limits are dropped right away, without banning them,
+	/// so that they can be submitted again once the limits are restored.
+	pub fn set_limits_percent(&self, percent: usize) {
+		let percent = percent.min(100);
+		let previous = self.limits_percent.swap(percent, Ordering::Relaxed);
+		if previous != percent {
+			log::info!(target: "txpool", "Enforcing {}% of the pool limits", percent);
+		}
+		if percent < previous {
+			self.enforce_limits(false);
+		}
+	}
+
+	/// Returns the limits currently enforced, after scaling the configured ones.
+	fn limits(&self) -> (base::Limit, base::Limit) {
+		let percent = self.limits_percent.load(Ordering::Relaxed);
+		let scale = |limit: &base::Limit| base::Limit {
+			count: limit.count.saturating_mul(percent) / 100,
+			total_bytes: limit.total_bytes.saturating_mul(percent) / 100,
+		};
+		(scale(&self.options.ready), scale(&self.options.future))
+	}
+
+	/// Sets the policy overriding the priority of the transactions validated from now on.
+	///
+	/// The transactions already in the pool keep their priority until they are revalidated.
+	pub fn set_priority_policy(&self, policy: Option<Arc<dyn PriorityPolicy<B>>>) {
+		*self.priority_policy.write() = policy;
+	}
+
+	/// Marks the transaction with given hash as never to be propagated to other peers, whatever
+	/// its validity says.
+	///
+	/// The mark is dropped once the transaction leaves the pool.
+	pub fn mark_local_only(&self, hash: ExHash<B>) {
+		self.local_only.lock().insert(hash);
+	}
+
+	/// Drops the local-only mark of the transaction with given hash, unless the transaction is
+	/// in the pool, e.g. because its submission failed.
+	pub fn unmark_local_only(&self, hash: &ExHash<B>) {
+		let pool = self.pool.read();
+		if pool.by_hashes(&[hash.clone()]).into_iter().all(|tx| tx.is_none()) {
+			self.local_only.lock().remove(hash);
+		}
+	}
+
+	/// Applies the priority policy and the local-only marks to a freshly validated transaction.
+	fn apply_overrides(&self, tx: ValidatedTransactionFor<B>) -> ValidatedTransactionFor<B> {
+		match tx {
+			ValidatedTransaction::Valid(mut tx) => {
+				if let Some(ref policy) = *self.priority_policy.read() {
+					tx.priority = policy.priority(&tx);
+				}
+				if self.local_only.lock().contains(&tx.hash) {
+					tx.propagate = false;
+				}
+				ValidatedTransaction::Valid(tx)
+			},
+			tx => tx,
+		}
+	}
+
+	/// Saves the banned transactions, if a file is configured for them.
+	fn save_banned(&self, now: &Instant) {
+		if let Some(ref path) = self.options.banned_transactions_path {
+			*self.banned_saved_at.lock() = *now;
+			if let Err(e) = self.rotator.save(now, path) {
+				log::warn!(
+					target: "txpool",
+					"Failed to save the banned transactions to {}: {}", path.display(), e,
+				);
+			}
 		}
 	}
 
@@ -146,7 +267,7 @@ impl<B: ChainApi> ValidatedPool<B> {
 
 		// only enforce limits if there is at least one imported transaction
 		let removed = if results.iter().any(|res| res.is_ok()) {
-			self.enforce_limits()
+			self.enforce_limits(true)
 		} else {
 			Default::default()
 		};
@@ -159,7 +280,7 @@ impl<B: ChainApi> ValidatedPool<B> {
 
 	/// Submit single pre-validated transaction to the pool.
 	fn submit_one(&self, tx: ValidatedTransactionFor<B>) -> Result<ExHash<B>, B::Error> {
-		match tx {
+		match self.apply_overrides(tx) {
 			ValidatedTransaction::Valid(tx) => {
 				let imported = self.pool.write().import(tx)?;
 
@@ -183,10 +304,11 @@ impl<B: ChainApi> ValidatedPool<B> {
 		}
 	}
 
-	fn enforce_limits(&self) -> HashSet<ExHash<B>> {
+	/// Drop the transactions exceeding the limits, banning them if `ban` is set.
+	fn enforce_limits(&self, ban: bool) -> HashSet<ExHash<B>> {
 		let status = self.pool.read().status();
-		let ready_limit = &self.options.ready;
-		let future_limit = &self.options.future;
+		let (ready_limit, future_limit) = self.limits();
+		let (ready_limit, future_limit) = (&ready_limit, &future_limit);
 
 		log::debug!(target: "txpool", "Pool Status: {:?}", status);
 		if ready_limit.is_exceeded(status.ready, status.ready_bytes)
@@ -204,8 +326,10 @@ impl<B: ChainApi> ValidatedPool<B> {
 				let mut pool = self.pool.write();
 				let removed = pool.enforce_limits(ready_limit, future_limit)
 					.into_iter().map(|x| x.hash.clone()).collect::<HashSet<_>>();
-				// ban all removed transactions
-				self.rotator.ban(&Instant::now(), removed.iter().map(|x| x.clone()));
+				if ban {
+					// ban all removed transactions
+					self.rotator.ban(&Instant::now(), removed.iter().map(|x| x.clone()));
+				}
 				removed
 			};
 			if !removed.is_empty() {
@@ -277,7 +401,7 @@ impl<B: ChainApi> ValidatedPool<B> {
 					let removed_hash = removed_tx.hash.clone();
 					let updated_transaction = updated_transactions.remove(&removed_hash);
 					let tx_to_resubmit = if let Some(updated_tx) = updated_transaction {
-						updated_tx
+						self.apply_overrides(updated_tx)
 					} else {
 						// in most cases we'll end up in successful `try_unwrap`, but if not
 						// we still need to reinsert transaction back to the pool => duplicate call
@@ -488,6 +612,22 @@ impl<B: ChainApi> ValidatedPool<B> {
 		self.remove_invalid(&futures_to_remove);
 		// clear banned transactions timeouts
 		self.rotator.clear_timeouts(&now);
+		// forget the local-only marks of the transactions that left the pool
+		{
+			let pool = self.pool.read();
+			let mut local_only = self.local_only.lock();
+			let hashes = local_only.iter().cloned().collect::<Vec<_>>();
+			for (hash, tx) in hashes.iter().zip(pool.by_hashes(&hashes)) {
+				if tx.is_none() {
+					local_only.remove(hash);
+				}
+			}
+		}
+
+		let banned_saved_at = *self.banned_saved_at.lock();
+		if now.duration_since(banned_saved_at) >= SAVE_BANNED_INTERVAL {
+			self.save_banned(&now);
+		}
 
 		Ok(())
 	}
@@ -573,6 +713,12 @@ impl<B: ChainApi> ValidatedPool<B> {
 	}
 }
 
+impl<B: ChainApi> Drop for ValidatedPool<B> {
+	fn drop(&mut self) {
+		self.save_banned(&Instant::now());
+	}
+}
+
 fn fire_events<H, B, Ex>(
 	listener: &mut Listener<H, B>,
 	imported: &base::Imported<H, Ex>,
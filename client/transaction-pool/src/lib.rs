@@ -34,7 +34,7 @@ pub mod testing;
 pub use sc_transaction_graph as txpool;
 pub use crate::api::{FullChainApi, LightChainApi};
 
-use std::{collections::HashMap, sync::Arc, pin::Pin};
+use std::{collections::{HashMap, HashSet}, sync::Arc, pin::Pin};
 use futures::{prelude::*, future::ready, channel::oneshot};
 use parking_lot::Mutex;
 
@@ -221,6 +221,15 @@ impl<PoolApi, Block> BasicPool<PoolApi, Block>
 	pub fn pool(&self) -> &Arc<sc_transaction_graph::Pool<PoolApi>> {
 		&self.pool
 	}
+
+	/// Sets the policy overriding the priority of the transactions validated from now on,
+	/// or removes it with `None`.
+	pub fn set_priority_policy(
+		&self,
+		policy: Option<Arc<dyn sc_transaction_graph::PriorityPolicy<PoolApi>>>,
+	) {
+		self.pool.validated_pool().set_priority_policy(policy)
+	}
 }
 
 impl<PoolApi, Block> TransactionPool for BasicPool<PoolApi, Block>
@@ -274,6 +283,24 @@ impl<PoolApi, Block> TransactionPool for BasicPool<PoolApi, Block>
 		}.boxed()
 	}
 
+	fn submit_local(
+		&self,
+		at: &BlockId<Self::Block>,
+		xt: TransactionFor<Self>,
+	) -> PoolFuture<TxHash<Self>, Self::Error> {
+		let pool = self.pool.clone();
+		let at = *at;
+
+		self.metrics.report(|metrics| metrics.validations_scheduled.inc());
+
+		let metrics = self.metrics.clone();
+		async move {
+			let res = pool.submit_local(&at, xt).await;
+			metrics.report(|metrics| metrics.validations_finished.inc());
+			res
+		}.boxed()
+	}
+
 	fn submit_and_watch(
 		&self,
 		at: &BlockId<Self::Block>,
@@ -323,6 +350,14 @@ impl<PoolApi, Block> TransactionPool for BasicPool<PoolApi, Block>
 		self.pool.validated_pool().ready_by_hash(hash)
 	}
 
+	fn set_paused(&self, paused: bool) {
+		self.pool.validated_pool().set_paused(paused)
+	}
+
+	fn set_limits_percent(&self, percent: usize) {
+		self.pool.validated_pool().set_limits_percent(percent)
+	}
+
 	fn ready_at(&self, at: NumberFor<Self::Block>) -> PolledIterator<PoolApi> {
 		if self.ready_poll.lock().updated_at() >= at {
 			let iterator: ReadyIteratorFor<PoolApi> = Box::new(self.pool.validated_pool().ready());
@@ -458,11 +493,15 @@ impl<PoolApi, Block> MaintainedTransactionPool for BasicPool<PoolApi, Block>
 				let retracted = retracted.clone();
 				let revalidation_queue = self.revalidation_queue.clone();
 				let ready_poll = self.ready_poll.clone();
+				let metrics = self.metrics.clone();
 
 				async move {
-					// We don't query block if we won't prune anything
-					if !pool.validated_pool().status().is_empty() {
-						let hashes = api.block_body(&id).await
+					let resubmit = next_action.resubmit && !retracted.is_empty();
+					let pool_is_empty = pool.validated_pool().status().is_empty();
+
+					// We don't query block if we won't prune or resubmit anything
+					let enacted_hashes = if !pool_is_empty || resubmit {
+						api.block_body(&id).await
 							.unwrap_or_else(|e| {
 								log::warn!("Prune known transactions: error request {:?}!", e);
 								None
@@ -470,9 +509,13 @@ impl<PoolApi, Block> MaintainedTransactionPool for BasicPool<PoolApi, Block>
 							.unwrap_or_default()
 							.into_iter()
 							.map(|tx| pool.hash_of(&tx))
-							.collect::<Vec<_>>();
+							.collect::<Vec<_>>()
+					} else {
+						Vec::new()
+					};
 
-						if let Err(e) = pool.prune_known(&id, &hashes) {
+					if !pool_is_empty {
+						if let Err(e) = pool.prune_known(&id, &enacted_hashes) {
 							log::error!("Cannot prune known in the pool {:?}!", e);
 						}
 					}
@@ -481,13 +524,18 @@ impl<PoolApi, Block> MaintainedTransactionPool for BasicPool<PoolApi, Block>
 					// After #5200 lands, this arguably might be moved to the handler of "all blocks notification".
 					ready_poll.lock().trigger(block_number, move || Box::new(extra_pool.validated_pool().ready()));
 
-					if next_action.resubmit {
+					for retracted_hash in &retracted {
+						// notify txs awaiting finality that it has been retracted
+						pool.validated_pool().on_block_retracted(retracted_hash.clone());
+					}
+
+					if resubmit {
 						let mut resubmit_transactions = Vec::new();
+						// Transactions included again in the new block, or in several retracted
+						// blocks, are only considered once.
+						let mut known_hashes = enacted_hashes.into_iter().collect::<HashSet<_>>();
 
 						for retracted_hash in retracted {
-							// notify txs awaiting finality that it has been retracted
-							pool.validated_pool().on_block_retracted(retracted_hash.clone());
-
 							let block_transactions = api.block_body(&BlockId::hash(retracted_hash.clone())).await
 								.unwrap_or_else(|e| {
 									log::warn!("Failed to fetch block body {:?}!", e);
@@ -495,10 +543,22 @@ impl<PoolApi, Block> MaintainedTransactionPool for BasicPool<PoolApi, Block>
 								})
 								.unwrap_or_default()
 								.into_iter()
-								.filter(|tx| tx.is_signed().unwrap_or(true));
+								.filter(|tx| tx.is_signed().unwrap_or(true))
+								.filter(|tx| known_hashes.insert(pool.hash_of(tx)));
 
 							resubmit_transactions.extend(block_transactions);
 						}
+
+						log::debug!(
+							target: "txpool",
+							"[{:?}] Re-submitting {} transactions from retracted blocks",
+							id,
+							resubmit_transactions.len(),
+						);
+						metrics.report(|metrics| metrics
+							.block_transactions_resubmitted
+							.inc_by(resubmit_transactions.len() as u64)
+						);
 						if let Err(e) = pool.submit_at(
 							&id,
 							// These transactions are coming from retracted blocks, we should
@@ -48,6 +48,7 @@ pub struct Metrics {
 	pub validations_scheduled: Counter<U64>,
 	pub validations_finished: Counter<U64>,
 	pub validations_invalid: Counter<U64>,
+	pub block_transactions_resubmitted: Counter<U64>,
 }
 
 impl Metrics {
@@ -74,6 +75,13 @@ impl Metrics {
 				)?,
 				registry,
 			)?,
+			block_transactions_resubmitted: register(
+				Counter::new(
+					"sub_txpool_block_transactions_resubmitted",
+					"Total number of transactions resubmitted from retracted blocks",
+				)?,
+				registry,
+			)?,
 		})
 	}
 }
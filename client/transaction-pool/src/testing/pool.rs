@@ -248,6 +248,26 @@ fn should_resubmit_from_retracted_during_maintenance() {
 	assert_eq!(pool.status().ready, 1);
 }
 
+#[test]
+fn should_not_resubmit_transactions_included_in_new_block() {
+	let xt1 = uxt(Alice, 209);
+	let xt2 = uxt(Bob, 0);
+	let retracted_hash = Hash::random();
+	let other_retracted_hash = Hash::random();
+
+	let (pool, _guard, _notifier) = maintained_pool();
+
+	pool.api.push_block(1, vec![xt1.clone()]);
+	pool.api.push_fork_block(retracted_hash, vec![xt1.clone(), xt2.clone()]);
+	pool.api.push_fork_block(other_retracted_hash, vec![xt2.clone()]);
+
+	let event = block_event_with_retracted(1, vec![retracted_hash, other_retracted_hash]);
+
+	block_on(pool.maintain(event));
+	assert_eq!(pool.status().ready, 1);
+	assert_eq!(pool.api.validation_requests(), vec![xt2]);
+}
+
 #[test]
 fn should_not_retain_invalid_hashes_from_retracted() {
 	let xt = uxt(Alice, 209);
@@ -233,10 +233,7 @@ where
 		digest: &Digest<System::Hash>,
 	) {
 		if Self::runtime_upgraded() {
-			// System is not part of `AllModules`, so we need to call this manually.
-			let mut weight = <frame_system::Module::<System> as OnRuntimeUpgrade>::on_runtime_upgrade();
-			weight = weight.saturating_add(COnRuntimeUpgrade::on_runtime_upgrade());
-			weight = weight.saturating_add(<AllModules as OnRuntimeUpgrade>::on_runtime_upgrade());
+			let weight = Self::on_runtime_upgrade();
 			<frame_system::Module<System>>::register_extra_weight_unchecked(weight, DispatchClass::Mandatory);
 		}
 		<frame_system::Module<System>>::initialize(
@@ -254,6 +251,17 @@ where
 		frame_system::Module::<System>::note_finished_initialize();
 	}
 
+	/// Execute the runtime upgrade hooks of all modules and return the weight they consumed.
+	///
+	/// This is done by `initialize_block` when the runtime was upgraded. Calling it directly allows
+	/// to dry-run the migrations of a runtime, e.g. through the `TryRuntime` runtime api.
+	pub fn on_runtime_upgrade() -> frame_support::weights::Weight {
+		// System is not part of `AllModules`, so we need to call this manually.
+		let mut weight = <frame_system::Module::<System> as OnRuntimeUpgrade>::on_runtime_upgrade();
+		weight = weight.saturating_add(COnRuntimeUpgrade::on_runtime_upgrade());
+		weight.saturating_add(<AllModules as OnRuntimeUpgrade>::on_runtime_upgrade())
+	}
+
 	/// Returns if the runtime was upgraded since the last time this function was called.
 	fn runtime_upgraded() -> bool {
 		let last = frame_system::LastRuntimeUpgrade::get();
@@ -1028,4 +1036,17 @@ mod tests {
 			assert_eq!(sp_io::storage::get(CUSTOM_ON_RUNTIME_KEY).unwrap(), true.encode());
 		});
 	}
+
+	#[test]
+	fn on_runtime_upgrade_runs_without_version_change() {
+		new_test_ext(1).execute_with(|| {
+			assert!(Executive::runtime_upgraded());
+			assert!(!Executive::runtime_upgraded());
+
+			Executive::on_runtime_upgrade();
+
+			assert_eq!(&sp_io::storage::get(TEST_KEY).unwrap()[..], *b"module");
+			assert_eq!(sp_io::storage::get(CUSTOM_ON_RUNTIME_KEY).unwrap(), true.encode());
+		});
+	}
 }
@@ -44,6 +44,49 @@ pub struct RuntimeDispatchInfo<Balance> {
 	pub partial_fee: Balance,
 }
 
+/// The parts of the fee of a dispatchable, as they would be charged at the queried block.
+#[derive(Eq, PartialEq, Encode, Decode, Default)]
+#[cfg_attr(feature = "std", derive(Debug, Serialize, Deserialize))]
+#[cfg_attr(feature = "std", serde(rename_all = "camelCase"))]
+#[cfg_attr(feature = "std", serde(bound(serialize = "Balance: std::fmt::Display")))]
+#[cfg_attr(feature = "std", serde(bound(deserialize = "Balance: std::str::FromStr")))]
+pub struct FeeDetails<Balance> {
+	/// Weight of this dispatch.
+	pub weight: Weight,
+	/// Class of this dispatch.
+	pub class: DispatchClass,
+	/// The parts of the inclusion fee of this dispatch, `None` if it doesn't pay fees. As for
+	/// `RuntimeDispatchInfo::partial_fee`, a tip isn't included.
+	pub inclusion_fee: Option<InclusionFee<Balance>>,
+}
+
+/// The parts of the fee that a transaction has to pay to be included in a block.
+///
+/// The inclusion fee is `base_fee + adjusted_fee`.
+#[derive(Eq, PartialEq, Encode, Decode, Default)]
+#[cfg_attr(feature = "std", derive(Debug, Serialize, Deserialize))]
+#[cfg_attr(feature = "std", serde(rename_all = "camelCase"))]
+#[cfg_attr(feature = "std", serde(bound(serialize = "Balance: std::fmt::Display")))]
+#[cfg_attr(feature = "std", serde(bound(deserialize = "Balance: std::str::FromStr")))]
+pub struct InclusionFee<Balance> {
+	/// The fee of the extrinsic base weight, paid by every transaction.
+	#[cfg_attr(feature = "std", serde(serialize_with = "serialize_as_string"))]
+	#[cfg_attr(feature = "std", serde(deserialize_with = "deserialize_from_string"))]
+	pub base_fee: Balance,
+	/// The fee of the encoded length of the transaction, before the fee adjustment.
+	#[cfg_attr(feature = "std", serde(serialize_with = "serialize_as_string"))]
+	#[cfg_attr(feature = "std", serde(deserialize_with = "deserialize_from_string"))]
+	pub len_fee: Balance,
+	/// The fee of the weight of the dispatch, before the fee adjustment.
+	#[cfg_attr(feature = "std", serde(serialize_with = "serialize_as_string"))]
+	#[cfg_attr(feature = "std", serde(deserialize_with = "deserialize_from_string"))]
+	pub weight_fee: Balance,
+	/// The length and weight fees, adjusted by the fee multiplier of the block.
+	#[cfg_attr(feature = "std", serde(serialize_with = "serialize_as_string"))]
+	#[cfg_attr(feature = "std", serde(deserialize_with = "deserialize_from_string"))]
+	pub adjusted_fee: Balance,
+}
+
 #[cfg(feature = "std")]
 fn serialize_as_string<S: Serializer, T: std::fmt::Display>(t: &T, serializer: S) -> Result<S::Ok, S::Error> {
 	serializer.serialize_str(&t.to_string())
@@ -56,11 +99,16 @@ fn deserialize_from_string<'de, D: Deserializer<'de>, T: std::str::FromStr>(dese
 }
 
 sp_api::decl_runtime_apis! {
+	#[api_version(2)]
 	pub trait TransactionPaymentApi<Balance, Extrinsic> where
 		Balance: Codec + MaybeDisplay + MaybeFromStr,
 		Extrinsic: Codec,
 	{
 		fn query_info(uxt: Extrinsic, len: u32) -> RuntimeDispatchInfo<Balance>;
+		/// Query the parts of the fee of `uxt`, whose encoded length is `len`.
+		///
+		/// Available since version 2 of the API.
+		fn query_fee_details(uxt: Extrinsic, len: u32) -> FeeDetails<Balance>;
 	}
 }
 
@@ -101,4 +149,30 @@ mod tests {
 		// should not panic
 		serde_json::to_value(&info).unwrap();
 	}
+
+	#[test]
+	fn should_serialize_and_deserialize_fee_details() {
+		let details = FeeDetails {
+			weight: 5,
+			class: DispatchClass::Normal,
+			inclusion_fee: Some(InclusionFee {
+				base_fee: 1u128,
+				len_fee: 2,
+				weight_fee: 3,
+				adjusted_fee: u128::max_value(),
+			}),
+		};
+
+		let json_str = concat!(
+			r#"{"weight":5,"class":"normal","inclusionFee":{"baseFee":"1","lenFee":"2","#,
+			r#""weightFee":"3","adjustedFee":"340282366920938463463374607431768211455"}}"#,
+		);
+
+		assert_eq!(serde_json::to_string(&details).unwrap(), json_str);
+		assert_eq!(serde_json::from_str::<FeeDetails<u128>>(json_str).unwrap(), details);
+
+		let details = FeeDetails::<u128> { inclusion_fee: None, ..details };
+		let json_str = r#"{"weight":5,"class":"normal","inclusionFee":null}"#;
+		assert_eq!(serde_json::to_string(&details).unwrap(), json_str);
+	}
 }
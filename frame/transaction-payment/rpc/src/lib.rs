@@ -23,20 +23,29 @@ use sp_blockchain::HeaderBackend;
 use jsonrpc_core::{Error as RpcError, ErrorCode, Result};
 use jsonrpc_derive::rpc;
 use sp_runtime::{generic::BlockId, traits::{Block as BlockT, MaybeDisplay, MaybeFromStr}};
-use sp_api::ProvideRuntimeApi;
+use sp_api::{ApiExt, ProvideRuntimeApi};
 use sp_core::Bytes;
-use pallet_transaction_payment_rpc_runtime_api::RuntimeDispatchInfo;
+use pallet_transaction_payment_rpc_runtime_api::{FeeDetails, RuntimeDispatchInfo};
 pub use pallet_transaction_payment_rpc_runtime_api::TransactionPaymentApi as TransactionPaymentRuntimeApi;
 pub use self::gen_client::Client as TransactionPaymentClient;
 
 #[rpc]
-pub trait TransactionPaymentApi<BlockHash, ResponseType> {
+pub trait TransactionPaymentApi<BlockHash, ResponseType, FeeDetailsType> {
 	#[rpc(name = "payment_queryInfo")]
 	fn query_info(
 		&self,
 		encoded_xt: Bytes,
 		at: Option<BlockHash>
 	) -> Result<ResponseType>;
+
+	/// Returns the parts of the fee of the encoded extrinsic, as they would be charged at the
+	/// given block, or the best block if none is given.
+	#[rpc(name = "payment_queryFeeDetails")]
+	fn query_fee_details(
+		&self,
+		encoded_xt: Bytes,
+		at: Option<BlockHash>
+	) -> Result<FeeDetailsType>;
 }
 
 /// A struct that implements the [`TransactionPaymentApi`].
@@ -58,6 +67,8 @@ pub enum Error {
 	DecodeError,
 	/// The call to runtime failed.
 	RuntimeError,
+	/// The runtime doesn't support the call.
+	Unsupported,
 }
 
 impl From<Error> for i64 {
@@ -65,12 +76,16 @@ impl From<Error> for i64 {
 		match e {
 			Error::RuntimeError => 1,
 			Error::DecodeError => 2,
+			Error::Unsupported => 3,
 		}
 	}
 }
 
-impl<C, Block, Balance, Extrinsic> TransactionPaymentApi<<Block as BlockT>::Hash, RuntimeDispatchInfo<Balance>>
-	for TransactionPayment<C, (Block, Extrinsic)>
+impl<C, Block, Balance, Extrinsic> TransactionPaymentApi<
+	<Block as BlockT>::Hash,
+	RuntimeDispatchInfo<Balance>,
+	FeeDetails<Balance>,
+> for TransactionPayment<C, (Block, Extrinsic)>
 where
 	Block: BlockT,
 	C: Send + Sync + 'static + ProvideRuntimeApi<Block> + HeaderBackend<Block>,
@@ -102,4 +117,40 @@ where
 			data: Some(format!("{:?}", e).into()),
 		})
 	}
+
+	fn query_fee_details(
+		&self,
+		encoded_xt: Bytes,
+		at: Option<<Block as BlockT>::Hash>
+	) -> Result<FeeDetails<Balance>> {
+		let api = self.client.runtime_api();
+		let at = BlockId::hash(at.unwrap_or_else(||
+			// If the block hash is not supplied assume the best block.
+			self.client.info().best_hash
+		));
+
+		let has_fee_details = api.has_api_with::<
+			dyn TransactionPaymentRuntimeApi<Block, Balance, Extrinsic, Error = ()>, _
+		>(&at, |v| v >= 2).unwrap_or_default();
+		if !has_fee_details {
+			return Err(RpcError {
+				code: ErrorCode::ServerError(Error::Unsupported.into()),
+				message: "Unable to query fee details.".into(),
+				data: Some("The runtime doesn't provide the fee details".into()),
+			});
+		}
+
+		let encoded_len = encoded_xt.len() as u32;
+
+		let uxt: Extrinsic = Decode::decode(&mut &*encoded_xt).map_err(|e| RpcError {
+			code: ErrorCode::ServerError(Error::DecodeError.into()),
+			message: "Unable to query fee details.".into(),
+			data: Some(format!("{:?}", e).into()),
+		})?;
+		api.query_fee_details(&at, uxt, encoded_len).map_err(|e| RpcError {
+			code: ErrorCode::ServerError(Error::RuntimeError.into()),
+			message: "Unable to query fee details.".into(),
+			data: Some(format!("{:?}", e).into()),
+		})
+	}
 }
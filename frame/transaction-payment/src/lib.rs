@@ -54,7 +54,7 @@ use sp_runtime::{
 		DispatchInfoOf, PostDispatchInfoOf, UniqueSaturatedFrom, UniqueSaturatedInto,
 	},
 };
-use pallet_transaction_payment_rpc_runtime_api::RuntimeDispatchInfo;
+use pallet_transaction_payment_rpc_runtime_api::{FeeDetails, InclusionFee, RuntimeDispatchInfo};
 
 type Multiplier = Fixed128;
 type BalanceOf<T> =
@@ -137,6 +137,29 @@ impl<T: Trait> Module<T> where
 		RuntimeDispatchInfo { weight, class, partial_fee }
 	}
 
+	/// Query the parts of the fee of a given `call`.
+	///
+	/// As for [`query_info`](Self::query_info), the signed extensions, and so the tip, aren't
+	/// taken into account.
+	pub fn query_fee_details<Extrinsic: GetDispatchInfo>(
+		unchecked_extrinsic: Extrinsic,
+		len: u32,
+	) -> FeeDetails<BalanceOf<T>>
+	where
+		T::Call: Dispatchable<Info=DispatchInfo>,
+	{
+		let DispatchInfo { weight, class, pays_fee } =
+			<Extrinsic as GetDispatchInfo>::get_dispatch_info(&unchecked_extrinsic);
+
+		let inclusion_fee = if pays_fee == Pays::Yes {
+			Some(Self::compute_inclusion_fee(len, weight))
+		} else {
+			None
+		};
+
+		FeeDetails { weight, class, inclusion_fee }
+	}
+
 	/// Compute the final fee value for a particular transaction.
 	///
 	/// The final fee is composed of:
@@ -190,22 +213,28 @@ impl<T: Trait> Module<T> where
 		pays_fee: Pays,
 	) -> BalanceOf<T> {
 		if pays_fee == Pays::Yes {
-			let len = <BalanceOf<T>>::from(len);
-			let per_byte = T::TransactionByteFee::get();
-			let len_fee = per_byte.saturating_mul(len);
-			let unadjusted_weight_fee = Self::weight_to_fee(weight);
-
-			// the adjustable part of the fee
-			let adjustable_fee = len_fee.saturating_add(unadjusted_weight_fee);
-			let targeted_fee_adjustment = NextFeeMultiplier::get();
-			let adjusted_fee = targeted_fee_adjustment.saturating_mul_acc_int(adjustable_fee);
-
-			let base_fee = Self::weight_to_fee(T::ExtrinsicBaseWeight::get());
+			let InclusionFee { base_fee, adjusted_fee, .. } =
+				Self::compute_inclusion_fee(len, weight);
 			base_fee.saturating_add(adjusted_fee).saturating_add(tip)
 		} else {
 			tip
 		}
 	}
+
+	fn compute_inclusion_fee(len: u32, weight: Weight) -> InclusionFee<BalanceOf<T>> {
+		let len = <BalanceOf<T>>::from(len);
+		let per_byte = T::TransactionByteFee::get();
+		let len_fee = per_byte.saturating_mul(len);
+		let weight_fee = Self::weight_to_fee(weight);
+
+		// the adjustable part of the fee
+		let adjustable_fee = len_fee.saturating_add(weight_fee);
+		let targeted_fee_adjustment = NextFeeMultiplier::get();
+		let adjusted_fee = targeted_fee_adjustment.saturating_mul_acc_int(adjustable_fee);
+
+		let base_fee = Self::weight_to_fee(T::ExtrinsicBaseWeight::get());
+		InclusionFee { base_fee, len_fee, weight_fee, adjusted_fee }
+	}
 }
 
 impl<T: Trait> Module<T> {
@@ -752,6 +781,48 @@ mod tests {
 		});
 	}
 
+	#[test]
+	fn query_fee_details_works() {
+		let call = Call::Balances(BalancesCall::transfer(2, 69));
+		let origin = 111111;
+		let extra = ();
+		let xt = TestXt::new(call, Some((origin, extra)));
+		let info  = xt.get_dispatch_info();
+		let ext = xt.encode();
+		let len = ext.len() as u32;
+		ExtBuilder::default()
+			.base_weight(5)
+			.weight_fee(2)
+			.build()
+			.execute_with(||
+		{
+			// all fees should be x1.5
+			NextFeeMultiplier::put(Fixed128::saturating_from_rational(1, 2));
+
+			let weight_fee = info.weight.min(MaximumBlockWeight::get()) as u64 * 2;
+			let details = TransactionPayment::query_fee_details(xt.clone(), len);
+			assert_eq!(
+				details,
+				FeeDetails {
+					weight: info.weight,
+					class: info.class,
+					inclusion_fee: Some(InclusionFee {
+						base_fee: 5 * 2,
+						len_fee: len as u64,
+						weight_fee,
+						adjusted_fee: (len as u64 + weight_fee) * 3 / 2,
+					}),
+				},
+			);
+
+			let inclusion_fee = details.inclusion_fee.unwrap();
+			assert_eq!(
+				inclusion_fee.base_fee + inclusion_fee.adjusted_fee,
+				TransactionPayment::query_info(xt, len).partial_fee,
+			);
+		});
+	}
+
 	#[test]
 	fn compute_fee_works_without_multiplier() {
 		ExtBuilder::default()
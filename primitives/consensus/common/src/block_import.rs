@@ -18,7 +18,7 @@
 //! Block import helpers.
 
 use sp_runtime::traits::{Block as BlockT, DigestItemFor, Header as HeaderT, NumberFor, HashFor};
-use sp_runtime::Justification;
+use sp_runtime::{ConsensusEngineId, Justification};
 use serde::{Serialize, Deserialize};
 use std::borrow::Cow;
 use std::collections::HashMap;
@@ -346,6 +346,43 @@ pub trait JustificationImport<B: BlockT> {
 	) -> Result<(), Self::Error>;
 }
 
+/// Checks the justifications of a chain of blocks before the blocks are imported, e.g. the blocks
+/// read from a file.
+pub trait JustificationCheck<B: BlockT> {
+	/// Check the block with `header`, and its `justification` if it has one.
+	///
+	/// The blocks of the chain are passed one after the other, starting with a child of a known
+	/// block, so that the changes of the authorities signaled by the blocks can be followed.
+	fn check_block(
+		&mut self,
+		header: &B::Header,
+		justification: Option<&Justification>,
+	) -> Result<(), String>;
+}
+
+/// Replays the consensus inputs recorded by a node against the blocks it imported, e.g. to debug
+/// a finality stall offline.
+///
+/// The inputs are passed in the order they were recorded in, with the number of milliseconds
+/// elapsed since the recording started. The timers of the replay are driven by these times
+/// rather than by the wall clock, so that replaying the same inputs always has the same outcome.
+/// Each call returns what happened, with the time it happened at.
+pub trait ConsensusReplay<B: BlockT> {
+	/// Advance the clock of the replay to `elapsed`, firing the timers due until then.
+	fn advance(&mut self, elapsed: u64) -> Vec<(u64, String)>;
+
+	/// Handle the finalization of the block `hash`, recorded at `elapsed`.
+	fn finalized(&mut self, elapsed: u64, hash: B::Hash) -> Vec<(u64, String)>;
+
+	/// Handle the gossip `message` of `engine_id`, received at `elapsed`.
+	fn gossip(
+		&mut self,
+		elapsed: u64,
+		engine_id: ConsensusEngineId,
+		message: &[u8],
+	) -> Vec<(u64, String)>;
+}
+
 /// Finality proof import trait.
 pub trait FinalityProofImport<B: BlockT> {
 	type Error: std::error::Error + Send + 'static;
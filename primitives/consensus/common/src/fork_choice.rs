@@ -0,0 +1,49 @@
+// Copyright 2019-2020 Parity Technologies (UK) Ltd.
+// This file is part of Substrate Consensus Common.
+
+// Substrate Demo is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate Consensus Common is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate Consensus Common.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Rules choosing the best block among the imported ones.
+
+use crate::error::Error;
+use sp_runtime::traits::{Block as BlockT, Header as HeaderT};
+
+/// Rule deciding whether a newly imported block becomes the best block.
+///
+/// The client consults it for the blocks imported with `ForkChoiceStrategy::LongestChain`, so
+/// that chains with a custom fork-choice, e.g. following an external finality gadget, can select
+/// their best block without wrapping the block import of every consensus engine. Blocks imported
+/// with `ForkChoiceStrategy::Custom` keep the choice made by their consensus engine.
+pub trait ForkChoiceRule<Block: BlockT>: Send + Sync {
+	/// Returns whether `imported` replaces `best` as the best block.
+	fn is_new_best(&self, best: &Block::Header, imported: &Block::Header) -> Result<bool, Error>;
+}
+
+impl<Block: BlockT, F> ForkChoiceRule<Block> for F where
+	F: Fn(&Block::Header, &Block::Header) -> Result<bool, Error> + Send + Sync,
+{
+	fn is_new_best(&self, best: &Block::Header, imported: &Block::Header) -> Result<bool, Error> {
+		(self)(best, imported)
+	}
+}
+
+/// The longest chain rule: a block becomes the best block if it's higher than the current one.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct LongestChainRule;
+
+impl<Block: BlockT> ForkChoiceRule<Block> for LongestChainRule {
+	fn is_new_best(&self, best: &Block::Header, imported: &Block::Header) -> Result<bool, Error> {
+		Ok(imported.number() > best.number())
+	}
+}
@@ -16,10 +16,14 @@
 // limitations under the License.
 
 use std::{mem, pin::Pin, time::Duration, marker::PhantomData};
+use codec::Encode;
 use futures::{prelude::*, task::Context, task::Poll};
 use futures_timer::Delay;
 use sp_runtime::{Justification, traits::{Block as BlockT, Header as HeaderT, NumberFor}};
-use sp_utils::mpsc::{TracingUnboundedSender, tracing_unbounded};
+use sp_utils::{
+	memory::{self, MemoryCharge, Subsystem},
+	mpsc::{TracingUnboundedSender, tracing_unbounded},
+};
 use prometheus_endpoint::Registry;
 
 use crate::block_import::BlockOrigin;
@@ -94,7 +98,8 @@ impl<B: BlockT, Transaction: Send> ImportQueue<B> for BasicQueue<B, Transaction>
 		}
 
 		trace!(target: "sync", "Scheduling {} blocks for import", blocks.len());
-		let _ = self.sender.unbounded_send(ToWorkerMsg::ImportBlocks(origin, blocks));
+		let charge = memory::charge(Subsystem::ImportQueue, incoming_blocks_size(&blocks));
+		let _ = self.sender.unbounded_send(ToWorkerMsg::ImportBlocks(origin, blocks, charge));
 	}
 
 	fn import_justification(
@@ -129,10 +134,22 @@ impl<B: BlockT, Transaction: Send> ImportQueue<B> for BasicQueue<B, Transaction>
 	}
 }
 
+/// Returns the encoded size of the given blocks, used to account them to the import queue.
+fn incoming_blocks_size<B: BlockT>(blocks: &[IncomingBlock<B>]) -> u64 {
+	blocks.iter()
+		.map(|block| {
+			block.header.as_ref().map_or(0, |header| header.encoded_size()) +
+				block.body.as_ref().map_or(0, |body| body.encoded_size()) +
+				block.justification.as_ref().map_or(0, |justification| justification.len())
+		})
+		.sum::<usize>() as u64
+}
+
 /// Message destinated to the background worker.
 #[derive(Debug)]
 enum ToWorkerMsg<B: BlockT> {
-	ImportBlocks(BlockOrigin, Vec<IncomingBlock<B>>),
+	/// The `MemoryCharge` accounts the blocks to the import queue until they are imported.
+	ImportBlocks(BlockOrigin, Vec<IncomingBlock<B>>, MemoryCharge),
 	ImportJustification(Origin, B::Hash, NumberFor<B>, Justification),
 	ImportFinalityProof(Origin, B::Hash, NumberFor<B>, Vec<u8>),
 }
@@ -223,12 +240,19 @@ impl<B: BlockT, Transaction: Send> BlockImportWorker<B, Transaction> {
 				};
 
 				match msg {
-					ToWorkerMsg::ImportBlocks(origin, blocks) => {
+					ToWorkerMsg::ImportBlocks(origin, blocks, charge) => {
 						// On blocks import request, we merely *start* the process and store
 						// a `Future` into `importing`.
 						let (bi, verif) = block_import_verifier.take()
 							.expect("block_import_verifier is always Some; qed");
-						importing = Some(worker.import_a_batch_of_blocks(bi, verif, origin, blocks));
+						// The blocks stay accounted to the import queue until they are imported.
+						importing = Some(
+							worker.import_a_batch_of_blocks(bi, verif, origin, blocks)
+								.map(move |result| {
+									drop(charge);
+									result
+								})
+						);
 					},
 					ToWorkerMsg::ImportFinalityProof(who, hash, number, proof) => {
 						let (_, verif) = block_import_verifier.as_mut()
@@ -42,6 +42,7 @@ pub mod offline_tracker;
 pub mod error;
 pub mod block_import;
 mod select_chain;
+mod fork_choice;
 pub mod import_queue;
 pub mod evaluation;
 mod metrics;
@@ -52,9 +53,10 @@ const MAX_BLOCK_SIZE: usize = 4 * 1024 * 1024 + 512;
 pub use self::error::Error;
 pub use block_import::{
 	BlockImport, BlockOrigin, ForkChoiceStrategy, ImportedAux, BlockImportParams, BlockCheckParams,
-	ImportResult, JustificationImport, FinalityProofImport,
+	ImportResult, JustificationImport, JustificationCheck, ConsensusReplay, FinalityProofImport,
 };
 pub use select_chain::SelectChain;
+pub use fork_choice::{ForkChoiceRule, LongestChainRule};
 pub use sp_state_machine::Backend as StateBackend;
 
 /// Block status.
@@ -176,6 +178,11 @@ pub trait SyncOracle {
 	/// Whether the synchronization service is offline.
 	/// Returns true if so.
 	fn is_offline(&mut self) -> bool;
+	/// Whether the node has been put into maintenance mode, in which it doesn't author blocks.
+	/// Returns true if so.
+	fn is_in_maintenance(&mut self) -> bool {
+		false
+	}
 }
 
 /// A synchronization oracle for when there is no network.
@@ -195,6 +202,10 @@ impl<T> SyncOracle for Arc<T> where T: ?Sized, for<'r> &'r T: SyncOracle {
 	fn is_offline(&mut self) -> bool {
 		<&T>::is_offline(&mut &**self)
 	}
+
+	fn is_in_maintenance(&mut self) -> bool {
+		<&T>::is_in_maintenance(&mut &**self)
+	}
 }
 
 /// Checks if the current active native block authoring implementation can author with the runtime
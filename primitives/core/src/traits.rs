@@ -27,6 +27,7 @@ use std::{
 	fmt::{Debug, Display},
 	panic::UnwindSafe,
 	sync::Arc,
+	time::{Duration, Instant},
 };
 
 pub use sp_externalities::{Externalities, ExternalitiesExt};
@@ -349,6 +350,18 @@ impl TaskExecutorExt {
 	}
 }
 
+sp_externalities::decl_extension! {
+	/// Deadline of the runtime call, after which its wasm execution is interrupted.
+	pub struct RuntimeCallDeadlineExt(Instant);
+}
+
+impl RuntimeCallDeadlineExt {
+	/// New deadline `timeout` from now.
+	pub fn after(timeout: Duration) -> Self {
+		Self(Instant::now() + timeout)
+	}
+}
+
 /// Something that can spawn a blocking future.
 pub trait SpawnBlocking {
 	/// Spawn the given blocking future.
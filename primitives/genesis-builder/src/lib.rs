@@ -0,0 +1,41 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2020 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: Apache-2.0
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Runtime api for building the genesis storage inside the runtime.
+//!
+//! This allows to generate the genesis state of a chain from its runtime wasm only, without
+//! constructing the native `GenesisConfig` of the runtime.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+#![warn(missing_docs)]
+
+use sp_std::vec::Vec;
+
+/// Name of the runtime function that builds the genesis storage.
+pub const BUILD_GENESIS: &str = "GenesisBuilder_build_genesis";
+
+sp_api::decl_runtime_apis! {
+	/// The genesis builder api.
+	pub trait GenesisBuilder {
+		/// Write the genesis storage of the chain, as described by the given `config`.
+		///
+		/// The encoding of `config` is defined by the runtime. An empty `config` requests the
+		/// default genesis of the runtime. Returns a description of the error if the config is
+		/// invalid.
+		fn build_genesis(config: Vec<u8>) -> Result<(), Vec<u8>>;
+	}
+}
@@ -85,3 +85,24 @@ where
 
 	Ok(())
 }
+
+/// Returns the key types of the session keys expected by the runtime at the given block.
+///
+/// The runtime doesn't expose them directly, so this decodes a zeroed buffer as session keys
+/// and collects the key types of the resulting (meaningless) public keys.
+#[cfg(feature = "std")]
+pub fn session_key_types<Block, T>(
+	client: &T,
+	at: &BlockId<Block>,
+) -> Result<Vec<KeyTypeId>, sp_api::ApiErrorFor<T, Block>>
+where
+	Block: BlockT,
+	T: ProvideRuntimeApi<Block>,
+	T::Api: SessionKeys<Block>,
+{
+	// Large enough for any reasonable number of public keys.
+	let zeroed = vec![0u8; 4096];
+	let keys = client.runtime_api().decode_session_keys(at, zeroed)?;
+
+	Ok(keys.unwrap_or_default().into_iter().map(|(_, key_type)| key_type).collect())
+}
@@ -45,7 +45,7 @@ mod stats;
 mod read_only;
 
 pub use sp_trie::{trie_types::{Layout, TrieDBMut}, StorageProof, TrieMut, DBValue, MemoryDB};
-pub use testing::TestExternalities;
+pub use testing::{TestExternalities, encode_storage_snapshot, decode_storage_snapshot};
 pub use basic::BasicExternalities;
 pub use read_only::{ReadOnlyExternalities, InspectState};
 pub use ext::Ext;
@@ -34,7 +34,7 @@ use sp_core::{
 	offchain::storage::OffchainOverlayedChanges,
 	storage::{
 		well_known_keys::{CHANGES_TRIE_CONFIG, CODE, HEAP_PAGES, is_child_storage_key},
-		Storage,
+		ChildInfo, Storage, StorageChild,
 	},
 };
 use codec::Encode;
@@ -119,6 +119,25 @@ impl<H: Hasher, N: ChangesTrieBlockNumber> TestExternalities<H, N>
 		}
 	}
 
+	/// Create a new instance of `TestExternalities` with the storage of a snapshot, as encoded by
+	/// [`encode_storage_snapshot`].
+	///
+	/// The code and heap pages of the snapshot are kept.
+	pub fn from_snapshot(snapshot: &[u8]) -> Result<Self, codec::Error> {
+		let mut storage = decode_storage_snapshot(snapshot)?;
+		let code = storage.top.remove(CODE).unwrap_or_default();
+		let heap_pages = storage.top.remove(HEAP_PAGES);
+		storage.children_default = storage.children_default.into_iter()
+			.map(|(_, child)| (child.child_info.prefixed_storage_key().into_inner(), child))
+			.collect();
+
+		let mut ext = Self::new_with_code(&code, storage);
+		if let Some(heap_pages) = heap_pages {
+			ext.insert(HEAP_PAGES.to_vec(), heap_pages);
+		}
+		Ok(ext)
+	}
+
 	/// Insert key/value into backend
 	pub fn insert(&mut self, k: StorageKey, v: StorageValue) {
 		self.backend.insert(vec![(None, vec![(k, Some(v))])]);
@@ -222,6 +241,31 @@ impl<H, N> sp_externalities::ExtensionStore for TestExternalities<H, N> where
 	}
 }
 
+/// Encode `storage`, e.g. the state of a live chain, to load it in tests with
+/// [`TestExternalities::from_snapshot`].
+pub fn encode_storage_snapshot(storage: &Storage) -> Vec<u8> {
+	let children = storage.children_default.iter()
+		.map(|(key, child)| (key, child.data.iter().collect::<Vec<_>>()))
+		.collect::<Vec<_>>();
+	(storage.top.iter().collect::<Vec<_>>(), children).encode()
+}
+
+/// Decode a storage snapshot encoded by [`encode_storage_snapshot`].
+pub fn decode_storage_snapshot(mut snapshot: &[u8]) -> Result<Storage, codec::Error> {
+	let (top, children) = <(
+		Vec<(StorageKey, StorageValue)>,
+		Vec<(Vec<u8>, Vec<(StorageKey, StorageValue)>)>,
+	)>::decode(&mut snapshot)?;
+
+	Ok(Storage {
+		top: top.into_iter().collect(),
+		children_default: children.into_iter().map(|(key, data)| {
+			let child_info = ChildInfo::new_default(&key);
+			(key, StorageChild { data: data.into_iter().collect(), child_info })
+		}).collect(),
+	})
+}
+
 #[cfg(test)]
 mod tests {
 	use super::*;
@@ -256,4 +300,27 @@ mod tests {
 		fn assert_send<T: Send>() {}
 		assert_send::<TestExternalities::<BlakeTwo256, u64>>();
 	}
+
+	#[test]
+	fn loads_storage_snapshot() {
+		let child_info = ChildInfo::new_default(b"child");
+		let storage = Storage {
+			top: vec![
+				(b"key".to_vec(), b"value".to_vec()),
+				(CODE.to_vec(), vec![1, 2, 3]),
+			].into_iter().collect(),
+			children_default: vec![(b"child".to_vec(), StorageChild {
+				data: vec![(b"child_key".to_vec(), b"child_value".to_vec())].into_iter().collect(),
+				child_info: child_info.clone(),
+			})].into_iter().collect(),
+		};
+
+		let snapshot = encode_storage_snapshot(&storage);
+		let mut ext = TestExternalities::<BlakeTwo256, u64>::from_snapshot(&snapshot).unwrap();
+		let ext = ext.ext();
+
+		assert_eq!(ext.storage(b"key"), Some(b"value".to_vec()));
+		assert_eq!(ext.storage(CODE), Some(vec![1, 2, 3]));
+		assert_eq!(ext.child_storage(&child_info, b"child_key"), Some(b"child_value".to_vec()));
+	}
 }
@@ -78,6 +78,74 @@ impl TimestampInherentData for InherentData {
 	}
 }
 
+/// Source of the current time of a node.
+///
+/// Nodes use the [`SystemClock`]. Tests can replace it by a virtual clock, to produce blocks
+/// without waiting for their slots.
+#[cfg(feature = "std")]
+pub trait Clock: Send + Sync {
+	/// Returns the duration since unix epoch.
+	fn now(&self) -> std::time::Duration;
+
+	/// Returns a future that completes once `duration` has passed on the clock.
+	fn delay(
+		&self,
+		duration: std::time::Duration,
+	) -> std::pin::Pin<Box<dyn std::future::Future<Output = ()> + Send>>;
+}
+
+/// The clock of the system.
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+#[cfg(feature = "std")]
+impl Clock for SystemClock {
+	fn now(&self) -> std::time::Duration {
+		use wasm_timer::SystemTime;
+
+		let now = SystemTime::now();
+		now.duration_since(SystemTime::UNIX_EPOCH).unwrap_or_else(|e| panic!(
+			"Current time {:?} is before unix epoch. Something is wrong: {:?}",
+			now,
+			e,
+		))
+	}
+
+	fn delay(
+		&self,
+		duration: std::time::Duration,
+	) -> std::pin::Pin<Box<dyn std::future::Future<Output = ()> + Send>> {
+		let delay = wasm_timer::Delay::new(duration);
+		Box::pin(async move {
+			let _ = delay.await;
+		})
+	}
+}
+
+/// Provide the duration since unix epoch of a [`Clock`] in millisecond for timestamp inherent.
+#[cfg(feature = "std")]
+pub struct ClockInherentDataProvider(pub std::sync::Arc<dyn Clock>);
+
+#[cfg(feature = "std")]
+impl ProvideInherentData for ClockInherentDataProvider {
+	fn inherent_identifier(&self) -> &'static InherentIdentifier {
+		&INHERENT_IDENTIFIER
+	}
+
+	fn provide_inherent_data(
+		&self,
+		inherent_data: &mut InherentData,
+	) -> Result<(), sp_inherents::Error> {
+		let duration: InherentType = self.0.now().as_millis() as u64;
+		inherent_data.put_data(INHERENT_IDENTIFIER, &duration)
+	}
+
+	fn error_to_string(&self, error: &[u8]) -> Option<String> {
+		InherentError::try_from(&INHERENT_IDENTIFIER, error).map(|e| format!("{:?}", e))
+	}
+}
+
 /// Provide duration since unix epoch in millisecond for timestamp inherent.
 #[cfg(feature = "std")]
 pub struct InherentDataProvider;
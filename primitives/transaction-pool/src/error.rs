@@ -64,6 +64,9 @@ pub enum Error {
 	/// The pool is not accepting future transactions.
 	#[display(fmt="The pool is not accepting future transactions")]
 	RejectedFutureTransaction,
+	/// The pool is paused and doesn't accept new transactions.
+	#[display(fmt="The pool is not accepting new transactions for now")]
+	Paused,
 }
 
 impl std::error::Error for Error {}
@@ -203,6 +203,14 @@ pub trait TransactionPool: Send + Sync {
 		xt: TransactionFor<Self>,
 	) -> PoolFuture<TxHash<Self>, Self::Error>;
 
+	/// Returns a future that imports one unverified local transaction to the pool, which is never
+	/// propagated to other peers.
+	fn submit_local(
+		&self,
+		at: &BlockId<Self::Block>,
+		xt: TransactionFor<Self>,
+	) -> PoolFuture<TxHash<Self>, Self::Error>;
+
 	/// Returns a future that import a single transaction and starts to watch their progress in the pool.
 	fn submit_and_watch(
 		&self,
@@ -243,6 +251,45 @@ pub trait TransactionPool: Send + Sync {
 
 	/// Return specific ready transaction by hash, if there is one.
 	fn ready_transaction(&self, hash: &TxHash<Self>) -> Option<Arc<Self::InPoolTransaction>>;
+
+	/// Stop or resume accepting new transactions, e.g. while the node is low on disk space.
+	///
+	/// The transactions already in the pool are kept. Does nothing by default.
+	fn set_paused(&self, _paused: bool) {}
+
+	/// Enforce only `percent` of the configured count and size limits, e.g. while the node is
+	/// under memory pressure, or all of them again with 100.
+	///
+	/// The transactions exceeding the new limits are dropped. Does nothing by default.
+	fn set_limits_percent(&self, _percent: usize) {}
+}
+
+/// Iterator over the ready transactions of a pool.
+pub type ReadyTransactions<P> =
+	Box<dyn Iterator<Item=Arc<<P as TransactionPool>::InPoolTransaction>> + Send>;
+
+/// Selection of the ready transactions included in the blocks authored by the node.
+///
+/// The proposer pushes the selected transactions in order until the block is full, so a
+/// selector can reorder the ready transactions, exclude some of them or cap the number of
+/// transactions of a kind, e.g. by decoding their call.
+///
+/// The pool is only bound on the method, so that a selector can be stored alongside a pool whose
+/// type isn't known yet, e.g. by the service builder.
+pub trait TransactionSelector<P>: Send + Sync {
+	/// Select the transactions to include in a block built on top of `at` among `ready`, which
+	/// are ordered by priority.
+	fn select(&self, at: &BlockId<P::Block>, ready: ReadyTransactions<P>) -> ReadyTransactions<P>
+		where P: TransactionPool;
+}
+
+impl<P, F> TransactionSelector<P> for F where
+	P: TransactionPool,
+	F: Fn(&BlockId<P::Block>, ReadyTransactions<P>) -> ReadyTransactions<P> + Send + Sync,
+{
+	fn select(&self, at: &BlockId<P::Block>, ready: ReadyTransactions<P>) -> ReadyTransactions<P> {
+		(self)(at, ready)
+	}
 }
 
 /// Events that the transaction pool listens for.
@@ -0,0 +1,39 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2020 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: Apache-2.0
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Runtime api for dry-running the migrations of a runtime upgrade.
+//!
+//! Migrations are normally only executed by the first block built with a new runtime. This api
+//! allows to execute them on any state, e.g. to check a new runtime against the live chain
+//! before proposing the upgrade.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+#![warn(missing_docs)]
+
+/// Name of the runtime function that executes the migrations.
+pub const ON_RUNTIME_UPGRADE: &str = "TryRuntime_on_runtime_upgrade";
+
+sp_api::decl_runtime_apis! {
+	/// The try-runtime api.
+	pub trait TryRuntime {
+		/// Execute the migrations that run after a runtime upgrade, and return the weight they
+		/// consumed.
+		///
+		/// The migrations run whether or not the runtime version changed.
+		fn on_runtime_upgrade() -> u64;
+	}
+}
@@ -17,5 +17,7 @@
 
 //! Utilities Primitives for Substrate
 
+pub mod memory;
 pub mod metrics;
-pub mod mpsc;
\ No newline at end of file
+pub mod mpsc;
+pub mod shutdown;
\ No newline at end of file
@@ -0,0 +1,126 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2020 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: Apache-2.0
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Lightweight accounting of the memory held by the node's subsystems.
+//!
+//! Each [`Subsystem`] reports the bytes it currently holds, either by setting its usage with
+//! [`set_usage`] when it tracks it on its own, or by holding a [`MemoryCharge`] for as long as
+//! the accounted data is alive. A subsystem must use only one of the two ways.
+//!
+//! The accounted usage is the size of the data itself, which is an estimate of the memory
+//! actually allocated to hold it.
+
+use std::{fmt, sync::atomic::{AtomicU64, Ordering}};
+
+/// Bytes accounted to each subsystem, indexed by the `Subsystem` discriminant.
+static USAGE: [AtomicU64; 4] = [
+	AtomicU64::new(0),
+	AtomicU64::new(0),
+	AtomicU64::new(0),
+	AtomicU64::new(0),
+];
+
+/// A subsystem whose memory usage is accounted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Subsystem {
+	/// Network events waiting to be processed by their listeners.
+	NetworkBuffers = 0,
+	/// Transactions in the transaction pool.
+	TransactionPool = 1,
+	/// Storage entries in the state cache.
+	StateCache = 2,
+	/// Blocks waiting to be imported.
+	ImportQueue = 3,
+}
+
+impl Subsystem {
+	/// All the accounted subsystems.
+	pub const ALL: [Subsystem; 4] = [
+		Subsystem::NetworkBuffers,
+		Subsystem::TransactionPool,
+		Subsystem::StateCache,
+		Subsystem::ImportQueue,
+	];
+
+	/// Name of the subsystem, as used in logs and metrics.
+	pub fn name(&self) -> &'static str {
+		match self {
+			Subsystem::NetworkBuffers => "network_buffers",
+			Subsystem::TransactionPool => "transaction_pool",
+			Subsystem::StateCache => "state_cache",
+			Subsystem::ImportQueue => "import_queue",
+		}
+	}
+
+	fn counter(&self) -> &'static AtomicU64 {
+		&USAGE[*self as usize]
+	}
+}
+
+impl fmt::Display for Subsystem {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.write_str(self.name())
+	}
+}
+
+/// Bytes charged to a subsystem until it is dropped.
+#[must_use = "the bytes are only accounted while the charge is alive"]
+#[derive(Debug)]
+pub struct MemoryCharge {
+	subsystem: Subsystem,
+	bytes: u64,
+}
+
+impl Drop for MemoryCharge {
+	fn drop(&mut self) {
+		self.subsystem.counter().fetch_sub(self.bytes, Ordering::Relaxed);
+	}
+}
+
+/// Account `bytes` to `subsystem` until the returned charge is dropped.
+pub fn charge(subsystem: Subsystem, bytes: u64) -> MemoryCharge {
+	subsystem.counter().fetch_add(bytes, Ordering::Relaxed);
+	MemoryCharge { subsystem, bytes }
+}
+
+/// Set the memory usage of a subsystem that keeps track of it on its own.
+pub fn set_usage(subsystem: Subsystem, bytes: u64) {
+	subsystem.counter().store(bytes, Ordering::Relaxed);
+}
+
+/// Returns the bytes currently accounted to `subsystem`.
+pub fn usage(subsystem: Subsystem) -> u64 {
+	subsystem.counter().load(Ordering::Relaxed)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn charges_are_accounted_while_alive() {
+		let first = charge(Subsystem::ImportQueue, 100);
+		let second = charge(Subsystem::ImportQueue, 20);
+		assert_eq!(usage(Subsystem::ImportQueue), 120);
+
+		drop(first);
+		assert_eq!(usage(Subsystem::ImportQueue), 20);
+
+		drop(second);
+		assert_eq!(usage(Subsystem::ImportQueue), 0);
+	}
+}
@@ -0,0 +1,66 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2020 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: Apache-2.0
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Delaying a graceful shutdown while critical work is in progress.
+//!
+//! Work that shouldn't be interrupted by a shutdown, like authoring a block in the node's
+//! slot, holds a [`ShutdownGuard`] while it runs. On shutdown, the node can wait until all
+//! guards are dropped before it stops its services.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Number of guards that are currently alive.
+static ACTIVE_GUARDS: AtomicUsize = AtomicUsize::new(0);
+
+/// Delays a graceful shutdown until it is dropped.
+#[must_use = "the shutdown is only delayed while the guard is alive"]
+pub struct ShutdownGuard(());
+
+impl Drop for ShutdownGuard {
+	fn drop(&mut self) {
+		ACTIVE_GUARDS.fetch_sub(1, Ordering::SeqCst);
+	}
+}
+
+/// Delay a graceful shutdown until the returned guard is dropped.
+pub fn delay_shutdown() -> ShutdownGuard {
+	ACTIVE_GUARDS.fetch_add(1, Ordering::SeqCst);
+	ShutdownGuard(())
+}
+
+/// Returns the number of guards that currently delay a graceful shutdown.
+pub fn active_guards() -> usize {
+	ACTIVE_GUARDS.load(Ordering::SeqCst)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn guards_are_counted_while_alive() {
+		let first = delay_shutdown();
+		let second = delay_shutdown();
+		assert_eq!(active_guards(), 2);
+
+		drop(first);
+		assert_eq!(active_guards(), 1);
+
+		drop(second);
+		assert_eq!(active_guards(), 0);
+	}
+}
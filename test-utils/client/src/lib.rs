@@ -44,6 +44,7 @@ use std::collections::HashMap;
 use sp_core::storage::ChildInfo;
 use sp_runtime::traits::{Block as BlockT, BlakeTwo256};
 use sc_service::client::{LocalCallExecutor, ClientConfig};
+use sc_service::{BuildGenesisBlock, DefaultGenesisBlockBuilder};
 
 /// Test client light database backend.
 pub type LightBackend<Block> = client::light::backend::Backend<
@@ -75,6 +76,8 @@ pub struct TestClientBuilder<Block: BlockT, Executor, Backend, G: GenesisInit> {
 	keystore: Option<BareCryptoStorePtr>,
 	fork_blocks: ForkBlocks<Block>,
 	bad_blocks: BadBlocks<Block>,
+	genesis_block_builder: Option<Box<dyn BuildGenesisBlock<Block>>>,
+	client_config: ClientConfig,
 }
 
 impl<Block: BlockT, Executor, G: GenesisInit> Default
@@ -110,6 +113,8 @@ impl<Block: BlockT, Executor, Backend, G: GenesisInit> TestClientBuilder<Block,
 			keystore: None,
 			fork_blocks: None,
 			bad_blocks: None,
+			genesis_block_builder: None,
+			client_config: ClientConfig::default(),
 		}
 	}
 
@@ -171,6 +176,21 @@ impl<Block: BlockT, Executor, Backend, G: GenesisInit> TestClientBuilder<Block,
 		self
 	}
 
+	/// Sets the builder of the genesis block.
+	pub fn set_genesis_block_builder(
+		mut self,
+		genesis_block_builder: impl BuildGenesisBlock<Block> + 'static,
+	) -> Self {
+		self.genesis_block_builder = Some(Box::new(genesis_block_builder));
+		self
+	}
+
+	/// Sets the configuration of the client.
+	pub fn set_client_config(mut self, client_config: ClientConfig) -> Self {
+		self.client_config = client_config;
+		self
+	}
+
 	/// Build the test client with the given native executor.
 	pub fn build_with_executor<RuntimeApi>(
 		self,
@@ -208,6 +228,7 @@ impl<Block: BlockT, Executor, Backend, G: GenesisInit> TestClientBuilder<Block,
 			self.backend.clone(),
 			executor,
 			&storage,
+			self.genesis_block_builder.as_deref().unwrap_or(&DefaultGenesisBlockBuilder),
 			self.fork_blocks,
 			self.bad_blocks,
 			ExecutionExtensions::new(
@@ -215,7 +236,7 @@ impl<Block: BlockT, Executor, Backend, G: GenesisInit> TestClientBuilder<Block,
 				self.keystore.clone(),
 			),
 			None,
-			ClientConfig::default(),
+			self.client_config,
 		).expect("Creates new client");
 
 		let longest_chain = sc_consensus::LongestChain::new(self.backend);
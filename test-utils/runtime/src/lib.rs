@@ -687,6 +687,12 @@ cfg_if! {
 				}
 			}
 
+			impl sp_genesis_builder::GenesisBuilder<Block> for Runtime {
+				fn build_genesis(config: Vec<u8>) -> Result<(), Vec<u8>> {
+					system::build_genesis(&config)
+				}
+			}
+
 			impl sp_finality_grandpa::GrandpaApi<Block> for Runtime {
 				fn grandpa_authorities() -> sp_finality_grandpa::AuthorityList {
 					Vec::new()
@@ -908,6 +914,12 @@ cfg_if! {
 				}
 			}
 
+			impl sp_genesis_builder::GenesisBuilder<Block> for Runtime {
+				fn build_genesis(config: Vec<u8>) -> Result<(), Vec<u8>> {
+					system::build_genesis(&config)
+				}
+			}
+
 			impl frame_system_rpc_runtime_api::AccountNonceApi<Block, AccountId, Index> for Runtime {
 				fn account_nonce(_account: AccountId) -> Index {
 					0
@@ -1070,4 +1082,43 @@ mod tests {
 
 		runtime_api.test_storage(&block_id).unwrap();
 	}
+
+	#[test]
+	fn genesis_is_built_by_the_runtime() {
+		use sc_executor::WasmExecutionMethod;
+		use sc_service::chain_ops::build_genesis_storage_from_wasm;
+		use sp_core::storage::well_known_keys::CODE;
+		use sp_keyring::{AccountKeyring, Sr25519Keyring};
+		use crate::{system::balance_of_key, AuthorityId, WASM_BINARY};
+
+		let authorities: Vec<AuthorityId> = vec![Sr25519Keyring::Alice.public().into()];
+		let balances = vec![(AccountKeyring::Bob.into(), 100u64)];
+		let config = (authorities.clone(), balances).encode();
+
+		let storage = build_genesis_storage_from_wasm(
+			WASM_BINARY,
+			&config,
+			WasmExecutionMethod::Interpreted,
+			None,
+		).unwrap();
+
+		assert_eq!(storage.top.get(CODE), Some(&WASM_BINARY.to_vec()));
+		assert_eq!(
+			storage.top.get(&sp_core::hashing::blake2_256(
+				&balance_of_key(AccountKeyring::Bob.into()),
+			)[..]),
+			Some(&100u64.encode()),
+		);
+		assert_eq!(
+			storage.top.get(&sp_core::hashing::twox_128(b"sys:auth")[..]),
+			Some(&authorities.encode()),
+		);
+
+		assert!(build_genesis_storage_from_wasm(
+			WASM_BINARY,
+			&[1, 2, 3],
+			WasmExecutionMethod::Interpreted,
+			None,
+		).is_err());
+	}
 }
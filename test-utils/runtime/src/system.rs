@@ -84,6 +84,25 @@ pub fn initialize_block(header: &Header) {
 	}
 }
 
+/// Write the genesis storage described by `config`, the encoded authorities and balances of the
+/// accounts. An empty `config` builds a genesis without authorities nor balances.
+pub fn build_genesis(config: &[u8]) -> Result<(), Vec<u8>> {
+	let (authorities, balances) = if config.is_empty() {
+		(Vec::new(), Vec::new())
+	} else {
+		<(Vec<AuthorityId>, Vec<(AccountId, u64)>)>::decode(&mut &config[..])
+			.map_err(|_| b"Invalid genesis config".to_vec())?
+	};
+
+	for (account, balance) in balances {
+		storage::hashed::put(&blake2_256, &balance_of_key(account), &balance);
+	}
+	storage::unhashed::put(well_known_keys::HEAP_PAGES, &16u64);
+	storage::unhashed::put(&sp_io::hashing::twox_128(b"sys:auth"), &authorities);
+	<Authorities>::put(authorities);
+	Ok(())
+}
+
 pub fn authorities() -> Vec<AuthorityId> {
 	Authorities::get()
 }
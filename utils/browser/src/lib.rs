@@ -28,6 +28,7 @@ use wasm_bindgen::prelude::*;
 use futures::{prelude::*, channel::{oneshot, mpsc}, future::{poll_fn, ok}, compat::*};
 use std::task::Poll;
 use std::pin::Pin;
+use std::time::Duration;
 use sc_chain_spec::Extension;
 use libp2p_wasm_ext::{ExtTransport, ffi};
 
@@ -66,6 +67,7 @@ where
 		chain_spec: Box::new(chain_spec),
 		task_executor: Arc::new(move |fut, _| wasm_bindgen_futures::spawn_local(fut)),
 		telemetry_external_transport: Some(transport),
+		telemetry_auth: Default::default(),
 		role: Role::Light,
 		database: {
 			info!("Opening Indexed DB database '{}'...", name);
@@ -73,11 +75,22 @@ where
 
 			DatabaseConfig::Custom(sp_database::as_database(db))
 		},
+		cold_storage: None,
+		database_read_only: false,
+		block_compression: Default::default(),
 		keystore: KeystoreConfig::InMemory,
 		default_heap_pages: Default::default(),
 		dev_key_seed: Default::default(),
 		disable_grandpa: Default::default(),
+		grandpa_justification_period: Some(512),
+		grandpa_request_justifications: false,
+		grandpa_justification_workers: 0,
+		grandpa_gossip_duration: Duration::from_millis(333),
 		execution_strategies: Default::default(),
+		signature_verification: Default::default(),
+		client_notifications: Default::default(),
+		storage_diffs: false,
+		replay_log: None,
 		force_authoring: Default::default(),
 		impl_name: "parity-substrate",
 		impl_version: "0.0.0",
@@ -85,18 +98,29 @@ where
 		prometheus_config: Default::default(),
 		pruning: Default::default(),
 		rpc_cors: Default::default(),
+		rpc_allowed_hosts: Default::default(),
 		rpc_http: Default::default(),
 		rpc_ws: Default::default(),
 		rpc_ws_max_connections: Default::default(),
+		rpc_ws_idle_timeout: Default::default(),
 		rpc_methods: Default::default(),
+		rpc_finalized_only: false,
+		rpc_subscription_replay: None,
+		rpc_call_timeout: None,
+		admin_rpc_peers: Vec::new(),
+		node_labels: Vec::new(),
+		disk_space_watchdog: None,
+		pool_memory_limits: None,
 		state_cache_child_ratio: Default::default(),
-		state_cache_size: Default::default(),
+		trie_cache_size: Default::default(),
+		value_cache_size: Default::default(),
 		tracing_receiver: Default::default(),
 		tracing_targets: Default::default(),
 		transaction_pool: Default::default(),
 		wasm_method: Default::default(),
 		max_runtime_instances: 8,
 		announce_block: true,
+		clock: None,
 	};
 
 	Ok(config)
@@ -16,6 +16,9 @@
 // limitations under the License.
 
 mod command;
+mod storage;
+
+pub use storage::StorageCmd;
 
 use sc_cli::{ExecutionStrategy, WasmExecutionMethod};
 use std::fmt::Debug;
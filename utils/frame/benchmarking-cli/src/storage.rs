@@ -0,0 +1,195 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2020 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: Apache-2.0
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+
+//! Benchmark of the storage of a node, measured against its own database.
+
+use sc_cli::{CliConfiguration, DatabaseParams, PruningParams, Result, SharedParams};
+use sc_client_api::{backend::Backend as _, blockchain::HeaderBackend};
+use sc_client_db::{Backend, BlockCompression, DatabaseSettings};
+use sc_service::Configuration;
+use sp_runtime::{generic::BlockId, traits::Block as BlockT};
+use sp_state_machine::Backend as StateBackend;
+use std::{fmt, time::{Duration, Instant}};
+
+/// Blocks to keep before canonicalizing, same as used by the service.
+const CANONICALIZATION_DELAY: u64 = 4096;
+
+/// The `benchmark-storage` command used to measure storage read and write latency.
+///
+/// The node must not be running. The state at the best block is sampled and every sampled
+/// item is read and then written with a modified value. Writes are removed again afterwards.
+#[derive(Debug, structopt::StructOpt, Clone)]
+pub struct StorageCmd {
+	/// Number of storage items to sample.
+	#[structopt(long, default_value = "10000")]
+	pub samples: usize,
+
+	/// Only benchmark reads.
+	#[structopt(long)]
+	pub skip_write: bool,
+
+	#[allow(missing_docs)]
+	#[structopt(flatten)]
+	pub shared_params: SharedParams,
+
+	#[allow(missing_docs)]
+	#[structopt(flatten)]
+	pub database_params: DatabaseParams,
+
+	#[allow(missing_docs)]
+	#[structopt(flatten)]
+	pub pruning_params: PruningParams,
+}
+
+/// Statistics over the measured latencies of one kind of operation.
+struct Stats {
+	min: Duration,
+	avg: Duration,
+	median: Duration,
+	p99: Duration,
+	max: Duration,
+}
+
+impl Stats {
+	fn new(mut samples: Vec<Duration>) -> Option<Self> {
+		if samples.is_empty() {
+			return None
+		}
+		samples.sort();
+
+		let total: Duration = samples.iter().sum();
+		let percentile = |p: usize| samples[(samples.len() - 1) * p / 100];
+		Some(Stats {
+			min: samples[0],
+			avg: total / samples.len() as u32,
+			median: percentile(50),
+			p99: percentile(99),
+			max: samples[samples.len() - 1],
+		})
+	}
+}
+
+impl fmt::Display for Stats {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(
+			f,
+			"min {:?}, avg {:?}, median {:?}, p99 {:?}, max {:?}",
+			self.min, self.avg, self.median, self.p99, self.max,
+		)
+	}
+}
+
+impl StorageCmd {
+	/// Runs the command and benchmarks the storage of the node.
+	pub fn run<B: BlockT>(&self, config: Configuration) -> Result<()> {
+		// Caches would make repeated reads of the upper trie nodes free.
+		let settings = DatabaseSettings {
+			trie_cache_size: 0,
+			value_cache_size: 0,
+			state_cache_child_ratio: None,
+			pruning: config.pruning.clone(),
+			source: config.database.clone(),
+			cold_storage: None,
+			read_only: false,
+			block_compression: BlockCompression::None,
+		};
+		let backend = Backend::<B>::new(settings, CANONICALIZATION_DELAY)?;
+		let best = backend.blockchain().info().best_hash;
+		let state = backend.state_at(BlockId::Hash(best))?;
+
+		// The keys are iterated twice rather than collected, the state may not fit in memory.
+		let mut key_count = 0;
+		state.for_keys_with_prefix(&[], |_| key_count += 1);
+		let step = (key_count / self.samples.max(1)).max(1);
+		println!(
+			"Sampling {} of {} storage items at block {:?}",
+			key_count.min(self.samples),
+			key_count,
+			best,
+		);
+
+		let mut keys = Vec::with_capacity(key_count.min(self.samples));
+		let mut index = 0;
+		state.for_keys_with_prefix(&[], |key| {
+			if index % step == 0 && keys.len() < self.samples {
+				keys.push(key.to_vec());
+			}
+			index += 1;
+		});
+
+		let mut reads = Vec::new();
+		let mut items = Vec::new();
+		for key in keys {
+			let started = Instant::now();
+			let value = state.storage(&key)
+				.map_err(|e| format!("Error reading storage: {}", e))?;
+			reads.push(started.elapsed());
+			items.push((key, value.unwrap_or_default()));
+		}
+
+		let mut writes = Vec::new();
+		if !self.skip_write {
+			for (key, value) in &items {
+				let mut new_value: Vec<u8> = value.iter().map(|b| !b).collect();
+				if new_value.is_empty() {
+					new_value.push(0);
+				}
+
+				let started = Instant::now();
+				let (_, update) = state.storage_root(std::iter::once((&key[..], Some(&new_value[..]))));
+				let root_time = started.elapsed();
+				writes.push(root_time + backend.benchmark_state_write(update)?);
+			}
+		}
+
+		let reads = Stats::new(reads);
+		let writes = Stats::new(writes);
+		if let Some(reads) = &reads {
+			println!("Reads: {}", reads);
+		}
+		if let Some(writes) = &writes {
+			println!("Writes: {}", writes);
+		}
+
+		let nanos = |stats: Option<Stats>| stats.map_or(0, |s| s.avg.as_nanos());
+		println!();
+		println!("parameter_types! {{");
+		println!("\t/// Average storage latency measured by `benchmark-storage`.");
+		println!("\tpub const StorageDbWeight: RuntimeDbWeight = RuntimeDbWeight {{");
+		println!("\t\tread: {} * WEIGHT_PER_NANOS,", nanos(reads));
+		println!("\t\twrite: {} * WEIGHT_PER_NANOS,", nanos(writes));
+		println!("\t}};");
+		println!("}}");
+
+		Ok(())
+	}
+}
+
+impl CliConfiguration for StorageCmd {
+	fn shared_params(&self) -> &SharedParams {
+		&self.shared_params
+	}
+
+	fn database_params(&self) -> Option<&DatabaseParams> {
+		Some(&self.database_params)
+	}
+
+	fn pruning_params(&self) -> Option<&PruningParams> {
+		Some(&self.pruning_params)
+	}
+}
@@ -27,15 +27,15 @@ pub use prometheus::{
 	}
 };
 use prometheus::{Encoder, TextEncoder, core::Collector};
-use std::net::SocketAddr;
+use std::{net::SocketAddr, time::Duration};
 
 #[cfg(not(target_os = "unknown"))]
 mod networking;
 
 #[cfg(target_os = "unknown")]
-pub use unknown_os::init_prometheus;
+pub use unknown_os::{init_prometheus, push_metrics};
 #[cfg(not(target_os = "unknown"))]
-pub use known_os::init_prometheus;
+pub use known_os::{init_prometheus, push_metrics};
 
 pub fn register<T: Clone + Collector + 'static>(metric: T, registry: &Registry) -> Result<T, PrometheusError> {
 	registry.register(Box::new(metric.clone()))?;
@@ -49,9 +49,25 @@ mod unknown_os {
 
 	pub enum Error {}
 
+	impl std::fmt::Display for Error {
+		fn fmt(&self, _: &mut std::fmt::Formatter) -> std::fmt::Result {
+			match *self {}
+		}
+	}
+
 	pub async fn init_prometheus(_: SocketAddr, _registry: Registry) -> Result<(), Error> {
 		Ok(())
 	}
+
+	pub async fn push_metrics(
+		_url: String,
+		_job: String,
+		_instance: String,
+		_interval: Duration,
+		_registry: Registry,
+	) -> Result<(), Error> {
+		Ok(())
+	}
 }
 
 #[cfg(not(target_os = "unknown"))]
@@ -144,4 +160,81 @@ mod known_os {
 
 		result
 	}
+
+	/// Periodically pushes the metrics of the registry to a Prometheus Pushgateway.
+	///
+	/// The metrics replace the group identified by the `job` and `instance` labels at the
+	/// Pushgateway reachable at `url`. Failed pushes are logged and retried at the next interval.
+	///
+	/// Returns an error if `url` is invalid.
+	pub async fn push_metrics(
+		url: String,
+		job: String,
+		instance: String,
+		interval: Duration,
+		registry: Registry,
+	) -> Result<(), Error> {
+		let uri: hyper::Uri = push_url(&url, &job, &instance).parse()
+			.map_err(|e: hyper::http::uri::InvalidUri| Error::Http(e.into()))?;
+		let client = hyper::Client::builder()
+			.executor(Executor)
+			.build_http::<Body>();
+
+		log::info!("〽️ Pushing Prometheus metrics to {}", url);
+
+		loop {
+			async_std::task::sleep(interval).await;
+
+			let metric_families = registry.gather();
+			let mut buffer = vec![];
+			let encoder = TextEncoder::new();
+			encoder.encode(&metric_families, &mut buffer).unwrap();
+
+			let request = Request::put(uri.clone())
+				.header("Content-Type", encoder.format_type())
+				.body(Body::from(buffer))?;
+
+			match client.request(request).await {
+				Ok(response) if response.status().is_success() => {},
+				Ok(response) => log::warn!(
+					"Prometheus Pushgateway at {} responded with {}", url, response.status(),
+				),
+				Err(e) => log::warn!("Failed to push metrics to Prometheus Pushgateway: {}", e),
+			}
+		}
+	}
+}
+
+/// Returns the Pushgateway URL of the group identified by `job` and `instance`.
+#[cfg_attr(target_os = "unknown", allow(unused))]
+fn push_url(url: &str, job: &str, instance: &str) -> String {
+	format!(
+		"{}/metrics/job/{}/instance/{}",
+		url.trim_end_matches('/'),
+		escape_path_segment(job),
+		escape_path_segment(instance),
+	)
+}
+
+/// Percent-encode all characters of `segment` that aren't allowed in a URL path segment.
+#[cfg_attr(target_os = "unknown", allow(unused))]
+fn escape_path_segment(segment: &str) -> String {
+	segment.bytes().map(|b| match b {
+		b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' =>
+			(b as char).to_string(),
+		_ => format!("%{:02X}", b),
+	}).collect()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn push_url_escapes_labels() {
+		assert_eq!(
+			push_url("http://localhost:9091/", "substrate", "my node/1"),
+			"http://localhost:9091/metrics/job/substrate/instance/my%20node%2F1",
+		);
+	}
 }